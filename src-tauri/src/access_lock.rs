@@ -0,0 +1,313 @@
+//! Защита диапазонов адресов паролем.
+//!
+//! Некоторые счётчики и приводы открывают доступ к части регистров только
+//! после записи пароля в регистр разблокировки. Сервер повторяет эту схему:
+//! пока клиент не записал пароль, обращения к защищённым диапазонам
+//! получают исключение. Разблокировка действует для соединения клиента и
+//! снимается при его закрытии, неверным паролем, по таймауту или при
+//! перезапуске сервера.
+//! Регистр разблокировки обрабатывается здесь же и не требует переменной.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::access_map::function_area;
+use crate::modbus_protocol::{ExceptionCode, FunctionCode, ModbusRequest, ModbusResponse};
+use crate::types::ModbusArea;
+
+/// Защищённый диапазон адресов.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtectedRange {
+    #[serde(default)]
+    pub name: String,
+    pub area: ModbusArea,
+    pub start: u16,
+    pub count: u16,
+    /// Защищена только запись, чтение доступно всегда.
+    #[serde(default)]
+    pub writes_only: bool,
+}
+
+impl ProtectedRange {
+    fn overlaps(&self, start: u16, count: u16) -> bool {
+        let end = self.start as u32 + self.count as u32;
+        (start as u32) < end && (self.start as u32) < start as u32 + count.max(1) as u32
+    }
+}
+
+/// Исключение на обращение без разблокировки.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LockedResponse {
+    /// Illegal Function (01).
+    Function,
+    /// Illegal Data Address (02).
+    #[default]
+    DataAddress,
+    /// Illegal Data Value (03).
+    DataValue,
+}
+
+impl From<LockedResponse> for ExceptionCode {
+    fn from(response: LockedResponse) -> Self {
+        match response {
+            LockedResponse::Function => ExceptionCode::IllegalFunction,
+            LockedResponse::DataAddress => ExceptionCode::IllegalDataAddress,
+            LockedResponse::DataValue => ExceptionCode::IllegalDataValue,
+        }
+    }
+}
+
+/// Настройки защиты паролем.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLockConfig {
+    /// Holding-регистр, в который записывается пароль.
+    pub unlock_register: u16,
+    pub password: u16,
+    /// Время действия разблокировки, мс; None — до неверного пароля.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub locked_response: LockedResponse,
+    pub ranges: Vec<ProtectedRange>,
+}
+
+impl AccessLockConfig {
+    /// Проверить диапазоны.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ranges.is_empty() {
+            return Err("Не заданы защищённые диапазоны".to_string());
+        }
+        for range in &self.ranges {
+            if range.count == 0 || range.start as u32 + range.count as u32 > 0x10000 {
+                return Err(format!(
+                    "Защищённый диапазон '{}' {}..+{} вне адресов 0..65535",
+                    range.name, range.start, range.count
+                ));
+            }
+        }
+        if self.timeout_ms == Some(0) {
+            return Err("Время разблокировки должно быть больше нуля".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Итог проверки запроса.
+#[derive(Debug, PartialEq)]
+pub enum AccessDecision {
+    /// Запрос записи пароля: ответ готов.
+    Unlock { accepted: bool, response: Vec<u8> },
+    /// Обращение к защищённому диапазону без разблокировки.
+    Denied { range: String, response: Vec<u8> },
+}
+
+/// Защита паролем и разблокированные клиенты.
+#[derive(Debug, Default)]
+pub struct AccessLock {
+    config: RwLock<Option<AccessLockConfig>>,
+    /// Разблокированные клиенты и срок разблокировки.
+    unlocked: Mutex<HashMap<String, Option<Instant>>>,
+}
+
+/// Значение, записываемое в регистр `address` запросом 0x06 или 0x10.
+fn written_register(request: &ModbusRequest, address: u16) -> Option<u16> {
+    let (start, count) = request.address_range()?;
+    let offset = address
+        .checked_sub(start)
+        .filter(|&offset| offset < count)?;
+    let word = |at: usize| {
+        let bytes = request.data.get(at..at + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+    match FunctionCode::from_u8(request.function_code)? {
+        FunctionCode::WriteSingleRegister => word(2),
+        FunctionCode::WriteMultipleRegisters => word(5 + 2 * offset as usize),
+        _ => None,
+    }
+}
+
+fn is_write(function: FunctionCode) -> bool {
+    matches!(
+        function,
+        FunctionCode::WriteSingleCoil
+            | FunctionCode::WriteSingleRegister
+            | FunctionCode::WriteMultipleCoils
+            | FunctionCode::WriteMultipleRegisters
+            | FunctionCode::MaskWriteRegister
+    )
+}
+
+impl AccessLock {
+    /// Заменить настройки; разблокировки сбрасываются.
+    pub fn set_config(&self, config: Option<AccessLockConfig>) {
+        *self.config.write() = config;
+        self.unlocked.lock().clear();
+    }
+
+    /// Проверить настройки перед запуском сервера.
+    pub fn validate(&self) -> Result<(), String> {
+        self.config
+            .read()
+            .as_ref()
+            .map_or(Ok(()), AccessLockConfig::validate)
+    }
+
+    /// Заблокировать всех клиентов (при запуске сервера).
+    pub fn reset(&self) {
+        self.unlocked.lock().clear();
+    }
+
+    /// Снять разблокировку закрытого соединения клиента.
+    pub fn disconnect(&self, client_addr: &str) {
+        self.unlocked.lock().remove(client_addr);
+    }
+
+    /// Проверить запрос клиента. `None` — запрос обрабатывается как обычно.
+    pub fn check(
+        &self,
+        request: &ModbusRequest,
+        client_addr: &str,
+        now: Instant,
+    ) -> Option<AccessDecision> {
+        let config = self.config.read();
+        let config = config.as_ref()?;
+        let function = FunctionCode::from_u8(request.function_code)?;
+
+        // Запись только в регистр разблокировки — попытка ввода пароля
+        if request.address_range() == Some((config.unlock_register, 1)) {
+            if let Some(value) = written_register(request, config.unlock_register) {
+                let accepted = value == config.password;
+                let mut unlocked = self.unlocked.lock();
+                let response = if accepted {
+                    let expires = config.timeout_ms.map(|ms| now + Duration::from_millis(ms));
+                    unlocked.insert(client_addr.to_string(), expires);
+                    ModbusResponse::build_response(
+                        request,
+                        request.function_code,
+                        &request.data[..4],
+                    )
+                } else {
+                    unlocked.remove(client_addr);
+                    ModbusResponse::build_exception(
+                        request,
+                        request.function_code,
+                        ExceptionCode::IllegalDataValue,
+                    )
+                };
+                return Some(AccessDecision::Unlock { accepted, response });
+            }
+        }
+
+        let area = function_area(function)?;
+        let (start, count) = request.address_range()?;
+        let write = is_write(function);
+        let range = config.ranges.iter().find(|range| {
+            range.area == area && (write || !range.writes_only) && range.overlaps(start, count)
+        })?;
+        let mut unlocked = self.unlocked.lock();
+        match unlocked.get(client_addr) {
+            Some(None) => return None,
+            Some(Some(expires)) if now < *expires => return None,
+            Some(Some(_)) => {
+                unlocked.remove(client_addr);
+            }
+            None => {}
+        }
+        Some(AccessDecision::Denied {
+            range: range.name.clone(),
+            response: ModbusResponse::build_exception(
+                request,
+                request.function_code,
+                config.locked_response.into(),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus_protocol::MbapHeader;
+
+    fn request(function_code: u8, data: &[u8]) -> ModbusRequest {
+        let mut frame = Vec::new();
+        MbapHeader {
+            transaction_id: 1,
+            protocol_id: 0,
+            length: data.len() as u16 + 2,
+            unit_id: 1,
+        }
+        .write_to(&mut frame);
+        frame.push(function_code);
+        frame.extend_from_slice(data);
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_password_unlocks_protected_range_per_client() {
+        let lock = AccessLock::default();
+        lock.set_config(Some(AccessLockConfig {
+            unlock_register: 900,
+            password: 1234,
+            timeout_ms: Some(1000),
+            locked_response: LockedResponse::DataAddress,
+            ranges: vec![ProtectedRange {
+                name: "Калибровка".to_string(),
+                area: ModbusArea::HoldingRegister,
+                start: 100,
+                count: 10,
+                writes_only: true,
+            }],
+        }));
+        let t0 = Instant::now();
+        let write = request(0x06, &[0x00, 0x64, 0x00, 0x01]);
+        let read = request(0x03, &[0x00, 0x64, 0x00, 0x02]);
+        let outside = request(0x06, &[0x00, 0x6E, 0x00, 0x01]);
+
+        assert!(lock.check(&read, "a", t0).is_none());
+        assert!(lock.check(&outside, "a", t0).is_none());
+        let Some(AccessDecision::Denied { range, response }) = lock.check(&write, "a", t0) else {
+            panic!("запись без пароля должна быть отклонена");
+        };
+        assert_eq!(range, "Калибровка");
+        assert_eq!(response[7..], [0x86, 0x02]);
+
+        // Неверный пароль — исключение, верный — эхо запроса
+        let wrong = request(0x06, &[0x03, 0x84, 0x00, 0x01]);
+        assert!(matches!(
+            lock.check(&wrong, "a", t0),
+            Some(AccessDecision::Unlock {
+                accepted: false,
+                ..
+            })
+        ));
+        let password = request(0x10, &[0x03, 0x84, 0x00, 0x01, 0x02, 0x04, 0xD2]);
+        let Some(AccessDecision::Unlock {
+            accepted: true,
+            response,
+        }) = lock.check(&password, "a", t0)
+        else {
+            panic!("верный пароль должен быть принят");
+        };
+        assert_eq!(response[7..], [0x10, 0x03, 0x84, 0x00, 0x01]);
+
+        assert!(lock.check(&write, "a", t0).is_none());
+        assert!(lock.check(&write, "b", t0).is_some());
+        assert!(lock
+            .check(&write, "a", t0 + Duration::from_millis(1000))
+            .is_some());
+
+        // Разблокировка не переживает закрытие соединения
+        assert!(lock.check(&password, "a", t0).is_some());
+        assert!(lock.check(&write, "a", t0).is_none());
+        lock.disconnect("a");
+        assert!(lock.check(&write, "a", t0).is_some());
+        assert!(lock.unlocked.lock().is_empty());
+    }
+}
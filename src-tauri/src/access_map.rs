@@ -0,0 +1,221 @@
+//! Карта обращений мастера, восстановленная по трафику.
+//!
+//! Сервер отмечает каждый адрес, к которому обращался мастер, и функции
+//! обращения. По этой карте видно, какие адреса мастер реально опрашивает,
+//! а для адресов без переменных можно одной командой сгенерировать
+//! определения переменных.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::data_store::SharedDataStore;
+use crate::modbus_protocol::{FunctionCode, ModbusRequest};
+use crate::types::{generate_variable_id, ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+/// Области в порядке вывода.
+const AREAS: [ModbusArea; 4] = [
+    ModbusArea::Coil,
+    ModbusArea::DiscreteInput,
+    ModbusArea::InputRegister,
+    ModbusArea::HoldingRegister,
+];
+
+/// Область, к которой обращается функция; `None` для файловых записей.
+pub(crate) fn function_area(function: FunctionCode) -> Option<ModbusArea> {
+    match function {
+        FunctionCode::ReadCoils
+        | FunctionCode::WriteSingleCoil
+        | FunctionCode::WriteMultipleCoils => Some(ModbusArea::Coil),
+        FunctionCode::ReadDiscreteInputs => Some(ModbusArea::DiscreteInput),
+        FunctionCode::ReadInputRegisters => Some(ModbusArea::InputRegister),
+        FunctionCode::ReadHoldingRegisters
+        | FunctionCode::WriteSingleRegister
+        | FunctionCode::WriteMultipleRegisters
+        | FunctionCode::MaskWriteRegister => Some(ModbusArea::HoldingRegister),
+        FunctionCode::ReadFileRecord | FunctionCode::WriteFileRecord => None,
+    }
+}
+
+/// Порядковый номер области (для ключа карты).
+fn area_index(area: ModbusArea) -> usize {
+    AREAS.iter().position(|&a| a == area).unwrap_or(0)
+}
+
+/// Обращения к одному адресу.
+#[derive(Debug, Default)]
+struct CellAccess {
+    functions: BTreeSet<u8>,
+    hits: u64,
+}
+
+/// Диапазон адресов, к которым обращался мастер.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObservedRange {
+    pub area: ModbusArea,
+    pub start: u16,
+    pub count: u16,
+    /// Коды функций обращения.
+    pub functions: Vec<u8>,
+    /// Наибольшее число обращений к адресу диапазона.
+    pub hits: u64,
+    /// Адреса описаны переменными.
+    pub mapped: bool,
+}
+
+/// Накопитель обращений мастера.
+#[derive(Debug, Default)]
+pub struct AccessMap {
+    cells: Mutex<BTreeMap<(usize, u16), CellAccess>>,
+}
+
+impl AccessMap {
+    /// Учесть запрос мастера (до его обработки, независимо от результата).
+    pub fn record(&self, request: &ModbusRequest) {
+        let Some(area) = FunctionCode::from_u8(request.function_code).and_then(function_area)
+        else {
+            return;
+        };
+        let Some((start, quantity)) = request.address_range() else {
+            return;
+        };
+        let area = area_index(area);
+        let end = (start as u32 + quantity as u32).min(u16::MAX as u32 + 1);
+
+        let mut cells = self.cells.lock();
+        for address in start as u32..end {
+            let cell = cells.entry((area, address as u16)).or_default();
+            cell.functions.insert(request.function_code);
+            cell.hits += 1;
+        }
+    }
+
+    /// Сбросить накопленные обращения.
+    pub fn reset(&self) {
+        self.cells.lock().clear();
+    }
+
+    /// Диапазоны обращений. Соседние адреса объединяются, если совпадают
+    /// функции и признак наличия переменных.
+    pub fn observed(&self, data_store: &SharedDataStore) -> Vec<ObservedRange> {
+        let cells = self.cells.lock();
+        let mut ranges: Vec<ObservedRange> = Vec::new();
+
+        for (&(area, address), cell) in cells.iter() {
+            let area = AREAS[area];
+            let functions: Vec<u8> = cell.functions.iter().copied().collect();
+            let mapped = data_store.is_defined(area, address);
+            match ranges.last_mut() {
+                Some(range)
+                    if range.area == area
+                        && range.start as u32 + range.count as u32 == address as u32
+                        && range.functions == functions
+                        && range.mapped == mapped =>
+                {
+                    range.count += 1;
+                    range.hits = range.hits.max(cell.hits);
+                }
+                _ => ranges.push(ObservedRange {
+                    area,
+                    start: address,
+                    count: 1,
+                    functions,
+                    hits: cell.hits,
+                    mapped,
+                }),
+            }
+        }
+        ranges
+    }
+
+    /// Определения переменных для адресов, которые мастер опрашивает,
+    /// но которые не описаны переменными: uint16 для регистров, bool для битов.
+    pub fn generate_variables(&self, data_store: &SharedDataStore) -> Vec<ModbusVariable> {
+        let cells = self.cells.lock();
+        cells
+            .iter()
+            .map(|(&(area, address), cell)| (AREAS[area], address, cell))
+            .filter(|&(area, address, _)| !data_store.is_defined(area, address))
+            .enumerate()
+            .map(|(seq, (area, address, cell))| {
+                let (prefix, data_type, value) = match area {
+                    ModbusArea::Coil => ("coil", ModbusDataType::Bool, ModbusValue::Bool(false)),
+                    ModbusArea::DiscreteInput => {
+                        ("di", ModbusDataType::Bool, ModbusValue::Bool(false))
+                    }
+                    ModbusArea::InputRegister => {
+                        ("ir", ModbusDataType::Uint16, ModbusValue::Number(0.0))
+                    }
+                    ModbusArea::HoldingRegister => {
+                        ("hr", ModbusDataType::Uint16, ModbusValue::Number(0.0))
+                    }
+                };
+                let functions: Vec<String> = cell
+                    .functions
+                    .iter()
+                    .map(|f| format!("{:02X}", f))
+                    .collect();
+                ModbusVariable {
+                    name: format!("{}_{}", prefix, address),
+                    value,
+                    note: Some(format!(
+                        "Создано по трафику мастера: функции {}",
+                        functions.join(", ")
+                    )),
+                    ..ModbusVariable::new(generate_variable_id(seq), area, address, data_type)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Общая ссылка на карту обращений.
+pub type SharedAccessMap = Arc<AccessMap>;
+
+/// Создать общую карту обращений.
+pub fn create_shared_access_map() -> SharedAccessMap {
+    Arc::new(AccessMap::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+
+    fn request(function_code: u8, start: u16, quantity: u16) -> ModbusRequest {
+        let mut frame = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, function_code];
+        frame.extend_from_slice(&start.to_be_bytes());
+        frame.extend_from_slice(&quantity.to_be_bytes());
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_unmapped_polled_addresses_become_variables() {
+        let store = create_shared_data_store();
+        store.load_variables(&[ModbusVariable::new(
+            "temp",
+            ModbusArea::HoldingRegister,
+            10,
+            ModbusDataType::Uint16,
+        )]);
+        let map = AccessMap::default();
+        map.record(&request(0x03, 10, 3));
+        map.record(&request(0x03, 10, 3));
+        // Запись одного регистра: в поле количества — значение, а не длина
+        map.record(&request(0x06, 12, 0xBEEF));
+
+        let observed = map.observed(&store);
+        assert_eq!(observed.len(), 3);
+        assert!(observed[0].mapped && observed[0].start == 10 && observed[0].hits == 2);
+        assert_eq!((observed[1].start, observed[1].count), (11, 1));
+        assert_eq!(observed[2].functions, vec![0x03, 0x06]);
+
+        let variables = map.generate_variables(&store);
+        let names: Vec<&str> = variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["hr_11", "hr_12"]);
+        assert!(variables[1].note.as_deref().unwrap().contains("03, 06"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Соглашения об адресации Modbus.
+//!
+//! Внутри приложения (хранилище данных, файл проекта, протокол) адреса всегда
+//! 0-based — так, как они передаются в PDU. Соглашение проекта влияет только на
+//! то, как адреса показываются пользователю и принимаются командами/импортом:
+//! - `zero_based` — адрес протокола как есть (holding register 0);
+//! - `one_based` — адрес протокола + 1 (holding register 1);
+//! - `modicon` — номер с префиксом области (40001 для holding register 0).
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::ModbusArea;
+
+/// Соглашение об адресации, используемое в проекте.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressingConvention {
+    #[default]
+    ZeroBased,
+    OneBased,
+    Modicon,
+}
+
+/// Наибольший адрес протокола, который записывается 5-значным номером Modicon.
+const MODICON_5_DIGIT_MAX: u16 = 9998;
+
+/// Префикс области в нотации Modicon (0x, 1x, 3x, 4x).
+fn modicon_prefix(area: ModbusArea) -> u32 {
+    match area {
+        ModbusArea::Coil => 0,
+        ModbusArea::DiscreteInput => 1,
+        ModbusArea::InputRegister => 3,
+        ModbusArea::HoldingRegister => 4,
+    }
+}
+
+impl AddressingConvention {
+    /// Перевести адрес протокола в отображаемый номер.
+    pub fn to_display(self, area: ModbusArea, address: u16) -> u32 {
+        match self {
+            AddressingConvention::ZeroBased => address as u32,
+            AddressingConvention::OneBased => address as u32 + 1,
+            AddressingConvention::Modicon => {
+                let base = if address <= MODICON_5_DIGIT_MAX {
+                    10_000
+                } else {
+                    100_000
+                };
+                modicon_prefix(area) * base + address as u32 + 1
+            }
+        }
+    }
+
+    /// Перевести отображаемый номер в адрес протокола.
+    /// Для Modicon префикс должен соответствовать области.
+    pub fn to_protocol(self, area: ModbusArea, display: u32) -> Result<u16, String> {
+        let address = match self {
+            AddressingConvention::ZeroBased => Some(display),
+            AddressingConvention::OneBased => display.checked_sub(1),
+            // У coils префикс 0, поэтому 5- и 6-значные номера не различаются
+            AddressingConvention::Modicon if area == ModbusArea::Coil => display.checked_sub(1),
+            AddressingConvention::Modicon => {
+                let (parsed_area, address) = parse_modicon(display)?;
+                if parsed_area != area {
+                    return Err(format!(
+                        "Адрес {} не относится к области {:?}",
+                        display, area
+                    ));
+                }
+                Some(address as u32)
+            }
+        };
+
+        address
+            .filter(|a| *a <= u16::MAX as u32)
+            .map(|a| a as u16)
+            .ok_or_else(|| format!("Адрес {} вне допустимого диапазона", display))
+    }
+
+    /// Форматировать адрес для отображения (Modicon с ведущими нулями для coils).
+    pub fn format(self, area: ModbusArea, address: u16) -> String {
+        let display = self.to_display(area, address);
+        match self {
+            AddressingConvention::Modicon if address <= MODICON_5_DIGIT_MAX => {
+                format!("{:05}", display)
+            }
+            AddressingConvention::Modicon => format!("{:06}", display),
+            _ => display.to_string(),
+        }
+    }
+}
+
+/// Разобрать номер Modicon (5 или 6 знаков) в область и адрес протокола.
+pub fn parse_modicon(display: u32) -> Result<(ModbusArea, u16), String> {
+    let (prefix, offset) = if display < 100_000 {
+        (display / 10_000, display % 10_000)
+    } else {
+        (display / 100_000, display % 100_000)
+    };
+
+    let area = match prefix {
+        0 => ModbusArea::Coil,
+        1 => ModbusArea::DiscreteInput,
+        3 => ModbusArea::InputRegister,
+        4 => ModbusArea::HoldingRegister,
+        _ => return Err(format!("Неизвестный префикс области в адресе {}", display)),
+    };
+
+    if offset == 0 || offset - 1 > u16::MAX as u32 {
+        return Err(format!("Адрес {} вне допустимого диапазона", display));
+    }
+
+    Ok((area, (offset - 1) as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modicon_roundtrip() {
+        let conv = AddressingConvention::Modicon;
+        assert_eq!(conv.to_display(ModbusArea::HoldingRegister, 0), 40001);
+        assert_eq!(conv.to_display(ModbusArea::InputRegister, 9), 30010);
+        assert_eq!(conv.to_display(ModbusArea::HoldingRegister, 9999), 410000);
+        assert_eq!(conv.format(ModbusArea::Coil, 11), "00012");
+
+        for address in [0u16, 1, 9998, 9999, 65535] {
+            let display = conv.to_display(ModbusArea::HoldingRegister, address);
+            assert_eq!(
+                conv.to_protocol(ModbusArea::HoldingRegister, display),
+                Ok(address)
+            );
+        }
+    }
+
+    #[test]
+    fn test_modicon_rejects_wrong_area() {
+        let conv = AddressingConvention::Modicon;
+        assert!(conv.to_protocol(ModbusArea::InputRegister, 40001).is_err());
+        assert!(parse_modicon(20001).is_err());
+        assert!(parse_modicon(40000).is_err());
+    }
+
+    #[test]
+    fn test_one_based() {
+        let conv = AddressingConvention::OneBased;
+        assert_eq!(conv.to_display(ModbusArea::Coil, 0), 1);
+        assert_eq!(conv.to_protocol(ModbusArea::Coil, 1), Ok(0));
+        assert!(conv.to_protocol(ModbusArea::Coil, 0).is_err());
+        assert!(conv.to_protocol(ModbusArea::Coil, 65537).is_err());
+    }
+}
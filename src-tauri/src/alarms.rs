@@ -0,0 +1,363 @@
+//! Подсистема тревог и событий.
+//!
+//! Тревога задаётся условием-выражением над переменными, важностью и сообщением.
+//! Условия вычисляются на каждом такте движка симуляции. Тревога остаётся в списке
+//! активных, пока условие выполняется или пока она не квитирована.
+//!
+//! Для проверки логики HMI состояние тревоги может отражаться в переменных:
+//! флаг активности и флаг «не квитирована». Запись мастером 0 во флаг
+//! «не квитирована» квитирует тревогу так же, как команда из UI.
+
+use std::collections::HashMap;
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::SharedDataStore;
+use crate::expression::Expr;
+use crate::types::chrono_now_iso;
+
+/// Важность тревоги.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlarmSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// Определение тревоги.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlarmDefinition {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Условие срабатывания (см. [`crate::expression`]).
+    pub condition: String,
+    #[serde(default)]
+    pub severity: AlarmSeverity,
+    #[serde(default)]
+    pub message: String,
+    /// ID переменной, в которую выставляется флаг активности тревоги.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_variable_id: Option<String>,
+    /// ID переменной с флагом «не квитирована»; запись мастером 0 квитирует тревогу.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unacked_variable_id: Option<String>,
+}
+
+/// Состояние тревоги для UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlarmStatus {
+    pub id: String,
+    pub name: String,
+    pub severity: AlarmSeverity,
+    pub message: String,
+    pub active: bool,
+    pub acknowledged: bool,
+    pub raised_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cleared_at: Option<String>,
+    /// Ошибка вычисления условия (например, неизвестная переменная).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Состояние выполнения одной тревоги.
+#[derive(Debug, Default)]
+struct AlarmRuntime {
+    active: bool,
+    acknowledged: bool,
+    raised_at: Option<String>,
+    cleared_at: Option<String>,
+    error: Option<String>,
+    /// Последние записанные значения флагов (активна, не квитирована).
+    published: Option<(bool, bool)>,
+}
+
+impl AlarmRuntime {
+    /// Тревога в списке активных: условие выполняется или не квитирована.
+    fn is_listed(&self) -> bool {
+        self.raised_at.is_some() && (self.active || !self.acknowledged)
+    }
+}
+
+/// Определения тревог и их текущее состояние.
+#[derive(Default)]
+pub struct AlarmManager {
+    definitions: RwLock<Vec<(AlarmDefinition, Expr)>>,
+    states: Mutex<HashMap<String, AlarmRuntime>>,
+}
+
+impl AlarmManager {
+    /// Есть ли определения тревог.
+    pub fn is_empty(&self) -> bool {
+        self.definitions.read().is_empty()
+    }
+
+    /// Список определений тревог.
+    pub fn definitions(&self) -> Vec<AlarmDefinition> {
+        self.definitions
+            .read()
+            .iter()
+            .map(|(def, _)| def.clone())
+            .collect()
+    }
+
+    /// Заменить все определения (например, при загрузке проекта).
+    /// Определения с некорректным условием пропускаются.
+    pub fn set_definitions(&self, definitions: Vec<AlarmDefinition>) {
+        let parsed = definitions
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, def)| match compile(def, i) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::warn!("Тревога пропущена: {}", e);
+                    None
+                }
+            })
+            .collect();
+        *self.definitions.write() = parsed;
+        self.states.lock().clear();
+    }
+
+    /// Добавить тревогу или заменить существующую с тем же ID.
+    pub fn upsert(&self, definition: AlarmDefinition) -> Result<AlarmDefinition, String> {
+        let mut definitions = self.definitions.write();
+        let (definition, expr) = compile(definition, definitions.len())?;
+
+        match definitions.iter_mut().find(|(d, _)| d.id == definition.id) {
+            Some(existing) => *existing = (definition.clone(), expr),
+            None => definitions.push((definition.clone(), expr)),
+        }
+        self.states.lock().remove(&definition.id);
+        Ok(definition)
+    }
+
+    /// Удалить тревогу. Возвращает false, если тревога не найдена.
+    pub fn remove(&self, id: &str) -> bool {
+        let mut definitions = self.definitions.write();
+        let before = definitions.len();
+        definitions.retain(|(d, _)| d.id != id);
+        self.states.lock().remove(id);
+        definitions.len() != before
+    }
+
+    /// Квитировать тревогу.
+    pub fn acknowledge(&self, id: &str) -> Result<(), String> {
+        let mut states = self.states.lock();
+        match states.get_mut(id) {
+            Some(state) if state.is_listed() => {
+                state.acknowledged = true;
+                Ok(())
+            }
+            _ => Err(format!("Тревога '{}' не активна", id)),
+        }
+    }
+
+    /// Квитировать все тревоги. Возвращает количество квитированных.
+    pub fn acknowledge_all(&self) -> usize {
+        let mut states = self.states.lock();
+        states
+            .values_mut()
+            .filter(|s| s.is_listed() && !s.acknowledged)
+            .map(|s| s.acknowledged = true)
+            .count()
+    }
+
+    /// Список активных и неквитированных тревог.
+    pub fn active_alarms(&self) -> Vec<AlarmStatus> {
+        let definitions = self.definitions.read();
+        let states = self.states.lock();
+
+        definitions
+            .iter()
+            .filter_map(|(def, _)| {
+                let state = states.get(&def.id)?;
+                if !state.is_listed() && state.error.is_none() {
+                    return None;
+                }
+                Some(AlarmStatus {
+                    id: def.id.clone(),
+                    name: def.name.clone(),
+                    severity: def.severity,
+                    message: def.message.clone(),
+                    active: state.active,
+                    acknowledged: state.acknowledged,
+                    raised_at: state.raised_at.clone().unwrap_or_default(),
+                    cleared_at: state.cleared_at.clone(),
+                    error: state.error.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Вычислить условия всех тревог и обновить флаги в хранилище.
+    /// Возвращает true, если состояние хотя бы одной тревоги изменилось.
+    pub fn evaluate(&self, data_store: &SharedDataStore, snapshot: &HashMap<String, f64>) -> bool {
+        let definitions = self.definitions.read();
+        let mut states = self.states.lock();
+        let resolve = |name: &str| snapshot.get(name).copied();
+        let mut changed = false;
+
+        for (def, expr) in definitions.iter() {
+            let state = states.entry(def.id.clone()).or_default();
+
+            // Квитирование мастером: флаг «не квитирована» сброшен в 0
+            if let (Some(var_id), Some((_, true))) = (&def.unacked_variable_id, state.published) {
+                if snapshot.get(var_id) == Some(&0.0) && !state.acknowledged {
+                    state.acknowledged = true;
+                    changed = true;
+                }
+            }
+
+            match expr.eval_bool(&resolve) {
+                Ok(condition) => {
+                    if state.error.take().is_some() {
+                        changed = true;
+                    }
+                    if condition && !state.active {
+                        state.active = true;
+                        state.acknowledged = false;
+                        state.raised_at = Some(chrono_now_iso());
+                        state.cleared_at = None;
+                        changed = true;
+                        log::info!("Тревога '{}': {}", def.name, def.message);
+                    } else if !condition && state.active {
+                        state.active = false;
+                        state.cleared_at = Some(chrono_now_iso());
+                        changed = true;
+                    }
+                }
+                Err(e) => {
+                    if state.error.as_deref() != Some(e.as_str()) {
+                        state.error = Some(e);
+                        changed = true;
+                    }
+                }
+            }
+
+            let flags = (state.active, state.is_listed() && !state.acknowledged);
+            if state.published != Some(flags) {
+                if let Some(var_id) = &def.active_variable_id {
+                    data_store.set_flag(var_id, flags.0);
+                }
+                if let Some(var_id) = &def.unacked_variable_id {
+                    data_store.set_flag(var_id, flags.1);
+                }
+                state.published = Some(flags);
+            }
+        }
+
+        changed
+    }
+}
+
+/// Разобрать условие тревоги и назначить ID при необходимости.
+fn compile(mut definition: AlarmDefinition, seq: usize) -> Result<(AlarmDefinition, Expr), String> {
+    let expr = Expr::parse(&definition.condition)
+        .map_err(|e| format!("Условие тревоги '{}': {}", definition.name, e))?;
+    if definition.id.is_empty() {
+        definition.id = format!("alarm_{}_{}", seq + 1, chrono_now_iso().replace('.', ""));
+    }
+    if definition.name.is_empty() {
+        definition.name = definition.condition.clone();
+    }
+    Ok((definition, expr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+    fn variable(
+        id: &str,
+        area: ModbusArea,
+        address: u16,
+        data_type: ModbusDataType,
+    ) -> ModbusVariable {
+        ModbusVariable {
+            value: ModbusValue::Number(0.0),
+            ..ModbusVariable::new(id, area, address, data_type)
+        }
+    }
+
+    fn setup() -> (SharedDataStore, AlarmManager) {
+        let store = create_shared_data_store();
+        store.load_variables(&[
+            variable(
+                "temp",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            ),
+            variable("alarm", ModbusArea::Coil, 0, ModbusDataType::Bool),
+            variable("unacked", ModbusArea::Coil, 1, ModbusDataType::Bool),
+        ]);
+        let manager = AlarmManager::default();
+        manager
+            .upsert(AlarmDefinition {
+                id: "hi".to_string(),
+                name: "Высокая температура".to_string(),
+                condition: "temp > 80".to_string(),
+                severity: AlarmSeverity::Critical,
+                message: "Перегрев".to_string(),
+                active_variable_id: Some("alarm".to_string()),
+                unacked_variable_id: Some("unacked".to_string()),
+            })
+            .unwrap();
+        (store, manager)
+    }
+
+    #[test]
+    fn test_alarm_lifecycle_with_ui_acknowledge() {
+        let (store, manager) = setup();
+
+        store.update_variable("temp", ModbusValue::Number(90.0));
+        assert!(manager.evaluate(&store, &store.numeric_snapshot()));
+        assert_eq!(store.read_coils(0, 2).unwrap(), vec![true, true]);
+        assert_eq!(manager.active_alarms().len(), 1);
+
+        // Условие пропало, но тревога не квитирована — остаётся в списке
+        store.update_variable("temp", ModbusValue::Number(20.0));
+        manager.evaluate(&store, &store.numeric_snapshot());
+        assert_eq!(store.read_coils(0, 2).unwrap(), vec![false, true]);
+        assert!(!manager.active_alarms()[0].active);
+
+        manager.acknowledge("hi").unwrap();
+        manager.evaluate(&store, &store.numeric_snapshot());
+        assert!(manager.active_alarms().is_empty());
+        assert_eq!(store.read_coils(0, 2).unwrap(), vec![false, false]);
+    }
+
+    #[test]
+    fn test_master_acknowledges_via_coil() {
+        let (store, manager) = setup();
+
+        store.update_variable("temp", ModbusValue::Number(90.0));
+        manager.evaluate(&store, &store.numeric_snapshot());
+
+        store.write_single_coil(1, false).unwrap();
+        assert!(manager.evaluate(&store, &store.numeric_snapshot()));
+        let alarms = manager.active_alarms();
+        assert!(alarms[0].active && alarms[0].acknowledged);
+        assert!(manager
+            .upsert(AlarmDefinition {
+                id: String::new(),
+                name: String::new(),
+                condition: "temp >".to_string(),
+                severity: AlarmSeverity::Info,
+                message: String::new(),
+                active_variable_id: None,
+                unacked_variable_id: None,
+            })
+            .is_err());
+    }
+}
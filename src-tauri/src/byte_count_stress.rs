@@ -0,0 +1,97 @@
+//! Ответы чтения с несоответствием счётчика байт.
+//!
+//! Обычно симулятор формирует безупречные кадры. В этом режиме данные ответов
+//! на функции чтения (0x01–0x04) намеренно дополняются или обрезаются на
+//! заданное число байт, а поле счётчика байт остаётся прежним. Длина в MBAP
+//! пересчитывается под фактический кадр, чтобы мастер получил ровно этот кадр
+//! и проверял именно счётчик байт, а не сборку TCP-потока.
+
+use serde::{Deserialize, Serialize};
+
+use crate::modbus_protocol::MbapHeader;
+
+/// Вид искажения.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ByteCountMode {
+    /// Ответы не искажаются.
+    #[default]
+    Off,
+    /// Лишние байты (0x00) после данных.
+    Pad,
+    /// Данные короче счётчика байт.
+    Truncate,
+}
+
+/// Настройки искажения в профиле подключения.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ByteCountStress {
+    #[serde(default)]
+    pub mode: ByteCountMode,
+    /// На сколько байт дополнить или обрезать данные.
+    #[serde(default = "default_bytes")]
+    pub bytes: u8,
+}
+
+fn default_bytes() -> u8 {
+    1
+}
+
+impl ByteCountStress {
+    /// Исказить ответ чтения. Исключения и ответы других функций не меняются.
+    /// Возвращает счётчик байт и фактическую длину данных, если ответ искажён.
+    pub fn apply(&self, response: &mut Vec<u8>) -> Option<(u8, usize)> {
+        if self.mode == ByteCountMode::Off || self.bytes == 0 {
+            return None;
+        }
+        let &function = response.get(MbapHeader::SIZE)?;
+        let &byte_count = response.get(MbapHeader::SIZE + 1)?;
+        if !(0x01..=0x04).contains(&function) {
+            return None;
+        }
+        let data_start = MbapHeader::SIZE + 2;
+        match self.mode {
+            ByteCountMode::Off => return None,
+            ByteCountMode::Pad => response.resize(response.len() + self.bytes as usize, 0),
+            ByteCountMode::Truncate => {
+                let keep = response.len().saturating_sub(self.bytes as usize);
+                response.truncate(keep.max(data_start));
+            }
+        }
+        let length = (response.len() - (MbapHeader::SIZE - 1)) as u16;
+        response[4..6].copy_from_slice(&length.to_be_bytes());
+        Some((byte_count, response.len() - data_start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_and_truncate_keep_byte_count() {
+        // Ответ 0x03 с двумя регистрами
+        let frame = vec![0, 1, 0, 0, 0, 7, 1, 0x03, 4, 0x12, 0x34, 0x56, 0x78];
+        let stress = |mode, bytes| ByteCountStress { mode, bytes };
+
+        let mut padded = frame.clone();
+        assert_eq!(
+            stress(ByteCountMode::Pad, 2).apply(&mut padded),
+            Some((4, 6))
+        );
+        assert_eq!(&padded[4..9], &[0, 9, 1, 0x03, 4]);
+        assert_eq!(padded.len(), frame.len() + 2);
+
+        let mut truncated = frame.clone();
+        assert_eq!(
+            stress(ByteCountMode::Truncate, 10).apply(&mut truncated),
+            Some((4, 0))
+        );
+        assert_eq!(truncated, vec![0, 1, 0, 0, 0, 3, 1, 0x03, 4]);
+
+        // Исключения не искажаются
+        let mut exception = vec![0, 1, 0, 0, 0, 3, 1, 0x83, 2];
+        assert_eq!(stress(ByteCountMode::Pad, 1).apply(&mut exception), None);
+    }
+}
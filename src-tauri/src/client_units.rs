@@ -0,0 +1,90 @@
+//! Назначение unit ID по адресу клиента.
+//!
+//! Когда два мастера подключаются к одному порту, но должны видеть разные
+//! данные, запросы клиента с заданным IP обслуживаются указанным unit ID
+//! независимо от Unit ID в кадре: unit ID сервера — переменными проекта,
+//! unit ID ведомого шлюза TCP → RTU — его собственным хранилищем. В ответе
+//! мастер получает свой исходный Unit ID.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::serial_gateway::SerialGatewayConfig;
+
+/// Unit ID для клиента с заданным IP.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientUnit {
+    pub client: IpAddr,
+    pub unit_id: u8,
+}
+
+/// Назначенные unit ID по IP клиента.
+#[derive(Debug, Clone, Default)]
+pub struct ClientUnits {
+    units: HashMap<IpAddr, u8>,
+}
+
+impl ClientUnits {
+    /// Проверить назначения: каждый клиент указан один раз, а unit ID
+    /// обслуживается сервером или ведомым шлюза.
+    pub fn new(
+        list: &[ClientUnit],
+        server_unit_id: u8,
+        serial_gateway: Option<&SerialGatewayConfig>,
+    ) -> Result<Self, String> {
+        let mut units = HashMap::with_capacity(list.len());
+        for entry in list {
+            let served = entry.unit_id == server_unit_id
+                || serial_gateway
+                    .is_some_and(|g| g.slaves.iter().any(|s| s.unit_id == entry.unit_id));
+            if !served {
+                return Err(format!(
+                    "Unit ID {} клиента {} не обслуживается сервером",
+                    entry.unit_id, entry.client
+                ));
+            }
+            if units.insert(entry.client, entry.unit_id).is_some() {
+                return Err(format!("Клиент {} указан дважды", entry.client));
+            }
+        }
+        Ok(Self { units })
+    }
+
+    /// Назначенный unit ID клиента `ip:порт`.
+    pub fn unit_for(&self, client_addr: &str) -> Option<u8> {
+        if self.units.is_empty() {
+            return None;
+        }
+        let addr: SocketAddr = client_addr.parse().ok()?;
+        self.units.get(&addr.ip().to_canonical()).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units_by_client_ip() {
+        let gateway = SerialGatewayConfig {
+            slaves: vec![serde_json::from_str(r#"{"unitId": 2}"#).unwrap()],
+            ..SerialGatewayConfig::default()
+        };
+        let list: Vec<ClientUnit> = serde_json::from_str(
+            r#"[{"client": "10.0.0.5", "unitId": 1}, {"client": "10.0.0.6", "unitId": 2}]"#,
+        )
+        .unwrap();
+        let units = ClientUnits::new(&list, 1, Some(&gateway)).unwrap();
+        assert_eq!(units.unit_for("10.0.0.5:40000"), Some(1));
+        assert_eq!(units.unit_for("[::ffff:10.0.0.6]:40000"), Some(2));
+        assert_eq!(units.unit_for("10.0.0.7:40000"), None);
+        assert_eq!(units.unit_for("simulation"), None);
+
+        assert!(ClientUnits::new(&list, 1, None).is_err());
+        let duplicate = [list[0].clone(), list[0].clone()];
+        assert!(ClientUnits::new(&duplicate, 1, None).is_err());
+    }
+}
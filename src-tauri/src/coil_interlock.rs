@@ -0,0 +1,181 @@
+//! Взаимоисключающие группы coils (блокировки переключателя).
+//!
+//! В группе включённым может быть только один coil, как у избирательного
+//! переключателя режимов. Когда мастер включает coil группы, остальные
+//! включённые coils либо выключаются автоматически, либо запись отклоняется
+//! исключением Illegal Data Value — по настройке группы. Запрос, включающий
+//! сразу несколько coils одной группы, отклоняется всегда.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::ModbusDataStore;
+use crate::modbus_protocol::{FunctionCode, ModbusRequest};
+use crate::types::ModbusArea;
+
+/// Реакция на включение coil при уже включённом соседе по группе.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InterlockAction {
+    /// Выключить остальные coils группы.
+    #[default]
+    ClearOthers,
+    /// Отклонить запись.
+    Reject,
+}
+
+/// Группа взаимоисключающих coils.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoilInterlockGroup {
+    #[serde(default)]
+    pub name: String,
+    /// Адреса coils группы.
+    pub coils: Vec<u16>,
+    #[serde(default)]
+    pub action: InterlockAction,
+}
+
+/// Группы блокировок, общие для всех клиентов сервера.
+#[derive(Debug, Default)]
+pub struct CoilInterlocks {
+    groups: RwLock<Vec<CoilInterlockGroup>>,
+}
+
+/// Значения coils, записываемые запросом 0x05 или 0x0F.
+fn written_coils(request: &ModbusRequest) -> Vec<(u16, bool)> {
+    let Some((start, count)) = request.address_range() else {
+        return Vec::new();
+    };
+    match FunctionCode::from_u8(request.function_code) {
+        Some(FunctionCode::WriteSingleCoil) => request
+            .data
+            .get(2)
+            .map(|&high| vec![(start, high == 0xFF)])
+            .unwrap_or_default(),
+        Some(FunctionCode::WriteMultipleCoils) => (0..count)
+            .filter_map(|i| {
+                let byte = request.data.get(5 + i as usize / 8)?;
+                Some((start.wrapping_add(i), byte >> (i % 8) & 1 == 1))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl CoilInterlocks {
+    /// Заменить группы.
+    pub fn set_groups(&self, groups: Vec<CoilInterlockGroup>) {
+        *self.groups.write() = groups;
+    }
+
+    /// Проверить запись coils. `Ok` — адреса coils, которые нужно выключить
+    /// после выполнения записи; `Err` — имя группы, запретившей запись.
+    pub fn check(
+        &self,
+        request: &ModbusRequest,
+        data_store: &ModbusDataStore,
+    ) -> Result<Vec<u16>, String> {
+        let groups = self.groups.read();
+        if groups.is_empty() {
+            return Ok(Vec::new());
+        }
+        let written = written_coils(request);
+        if written.is_empty() {
+            return Ok(Vec::new());
+        }
+        let value_after = |address: u16| {
+            written
+                .iter()
+                .find(|(a, _)| *a == address)
+                .map(|(_, on)| *on)
+                .unwrap_or_else(|| data_store.dump_area(ModbusArea::Coil, address, 1)[0] != 0)
+        };
+
+        let mut clear = Vec::new();
+        for group in groups.iter() {
+            let switched_on: Vec<u16> = written
+                .iter()
+                .filter(|(address, on)| *on && group.coils.contains(address))
+                .map(|(address, _)| *address)
+                .collect();
+            let selected = match switched_on[..] {
+                [] => continue,
+                [selected] => selected,
+                _ => return Err(group.name.clone()),
+            };
+            let others: Vec<u16> = group
+                .coils
+                .iter()
+                .copied()
+                .filter(|&address| address != selected && value_after(address))
+                .collect();
+            if others.is_empty() {
+                continue;
+            }
+            match group.action {
+                InterlockAction::Reject => return Err(group.name.clone()),
+                InterlockAction::ClearOthers => clear.extend(others),
+            }
+        }
+        Ok(clear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus_protocol::MbapHeader;
+    use crate::types::{ModbusDataType, ModbusValue, ModbusVariable};
+
+    fn write_coil(address: u16) -> ModbusRequest {
+        let mut frame = Vec::new();
+        MbapHeader {
+            transaction_id: 1,
+            protocol_id: 0,
+            length: 6,
+            unit_id: 1,
+        }
+        .write_to(&mut frame);
+        frame.push(0x05);
+        frame.extend_from_slice(&address.to_be_bytes());
+        frame.extend_from_slice(&[0xFF, 0x00]);
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    fn coil(address: u16, on: bool) -> ModbusVariable {
+        ModbusVariable {
+            value: ModbusValue::Bool(on),
+            ..ModbusVariable::new(
+                format!("mode{}", address),
+                ModbusArea::Coil,
+                address,
+                ModbusDataType::Bool,
+            )
+        }
+    }
+
+    #[test]
+    fn test_selector_clears_or_rejects() {
+        let store = ModbusDataStore::new();
+        store.load_variables(&[coil(0, true), coil(1, false), coil(2, false)]);
+        let interlocks = CoilInterlocks::default();
+        let group = |action| CoilInterlockGroup {
+            name: "mode".to_string(),
+            coils: vec![0, 1, 2],
+            action,
+        };
+
+        interlocks.set_groups(vec![group(InterlockAction::ClearOthers)]);
+        assert_eq!(interlocks.check(&write_coil(1), &store), Ok(vec![0]));
+        // Повторное включение уже включённого coil ничего не выключает
+        assert_eq!(interlocks.check(&write_coil(0), &store), Ok(vec![]));
+        assert_eq!(interlocks.check(&write_coil(7), &store), Ok(vec![]));
+
+        interlocks.set_groups(vec![group(InterlockAction::Reject)]);
+        assert_eq!(
+            interlocks.check(&write_coil(2), &store),
+            Err("mode".to_string())
+        );
+    }
+}
@@ -2,94 +2,1911 @@
 //!
 //! Эти команды обеспечивают интерфейс между Vue-фронтендом и Rust-бэкендом.
 
-use tauri::{AppHandle, State};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::data_store::SharedDataStore;
-use crate::server::SharedModbusServer;
+use parking_lot::RwLock;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::access_map::{function_area, ObservedRange};
+use crate::addressing::AddressingConvention;
+
+use crate::alarms::{AlarmDefinition, AlarmStatus};
+use crate::comms_failure::{CommsFailureConfig, CommsFailureStatus};
+use crate::consistency_check::{self, ConsistencyReport, ConsistencyTestRequest};
+use crate::data_store::{BitBank, ClearScope, ForcedVariable, RecordFile, SharedDataStore};
+use crate::device_scan::{self, ScanRequest, ScanResult};
+use crate::disturbance_library::{DisturbanceLibrary, ImportedLibrary};
+use crate::edit_session::{EditSessionInfo, SharedEditManager};
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::event_buffer::EventBufferStatus;
+use crate::exception_injection::ExceptionInjection;
+use crate::exception_stats::ExceptionStatEntry;
+use crate::fault_rules::{FaultPreset, FaultRule};
+use crate::generator::GeneratorConfig;
+use crate::handshake::{handshake_templates, HandshakeTemplate};
+use crate::harness::{self, Scenario, StepPreview};
+use crate::integrity::{self, IntegrityReport};
+use crate::ipc_payload::{self, PayloadFormat};
+use crate::map_server::{MapServer, MapServerStatus, MapSource};
+use crate::master::{PollConfig, PollTag, SharedModbusMaster, TagStats};
+use crate::memory_dump::{self, DumpFormat};
+use crate::modbus_protocol::decode::{self, DecodedFrame, Framing};
+use crate::modbus_protocol::golden::{self, GoldenReport};
+use crate::modbus_protocol::FunctionCode;
+use crate::pcap_export;
+use crate::plc_import::{import_symbols, PlcImportOptions, PlcImportResult};
+use crate::project_watcher::{ProjectWatchStatus, SharedProjectWatcher};
+use crate::quality::{QualityConfig, QualityStatus, VariableQuality};
+use crate::register_map::{RegisterMap, REGISTER_MAP_SCHEMA};
+use crate::request_script::{self, RequestScript, ScriptReport};
+use crate::request_stats::{StatisticsFormat, StatisticsReport};
+use crate::scenario_tracks::{SharedScenarioTracks, TrackConflict, TrackStatus};
+use crate::sensor_fault::{SensorFault, SensorFaultStatus};
+use crate::server::{SharedModbusServer, SimulatedResponse};
+use crate::session_diff::{compare_profiles, SessionDiffReport, SessionProfile, SessionSource};
+use crate::settings::{unix_time_secs, Preferences, RecentProject, SettingsStore, SharedSettings};
+use crate::simulation::{Behavior, SharedSimulationEngine};
+use crate::snapshot_schedule::{
+    SharedSnapshotScheduler, SnapshotInfo, SnapshotScheduleConfig, SnapshotScheduleStatus,
+};
+use crate::state_snapshot::StateSnapshot;
+use crate::subscriptions::{SharedSubscriptionManager, SubscriptionInfo};
+use crate::tick_stats::TickStatsSnapshot;
+use crate::traffic_log::{TrafficPage, TrafficQuery};
+use crate::triggers::TriggerDefinition;
 use crate::types::{
-    ModbusConnectionProfile, ModbusProject, ModbusValue, ModbusVariable, ServerStatus,
+    chrono_now_iso, exception_code_name, AddressValidation, LoadProgressEvent, LogSeverity,
+    ModbusArea, ModbusConnectionProfile, ModbusProject, ModbusValue, ModbusVariable,
+    ProjectMetadata, ServerStatus, VariablesChangedEvent,
 };
+use crate::value_override::ValueOverrideStatus;
+use crate::watch::{SharedWatchManager, WatchInfo};
+use crate::write_learning::QuarantinedWrite;
+
+/// Название события об изменении набора переменных.
+const VARIABLES_CHANGED_EVENT_NAME: &str = "variables-changed";
+
+/// Название события о прогрессе загрузки переменных.
+const LOAD_PROGRESS_EVENT_NAME: &str = "variables-load-progress";
+
+fn project_file_path(settings: &SettingsStore, path: Option<String>) -> CommandResult<PathBuf> {
+    match path {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => settings
+            .default_project_path()
+            .map_err(|e| AppError::new(ErrorCode::Io, e)),
+    }
+}
+
+/// Запомнить проект в списке недавних.
+fn remember_recent_project(settings: &SharedSettings, path: &std::path::Path) {
+    let path = path.to_string_lossy().to_string();
+    if let Err(e) = settings.update(|s| s.touch_recent_project(&path, unix_time_secs())) {
+        log::warn!("Не удалось обновить список недавних проектов: {e}");
+    }
+}
+
+/// Загрузить переменные в хранилище в фоновом потоке, сообщая о прогрессе
+/// событием `variables-load-progress`, чтобы большой проект не блокировал UI.
+async fn load_variables_in_background(
+    app_handle: &AppHandle,
+    data_store: &SharedDataStore,
+    variables: Vec<ModbusVariable>,
+) -> CommandResult<()> {
+    let app_handle = app_handle.clone();
+    let data_store = data_store.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        data_store.load_variables_with_progress(&variables, |loaded, total| {
+            let event = LoadProgressEvent { loaded, total };
+            if let Err(e) = app_handle.emit(LOAD_PROGRESS_EVENT_NAME, &event) {
+                log::warn!("Не удалось отправить прогресс загрузки переменных: {}", e);
+            }
+        })
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Загрузка переменных прервана: {e}")))
+}
+
+/// Подключить или отключить образ процесса согласно настройке проекта.
+/// Файл образа лежит рядом с файлом проекта (`<проект>.image`).
+fn apply_process_image(
+    data_store: &SharedDataStore,
+    project_path: &Path,
+    project: &ModbusProject,
+) -> CommandResult<()> {
+    if project.persist_process_image {
+        let image_path = project_path.with_extension("image");
+        data_store
+            .attach_image(&image_path)
+            .map_err(|e| AppError::new(ErrorCode::Io, e).with_context(image_path.to_string_lossy()))
+    } else {
+        data_store.detach_image();
+        Ok(())
+    }
+}
+
+/// Загрузить проект из файла.
+/// Без указания пути используется файл в каталоге данных.
+#[tauri::command]
+pub fn load_project_file(
+    state: State<'_, AppState>,
+    path: Option<String>,
+) -> CommandResult<Option<ModbusProject>> {
+    let path = project_file_path(&state.settings, path)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let context = path.to_string_lossy().to_string();
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io(&e, "Не удалось прочитать файл проекта", &context))?;
+    let project: ModbusProject = serde_json::from_str(&data).map_err(|e| {
+        AppError::invalid(format!("Ошибка JSON проекта: {e}")).with_context(&context)
+    })?;
+    *state.addressing.write() = project.addressing;
+    *state.metadata.write() = project.metadata.clone();
+    check_integrity(&state, &project.variables);
+    apply_process_image(&state.data_store, &path, &project)?;
+    state
+        .data_store
+        .set_record_files(&project.record_files)
+        .map_err(|e| AppError::invalid(e).with_context(&context))?;
+    if let Some(seed) = project.random_seed {
+        state.server.rng().set_seed(seed);
+    }
+    state.simulation.apply_project(&project);
+    state
+        .server
+        .faults()
+        .set_presets(project.fault_presets.clone());
+    remember_recent_project(&state.settings, &path);
+    Ok(Some(project))
+}
+
+/// Сохранить проект в файл.
+/// Без указания пути используется файл в каталоге данных.
+/// Поведения симуляции, тревоги, триггеры, пресеты сбоев, файлы записей,
+/// зерно генератора и сведения о проекте берутся из бэкенда (источник истины).
+#[tauri::command]
+pub fn save_project_file(
+    state: State<'_, AppState>,
+    mut project: ModbusProject,
+    path: Option<String>,
+) -> CommandResult<()> {
+    let path = project_file_path(&state.settings, path)?;
+    state.simulation.fill_project(&mut project);
+    project.fault_presets = state.server.faults().presets();
+    project.record_files = state.data_store.record_files();
+    project.random_seed = Some(state.server.rng().seed());
+    project.metadata = state.metadata.read().clone();
+    let data = serde_json::to_string_pretty(&project).map_err(|e| {
+        AppError::internal(format!("Не удалось сериализовать проект: {e}"))
+            .with_context(path.to_string_lossy())
+    })?;
+    *state.addressing.write() = project.addressing;
+    std::fs::write(&path, data).map_err(|e| {
+        AppError::io(
+            &e,
+            "Не удалось записать файл проекта",
+            path.to_string_lossy(),
+        )
+    })?;
+    apply_process_image(&state.data_store, &path, &project)?;
+    state.project_watcher.note_saved(&path);
+    remember_recent_project(&state.settings, &path);
+    Ok(())
+}
+
+/// Получить список недавно открытых проектов (закреплённые первыми).
+#[tauri::command]
+pub fn list_recent_projects(state: State<'_, AppState>) -> Vec<RecentProject> {
+    state.settings.get().recent_projects
+}
+
+/// Закрепить или открепить проект в списке недавних.
+#[tauri::command]
+pub fn pin_recent_project(
+    state: State<'_, AppState>,
+    path: String,
+    pinned: bool,
+) -> CommandResult<Vec<RecentProject>> {
+    let found = state
+        .settings
+        .update(|s| s.set_recent_project_pinned(&path, pinned))?;
+    if !found {
+        return Err(AppError::not_found(
+            format!("Проект '{}' не найден в списке недавних", path),
+            path,
+        ));
+    }
+    Ok(state.settings.get().recent_projects)
+}
+
+/// Очистить список недавних проектов.
+/// По умолчанию закреплённые проекты сохраняются.
+#[tauri::command]
+pub fn clear_recent_projects(
+    state: State<'_, AppState>,
+    keep_pinned: Option<bool>,
+) -> CommandResult<Vec<RecentProject>> {
+    state
+        .settings
+        .update(|s| s.clear_recent_projects(keep_pinned.unwrap_or(true)))?;
+    Ok(state.settings.get().recent_projects)
+}
+
+/// Получить предпочтения пользователя.
+#[tauri::command]
+pub fn get_preferences(state: State<'_, AppState>) -> Preferences {
+    state.settings.preferences()
+}
+
+/// Сохранить предпочтения пользователя. Новый каталог данных и срок хранения
+/// журнала обмена применяются сразу.
+#[tauri::command]
+pub fn set_preferences(
+    state: State<'_, AppState>,
+    preferences: Preferences,
+) -> CommandResult<Preferences> {
+    preferences.validate().map_err(AppError::invalid)?;
+    let previous = state.settings.preferences();
+    state
+        .settings
+        .update(|s| s.preferences = preferences.clone())?;
+
+    let traffic_log = state.server.traffic_log();
+    if preferences.data_dir != previous.data_dir {
+        traffic_log.open_default(state.settings.data_dir());
+    }
+    let pruned = traffic_log.prune(preferences.log_retention_days)?;
+    if pruned > 0 {
+        log::info!("Из журнала обмена удалено {} старых записей", pruned);
+    }
+    Ok(preferences)
+}
+
+/// Начать наблюдение за внешними изменениями файла проекта.
+/// При `auto_reload` переменные из изменённого файла сливаются с хранилищем данных
+/// с сохранением текущих значений.
+#[tauri::command]
+pub fn watch_project_file(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+    auto_reload: Option<bool>,
+) -> CommandResult<ProjectWatchStatus> {
+    let path = project_file_path(&state.settings, path)?;
+    state
+        .project_watcher
+        .watch(app_handle, path, auto_reload.unwrap_or(false));
+    Ok(state.project_watcher.status())
+}
+
+/// Прекратить наблюдение за файлом проекта.
+#[tauri::command]
+pub fn unwatch_project_file(state: State<'_, AppState>) -> ProjectWatchStatus {
+    state.project_watcher.unwatch();
+    state.project_watcher.status()
+}
+
+/// Получить состояние наблюдения за файлом проекта.
+#[tauri::command]
+pub fn get_project_watch_status(state: State<'_, AppState>) -> ProjectWatchStatus {
+    state.project_watcher.status()
+}
+
+/// Получить JSON Schema формата карты регистров.
+#[tauri::command]
+pub fn get_register_map_schema() -> String {
+    REGISTER_MAP_SCHEMA.to_string()
+}
+
+/// Экспортировать карту регистров (только переменные) в JSON-файл.
+/// Адреса записываются в соглашении проекта.
+#[tauri::command]
+pub fn export_register_map(
+    state: State<'_, AppState>,
+    path: String,
+    variables: Vec<ModbusVariable>,
+    name: Option<String>,
+) -> CommandResult<()> {
+    let mut map = RegisterMap::from_variables(name, &variables, *state.addressing.read());
+    let metadata = state.metadata.read().clone();
+    map.metadata = (!metadata.is_empty()).then_some(metadata);
+    let data = serde_json::to_string_pretty(&map).map_err(|e| {
+        AppError::internal(format!("Не удалось сериализовать карту регистров: {e}"))
+            .with_context(&path)
+    })?;
+    std::fs::write(&path, data)
+        .map_err(|e| AppError::io(&e, "Не удалось записать файл карты регистров", &path))?;
+    log::info!(
+        "Карта регистров ({} переменных) экспортирована в {}",
+        variables.len(),
+        path
+    );
+    Ok(())
+}
+
+/// Импортировать карту регистров из JSON-файла.
+/// Адреса переводятся из соглашения, указанного в файле, в адреса протокола.
+/// Возвращает переменные для добавления в проект.
+#[tauri::command]
+pub fn import_register_map(path: String) -> CommandResult<Vec<ModbusVariable>> {
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io(&e, "Не удалось прочитать файл карты регистров", &path))?;
+    let variables = RegisterMap::parse(&data)?.into_variables();
+    log::info!("Импортировано {} переменных из {}", variables.len(), path);
+    Ok(variables)
+}
+
+/// Открыть текущую карту регистров по HTTP (Markdown и JSON) для коллег
+/// в локальной сети. Без `host` сервер слушает все интерфейсы, без `port` —
+/// порт из предпочтений. Возвращает адрес для браузера.
+#[tauri::command]
+pub async fn start_map_server(
+    state: State<'_, AppState>,
+    host: Option<String>,
+    port: Option<u16>,
+) -> CommandResult<String> {
+    let host = host.unwrap_or_else(|| "0.0.0.0".to_string());
+    let port = port.unwrap_or_else(|| state.settings.preferences().map_server_port);
+    let data_store = state.data_store.clone();
+    let addressing = state.addressing.clone();
+    let metadata = state.metadata.clone();
+    let source: MapSource = Arc::new(move || {
+        let mut map =
+            RegisterMap::from_variables(None, &data_store.get_variables(), *addressing.read());
+        let metadata = metadata.read().clone();
+        map.metadata = (!metadata.is_empty()).then_some(metadata);
+        map
+    });
+    let context = format!("{}:{}", host, port);
+    state
+        .map_server
+        .start(&host, port, source)
+        .await
+        .map_err(|e| {
+            AppError::io(
+                &e,
+                "Не удалось открыть HTTP-доступ к карте регистров",
+                context,
+            )
+        })
+}
+
+/// Выключить HTTP-доступ к карте регистров.
+#[tauri::command]
+pub fn stop_map_server(state: State<'_, AppState>) -> CommandResult<()> {
+    if state.map_server.stop() {
+        Ok(())
+    } else {
+        Err(AppError::new(
+            ErrorCode::NotRunning,
+            "HTTP-доступ к карте регистров не включён",
+        ))
+    }
+}
+
+/// Состояние HTTP-доступа к карте регистров.
+#[tauri::command]
+pub fn get_map_server_status(state: State<'_, AppState>) -> MapServerStatus {
+    state.map_server.status()
+}
+
+/// Импортировать переменные из таблицы символов ПЛК (TIA Portal, Step7, Codesys).
+/// Символы, которые не удалось перевести в адреса Modbus, возвращаются в списке `skipped`.
+#[tauri::command]
+pub fn import_plc_symbols(
+    state: State<'_, AppState>,
+    path: String,
+    options: PlcImportOptions,
+) -> CommandResult<PlcImportResult> {
+    let content = std::fs::read(&path)
+        .map_err(|e| AppError::io(&e, "Не удалось прочитать таблицу символов", &path))?;
+    let content = String::from_utf8_lossy(&content);
+    let result = import_symbols(&content, &options, *state.addressing.read());
+    log::info!(
+        "Импорт символов ПЛК из {}: {} переменных, {} пропущено",
+        path,
+        result.variables.len(),
+        result.skipped.len()
+    );
+    Ok(result)
+}
+
+/// Выгрузить диапазон области в файл дампа (двоичный или Intel HEX).
+/// Диапазон обрезается по границе области. Возвращает количество выгруженных ячеек.
+#[tauri::command]
+pub fn export_memory_dump(
+    state: State<'_, AppState>,
+    area: ModbusArea,
+    start: u32,
+    count: usize,
+    format: DumpFormat,
+    path: String,
+) -> CommandResult<usize> {
+    let addressing = *state.addressing.read();
+    let (data, exported) =
+        memory_dump::export_range(&state.data_store, addressing, area, start, count, format)
+            .map_err(AppError::invalid)?;
+    std::fs::write(&path, data)
+        .map_err(|e| AppError::io(&e, "Не удалось записать дамп памяти", &path))?;
+    log::info!(
+        "Дамп {:?} {}..+{} выгружен в {}",
+        area,
+        start,
+        exported,
+        path
+    );
+    Ok(exported)
+}
+
+/// Битовые карты диапазона coils или discrete inputs (значения и описанные
+/// адреса) для сеточного отображения без списка переменных.
+/// Начало диапазона — в соглашении об адресации проекта.
+#[tauri::command]
+pub fn get_bit_bank(
+    state: State<'_, AppState>,
+    area: ModbusArea,
+    start: u32,
+    count: usize,
+) -> CommandResult<BitBank> {
+    let mut bank = state
+        .data_store
+        .bit_bank(area, protocol_address(&state, area, start)?, count)
+        .map_err(AppError::invalid)?;
+    bank.start = start;
+    Ok(bank)
+}
+
+/// Получить файлы записей (функции 0x14/0x15).
+#[tauri::command]
+pub fn get_record_files(state: State<'_, AppState>) -> Vec<RecordFile> {
+    state.data_store.record_files()
+}
+
+/// Добавить или заменить файл записей.
+#[tauri::command]
+pub fn set_record_file(state: State<'_, AppState>, file: RecordFile) -> CommandResult<()> {
+    log::info!(
+        "Файл записей {}: {} записей",
+        file.file_number,
+        file.records.len()
+    );
+    state
+        .data_store
+        .set_record_file(file)
+        .map_err(AppError::invalid)
+}
+
+/// Удалить файл записей.
+#[tauri::command]
+pub fn delete_record_file(state: State<'_, AppState>, file_number: u16) -> CommandResult<()> {
+    if state.data_store.remove_record_file(file_number) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Файл записей {} не найден", file_number),
+            file_number.to_string(),
+        ))
+    }
+}
+
+/// Сохранить полное состояние приложения в файл: профиль сервера, переменные
+/// с текущими значениями, области данных, настройки симуляции и активные
+/// вмешательства (форсирование, отказы датчиков, инжекции).
+#[tauri::command]
+pub fn export_state(
+    state: State<'_, AppState>,
+    mut project: ModbusProject,
+    path: String,
+) -> CommandResult<()> {
+    project.addressing = *state.addressing.read();
+    project.metadata = state.metadata.read().clone();
+    let snapshot = StateSnapshot::capture(
+        project,
+        &state.data_store,
+        &state.server,
+        &state.simulation,
+        unix_time_secs(),
+    );
+    let data = serde_json::to_string_pretty(&snapshot).map_err(|e| {
+        AppError::internal(format!("Не удалось сериализовать снимок состояния: {e}"))
+            .with_context(&path)
+    })?;
+    std::fs::write(&path, data)
+        .map_err(|e| AppError::io(&e, "Не удалось записать снимок состояния", &path))?;
+    log::info!("Снимок состояния сохранён в {}", path);
+    Ok(())
+}
+
+/// Восстановить состояние приложения из файла снимка. Работающий сервер
+/// останавливается и, если в снимке он был запущен, запускается заново с
+/// профилем из снимка. Возвращает проект для отображения в UI.
+#[tauri::command]
+pub async fn import_state(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> CommandResult<ModbusProject> {
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io(&e, "Не удалось прочитать снимок состояния", &path))?;
+    let snapshot: StateSnapshot = serde_json::from_str(&data).map_err(|e| {
+        AppError::invalid(format!("Ошибка JSON снимка состояния: {e}")).with_context(&path)
+    })?;
+
+    if state.server.is_running() {
+        state.server.stop()?;
+    }
+    snapshot.restore(&state.data_store, &state.server, &state.simulation)?;
+    *state.addressing.write() = snapshot.project.addressing;
+    *state.metadata.write() = snapshot.project.metadata.clone();
+    if snapshot.server_running {
+        state.server.set_app_handle(app_handle);
+        state.server.start().await?;
+    }
+    log::info!("Состояние восстановлено из {}", path);
+    Ok(snapshot.project)
+}
+
+/// Включить периодические снимки хранилища данных (в памяти).
+#[tauri::command]
+pub fn start_snapshot_schedule(
+    state: State<'_, AppState>,
+    config: SnapshotScheduleConfig,
+) -> CommandResult<SnapshotScheduleStatus> {
+    state.snapshots.start(config)?;
+    Ok(state.snapshots.status())
+}
+
+/// Выключить периодические снимки; накопленные снимки сохраняются.
+#[tauri::command]
+pub fn stop_snapshot_schedule(state: State<'_, AppState>) -> SnapshotScheduleStatus {
+    state.snapshots.stop();
+    state.snapshots.status()
+}
+
+/// Получить расписание и список снимков (от старых к новым).
+#[tauri::command]
+pub fn list_snapshots(state: State<'_, AppState>) -> SnapshotScheduleStatus {
+    state.snapshots.status()
+}
+
+/// Откатить хранилище данных к снимку. Сервер и симуляция продолжают работу.
+#[tauri::command]
+pub fn restore_snapshot(state: State<'_, AppState>, id: u64) -> CommandResult<SnapshotInfo> {
+    state
+        .snapshots
+        .restore(id)
+        .map_err(|e| AppError::not_found(e, id.to_string()))
+}
+
+/// Загрузить файл дампа в область начиная с адреса `start`.
+/// Адреса без переменных тоже записываются; значения переменных обновляются.
+/// Возвращает количество записанных ячеек.
+#[tauri::command]
+pub fn import_memory_dump(
+    state: State<'_, AppState>,
+    area: ModbusArea,
+    start: u32,
+    format: DumpFormat,
+    path: String,
+) -> CommandResult<usize> {
+    let data = std::fs::read(&path)
+        .map_err(|e| AppError::io(&e, "Не удалось прочитать дамп памяти", &path))?;
+    let addressing = *state.addressing.read();
+    let written =
+        memory_dump::import_range(&state.data_store, addressing, area, start, format, &data)
+            .map_err(AppError::invalid)?;
+    log::info!(
+        "Дамп {} загружен в {:?} {}..+{}",
+        path,
+        area,
+        start,
+        written
+    );
+    Ok(written)
+}
+
+/// Записать сырое значение во входную область (discrete inputs, input registers)
+/// по адресу в соглашении проекта, без переменной. Предназначено для скриптов
+/// и внешних источников данных.
+#[tauri::command]
+pub fn set_input(
+    state: State<'_, AppState>,
+    area: ModbusArea,
+    address: u32,
+    raw_value: u16,
+) -> CommandResult<()> {
+    state
+        .data_store
+        .set_input(area, protocol_address(&state, area, address)?, raw_value)
+        .map_err(AppError::invalid)
+}
+
+/// Установить соглашение об адресации проекта.
+#[tauri::command]
+pub fn set_addressing_convention(state: State<'_, AppState>, convention: AddressingConvention) {
+    log::info!("Соглашение об адресации: {:?}", convention);
+    *state.addressing.write() = convention;
+}
+
+/// Получить текущее соглашение об адресации проекта.
+#[tauri::command]
+pub fn get_addressing_convention(state: State<'_, AppState>) -> AddressingConvention {
+    *state.addressing.read()
+}
+
+/// Задать сведения о проекте (описание, заказчик, прошивка, теги).
+/// Сохраняются в файл проекта при следующем сохранении.
+#[tauri::command]
+pub fn set_project_metadata(state: State<'_, AppState>, metadata: ProjectMetadata) {
+    *state.metadata.write() = metadata;
+}
+
+/// Получить сведения о текущем проекте.
+#[tauri::command]
+pub fn get_project_metadata(state: State<'_, AppState>) -> ProjectMetadata {
+    state.metadata.read().clone()
+}
+
+/// Форматировать адрес протокола в соглашении проекта (например, 0 → "40001").
+#[tauri::command]
+pub fn format_address(state: State<'_, AppState>, area: ModbusArea, address: u16) -> String {
+    state.addressing.read().format(area, address)
+}
+
+/// Перевести адрес, введённый в соглашении проекта, в адрес протокола.
+#[tauri::command]
+pub fn parse_address(
+    state: State<'_, AppState>,
+    area: ModbusArea,
+    display: u32,
+) -> CommandResult<u16> {
+    protocol_address(&state, area, display)
+}
+
+/// Адрес протокола для адреса, принятого командой в соглашении проекта.
+fn protocol_address(state: &AppState, area: ModbusArea, display: u32) -> CommandResult<u16> {
+    state
+        .addressing
+        .read()
+        .to_protocol(area, display)
+        .map_err(AppError::invalid)
+}
+
+/// Начать транзакционную сессию редактирования переменных.
+#[tauri::command]
+pub fn begin_edit(state: State<'_, AppState>) -> CommandResult<EditSessionInfo> {
+    state
+        .edit_manager
+        .begin()
+        .map_err(|e| AppError::new(ErrorCode::Conflict, e))
+}
+
+/// Добавить или заменить переменные в открытой сессии редактирования.
+#[tauri::command]
+pub fn edit_upsert_variables(
+    state: State<'_, AppState>,
+    session_id: u64,
+    variables: Vec<ModbusVariable>,
+) -> CommandResult<EditSessionInfo> {
+    state
+        .edit_manager
+        .upsert(session_id, variables)
+        .map_err(|e| AppError::not_found(e, session_id.to_string()))
+}
+
+/// Удалить переменные в открытой сессии редактирования.
+#[tauri::command]
+pub fn edit_remove_variables(
+    state: State<'_, AppState>,
+    session_id: u64,
+    ids: Vec<String>,
+) -> CommandResult<EditSessionInfo> {
+    state
+        .edit_manager
+        .remove(session_id, &ids)
+        .map_err(|e| AppError::not_found(e, session_id.to_string()))
+}
+
+/// Зафиксировать сессию редактирования: все изменения применяются одной
+/// перезагрузкой хранилища и одним событием `variables-changed`.
+#[tauri::command]
+pub fn commit_edit(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    session_id: u64,
+) -> CommandResult<Vec<ModbusVariable>> {
+    let variables = state.edit_manager.commit(session_id)?;
+    let event = VariablesChangedEvent {
+        reason: "edit_commit".to_string(),
+        variables: variables.clone(),
+    };
+    if let Err(e) = app_handle.emit(VARIABLES_CHANGED_EVENT_NAME, &event) {
+        log::warn!("Не удалось отправить событие изменения переменных: {}", e);
+    }
+    Ok(variables)
+}
+
+/// Откатить сессию редактирования.
+#[tauri::command]
+pub fn rollback_edit(state: State<'_, AppState>, session_id: u64) -> CommandResult<()> {
+    state
+        .edit_manager
+        .rollback(session_id)
+        .map_err(|e| AppError::not_found(e, session_id.to_string()))
+}
+
+/// Получить состояние открытой сессии редактирования.
+#[tauri::command]
+pub fn get_edit_session(state: State<'_, AppState>) -> Option<EditSessionInfo> {
+    state.edit_manager.current()
+}
+
+/// Подписаться на изменения набора переменных.
+/// События `variable-updates` содержат только изменившиеся переменные набора;
+/// для чисел изменение меньше `deadband` не считается изменением.
+#[tauri::command]
+pub fn subscribe_variables(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    interval: u64,
+    deadband: Option<f64>,
+) -> u64 {
+    state
+        .subscriptions
+        .subscribe(app_handle, ids, interval, deadband.unwrap_or(0.0))
+}
+
+/// Отменить подписку на переменные.
+#[tauri::command]
+pub fn unsubscribe_variables(
+    state: State<'_, AppState>,
+    subscription_id: u64,
+) -> CommandResult<()> {
+    if state.subscriptions.unsubscribe(subscription_id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Подписка {} не найдена", subscription_id),
+            subscription_id.to_string(),
+        ))
+    }
+}
+
+/// Получить список активных подписок.
+#[tauri::command]
+pub fn list_subscriptions(state: State<'_, AppState>) -> Vec<SubscriptionInfo> {
+    state.subscriptions.list()
+}
+
+/// Добавить выражение наблюдения над сырыми адресами (`HR[100] & 0x00FF`).
+/// События `watch-update` приходят при изменении результата.
+#[tauri::command]
+pub fn add_watch(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    expression: String,
+    interval: u64,
+) -> CommandResult<WatchInfo> {
+    state
+        .watches
+        .add(app_handle, expression, interval)
+        .map_err(AppError::invalid)
+}
+
+/// Удалить выражение наблюдения.
+#[tauri::command]
+pub fn remove_watch(state: State<'_, AppState>, watch_id: u64) -> CommandResult<()> {
+    if state.watches.remove(watch_id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Наблюдение {} не найдено", watch_id),
+            watch_id.to_string(),
+        ))
+    }
+}
+
+/// Получить список выражений наблюдения.
+#[tauri::command]
+pub fn list_watches(state: State<'_, AppState>) -> Vec<WatchInfo> {
+    state.watches.list()
+}
+
+/// Получить список поведений симуляции.
+#[tauri::command]
+pub fn list_behaviors(state: State<'_, AppState>) -> Vec<Behavior> {
+    state.simulation.behaviors()
+}
+
+/// Добавить или изменить поведение симуляции.
+/// Поведению без ID назначается новый идентификатор.
+#[tauri::command]
+pub fn upsert_behavior(state: State<'_, AppState>, behavior: Behavior) -> CommandResult<Behavior> {
+    state
+        .simulation
+        .upsert_behavior(behavior)
+        .map_err(AppError::invalid)
+}
+
+/// Удалить поведение симуляции.
+#[tauri::command]
+pub fn remove_behavior(state: State<'_, AppState>, id: String) -> CommandResult<()> {
+    if state.simulation.remove_behavior(&id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Поведение '{}' не найдено", id),
+            id,
+        ))
+    }
+}
+
+/// Подключить генератор значений к переменной без перезагрузки проекта.
+#[tauri::command]
+pub fn attach_generator(
+    state: State<'_, AppState>,
+    generator: GeneratorConfig,
+) -> CommandResult<Behavior> {
+    state
+        .simulation
+        .attach_generator(generator)
+        .map_err(AppError::invalid)
+}
+
+/// Изменить параметры генератора переменной (фаза сигнала сохраняется).
+#[tauri::command]
+pub fn update_generator(
+    state: State<'_, AppState>,
+    generator: GeneratorConfig,
+) -> CommandResult<Behavior> {
+    state
+        .simulation
+        .update_generator(generator)
+        .map_err(AppError::invalid)
+}
+
+/// Приостановить или возобновить генератор переменной.
+#[tauri::command]
+pub fn pause_generator(
+    state: State<'_, AppState>,
+    variable_id: String,
+    paused: bool,
+) -> CommandResult<Behavior> {
+    state
+        .simulation
+        .pause_generator(&variable_id, paused)
+        .map_err(|e| AppError::not_found(e, &variable_id))
+}
+
+/// Отключить генератор переменной (значение остаётся последним сгенерированным).
+#[tauri::command]
+pub fn remove_generator(state: State<'_, AppState>, variable_id: String) -> CommandResult<()> {
+    if state.simulation.remove_generator(&variable_id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("У переменной '{}' нет генератора", variable_id),
+            variable_id,
+        ))
+    }
+}
+
+/// Получить диагностику тактов симуляции: джиттер интервала, время поведений,
+/// переполнения такта.
+#[tauri::command]
+pub fn get_simulation_stats(state: State<'_, AppState>) -> TickStatsSnapshot {
+    state.simulation.tick_stats()
+}
+
+/// Сбросить диагностику тактов симуляции.
+#[tauri::command]
+pub fn reset_simulation_stats(state: State<'_, AppState>) {
+    state.simulation.reset_tick_stats();
+}
+
+/// Задать период такта симуляции (50–1000 мс). Сохраняется в проекте.
+#[tauri::command]
+pub fn set_simulation_interval(state: State<'_, AppState>, interval_ms: u64) -> CommandResult<()> {
+    state
+        .simulation
+        .set_tick_interval(interval_ms)
+        .map_err(AppError::invalid)
+}
+
+/// Получить период такта симуляции, мс.
+#[tauri::command]
+pub fn get_simulation_interval(state: State<'_, AppState>) -> u64 {
+    state.simulation.tick_interval().as_millis() as u64
+}
+
+/// Получить встроенные шаблоны обмена команда/статус.
+#[tauri::command]
+pub fn get_handshake_templates() -> Vec<HandshakeTemplate> {
+    handshake_templates()
+}
+
+/// Получить список определений тревог.
+#[tauri::command]
+pub fn list_alarm_definitions(state: State<'_, AppState>) -> Vec<AlarmDefinition> {
+    state.simulation.alarms().definitions()
+}
+
+/// Добавить или изменить определение тревоги.
+#[tauri::command]
+pub fn upsert_alarm_definition(
+    state: State<'_, AppState>,
+    definition: AlarmDefinition,
+) -> CommandResult<AlarmDefinition> {
+    state
+        .simulation
+        .alarms()
+        .upsert(definition)
+        .map_err(AppError::invalid)
+}
+
+/// Удалить определение тревоги.
+#[tauri::command]
+pub fn remove_alarm_definition(state: State<'_, AppState>, id: String) -> CommandResult<()> {
+    if state.simulation.alarms().remove(&id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Тревога '{}' не найдена", id),
+            id,
+        ))
+    }
+}
+
+/// Получить список активных и неквитированных тревог.
+#[tauri::command]
+pub fn get_active_alarms(state: State<'_, AppState>) -> Vec<AlarmStatus> {
+    state.simulation.alarms().active_alarms()
+}
+
+/// Квитировать тревогу.
+#[tauri::command]
+pub fn acknowledge_alarm(state: State<'_, AppState>, id: String) -> CommandResult<()> {
+    state
+        .simulation
+        .alarms()
+        .acknowledge(&id)
+        .map_err(|e| AppError::not_found(e, &id))
+}
+
+/// Квитировать все тревоги. Возвращает количество квитированных.
+#[tauri::command]
+pub fn acknowledge_all_alarms(state: State<'_, AppState>) -> usize {
+    state.simulation.alarms().acknowledge_all()
+}
+
+/// Получить список триггеров событий UI.
+#[tauri::command]
+pub fn list_triggers(state: State<'_, AppState>) -> Vec<TriggerDefinition> {
+    state.simulation.triggers().definitions()
+}
+
+/// Добавить или изменить триггер события UI.
+#[tauri::command]
+pub fn upsert_trigger(
+    state: State<'_, AppState>,
+    definition: TriggerDefinition,
+) -> CommandResult<TriggerDefinition> {
+    state
+        .simulation
+        .triggers()
+        .upsert(definition)
+        .map_err(AppError::invalid)
+}
+
+/// Удалить триггер события UI.
+#[tauri::command]
+pub fn remove_trigger(state: State<'_, AppState>, id: String) -> CommandResult<()> {
+    if state.simulation.triggers().remove(&id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Триггер '{}' не найден", id),
+            id,
+        ))
+    }
+}
+
+/// Получить список настроек качества данных переменных.
+#[tauri::command]
+pub fn list_quality_configs(state: State<'_, AppState>) -> Vec<QualityConfig> {
+    state.simulation.quality().configs()
+}
+
+/// Добавить или изменить настройку качества переменной.
+#[tauri::command]
+pub fn upsert_quality_config(
+    state: State<'_, AppState>,
+    config: QualityConfig,
+) -> CommandResult<QualityConfig> {
+    state
+        .simulation
+        .quality()
+        .upsert(config)
+        .map_err(AppError::invalid)
+}
+
+/// Удалить настройку качества переменной.
+#[tauri::command]
+pub fn remove_quality_config(state: State<'_, AppState>, variable_id: String) -> CommandResult<()> {
+    if state.simulation.quality().remove(&variable_id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Настройка качества '{}' не найдена", variable_id),
+            variable_id,
+        ))
+    }
+}
+
+/// Задать качество переменной вручную; без значения — вычислять по таймауту.
+#[tauri::command]
+pub fn set_variable_quality(
+    state: State<'_, AppState>,
+    variable_id: String,
+    quality: Option<VariableQuality>,
+) {
+    state.simulation.quality().set_manual(&variable_id, quality);
+}
+
+/// Получить переменные с качеством, отличным от Good, или заданным вручную.
+#[tauri::command]
+pub fn get_variable_qualities(state: State<'_, AppState>) -> Vec<QualityStatus> {
+    state.simulation.quality().statuses()
+}
+
+/// Назначить переменной режим отказа датчика.
+#[tauri::command]
+pub fn set_sensor_fault(
+    state: State<'_, AppState>,
+    variable_id: String,
+    fault: SensorFault,
+) -> CommandResult<()> {
+    state
+        .simulation
+        .sensor_faults()
+        .set(&state.data_store, &variable_id, fault)
+        .map_err(AppError::invalid)
+}
+
+/// Снять отказ датчика и вернуть значение до отказа.
+#[tauri::command]
+pub fn clear_sensor_fault(state: State<'_, AppState>, variable_id: String) -> CommandResult<()> {
+    if state
+        .simulation
+        .sensor_faults()
+        .clear(&state.data_store, &variable_id)
+    {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("У переменной '{}' нет отказа", variable_id),
+            variable_id,
+        ))
+    }
+}
+
+/// Снять все отказы датчиков. Возвращает количество снятых.
+#[tauri::command]
+pub fn clear_all_sensor_faults(state: State<'_, AppState>) -> usize {
+    state
+        .simulation
+        .sensor_faults()
+        .clear_all(&state.data_store)
+}
+
+/// Получить список активных отказов датчиков.
+#[tauri::command]
+pub fn get_sensor_faults(state: State<'_, AppState>) -> Vec<SensorFaultStatus> {
+    state.simulation.sensor_faults().statuses()
+}
+
+/// Подменить значение переменной на `duration_secs` секунд. По истечении
+/// срока значение вернётся к прежнему или к генератору.
+#[tauri::command]
+pub fn set_value_override(
+    state: State<'_, AppState>,
+    variable_id: String,
+    value: ModbusValue,
+    duration_secs: f64,
+) -> CommandResult<()> {
+    state
+        .simulation
+        .value_overrides()
+        .set(
+            &state.data_store,
+            &variable_id,
+            value,
+            duration_secs,
+            Instant::now(),
+        )
+        .map_err(AppError::invalid)
+}
+
+/// Снять временную подмену значения досрочно.
+#[tauri::command]
+pub fn clear_value_override(state: State<'_, AppState>, variable_id: String) -> CommandResult<()> {
+    let generated = state.simulation.has_running_generator(&variable_id);
+    if state
+        .simulation
+        .value_overrides()
+        .clear(&state.data_store, &variable_id, generated)
+    {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("У переменной '{}' нет подмены значения", variable_id),
+            variable_id,
+        ))
+    }
+}
+
+/// Получить список активных временных подмен.
+#[tauri::command]
+pub fn get_value_overrides(state: State<'_, AppState>) -> Vec<ValueOverrideStatus> {
+    state.simulation.value_overrides().statuses(Instant::now())
+}
+
+/// Получить отчёт об исключениях по диапазонам адресов (самые частые первыми).
+#[tauri::command]
+pub fn get_exception_report(state: State<'_, AppState>) -> Vec<ExceptionStatEntry> {
+    state.server.exception_stats().report()
+}
+
+/// Сбросить статистику исключений.
+#[tauri::command]
+pub fn reset_exception_stats(state: State<'_, AppState>) {
+    state.server.exception_stats().reset();
+}
+
+/// Обнулить все счётчики: запросы по функциям и клиентам, гистограммы
+/// времени обработки, исключения, счётчики сервера и тактов симуляции.
+#[tauri::command]
+pub fn reset_statistics(state: State<'_, AppState>) {
+    state.server.reset_statistics();
+    state.simulation.reset_tick_stats();
+    log::info!("Статистика сброшена");
+}
+
+/// Выгрузить отчёт со всеми счётчиками в файл (JSON или CSV).
+#[tauri::command]
+pub fn export_statistics(
+    state: State<'_, AppState>,
+    path: String,
+    format: StatisticsFormat,
+) -> CommandResult<()> {
+    let (since, functions, clients, latency) = state.server.request_stats().snapshot();
+    let metadata = state.metadata.read().clone();
+    let report = StatisticsReport {
+        generated_at: chrono_now_iso(),
+        since,
+        metadata: (!metadata.is_empty()).then_some(metadata),
+        server: state.server.get_status(),
+        functions,
+        clients,
+        latency,
+        exceptions: state.server.exception_stats().report(),
+        simulation: state.simulation.tick_stats(),
+    };
+    std::fs::write(&path, report.encode(format)?)
+        .map_err(|e| AppError::io(&e, "Не удалось записать статистику", &path))?;
+    log::info!("Статистика выгружена в {}", path);
+    Ok(())
+}
+
+/// Получить диапазоны адресов, к которым обращался мастер, с признаком
+/// наличия переменных.
+#[tauri::command]
+pub fn get_observed_access(state: State<'_, AppState>) -> Vec<ObservedRange> {
+    state.server.access_map().observed(&state.data_store)
+}
+
+/// Сгенерировать определения переменных для адресов, которые мастер опрашивает,
+/// но которые не описаны переменными. Переменные добавляет в проект UI.
+#[tauri::command]
+pub fn generate_variables_from_traffic(state: State<'_, AppState>) -> Vec<ModbusVariable> {
+    state
+        .server
+        .access_map()
+        .generate_variables(&state.data_store)
+}
+
+/// Сбросить накопленную карту обращений мастера.
+#[tauri::command]
+pub fn reset_observed_access(state: State<'_, AppState>) {
+    state.server.access_map().reset();
+}
+
+/// Включить или выключить режим обучения: записи мастера в неописанные
+/// адреса принимаются в карантин, а не отклоняются.
+#[tauri::command]
+pub fn set_write_learning(state: State<'_, AppState>, enabled: bool) {
+    state.server.write_learning().set_enabled(enabled);
+    log::info!(
+        "Режим обучения по записям мастера {}",
+        if enabled {
+            "включён"
+        } else {
+            "выключен"
+        }
+    );
+}
+
+/// Получить адреса в карантине режима обучения.
+#[tauri::command]
+pub fn get_write_quarantine(state: State<'_, AppState>) -> Vec<QuarantinedWrite> {
+    state.server.write_learning().quarantine()
+}
+
+/// Превратить карантин в определения переменных. Переменные добавляет в проект UI.
+#[tauri::command]
+pub fn convert_write_quarantine(state: State<'_, AppState>) -> Vec<ModbusVariable> {
+    state.server.write_learning().convert(&state.data_store)
+}
+
+/// Очистить карантин режима обучения.
+#[tauri::command]
+pub fn clear_write_quarantine(state: State<'_, AppState>) {
+    state.server.write_learning().clear();
+}
+
+/// Найти записи журнала обмена по фильтру с постраничным выводом.
+#[tauri::command]
+pub fn query_traffic_log(
+    state: State<'_, AppState>,
+    query: TrafficQuery,
+) -> CommandResult<TrafficPage> {
+    state
+        .server
+        .traffic_log()
+        .query(&query)
+        .map_err(AppError::invalid)
+}
+
+/// Отправлять в UI только записи лога не ниже заданной важности
+/// (журнал обмена сохраняет все записи).
+#[tauri::command]
+pub fn set_log_event_min_severity(state: State<'_, AppState>, severity: LogSeverity) {
+    state.server.traffic_log().set_event_min_severity(severity);
+}
+
+/// Выгрузить кадры журнала обмена по фильтру в pcap для Wireshark.
+/// Возвращает число записанных пакетов.
+#[tauri::command]
+pub fn export_traffic_pcap(
+    state: State<'_, AppState>,
+    query: TrafficQuery,
+    path: String,
+) -> CommandResult<usize> {
+    let entries = state.server.traffic_log().collect(&query)?;
+    let profile = state.server.profile().unwrap_or_default();
+    let (data, count) = pcap_export::encode(
+        &entries,
+        pcap_export::server_addr(&profile.host, profile.port),
+    );
+    std::fs::write(&path, data)
+        .map_err(|e| AppError::io(&e, "Не удалось записать файл pcap", &path))?;
+    log::info!("{} кадров журнала обмена выгружено в {}", count, path);
+    Ok(count)
+}
 
-fn project_file_path(_app_handle: &AppHandle) -> Result<std::path::PathBuf, String> {
-    let exe_path = std::env::current_exe()
-        .map_err(|e| format!("Не удалось получить путь к exe: {e}"))?;
-    let dir = exe_path
-        .parent()
-        .ok_or("Не удалось определить каталог приложения")?;
-    Ok(dir.join("modbus_project.json"))
+/// Очистить журнал обмена.
+#[tauri::command]
+pub fn clear_traffic_log(state: State<'_, AppState>) -> CommandResult<()> {
+    state
+        .server
+        .traffic_log()
+        .clear()
+        .map_err(|e| AppError::new(ErrorCode::Io, e))
+}
+
+/// Сохранить профиль сессии из журнала обмена как эталон для сравнения.
+#[tauri::command]
+pub fn save_session_baseline(
+    state: State<'_, AppState>,
+    query: TrafficQuery,
+    path: String,
+) -> CommandResult<SessionProfile> {
+    let profile = SessionSource::Traffic(query).load(state.server.traffic_log())?;
+    let data = serde_json::to_string_pretty(&profile).map_err(|e| {
+        AppError::internal(format!("Не удалось сериализовать эталон: {}", e)).with_context(&path)
+    })?;
+    std::fs::write(&path, data)
+        .map_err(|e| AppError::io(&e, "Не удалось записать эталон", &path))?;
+    Ok(profile)
+}
+
+/// Сравнить две сессии обмена (записи журнала или сохранённые эталоны).
+#[tauri::command]
+pub fn compare_sessions(
+    state: State<'_, AppState>,
+    baseline: SessionSource,
+    current: SessionSource,
+) -> CommandResult<SessionDiffReport> {
+    let traffic_log = state.server.traffic_log();
+    let mut report = compare_profiles(&baseline.load(traffic_log)?, &current.load(traffic_log)?);
+    report.metadata = state.metadata.read().clone();
+    Ok(report)
+}
+
+/// Состояние приложения, управляемое Tauri.
+pub struct AppState {
+    pub server: SharedModbusServer,
+    pub data_store: SharedDataStore,
+    pub settings: SharedSettings,
+    pub project_watcher: SharedProjectWatcher,
+    pub edit_manager: SharedEditManager,
+    pub subscriptions: SharedSubscriptionManager,
+    /// Выражения наблюдения над сырыми адресами.
+    pub watches: SharedWatchManager,
+    pub simulation: SharedSimulationEngine,
+    /// Мастер для опроса удалённых устройств.
+    pub master: SharedModbusMaster,
+    /// Соглашение об адресации текущего проекта.
+    pub addressing: Arc<RwLock<AddressingConvention>>,
+    /// Сведения о текущем проекте для экспорта и отчётов.
+    pub metadata: Arc<RwLock<ProjectMetadata>>,
+    /// Периодические снимки хранилища данных.
+    pub snapshots: SharedSnapshotScheduler,
+    /// Параллельные дорожки сценариев.
+    pub tracks: SharedScenarioTracks,
+    /// HTTP-доступ к карте регистров.
+    pub map_server: MapServer,
+    /// Последняя проверка целостности переменных (загрузка проекта или запуск).
+    pub integrity: RwLock<IntegrityReport>,
+}
+
+/// Проверить переменные, запомнить и записать замечания в лог.
+fn check_integrity(state: &AppState, variables: &[ModbusVariable]) -> IntegrityReport {
+    let report = integrity::check_variables(variables);
+    for issue in &report.issues {
+        log::warn!(
+            "Целостность проекта ({:?}): {}",
+            issue.severity,
+            issue.message
+        );
+    }
+    *state.integrity.write() = report.clone();
+    report
+}
+
+/// Получить результат последней проверки целостности.
+#[tauri::command]
+pub fn get_integrity_report(state: State<'_, AppState>) -> IntegrityReport {
+    state.integrity.read().clone()
+}
+
+/// Проверить переменные без загрузки в хранилище.
+#[tauri::command]
+pub fn check_variables_integrity(variables: Vec<ModbusVariable>) -> IntegrityReport {
+    integrity::check_variables(&variables)
+}
+
+/// Запустить Modbus TCP сервер с указанным профилем и переменными.
+#[tauri::command]
+pub async fn start_server(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    profile: ModbusConnectionProfile,
+    variables: Vec<ModbusVariable>,
+) -> CommandResult<ServerStatus> {
+    log::info!(
+        "Запуск сервера на {}:{} с unit_id={}, {} переменных",
+        profile.host,
+        profile.port,
+        profile.unit_id,
+        variables.len()
+    );
+
+    let report = check_integrity(&state, &variables);
+    if report.has_fatal() {
+        return Err(AppError::new(
+            ErrorCode::IntegrityFailed,
+            format!(
+                "Проект не прошёл проверку целостности: {}",
+                report.fatal_summary()
+            ),
+        ));
+    }
+
+    // Загружаем переменные в хранилище данных
+    load_variables_in_background(&app_handle, &state.data_store, variables).await?;
+
+    // Устанавливаем AppHandle для отправки событий логирования
+    state.server.set_app_handle(app_handle);
+
+    // Настраиваем и запускаем сервер
+    state.server.apply_profile(profile);
+    state.server.start().await?;
+
+    Ok(state.server.get_status())
+}
+
+/// Остановить Modbus TCP сервер.
+#[tauri::command]
+pub async fn stop_server(state: State<'_, AppState>) -> CommandResult<ServerStatus> {
+    log::info!("Остановка сервера");
+
+    state.server.stop()?;
+
+    Ok(state.server.get_status())
+}
+
+/// Закрыть все клиентские соединения, не останавливая сервер.
+/// Мастера переподключатся и начнут сессию заново.
+#[tauri::command]
+pub fn disconnect_all_clients(state: State<'_, AppState>) -> CommandResult<usize> {
+    state.server.disconnect_all()
 }
 
-/// Загрузить проект из файла рядом с приложением.
+/// Включить отказ связи: заморозить симуляцию, пометить качество Stale,
+/// при желании закрыть соединения и отвечать на чтения исключением.
 #[tauri::command]
-pub fn load_project_file(app_handle: AppHandle) -> Result<Option<ModbusProject>, String> {
-    let path = project_file_path(&app_handle)?;
-    if !path.exists() {
-        return Ok(None);
+pub fn start_comms_failure(
+    state: State<'_, AppState>,
+    config: CommsFailureConfig,
+) -> CommandResult<CommsFailureStatus> {
+    let disconnect = config.disconnect;
+    let status = state
+        .server
+        .comms_failure()
+        .start(config, &state.data_store, state.simulation.quality())
+        .map_err(AppError::invalid)?;
+    state.server.log_info(
+        "SERVER",
+        &format!(
+            "Отказ связи: заморожено переменных {}, качество Stale у {}",
+            status.frozen_variables, status.stale_variables
+        ),
+    );
+    if disconnect && state.server.is_running() {
+        state.server.disconnect_all()?;
     }
-    let data = std::fs::read_to_string(&path)
-        .map_err(|e| format!("Не удалось прочитать файл проекта: {e}"))?;
-    let project: ModbusProject =
-        serde_json::from_str(&data).map_err(|e| format!("Ошибка JSON проекта: {e}"))?;
-    Ok(Some(project))
+    Ok(status)
 }
 
-/// Сохранить проект в файл рядом с приложением.
+/// Восстановить связь после `start_comms_failure`.
 #[tauri::command]
-pub fn save_project_file(app_handle: AppHandle, project: ModbusProject) -> Result<(), String> {
-    let path = project_file_path(&app_handle)?;
-    let data = serde_json::to_string_pretty(&project)
-        .map_err(|e| format!("Не удалось сериализовать проект: {e}"))?;
-    std::fs::write(&path, data)
-        .map_err(|e| format!("Не удалось записать файл проекта: {e}"))?;
+pub fn stop_comms_failure(state: State<'_, AppState>) -> CommandResult<()> {
+    if !state
+        .server
+        .comms_failure()
+        .stop(&state.data_store, state.simulation.quality())
+    {
+        return Err(AppError::invalid("Отказ связи не включён"));
+    }
+    state.server.log_info("SERVER", "Связь восстановлена");
     Ok(())
 }
 
-/// Состояние приложения, управляемое Tauri.
-pub struct AppState {
-    pub server: SharedModbusServer,
-    pub data_store: SharedDataStore,
+/// Получить состояние отказа связи; `None` — связь в норме.
+#[tauri::command]
+pub fn get_comms_failure(state: State<'_, AppState>) -> Option<CommsFailureStatus> {
+    state.server.comms_failure().status()
 }
 
-/// Запустить Modbus TCP сервер с указанным профилем и переменными.
+/// Задать зерно генератора случайных чисел проекта.
+/// Последовательность начинается заново; зерно сохраняется вместе с проектом.
 #[tauri::command]
-pub async fn start_server(
-    app_handle: AppHandle,
+pub fn set_seed(state: State<'_, AppState>, seed: u64) {
+    state.server.rng().set_seed(seed);
+}
+
+/// Получить текущее зерно генератора случайных чисел.
+#[tauri::command]
+pub fn get_seed(state: State<'_, AppState>) -> u64 {
+    state.server.rng().seed()
+}
+
+/// Начать опрос удалённого устройства в режиме мастера.
+/// Статистика по тегам приходит событием `poll-stats` после каждого цикла.
+#[tauri::command]
+pub fn start_polling(state: State<'_, AppState>, config: PollConfig) -> CommandResult<()> {
+    log::info!(
+        "Опрос {}:{} (unit_id={}), {} тегов",
+        config.host,
+        config.port,
+        config.unit_id,
+        config.tags.len()
+    );
+    state.master.start(config).map_err(AppError::invalid)
+}
+
+/// Остановить опрос.
+#[tauri::command]
+pub fn stop_polling(state: State<'_, AppState>) -> CommandResult<()> {
+    state
+        .master
+        .stop()
+        .map_err(|e| AppError::new(ErrorCode::NotRunning, e))
+}
+
+/// Теги опроса по переменным проекта: те же адреса и типы данных, что
+/// обслуживает сервер. Переменные-биты регистров пропускаются.
+#[tauri::command]
+pub fn poll_tags_from_project(state: State<'_, AppState>) -> Vec<PollTag> {
+    state
+        .data_store
+        .get_variables()
+        .iter()
+        .filter(|var| var.bit.is_none())
+        .map(PollTag::from)
+        .collect()
+}
+
+/// Получить текущую статистику опроса по тегам.
+#[tauri::command]
+pub fn get_poll_stats(state: State<'_, AppState>) -> Vec<TagStats> {
+    state.master.stats()
+}
+
+/// Обследовать хост в режиме мастера: найти отвечающие Unit ID и диапазоны адресов.
+#[tauri::command]
+pub async fn scan_devices(request: ScanRequest) -> CommandResult<ScanResult> {
+    log::info!(
+        "Обследование {}:{}, Unit ID {}..={}, адреса {}..={}",
+        request.host,
+        request.port,
+        request.unit_from,
+        request.unit_to,
+        request.address_from,
+        request.address_to
+    );
+    device_scan::scan(&request).await.map_err(AppError::invalid)
+}
+
+/// Прогнать регрессионный набор эталонных векторов протокола.
+/// Возвращает расхождения и покрытие функций и кодов исключений.
+#[tauri::command]
+pub fn run_protocol_test_vectors() -> CommandResult<GoldenReport> {
+    let report = golden::run_suite()?;
+    log::info!(
+        "Эталонные векторы протокола: {} из {} совпали",
+        report.passed,
+        report.total
+    );
+    Ok(report)
+}
+
+/// Разобрать произвольный кадр Modbus TCP или RTU из hex для журнала:
+/// поля заголовка, функция, данные в разных представлениях.
+/// Без `framing` формат кадра определяется автоматически.
+#[tauri::command]
+pub fn decode_frame(hex: String, framing: Option<Framing>) -> CommandResult<DecodedFrame> {
+    let frame = decode::parse_hex(&hex).map_err(AppError::invalid)?;
+    decode::decode_frame(&frame, framing).map_err(AppError::invalid)
+}
+
+/// Выполнить сценарий запросов мастера против удалённого устройства
+/// (чтения с ожиданиями, записи, паузы) и вернуть структурированный отчёт.
+#[tauri::command]
+pub async fn run_request_script(
     state: State<'_, AppState>,
-    profile: ModbusConnectionProfile,
-    variables: Vec<ModbusVariable>,
-) -> Result<ServerStatus, String> {
+    script: RequestScript,
+    dry_run: Option<bool>,
+) -> CommandResult<ScriptReport> {
+    if dry_run.unwrap_or(false) {
+        return Ok(ScriptReport {
+            metadata: state.metadata.read().clone(),
+            ..request_script::preview(&script)
+        });
+    }
     log::info!(
-        "Запуск сервера на {}:{} с unit_id={}, {} переменных",
-        profile.host,
-        profile.port,
-        profile.unit_id,
-        variables.len()
+        "Сценарий запросов к {}:{}: {} шагов",
+        script.host,
+        script.port,
+        script.steps.len()
     );
+    let metadata = state.metadata.read().clone();
+    let mut report = request_script::run(&script).await;
+    report.metadata = metadata;
+    Ok(report)
+}
 
-    // Загружаем переменные в хранилище данных
-    state.data_store.load_variables(&variables);
+/// Пробный прогон сценария на копии хранилища: изменения значений по шагам
+/// без применения к работающему устройству.
+#[tauri::command]
+pub fn preview_scenario(state: State<'_, AppState>, scenario: Scenario) -> Vec<StepPreview> {
+    let sandbox = state.data_store.sandbox();
+    let presets = state.server.faults().presets();
+    harness::preview_steps(&scenario.steps, &sandbox, &presets)
+}
 
-    // Устанавливаем AppHandle для отправки событий логирования
-    state.server.set_app_handle(app_handle);
+/// Запустить дорожку сценария параллельно с уже работающими.
+/// Если другие дорожки записывают те же переменные, запуск отклоняется
+/// с кодом `conflict`; с `allow_conflicts` дорожка запускается, а
+/// пересечения возвращаются.
+#[tauri::command]
+pub fn start_scenario_track(
+    state: State<'_, AppState>,
+    name: String,
+    scenario: Scenario,
+    looped: bool,
+    allow_conflicts: Option<bool>,
+) -> CommandResult<Vec<TrackConflict>> {
+    if state.tracks.is_running(&name) {
+        return Err(AppError::new(
+            ErrorCode::AlreadyRunning,
+            format!("Дорожка '{}' уже запущена", name),
+        )
+        .with_context(name));
+    }
+    let conflicts = state.tracks.conflicts(&name, &scenario);
+    if !conflicts.is_empty() && !allow_conflicts.unwrap_or(false) {
+        let list: Vec<String> = conflicts
+            .iter()
+            .map(|c| format!("'{}' (дорожка '{}')", c.variable, c.track))
+            .collect();
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            format!(
+                "Дорожка '{}' записывает переменные других дорожек: {}",
+                name,
+                list.join(", ")
+            ),
+        )
+        .with_context(name));
+    }
+    state
+        .tracks
+        .start(&name, scenario, looped)
+        .map_err(AppError::invalid)?;
+    Ok(conflicts)
+}
 
-    // Настраиваем и запускаем сервер
+/// Остановить дорожку сценария.
+#[tauri::command]
+pub fn stop_scenario_track(state: State<'_, AppState>, name: String) -> CommandResult<()> {
+    state
+        .tracks
+        .stop(&name)
+        .map_err(|e| AppError::not_found(e, name))
+}
+
+/// Состояние дорожек сценариев.
+#[tauri::command]
+pub fn list_scenario_tracks(state: State<'_, AppState>) -> Vec<TrackStatus> {
+    state.tracks.list()
+}
+
+/// Проверить согласованность хранилища при одновременной работе нескольких
+/// клиентов (на временном сервере, текущий проект не затрагивается).
+#[tauri::command]
+pub async fn run_consistency_test(
+    request: ConsistencyTestRequest,
+) -> CommandResult<ConsistencyReport> {
+    log::info!(
+        "Проверка согласованности: {} клиентов, {} итераций",
+        request.clients,
+        request.iterations
+    );
+    consistency_check::run(&request)
+        .await
+        .map_err(AppError::from)
+}
+
+/// Подменить Transaction ID в следующих `count` ответах (0 — отменить).
+/// Возвращает число ожидающих подмен.
+#[tauri::command]
+pub fn inject_transaction_id_mismatches(state: State<'_, AppState>, count: u32) -> u32 {
+    state.server.inject_transaction_id_mismatches(count)
+}
+
+/// Отвечать исключением с заданным (в том числе нестандартным) кодом.
+#[tauri::command]
+pub fn inject_exception(
+    state: State<'_, AppState>,
+    injection: ExceptionInjection,
+) -> CommandResult<()> {
+    log::info!(
+        "Инжекция исключения 0x{:02X} ({})",
+        injection.exception_code,
+        exception_code_name(injection.exception_code)
+    );
     state
         .server
-        .set_config(profile.host, profile.port, profile.unit_id);
+        .exception_injector()
+        .schedule(injection)
+        .map_err(AppError::invalid)
+}
 
-    state.server.start().await?;
+/// Отменить инжекцию исключений.
+#[tauri::command]
+pub fn cancel_exception_injection(state: State<'_, AppState>) {
+    state.server.exception_injector().cancel();
+}
 
-    Ok(state.server.get_status())
+/// Текущая инжекция исключений.
+#[tauri::command]
+pub fn get_exception_injection(state: State<'_, AppState>) -> Option<ExceptionInjection> {
+    state.server.exception_injector().pending()
 }
 
-/// Остановить Modbus TCP сервер.
+/// Пресеты сбоев проекта.
 #[tauri::command]
-pub async fn stop_server(state: State<'_, AppState>) -> Result<ServerStatus, String> {
-    log::info!("Остановка сервера");
+pub fn get_fault_presets(state: State<'_, AppState>) -> Vec<FaultPreset> {
+    state.server.faults().presets()
+}
 
-    state.server.stop()?;
+/// Добавить пресет сбоев или заменить пресет с тем же именем.
+#[tauri::command]
+pub fn save_fault_preset(state: State<'_, AppState>, preset: FaultPreset) -> CommandResult<()> {
+    state
+        .server
+        .faults()
+        .save_preset(preset)
+        .map_err(AppError::invalid)
+}
 
-    Ok(state.server.get_status())
+/// Удалить пресет сбоев.
+#[tauri::command]
+pub fn delete_fault_preset(state: State<'_, AppState>, name: String) -> CommandResult<()> {
+    if state.server.faults().remove_preset(&name) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Пресет сбоев '{}' не найден", name),
+            name,
+        ))
+    }
+}
+
+/// Включить все правила пресета сбоев.
+#[tauri::command]
+pub fn activate_fault_preset(state: State<'_, AppState>, name: String) -> CommandResult<()> {
+    let faults = state.server.faults();
+    if !faults.presets().iter().any(|p| p.name == name) {
+        return Err(AppError::not_found(
+            format!("Пресет сбоев '{}' не найден", name),
+            name,
+        ));
+    }
+    faults.activate_preset(&name).map_err(AppError::invalid)
+}
+
+/// Выключить правила пресета сбоев.
+#[tauri::command]
+pub fn deactivate_fault_preset(state: State<'_, AppState>, name: String) -> CommandResult<()> {
+    state
+        .server
+        .faults()
+        .deactivate_preset(&name)
+        .map_err(|e| AppError::not_found(e, name))
+}
+
+/// Сохранить пресеты сбоев и сценарии в файл библиотеки возмущений.
+/// `preset_names` — выбранные пресеты проекта; `None` — все.
+#[tauri::command]
+pub fn export_disturbance_library(
+    state: State<'_, AppState>,
+    path: String,
+    preset_names: Option<Vec<String>>,
+    scenarios: BTreeMap<String, Scenario>,
+) -> CommandResult<()> {
+    let mut presets = state.server.faults().presets();
+    if let Some(names) = &preset_names {
+        if let Some(missing) = names
+            .iter()
+            .find(|name| !presets.iter().any(|p| &p.name == *name))
+        {
+            return Err(AppError::not_found(
+                format!("Пресет сбоев '{}' не найден", missing),
+                missing.clone(),
+            ));
+        }
+        presets.retain(|preset| names.contains(&preset.name));
+    }
+    let library = DisturbanceLibrary::new(presets, scenarios);
+    let data = serde_json::to_string_pretty(&library).map_err(|e| {
+        AppError::internal(format!(
+            "Не удалось сериализовать библиотеку возмущений: {e}"
+        ))
+        .with_context(&path)
+    })?;
+    std::fs::write(&path, data)
+        .map_err(|e| AppError::io(&e, "Не удалось записать библиотеку возмущений", &path))?;
+    log::info!(
+        "Библиотека возмущений сохранена в {}: пресетов {}, сценариев {}",
+        path,
+        library.fault_presets.len(),
+        library.scenarios.len()
+    );
+    Ok(())
+}
+
+/// Загрузить библиотеку возмущений в проект. Пресеты добавляются, заменяя
+/// пресеты с теми же именами; сценарии возвращаются вместе с предупреждениями
+/// о ссылках на отсутствующие переменные и пресеты.
+#[tauri::command]
+pub fn import_disturbance_library(
+    state: State<'_, AppState>,
+    path: String,
+) -> CommandResult<ImportedLibrary> {
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io(&e, "Не удалось прочитать библиотеку возмущений", &path))?;
+    let library =
+        DisturbanceLibrary::parse(&data).map_err(|e| AppError::invalid(e).with_context(&path))?;
+    let faults = state.server.faults();
+    let warnings = library.unresolved(&state.data_store.get_variables(), &faults.presets());
+    for warning in &warnings {
+        log::warn!("Библиотека возмущений {}: {}", path, warning);
+    }
+    let names = library
+        .fault_presets
+        .iter()
+        .map(|preset| preset.name.clone())
+        .collect();
+    faults
+        .save_presets(library.fault_presets)
+        .map_err(|e| AppError::invalid(e).with_context(&path))?;
+    log::info!("Библиотека возмущений загружена из {}", path);
+    Ok(ImportedLibrary {
+        fault_presets: names,
+        scenarios: library.scenarios,
+        warnings,
+    })
+}
+
+/// Включённые правила сбоев.
+#[tauri::command]
+pub fn get_armed_faults(state: State<'_, AppState>) -> BTreeMap<String, FaultRule> {
+    state.server.faults().armed()
+}
+
+/// Добавить событие с текущим временем в буфер событий устройства.
+#[tauri::command]
+pub fn push_device_event(
+    state: State<'_, AppState>,
+    code: u16,
+) -> CommandResult<EventBufferStatus> {
+    log::info!("Событие 0x{:04X} в буфер событий", code);
+    state
+        .server
+        .event_buffer()
+        .push(&state.data_store, code)
+        .map_err(AppError::invalid)
+}
+
+/// Указатели буфера событий; `None`, если буфер выключен.
+#[tauri::command]
+pub fn get_event_buffer_status(state: State<'_, AppState>) -> Option<EventBufferStatus> {
+    state.server.event_buffer().status(&state.data_store)
+}
+
+/// Выполнить запись так, как её выполнил бы мастер по сети (функции 0x05,
+/// 0x06, 0x0F, 0x10), без сокета: с проверками, исключениями и журналом.
+/// Адрес — в соглашении об адресации проекта.
+#[tauri::command]
+pub async fn simulate_master_write(
+    state: State<'_, AppState>,
+    function: u8,
+    address: u32,
+    values: Vec<u16>,
+) -> CommandResult<SimulatedResponse> {
+    let area = FunctionCode::from_u8(function)
+        .and_then(function_area)
+        .ok_or_else(|| {
+            AppError::invalid(format!(
+                "Неподдерживаемая функция записи 0x{:02X}",
+                function
+            ))
+        })?;
+    let address = protocol_address(&state, area, address)?;
+    state
+        .server
+        .simulate_master_write(function, address, &values)
+        .await
+        .map_err(AppError::invalid)
 }
 
 /// Получить текущий статус сервера.
@@ -98,6 +1915,19 @@ pub fn get_server_status(state: State<'_, AppState>) -> ServerStatus {
     state.server.get_status()
 }
 
+/// Переключить строгую проверку адресов без перезагрузки переменных.
+/// Действует со следующего запроса мастера.
+#[tauri::command]
+pub fn set_address_validation(state: State<'_, AppState>, mode: AddressValidation) -> ServerStatus {
+    state.data_store.set_address_validation(mode);
+    let message = match mode {
+        AddressValidation::Strict => "Включена строгая проверка адресов",
+        AddressValidation::Permissive => "Строгая проверка адресов отключена",
+    };
+    state.server.log_info("SERVER", message);
+    state.server.get_status()
+}
+
 /// Обновить значение переменной по её ID.
 /// Обновляет как хранилище данных, так и соответствующие регистры/коилы.
 #[tauri::command]
@@ -105,7 +1935,7 @@ pub fn update_variable(
     state: State<'_, AppState>,
     id: String,
     value: ModbusValue,
-) -> Result<bool, String> {
+) -> CommandResult<bool> {
     log::debug!("Обновление переменной {} на {:?}", id, value);
 
     let updated = state.data_store.update_variable(&id, value);
@@ -113,38 +1943,291 @@ pub fn update_variable(
     if updated {
         Ok(true)
     } else {
-        Err(format!("Переменная с id '{}' не найдена", id))
+        Err(AppError::not_found(
+            format!("Переменная с id '{}' не найдена", id),
+            id,
+        ))
+    }
+}
+
+/// Unit ID ведомых имитируемого шлюза TCP → RTU.
+#[tauri::command]
+pub fn get_unit_ids(state: State<'_, AppState>) -> Vec<u8> {
+    state.server.unit_ids()
+}
+
+fn unit_store(state: &AppState, unit_id: u8) -> CommandResult<SharedDataStore> {
+    state.server.unit_store(unit_id).ok_or_else(|| {
+        AppError::not_found(
+            format!("Ведомый с unit ID {} не найден", unit_id),
+            unit_id.to_string(),
+        )
+    })
+}
+
+/// Получить переменные ведомого шлюза с текущими значениями.
+#[tauri::command]
+pub fn get_unit_variables(
+    state: State<'_, AppState>,
+    unit_id: u8,
+) -> CommandResult<Vec<ModbusVariable>> {
+    Ok(unit_store(&state, unit_id)?.get_variables())
+}
+
+/// Обновить значение переменной ведомого шлюза.
+#[tauri::command]
+pub fn update_unit_variable(
+    state: State<'_, AppState>,
+    unit_id: u8,
+    id: String,
+    value: ModbusValue,
+) -> CommandResult<bool> {
+    log::debug!(
+        "Обновление переменной {} ведомого {} на {:?}",
+        id,
+        unit_id,
+        value
+    );
+    if unit_store(&state, unit_id)?.update_variable(&id, value) {
+        Ok(true)
+    } else {
+        Err(AppError::not_found(
+            format!("Переменная с id '{}' у ведомого {} не найдена", id, unit_id),
+            id,
+        ))
+    }
+}
+
+/// Заменить весь набор переменных ведомого шлюза. Возвращает загруженный набор.
+#[tauri::command]
+pub fn load_unit_variables(
+    state: State<'_, AppState>,
+    unit_id: u8,
+    variables: Vec<ModbusVariable>,
+) -> CommandResult<Vec<ModbusVariable>> {
+    unit_store(&state, unit_id)?;
+    state
+        .server
+        .set_unit_variables(unit_id, &variables)
+        .map_err(AppError::invalid)
+}
+
+/// Добавить переменные ведомого шлюза или заменить переменные с тем же ID.
+#[tauri::command]
+pub fn upsert_unit_variables(
+    state: State<'_, AppState>,
+    unit_id: u8,
+    variables: Vec<ModbusVariable>,
+) -> CommandResult<Vec<ModbusVariable>> {
+    let mut current = unit_store(&state, unit_id)?.get_variables();
+    for var in variables {
+        match current.iter_mut().find(|v| v.id == var.id) {
+            Some(existing) => *existing = var,
+            None => current.push(var),
+        }
+    }
+    state
+        .server
+        .set_unit_variables(unit_id, &current)
+        .map_err(AppError::invalid)
+}
+
+/// Удалить переменные ведомого шлюза по ID.
+#[tauri::command]
+pub fn remove_unit_variables(
+    state: State<'_, AppState>,
+    unit_id: u8,
+    ids: Vec<String>,
+) -> CommandResult<Vec<ModbusVariable>> {
+    let mut current = unit_store(&state, unit_id)?.get_variables();
+    current.retain(|var| !ids.contains(&var.id));
+    state
+        .server
+        .set_unit_variables(unit_id, &current)
+        .map_err(AppError::invalid)
+}
+
+/// Форсировать значение переменной: записи мастера, поведений, генераторов
+/// и UI отбрасываются, пока форсирование не снято.
+#[tauri::command]
+pub fn force_variable(
+    state: State<'_, AppState>,
+    id: String,
+    value: ModbusValue,
+) -> CommandResult<()> {
+    if !state.data_store.force_variable(&id, value.clone()) {
+        return Err(AppError::not_found(
+            format!("Переменная с id '{}' не найдена", id),
+            id,
+        ));
+    }
+    log::info!("Переменная {} форсирована значением {:?}", id, value);
+    Ok(())
+}
+
+/// Снять форсирование переменной.
+#[tauri::command]
+pub fn unforce_variable(state: State<'_, AppState>, id: String) -> CommandResult<()> {
+    if state.data_store.unforce_variable(&id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(
+            format!("Переменная '{}' не форсирована", id),
+            id,
+        ))
     }
 }
 
+/// Получить таблицу форсирования.
+#[tauri::command]
+pub fn list_forces(state: State<'_, AppState>) -> Vec<ForcedVariable> {
+    state.data_store.forced_variables()
+}
+
+/// Снять всё форсирование. Возвращает число снятых форсирований.
+#[tauri::command]
+pub fn clear_forces(state: State<'_, AppState>) -> usize {
+    let count = state.data_store.clear_forces();
+    log::info!("Снято форсирований: {}", count);
+    count
+}
+
+/// Приостановить обновление переменных генераторами, поведениями и
+/// сценариями; остальная симуляция продолжает работать. Возвращает
+/// переменные на паузе.
+#[tauri::command]
+pub fn pause_variable_simulation(
+    state: State<'_, AppState>,
+    variable_ids: Vec<String>,
+) -> CommandResult<Vec<String>> {
+    state
+        .data_store
+        .pause_simulation(&variable_ids)
+        .map_err(AppError::invalid)?;
+    log::info!("Симуляция приостановлена для {:?}", variable_ids);
+    Ok(state.data_store.simulation_paused())
+}
+
+/// Возобновить обновление переменных симуляцией. Возвращает переменные,
+/// оставшиеся на паузе.
+#[tauri::command]
+pub fn resume_variable_simulation(
+    state: State<'_, AppState>,
+    variable_ids: Vec<String>,
+) -> Vec<String> {
+    let count = state.data_store.resume_simulation(&variable_ids);
+    log::info!("Симуляция возобновлена для {} переменных", count);
+    state.data_store.simulation_paused()
+}
+
+/// Получить переменные на паузе симуляции.
+#[tauri::command]
+pub fn get_paused_variables(state: State<'_, AppState>) -> Vec<String> {
+    state.data_store.simulation_paused()
+}
+
+/// Восстановить значения сброса переменных без перезагрузки определений
+/// (в отличие от `clear_data_store`). Возвращает число сброшенных переменных.
+#[tauri::command]
+pub fn reset_values(state: State<'_, AppState>) -> usize {
+    let count = state.data_store.reset_values();
+    log::info!("Сброшено значений переменных: {}", count);
+    count
+}
+
 /// Получить все текущие переменные с их runtime-значениями.
 /// Возвращает переменные в том виде, как они хранятся в data_store,
 /// включая изменения, внесённые операциями записи от мастера.
 #[tauri::command]
 pub fn get_variables(state: State<'_, AppState>) -> Vec<ModbusVariable> {
-    state.data_store.get_variables()
+    let mut variables = state.data_store.get_variables();
+    state.simulation.annotate_generators(&mut variables);
+    variables
+}
+
+/// Получить все переменные в выбранном формате полезной нагрузки.
+/// Для `messagepack` фронтенд получает `ArrayBuffer` вместо JSON.
+#[tauri::command]
+pub fn get_variables_encoded(
+    state: State<'_, AppState>,
+    format: PayloadFormat,
+) -> CommandResult<tauri::ipc::Response> {
+    let mut variables = state.data_store.get_variables();
+    state.simulation.annotate_generators(&mut variables);
+    ipc_payload::encode_response(&variables, format).map_err(AppError::internal)
 }
 
 /// Перезагрузить переменные в хранилище данных без перезапуска сервера.
 /// Полезно для обновления определений переменных во время работы сервера.
 #[tauri::command]
-pub fn reload_variables(
+pub async fn reload_variables(
+    app_handle: AppHandle,
     state: State<'_, AppState>,
     variables: Vec<ModbusVariable>,
-) -> Result<(), String> {
+) -> CommandResult<()> {
     log::info!("Перезагрузка {} переменных", variables.len());
 
-    state.data_store.load_variables(&variables);
-
-    Ok(())
+    load_variables_in_background(&app_handle, &state.data_store, variables).await
 }
 
 /// Очистить все данные в хранилище (сбросить все регистры и коилы к значениям по умолчанию).
 #[tauri::command]
-pub fn clear_data_store(state: State<'_, AppState>) -> Result<(), String> {
+pub fn clear_data_store(state: State<'_, AppState>) -> CommandResult<()> {
     log::info!("Очистка хранилища данных");
 
     state.data_store.clear();
 
     Ok(())
 }
+
+/// Выборочно очистить хранилище и записать результат в лог.
+fn clear_scoped(state: &AppState, scope: ClearScope) -> usize {
+    let count = state.data_store.clear_scoped(&scope);
+    log::info!(
+        "Очистка хранилища {:?}: затронуто переменных {}",
+        scope,
+        count
+    );
+    count
+}
+
+/// Очистить одну область: удалить её переменные и сбросить ячейки.
+/// Возвращает число удалённых переменных.
+#[tauri::command]
+pub fn clear_area(state: State<'_, AppState>, area: ModbusArea) -> usize {
+    clear_scoped(&state, ClearScope::Area { area })
+}
+
+/// Удалить группу переменных по ID и сбросить их ячейки.
+/// Возвращает число удалённых переменных.
+#[tauri::command]
+pub fn clear_group(state: State<'_, AppState>, ids: Vec<String>) -> usize {
+    clear_scoped(&state, ClearScope::Group { ids })
+}
+
+/// Очистить диапазон адресов области (включительно).
+/// Возвращает число удалённых переменных.
+#[tauri::command]
+pub fn clear_range(
+    state: State<'_, AppState>,
+    area: ModbusArea,
+    start: u32,
+    end: u32,
+) -> CommandResult<usize> {
+    let start = protocol_address(&state, area, start)?;
+    let end = protocol_address(&state, area, end)?;
+    if start > end {
+        return Err(AppError::invalid(format!(
+            "Начало диапазона {} больше конца {}",
+            start, end
+        )));
+    }
+    Ok(clear_scoped(&state, ClearScope::Range { area, start, end }))
+}
+
+/// Сбросить все значения, сохранив определения переменных.
+/// Возвращает число сброшенных переменных.
+#[tauri::command]
+pub fn clear_values(state: State<'_, AppState>) -> usize {
+    clear_scoped(&state, ClearScope::ValuesOnly)
+}
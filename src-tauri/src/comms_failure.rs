@@ -0,0 +1,249 @@
+//! Режим «отказ связи» одной командой.
+//!
+//! Повторяет обрыв сети на объекте целиком: обновление всех переменных
+//! симуляцией замирает, качество помечается устаревшим, клиенты при желании
+//! отключаются, а чтения получают исключение с заданным кодом. При выходе из
+//! режима снимается только то, что он сам включил: переменные, стоявшие на
+//! паузе или с ручным качеством до отказа, остаются как были.
+
+use std::collections::BTreeSet;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::SharedDataStore;
+use crate::modbus_protocol::{ModbusRequest, ModbusResponse};
+use crate::quality::{QualityManager, VariableQuality};
+
+/// Настройки отказа связи.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommsFailureConfig {
+    /// Закрыть все клиентские соединения при входе в режим.
+    #[serde(default)]
+    pub disconnect: bool,
+    /// Код исключения на чтения из стандартного диапазона Modbus
+    /// 0x01..0x0B (включая коды шлюза 0x0A и 0x0B); `None` — чтения
+    /// отвечают замороженными значениями.
+    #[serde(default)]
+    pub read_exception: Option<u8>,
+}
+
+/// Состояние режима для UI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommsFailureStatus {
+    pub config: CommsFailureConfig,
+    /// Сколько переменных режим поставил на паузу.
+    pub frozen_variables: usize,
+    /// Скольким переменным режим задал качество Stale.
+    pub stale_variables: usize,
+}
+
+/// Что включил активный режим.
+#[derive(Debug)]
+struct ActiveFailure {
+    config: CommsFailureConfig,
+    paused: Vec<String>,
+    stale: Vec<String>,
+}
+
+impl ActiveFailure {
+    fn status(&self) -> CommsFailureStatus {
+        CommsFailureStatus {
+            config: self.config.clone(),
+            frozen_variables: self.paused.len(),
+            stale_variables: self.stale.len(),
+        }
+    }
+}
+
+/// Активный отказ связи сервера.
+#[derive(Debug, Default)]
+pub struct CommsFailure {
+    active: Mutex<Option<ActiveFailure>>,
+}
+
+/// Стандартные коды исключений Modbus, допустимые для отказа связи.
+const READ_EXCEPTIONS: std::ops::RangeInclusive<u8> = 0x01..=0x0B;
+
+fn is_read(function_code: u8) -> bool {
+    (0x01..=0x04).contains(&function_code)
+}
+
+impl CommsFailure {
+    /// Войти в режим: приостановить симуляцию и пометить качество Stale.
+    /// Ошибка, если режим уже включён или код исключения вне 0x01..0x0B.
+    pub fn start(
+        &self,
+        config: CommsFailureConfig,
+        data_store: &SharedDataStore,
+        quality: &QualityManager,
+    ) -> Result<CommsFailureStatus, String> {
+        if let Some(code) = config
+            .read_exception
+            .filter(|code| !READ_EXCEPTIONS.contains(code))
+        {
+            return Err(format!(
+                "Код исключения 0x{:02X} вне диапазона 0x01..0x0B",
+                code
+            ));
+        }
+        let mut active = self.active.lock();
+        if active.is_some() {
+            return Err("Отказ связи уже включён".to_string());
+        }
+
+        let variables = data_store.get_variables();
+        let paused: Vec<String> = variables
+            .iter()
+            .filter(|var| !data_store.is_simulation_paused(&var.id))
+            .map(|var| var.id.clone())
+            .collect();
+        data_store.pause_simulation(&paused)?;
+
+        let manual: BTreeSet<String> = quality
+            .statuses()
+            .into_iter()
+            .filter(|status| status.manual)
+            .map(|status| status.variable_id)
+            .collect();
+        let stale: Vec<String> = variables
+            .into_iter()
+            .map(|var| var.id)
+            .filter(|id| !manual.contains(id))
+            .collect();
+        for id in &stale {
+            quality.set_manual(id, Some(VariableQuality::Stale));
+        }
+
+        let failure = ActiveFailure {
+            config,
+            paused,
+            stale,
+        };
+        let status = failure.status();
+        *active = Some(failure);
+        Ok(status)
+    }
+
+    /// Выйти из режима. Возвращает false, если режим не был включён.
+    /// Качество снимается только у переменных, где оно всё ещё ручное Stale:
+    /// заданное пользователем во время отказа сохраняется.
+    pub fn stop(&self, data_store: &SharedDataStore, quality: &QualityManager) -> bool {
+        let Some(failure) = self.active.lock().take() else {
+            return false;
+        };
+        data_store.resume_simulation(&failure.paused);
+        for id in &failure.stale {
+            if quality.manual(id) == Some(VariableQuality::Stale) {
+                quality.set_manual(id, None);
+            }
+        }
+        true
+    }
+
+    /// Текущее состояние; `None` — связь в норме.
+    pub fn status(&self) -> Option<CommsFailureStatus> {
+        self.active.lock().as_ref().map(ActiveFailure::status)
+    }
+
+    /// Ответ-исключение на чтение во время отказа связи.
+    pub fn respond(&self, request: &ModbusRequest) -> Option<Vec<u8>> {
+        if !is_read(request.function_code) {
+            return None;
+        }
+        let code = self.active.lock().as_ref()?.config.read_exception?;
+        Some(ModbusResponse::build_response(
+            request,
+            request.function_code | 0x80,
+            &[code],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+    fn variable(id: &str, address: u16) -> ModbusVariable {
+        ModbusVariable {
+            value: ModbusValue::Number(1.0),
+            ..ModbusVariable::new(
+                id,
+                ModbusArea::HoldingRegister,
+                address,
+                ModbusDataType::Uint16,
+            )
+        }
+    }
+
+    #[test]
+    fn test_failure_freezes_and_restores_only_its_own_changes() {
+        let store = create_shared_data_store();
+        store.load_variables(&[variable("a", 0), variable("b", 1), variable("c", 2)]);
+        store.pause_simulation(&["b".to_string()]).unwrap();
+        let quality = QualityManager::default();
+        quality.set_manual("c", Some(VariableQuality::Bad));
+        let failure = CommsFailure::default();
+
+        let status = failure
+            .start(
+                CommsFailureConfig {
+                    disconnect: false,
+                    read_exception: Some(0x0B),
+                },
+                &store,
+                &quality,
+            )
+            .unwrap();
+        assert_eq!((status.frozen_variables, status.stale_variables), (2, 2));
+        assert!(failure
+            .start(CommsFailureConfig::default(), &store, &quality)
+            .is_err());
+        // Во время отказа пользователь задаёт качество сам — оно переживёт выход.
+        quality.set_manual("a", Some(VariableQuality::Bad));
+        store.update_simulated("a", ModbusValue::Number(5.0));
+        assert_eq!(store.read_holding_registers(0, 1).unwrap(), vec![1]);
+
+        let read = ModbusRequest::parse(&[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ])
+        .unwrap();
+        let write = ModbusRequest::parse(&[
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x00, 0x00, 0x01,
+        ])
+        .unwrap();
+        assert_eq!(failure.respond(&read).unwrap()[7..], [0x83, 0x0B]);
+        assert_eq!(failure.respond(&write), None);
+
+        assert!(failure.stop(&store, &quality));
+        assert!(!failure.stop(&store, &quality));
+        assert_eq!(failure.respond(&read), None);
+        assert_eq!(store.simulation_paused(), vec!["b".to_string()]);
+        let manual: Vec<_> = quality
+            .statuses()
+            .into_iter()
+            .filter(|s| s.manual)
+            .map(|s| s.variable_id)
+            .collect();
+        assert_eq!(manual, vec!["a".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn test_read_exception_must_be_standard_code() {
+        let store = create_shared_data_store();
+        let quality = QualityManager::default();
+        let failure = CommsFailure::default();
+        for code in [0x00, 0x0C, 0xFF] {
+            let config = CommsFailureConfig {
+                disconnect: false,
+                read_exception: Some(code),
+            };
+            assert!(failure.start(config, &store, &quality).is_err());
+        }
+        assert!(failure.status().is_none());
+    }
+}
@@ -0,0 +1,266 @@
+//! Встроенная проверка согласованности хранилища при нескольких клиентах.
+//!
+//! Поднимается временный сервер на свободном порту с собственным
+//! хранилищем, к нему подключаются несколько внутренних мастеров и
+//! вперемешку читают и пишут регистры:
+//! - все клиенты пишут в общий 32-битный регистр слова с равными половинами
+//!   (FC16) и читают его обратно — разные половины означают «разорванное»
+//!   значение;
+//! - каждый клиент увеличивает свой счётчик (FC06) и сразу читает его —
+//!   расхождение, как и итоговое значение в хранилище, отличное от числа
+//!   итераций, означает потерянную запись.
+//!
+//! Проверка страхует переработки блокировок в хранилище данных.
+
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::create_shared_data_store;
+use crate::master::{MasterConnection, RequestError};
+use crate::modbus_protocol::FunctionCode;
+use crate::server::create_shared_server;
+use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+/// Адрес общего 32-битного регистра.
+const SHARED_ADDRESS: u16 = 0;
+/// Адрес счётчика первого клиента; следующие идут подряд.
+const COUNTER_BASE_ADDRESS: u16 = 100;
+/// Максимальное число клиентов.
+const MAX_CLIENTS: u16 = 64;
+/// Сколько сообщений о сбоях сохраняется в отчёте.
+const MAX_FAILURES: usize = 20;
+
+/// Параметры проверки.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyTestRequest {
+    /// Число одновременных клиентов.
+    #[serde(default = "default_clients")]
+    pub clients: u16,
+    /// Число итераций на клиента.
+    #[serde(default = "default_iterations")]
+    pub iterations: u16,
+}
+
+fn default_clients() -> u16 {
+    4
+}
+
+fn default_iterations() -> u16 {
+    200
+}
+
+impl Default for ConsistencyTestRequest {
+    fn default() -> Self {
+        Self {
+            clients: default_clients(),
+            iterations: default_iterations(),
+        }
+    }
+}
+
+/// Отчёт о проверке.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyReport {
+    pub clients: u16,
+    pub iterations: u16,
+    /// Всего отправлено запросов.
+    pub requests: u64,
+    /// Чтений общего регистра с разными половинами.
+    pub torn_reads: u64,
+    /// Потерянных записей счётчиков.
+    pub lost_writes: u64,
+    /// Ошибок обмена.
+    pub request_errors: u64,
+    /// Первые сообщения о сбоях.
+    pub failures: Vec<String>,
+    pub duration_ms: u64,
+    /// Проверка пройдена: ни разрывов, ни потерь, ни ошибок обмена.
+    pub passed: bool,
+}
+
+impl ConsistencyReport {
+    fn fail(&mut self, message: String) {
+        if self.failures.len() < MAX_FAILURES {
+            self.failures.push(message);
+        }
+    }
+
+    fn merge(&mut self, other: ConsistencyReport) {
+        self.requests += other.requests;
+        self.torn_reads += other.torn_reads;
+        self.lost_writes += other.lost_writes;
+        self.request_errors += other.request_errors;
+        for message in other.failures {
+            self.fail(message);
+        }
+    }
+}
+
+fn counter_id(client: u16) -> String {
+    format!("counter_{}", client)
+}
+
+/// Рабочий цикл одного клиента.
+async fn run_client(port: u16, client: u16, iterations: u16) -> ConsistencyReport {
+    let mut report = ConsistencyReport::default();
+    let mut conn =
+        match MasterConnection::connect("127.0.0.1", port, 1, Duration::from_secs(2)).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                report.request_errors += 1;
+                report.fail(format!("Клиент {}: {}", client, e));
+                return report;
+            }
+        };
+    let counter_address = COUNTER_BASE_ADDRESS + client;
+
+    for iteration in 1..=iterations {
+        // Обе половины общего регистра получают одно и то же слово клиента
+        let word = (client << 10) | (iteration & 0x3FF);
+        let mut write = SHARED_ADDRESS.to_be_bytes().to_vec();
+        write.extend_from_slice(&[0x00, 0x02, 0x04]);
+        write.extend_from_slice(&word.to_be_bytes());
+        write.extend_from_slice(&word.to_be_bytes());
+        let mut counter = counter_address.to_be_bytes().to_vec();
+        counter.extend_from_slice(&iteration.to_be_bytes());
+
+        let result: Result<(), RequestError> = async {
+            conn.request(FunctionCode::WriteMultipleRegisters as u8, &write)
+                .await?;
+            let shared = conn
+                .read_area(ModbusArea::HoldingRegister, SHARED_ADDRESS, 2)
+                .await?;
+            if shared[0] != shared[1] {
+                report.torn_reads += 1;
+                report.fail(format!(
+                    "Клиент {}: разорванное значение 0x{:04X}{:04X}",
+                    client, shared[0], shared[1]
+                ));
+            }
+
+            conn.request(FunctionCode::WriteSingleRegister as u8, &counter)
+                .await?;
+            let own = conn
+                .read_area(ModbusArea::HoldingRegister, counter_address, 1)
+                .await?;
+            if own[0] != iteration {
+                report.lost_writes += 1;
+                report.fail(format!(
+                    "Клиент {}: записано {}, прочитано {}",
+                    client, iteration, own[0]
+                ));
+            }
+            Ok(())
+        }
+        .await;
+        report.requests += 4;
+
+        if let Err(e) = result {
+            report.request_errors += 1;
+            report.fail(format!("Клиент {}: {}", client, e));
+            if matches!(e, RequestError::Transport(_)) {
+                break;
+            }
+        }
+    }
+    report
+}
+
+/// Выполнить проверку согласованности.
+pub async fn run(request: &ConsistencyTestRequest) -> Result<ConsistencyReport, String> {
+    if request.clients == 0 || request.clients > MAX_CLIENTS {
+        return Err(format!(
+            "Число клиентов должно быть от 1 до {}",
+            MAX_CLIENTS
+        ));
+    }
+    if request.iterations == 0 {
+        return Err("Число итераций должно быть больше нуля".to_string());
+    }
+
+    let store = create_shared_data_store();
+    let mut variables = vec![ModbusVariable::new(
+        "shared",
+        ModbusArea::HoldingRegister,
+        SHARED_ADDRESS,
+        ModbusDataType::Uint32,
+    )];
+    variables.extend((0..request.clients).map(|client| {
+        ModbusVariable::new(
+            counter_id(client),
+            ModbusArea::HoldingRegister,
+            COUNTER_BASE_ADDRESS + client,
+            ModbusDataType::Uint16,
+        )
+    }));
+    store.load_variables(&variables);
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map_err(|e| format!("Не удалось выбрать порт: {}", e))?
+        .port();
+    let server = create_shared_server(store.clone());
+    server.set_config("127.0.0.1".to_string(), port, 1);
+    server.start().await?;
+
+    let started = Instant::now();
+    let handles: Vec<_> = (0..request.clients)
+        .map(|client| tokio::spawn(run_client(port, client, request.iterations)))
+        .collect();
+    let mut report = ConsistencyReport {
+        clients: request.clients,
+        iterations: request.iterations,
+        ..Default::default()
+    };
+    for handle in handles {
+        match handle.await {
+            Ok(client_report) => report.merge(client_report),
+            Err(e) => {
+                report.request_errors += 1;
+                report.fail(format!("Клиент завершился аварийно: {}", e));
+            }
+        }
+    }
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    let _ = server.stop();
+
+    // Итоговые значения счётчиков в хранилище
+    for client in 0..request.clients {
+        let value = store
+            .get_variable(&counter_id(client))
+            .map(|variable| variable.value);
+        if value != Some(ModbusValue::Number(request.iterations as f64)) {
+            report.lost_writes += 1;
+            report.fail(format!(
+                "Счётчик клиента {} в хранилище: {:?}",
+                client, value
+            ));
+        }
+    }
+
+    report.passed = report.torn_reads == 0 && report.lost_writes == 0 && report.request_errors == 0;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_clients_stay_consistent() {
+        tauri::async_runtime::block_on(async {
+            let report = run(&ConsistencyTestRequest {
+                clients: 4,
+                iterations: 50,
+            })
+            .await
+            .unwrap();
+            assert!(report.passed, "{:?}", report.failures);
+            assert_eq!(report.requests, 4 * 50 * 4);
+        });
+    }
+}
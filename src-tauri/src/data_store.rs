@@ -8,14 +8,53 @@
 //!
 //! СТРОГАЯ ПРОВЕРКА АДРЕСОВ:
 //! Сервер возвращает ошибку IllegalDataAddress для адресов,
-//! по которым нет определённых переменных.
+//! по которым нет определённых переменных. Проверку можно отключить на ходу
+//! ([`AddressValidation::Permissive`]): тогда мастер читает и пишет любые
+//! ячейки области, неописанные читаются нулями.
+//!
+//! БЛОКИРОВКИ:
+//! Каждая область — отдельный шард: значения, определённые адреса, индекс адресов
+//! и переменные области лежат под одной RwLock. Запрос мастера берёт ровно одну
+//! блокировку своей области, поэтому чтение holding registers не конкурирует
+//! с записью coils.
+//!
+//! ФОРСИРОВАНИЕ:
+//! Как в таблицах форсирования ПЛК, значение переменной можно закрепить:
+//! запись мастера завершается успешно, но форсированные ячейки сразу
+//! восстанавливаются, а записи поведений, генераторов и UI отбрасываются.
+//!
+//! ПАУЗА СИМУЛЯЦИИ:
+//! Отдельные переменные можно удержать от поведений, генераторов и шагов
+//! сценариев (они пишут через [`ModbusDataStore::update_simulated`]), пока
+//! остальная симуляция работает. Мастер и UI пишут в них как обычно.
+//!
+//! ОБРАЗ ПРОЦЕССА:
+//! Области можно подключить к файлу, отображённому в память (см. [`crate::process_image`]).
+//! Каждое изменение ячеек сразу копируется в отображение, поэтому значения
+//! переживают аварийное завершение и восстанавливаются при следующем запуске.
+//!
+//! ФАЙЛОВЫЕ ЗАПИСИ:
+//! Кроме областей, хранилище держит файлы записей для функций 0x14/0x15
+//! (например, таблицы калибровки прошивки). Файл — массив 16-битных записей,
+//! адресуемых номером записи; обращение к неописанному файлу или за его
+//! пределы возвращает IllegalDataAddress.
 
+use memmap2::MmapMut;
 use parking_lot::RwLock;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-use crate::modbus_protocol::ExceptionCode;
-use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+use crate::modbus_protocol::engine::DataModel;
+use crate::modbus_protocol::{
+    pack_bits, ExceptionCode, FileRecordRef, MaskWriteRegisterRequest, ReadFileRecordRequest,
+    WriteFileRecordRequest, MAX_FILE_RECORDS,
+};
+use crate::process_image::ProcessImage;
+use crate::types::{AddressValidation, ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
 
 /// Размер по умолчанию для каждой области данных.
 /// 65536 адресов (0..=65535), чтобы покрыть полный диапазон Modbus.
@@ -24,350 +63,1175 @@ const DEFAULT_DISCRETE_INPUTS_SIZE: usize = 65536;
 const DEFAULT_INPUT_REGISTERS_SIZE: usize = 65536;
 const DEFAULT_HOLDING_REGISTERS_SIZE: usize = 65536;
 
-/// Потокобезопасное хранилище данных Modbus.
+/// Через сколько подготовленных переменных сообщать о прогрессе загрузки.
+const LOAD_PROGRESS_STEP: usize = 5000;
+
+/// Тип ячейки области данных: бит (coils, discrete inputs) или 16-битный регистр.
+trait Cell: Copy + Default {
+    /// Размер ячейки в образе процесса, байт.
+    const IMAGE_BYTES: usize;
+
+    /// Записать значение переменной в ячейки области.
+    fn store(cells: &mut [Self], var: &ModbusVariable);
+    /// Прочитать значение переменной из ячеек области.
+    /// Возвращает None, если переменная выходит за границы области.
+    fn load(cells: &[Self], var: &ModbusVariable) -> Option<ModbusValue>;
+    /// Закодировать ячейку в байты образа процесса.
+    fn encode(self, out: &mut [u8]);
+    /// Декодировать ячейку из байтов образа процесса.
+    fn decode(bytes: &[u8]) -> Self;
+    /// Значение ячейки как 16-битное слово (биты — 0/1).
+    fn to_word(self) -> u16;
+    /// Ячейка из 16-битного слова (для битов — любое ненулевое значение).
+    fn from_word(word: u16) -> Self;
+}
+
+impl Cell for bool {
+    const IMAGE_BYTES: usize = 1;
+
+    fn store(cells: &mut [Self], var: &ModbusVariable) {
+        if let Some(cell) = cells.get_mut(var.address as usize) {
+            *cell = var.value.as_bool();
+        }
+    }
+
+    fn load(cells: &[Self], var: &ModbusVariable) -> Option<ModbusValue> {
+        cells
+            .get(var.address as usize)
+            .map(|&bit| ModbusValue::Bool(bit))
+    }
+
+    fn encode(self, out: &mut [u8]) {
+        out[0] = self as u8;
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+
+    fn to_word(self) -> u16 {
+        self as u16
+    }
+
+    fn from_word(word: u16) -> Self {
+        word != 0
+    }
+}
+
+impl Cell for u16 {
+    const IMAGE_BYTES: usize = 2;
+
+    fn store(cells: &mut [Self], var: &ModbusVariable) {
+        write_register_value(cells, var.address, &var.data_type, &var.value);
+    }
+
+    fn load(cells: &[Self], var: &ModbusVariable) -> Option<ModbusValue> {
+        read_register_value(cells, var.address, &var.data_type)
+    }
+
+    // Big-endian, как и в кадрах Modbus
+    fn encode(self, out: &mut [u8]) {
+        out.copy_from_slice(&self.to_be_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        u16::from_be_bytes([bytes[0], bytes[1]])
+    }
+
+    fn to_word(self) -> u16 {
+        self
+    }
+
+    fn from_word(word: u16) -> Self {
+        word
+    }
+}
+
+/// Шард одной области данных.
 #[derive(Debug)]
-pub struct ModbusDataStore {
-    /// Coils (0x) - массив битов
-    coils: RwLock<Vec<bool>>,
-    /// Discrete Inputs (1x) - массив битов
-    discrete_inputs: RwLock<Vec<bool>>,
-    /// Input Registers (3x) - массив u16
-    input_registers: RwLock<Vec<u16>>,
-    /// Holding Registers (4x) - массив u16
-    holding_registers: RwLock<Vec<u16>>,
-    /// Соответствие ID переменной её определению (для быстрого поиска)
-    variables: RwLock<HashMap<String, ModbusVariable>>,
-
-    // === Множества определённых адресов для строгой проверки ===
-    /// Определённые адреса coils
-    defined_coils: RwLock<HashSet<u16>>,
-    /// Определённые адреса discrete inputs
-    defined_discrete_inputs: RwLock<HashSet<u16>>,
-    /// Определённые адреса holding registers
-    defined_holding_registers: RwLock<HashSet<u16>>,
-    /// Определённые адреса input registers
-    defined_input_registers: RwLock<HashSet<u16>>,
+struct AreaShard<T> {
+    /// Значения ячеек
+    cells: Vec<T>,
+    /// Определённые адреса для строгой проверки
+    defined: Vec<bool>,
+    /// Индекс адрес → ID переменных.
+    /// Нужен, чтобы синхронизация после записи мастером не перебирала все переменные.
+    index: HashMap<u16, Vec<String>>,
+    /// Переменные области по ID
+    variables: HashMap<String, ModbusVariable>,
+    /// Отображение области в файле образа процесса
+    image: Option<MmapMut>,
+    /// Образ восстановлен с диска: при следующей загрузке переменные
+    /// получают значения из ячеек, а не из своих определений.
+    prefer_image: bool,
+    /// Форсированные значения переменных области по ID.
+    forced: HashMap<String, ModbusValue>,
+    /// Строгая проверка адресов.
+    strict: bool,
 }
 
-impl Default for ModbusDataStore {
-    fn default() -> Self {
-        Self::new()
+/// Переменные области, подготовленные к загрузке без блокировок.
+struct PreparedArea {
+    defined: Vec<bool>,
+    index: HashMap<u16, Vec<String>>,
+    variables: HashMap<String, ModbusVariable>,
+}
+
+impl PreparedArea {
+    fn new(size: usize) -> Self {
+        Self {
+            defined: vec![false; size],
+            index: HashMap::new(),
+            variables: HashMap::new(),
+        }
+    }
+
+    /// Отметить адреса переменной и проиндексировать её.
+    fn add(&mut self, var: ModbusVariable) {
+        let start = var.address as usize;
+        let end = (start + var_width(&var)).min(self.defined.len());
+        self.defined[start..end].fill(true);
+        self.index
+            .entry(var.address)
+            .or_default()
+            .push(var.id.clone());
+        self.variables.insert(var.id.clone(), var);
     }
 }
 
-impl ModbusDataStore {
-    /// Создать новое хранилище данных с размерами по умолчанию.
-    pub fn new() -> Self {
+/// Набор переменных всех областей, подготовленный к загрузке.
+struct PreparedVariables {
+    areas: HashMap<String, ModbusArea>,
+    coils: PreparedArea,
+    discrete_inputs: PreparedArea,
+    input_registers: PreparedArea,
+    holding_registers: PreparedArea,
+}
+
+impl PreparedVariables {
+    /// Разложить переменные по областям и построить индексы.
+    /// `progress(подготовлено, всего)` вызывается каждые [`LOAD_PROGRESS_STEP`]
+    /// переменных и в конце.
+    fn build(
+        variables: impl ExactSizeIterator<Item = ModbusVariable>,
+        progress: &dyn Fn(usize, usize),
+    ) -> Self {
+        let total = variables.len();
+        let mut prepared = Self {
+            areas: HashMap::with_capacity(total),
+            coils: PreparedArea::new(DEFAULT_COILS_SIZE),
+            discrete_inputs: PreparedArea::new(DEFAULT_DISCRETE_INPUTS_SIZE),
+            input_registers: PreparedArea::new(DEFAULT_INPUT_REGISTERS_SIZE),
+            holding_registers: PreparedArea::new(DEFAULT_HOLDING_REGISTERS_SIZE),
+        };
+        for (done, var) in variables.enumerate() {
+            if done > 0 && done % LOAD_PROGRESS_STEP == 0 {
+                progress(done, total);
+            }
+            prepared.areas.insert(var.id.clone(), var.area);
+            match var.area {
+                ModbusArea::Coil => prepared.coils.add(var),
+                ModbusArea::DiscreteInput => prepared.discrete_inputs.add(var),
+                ModbusArea::InputRegister => prepared.input_registers.add(var),
+                ModbusArea::HoldingRegister => prepared.holding_registers.add(var),
+            }
+        }
+        progress(total, total);
+        prepared
+    }
+}
+
+impl<T: Cell> AreaShard<T> {
+    fn new(size: usize) -> Self {
         Self {
-            coils: RwLock::new(vec![false; DEFAULT_COILS_SIZE]),
-            discrete_inputs: RwLock::new(vec![false; DEFAULT_DISCRETE_INPUTS_SIZE]),
-            input_registers: RwLock::new(vec![0u16; DEFAULT_INPUT_REGISTERS_SIZE]),
-            holding_registers: RwLock::new(vec![0u16; DEFAULT_HOLDING_REGISTERS_SIZE]),
-            variables: RwLock::new(HashMap::new()),
-            defined_coils: RwLock::new(HashSet::new()),
-            defined_discrete_inputs: RwLock::new(HashSet::new()),
-            defined_holding_registers: RwLock::new(HashSet::new()),
-            defined_input_registers: RwLock::new(HashSet::new()),
+            cells: vec![T::default(); size],
+            defined: vec![false; size],
+            index: HashMap::new(),
+            variables: HashMap::new(),
+            image: None,
+            prefer_image: false,
+            forced: HashMap::new(),
+            strict: true,
         }
     }
 
-    /// Инициализировать хранилище данных из списка переменных.
-    /// Устанавливает начальные значения на основе определений переменных.
-    pub fn load_variables(&self, variables: &[ModbusVariable]) {
-        // Очищаем все данные
-        {
-            let mut vars_map = self.variables.write();
-            vars_map.clear();
+    /// Размер области в образе процесса, байт.
+    fn image_size(&self) -> usize {
+        self.cells.len() * T::IMAGE_BYTES
+    }
+
+    /// Подключить отображение области. Восстановленный образ заменяет значения
+    /// ячеек, новый заполняется текущими значениями.
+    fn attach_image(&mut self, image: MmapMut, restored: bool) {
+        if restored {
+            for (cell, bytes) in self
+                .cells
+                .iter_mut()
+                .zip(image.chunks_exact(T::IMAGE_BYTES))
+            {
+                *cell = T::decode(bytes);
+            }
         }
-        {
-            let mut defined = self.defined_coils.write();
-            defined.clear();
+        self.image = Some(image);
+        self.prefer_image = restored;
+        if !restored {
+            self.persist(0, self.cells.len());
         }
-        {
-            let mut defined = self.defined_discrete_inputs.write();
-            defined.clear();
+    }
+
+    /// Отключить отображение области, сбросив его на диск.
+    fn detach_image(&mut self) {
+        if let Some(image) = self.image.take() {
+            if let Err(e) = image.flush() {
+                log::warn!("Не удалось сохранить образ процесса: {e}");
+            }
         }
-        {
-            let mut defined = self.defined_holding_registers.write();
-            defined.clear();
+        self.prefer_image = false;
+    }
+
+    /// Скопировать ячейки диапазона в образ процесса (если он подключён).
+    fn persist(&mut self, start: usize, end: usize) {
+        let Some(image) = self.image.as_mut() else {
+            return;
+        };
+        let end = end.min(self.cells.len());
+        let start = start.min(end);
+        for (offset, cell) in self.cells[start..end].iter().enumerate() {
+            let at = (start + offset) * T::IMAGE_BYTES;
+            cell.encode(&mut image[at..at + T::IMAGE_BYTES]);
         }
-        {
-            let mut defined = self.defined_input_registers.write();
-            defined.clear();
+    }
+
+    /// Записать значение переменной в ячейки и образ процесса.
+    fn store_variable(&mut self, var: &ModbusVariable) {
+        T::store(&mut self.cells, var);
+        let start = var.address as usize;
+        self.persist(start, start + var_width(var));
+    }
+
+    /// Забыть переменные области (значения ячеек сохраняются).
+    fn forget_variables(&mut self) {
+        self.defined.fill(false);
+        self.index.clear();
+        self.variables.clear();
+    }
+
+    /// Сбросить ячейки к значениям по умолчанию, забыть переменные и форсирование.
+    fn reset(&mut self) {
+        self.cells.fill(T::default());
+        self.persist(0, self.cells.len());
+        self.forget_variables();
+        self.forced.clear();
+    }
+
+    /// Форсировать значение переменной. Возвращает false, если переменной нет.
+    fn force(&mut self, id: &str, value: ModbusValue) -> bool {
+        if !self.variables.contains_key(id) {
+            return false;
         }
+        self.forced.insert(id.to_string(), value);
+        self.reapply_forces(0, self.cells.len());
+        true
+    }
 
-        // Загружаем переменные
-        for var in variables {
-            // Сохраняем переменную
-            {
-                let mut vars_map = self.variables.write();
-                vars_map.insert(var.id.clone(), var.clone());
+    /// Снять форсирование переменных, которых больше нет в области.
+    fn drop_stale_forces(&mut self) {
+        let variables = &self.variables;
+        self.forced.retain(|id, _| variables.contains_key(id));
+    }
+
+    /// Восстановить форсированные значения переменных, задевающих диапазон ячеек.
+    fn reapply_forces(&mut self, start: usize, end: usize) {
+        let forced: Vec<ModbusVariable> = self
+            .forced
+            .iter()
+            .filter_map(|(id, value)| {
+                let var = self.variables.get(id)?;
+                let first = var.address as usize;
+                (first < end && first + var_width(var) > start).then(|| ModbusVariable {
+                    value: value.clone(),
+                    ..var.clone()
+                })
+            })
+            .collect();
+        for var in forced {
+            self.store_variable(&var);
+            self.variables.insert(var.id.clone(), var);
+        }
+    }
+
+    /// Сбросить ячейки диапазона к значениям по умолчанию
+    /// (диапазон обрезается по границе области).
+    fn clear_cells(&mut self, start: usize, end: usize) {
+        let end = end.min(self.cells.len());
+        let start = start.min(end);
+        self.cells[start..end].fill(T::default());
+        self.persist(start, end);
+    }
+
+    /// Сбросить все ячейки, сохранив переменные: их значения перечитываются из ячеек.
+    fn clear_values(&mut self) -> usize {
+        self.clear_cells(0, self.cells.len());
+        self.reapply_forces(0, self.cells.len());
+        let Self {
+            cells, variables, ..
+        } = self;
+        for var in variables.values_mut() {
+            if let Some(value) = T::load(cells, var) {
+                var.value = value;
+            }
+        }
+        variables.len()
+    }
+
+    /// Заменить переменные области подготовленными и записать их значения.
+    /// Форсированные переменные получают форсированное значение, а после
+    /// восстановления образа значение, наоборот, берётся из ячеек.
+    fn install(&mut self, prepared: PreparedArea) {
+        self.defined = prepared.defined;
+        self.index = prepared.index;
+        let mut variables = prepared.variables;
+        for var in variables.values_mut() {
+            if let Some(value) = self.forced.get(&var.id) {
+                var.value = value.clone();
+                self.store_variable(var);
+            } else {
+                match T::load(&self.cells, var).filter(|_| self.prefer_image) {
+                    Some(value) => var.value = value,
+                    None => self.store_variable(var),
+                }
             }
+        }
+        self.variables = variables;
+    }
 
-            // Отмечаем адреса как определённые
-            self.mark_addresses_defined(var);
+    /// Обновить значение переменной и её ячейки.
+    /// Значение форсированной переменной отбрасывается.
+    fn update_variable(&mut self, id: &str, value: ModbusValue) -> bool {
+        if self.forced.contains_key(id) {
+            return self.variables.contains_key(id);
+        }
+        let Some(var) = self.variables.get_mut(id) else {
+            return false;
+        };
+        var.value = value;
+        let var = var.clone();
+        self.store_variable(&var);
+        true
+    }
 
-            // Записываем значение
-            self.write_variable_value(var);
+    /// Проверить, что все адреса в диапазоне определены. Без строгой
+    /// проверки достаточно, чтобы диапазон помещался в область.
+    fn check_defined(&self, start: u16, count: usize) -> Result<(), ExceptionCode> {
+        let start = start as usize;
+        let end = start + count;
+        if end > self.defined.len() || (self.strict && !self.defined[start..end].iter().all(|&d| d))
+        {
+            return Err(ExceptionCode::IllegalDataAddress);
         }
+        Ok(())
     }
 
-    /// Отметить адреса переменной как определённые.
-    /// Для типов uint32 и float32 отмечаем 2 регистра.
-    fn mark_addresses_defined(&self, var: &ModbusVariable) {
-        let register_count = match var.data_type {
-            ModbusDataType::Uint32 | ModbusDataType::Float32 => 2,
-            _ => 1,
+    /// Прочитать диапазон ячеек.
+    /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
+    fn read(&self, start: u16, count: u16) -> Result<Vec<T>, ExceptionCode> {
+        self.check_defined(start, count as usize)?;
+        let start = start as usize;
+        Ok(self.cells[start..start + count as usize].to_vec())
+    }
+
+    /// Записать диапазон ячеек.
+    /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
+    fn write(&mut self, start: u16, values: &[T]) -> Result<(), ExceptionCode> {
+        self.check_defined(start, values.len())?;
+        let start = start as usize;
+        self.cells[start..start + values.len()].copy_from_slice(values);
+        self.persist(start, start + values.len());
+        self.reapply_forces(start, start + values.len());
+        Ok(())
+    }
+
+    /// Прочитать ячейки без строгой проверки адресов (для дампов памяти).
+    /// Диапазон обрезается по границе области.
+    fn dump(&self, start: u16, count: usize) -> Vec<u16> {
+        let start = start as usize;
+        let end = (start + count).min(self.cells.len());
+        self.cells[start..end].iter().map(|c| c.to_word()).collect()
+    }
+
+    /// Упакованные значения и маска описанных адресов диапазона
+    /// (обрезается по границе области).
+    fn bitmap(&self, start: u16, count: usize) -> (Vec<u8>, Vec<u8>) {
+        let start = start as usize;
+        let end = (start + count).min(self.cells.len());
+        let values: Vec<bool> = self.cells[start..end]
+            .iter()
+            .map(|c| c.to_word() != 0)
+            .collect();
+        (pack_bits(&values), pack_bits(&self.defined[start..end]))
+    }
+
+    /// Записать ячейки без строгой проверки адресов и синхронизировать переменные.
+    /// Возвращает количество записанных ячеек (диапазон обрезается по границе области).
+    fn restore(&mut self, start: u16, words: &[u16]) -> usize {
+        let begin = start as usize;
+        let end = (begin + words.len()).min(self.cells.len());
+        for (cell, &word) in self.cells[begin..end].iter_mut().zip(words) {
+            *cell = T::from_word(word);
+        }
+        self.persist(begin, end);
+        self.reapply_forces(begin, end);
+        for address in begin..end {
+            self.sync_from_cells(address as u16);
+        }
+        end - begin
+    }
+
+    /// Записать ячейки без переменной и открыть адреса для чтения мастером
+    /// (до следующей загрузки переменных).
+    fn set_raw(&mut self, start: u16, words: &[u16]) {
+        let written = self.restore(start, words);
+        let begin = start as usize;
+        self.defined[begin..begin + written].fill(true);
+    }
+
+    /// Синхронизировать переменные когда ячейка записана мастером.
+    fn sync_from_cells(&mut self, address: u16) {
+        let Some(ids) = self.index.get(&address) else {
+            return;
         };
+        for id in ids {
+            let Some(var) = self.variables.get_mut(id) else {
+                continue;
+            };
+            if let Some(value) = T::load(&self.cells, var) {
+                var.value = value;
+            }
+        }
+    }
+}
+
+/// Сколько ячеек занимает переменная.
+/// Для типов uint32 и float32 в регистровых областях — 2 регистра.
+fn var_width(var: &ModbusVariable) -> usize {
+    match (var.area, var.data_type) {
+        (ModbusArea::Coil | ModbusArea::DiscreteInput, _) => 1,
+        (_, ModbusDataType::Uint32 | ModbusDataType::Float32) => 2,
+        _ => 1,
+    }
+}
 
-        match var.area {
-            ModbusArea::Coil => {
-                let mut defined = self.defined_coils.write();
-                defined.insert(var.address);
+/// Записать значение в массив регистров в зависимости от типа данных.
+pub fn write_register_value(
+    regs: &mut [u16],
+    address: u16,
+    data_type: &ModbusDataType,
+    value: &ModbusValue,
+) {
+    let addr = address as usize;
+
+    match data_type {
+        ModbusDataType::Bool => {
+            if addr < regs.len() {
+                regs[addr] = if value.as_bool() { 1 } else { 0 };
             }
-            ModbusArea::DiscreteInput => {
-                let mut defined = self.defined_discrete_inputs.write();
-                defined.insert(var.address);
+        }
+        ModbusDataType::Uint16 => {
+            if addr < regs.len() {
+                regs[addr] = value.as_u16();
             }
-            ModbusArea::HoldingRegister => {
-                let mut defined = self.defined_holding_registers.write();
-                for i in 0..register_count {
-                    defined.insert(var.address + i);
-                }
+        }
+        ModbusDataType::Int16 => {
+            if addr < regs.len() {
+                regs[addr] = value.as_i16() as u16;
             }
-            ModbusArea::InputRegister => {
-                let mut defined = self.defined_input_registers.write();
-                for i in 0..register_count {
-                    defined.insert(var.address + i);
-                }
+        }
+        ModbusDataType::Uint32 => {
+            let val = value.as_u32();
+            if addr + 1 < regs.len() {
+                // Big-endian: старшее слово первым
+                regs[addr] = (val >> 16) as u16;
+                regs[addr + 1] = (val & 0xFFFF) as u16;
+            }
+        }
+        ModbusDataType::Float32 => {
+            let val = value.as_f32();
+            let bits = val.to_bits();
+            if addr + 1 < regs.len() {
+                // Big-endian: старшее слово первым
+                regs[addr] = (bits >> 16) as u16;
+                regs[addr + 1] = (bits & 0xFFFF) as u16;
             }
         }
     }
+}
 
-    /// Проверить, что все адреса в диапазоне определены.
-    fn check_addresses_defined(
-        &self,
-        defined_set: &HashSet<u16>,
-        start: u16,
-        count: u16,
-    ) -> Result<(), ExceptionCode> {
-        for addr in start..(start + count) {
-            if !defined_set.contains(&addr) {
-                return Err(ExceptionCode::IllegalDataAddress);
-            }
+/// Прочитать значение переменной из массива регистров.
+/// Возвращает None, если переменная выходит за границы области.
+pub fn read_register_value(
+    regs: &[u16],
+    address: u16,
+    data_type: &ModbusDataType,
+) -> Option<ModbusValue> {
+    let addr = address as usize;
+    let word = |i: usize| regs.get(addr + i).copied();
+
+    let value = match data_type {
+        ModbusDataType::Bool => ModbusValue::Bool(word(0)? != 0),
+        ModbusDataType::Uint16 => ModbusValue::Number(word(0)? as f64),
+        ModbusDataType::Int16 => ModbusValue::Number(word(0)? as i16 as f64),
+        ModbusDataType::Uint32 => {
+            let val = ((word(0)? as u32) << 16) | (word(1)? as u32);
+            ModbusValue::Number(val as f64)
+        }
+        ModbusDataType::Float32 => {
+            let bits = ((word(0)? as u32) << 16) | (word(1)? as u32);
+            ModbusValue::Number(f32::from_bits(bits) as f64)
+        }
+    };
+    Some(value)
+}
+
+/// Запись таблицы форсирования.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForcedVariable {
+    pub id: String,
+    pub value: ModbusValue,
+}
+
+/// Диапазон coils или discrete inputs в виде битовых карт для сеточного
+/// отображения: биты упакованы как в ответе Modbus (младший бит первого
+/// байта — адрес `start`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BitBank {
+    pub area: ModbusArea,
+    /// Начало диапазона (команды возвращают его в соглашении проекта).
+    pub start: u32,
+    /// Число адресов (диапазон обрезается по границе области).
+    pub count: usize,
+    /// Значения ячеек.
+    pub values: Vec<u8>,
+    /// Адреса, описанные переменными или открытые записью входов.
+    pub defined: Vec<u8>,
+}
+
+/// Файл записей для функций 0x14/0x15.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordFile {
+    /// Номер файла (1..=65535).
+    pub file_number: u16,
+    /// Записи файла по порядку номеров, начиная с 0.
+    pub records: Vec<u16>,
+}
+
+impl RecordFile {
+    /// Проверить номер файла и число записей.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.file_number == 0 {
+            return Err("Номер файла должен быть от 1 до 65535".to_string());
+        }
+        if self.records.is_empty() || self.records.len() > MAX_FILE_RECORDS {
+            return Err(format!(
+                "Файл {} должен содержать от 1 до {} записей",
+                self.file_number, MAX_FILE_RECORDS
+            ));
         }
         Ok(())
     }
+}
 
-    /// Записать значение одной переменной в соответствующую область данных.
-    fn write_variable_value(&self, var: &ModbusVariable) {
-        match var.area {
-            ModbusArea::Coil => {
-                let value = var.value.as_bool();
-                let mut coils = self.coils.write();
-                if (var.address as usize) < coils.len() {
-                    coils[var.address as usize] = value;
-                }
-            }
-            ModbusArea::DiscreteInput => {
-                let value = var.value.as_bool();
-                let mut inputs = self.discrete_inputs.write();
-                if (var.address as usize) < inputs.len() {
-                    inputs[var.address as usize] = value;
-                }
-            }
-            ModbusArea::InputRegister => {
-                self.write_register_value(
-                    &self.input_registers,
-                    var.address,
-                    &var.data_type,
-                    &var.value,
-                );
+/// Диапазон записей файла, к которому обращается подзапрос.
+fn record_range(
+    files: &BTreeMap<u16, Vec<u16>>,
+    reference: &FileRecordRef,
+) -> Result<Range<usize>, ExceptionCode> {
+    let records = files
+        .get(&reference.file_number)
+        .ok_or(ExceptionCode::IllegalDataAddress)?;
+    let start = reference.record_number as usize;
+    let end = start + reference.record_length as usize;
+    if end > records.len() {
+        return Err(ExceptionCode::IllegalDataAddress);
+    }
+    Ok(start..end)
+}
+
+/// Область выборочной очистки хранилища.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "camelCase")]
+pub enum ClearScope {
+    /// Вся область: переменные удаляются, ячейки сбрасываются.
+    Area { area: ModbusArea },
+    /// Группа переменных по ID: переменные удаляются, их ячейки сбрасываются.
+    Group { ids: Vec<String> },
+    /// Диапазон адресов области (включительно): удаляются переменные,
+    /// задевающие диапазон, ячейки диапазона сбрасываются.
+    Range {
+        area: ModbusArea,
+        start: u16,
+        end: u16,
+    },
+    /// Только значения: все ячейки сбрасываются, определения переменных сохраняются.
+    ValuesOnly,
+}
+
+impl ClearScope {
+    /// Попадает ли переменная под удаление.
+    pub fn matches(&self, var: &ModbusVariable) -> bool {
+        match self {
+            ClearScope::Area { area } => var.area == *area,
+            ClearScope::Group { ids } => ids.contains(&var.id),
+            ClearScope::Range { area, start, end } => {
+                let first = var.address as usize;
+                let last = first + var_width(var) - 1;
+                var.area == *area && first <= *end as usize && last >= *start as usize
             }
-            ModbusArea::HoldingRegister => {
-                self.write_register_value(
-                    &self.holding_registers,
-                    var.address,
-                    &var.data_type,
-                    &var.value,
-                );
+            ClearScope::ValuesOnly => false,
+        }
+    }
+}
+
+/// Потокобезопасное хранилище данных Modbus.
+#[derive(Debug)]
+pub struct ModbusDataStore {
+    /// Coils (0x) - биты
+    coils: RwLock<AreaShard<bool>>,
+    /// Discrete Inputs (1x) - биты
+    discrete_inputs: RwLock<AreaShard<bool>>,
+    /// Input Registers (3x) - u16
+    input_registers: RwLock<AreaShard<u16>>,
+    /// Holding Registers (4x) - u16
+    holding_registers: RwLock<AreaShard<u16>>,
+    /// Область каждой переменной по ID — чтобы найти нужный шард.
+    /// При одновременном захвате берётся раньше шардов.
+    variable_areas: RwLock<HashMap<String, ModbusArea>>,
+    /// Путь к подключённому файлу образа процесса
+    image_path: RwLock<Option<PathBuf>>,
+    /// Файлы записей (функции 0x14/0x15) по номеру файла.
+    record_files: RwLock<BTreeMap<u16, Vec<u16>>>,
+    /// Переменные, которые симуляция не обновляет.
+    simulation_paused: RwLock<BTreeSet<String>>,
+    /// Номер набора определений: растёт при каждой загрузке или очистке
+    /// переменных (но не при записи значений).
+    generation: AtomicU64,
+}
+
+impl Default for ModbusDataStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModbusDataStore {
+    /// Создать новое хранилище данных с размерами по умолчанию.
+    pub fn new() -> Self {
+        Self {
+            coils: RwLock::new(AreaShard::new(DEFAULT_COILS_SIZE)),
+            discrete_inputs: RwLock::new(AreaShard::new(DEFAULT_DISCRETE_INPUTS_SIZE)),
+            input_registers: RwLock::new(AreaShard::new(DEFAULT_INPUT_REGISTERS_SIZE)),
+            holding_registers: RwLock::new(AreaShard::new(DEFAULT_HOLDING_REGISTERS_SIZE)),
+            variable_areas: RwLock::new(HashMap::new()),
+            image_path: RwLock::new(None),
+            record_files: RwLock::new(BTreeMap::new()),
+            simulation_paused: RwLock::new(BTreeSet::new()),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Номер текущего набора определений переменных.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Подключить области данных к файлу образа процесса.
+    /// Если файл уже содержит образ, значения ячеек восстанавливаются из него,
+    /// и следующая загрузка переменных берёт значения из образа.
+    /// Повторное подключение того же файла ничего не делает.
+    pub fn attach_image(&self, path: &Path) -> Result<(), String> {
+        let mut image_path = self.image_path.write();
+        if image_path.as_deref() == Some(path) {
+            return Ok(());
+        }
+
+        let mut coils = self.coils.write();
+        let mut discrete_inputs = self.discrete_inputs.write();
+        let mut input_registers = self.input_registers.write();
+        let mut holding_registers = self.holding_registers.write();
+
+        let image = ProcessImage::open(
+            path,
+            &[
+                coils.image_size(),
+                discrete_inputs.image_size(),
+                input_registers.image_size(),
+                holding_registers.image_size(),
+            ],
+        )?;
+        let restored = image.restored;
+        let mut regions = image.regions.into_iter();
+        let mut next = || regions.next().expect("область образа процесса");
+        coils.attach_image(next(), restored);
+        discrete_inputs.attach_image(next(), restored);
+        input_registers.attach_image(next(), restored);
+        holding_registers.attach_image(next(), restored);
+
+        log::info!(
+            "Образ процесса {} {}",
+            path.display(),
+            if restored {
+                "восстановлен"
+            } else {
+                "создан"
             }
+        );
+        *image_path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Отключить файл образа процесса (значения остаются в памяти).
+    pub fn detach_image(&self) {
+        let mut image_path = self.image_path.write();
+        if image_path.take().is_none() {
+            return;
         }
+        self.coils.write().detach_image();
+        self.discrete_inputs.write().detach_image();
+        self.input_registers.write().detach_image();
+        self.holding_registers.write().detach_image();
+    }
+
+    /// Инициализировать хранилище данных из списка переменных.
+    /// Устанавливает начальные значения на основе определений переменных:
+    /// `initial_value`, если задано, иначе `value`.
+    pub fn load_variables(&self, variables: &[ModbusVariable]) {
+        self.load_variables_with_progress(variables, |_, _| {});
     }
 
-    /// Записать значение в массив регистров в зависимости от типа данных.
-    fn write_register_value(
+    /// То же, что [`Self::load_variables`], с сообщениями о прогрессе
+    /// `progress(подготовлено, всего)` для больших проектов.
+    pub fn load_variables_with_progress(
         &self,
-        registers: &RwLock<Vec<u16>>,
-        address: u16,
-        data_type: &ModbusDataType,
-        value: &ModbusValue,
+        variables: &[ModbusVariable],
+        progress: impl Fn(usize, usize),
     ) {
-        let mut regs = registers.write();
-        let addr = address as usize;
-
-        match data_type {
-            ModbusDataType::Bool => {
-                if addr < regs.len() {
-                    regs[addr] = if value.as_bool() { 1 } else { 0 };
-                }
+        let variables = variables.iter().map(|var| {
+            let mut var = var.clone();
+            if let Some(initial) = &var.initial_value {
+                var.value = initial.clone();
             }
-            ModbusDataType::Uint16 => {
-                if addr < regs.len() {
-                    regs[addr] = value.as_u16();
-                }
-            }
-            ModbusDataType::Int16 => {
-                if addr < regs.len() {
-                    regs[addr] = value.as_i16() as u16;
+            var
+        });
+        self.install(PreparedVariables::build(variables, &progress));
+    }
+
+    /// Загрузить определения переменных со значениями как есть.
+    pub fn load_definitions(&self, variables: &[ModbusVariable]) {
+        self.install(PreparedVariables::build(
+            variables.iter().cloned(),
+            &|_, _| {},
+        ));
+    }
+
+    /// Подменить переменные подготовленным набором. Клонирование и построение
+    /// индексов уже выполнены без блокировок; под ними остаётся только запись
+    /// значений в ячейки.
+    fn install(&self, prepared: PreparedVariables) {
+        // Захватываем все шарды, чтобы читатели не увидели частично загруженный набор
+        let mut areas = self.variable_areas.write();
+        let mut coils = self.coils.write();
+        let mut discrete_inputs = self.discrete_inputs.write();
+        let mut input_registers = self.input_registers.write();
+        let mut holding_registers = self.holding_registers.write();
+
+        *areas = prepared.areas;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        coils.install(prepared.coils);
+        discrete_inputs.install(prepared.discrete_inputs);
+        input_registers.install(prepared.input_registers);
+        holding_registers.install(prepared.holding_registers);
+
+        // Значения из восстановленного образа используются только для первой загрузки
+        coils.prefer_image = false;
+        discrete_inputs.prefer_image = false;
+        input_registers.prefer_image = false;
+        holding_registers.prefer_image = false;
+
+        coils.drop_stale_forces();
+        discrete_inputs.drop_stale_forces();
+        input_registers.drop_stale_forces();
+        holding_registers.drop_stale_forces();
+    }
+
+    /// Слить новые определения переменных с текущими.
+    /// Для переменных с тем же ID, областью, адресом и типом сохраняется текущее
+    /// runtime-значение, остальные получают начальное значение из нового определения.
+    /// Возвращает итоговый список загруженных переменных.
+    pub fn merge_variables(&self, variables: &[ModbusVariable]) -> Vec<ModbusVariable> {
+        let merged: Vec<ModbusVariable> = variables
+            .iter()
+            .map(|var| {
+                let mut var = var.clone();
+                if let Some(existing) = self.get_variable(&var.id) {
+                    if existing.area == var.area
+                        && existing.address == var.address
+                        && existing.data_type == var.data_type
+                    {
+                        var.value = existing.value;
+                        return var;
+                    }
                 }
-            }
-            ModbusDataType::Uint32 => {
-                let val = value.as_u32();
-                if addr + 1 < regs.len() {
-                    // Big-endian: старшее слово первым
-                    regs[addr] = (val >> 16) as u16;
-                    regs[addr + 1] = (val & 0xFFFF) as u16;
+                if let Some(initial) = &var.initial_value {
+                    var.value = initial.clone();
                 }
+                var
+            })
+            .collect();
+
+        self.load_definitions(&merged);
+        merged
+    }
+
+    /// Восстановить значения сброса (`reset_value`, иначе `initial_value`)
+    /// без перезагрузки определений. Переменные без них не меняются.
+    /// Возвращает число сброшенных переменных.
+    pub fn reset_values(&self) -> usize {
+        self.get_variables()
+            .into_iter()
+            .filter(|var| {
+                var.reset_value
+                    .clone()
+                    .or_else(|| var.initial_value.clone())
+                    .is_some_and(|value| self.update_variable(&var.id, value))
+            })
+            .count()
+    }
+
+    /// Обновить значение переменной по её ID.
+    /// Возвращает true, если переменная найдена и обновлена.
+    pub fn update_variable(&self, id: &str, value: ModbusValue) -> bool {
+        let Some(area) = self.variable_areas.read().get(id).copied() else {
+            return false;
+        };
+        match area {
+            ModbusArea::Coil => self.coils.write().update_variable(id, value),
+            ModbusArea::DiscreteInput => self.discrete_inputs.write().update_variable(id, value),
+            ModbusArea::InputRegister => self.input_registers.write().update_variable(id, value),
+            ModbusArea::HoldingRegister => {
+                self.holding_registers.write().update_variable(id, value)
             }
-            ModbusDataType::Float32 => {
-                let val = value.as_f32();
-                let bits = val.to_bits();
-                if addr + 1 < regs.len() {
-                    // Big-endian: старшее слово первым
-                    regs[addr] = (bits >> 16) as u16;
-                    regs[addr + 1] = (bits & 0xFFFF) as u16;
-                }
+        }
+    }
+
+    /// Форсировать значение переменной. Возвращает false, если переменная не найдена.
+    pub fn force_variable(&self, id: &str, value: ModbusValue) -> bool {
+        let Some(area) = self.variable_areas.read().get(id).copied() else {
+            return false;
+        };
+        match area {
+            ModbusArea::Coil => self.coils.write().force(id, value),
+            ModbusArea::DiscreteInput => self.discrete_inputs.write().force(id, value),
+            ModbusArea::InputRegister => self.input_registers.write().force(id, value),
+            ModbusArea::HoldingRegister => self.holding_registers.write().force(id, value),
+        }
+    }
+
+    /// Снять форсирование переменной (закреплённое значение остаётся, пока его
+    /// не перезапишут). Возвращает false, если переменная не была форсирована.
+    pub fn unforce_variable(&self, id: &str) -> bool {
+        let Some(area) = self.variable_areas.read().get(id).copied() else {
+            return false;
+        };
+        match area {
+            ModbusArea::Coil => self.coils.write().forced.remove(id),
+            ModbusArea::DiscreteInput => self.discrete_inputs.write().forced.remove(id),
+            ModbusArea::InputRegister => self.input_registers.write().forced.remove(id),
+            ModbusArea::HoldingRegister => self.holding_registers.write().forced.remove(id),
+        }
+        .is_some()
+    }
+
+    /// Таблица форсирования, упорядоченная по ID.
+    pub fn forced_variables(&self) -> Vec<ForcedVariable> {
+        let mut forced: Vec<ForcedVariable> = [
+            self.coils.read().forced.clone(),
+            self.discrete_inputs.read().forced.clone(),
+            self.input_registers.read().forced.clone(),
+            self.holding_registers.read().forced.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|(id, value)| ForcedVariable { id, value })
+        .collect();
+        forced.sort_by(|a, b| a.id.cmp(&b.id));
+        forced
+    }
+
+    /// Снять всё форсирование. Возвращает число снятых форсирований.
+    pub fn clear_forces(&self) -> usize {
+        [
+            self.coils.write().forced.drain().count(),
+            self.discrete_inputs.write().forced.drain().count(),
+            self.input_registers.write().forced.drain().count(),
+            self.holding_registers.write().forced.drain().count(),
+        ]
+        .iter()
+        .sum()
+    }
+
+    /// Обновить значение от симуляции (поведения, генераторы, сценарии).
+    /// Значение переменной на паузе отбрасывается.
+    /// Возвращает true, если переменная найдена.
+    pub fn update_simulated(&self, id: &str, value: ModbusValue) -> bool {
+        if self.is_simulation_paused(id) {
+            return self.variable_areas.read().contains_key(id);
+        }
+        self.update_variable(id, value)
+    }
+
+    /// Приостановить обновление переменных симуляцией. Ошибка, если
+    /// какой-либо переменной нет (тогда ни одна не приостанавливается).
+    pub fn pause_simulation(&self, ids: &[String]) -> Result<(), String> {
+        let areas = self.variable_areas.read();
+        if let Some(missing) = ids.iter().find(|id| !areas.contains_key(*id)) {
+            return Err(format!("Переменная '{}' не найдена", missing));
+        }
+        self.simulation_paused.write().extend(ids.iter().cloned());
+        Ok(())
+    }
+
+    /// Возобновить обновление переменных симуляцией. Возвращает число
+    /// снятых с паузы.
+    pub fn resume_simulation(&self, ids: &[String]) -> usize {
+        let mut paused = self.simulation_paused.write();
+        ids.iter().filter(|id| paused.remove(*id)).count()
+    }
+
+    /// Стоит ли переменная на паузе симуляции.
+    pub fn is_simulation_paused(&self, id: &str) -> bool {
+        self.simulation_paused.read().contains(id)
+    }
+
+    /// Переменные на паузе симуляции.
+    pub fn simulation_paused(&self) -> Vec<String> {
+        self.simulation_paused.read().iter().cloned().collect()
+    }
+
+    /// Получить копию переменной по ID.
+    pub fn get_variable(&self, id: &str) -> Option<ModbusVariable> {
+        let area = self.variable_areas.read().get(id).copied()?;
+        match area {
+            ModbusArea::Coil => self.coils.read().variables.get(id).cloned(),
+            ModbusArea::DiscreteInput => self.discrete_inputs.read().variables.get(id).cloned(),
+            ModbusArea::InputRegister => self.input_registers.read().variables.get(id).cloned(),
+            ModbusArea::HoldingRegister => self.holding_registers.read().variables.get(id).cloned(),
+        }
+    }
+
+    /// Обойти переменные всех областей согласованным снимком.
+    fn for_each_variable(&self, f: impl FnMut(&ModbusVariable)) {
+        let coils = self.coils.read();
+        let discrete_inputs = self.discrete_inputs.read();
+        let input_registers = self.input_registers.read();
+        let holding_registers = self.holding_registers.read();
+        coils
+            .variables
+            .values()
+            .chain(discrete_inputs.variables.values())
+            .chain(input_registers.variables.values())
+            .chain(holding_registers.variables.values())
+            .for_each(f);
+    }
+
+    /// Получить все текущие переменные с их значениями.
+    pub fn get_variables(&self) -> Vec<ModbusVariable> {
+        let mut vars = Vec::new();
+        self.for_each_variable(|var| vars.push(var.clone()));
+        vars
+    }
+
+    /// Получить текущие значения указанных переменных.
+    /// Неизвестные ID пропускаются.
+    pub fn get_variable_values(&self, ids: &[String]) -> Vec<(String, ModbusValue)> {
+        ids.iter()
+            .filter_map(|id| self.get_variable(id).map(|v| (id.clone(), v.value)))
+            .collect()
+    }
+
+    /// Снимок числовых значений всех переменных для вычисления выражений.
+    /// Ключи — имена и ID переменных (при совпадении приоритет у ID).
+    pub fn numeric_snapshot(&self) -> HashMap<String, f64> {
+        let mut by_name = HashMap::new();
+        let mut by_id = HashMap::new();
+        self.for_each_variable(|var| {
+            by_name.insert(var.name.clone(), var.value.as_f64());
+            by_id.insert(var.id.clone(), var.value.as_f64());
+        });
+        by_name.extend(by_id);
+        by_name
+    }
+
+    /// Установить логический флаг в переменную: `Bool` для битовых переменных,
+    /// 1/0 для регистров. Флаги ставит симуляция, поэтому переменная на
+    /// паузе не меняется. Возвращает false, если переменная не найдена.
+    pub fn set_flag(&self, id: &str, on: bool) -> bool {
+        let value = match self.get_variable(id) {
+            Some(var) if var.data_type == ModbusDataType::Bool => ModbusValue::Bool(on),
+            Some(_) => ModbusValue::Number(if on { 1.0 } else { 0.0 }),
+            None => return false,
+        };
+        self.update_simulated(id, value)
+    }
+
+    /// Прочитать диапазон области без строгой проверки адресов (для дампов памяти).
+    /// Биты возвращаются как 0/1.
+    pub fn dump_area(&self, area: ModbusArea, start: u16, count: usize) -> Vec<u16> {
+        match area {
+            ModbusArea::Coil => self.coils.read().dump(start, count),
+            ModbusArea::DiscreteInput => self.discrete_inputs.read().dump(start, count),
+            ModbusArea::InputRegister => self.input_registers.read().dump(start, count),
+            ModbusArea::HoldingRegister => self.holding_registers.read().dump(start, count),
+        }
+    }
+
+    /// Загрузить ячейки в область без строгой проверки адресов, обновив значения
+    /// переменных. Возвращает количество записанных ячеек.
+    pub fn restore_area(&self, area: ModbusArea, start: u16, words: &[u16]) -> usize {
+        match area {
+            ModbusArea::Coil => self.coils.write().restore(start, words),
+            ModbusArea::DiscreteInput => self.discrete_inputs.write().restore(start, words),
+            ModbusArea::InputRegister => self.input_registers.write().restore(start, words),
+            ModbusArea::HoldingRegister => self.holding_registers.write().restore(start, words),
+        }
+    }
+
+    /// Битовые карты диапазона coils или discrete inputs.
+    pub fn bit_bank(&self, area: ModbusArea, start: u16, count: usize) -> Result<BitBank, String> {
+        let (values, defined) = match area {
+            ModbusArea::Coil => self.coils.read().bitmap(start, count),
+            ModbusArea::DiscreteInput => self.discrete_inputs.read().bitmap(start, count),
+            _ => {
+                return Err(format!(
+                    "Битовые карты строятся только для coils и discrete inputs, не для {:?}",
+                    area
+                ))
             }
+        };
+        Ok(BitBank {
+            area,
+            start: start as u32,
+            count: count.min(u16::MAX as usize + 1 - start as usize),
+            values,
+            defined,
+        })
+    }
+
+    /// Режим проверки адресов запросов мастера.
+    pub fn address_validation(&self) -> AddressValidation {
+        if self.coils.read().strict {
+            AddressValidation::Strict
+        } else {
+            AddressValidation::Permissive
+        }
+    }
+
+    /// Переключить проверку адресов. Действует со следующего запроса,
+    /// переменные не перезагружаются.
+    pub fn set_address_validation(&self, mode: AddressValidation) {
+        let strict = mode == AddressValidation::Strict;
+        self.coils.write().strict = strict;
+        self.discrete_inputs.write().strict = strict;
+        self.input_registers.write().strict = strict;
+        self.holding_registers.write().strict = strict;
+    }
+
+    /// Описан ли адрес области переменной.
+    pub fn is_defined(&self, area: ModbusArea, address: u16) -> bool {
+        let address = address as usize;
+        match area {
+            ModbusArea::Coil => self.coils.read().defined[address],
+            ModbusArea::DiscreteInput => self.discrete_inputs.read().defined[address],
+            ModbusArea::InputRegister => self.input_registers.read().defined[address],
+            ModbusArea::HoldingRegister => self.holding_registers.read().defined[address],
         }
     }
 
-    /// Обновить значение переменной по её ID.
-    /// Возвращает true, если переменная найдена и обновлена.
-    pub fn update_variable(&self, id: &str, value: ModbusValue) -> bool {
-        let mut vars = self.variables.write();
-        if let Some(var) = vars.get_mut(id) {
-            var.value = value.clone();
-            let var_clone = var.clone();
-            drop(vars); // Освобождаем блокировку перед записью в регистры
-            self.write_variable_value(&var_clone);
-            true
-        } else {
-            false
+    /// Записать сырое значение во входную область (discrete inputs, input registers)
+    /// по адресу, без переменной. Для discrete inputs ненулевое значение — 1.
+    /// Мастер не может писать в эти области, поэтому это канал для скриптов и внешних данных.
+    pub fn set_input(&self, area: ModbusArea, address: u16, raw_value: u16) -> Result<(), String> {
+        match area {
+            ModbusArea::DiscreteInput => self
+                .discrete_inputs
+                .write()
+                .set_raw(address, &[(raw_value != 0) as u16]),
+            ModbusArea::InputRegister => self.input_registers.write().set_raw(address, &[raw_value]),
+            _ => {
+                return Err(format!(
+                    "Область {:?} записывается мастером; set_input только для discrete inputs и input registers",
+                    area
+                ))
+            }
         }
+        Ok(())
     }
 
-    /// Получить все текущие переменные с их значениями.
-    pub fn get_variables(&self) -> Vec<ModbusVariable> {
-        self.variables.read().values().cloned().collect()
+    /// Записать несколько input registers подряд одной операцией, чтобы мастер
+    /// не прочитал многословное значение наполовину обновлённым.
+    pub fn set_input_registers(&self, start: u16, words: &[u16]) {
+        self.input_registers.write().set_raw(start, words);
+    }
+
+    /// Записать несколько holding registers подряд одной операцией (служебные
+    /// окна устройства, которые мастер может и читать, и записывать).
+    pub fn set_holding_registers(&self, start: u16, words: &[u16]) {
+        self.holding_registers.write().set_raw(start, words);
     }
 
     // ========== Coils (0x) ==========
 
+    /// Установить служебный coil: переменная по адресу синхронизируется,
+    /// адрес без переменной становится доступен мастеру.
+    pub fn set_coil(&self, address: u16, value: bool) {
+        let mut coils = self.coils.write();
+        coils.set_raw(address, &[value as u16]);
+        coils.sync_from_cells(address);
+    }
+
     /// Читать coils начиная с адреса.
     /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
     pub fn read_coils(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode> {
-        // Проверяем, что все адреса определены
-        {
-            let defined = self.defined_coils.read();
-            self.check_addresses_defined(&defined, start, count)?;
-        }
-
-        let coils = self.coils.read();
-        let start_idx = start as usize;
-        let end_idx = start_idx + count as usize;
-
-        if end_idx > coils.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
-        }
-
-        Ok(coils[start_idx..end_idx].to_vec())
+        self.coils.read().read(start, count)
     }
 
     /// Записать один coil.
     /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
     pub fn write_single_coil(&self, address: u16, value: bool) -> Result<(), ExceptionCode> {
-        // Проверяем, что адрес определён
-        {
-            let defined = self.defined_coils.read();
-            if !defined.contains(&address) {
-                return Err(ExceptionCode::IllegalDataAddress);
-            }
-        }
-
-        let mut coils = self.coils.write();
-        let addr = address as usize;
-
-        if addr >= coils.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
-        }
-
-        coils[addr] = value;
-        drop(coils);
-        self.sync_variable_from_coil(address, value);
-        Ok(())
+        self.write_multiple_coils(address, &[value])
     }
 
     /// Записать несколько coils.
     /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
     pub fn write_multiple_coils(&self, start: u16, values: &[bool]) -> Result<(), ExceptionCode> {
-        // Проверяем, что все адреса определены
-        {
-            let defined = self.defined_coils.read();
-            self.check_addresses_defined(&defined, start, values.len() as u16)?;
-        }
-
         let mut coils = self.coils.write();
-        let start_addr = start as usize;
-        let end_addr = start_addr + values.len();
-
-        if end_addr > coils.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
-        }
-
-        for (i, &value) in values.iter().enumerate() {
-            coils[start_addr + i] = value;
-        }
+        coils.write(start, values)?;
 
         // Синхронизируем переменные
-        drop(coils);
-        for (i, &value) in values.iter().enumerate() {
-            self.sync_variable_from_coil(start + i as u16, value);
+        for i in 0..values.len() {
+            coils.sync_from_cells(start + i as u16);
         }
 
         Ok(())
     }
 
-    /// Синхронизировать переменную когда coil записан мастером.
-    fn sync_variable_from_coil(&self, address: u16, value: bool) {
-        let mut vars = self.variables.write();
-        for var in vars.values_mut() {
-            if var.area == ModbusArea::Coil && var.address == address {
-                var.value = ModbusValue::Bool(value);
-            }
-        }
-    }
-
     // ========== Discrete Inputs (1x) ==========
 
     /// Читать discrete inputs начиная с адреса.
     /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
     pub fn read_discrete_inputs(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode> {
-        // Проверяем, что все адреса определены
-        {
-            let defined = self.defined_discrete_inputs.read();
-            self.check_addresses_defined(&defined, start, count)?;
-        }
-
-        let inputs = self.discrete_inputs.read();
-        let start_idx = start as usize;
-        let end_idx = start_idx + count as usize;
-
-        if end_idx > inputs.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
-        }
-
-        Ok(inputs[start_idx..end_idx].to_vec())
+        self.discrete_inputs.read().read(start, count)
     }
 
     // ========== Holding Registers (4x) ==========
@@ -379,45 +1243,13 @@ impl ModbusDataStore {
         start: u16,
         count: u16,
     ) -> Result<Vec<u16>, ExceptionCode> {
-        // Проверяем, что все адреса определены
-        {
-            let defined = self.defined_holding_registers.read();
-            self.check_addresses_defined(&defined, start, count)?;
-        }
-
-        let regs = self.holding_registers.read();
-        let start_idx = start as usize;
-        let end_idx = start_idx + count as usize;
-
-        if end_idx > regs.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
-        }
-
-        Ok(regs[start_idx..end_idx].to_vec())
+        self.holding_registers.read().read(start, count)
     }
 
     /// Записать один holding register.
     /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
     pub fn write_single_register(&self, address: u16, value: u16) -> Result<(), ExceptionCode> {
-        // Проверяем, что адрес определён
-        {
-            let defined = self.defined_holding_registers.read();
-            if !defined.contains(&address) {
-                return Err(ExceptionCode::IllegalDataAddress);
-            }
-        }
-
-        let mut regs = self.holding_registers.write();
-        let addr = address as usize;
-
-        if addr >= regs.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
-        }
-
-        regs[addr] = value;
-        drop(regs);
-        self.sync_variable_from_register(ModbusArea::HoldingRegister, address);
-        Ok(())
+        self.write_multiple_registers(address, &[value])
     }
 
     /// Записать несколько holding registers.
@@ -427,159 +1259,237 @@ impl ModbusDataStore {
         start: u16,
         values: &[u16],
     ) -> Result<(), ExceptionCode> {
-        // Проверяем, что все адреса определены
-        {
-            let defined = self.defined_holding_registers.read();
-            self.check_addresses_defined(&defined, start, values.len() as u16)?;
-        }
-
         let mut regs = self.holding_registers.write();
-        let start_addr = start as usize;
-        let end_addr = start_addr + values.len();
-
-        if end_addr > regs.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
-        }
-
-        for (i, &value) in values.iter().enumerate() {
-            regs[start_addr + i] = value;
-        }
+        regs.write(start, values)?;
 
-        drop(regs);
         // Синхронизируем переменные для каждого записанного регистра
         for i in 0..values.len() {
-            self.sync_variable_from_register(ModbusArea::HoldingRegister, start + i as u16);
+            regs.sync_from_cells(start + i as u16);
         }
 
         Ok(())
     }
 
+    /// Изменить биты holding register по маскам AND/OR (функция 0x16).
+    /// Чтение и запись идут под одной блокировкой области, поэтому
+    /// параллельная запись не теряется между ними.
+    /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
+    pub fn mask_write_register(
+        &self,
+        address: u16,
+        and_mask: u16,
+        or_mask: u16,
+    ) -> Result<(), ExceptionCode> {
+        let mut regs = self.holding_registers.write();
+        let current = regs.read(address, 1)?[0];
+        regs.write(address, &[(current & and_mask) | (or_mask & !and_mask)])?;
+        regs.sync_from_cells(address);
+        Ok(())
+    }
+
     // ========== Input Registers (3x) ==========
 
     /// Читать input registers начиная с адреса.
     /// СТРОГАЯ ПРОВЕРКА: возвращает ошибку для неопределённых адресов.
     pub fn read_input_registers(&self, start: u16, count: u16) -> Result<Vec<u16>, ExceptionCode> {
-        // Проверяем, что все адреса определены
-        {
-            let defined = self.defined_input_registers.read();
-            self.check_addresses_defined(&defined, start, count)?;
-        }
+        self.input_registers.read().read(start, count)
+    }
 
-        let regs = self.input_registers.read();
-        let start_idx = start as usize;
-        let end_idx = start_idx + count as usize;
+    // ========== File Records (0x14/0x15) ==========
 
-        if end_idx > regs.len() {
-            return Err(ExceptionCode::IllegalDataAddress);
+    /// Заменить все файлы записей (при загрузке проекта).
+    pub fn set_record_files(&self, files: &[RecordFile]) -> Result<(), String> {
+        let mut loaded = BTreeMap::new();
+        for file in files {
+            file.validate()?;
+            if loaded
+                .insert(file.file_number, file.records.clone())
+                .is_some()
+            {
+                return Err(format!("Файл {} описан дважды", file.file_number));
+            }
         }
+        *self.record_files.write() = loaded;
+        Ok(())
+    }
 
-        Ok(regs[start_idx..end_idx].to_vec())
+    /// Все файлы записей по возрастанию номера.
+    pub fn record_files(&self) -> Vec<RecordFile> {
+        self.record_files
+            .read()
+            .iter()
+            .map(|(&file_number, records)| RecordFile {
+                file_number,
+                records: records.clone(),
+            })
+            .collect()
     }
 
-    /// Синхронизировать переменную когда регистр записан мастером.
-    fn sync_variable_from_register(&self, area: ModbusArea, address: u16) {
-        let regs = match area {
-            ModbusArea::HoldingRegister => self.holding_registers.read(),
-            ModbusArea::InputRegister => self.input_registers.read(),
-            _ => return,
-        };
+    /// Добавить или заменить файл записей.
+    pub fn set_record_file(&self, file: RecordFile) -> Result<(), String> {
+        file.validate()?;
+        self.record_files
+            .write()
+            .insert(file.file_number, file.records);
+        Ok(())
+    }
 
-        let mut vars = self.variables.write();
-        for var in vars.values_mut() {
-            if var.area == area && var.address == address {
-                let addr = address as usize;
-                let new_value = match var.data_type {
-                    ModbusDataType::Bool => {
-                        if addr < regs.len() {
-                            ModbusValue::Bool(regs[addr] != 0)
-                        } else {
-                            continue;
-                        }
-                    }
-                    ModbusDataType::Uint16 => {
-                        if addr < regs.len() {
-                            ModbusValue::Number(regs[addr] as f64)
-                        } else {
-                            continue;
-                        }
-                    }
-                    ModbusDataType::Int16 => {
-                        if addr < regs.len() {
-                            ModbusValue::Number(regs[addr] as i16 as f64)
-                        } else {
-                            continue;
-                        }
-                    }
-                    ModbusDataType::Uint32 => {
-                        if addr + 1 < regs.len() {
-                            let val = ((regs[addr] as u32) << 16) | (regs[addr + 1] as u32);
-                            ModbusValue::Number(val as f64)
-                        } else {
-                            continue;
-                        }
-                    }
-                    ModbusDataType::Float32 => {
-                        if addr + 1 < regs.len() {
-                            let bits = ((regs[addr] as u32) << 16) | (regs[addr + 1] as u32);
-                            let val = f32::from_bits(bits);
-                            ModbusValue::Number(val as f64)
-                        } else {
-                            continue;
-                        }
-                    }
-                };
-                var.value = new_value;
-            }
-        }
+    /// Удалить файл записей. Возвращает false, если файла не было.
+    pub fn remove_record_file(&self, file_number: u16) -> bool {
+        self.record_files.write().remove(&file_number).is_some()
     }
 
-    /// Очистить все данные в хранилище (сбросить все регистры и коилы к значениям по умолчанию).
-    pub fn clear(&self) {
-        {
-            let mut coils = self.coils.write();
-            for c in coils.iter_mut() {
-                *c = false;
+    /// Прочитать записи всех подзапросов (функция 0x14).
+    /// СТРОГАЯ ПРОВЕРКА: неописанный файл или выход за его конец — ошибка.
+    pub fn read_file_record(
+        &self,
+        read: &ReadFileRecordRequest,
+    ) -> Result<Vec<Vec<u16>>, ExceptionCode> {
+        let files = self.record_files.read();
+        read.records
+            .iter()
+            .map(|reference| {
+                let range = record_range(&files, reference)?;
+                Ok(files[&reference.file_number][range].to_vec())
+            })
+            .collect()
+    }
+
+    /// Записать все подзапросы (функция 0x15). Подзапросы проверяются до
+    /// записи, поэтому ошибка в любом из них не меняет ни одного файла.
+    pub fn write_file_record(&self, write: &WriteFileRecordRequest) -> Result<(), ExceptionCode> {
+        let mut files = self.record_files.write();
+        let ranges = write
+            .records
+            .iter()
+            .map(|record| record_range(&files, &record.reference))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (record, range) in write.records.iter().zip(ranges) {
+            if let Some(records) = files.get_mut(&record.reference.file_number) {
+                records[range].copy_from_slice(&record.values);
             }
         }
-        {
-            let mut inputs = self.discrete_inputs.write();
-            for i in inputs.iter_mut() {
-                *i = false;
-            }
+        Ok(())
+    }
+
+    /// Сбросить ячейки диапазона области к значениям по умолчанию.
+    fn clear_cells(&self, area: ModbusArea, start: usize, end: usize) {
+        match area {
+            ModbusArea::Coil => self.coils.write().clear_cells(start, end),
+            ModbusArea::DiscreteInput => self.discrete_inputs.write().clear_cells(start, end),
+            ModbusArea::InputRegister => self.input_registers.write().clear_cells(start, end),
+            ModbusArea::HoldingRegister => self.holding_registers.write().clear_cells(start, end),
         }
-        {
-            let mut regs = self.input_registers.write();
-            for r in regs.iter_mut() {
-                *r = 0;
-            }
+    }
+
+    /// Выборочно очистить хранилище.
+    /// Возвращает число удалённых переменных (для `ValuesOnly` — сброшенных).
+    pub fn clear_scoped(&self, scope: &ClearScope) -> usize {
+        if let ClearScope::ValuesOnly = scope {
+            return self.coils.write().clear_values()
+                + self.discrete_inputs.write().clear_values()
+                + self.input_registers.write().clear_values()
+                + self.holding_registers.write().clear_values();
         }
-        {
-            let mut regs = self.holding_registers.write();
-            for r in regs.iter_mut() {
-                *r = 0;
+
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .get_variables()
+            .into_iter()
+            .partition(|var| scope.matches(var));
+        match scope {
+            ClearScope::Area { area } => self.clear_cells(*area, 0, usize::MAX),
+            ClearScope::Range { area, start, end } => {
+                self.clear_cells(*area, *start as usize, *end as usize + 1)
             }
+            _ => {}
         }
-        {
-            let mut vars = self.variables.write();
-            vars.clear();
-        }
-        // Очищаем множества определённых адресов
-        {
-            let mut defined = self.defined_coils.write();
-            defined.clear();
-        }
-        {
-            let mut defined = self.defined_discrete_inputs.write();
-            defined.clear();
+        for var in &removed {
+            let start = var.address as usize;
+            self.clear_cells(var.area, start, start + var_width(var));
         }
-        {
-            let mut defined = self.defined_holding_registers.write();
-            defined.clear();
+        // Оставшиеся переменные заново записывают свои значения поверх сброшенных ячеек
+        self.load_definitions(&kept);
+        removed.len()
+    }
+
+    /// Независимая копия хранилища: переменные, ячейки всех областей и
+    /// форсирование, без образа процесса. Для пробных прогонов.
+    pub fn sandbox(&self) -> Self {
+        let copy = Self::new();
+        copy.load_definitions(&self.get_variables());
+        for area in [
+            ModbusArea::Coil,
+            ModbusArea::DiscreteInput,
+            ModbusArea::InputRegister,
+            ModbusArea::HoldingRegister,
+        ] {
+            copy.restore_area(area, 0, &self.dump_area(area, 0, u16::MAX as usize + 1));
         }
-        {
-            let mut defined = self.defined_input_registers.write();
-            defined.clear();
+        for forced in self.forced_variables() {
+            copy.force_variable(&forced.id, forced.value);
         }
+        copy
+    }
+
+    /// Очистить все данные в хранилище (сбросить все регистры и коилы к значениям по умолчанию).
+    pub fn clear(&self) {
+        let mut areas = self.variable_areas.write();
+        areas.clear();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.coils.write().reset();
+        self.discrete_inputs.write().reset();
+        self.input_registers.write().reset();
+        self.holding_registers.write().reset();
+    }
+}
+
+/// Хранилище как модель данных для ядра протокола (общего для всех транспортов).
+impl DataModel for ModbusDataStore {
+    fn read_coils(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode> {
+        ModbusDataStore::read_coils(self, start, count)
+    }
+
+    fn read_discrete_inputs(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode> {
+        ModbusDataStore::read_discrete_inputs(self, start, count)
+    }
+
+    fn read_holding_registers(&self, start: u16, count: u16) -> Result<Vec<u16>, ExceptionCode> {
+        ModbusDataStore::read_holding_registers(self, start, count)
+    }
+
+    fn read_input_registers(&self, start: u16, count: u16) -> Result<Vec<u16>, ExceptionCode> {
+        ModbusDataStore::read_input_registers(self, start, count)
+    }
+
+    fn write_single_coil(&self, address: u16, value: bool) -> Result<(), ExceptionCode> {
+        ModbusDataStore::write_single_coil(self, address, value)
+    }
+
+    fn write_single_register(&self, address: u16, value: u16) -> Result<(), ExceptionCode> {
+        ModbusDataStore::write_single_register(self, address, value)
+    }
+
+    fn write_multiple_coils(&self, start: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        ModbusDataStore::write_multiple_coils(self, start, values)
+    }
+
+    fn write_multiple_registers(&self, start: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        ModbusDataStore::write_multiple_registers(self, start, values)
+    }
+
+    fn mask_write_register(&self, write: &MaskWriteRegisterRequest) -> Result<(), ExceptionCode> {
+        ModbusDataStore::mask_write_register(self, write.address, write.and_mask, write.or_mask)
+    }
+
+    fn read_file_record(
+        &self,
+        read: &ReadFileRecordRequest,
+    ) -> Result<Vec<Vec<u16>>, ExceptionCode> {
+        ModbusDataStore::read_file_record(self, read)
+    }
+
+    fn write_file_record(&self, write: &WriteFileRecordRequest) -> Result<(), ExceptionCode> {
+        ModbusDataStore::write_file_record(self, write)
     }
 }
 
@@ -605,21 +1515,70 @@ mod tests {
         assert_eq!(result.unwrap_err(), ExceptionCode::IllegalDataAddress);
     }
 
+    #[test]
+    fn test_simulation_pause_keeps_value_for_simulation_only() {
+        let store = ModbusDataStore::new();
+        store.load_variables(&[ModbusVariable {
+            name: String::new(),
+            value: ModbusValue::Number(5.0),
+            ..ModbusVariable::new(
+                "flow",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            )
+        }]);
+        assert!(store
+            .pause_simulation(&["flow".to_string(), "missing".to_string()])
+            .is_err());
+        assert!(store.simulation_paused().is_empty());
+
+        store.pause_simulation(&["flow".to_string()]).unwrap();
+        assert!(store.update_simulated("flow", ModbusValue::Number(9.0)));
+        assert_eq!(store.read_holding_registers(0, 1), Ok(vec![5]));
+        // Мастер и UI пишут как обычно
+        store.write_single_register(0, 7).unwrap();
+        assert_eq!(store.read_holding_registers(0, 1), Ok(vec![7]));
+
+        assert_eq!(store.resume_simulation(&["flow".to_string()]), 1);
+        store.update_simulated("flow", ModbusValue::Number(9.0));
+        assert_eq!(store.read_holding_registers(0, 1), Ok(vec![9]));
+    }
+
+    #[test]
+    fn test_permissive_validation_toggles_live() {
+        let store = ModbusDataStore::new();
+        store.set_address_validation(AddressValidation::Permissive);
+        assert_eq!(store.read_holding_registers(0, 2), Ok(vec![0, 0]));
+        store.write_single_register(1, 77).unwrap();
+        assert_eq!(store.read_holding_registers(1, 1), Ok(vec![77]));
+        assert_eq!(
+            store.read_coils(65535, 2),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+
+        store.set_address_validation(AddressValidation::Strict);
+        assert_eq!(store.address_validation(), AddressValidation::Strict);
+        assert_eq!(
+            store.read_holding_registers(1, 1),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+    }
+
     #[test]
     fn test_strict_validation_defined_address() {
         let store = ModbusDataStore::new();
 
         // Загружаем переменную
         let vars = vec![ModbusVariable {
-            id: "var1".to_string(),
             name: "Test Register".to_string(),
-            area: ModbusArea::HoldingRegister,
-            address: 100,
-            data_type: ModbusDataType::Uint16,
             value: ModbusValue::Number(12345.0),
-            bit: None,
-            readonly: None,
-            note: None,
+            ..ModbusVariable::new(
+                "var1",
+                ModbusArea::HoldingRegister,
+                100,
+                ModbusDataType::Uint16,
+            )
         }];
 
         store.load_variables(&vars);
@@ -634,21 +1593,44 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_set_input_without_variable() {
+        let store = ModbusDataStore::new();
+        store.load_variables(&[ModbusVariable {
+            name: "Level".to_string(),
+            ..ModbusVariable::new(
+                "level",
+                ModbusArea::InputRegister,
+                10,
+                ModbusDataType::Uint16,
+            )
+        }]);
+
+        store.set_input(ModbusArea::InputRegister, 10, 500).unwrap();
+        store.set_input(ModbusArea::InputRegister, 11, 7).unwrap();
+        store.set_input(ModbusArea::DiscreteInput, 3, 0xFF).unwrap();
+
+        assert_eq!(store.read_input_registers(10, 2).unwrap(), vec![500, 7]);
+        assert_eq!(store.read_discrete_inputs(3, 1).unwrap(), vec![true]);
+        let values = store.get_variable_values(&["level".to_string()]);
+        assert_eq!(values[0].1.as_u16(), 500);
+        assert!(store.set_input(ModbusArea::HoldingRegister, 0, 1).is_err());
+    }
+
     #[test]
     fn test_strict_validation_uint32_occupies_two_registers() {
         let store = ModbusDataStore::new();
 
         // Загружаем переменную uint32 (занимает 2 регистра)
         let vars = vec![ModbusVariable {
-            id: "var1".to_string(),
             name: "Test Register".to_string(),
-            area: ModbusArea::HoldingRegister,
-            address: 50,
-            data_type: ModbusDataType::Uint32,
             value: ModbusValue::Number(0x12345678 as f64),
-            bit: None,
-            readonly: None,
-            note: None,
+            ..ModbusVariable::new(
+                "var1",
+                ModbusArea::HoldingRegister,
+                50,
+                ModbusDataType::Uint32,
+            )
         }];
 
         store.load_variables(&vars);
@@ -676,15 +1658,9 @@ mod tests {
 
         // Загружаем coil
         let vars = vec![ModbusVariable {
-            id: "coil1".to_string(),
             name: "Test Coil".to_string(),
-            area: ModbusArea::Coil,
-            address: 0,
-            data_type: ModbusDataType::Bool,
             value: ModbusValue::Bool(true),
-            bit: None,
-            readonly: None,
-            note: None,
+            ..ModbusVariable::new("coil1", ModbusArea::Coil, 0, ModbusDataType::Bool)
         }];
 
         store.load_variables(&vars);
@@ -715,15 +1691,13 @@ mod tests {
 
         // Загружаем переменную
         let vars = vec![ModbusVariable {
-            id: "var1".to_string(),
             name: "Test Register".to_string(),
-            area: ModbusArea::HoldingRegister,
-            address: 10,
-            data_type: ModbusDataType::Uint16,
-            value: ModbusValue::Number(0.0),
-            bit: None,
-            readonly: None,
-            note: None,
+            ..ModbusVariable::new(
+                "var1",
+                ModbusArea::HoldingRegister,
+                10,
+                ModbusDataType::Uint16,
+            )
         }];
 
         store.load_variables(&vars);
@@ -737,4 +1711,396 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap()[0], 999);
     }
+
+    #[test]
+    fn test_bulk_load_reports_progress() {
+        let vars: Vec<ModbusVariable> = (0..12_000u16)
+            .map(|address| ModbusVariable {
+                value: ModbusValue::Number(address as f64),
+                ..ModbusVariable::new(
+                    format!("hr{}", address),
+                    ModbusArea::HoldingRegister,
+                    address,
+                    ModbusDataType::Uint16,
+                )
+            })
+            .collect();
+        let store = ModbusDataStore::new();
+        let reports = std::sync::Mutex::new(Vec::new());
+        store.load_variables_with_progress(&vars, |loaded, total| {
+            reports.lock().unwrap().push((loaded, total));
+        });
+        assert_eq!(
+            reports.into_inner().unwrap(),
+            [(5000, 12_000), (10_000, 12_000), (12_000, 12_000)]
+        );
+        assert_eq!(store.read_holding_registers(11_999, 1).unwrap(), [11_999]);
+        assert_eq!(store.get_variables().len(), 12_000);
+    }
+
+    #[test]
+    fn test_merge_variables_keeps_runtime_values() {
+        let store = ModbusDataStore::new();
+
+        let mut var = ModbusVariable {
+            name: "Test Register".to_string(),
+            value: ModbusValue::Number(1.0),
+            ..ModbusVariable::new(
+                "var1",
+                ModbusArea::HoldingRegister,
+                10,
+                ModbusDataType::Uint16,
+            )
+        };
+        store.load_variables(std::slice::from_ref(&var));
+
+        // Мастер записал новое значение
+        store.write_single_register(10, 42).unwrap();
+
+        // Определение изменено извне (только имя) — значение сохраняется
+        var.name = "Renamed".to_string();
+        store.merge_variables(std::slice::from_ref(&var));
+        assert_eq!(store.read_holding_registers(10, 1).unwrap()[0], 42);
+
+        // Адрес изменён — используется значение из файла
+        var.address = 20;
+        store.merge_variables(std::slice::from_ref(&var));
+        assert_eq!(store.read_holding_registers(20, 1).unwrap()[0], 1);
+        assert!(store.read_holding_registers(10, 1).is_err());
+    }
+
+    #[test]
+    fn test_initial_and_reset_values() {
+        let store = ModbusDataStore::new();
+
+        let var =
+            |id: &str, address: u16, initial: Option<f64>, reset: Option<f64>| ModbusVariable {
+                value: ModbusValue::Number(7.0),
+                initial_value: initial.map(ModbusValue::Number),
+                reset_value: reset.map(ModbusValue::Number),
+                ..ModbusVariable::new(
+                    id,
+                    ModbusArea::HoldingRegister,
+                    address,
+                    ModbusDataType::Uint16,
+                )
+            };
+        store.load_variables(&[
+            var("both", 0, Some(10.0), Some(0.0)),
+            var("initial", 1, Some(20.0), None),
+            var("plain", 2, None, None),
+        ]);
+        assert_eq!(store.read_holding_registers(0, 3).unwrap(), vec![10, 20, 7]);
+
+        store.write_multiple_registers(0, &[100, 200, 300]).unwrap();
+        assert_eq!(store.reset_values(), 2);
+        assert_eq!(
+            store.read_holding_registers(0, 3).unwrap(),
+            vec![0, 20, 300]
+        );
+        // Определения сохраняются
+        assert_eq!(
+            store.get_variable("both").unwrap().reset_value,
+            Some(ModbusValue::Number(0.0))
+        );
+    }
+
+    #[test]
+    fn test_clear_scoped() {
+        let store = ModbusDataStore::new();
+        let var =
+            |id: &str, area: ModbusArea, address: u16, data_type: ModbusDataType| ModbusVariable {
+                value: match data_type {
+                    ModbusDataType::Bool => ModbusValue::Bool(true),
+                    _ => ModbusValue::Number(5.0),
+                },
+                ..ModbusVariable::new(id, area, address, data_type)
+            };
+        store.load_variables(&[
+            var("c0", ModbusArea::Coil, 0, ModbusDataType::Bool),
+            var("h0", ModbusArea::HoldingRegister, 0, ModbusDataType::Uint16),
+            var("h1", ModbusArea::HoldingRegister, 1, ModbusDataType::Uint32),
+            var("h3", ModbusArea::HoldingRegister, 3, ModbusDataType::Uint16),
+        ]);
+
+        // Диапазон задевает вторую половину uint32 — переменная удаляется целиком
+        let range = ClearScope::Range {
+            area: ModbusArea::HoldingRegister,
+            start: 2,
+            end: 2,
+        };
+        assert_eq!(store.clear_scoped(&range), 1);
+        assert!(store.get_variable("h1").is_none());
+        assert_eq!(
+            store.dump_area(ModbusArea::HoldingRegister, 0, 4),
+            vec![5, 0, 0, 5]
+        );
+        assert!(store.read_holding_registers(1, 1).is_err());
+
+        let group = ClearScope::Group {
+            ids: vec!["h3".to_string()],
+        };
+        assert_eq!(store.clear_scoped(&group), 1);
+        assert_eq!(store.dump_area(ModbusArea::HoldingRegister, 3, 1), vec![0]);
+
+        assert_eq!(store.clear_scoped(&ClearScope::ValuesOnly), 2);
+        assert_eq!(store.read_coils(0, 1).unwrap(), vec![false]);
+        assert_eq!(
+            store.get_variable("h0").unwrap().value,
+            ModbusValue::Number(0.0)
+        );
+
+        let area = ClearScope::Area {
+            area: ModbusArea::Coil,
+        };
+        assert_eq!(store.clear_scoped(&area), 1);
+        assert_eq!(store.get_variables().len(), 1);
+    }
+
+    #[test]
+    fn test_forced_variable_ignores_writes() {
+        let store = ModbusDataStore::new();
+        store.load_variables(&[ModbusVariable {
+            value: ModbusValue::Number(1.0),
+            ..ModbusVariable::new(
+                "speed",
+                ModbusArea::HoldingRegister,
+                4,
+                ModbusDataType::Uint32,
+            )
+        }]);
+
+        assert!(store.force_variable("speed", ModbusValue::Number(70000.0)));
+        // Запись мастера успешна, но значение восстанавливается
+        store.write_multiple_registers(5, &[9]).unwrap();
+        assert_eq!(store.read_holding_registers(4, 2).unwrap(), vec![1, 4464]);
+        assert!(store.update_variable("speed", ModbusValue::Number(2.0)));
+        assert_eq!(
+            store.get_variable("speed").unwrap().value,
+            ModbusValue::Number(70000.0)
+        );
+        assert_eq!(store.forced_variables().len(), 1);
+
+        assert_eq!(store.clear_forces(), 1);
+        store.write_multiple_registers(4, &[1, 9]).unwrap();
+        assert_eq!(
+            store.get_variable("speed").unwrap().value,
+            ModbusValue::Number(65545.0)
+        );
+    }
+
+    #[test]
+    fn test_master_write_syncs_variables_via_index() {
+        let store = ModbusDataStore::new();
+
+        let var = |id: &str, area: ModbusArea, address: u16| {
+            ModbusVariable::new(id, area, address, ModbusDataType::Uint16)
+        };
+        store.load_variables(&[
+            var("hr", ModbusArea::HoldingRegister, 5),
+            var("alias", ModbusArea::HoldingRegister, 5),
+            var("coil", ModbusArea::Coil, 5),
+        ]);
+
+        // Обе переменные на одном адресе получают новое значение, coil с тем же адресом — нет
+        store.write_multiple_registers(5, &[77]).unwrap();
+        let values =
+            store.get_variable_values(&["hr".to_string(), "alias".to_string(), "coil".to_string()]);
+        let numbers: Vec<f64> = values.iter().map(|(_, v)| v.as_f64()).collect();
+        assert_eq!(numbers, vec![77.0, 77.0, 0.0]);
+
+        store.write_single_coil(5, true).unwrap();
+        assert_eq!(
+            store.get_variable_values(&["coil".to_string()])[0]
+                .1
+                .as_f64(),
+            1.0
+        );
+
+        // После очистки переменные и индекс сброшены
+        store.clear();
+        assert!(store.get_variables().is_empty());
+    }
+
+    #[test]
+    fn test_mask_write_register_syncs_variables() {
+        let store = ModbusDataStore::new();
+        store.load_variables(&[ModbusVariable {
+            value: ModbusValue::Number(0x1234 as f64),
+            ..ModbusVariable::new(
+                "status",
+                ModbusArea::HoldingRegister,
+                3,
+                ModbusDataType::Uint16,
+            )
+        }]);
+
+        // Сброс младшей тетрады и установка бита 7
+        store.mask_write_register(3, 0xFF70, 0x0080).unwrap();
+        assert_eq!(store.read_holding_registers(3, 1).unwrap(), vec![0x12B0]);
+        assert_eq!(
+            store.get_variable_values(&["status".to_string()])[0]
+                .1
+                .as_f64(),
+            0x12B0 as f64
+        );
+        assert_eq!(
+            store.mask_write_register(4, 0, 0),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+    }
+
+    #[test]
+    fn test_process_image_restores_values() {
+        let path = std::env::temp_dir().join(format!("mb_store_{}.image", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let var = ModbusVariable {
+            name: "Test Register".to_string(),
+            value: ModbusValue::Number(1.0),
+            ..ModbusVariable::new(
+                "var1",
+                ModbusArea::HoldingRegister,
+                10,
+                ModbusDataType::Uint32,
+            )
+        };
+
+        let store = ModbusDataStore::new();
+        store.attach_image(&path).unwrap();
+        store.load_variables(std::slice::from_ref(&var));
+        store
+            .write_multiple_registers(10, &[0x0001, 0x0002])
+            .unwrap();
+        drop(store);
+
+        // Новый процесс: значение берётся из образа, а не из определения
+        let store = ModbusDataStore::new();
+        store.attach_image(&path).unwrap();
+        store.load_variables(std::slice::from_ref(&var));
+        assert_eq!(store.read_holding_registers(10, 2).unwrap(), vec![1, 2]);
+        assert_eq!(store.get_variables()[0].value.as_f64(), 65538.0);
+
+        // Последующие загрузки снова используют значения из определений
+        store.load_variables(std::slice::from_ref(&var));
+        assert_eq!(store.read_holding_registers(10, 2).unwrap(), vec![0, 1]);
+        store.detach_image();
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Нагрузочный тест: 16 клиентов одновременно читают holding registers
+    /// и пишут coils. Для сравнения тот же поток запросов прогоняется через одну
+    /// общую блокировку на всё хранилище (как до разделения на шарды).
+    /// Запуск: `cargo test --release bench_concurrent_clients -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_concurrent_clients() {
+        const CLIENTS: usize = 16;
+        const OPS_PER_CLIENT: usize = 200_000;
+
+        let store = create_shared_data_store();
+        let mut vars = Vec::new();
+        for address in 0..1000u16 {
+            for (area, data_type) in [
+                (ModbusArea::HoldingRegister, ModbusDataType::Uint16),
+                (ModbusArea::Coil, ModbusDataType::Bool),
+            ] {
+                vars.push(ModbusVariable {
+                    value: ModbusValue::Number(0.0),
+                    ..ModbusVariable::new(
+                        format!("{:?}_{}", area, address),
+                        area,
+                        address,
+                        data_type,
+                    )
+                });
+            }
+        }
+        store.load_variables(&vars);
+
+        let run = |coarse: Option<Arc<RwLock<()>>>| {
+            let started = std::time::Instant::now();
+            let handles: Vec<_> = (0..CLIENTS)
+                .map(|client| {
+                    let store = store.clone();
+                    let coarse = coarse.clone();
+                    std::thread::spawn(move || {
+                        for i in 0..OPS_PER_CLIENT {
+                            let address = ((client * 31 + i) % 990) as u16;
+                            if i % 5 == 0 {
+                                let _guard = coarse.as_ref().map(|lock| lock.write());
+                                store.write_single_coil(address, i % 2 == 0).unwrap();
+                            } else {
+                                let _guard = coarse.as_ref().map(|lock| lock.read());
+                                store.read_holding_registers(address, 10).unwrap();
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            (CLIENTS * OPS_PER_CLIENT) as f64 / started.elapsed().as_secs_f64()
+        };
+
+        let coarse = run(Some(Arc::new(RwLock::new(()))));
+        let sharded = run(None);
+        println!(
+            "{} клиентов: общая блокировка {:.0} операций/с, шарды {:.0} операций/с (x{:.2})",
+            CLIENTS,
+            coarse,
+            sharded,
+            sharded / coarse
+        );
+    }
+
+    #[test]
+    fn test_bit_bank_packs_values_and_defined_mask() {
+        let store = ModbusDataStore::new();
+        let coil = |id: &str, address: u16, value: bool| ModbusVariable {
+            value: ModbusValue::Bool(value),
+            ..ModbusVariable::new(id, ModbusArea::Coil, address, ModbusDataType::Bool)
+        };
+        store.load_variables(&[coil("a", 1, true), coil("b", 2, false), coil("c", 9, true)]);
+
+        let bank = store.bit_bank(ModbusArea::Coil, 0, 10).unwrap();
+        assert_eq!(bank.values, [0b0000_0010, 0b0000_0010]);
+        assert_eq!(bank.defined, [0b0000_0110, 0b0000_0010]);
+        let tail = store.bit_bank(ModbusArea::Coil, 65530, 100).unwrap();
+        assert_eq!((tail.count, tail.values.len()), (6, 1));
+        assert!(store.bit_bank(ModbusArea::HoldingRegister, 0, 8).is_err());
+    }
+
+    #[test]
+    fn test_record_file_write_checks_every_subrequest_first() {
+        let store = ModbusDataStore::new();
+        let file = |file_number: u16, records: Vec<u16>| RecordFile {
+            file_number,
+            records,
+        };
+        assert!(store
+            .set_record_files(&[file(1, vec![0; 4]), file(1, vec![0; 2])])
+            .is_err());
+        assert!(store.set_record_file(file(0, vec![0])).is_err());
+        store.set_record_files(&[file(1, vec![0; 4])]).unwrap();
+
+        // Файл 1 записи 0..=1, затем запись 4 за концом файла
+        let write = WriteFileRecordRequest::parse(&[
+            0x14, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0A, 0x00, 0x0B, 0x06, 0x00,
+            0x01, 0x00, 0x04, 0x00, 0x01, 0x00, 0x0C,
+        ])
+        .unwrap();
+        assert_eq!(
+            store.write_file_record(&write),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+        assert_eq!(store.record_files(), [file(1, vec![0; 4])]);
+
+        let write = WriteFileRecordRequest {
+            records: write.records[..1].to_vec(),
+        };
+        store.write_file_record(&write).unwrap();
+        assert_eq!(store.record_files(), [file(1, vec![10, 11, 0, 0])]);
+    }
 }
@@ -0,0 +1,291 @@
+//! Автоматическое обследование устройства в режиме мастера.
+//!
+//! Перебираются Unit ID и диапазоны адресов на целевом хосте: сначала
+//! проверяется, отвечает ли Unit ID вообще (любой ответ, в том числе
+//! исключение, означает, что устройство есть), затем каждая область читается
+//! блоками. Блок, отклонённый исключением, дочитывается по одному адресу.
+//! Результат — карта найденных устройств и диапазонов, полезная, когда
+//! документации на устройство нет.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::master::{MasterConnection, RequestError};
+use crate::modbus_protocol::ExceptionCode;
+use crate::types::ModbusArea;
+
+/// Параметры обследования.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanRequest {
+    pub host: String,
+    pub port: u16,
+    #[serde(default = "default_unit_from")]
+    pub unit_from: u8,
+    #[serde(default = "default_unit_to")]
+    pub unit_to: u8,
+    /// Обследуемые области; по умолчанию все четыре.
+    #[serde(default = "default_areas")]
+    pub areas: Vec<ModbusArea>,
+    #[serde(default)]
+    pub address_from: u16,
+    #[serde(default = "default_address_to")]
+    pub address_to: u16,
+    /// Размер блока чтения.
+    #[serde(default = "default_block_size")]
+    pub block_size: u16,
+    /// Таймаут ответа, мс; молчание дольше считается отсутствием устройства.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_unit_from() -> u8 {
+    1
+}
+
+fn default_unit_to() -> u8 {
+    247
+}
+
+fn default_areas() -> Vec<ModbusArea> {
+    vec![
+        ModbusArea::Coil,
+        ModbusArea::DiscreteInput,
+        ModbusArea::InputRegister,
+        ModbusArea::HoldingRegister,
+    ]
+}
+
+fn default_address_to() -> u16 {
+    99
+}
+
+fn default_block_size() -> u16 {
+    16
+}
+
+fn default_timeout_ms() -> u64 {
+    300
+}
+
+/// Найденный непрерывный диапазон адресов.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredRange {
+    pub area: ModbusArea,
+    pub start: u16,
+    pub count: u16,
+}
+
+/// Найденное устройство.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredUnit {
+    pub unit_id: u8,
+    pub ranges: Vec<DiscoveredRange>,
+}
+
+/// Результат обследования.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub units: Vec<DiscoveredUnit>,
+    /// Количество отправленных запросов.
+    pub probes: u32,
+}
+
+/// Соединение обследования: переоткрывается после сбоя связи, чтобы
+/// запоздавший ответ не перепутался со следующим запросом.
+struct ScanSession<'a> {
+    request: &'a ScanRequest,
+    connection: Option<MasterConnection>,
+    unit_id: u8,
+    probes: u32,
+}
+
+impl ScanSession<'_> {
+    async fn read(&mut self, area: ModbusArea, start: u16, count: u16) -> Result<(), RequestError> {
+        let mut conn = match self.connection.take() {
+            Some(conn) => conn,
+            None => MasterConnection::connect(
+                &self.request.host,
+                self.request.port,
+                self.unit_id,
+                Duration::from_millis(self.request.timeout_ms),
+            )
+            .await
+            .map_err(RequestError::Transport)?,
+        };
+        conn.set_unit_id(self.unit_id);
+        self.probes += 1;
+        let result = conn.read_area(area, start, count).await.map(|_| ());
+        if !matches!(result, Err(RequestError::Transport(_))) {
+            self.connection = Some(conn);
+        }
+        result
+    }
+
+    /// Найти отвечающие адреса области.
+    async fn scan_area(&mut self, area: ModbusArea) -> Vec<u16> {
+        let (from, to) = (
+            self.request.address_from as u32,
+            self.request.address_to as u32,
+        );
+        let block = self.request.block_size.max(1) as u32;
+        let mut found = Vec::new();
+
+        let mut start = from;
+        while start <= to {
+            let count = block.min(to - start + 1) as u16;
+            match self.read(area, start as u16, count).await {
+                Ok(()) => found.extend(start as u16..start as u16 + count),
+                // Функция не поддерживается — область целиком отсутствует
+                Err(RequestError::Exception(code))
+                    if code == ExceptionCode::IllegalFunction as u8 =>
+                {
+                    return found;
+                }
+                Err(RequestError::Exception(_)) if count > 1 => {
+                    for address in start as u16..start as u16 + count {
+                        if self.read(area, address, 1).await.is_ok() {
+                            found.push(address);
+                        }
+                    }
+                }
+                Err(_) => {}
+            }
+            start += block;
+        }
+        found
+    }
+}
+
+/// Свернуть отсортированные адреса в непрерывные диапазоны.
+fn to_ranges(area: ModbusArea, addresses: &[u16]) -> Vec<DiscoveredRange> {
+    let mut ranges: Vec<DiscoveredRange> = Vec::new();
+    for &address in addresses {
+        match ranges.last_mut() {
+            Some(range) if range.start as u32 + range.count as u32 == address as u32 => {
+                range.count += 1
+            }
+            _ => ranges.push(DiscoveredRange {
+                area,
+                start: address,
+                count: 1,
+            }),
+        }
+    }
+    ranges
+}
+
+/// Обследовать устройство(а) на хосте.
+pub async fn scan(request: &ScanRequest) -> Result<ScanResult, String> {
+    if request.unit_from > request.unit_to || request.address_from > request.address_to {
+        return Err("Начало диапазона больше конца".to_string());
+    }
+    let mut session = ScanSession {
+        request,
+        connection: None,
+        unit_id: request.unit_from,
+        probes: 0,
+    };
+    let mut units = Vec::new();
+
+    for unit_id in request.unit_from..=request.unit_to {
+        session.unit_id = unit_id;
+        // Любой ответ, в том числе исключение, означает, что устройство есть
+        let alive = match session
+            .read(ModbusArea::HoldingRegister, request.address_from, 1)
+            .await
+        {
+            Ok(()) | Err(RequestError::Exception(_)) => true,
+            Err(_) => false,
+        };
+        if !alive {
+            continue;
+        }
+
+        let mut ranges = Vec::new();
+        for &area in &request.areas {
+            let addresses = session.scan_area(area).await;
+            ranges.extend(to_ranges(area, &addresses));
+        }
+        log::info!(
+            "Обследование {}: Unit ID {} отвечает, {} диапазонов",
+            request.host,
+            unit_id,
+            ranges.len()
+        );
+        units.push(DiscoveredUnit { unit_id, ranges });
+    }
+
+    Ok(ScanResult {
+        units,
+        probes: session.probes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::server::create_shared_server;
+    use crate::types::{ModbusDataType, ModbusValue, ModbusVariable};
+
+    fn variable(area: ModbusArea, address: u16, data_type: ModbusDataType) -> ModbusVariable {
+        ModbusVariable {
+            name: String::new(),
+            value: ModbusValue::Number(0.0),
+            ..ModbusVariable::new(format!("{:?}_{}", area, address), area, address, data_type)
+        }
+    }
+
+    #[test]
+    fn test_scan_finds_unit_and_ranges() {
+        tauri::async_runtime::block_on(async {
+            let port = std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            let store = create_shared_data_store();
+            store.load_variables(&[
+                variable(ModbusArea::HoldingRegister, 10, ModbusDataType::Uint32),
+                variable(ModbusArea::HoldingRegister, 12, ModbusDataType::Uint16),
+                variable(ModbusArea::HoldingRegister, 20, ModbusDataType::Uint16),
+                variable(ModbusArea::Coil, 3, ModbusDataType::Bool),
+            ]);
+            let server = create_shared_server(store);
+            server.set_config("127.0.0.1".to_string(), port, 5);
+            server.start().await.unwrap();
+
+            let result = scan(&ScanRequest {
+                host: "127.0.0.1".to_string(),
+                port,
+                unit_from: 4,
+                unit_to: 5,
+                areas: vec![ModbusArea::Coil, ModbusArea::HoldingRegister],
+                address_from: 0,
+                address_to: 31,
+                block_size: 8,
+                timeout_ms: 100,
+            })
+            .await
+            .unwrap();
+            server.stop().unwrap();
+
+            assert_eq!(result.units.len(), 1);
+            assert_eq!(result.units[0].unit_id, 5);
+            let range = |area, start, count| DiscoveredRange { area, start, count };
+            assert_eq!(
+                result.units[0].ranges,
+                vec![
+                    range(ModbusArea::Coil, 3, 1),
+                    range(ModbusArea::HoldingRegister, 10, 3),
+                    range(ModbusArea::HoldingRegister, 20, 1),
+                ]
+            );
+        });
+    }
+}
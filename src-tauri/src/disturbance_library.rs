@@ -0,0 +1,169 @@
+//! Библиотека возмущений: пресеты сбоев и сценарии в отдельном файле.
+//!
+//! Типовые испытания (потеря связи, медленное устройство, скачок датчика)
+//! сохраняются из одного проекта и загружаются в любой другой. Пресеты при
+//! загрузке добавляются в проект, заменяя пресеты с теми же именами;
+//! сценарии возвращаются в UI. Ссылки сценариев на переменные и пресеты,
+//! которых нет в проекте, не мешают загрузке и выводятся предупреждениями.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fault_rules::FaultPreset;
+use crate::harness::{Scenario, ScenarioStep};
+use crate::types::ModbusVariable;
+
+/// Текущая версия формата библиотеки.
+pub const DISTURBANCE_LIBRARY_VERSION: u32 = 1;
+
+/// Файл библиотеки возмущений.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisturbanceLibrary {
+    pub version: u32,
+    #[serde(default)]
+    pub fault_presets: Vec<FaultPreset>,
+    /// Сценарии по именам.
+    #[serde(default)]
+    pub scenarios: BTreeMap<String, Scenario>,
+}
+
+impl DisturbanceLibrary {
+    pub fn new(fault_presets: Vec<FaultPreset>, scenarios: BTreeMap<String, Scenario>) -> Self {
+        Self {
+            version: DISTURBANCE_LIBRARY_VERSION,
+            fault_presets,
+            scenarios,
+        }
+    }
+
+    /// Разобрать файл библиотеки и проверить версию и имена пресетов.
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let library: Self = serde_json::from_str(data)
+            .map_err(|e| format!("Ошибка JSON библиотеки возмущений: {e}"))?;
+        if library.version != DISTURBANCE_LIBRARY_VERSION {
+            return Err(format!(
+                "Версия библиотеки возмущений {} не поддерживается (ожидается {})",
+                library.version, DISTURBANCE_LIBRARY_VERSION
+            ));
+        }
+        let mut names = BTreeSet::new();
+        if let Some(duplicate) = library
+            .fault_presets
+            .iter()
+            .find(|preset| !names.insert(preset.name.as_str()))
+        {
+            return Err(format!(
+                "Пресет сбоев '{}' повторяется в библиотеке",
+                duplicate.name
+            ));
+        }
+        Ok(library)
+    }
+
+    /// Ссылки сценариев на переменные и пресеты, которых нет ни в проекте,
+    /// ни в библиотеке.
+    pub fn unresolved(
+        &self,
+        variables: &[ModbusVariable],
+        project_presets: &[FaultPreset],
+    ) -> Vec<String> {
+        let variable_ids: BTreeSet<&str> = variables.iter().map(|var| var.id.as_str()).collect();
+        let preset_names: BTreeSet<&str> = project_presets
+            .iter()
+            .chain(&self.fault_presets)
+            .map(|preset| preset.name.as_str())
+            .collect();
+        let mut warnings = BTreeSet::new();
+        for (name, scenario) in &self.scenarios {
+            for step in &scenario.steps {
+                match step {
+                    ScenarioStep::Set { variable, .. }
+                        if !variable_ids.contains(variable.as_str()) =>
+                    {
+                        warnings.insert(format!(
+                            "Сценарий '{}': переменная '{}' не найдена",
+                            name, variable
+                        ));
+                    }
+                    ScenarioStep::ActivateFaultPreset { name: preset }
+                    | ScenarioStep::DeactivateFaultPreset { name: preset }
+                        if !preset_names.contains(preset.as_str()) =>
+                    {
+                        warnings.insert(format!(
+                            "Сценарий '{}': пресет сбоев '{}' не найден",
+                            name, preset
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        warnings.into_iter().collect()
+    }
+}
+
+/// Итог загрузки библиотеки.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedLibrary {
+    /// Имена добавленных или заменённых пресетов сбоев.
+    pub fault_presets: Vec<String>,
+    pub scenarios: BTreeMap<String, Scenario>,
+    pub warnings: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ModbusArea, ModbusDataType};
+
+    #[test]
+    fn test_roundtrip_and_unresolved_references() {
+        let presets: Vec<FaultPreset> = serde_json::from_str(
+            r#"[{"name": "slow device", "rules": {"lag": {"kind": "delay", "ms": 800}}}]"#,
+        )
+        .unwrap();
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"steps": [
+                {"action": "activateFaultPreset", "name": "slow device"},
+                {"action": "set", "variable": "temp", "value": 35},
+                {"action": "clear", "scope": "range", "area": "coil", "start": 0, "end": 7},
+                {"action": "set", "variable": "flow", "value": 1},
+                {"action": "deactivateFaultPreset", "name": "lossy link"}
+            ]}"#,
+        )
+        .unwrap();
+        let library =
+            DisturbanceLibrary::new(presets, BTreeMap::from([("spike".to_string(), scenario)]));
+
+        let data = serde_json::to_string(&library).unwrap();
+        let loaded = DisturbanceLibrary::parse(&data).unwrap();
+        assert_eq!(loaded.fault_presets, library.fault_presets);
+        assert_eq!(loaded.scenarios["spike"].steps.len(), 5);
+
+        let temp = ModbusVariable::new(
+            "temp",
+            ModbusArea::HoldingRegister,
+            0,
+            ModbusDataType::Uint16,
+        );
+        assert_eq!(
+            loaded.unresolved(&[temp], &[]),
+            [
+                "Сценарий 'spike': переменная 'flow' не найдена",
+                "Сценарий 'spike': пресет сбоев 'lossy link' не найден",
+            ]
+        );
+
+        assert!(DisturbanceLibrary::parse(r#"{"version": 2}"#)
+            .unwrap_err()
+            .contains("Версия"));
+        assert!(DisturbanceLibrary::parse(
+            r#"{"version": 1, "faultPresets": [{"name": "a"}, {"name": "a"}]}"#
+        )
+        .unwrap_err()
+        .contains("повторяется"));
+    }
+}
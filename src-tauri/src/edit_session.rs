@@ -0,0 +1,316 @@
+//! Транзакционное пакетное редактирование переменных.
+//!
+//! Серия добавлений, изменений и удалений переменных накапливается в рабочей копии
+//! и применяется к хранилищу данных одной перезагрузкой при фиксации,
+//! либо отбрасывается целиком при откате. Это исключает «наполовину применённые»
+//! импорты. Переменные, не затронутые сессией, сохраняют текущие значения.
+//! Если переменные хранилища перезагрузили в обход открытой сессии, фиксация
+//! отклоняется: иначе рабочая копия молча удалила бы эти изменения.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::data_store::SharedDataStore;
+use crate::types::ModbusVariable;
+
+/// Открытая сессия редактирования.
+#[derive(Debug)]
+struct EditSession {
+    id: u64,
+    /// Рабочая копия переменных.
+    working: Vec<ModbusVariable>,
+    /// ID переменных, добавленных или изменённых в сессии.
+    touched: HashSet<String>,
+    /// Номер набора определений хранилища при открытии сессии.
+    generation: u64,
+    /// Количество изменений в сессии.
+    changes: usize,
+}
+
+/// Состояние сессии редактирования для UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditSessionInfo {
+    pub session_id: u64,
+    pub variables_count: usize,
+    pub changes: usize,
+}
+
+/// Менеджер сессий редактирования (одновременно открыта не более одной).
+pub struct EditManager {
+    data_store: SharedDataStore,
+    session: Mutex<Option<EditSession>>,
+    next_id: AtomicU64,
+}
+
+impl EditManager {
+    pub fn new(data_store: SharedDataStore) -> Self {
+        Self {
+            data_store,
+            session: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Начать сессию редактирования с копией текущих переменных.
+    pub fn begin(&self) -> Result<EditSessionInfo, String> {
+        let mut session = self.session.lock();
+        if let Some(existing) = session.as_ref() {
+            return Err(format!("Сессия редактирования {} уже открыта", existing.id));
+        }
+
+        let generation = self.data_store.generation();
+        let mut working = self.data_store.get_variables();
+        working.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let new_session = EditSession {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            working,
+            touched: HashSet::new(),
+            generation,
+            changes: 0,
+        };
+        let info = new_session.info();
+        *session = Some(new_session);
+        Ok(info)
+    }
+
+    /// Добавить или заменить переменные (по ID) в рабочей копии.
+    pub fn upsert(
+        &self,
+        session_id: u64,
+        variables: Vec<ModbusVariable>,
+    ) -> Result<EditSessionInfo, String> {
+        self.with_session(session_id, |session| {
+            for var in variables {
+                session.touched.insert(var.id.clone());
+                match session.working.iter_mut().find(|v| v.id == var.id) {
+                    Some(existing) => *existing = var,
+                    None => session.working.push(var),
+                }
+                session.changes += 1;
+            }
+            Ok(session.info())
+        })
+    }
+
+    /// Удалить переменные из рабочей копии.
+    pub fn remove(&self, session_id: u64, ids: &[String]) -> Result<EditSessionInfo, String> {
+        self.with_session(session_id, |session| {
+            let before = session.working.len();
+            session.working.retain(|v| !ids.contains(&v.id));
+            session.changes += before - session.working.len();
+            Ok(session.info())
+        })
+    }
+
+    /// Зафиксировать сессию: проверить рабочую копию и применить её одной перезагрузкой.
+    /// Изменённые в сессии переменные загружаются как есть, остальные — с текущими
+    /// значениями хранилища. При ошибке проверки или если переменные хранилища
+    /// перезагрузили после открытия сессии, она остаётся открытой.
+    pub fn commit(&self, session_id: u64) -> Result<Vec<ModbusVariable>, String> {
+        let mut guard = self.session.lock();
+        let session = Self::check_session(guard.as_ref(), session_id)?;
+        if self.data_store.generation() != session.generation {
+            return Err(format!(
+                "Переменные изменились после открытия сессии {}: отмените её и начните заново",
+                session.id
+            ));
+        }
+        validate_variables(&session.working)?;
+
+        let session = guard.take().expect("сессия проверена выше");
+        let applied: Vec<ModbusVariable> = session
+            .working
+            .into_iter()
+            .map(|mut var| {
+                if !session.touched.contains(&var.id) {
+                    if let Some(live) = self.data_store.get_variable(&var.id) {
+                        var.value = live.value;
+                    }
+                }
+                var
+            })
+            .collect();
+        self.data_store.load_definitions(&applied);
+        log::info!(
+            "Сессия редактирования {} зафиксирована: {} изменений, {} переменных",
+            session.id,
+            session.changes,
+            applied.len()
+        );
+        Ok(applied)
+    }
+
+    /// Откатить сессию, отбросив все изменения.
+    pub fn rollback(&self, session_id: u64) -> Result<(), String> {
+        let mut guard = self.session.lock();
+        Self::check_session(guard.as_ref(), session_id)?;
+        *guard = None;
+        log::info!("Сессия редактирования {} отменена", session_id);
+        Ok(())
+    }
+
+    /// Получить состояние открытой сессии.
+    pub fn current(&self) -> Option<EditSessionInfo> {
+        self.session.lock().as_ref().map(EditSession::info)
+    }
+
+    fn with_session<R>(
+        &self,
+        session_id: u64,
+        f: impl FnOnce(&mut EditSession) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let mut guard = self.session.lock();
+        Self::check_session(guard.as_ref(), session_id)?;
+        f(guard.as_mut().expect("сессия проверена выше"))
+    }
+
+    fn check_session(
+        session: Option<&EditSession>,
+        session_id: u64,
+    ) -> Result<&EditSession, String> {
+        match session {
+            Some(s) if s.id == session_id => Ok(s),
+            Some(s) => Err(format!(
+                "Сессия {} не активна (открыта сессия {})",
+                session_id, s.id
+            )),
+            None => Err("Нет открытой сессии редактирования".to_string()),
+        }
+    }
+}
+
+impl EditSession {
+    fn info(&self) -> EditSessionInfo {
+        EditSessionInfo {
+            session_id: self.id,
+            variables_count: self.working.len(),
+            changes: self.changes,
+        }
+    }
+}
+
+/// Проверить набор переменных перед применением.
+fn validate_variables(variables: &[ModbusVariable]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for var in variables {
+        if !ids.insert(var.id.as_str()) {
+            return Err(format!("Повторяющийся ID переменной '{}'", var.id));
+        }
+        let last = var.address as u32 + var.data_type.register_count() as u32 - 1;
+        if last > u16::MAX as u32 {
+            return Err(format!(
+                "Переменная '{}' выходит за пределы адресного пространства",
+                var.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Общая ссылка на менеджер сессий редактирования.
+pub type SharedEditManager = Arc<EditManager>;
+
+/// Создать общий менеджер сессий редактирования.
+pub fn create_shared_edit_manager(data_store: SharedDataStore) -> SharedEditManager {
+    Arc::new(EditManager::new(data_store))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue};
+
+    fn variable(id: &str, address: u16, data_type: ModbusDataType) -> ModbusVariable {
+        ModbusVariable {
+            value: ModbusValue::Number(0.0),
+            ..ModbusVariable::new(id, ModbusArea::HoldingRegister, address, data_type)
+        }
+    }
+
+    #[test]
+    fn test_commit_applies_all_changes() {
+        let store = create_shared_data_store();
+        store.load_variables(&[variable("a", 0, ModbusDataType::Uint16)]);
+        let manager = EditManager::new(store.clone());
+
+        let session = manager.begin().unwrap().session_id;
+        manager
+            .upsert(session, vec![variable("b", 1, ModbusDataType::Uint16)])
+            .unwrap();
+        manager.remove(session, &["a".to_string()]).unwrap();
+
+        // До фиксации хранилище не меняется
+        assert!(store.read_holding_registers(0, 1).is_ok());
+        assert!(store.read_holding_registers(1, 1).is_err());
+
+        manager.commit(session).unwrap();
+        assert!(store.read_holding_registers(0, 1).is_err());
+        assert!(store.read_holding_registers(1, 1).is_ok());
+        assert!(manager.current().is_none());
+    }
+
+    #[test]
+    fn test_commit_keeps_edited_and_live_values() {
+        let store = create_shared_data_store();
+        store.load_variables(&[
+            variable("a", 0, ModbusDataType::Uint16),
+            variable("b", 1, ModbusDataType::Uint16),
+        ]);
+        let manager = EditManager::new(store.clone());
+
+        let session = manager.begin().unwrap().session_id;
+        let mut edited = variable("a", 0, ModbusDataType::Uint16);
+        edited.value = ModbusValue::Number(42.0);
+        manager.upsert(session, vec![edited]).unwrap();
+        // Запись мастера в незатронутую переменную во время сессии
+        store.update_variable("b", ModbusValue::Number(7.0));
+
+        manager.commit(session).unwrap();
+        assert_eq!(store.read_holding_registers(0, 2).unwrap(), vec![42, 7]);
+    }
+
+    #[test]
+    fn test_commit_refused_after_outside_reload() {
+        let store = create_shared_data_store();
+        let manager = EditManager::new(store.clone());
+
+        let session = manager.begin().unwrap().session_id;
+        store.load_variables(&[variable("outside", 0, ModbusDataType::Uint16)]);
+        manager
+            .upsert(session, vec![variable("inside", 1, ModbusDataType::Uint16)])
+            .unwrap();
+
+        assert!(manager.commit(session).is_err());
+        assert!(manager.current().is_some());
+        assert!(store.get_variable("outside").is_some());
+    }
+
+    #[test]
+    fn test_invalid_commit_keeps_session_and_rollback_discards() {
+        let store = create_shared_data_store();
+        let manager = EditManager::new(store.clone());
+
+        let session = manager.begin().unwrap().session_id;
+        assert!(manager.begin().is_err());
+
+        manager
+            .upsert(
+                session,
+                vec![variable("big", 65535, ModbusDataType::Float32)],
+            )
+            .unwrap();
+        assert!(manager.commit(session).is_err());
+        assert!(manager.current().is_some());
+
+        manager.rollback(session).unwrap();
+        assert!(manager.current().is_none());
+        assert!(store.get_variables().is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Структурированные ошибки команд.
+//!
+//! Команды возвращают UI не строку, а объект `{ code, message, context }`:
+//! по коду интерфейс реагирует программно (например, предлагает другой порт
+//! при `portInUse` и запуск с правами при `permissionDenied`), а сообщение
+//! показывается пользователю как есть. Команды и их вспомогательные функции
+//! выбирают код явно; внутренние модули по-прежнему возвращают `String`, и
+//! только такие ошибки без своего кода получают `failed`.
+
+use std::fmt;
+use std::io;
+
+use serde::Serialize;
+
+/// Код ошибки для UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// Сервер уже запущен.
+    AlreadyRunning,
+    /// Сервер не запущен.
+    NotRunning,
+    /// Порт занят другим процессом.
+    PortInUse,
+    /// Недостаточно прав (например, порт ниже 1024).
+    PermissionDenied,
+    /// Адрес не назначен ни одному интерфейсу или не разрешается.
+    AddressUnavailable,
+    /// Объект с указанным ID не найден.
+    NotFound,
+    /// Действие пересекается с уже выполняемым (например, дорожки сценариев
+    /// записывают одну переменную).
+    Conflict,
+    /// Проект не прошёл проверку целостности (см. `get_integrity_report`).
+    IntegrityFailed,
+    /// Некорректные параметры или настройки.
+    InvalidInput,
+    /// Ошибка чтения или записи файла.
+    Io,
+    /// Внутренняя ошибка приложения, а не входных данных (например, не
+    /// удалось сериализовать данные или прервалась фоновая задача).
+    Internal,
+    /// Прочие ошибки.
+    Failed,
+}
+
+/// Ошибка команды.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Объект ошибки: адрес, путь к файлу, ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+/// Результат команды.
+pub type CommandResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            context: None,
+        }
+    }
+
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Объект с ID `id` не найден.
+    pub fn not_found(message: impl Into<String>, id: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message).with_context(id)
+    }
+
+    /// Некорректные параметры.
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    /// Внутренняя ошибка приложения.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    /// Ошибка ввода-вывода: код по виду ошибки, в контексте — объект операции.
+    pub fn io(error: &io::Error, message: impl Into<String>, context: impl Into<String>) -> Self {
+        let code = match error.kind() {
+            io::ErrorKind::AddrInUse => ErrorCode::PortInUse,
+            io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            io::ErrorKind::AddrNotAvailable => ErrorCode::AddressUnavailable,
+            io::ErrorKind::NotFound => ErrorCode::NotFound,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, format!("{}: {}", message.into(), error)).with_context(context)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Запасной вариант для `?` над ошибками внутренних модулей, которые
+/// возвращают `String`: код `failed`. Там, где вид ошибки известен, команда
+/// выбирает конструктор (`invalid`, `not_found`, `io`, `internal`) явно.
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Failed, message)
+    }
+}
+
+/// Запасной вариант, как и `From<String>`.
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorCode::Failed, message)
+    }
+}
+
+/// Для модулей, которые возвращают ошибки строкой.
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors_map_to_codes() {
+        let busy = io::Error::from(io::ErrorKind::AddrInUse);
+        let error = AppError::io(&busy, "Не удалось привязаться", "0.0.0.0:502");
+        assert_eq!(error.code, ErrorCode::PortInUse);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap()["code"],
+            serde_json::json!("portInUse")
+        );
+        assert_eq!(error.context.as_deref(), Some("0.0.0.0:502"));
+
+        let plain = AppError::from("Ошибка".to_string());
+        assert_eq!(plain.code, ErrorCode::Failed);
+        assert!(serde_json::to_value(&plain)
+            .unwrap()
+            .get("context")
+            .is_none());
+        assert_eq!(String::from(plain), "Ошибка");
+
+        let internal = AppError::internal("Не удалось сериализовать").with_context("a.json");
+        assert_eq!(
+            serde_json::to_value(&internal).unwrap()["code"],
+            serde_json::json!("internal")
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Кольцевой буфер событий с метками времени, доступный через регистры.
+//!
+//! Так устроены журналы событий многих устройств защиты: окно holding
+//! registers начинается с заголовка, за которым идут записи.
+//!
+//! | Смещение | Содержимое                                          |
+//! |----------|-----------------------------------------------------|
+//! | 0        | head — индекс следующей записи (пишет устройство)  |
+//! | 1        | tail — индекс первой непрочитанной (пишет мастер)  |
+//! | 2        | ёмкость буфера, записей                             |
+//! | 3        | число событий, потерянных при переполнении          |
+//! | 4 + 4·i  | запись i: секунды Unix (uint32), миллисекунды, код  |
+//!
+//! Мастер читает записи от tail до head и подтверждает прочтение, записывая
+//! новый tail. Буфер пуст при head = tail и вмещает не больше `capacity − 1`
+//! непрочитанных событий; при переполнении самое старое событие теряется,
+//! tail сдвигается и растёт счётчик потерь.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::ModbusDataStore;
+use crate::types::ModbusArea;
+
+/// Число регистров заголовка.
+const HEADER_WORDS: usize = 4;
+
+/// Число регистров одной записи.
+const RECORD_WORDS: usize = 4;
+
+/// Настройки буфера событий в профиле подключения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventBufferConfig {
+    /// Адрес заголовка в holding registers.
+    pub base_address: u16,
+    /// Число записей в кольце.
+    pub capacity: u16,
+}
+
+impl EventBufferConfig {
+    /// Проверить, что окно помещается в адресное пространство.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity < 2 {
+            return Err("Ёмкость буфера событий должна быть не меньше 2".to_string());
+        }
+        let end = self.base_address as usize + self.window_len();
+        if end > u16::MAX as usize + 1 {
+            return Err(format!(
+                "Буфер событий с адреса {} на {} записей выходит за пределы регистров",
+                self.base_address, self.capacity
+            ));
+        }
+        Ok(())
+    }
+
+    /// Размер окна в регистрах.
+    fn window_len(&self) -> usize {
+        HEADER_WORDS + self.capacity as usize * RECORD_WORDS
+    }
+
+    fn record_address(&self, index: u16) -> u16 {
+        (self.base_address as usize + HEADER_WORDS + index as usize * RECORD_WORDS) as u16
+    }
+}
+
+/// Состояние указателей буфера.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventBufferStatus {
+    pub head: u16,
+    pub tail: u16,
+    /// Непрочитанных событий.
+    pub pending: u16,
+    pub overflows: u16,
+}
+
+/// Буфер событий работающего сервера; указатели хранятся в самих регистрах.
+#[derive(Debug, Default)]
+pub struct EventBuffer {
+    config: Mutex<Option<EventBufferConfig>>,
+}
+
+impl EventBuffer {
+    /// Включить буфер с пустым окном (при запуске сервера); `None` — выключить.
+    pub fn start(&self, store: &ModbusDataStore, config: Option<EventBufferConfig>) {
+        let mut active = self.config.lock();
+        *active = config;
+        let Some(config) = config else {
+            return;
+        };
+        let mut window = vec![0u16; config.window_len()];
+        window[2] = config.capacity;
+        store.set_holding_registers(config.base_address, &window);
+    }
+
+    /// Добавить событие с текущим временем.
+    pub fn push(&self, store: &ModbusDataStore, code: u16) -> Result<EventBufferStatus, String> {
+        self.push_at(store, code, SystemTime::now())
+    }
+
+    fn push_at(
+        &self,
+        store: &ModbusDataStore,
+        code: u16,
+        at: SystemTime,
+    ) -> Result<EventBufferStatus, String> {
+        let active = self.config.lock();
+        let Some(config) = *active else {
+            return Err("Буфер событий не настроен".to_string());
+        };
+        let header = store.dump_area(
+            ModbusArea::HoldingRegister,
+            config.base_address,
+            HEADER_WORDS,
+        );
+        let head = header[0] % config.capacity;
+        let mut tail = header[1] % config.capacity;
+        let mut overflows = header[3];
+
+        let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = since_epoch.as_secs() as u32;
+        let record = [
+            (seconds >> 16) as u16,
+            seconds as u16,
+            since_epoch.subsec_millis() as u16,
+            code,
+        ];
+        store.set_holding_registers(config.record_address(head), &record);
+
+        let head = (head + 1) % config.capacity;
+        store.set_holding_registers(config.base_address, &[head]);
+        // tail переписывается только при переполнении, иначе он принадлежит мастеру
+        if head == tail {
+            tail = (tail + 1) % config.capacity;
+            overflows = overflows.wrapping_add(1);
+            store.set_holding_registers(config.base_address + 1, &[tail]);
+            store.set_holding_registers(config.base_address + 3, &[overflows]);
+        }
+        Ok(status(&config, head, tail, overflows))
+    }
+
+    /// Текущие указатели; `None`, если буфер выключен.
+    pub fn status(&self, store: &ModbusDataStore) -> Option<EventBufferStatus> {
+        let config = (*self.config.lock())?;
+        let header = store.dump_area(
+            ModbusArea::HoldingRegister,
+            config.base_address,
+            HEADER_WORDS,
+        );
+        Some(status(
+            &config,
+            header[0] % config.capacity,
+            header[1] % config.capacity,
+            header[3],
+        ))
+    }
+}
+
+fn status(config: &EventBufferConfig, head: u16, tail: u16, overflows: u16) -> EventBufferStatus {
+    EventBufferStatus {
+        head,
+        tail,
+        pending: (head + config.capacity - tail) % config.capacity,
+        overflows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_events_wrap_and_overflow() {
+        let store = ModbusDataStore::new();
+        let config = EventBufferConfig {
+            base_address: 100,
+            capacity: 3,
+        };
+        assert!(config.validate().is_ok());
+        let buffer = EventBuffer::default();
+        buffer.start(&store, Some(config));
+        assert_eq!(store.read_holding_registers(100, 4), Ok(vec![0, 0, 3, 0]));
+
+        let at = UNIX_EPOCH + Duration::from_millis(70_000_250);
+        buffer.push_at(&store, 0x11, at).unwrap();
+        assert_eq!(
+            store.read_holding_registers(104, 4),
+            Ok(vec![1, 4464, 250, 0x11])
+        );
+
+        // Мастер прочитал событие и подтвердил его
+        store.write_single_register(101, 1).unwrap();
+        buffer.push_at(&store, 0x12, at).unwrap();
+        let status = buffer.push_at(&store, 0x13, at).unwrap();
+        assert_eq!(status.pending, 2);
+        assert_eq!(status.overflows, 0);
+
+        // Третье непрочитанное вытесняет самое старое
+        let status = buffer.push_at(&store, 0x14, at).unwrap();
+        assert_eq!((status.head, status.tail), (1, 2));
+        assert_eq!(status.overflows, 1);
+        assert_eq!(store.read_holding_registers(100, 4), Ok(vec![1, 2, 3, 1]));
+    }
+}
@@ -0,0 +1,113 @@
+//! Ответы-исключения по команде, в том числе с нестандартными кодами.
+//!
+//! В топологиях со шлюзами мастер получает коды 0x0A (Gateway Path
+//! Unavailable), 0x0B (Gateway Target Device Failed Response) и
+//! производительские значения, которых нет в обычном обмене с устройством.
+//! Инжекция заменяет ответ на исключение с заданным кодом для следующих N
+//! запросов (или до отмены), при желании — только для одной функции.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::modbus_protocol::{ModbusRequest, ModbusResponse};
+
+/// Запрошенная инжекция исключений.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExceptionInjection {
+    /// Код исключения (1..=255, в том числе нестандартные).
+    pub exception_code: u8,
+    /// Только для запросов с этим кодом функции; `None` — для всех.
+    #[serde(default)]
+    pub function_code: Option<u8>,
+    /// Сколько ответов заменить; `None` — до отмены.
+    #[serde(default)]
+    pub remaining: Option<u32>,
+}
+
+/// Ожидающая инжекция исключений сервера.
+#[derive(Debug, Default)]
+pub struct ExceptionInjector {
+    pending: Mutex<Option<ExceptionInjection>>,
+}
+
+impl ExceptionInjector {
+    /// Заменить текущую инжекцию новой.
+    pub fn schedule(&self, injection: ExceptionInjection) -> Result<(), String> {
+        if injection.exception_code == 0 {
+            return Err("Код исключения 0 не допускается".to_string());
+        }
+        if injection.remaining == Some(0) {
+            return Err("Число ответов должно быть больше нуля".to_string());
+        }
+        *self.pending.lock() = Some(injection);
+        Ok(())
+    }
+
+    /// Отменить инжекцию.
+    pub fn cancel(&self) {
+        *self.pending.lock() = None;
+    }
+
+    /// Текущая инжекция с оставшимся числом ответов.
+    pub fn pending(&self) -> Option<ExceptionInjection> {
+        self.pending.lock().clone()
+    }
+
+    /// Ответ-исключение вместо обычного ответа, если инжекция применима к запросу.
+    pub fn respond(&self, request: &ModbusRequest) -> Option<Vec<u8>> {
+        let mut pending = self.pending.lock();
+        let injection = pending.as_mut()?;
+        if injection
+            .function_code
+            .is_some_and(|code| code != request.function_code)
+        {
+            return None;
+        }
+        let response = ModbusResponse::build_response(
+            request,
+            request.function_code | 0x80,
+            &[injection.exception_code],
+        );
+        if let Some(remaining) = injection.remaining.as_mut() {
+            *remaining -= 1;
+            if *remaining == 0 {
+                *pending = None;
+            }
+        }
+        Some(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gateway_exception_for_matching_function() {
+        let read = [
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let write = [
+            0x00, 0x08, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let read = ModbusRequest::parse(&read).unwrap();
+        let write = ModbusRequest::parse(&write).unwrap();
+
+        let injector = ExceptionInjector::default();
+        injector
+            .schedule(ExceptionInjection {
+                exception_code: 0x0B,
+                function_code: Some(0x03),
+                remaining: Some(1),
+            })
+            .unwrap();
+        assert_eq!(injector.respond(&write), None);
+        assert_eq!(
+            injector.respond(&read),
+            Some(vec![0x00, 0x07, 0x00, 0x00, 0x00, 0x03, 0x01, 0x83, 0x0B])
+        );
+        assert_eq!(injector.respond(&read), None);
+        assert_eq!(injector.pending(), None);
+    }
+}
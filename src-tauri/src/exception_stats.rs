@@ -0,0 +1,232 @@
+//! Статистика исключений Modbus по диапазонам адресов.
+//!
+//! Для каждой комбинации «функция + начальный адрес + количество + код исключения»
+//! считается число ответов с исключением. Отчёт позволяет быстро увидеть, какая
+//! часть плана опроса мастера не совпадает с картой регистров.
+//!
+//! Кроме того, раз в 10 секунд UI получает событие тренда: сколько исключений
+//! каждого кода по каждой функции было за окно и сколько всего запросов
+//! обработано. Этого достаточно для графика доли ошибок без разбора журнала.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+
+use crate::modbus_protocol::ModbusRequest;
+use crate::runtime_counters::RuntimeCounters;
+use crate::types::{chrono_now_iso, exception_code_name, function_code_name};
+
+/// Событие тренда исключений.
+pub const TREND_EVENT_NAME: &str = "exception-trend";
+
+/// Окно агрегации тренда.
+const TREND_WINDOW: Duration = Duration::from_secs(10);
+
+/// Строка отчёта об исключениях.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExceptionStatEntry {
+    pub function_code: u8,
+    pub function_name: String,
+    pub start_address: u16,
+    pub quantity: u16,
+    pub exception_code: u8,
+    pub exception_name: String,
+    pub count: u64,
+    pub last_client: String,
+    pub last_seen: String,
+}
+
+/// Число исключений одного кода по одной функции за окно тренда.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExceptionTrendEntry {
+    pub function_code: u8,
+    pub function_name: String,
+    pub exception_code: u8,
+    pub exception_name: String,
+    pub count: u64,
+}
+
+/// Событие тренда: итоги одного окна.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExceptionTrend {
+    /// Конец окна.
+    pub window_end: String,
+    pub window_ms: u64,
+    /// Запросов мастера за окно.
+    pub requests: u64,
+    /// Ответов с исключением за окно.
+    pub exceptions: u64,
+    /// По функциям и кодам исключений; пустой, если исключений не было.
+    pub entries: Vec<ExceptionTrendEntry>,
+}
+
+/// Ключ статистики: функция, начальный адрес, количество, код исключения.
+type StatKey = (u8, u16, u16, u8);
+
+/// Накопитель статистики исключений.
+#[derive(Debug, Default)]
+pub struct ExceptionStats {
+    entries: Mutex<HashMap<StatKey, ExceptionStatEntry>>,
+    /// Текущее окно тренда: (функция, код исключения) → число.
+    window: Mutex<BTreeMap<(u8, u8), u64>>,
+}
+
+impl ExceptionStats {
+    /// Учесть ответ с исключением на запрос.
+    pub fn record(&self, request: &ModbusRequest, exception_code: u8, client_addr: &str) {
+        let (start_address, quantity) = request.address_range().unwrap_or((0, 0));
+        let key = (
+            request.function_code,
+            start_address,
+            quantity,
+            exception_code,
+        );
+
+        let mut entries = self.entries.lock();
+        let entry = entries.entry(key).or_insert_with(|| ExceptionStatEntry {
+            function_code: request.function_code,
+            function_name: function_code_name(request.function_code).to_string(),
+            start_address,
+            quantity,
+            exception_code,
+            exception_name: exception_code_name(exception_code).to_string(),
+            count: 0,
+            last_client: String::new(),
+            last_seen: String::new(),
+        });
+        entry.count += 1;
+        entry.last_client = client_addr.to_string();
+        entry.last_seen = chrono_now_iso();
+        drop(entries);
+
+        *self
+            .window
+            .lock()
+            .entry((request.function_code, exception_code))
+            .or_default() += 1;
+    }
+
+    /// Забрать итоги окна тренда и начать новое.
+    pub fn take_window(&self) -> Vec<ExceptionTrendEntry> {
+        std::mem::take(&mut *self.window.lock())
+            .into_iter()
+            .map(
+                |((function_code, exception_code), count)| ExceptionTrendEntry {
+                    function_code,
+                    function_name: function_code_name(function_code).to_string(),
+                    exception_code,
+                    exception_name: exception_code_name(exception_code).to_string(),
+                    count,
+                },
+            )
+            .collect()
+    }
+
+    /// Отчёт: самые частые исключения первыми.
+    pub fn report(&self) -> Vec<ExceptionStatEntry> {
+        let mut report: Vec<_> = self.entries.lock().values().cloned().collect();
+        report.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.function_code.cmp(&b.function_code))
+                .then(a.start_address.cmp(&b.start_address))
+        });
+        report
+    }
+
+    /// Сбросить статистику.
+    pub fn reset(&self) {
+        self.entries.lock().clear();
+        self.window.lock().clear();
+    }
+}
+
+/// Отправлять тренд исключений раз в окно до сигнала завершения.
+/// Окна без исключений тоже отправляются, чтобы график не прерывался.
+pub async fn publish_trend(
+    app_handle: AppHandle,
+    stats: SharedExceptionStats,
+    counters: Arc<RuntimeCounters>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut tick =
+        tokio::time::interval_at(tokio::time::Instant::now() + TREND_WINDOW, TREND_WINDOW);
+    let mut last_requests = counters.requests();
+    stats.take_window();
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                let requests = counters.requests();
+                let entries = stats.take_window();
+                let trend = ExceptionTrend {
+                    window_end: chrono_now_iso(),
+                    window_ms: TREND_WINDOW.as_millis() as u64,
+                    requests: requests.saturating_sub(last_requests),
+                    exceptions: entries.iter().map(|e| e.count).sum(),
+                    entries,
+                };
+                last_requests = requests;
+                if let Err(e) = app_handle.emit(TREND_EVENT_NAME, trend) {
+                    log::warn!("Не удалось отправить тренд исключений: {}", e);
+                }
+            }
+            _ = shutdown_rx.recv() => break,
+        }
+    }
+}
+
+/// Общая ссылка на статистику исключений.
+pub type SharedExceptionStats = Arc<ExceptionStats>;
+
+/// Создать общую статистику исключений.
+pub fn create_shared_exception_stats() -> SharedExceptionStats {
+    Arc::new(ExceptionStats::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Собрать запрос чтения holding registers.
+    fn read_request(start: u16, quantity: u16) -> ModbusRequest {
+        let mut frame = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03];
+        frame.extend_from_slice(&start.to_be_bytes());
+        frame.extend_from_slice(&quantity.to_be_bytes());
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_exception_stats_groups_by_range() {
+        let stats = ExceptionStats::default();
+        stats.record(&read_request(100, 10), 0x02, "10.0.0.1:5000");
+        stats.record(&read_request(100, 10), 0x02, "10.0.0.2:5000");
+        stats.record(&read_request(0, 200), 0x03, "10.0.0.1:5000");
+
+        let report = stats.report();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].start_address, 100);
+        assert_eq!(report[0].quantity, 10);
+        assert_eq!(report[0].count, 2);
+        assert_eq!(report[0].exception_name, "Illegal Data Address");
+        assert_eq!(report[0].last_client, "10.0.0.2:5000");
+
+        let trend = stats.take_window();
+        assert_eq!(trend.len(), 2);
+        assert_eq!((trend[0].exception_code, trend[0].count), (0x02, 2));
+        assert_eq!(trend[1].exception_name, "Illegal Data Value");
+        assert!(stats.take_window().is_empty());
+        // Окно тренда не влияет на накопленный отчёт
+        assert_eq!(stats.report().len(), 2);
+
+        stats.reset();
+        assert!(stats.report().is_empty());
+    }
+}
@@ -0,0 +1,516 @@
+//! Простые выражения над переменными проекта.
+//!
+//! Используются в условиях тревог и других поведениях симуляции.
+//! Поддерживаются числа, `true`/`false`, ссылки на переменные (по имени или ID),
+//! арифметика `+ - * / %`, сравнения `< <= > >= == !=`, логика `&& || !`,
+//! скобки и функции `abs`, `min`, `max`.
+//!
+//! Для упакованных слов состояния есть целочисленные литералы `0x00FF` и
+//! `0b1010` и побитовые операции `& | ^ << >> ~` над целой частью значений;
+//! они связывают сильнее сравнений: `HR[100] & 0x0F == 3`.
+//!
+//! Имена с пробелами и прочими символами записываются в квадратных скобках:
+//! `[Давление насоса] > 5.5`. Имя, за которым без пробела следуют скобки,
+//! образует одно имя: `HR[100]` — так вызывающий адресует сырые ячейки.
+//! Логические значения — 1.0 (истина) и 0.0 (ложь).
+
+use std::fmt;
+
+/// Унарная операция.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+/// Бинарная операция.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+/// Разобранное выражение.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Var(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    /// Разобрать выражение из строки.
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("Неожиданный символ '{}' в выражении", token)),
+        }
+    }
+
+    /// Вычислить выражение. `resolve` возвращает значение переменной по имени.
+    pub fn eval(&self, resolve: &dyn Fn(&str) -> Option<f64>) -> Result<f64, String> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Var(name) => {
+                resolve(name).ok_or_else(|| format!("Неизвестная переменная '{}'", name))?
+            }
+            Expr::Unary(op, arg) => {
+                let v = arg.eval(resolve)?;
+                match op {
+                    UnaryOp::Neg => -v,
+                    UnaryOp::Not => bool_to_f64(v == 0.0),
+                    UnaryOp::BitNot => !(v as i64) as f64,
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let a = lhs.eval(resolve)?;
+                // Логические операции вычисляются по короткой схеме
+                match op {
+                    BinaryOp::And if a == 0.0 => return Ok(0.0),
+                    BinaryOp::Or if a != 0.0 => return Ok(1.0),
+                    _ => {}
+                }
+                let b = rhs.eval(resolve)?;
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Rem => a % b,
+                    BinaryOp::Lt => bool_to_f64(a < b),
+                    BinaryOp::Le => bool_to_f64(a <= b),
+                    BinaryOp::Gt => bool_to_f64(a > b),
+                    BinaryOp::Ge => bool_to_f64(a >= b),
+                    BinaryOp::Eq => bool_to_f64(a == b),
+                    BinaryOp::Ne => bool_to_f64(a != b),
+                    BinaryOp::And | BinaryOp::Or => bool_to_f64(b != 0.0),
+                    BinaryOp::BitAnd => ((a as i64) & (b as i64)) as f64,
+                    BinaryOp::BitOr => ((a as i64) | (b as i64)) as f64,
+                    BinaryOp::BitXor => ((a as i64) ^ (b as i64)) as f64,
+                    BinaryOp::Shl => (a as i64).wrapping_shl(b as u32) as f64,
+                    BinaryOp::Shr => (a as i64).wrapping_shr(b as u32) as f64,
+                }
+            }
+            Expr::Call(name, args) => {
+                let values = args
+                    .iter()
+                    .map(|a| a.eval(resolve))
+                    .collect::<Result<Vec<f64>, String>>()?;
+                call_function(name, &values)?
+            }
+        })
+    }
+
+    /// Вычислить выражение как условие (ненулевое значение — истина).
+    pub fn eval_bool(&self, resolve: &dyn Fn(&str) -> Option<f64>) -> Result<bool, String> {
+        self.eval(resolve).map(|v| v != 0.0)
+    }
+}
+
+fn bool_to_f64(b: bool) -> f64 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn call_function(name: &str, args: &[f64]) -> Result<f64, String> {
+    match (name, args) {
+        ("abs", [x]) => Ok(x.abs()),
+        ("min", [first, rest @ ..]) => Ok(rest.iter().fold(*first, |m, v| m.min(*v))),
+        ("max", [first, rest @ ..]) => Ok(rest.iter().fold(*first, |m, v| m.max(*v))),
+        ("abs" | "min" | "max", _) => Err(format!(
+            "Неверное число аргументов функции '{}': {}",
+            name,
+            args.len()
+        )),
+        _ => Err(format!("Неизвестная функция '{}'", name)),
+    }
+}
+
+// ========== Лексический разбор ==========
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Ident(s) => write!(f, "{}", s),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+/// Операторы; двухсимвольные проверяются раньше односимвольных.
+const OPERATORS: [&str; 21] = [
+    "<=", ">=", "==", "!=", "&&", "||", "<<", ">>", "<", ">", "+", "-", "*", "/", "%", "!", "=",
+    "&", "|", "^", "~",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '0' && matches!(chars.get(i + 1), Some('x' | 'X' | 'b' | 'B')) {
+            let radix = if matches!(chars[i + 1], 'x' | 'X') {
+                16
+            } else {
+                2
+            };
+            let start = i + 2;
+            i = start;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().filter(|&&ch| ch != '_').collect();
+            let value = u64::from_str_radix(&text, radix).map_err(|_| {
+                format!(
+                    "Некорректное число '{}'",
+                    chars[start - 2..i].iter().collect::<String>()
+                )
+            })?;
+            tokens.push(Token::Number(value as f64));
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse::<f64>()
+                .map_err(|_| format!("Некорректное число '{}'", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            let mut name: String = chars[start..i].iter().collect();
+            // Скобки вплотную к имени — часть имени (HR[100])
+            if chars.get(i) == Some(&'[') {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == ']')
+                    .ok_or("Не закрыта квадратная скобка в выражении")?;
+                let index: String = chars[i + 1..i + 1 + end].iter().collect();
+                name = format!("{}[{}]", name, index.trim());
+                i += end + 2;
+            }
+            tokens.push(Token::Ident(name));
+        } else if c == '[' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&ch| ch == ']')
+                .ok_or("Не закрыта квадратная скобка в выражении")?;
+            let name: String = chars[i + 1..i + 1 + end].iter().collect();
+            tokens.push(Token::Ident(name.trim().to_string()));
+            i += end + 2;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| format!("Недопустимый символ '{}' в выражении", c))?;
+            // Одиночное '=' трактуется как сравнение
+            tokens.push(Token::Op(if *op == "=" { "==" } else { op }));
+            i += op.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+// ========== Синтаксический разбор ==========
+
+/// Предельная вложенность скобок и унарных операторов: защищает разбор
+/// и вычисление от переполнения стека.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Текущая вложенность `parse_unary`.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        if let Some(Token::Op(op)) = self.peek() {
+            if let Some((_, binary)) = ops.iter().find(|(s, _)| s == op) {
+                self.pos += 1;
+                return Some(*binary);
+            }
+        }
+        None
+    }
+
+    fn parse_binary(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        next: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut lhs = next(self)?;
+        while let Some(op) = self.eat_op(ops) {
+            let rhs = next(self)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&[("||", BinaryOp::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&[("&&", BinaryOp::And)], Self::parse_cmp)
+    }
+
+    fn parse_cmp(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[
+                ("<", BinaryOp::Lt),
+                ("<=", BinaryOp::Le),
+                (">", BinaryOp::Gt),
+                (">=", BinaryOp::Ge),
+                ("==", BinaryOp::Eq),
+                ("!=", BinaryOp::Ne),
+            ],
+            Self::parse_bit_or,
+        )
+    }
+
+    fn parse_bit_or(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&[("|", BinaryOp::BitOr)], Self::parse_bit_xor)
+    }
+
+    fn parse_bit_xor(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&[("^", BinaryOp::BitXor)], Self::parse_bit_and)
+    }
+
+    fn parse_bit_and(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&[("&", BinaryOp::BitAnd)], Self::parse_shift)
+    }
+
+    fn parse_shift(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[("<<", BinaryOp::Shl), (">>", BinaryOp::Shr)],
+            Self::parse_add,
+        )
+    }
+
+    fn parse_add(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            Self::parse_mul,
+        )
+    }
+
+    fn parse_mul(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("Слишком глубокая вложенность выражения".to_string());
+        }
+        self.depth += 1;
+        let expr = self.parse_prefixed();
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_prefixed(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(Expr::Unary(UnaryOp::Neg, Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(Expr::Unary(UnaryOp::Not, Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Op("~")) => {
+                self.pos += 1;
+                Ok(Expr::Unary(UnaryOp::BitNot, Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) if name == "true" => Ok(Expr::Number(1.0)),
+            Some(Token::Ident(name)) if name == "false" => Ok(Expr::Number(0.0)),
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Expr::Var(name));
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(self.parse_or()?);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect_rparen()?;
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                self.expect_rparen()?;
+                Ok(expr)
+            }
+            Some(token) => Err(format!("Неожиданный символ '{}' в выражении", token)),
+            None => Err("Неожиданный конец выражения".to_string()),
+        }
+    }
+
+    fn expect_rparen(&mut self) -> Result<(), String> {
+        match self.next() {
+            Some(Token::RParen) => Ok(()),
+            _ => Err("Ожидается ')' в выражении".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> f64 {
+        let resolve = |name: &str| match name {
+            "temp" => Some(80.0),
+            "Давление насоса" => Some(2.5),
+            "run" => Some(1.0),
+            "HR[100]" => Some(0x1234 as f64),
+            _ => None,
+        };
+        Expr::parse(source).unwrap().eval(&resolve).unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic_and_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("-temp / 4 + 10 % 3"), -19.0);
+        assert_eq!(eval("max(1, temp, 5) - abs(-2)"), 78.0);
+    }
+
+    #[test]
+    fn test_conditions_with_variables() {
+        assert_eq!(eval("temp > 75 && run"), 1.0);
+        assert_eq!(eval("temp >= 90 || [Давление насоса] < 2"), 0.0);
+        assert_eq!(eval("!run = false"), 1.0);
+    }
+
+    #[test]
+    fn test_bitwise_on_raw_words() {
+        assert_eq!(eval("HR[100] & 0x00FF"), 0x34 as f64);
+        assert_eq!(eval("HR[100] >> 8 | 0b1"), 0x13 as f64);
+        assert_eq!(eval("HR[100] & 0x0F == 4"), 1.0);
+        assert_eq!(eval("~0 & 0xFFFF ^ 0x00FF"), 0xFF00 as f64);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("(1 + 2").is_err());
+        assert!(Expr::parse("temp # 2").is_err());
+        assert!(Expr::parse("[unterminated").is_err());
+
+        let expr = Expr::parse("unknown > 1").unwrap();
+        assert!(expr.eval(&|_| None).is_err());
+        assert!(Expr::parse("sqrt(4)").unwrap().eval(&|_| None).is_err());
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_DEPTH - 1)), 1.0);
+        assert_eq!(
+            Expr::parse(&nested(100_000)).unwrap_err(),
+            "Слишком глубокая вложенность выражения"
+        );
+        assert!(Expr::parse(&"-".repeat(100_000)).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! Именованные правила инжекции сбоев.
+//!
+//! Правило задерживает ответы, не отправляет их или заменяет исключением —
+//! для всех запросов или одной функции, для каждого запроса или заданной доли.
+//! Правила включаются и выключаются по имени (шагами сценария стенда), поэтому
+//! последовательность вроде «через 5 минут терять 10% ответов в течение минуты»
+//! воспроизводится без ручных действий. Доля отбирается генератором с зерном
+//! проекта, так что при том же зерне теряются те же ответы.
+//!
+//! Группы правил сохраняются в проекте как именованные пресеты («нестабильная
+//! сеть», «медленное устройство») и включаются одной командой или шагом
+//! сценария `activateFaultPreset`.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::modbus_protocol::{ModbusRequest, ModbusResponse};
+use crate::seeded_rng::ProjectRng;
+
+/// Вид сбоя.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FaultKind {
+    /// Задержать ответ.
+    Delay { ms: u64 },
+    /// Обработать запрос, но не отправлять ответ.
+    Drop,
+    /// Ответить исключением с заданным кодом.
+    #[serde(rename_all = "camelCase")]
+    Exception { exception_code: u8 },
+}
+
+/// Правило сбоя.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultRule {
+    #[serde(flatten)]
+    pub kind: FaultKind,
+    /// Только для запросов с этим кодом функции; `None` — для всех.
+    #[serde(default)]
+    pub function_code: Option<u8>,
+    /// Доля затронутых запросов, проценты.
+    #[serde(default = "default_percent")]
+    pub percent: u8,
+}
+
+fn default_percent() -> u8 {
+    100
+}
+
+/// Именованная группа правил, сохраняемая в проекте.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultPreset {
+    pub name: String,
+    /// Правила по именам; при включении пресета правила с теми же именами
+    /// заменяются.
+    #[serde(default)]
+    pub rules: BTreeMap<String, FaultRule>,
+}
+
+fn validate_rule(name: &str, rule: &FaultRule) -> Result<(), String> {
+    if rule.percent == 0 || rule.percent > 100 {
+        return Err(format!(
+            "Доля запросов правила '{}' должна быть от 1 до 100%",
+            name
+        ));
+    }
+    if rule.kind == (FaultKind::Exception { exception_code: 0 }) {
+        return Err("Код исключения 0 не допускается".to_string());
+    }
+    Ok(())
+}
+
+/// Действие включённых правил над одним запросом.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaultEffect {
+    /// Суммарная задержка ответа.
+    pub delay: Duration,
+    /// Правило, по которому ответ не отправляется.
+    pub drop: Option<String>,
+    /// Ответ-исключение вместо обычного ответа.
+    pub exception: Option<Vec<u8>>,
+}
+
+/// Включённые правила сервера.
+#[derive(Debug, Default)]
+pub struct FaultRules {
+    rules: RwLock<BTreeMap<String, FaultRule>>,
+    presets: RwLock<Vec<FaultPreset>>,
+}
+
+impl FaultRules {
+    /// Включить правило. Правило с тем же именем заменяется.
+    pub fn arm(&self, name: &str, rule: FaultRule) -> Result<(), String> {
+        validate_rule(name, &rule)?;
+        log::info!("Правило сбоя '{}' включено: {:?}", name, rule);
+        self.rules.write().insert(name.to_string(), rule);
+        Ok(())
+    }
+
+    /// Выключить правило. Возвращает false, если оно не было включено.
+    pub fn disarm(&self, name: &str) -> bool {
+        let removed = self.rules.write().remove(name).is_some();
+        if removed {
+            log::info!("Правило сбоя '{}' выключено", name);
+        }
+        removed
+    }
+
+    /// Включённые правила.
+    pub fn armed(&self) -> BTreeMap<String, FaultRule> {
+        self.rules.read().clone()
+    }
+
+    /// Пресеты проекта.
+    pub fn presets(&self) -> Vec<FaultPreset> {
+        self.presets.read().clone()
+    }
+
+    /// Заменить пресеты (при загрузке проекта). Включённые правила не меняются.
+    pub fn set_presets(&self, presets: Vec<FaultPreset>) {
+        *self.presets.write() = presets;
+    }
+
+    /// Добавить пресет или заменить пресет с тем же именем.
+    pub fn save_preset(&self, preset: FaultPreset) -> Result<(), String> {
+        if preset.name.trim().is_empty() {
+            return Err("Имя пресета сбоев не задано".to_string());
+        }
+        for (name, rule) in &preset.rules {
+            validate_rule(name, rule)?;
+        }
+        let mut presets = self.presets.write();
+        match presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+        Ok(())
+    }
+
+    /// Добавить несколько пресетов (например, из библиотеки возмущений).
+    /// При ошибке в любом пресете ни один не добавляется.
+    pub fn save_presets(&self, presets: Vec<FaultPreset>) -> Result<(), String> {
+        for preset in &presets {
+            if preset.name.trim().is_empty() {
+                return Err("Имя пресета сбоев не задано".to_string());
+            }
+            for (name, rule) in &preset.rules {
+                validate_rule(name, rule)?;
+            }
+        }
+        for preset in presets {
+            self.save_preset(preset)?;
+        }
+        Ok(())
+    }
+
+    /// Удалить пресет. Его включённые правила остаются включёнными.
+    pub fn remove_preset(&self, name: &str) -> bool {
+        let mut presets = self.presets.write();
+        let before = presets.len();
+        presets.retain(|p| p.name != name);
+        presets.len() != before
+    }
+
+    fn preset(&self, name: &str) -> Result<FaultPreset, String> {
+        self.presets
+            .read()
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .ok_or_else(|| format!("Пресет сбоев '{}' не найден", name))
+    }
+
+    /// Включить все правила пресета.
+    pub fn activate_preset(&self, name: &str) -> Result<(), String> {
+        let preset = self.preset(name)?;
+        for (rule_name, rule) in &preset.rules {
+            validate_rule(rule_name, rule)?;
+        }
+        self.rules.write().extend(preset.rules);
+        log::info!("Пресет сбоев '{}' включён", name);
+        Ok(())
+    }
+
+    /// Выключить правила пресета, включённые под теми же именами.
+    pub fn deactivate_preset(&self, name: &str) -> Result<(), String> {
+        let preset = self.preset(name)?;
+        let mut rules = self.rules.write();
+        for rule_name in preset.rules.keys() {
+            rules.remove(rule_name);
+        }
+        log::info!("Пресет сбоев '{}' выключен", name);
+        Ok(())
+    }
+
+    /// Что сделать с ответом на запрос. Правила перебираются по именам;
+    /// задержки складываются, исключение берётся из первого сработавшего.
+    pub fn effect(&self, request: &ModbusRequest, rng: &ProjectRng) -> FaultEffect {
+        let mut effect = FaultEffect::default();
+        for (name, rule) in self.rules.read().iter() {
+            if rule
+                .function_code
+                .is_some_and(|code| code != request.function_code)
+            {
+                continue;
+            }
+            if rule.percent < 100 && rng.up_to(99) >= rule.percent as u64 {
+                continue;
+            }
+            match rule.kind {
+                FaultKind::Delay { ms } => effect.delay += Duration::from_millis(ms),
+                FaultKind::Drop => {
+                    effect.drop.get_or_insert_with(|| name.clone());
+                }
+                FaultKind::Exception { exception_code } => {
+                    effect.exception.get_or_insert_with(|| {
+                        ModbusResponse::build_response(
+                            request,
+                            request.function_code | 0x80,
+                            &[exception_code],
+                        )
+                    });
+                }
+            }
+        }
+        effect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_combine_and_sample() {
+        let read = [
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let read = ModbusRequest::parse(&read).unwrap();
+        let rng = ProjectRng::with_seed(7);
+        let rules = FaultRules::default();
+
+        let rule: FaultRule = serde_json::from_str(r#"{"kind": "delay", "ms": 200}"#).unwrap();
+        rules.arm("slow", rule).unwrap();
+        rules
+            .arm(
+                "busy",
+                FaultRule {
+                    kind: FaultKind::Exception { exception_code: 6 },
+                    function_code: Some(0x06),
+                    percent: 100,
+                },
+            )
+            .unwrap();
+        let effect = rules.effect(&read, &rng);
+        assert_eq!(effect.delay, Duration::from_millis(200));
+        assert_eq!(effect.exception, None);
+
+        let lossy = FaultRule {
+            kind: FaultKind::Drop,
+            function_code: None,
+            percent: 10,
+        };
+        rules.arm("lossy", lossy.clone()).unwrap();
+        let dropped = (0..1000)
+            .filter(|_| rules.effect(&read, &rng).drop.is_some())
+            .count();
+        assert!((50..150).contains(&dropped), "{}", dropped);
+
+        assert!(rules.disarm("lossy"));
+        assert!(!rules.disarm("lossy"));
+        assert!(rules
+            .arm(
+                "bad",
+                FaultRule {
+                    percent: 0,
+                    ..lossy
+                }
+            )
+            .is_err());
+        assert_eq!(rules.rules.read().len(), 2);
+    }
+
+    #[test]
+    fn test_presets_arm_and_disarm_their_rules() {
+        let preset: FaultPreset = serde_json::from_str(
+            r#"{"name": "flaky network", "rules": {
+                "loss": {"kind": "drop", "percent": 5},
+                "lag": {"kind": "delay", "ms": 300}
+            }}"#,
+        )
+        .unwrap();
+        let rules = FaultRules::default();
+        rules.save_preset(preset.clone()).unwrap();
+        rules
+            .arm(
+                "busy",
+                FaultRule {
+                    kind: FaultKind::Exception { exception_code: 6 },
+                    function_code: None,
+                    percent: 100,
+                },
+            )
+            .unwrap();
+
+        rules.activate_preset("flaky network").unwrap();
+        let armed: Vec<String> = rules.armed().into_keys().collect();
+        assert_eq!(armed, ["busy", "lag", "loss"]);
+        rules.deactivate_preset("flaky network").unwrap();
+        assert_eq!(rules.armed().len(), 1);
+        assert!(rules.activate_preset("slow device").is_err());
+
+        let mut invalid = preset;
+        invalid.rules.get_mut("loss").unwrap().percent = 0;
+        assert!(rules.save_preset(invalid).is_err());
+        assert!(rules.remove_preset("flaky network"));
+        assert!(rules.presets().is_empty());
+    }
+}
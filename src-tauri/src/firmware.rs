@@ -0,0 +1,167 @@
+//! Уровни прошивки устройства.
+//!
+//! Разные версии прошивки одного устройства отличаются набором функций,
+//! картой адресов и особенностями ответов. Профиль описывает такие уровни,
+//! а одна настройка `firmware` выбирает активный — при проверке совместимости
+//! достаточно переключить её, чтобы сервер вёл себя как прошивка A или B.
+//!
+//! Уровень может:
+//! - не поддерживать часть функций (ответ — исключение Illegal Function);
+//! - ограничить карту адресов областей диапазонами (вне их — Illegal Data Address);
+//! - добавить подменённые ответы и заменить искажение счётчика байт и ответ
+//!   на чтение 0 элементов из профиля.
+
+use serde::{Deserialize, Serialize};
+
+use crate::byte_count_stress::ByteCountStress;
+use crate::modbus_protocol::{ExceptionCode, FunctionCode, ModbusRequest, ModbusResponse};
+use crate::response_override::ResponseOverride;
+use crate::types::ModbusArea;
+use crate::zero_quantity::ZeroQuantityReads;
+
+/// Диапазон адресов области, который обслуживает прошивка (включительно).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirmwareRange {
+    pub area: ModbusArea,
+    pub start: u16,
+    pub end: u16,
+}
+
+/// Уровень прошивки в профиле подключения.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirmwareLevel {
+    pub name: String,
+    /// Коды функций, которых нет в этой прошивке.
+    #[serde(default)]
+    pub unsupported_functions: Vec<u8>,
+    /// Карта адресов; области без диапазонов не ограничиваются.
+    #[serde(default)]
+    pub ranges: Vec<FirmwareRange>,
+    /// Подменённые ответы в дополнение к профилю.
+    #[serde(default)]
+    pub response_overrides: Vec<ResponseOverride>,
+    /// Искажение счётчика байт вместо заданного в профиле.
+    #[serde(default)]
+    pub byte_count_stress: Option<ByteCountStress>,
+    /// Ответ на чтение 0 элементов вместо заданного в профиле.
+    #[serde(default)]
+    pub zero_quantity_reads: Option<ZeroQuantityReads>,
+}
+
+/// Найти выбранный уровень. `None` — прошивка не выбрана.
+pub fn select<'a>(
+    levels: &'a [FirmwareLevel],
+    name: Option<&str>,
+) -> Result<Option<&'a FirmwareLevel>, String> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    levels
+        .iter()
+        .find(|level| level.name == name)
+        .map(Some)
+        .ok_or_else(|| format!("Уровень прошивки '{}' не описан в профиле", name))
+}
+
+/// Область, к которой обращается функция; `None` для файловых записей.
+fn function_area(function: FunctionCode) -> Option<ModbusArea> {
+    match function {
+        FunctionCode::ReadCoils
+        | FunctionCode::WriteSingleCoil
+        | FunctionCode::WriteMultipleCoils => Some(ModbusArea::Coil),
+        FunctionCode::ReadDiscreteInputs => Some(ModbusArea::DiscreteInput),
+        FunctionCode::ReadInputRegisters => Some(ModbusArea::InputRegister),
+        FunctionCode::ReadHoldingRegisters
+        | FunctionCode::WriteSingleRegister
+        | FunctionCode::WriteMultipleRegisters
+        | FunctionCode::MaskWriteRegister => Some(ModbusArea::HoldingRegister),
+        FunctionCode::ReadFileRecord | FunctionCode::WriteFileRecord => None,
+    }
+}
+
+impl FirmwareLevel {
+    /// Ответ-исключение, если функция или адреса запроса недоступны в прошивке.
+    pub fn respond(&self, request: &ModbusRequest) -> Option<Vec<u8>> {
+        let exception = |code: ExceptionCode| {
+            Some(ModbusResponse::build_exception(
+                request,
+                request.function_code,
+                code,
+            ))
+        };
+        if self.unsupported_functions.contains(&request.function_code) {
+            return exception(ExceptionCode::IllegalFunction);
+        }
+        let area = function_area(FunctionCode::from_u8(request.function_code)?)?;
+        let (start, quantity) = request.address_range()?;
+        let mut ranges = self.ranges.iter().filter(|r| r.area == area).peekable();
+        // Области без диапазонов не ограничиваются
+        ranges.peek()?;
+        let last = start as u32 + quantity.max(1) as u32 - 1;
+        let inside = ranges.any(|r| r.start <= start && last <= r.end as u32);
+        if inside {
+            None
+        } else {
+            exception(ExceptionCode::IllegalDataAddress)
+        }
+    }
+
+    /// Дополнить настройки профиля особенностями прошивки.
+    pub fn apply_quirks(
+        &self,
+        response_overrides: &mut Vec<ResponseOverride>,
+        byte_count_stress: &mut ByteCountStress,
+        zero_quantity_reads: &mut ZeroQuantityReads,
+    ) {
+        response_overrides.extend(self.response_overrides.iter().cloned());
+        if let Some(stress) = self.byte_count_stress {
+            *byte_count_stress = stress;
+        }
+        if let Some(reads) = &self.zero_quantity_reads {
+            *zero_quantity_reads = reads.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(function_code: u8, address: u16, quantity: u16) -> ModbusRequest {
+        let mut frame = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, function_code];
+        frame.extend_from_slice(&address.to_be_bytes());
+        frame.extend_from_slice(&quantity.to_be_bytes());
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_level_restricts_functions_and_map() {
+        let levels: Vec<FirmwareLevel> = serde_json::from_str(
+            r#"[
+                {"name": "1.0", "unsupportedFunctions": [16],
+                 "ranges": [{"area": "holding_register", "start": 0, "end": 99}]},
+                {"name": "2.0"}
+            ]"#,
+        )
+        .unwrap();
+        let old = select(&levels, Some("1.0")).unwrap().unwrap();
+        assert_eq!(old.respond(&request(0x03, 90, 10)), None);
+        assert_eq!(
+            old.respond(&request(0x03, 95, 10)).unwrap()[7..],
+            [0x83, 0x02]
+        );
+        // Области без диапазонов не ограничены
+        assert_eq!(old.respond(&request(0x04, 500, 1)), None);
+
+        let mut write = request(0x10, 0, 1);
+        write.data.extend_from_slice(&[2, 0, 1]);
+        assert_eq!(old.respond(&write).unwrap()[7..], [0x90, 0x01]);
+
+        let new = select(&levels, Some("2.0")).unwrap().unwrap();
+        assert_eq!(new.respond(&write), None);
+        assert!(select(&levels, Some("3.0")).is_err());
+        assert_eq!(select(&levels, None), Ok(None));
+    }
+}
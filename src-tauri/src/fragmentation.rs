@@ -0,0 +1,86 @@
+//! Дробление ответов на мелкие TCP-сегменты.
+//!
+//! Ответ отправляется частями по `segment_size` байт с паузой между ними,
+//! а Nagle на сокете отключается, чтобы части не склеивались. Так
+//! проверяется, умеет ли мастер собирать кадр из нескольких чтений, а не
+//! рассчитывает на то, что ответ придёт одним сегментом.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Настройки дробления ответов в профиле подключения.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fragmentation {
+    /// Размер сегмента, байт; 0 — ответ отправляется целиком.
+    #[serde(default)]
+    pub segment_size: usize,
+    /// Пауза между сегментами, мс.
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+impl Fragmentation {
+    /// Включено ли дробление.
+    pub fn is_enabled(&self) -> bool {
+        self.segment_size > 0
+    }
+
+    /// Отправить ответ: целиком или сегментами с паузами.
+    pub async fn write<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        response: &[u8],
+    ) -> std::io::Result<()> {
+        if !self.is_enabled() {
+            return writer.write_all(response).await;
+        }
+        let delay = Duration::from_millis(self.delay_ms);
+        for (index, segment) in response.chunks(self.segment_size).enumerate() {
+            if index > 0 && !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            writer.write_all(segment).await?;
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_fragments_arrive_separately() {
+        tauri::async_runtime::block_on(async {
+            let fragmentation = Fragmentation {
+                segment_size: 3,
+                delay_ms: 5,
+            };
+            let response: Vec<u8> = (0..11).collect();
+            let (mut server, mut client) = tokio::io::duplex(64);
+            let writer = async {
+                fragmentation.write(&mut server, &response).await.unwrap();
+                drop(server);
+            };
+            let reader = async {
+                let mut reads = Vec::new();
+                let mut buffer = [0u8; 64];
+                loop {
+                    let n = client.read(&mut buffer).await.unwrap();
+                    if n == 0 {
+                        return reads;
+                    }
+                    reads.push(buffer[..n].to_vec());
+                }
+            };
+            let ((), reads) = tokio::join!(writer, reader);
+            assert_eq!(reads.concat(), response);
+            assert_eq!(reads.len(), 4);
+        });
+    }
+}
@@ -0,0 +1,43 @@
+//! Высокоточные метки времени кадров.
+//!
+//! Метка записи журнала имеет миллисекундную точность и берётся из системных
+//! часов, которые могут переводиться. Для анализа интервалов между кадрами
+//! (джиттер опроса мастера) принятым и отправленным кадрам ставится метка в
+//! микросекундах по монотонным часам. Монотонные часы один раз привязываются
+//! к системному времени, поэтому метка остаётся временем Unix, а разность
+//! меток двух кадров не скачет при переводе системных часов.
+
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Момент привязки и системное время в этот момент, мкс с эпохи Unix.
+static ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+
+/// Текущее время кадра, мкс с эпохи Unix.
+pub fn now_us() -> u64 {
+    let (instant, wall_us) = ANCHOR.get_or_init(|| {
+        let wall = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        (Instant::now(), wall.as_micros() as u64)
+    });
+    wall_us + instant.elapsed().as_micros() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monotonic_wall_time() {
+        let first = now_us();
+        std::thread::sleep(std::time::Duration::from_micros(300));
+        let second = now_us();
+        assert!(second - first >= 300);
+        let wall = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+        assert!(wall.abs_diff(second) < 1_000_000);
+    }
+}
@@ -0,0 +1,209 @@
+//! Режим шлюза: запись мастера в смешанную карту (симулируемые и реальные адреса).
+//!
+//! Для каждого диапазона coils и holding registers задаётся политика записи:
+//! - `forward` — запрос пересылается на реальное устройство за шлюзом, ответ
+//!   устройства возвращается мастеру; после подтверждения значение записывается
+//!   и в локальную карту (write-through), чтобы чтение видело то же значение;
+//! - `local` — запись обслуживает симулятор;
+//! - `reject` — мастер получает исключение Illegal Data Address.
+//!
+//! Чтение всегда обслуживается из локальной карты. Запрос, задевающий диапазоны
+//! с разными политиками, отклоняется целиком — частичной записи не бывает.
+//! Недоступное устройство даёт исключения 0x0A (Gateway Path Unavailable) и
+//! 0x0B (Gateway Target Device Failed to Respond), как у настоящего шлюза.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::master::{MasterConnection, RequestError};
+use crate::modbus_protocol::{FunctionCode, ModbusRequest};
+use crate::types::ModbusArea;
+
+/// Исключение «путь через шлюз недоступен».
+pub const GATEWAY_PATH_UNAVAILABLE: u8 = 0x0A;
+/// Исключение «устройство за шлюзом не ответило».
+pub const GATEWAY_TARGET_FAILED: u8 = 0x0B;
+
+/// Политика записи для диапазона адресов.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WritePolicy {
+    /// Переслать на устройство и записать локально после подтверждения.
+    Forward,
+    /// Обслужить локально.
+    #[default]
+    Local,
+    /// Отклонить с исключением Illegal Data Address.
+    Reject,
+}
+
+/// Политика записи для диапазона адресов области (включительно).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritePolicyRange {
+    pub area: ModbusArea,
+    pub start: u16,
+    pub end: u16,
+    pub policy: WritePolicy,
+}
+
+/// Настройки шлюза в профиле подключения.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayConfig {
+    /// Адрес устройства за шлюзом.
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Политика для адресов вне диапазонов.
+    #[serde(default)]
+    pub default_policy: WritePolicy,
+    /// Диапазоны; при пересечении действует первый подходящий.
+    #[serde(default)]
+    pub ranges: Vec<WritePolicyRange>,
+}
+
+fn default_port() -> u16 {
+    502
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+impl GatewayConfig {
+    /// Политика для записи `count` ячеек области начиная с `start`.
+    /// Если ячейки попадают под разные политики, запрос отклоняется.
+    pub fn policy_for(&self, area: ModbusArea, start: u16, count: u16) -> WritePolicy {
+        let policy_at = |address: u32| {
+            self.ranges
+                .iter()
+                .find(|range| {
+                    range.area == area
+                        && range.start as u32 <= address
+                        && address <= range.end as u32
+                })
+                .map_or(self.default_policy, |range| range.policy)
+        };
+        let start = start as u32;
+        let first = policy_at(start);
+        if (start + 1..start + count as u32).all(|address| policy_at(address) == first) {
+            first
+        } else {
+            WritePolicy::Reject
+        }
+    }
+}
+
+/// Шлюз сервера: политики записи и соединение с устройством,
+/// общее для всех клиентов.
+pub struct Gateway {
+    config: GatewayConfig,
+    connection: Mutex<Option<MasterConnection>>,
+}
+
+impl Gateway {
+    pub fn new(config: GatewayConfig) -> Self {
+        Self {
+            config,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Политика для запроса записи; `None` для чтения и неизвестных функций.
+    pub fn policy_for_request(&self, request: &ModbusRequest) -> Option<WritePolicy> {
+        let area = match FunctionCode::from_u8(request.function_code)? {
+            FunctionCode::WriteSingleCoil | FunctionCode::WriteMultipleCoils => ModbusArea::Coil,
+            FunctionCode::WriteSingleRegister
+            | FunctionCode::WriteMultipleRegisters
+            | FunctionCode::MaskWriteRegister => ModbusArea::HoldingRegister,
+            _ => return None,
+        };
+        let (start, count) = request.address_range()?;
+        Some(self.config.policy_for(area, start, count))
+    }
+
+    /// Переслать запрос на устройство. Возвращает данные PDU ответа
+    /// или код исключения для мастера.
+    pub async fn forward(&self, request: &ModbusRequest) -> Result<Vec<u8>, u8> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            let connected = MasterConnection::connect(
+                &self.config.host,
+                self.config.port,
+                self.config.unit_id,
+                Duration::from_millis(self.config.timeout_ms),
+            )
+            .await
+            .map_err(|e| {
+                log::warn!("Шлюз: {}", e);
+                GATEWAY_PATH_UNAVAILABLE
+            })?;
+            *connection = Some(connected);
+        }
+        let Some(device) = connection.as_mut() else {
+            return Err(GATEWAY_PATH_UNAVAILABLE);
+        };
+        match device.request(request.function_code, &request.data).await {
+            Ok(data) => Ok(data),
+            Err(RequestError::Exception(code)) => Err(code),
+            Err(RequestError::Transport(e)) => {
+                log::warn!("Шлюз: {}", e);
+                // Соединение переоткрывается при следующей записи
+                *connection = None;
+                Err(GATEWAY_TARGET_FAILED)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_for_mixed_ranges() {
+        let config = GatewayConfig {
+            host: "127.0.0.1".to_string(),
+            port: 502,
+            unit_id: 1,
+            timeout_ms: 1000,
+            default_policy: WritePolicy::Local,
+            ranges: vec![
+                WritePolicyRange {
+                    area: ModbusArea::HoldingRegister,
+                    start: 100,
+                    end: 199,
+                    policy: WritePolicy::Forward,
+                },
+                WritePolicyRange {
+                    area: ModbusArea::HoldingRegister,
+                    start: 200,
+                    end: 200,
+                    policy: WritePolicy::Reject,
+                },
+            ],
+        };
+        let hr = ModbusArea::HoldingRegister;
+        assert_eq!(config.policy_for(hr, 0, 10), WritePolicy::Local);
+        assert_eq!(config.policy_for(hr, 100, 100), WritePolicy::Forward);
+        assert_eq!(config.policy_for(hr, 200, 1), WritePolicy::Reject);
+        assert_eq!(
+            config.policy_for(ModbusArea::Coil, 150, 1),
+            WritePolicy::Local
+        );
+        // Запрос на границе разных политик отклоняется целиком
+        assert_eq!(config.policy_for(hr, 98, 4), WritePolicy::Reject);
+        assert_eq!(config.policy_for(hr, 65535, 1), WritePolicy::Local);
+    }
+}
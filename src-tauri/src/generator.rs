@@ -0,0 +1,215 @@
+//! Поведение «генератор значений».
+//!
+//! Переменная получает значение по форме сигнала: синус, пила, меандр или
+//! случайное значение в диапазоне с заданным периодом смены. Фаза отсчитывается
+//! от первого такта после подключения генератора и сохраняется при изменении
+//! его параметров, поэтому правка амплитуды на ходу не даёт скачка фазы.
+
+use std::f64::consts::TAU;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::seeded_rng::ProjectRng;
+
+/// Форма сигнала генератора.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "waveform", rename_all = "snake_case")]
+pub enum Waveform {
+    /// `offset + amplitude · sin(2π·t / period)`.
+    #[serde(rename_all = "camelCase")]
+    Sine {
+        amplitude: f64,
+        #[serde(default)]
+        offset: f64,
+        period_ms: u64,
+    },
+    /// Пила: линейный рост от `min` до `max` за период.
+    #[serde(rename_all = "camelCase")]
+    Ramp { min: f64, max: f64, period_ms: u64 },
+    /// Меандр: первую половину периода `high`, вторую — `low`.
+    #[serde(rename_all = "camelCase")]
+    Square { low: f64, high: f64, period_ms: u64 },
+    /// Случайное значение в `min..=max`, новое раз в период.
+    #[serde(rename_all = "camelCase")]
+    Random { min: f64, max: f64, period_ms: u64 },
+}
+
+impl Waveform {
+    fn period_ms(&self) -> u64 {
+        match self {
+            Waveform::Sine { period_ms, .. }
+            | Waveform::Ramp { period_ms, .. }
+            | Waveform::Square { period_ms, .. }
+            | Waveform::Random { period_ms, .. } => *period_ms,
+        }
+    }
+}
+
+/// Настройка генератора.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratorConfig {
+    /// ID переменной, которую анимирует генератор.
+    pub target_id: String,
+    #[serde(flatten)]
+    pub waveform: Waveform,
+    /// Минимальный интервал между обновлениями значения, мс; None — каждый такт.
+    #[serde(default)]
+    pub update_interval_ms: Option<u64>,
+}
+
+impl GeneratorConfig {
+    /// Проверить переменную и параметры сигнала.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.target_id.is_empty() {
+            return Err("Не задана переменная генератора".to_string());
+        }
+        if self.waveform.period_ms() == 0 {
+            return Err("Период генератора должен быть больше нуля".to_string());
+        }
+        if self.update_interval_ms == Some(0) {
+            return Err("Интервал обновления генератора должен быть больше нуля".to_string());
+        }
+        match self.waveform {
+            Waveform::Ramp { min, max, .. } | Waveform::Random { min, max, .. } if min > max => {
+                Err(format!("Минимум {} больше максимума {}", min, max))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Состояние генератора переменной для `get_variables`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratorStatus {
+    /// ID поведения генератора.
+    pub behavior_id: String,
+    #[serde(flatten)]
+    pub waveform: Waveform,
+    pub update_interval_ms: Option<u64>,
+    pub paused: bool,
+}
+
+/// Состояние генератора между тактами.
+#[derive(Debug, Default)]
+pub struct GeneratorState {
+    /// Начало отсчёта фазы.
+    started: Option<Instant>,
+    /// Номер периода последнего случайного значения.
+    random_period: Option<u128>,
+    /// Момент последнего обновления значения.
+    last_update: Option<Instant>,
+}
+
+impl GeneratorState {
+    /// Значение сигнала в момент `now`. `None`, пока не истёк интервал
+    /// обновления, а для случайного сигнала — пока не начался новый период.
+    pub fn step(
+        &mut self,
+        config: &GeneratorConfig,
+        now: Instant,
+        rng: &ProjectRng,
+    ) -> Option<f64> {
+        if let (Some(interval_ms), Some(last)) = (config.update_interval_ms, self.last_update) {
+            if now.duration_since(last) < Duration::from_millis(interval_ms) {
+                return None;
+            }
+        }
+        let started = *self.started.get_or_insert(now);
+        let period = Duration::from_millis(config.waveform.period_ms()).as_nanos();
+        let elapsed = now.duration_since(started).as_nanos();
+        let phase = (elapsed % period) as f64 / period as f64;
+
+        let value = match config.waveform {
+            Waveform::Sine {
+                amplitude, offset, ..
+            } => offset + amplitude * (TAU * phase).sin(),
+            Waveform::Ramp { min, max, .. } => min + (max - min) * phase,
+            Waveform::Square { low, high, .. } => {
+                if phase < 0.5 {
+                    high
+                } else {
+                    low
+                }
+            }
+            Waveform::Random { min, max, .. } => {
+                let index = elapsed / period;
+                if self.random_period == Some(index) {
+                    return None;
+                }
+                self.random_period = Some(index);
+                let unit = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+                min + (max - min) * unit
+            }
+        };
+        self.last_update = Some(now);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waveforms() {
+        let rng = ProjectRng::with_seed(1);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let config = |waveform| GeneratorConfig {
+            target_id: "level".to_string(),
+            waveform,
+            update_interval_ms: None,
+        };
+
+        let ramp = config(Waveform::Ramp {
+            min: 0.0,
+            max: 100.0,
+            period_ms: 1000,
+        });
+        let mut state = GeneratorState::default();
+        assert_eq!(state.step(&ramp, at(0), &rng), Some(0.0));
+        assert_eq!(state.step(&ramp, at(250), &rng), Some(25.0));
+        assert_eq!(state.step(&ramp, at(1500), &rng), Some(50.0));
+
+        let square = config(Waveform::Square {
+            low: 0.0,
+            high: 1.0,
+            period_ms: 200,
+        });
+        let mut state = GeneratorState::default();
+        assert_eq!(state.step(&square, at(0), &rng), Some(1.0));
+        assert_eq!(state.step(&square, at(100), &rng), Some(0.0));
+
+        let random = config(Waveform::Random {
+            min: 10.0,
+            max: 20.0,
+            period_ms: 1000,
+        });
+        let mut state = GeneratorState::default();
+        let value = state.step(&random, at(0), &rng).unwrap();
+        assert!((10.0..=20.0).contains(&value));
+        assert_eq!(state.step(&random, at(500), &rng), None);
+        assert!(state.step(&random, at(1000), &rng).is_some());
+
+        assert!(config(Waveform::Random {
+            min: 2.0,
+            max: 1.0,
+            period_ms: 10
+        })
+        .validate()
+        .is_err());
+
+        // Медленное обновление: значение меняется не чаще раза в 500 мс
+        let slow = GeneratorConfig {
+            update_interval_ms: Some(500),
+            ..ramp
+        };
+        let mut state = GeneratorState::default();
+        assert_eq!(state.step(&slow, at(0), &rng), Some(0.0));
+        assert_eq!(state.step(&slow, at(100), &rng), None);
+        assert_eq!(state.step(&slow, at(500), &rng), Some(50.0));
+    }
+}
@@ -0,0 +1,235 @@
+//! Поведение «команда/статус» (handshake).
+//!
+//! Мастер записывает код команды в регистр команды, симулятор переводит
+//! регистр статуса в «Занят», а через заданную задержку — в «Выполнено»
+//! или «Ошибка». Такой обмен типичен для профилей задвижек и приводов.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Настройка поведения команда/статус.
+/// Переменные задаются по ID; значения статуса — коды, записываемые в регистр статуса.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandshakeConfig {
+    /// ID переменной регистра команды.
+    pub command_id: String,
+    /// ID переменной регистра статуса.
+    pub status_id: String,
+    #[serde(default)]
+    pub idle_status: u16,
+    #[serde(default = "default_busy_status")]
+    pub busy_status: u16,
+    #[serde(default = "default_done_status")]
+    pub done_status: u16,
+    #[serde(default = "default_error_status")]
+    pub error_status: u16,
+    /// Время выполнения команды, мс.
+    #[serde(default = "default_delay_ms")]
+    pub delay_ms: u64,
+    /// Коды команд, завершающиеся ошибкой.
+    #[serde(default)]
+    pub error_commands: Vec<u16>,
+    /// Сбрасывать регистр команды в 0 после завершения.
+    #[serde(default)]
+    pub reset_command: bool,
+}
+
+fn default_busy_status() -> u16 {
+    1
+}
+
+fn default_done_status() -> u16 {
+    2
+}
+
+fn default_error_status() -> u16 {
+    3
+}
+
+fn default_delay_ms() -> u64 {
+    1000
+}
+
+impl Default for HandshakeConfig {
+    fn default() -> Self {
+        Self {
+            command_id: String::new(),
+            status_id: String::new(),
+            idle_status: 0,
+            busy_status: default_busy_status(),
+            done_status: default_done_status(),
+            error_status: default_error_status(),
+            delay_ms: default_delay_ms(),
+            error_commands: Vec::new(),
+            reset_command: false,
+        }
+    }
+}
+
+/// Готовый шаблон поведения для UI (ID переменных заполняет пользователь).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandshakeTemplate {
+    pub name: String,
+    pub description: String,
+    pub config: HandshakeConfig,
+}
+
+/// Встроенные шаблоны поведения команда/статус.
+pub fn handshake_templates() -> Vec<HandshakeTemplate> {
+    vec![
+        HandshakeTemplate {
+            name: "generic".to_string(),
+            description: "0 — ожидание, 1 — занят, 2 — выполнено, 3 — ошибка".to_string(),
+            config: HandshakeConfig::default(),
+        },
+        HandshakeTemplate {
+            name: "valve".to_string(),
+            description:
+                "Задвижка: 1 — движение, 2 — в положении, 4 — авария; команда сбрасывается"
+                    .to_string(),
+            config: HandshakeConfig {
+                error_status: 4,
+                delay_ms: 5000,
+                reset_command: true,
+                ..HandshakeConfig::default()
+            },
+        },
+        HandshakeTemplate {
+            name: "drive".to_string(),
+            description: "Привод: 0x0001 — разгон, 0x0002 — готов, 0x0008 — авария".to_string(),
+            config: HandshakeConfig {
+                busy_status: 0x0001,
+                done_status: 0x0002,
+                error_status: 0x0008,
+                delay_ms: 2000,
+                ..HandshakeConfig::default()
+            },
+        },
+    ]
+}
+
+/// Результат шага поведения: что записать в переменные.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandshakeOutput {
+    pub status: u16,
+    pub reset_command: bool,
+}
+
+/// Состояние выполнения поведения команда/статус.
+#[derive(Debug, Default)]
+pub struct HandshakeState {
+    /// Последнее увиденное значение регистра команды.
+    last_command: u16,
+    /// Выполняемая команда и момент её получения.
+    busy: Option<(u16, Instant)>,
+}
+
+impl HandshakeState {
+    /// Обработать текущее значение регистра команды.
+    /// Новая команда — переход регистра команды к ненулевому значению, отличному от прежнего.
+    /// Сброс команды мастером в 0 возвращает статус в состояние ожидания.
+    pub fn step(
+        &mut self,
+        config: &HandshakeConfig,
+        command: u16,
+        now: Instant,
+    ) -> Option<HandshakeOutput> {
+        let previous = std::mem::replace(&mut self.last_command, command);
+
+        if let Some((running, since)) = self.busy {
+            if now.duration_since(since) < Duration::from_millis(config.delay_ms) {
+                return None;
+            }
+            self.busy = None;
+            let status = if config.error_commands.contains(&running) {
+                config.error_status
+            } else {
+                config.done_status
+            };
+            if config.reset_command {
+                self.last_command = 0;
+            }
+            return Some(HandshakeOutput {
+                status,
+                reset_command: config.reset_command,
+            });
+        }
+
+        if command == 0 && previous != 0 {
+            return Some(HandshakeOutput {
+                status: config.idle_status,
+                reset_command: false,
+            });
+        }
+
+        if command != 0 && command != previous {
+            self.busy = Some((command, now));
+            return Some(HandshakeOutput {
+                status: config.busy_status,
+                reset_command: false,
+            });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake_busy_then_done() {
+        let config = HandshakeConfig {
+            delay_ms: 100,
+            ..HandshakeConfig::default()
+        };
+        let mut state = HandshakeState::default();
+        let t0 = Instant::now();
+
+        assert_eq!(state.step(&config, 0, t0), None);
+        assert_eq!(state.step(&config, 5, t0).map(|o| o.status), Some(1));
+        // Пока задержка не истекла — статус не меняется
+        assert_eq!(state.step(&config, 5, t0 + Duration::from_millis(50)), None);
+        assert_eq!(
+            state.step(&config, 5, t0 + Duration::from_millis(100)),
+            Some(HandshakeOutput {
+                status: 2,
+                reset_command: false
+            })
+        );
+        // Та же команда без изменения регистра повторно не выполняется
+        assert_eq!(
+            state.step(&config, 5, t0 + Duration::from_millis(200)),
+            None
+        );
+        // Мастер сбрасывает команду — статус возвращается в ожидание
+        assert_eq!(state.step(&config, 0, t0).map(|o| o.status), Some(0));
+    }
+
+    #[test]
+    fn test_handshake_error_command_and_reset() {
+        let config = HandshakeConfig {
+            delay_ms: 0,
+            error_commands: vec![9],
+            reset_command: true,
+            ..HandshakeConfig::default()
+        };
+        let mut state = HandshakeState::default();
+        let t0 = Instant::now();
+
+        assert_eq!(state.step(&config, 9, t0).map(|o| o.status), Some(1));
+        assert_eq!(
+            state.step(&config, 9, t0),
+            Some(HandshakeOutput {
+                status: 3,
+                reset_command: true
+            })
+        );
+        // После сброса та же команда запускается снова
+        assert_eq!(state.step(&config, 9, t0).map(|o| o.status), Some(1));
+    }
+}
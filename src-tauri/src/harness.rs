@@ -0,0 +1,683 @@
+//! Безоконный тестовый стенд для CI.
+//!
+//! Запуск с `--run-scenario <файл>` и/или `--run-assertions <файл>` (и
+//! необязательным `--project <файл>`) поднимает сервер без окна, выполняет
+//! шаги сценария, проверяет условия и печатает в stdout JSON-отчёт.
+//! Код завершения: 0 — все проверки пройдены, 1 — есть провалы,
+//! 2 — ошибка аргументов, файлов или запуска сервера.
+//!
+//! Сценарий: `{"steps": [{"action": "set", "variable": "id", "value": 1},
+//! {"action": "wait", "ms": 500}, {"action": "waitFor", "condition": "temp > 10",
+//! "timeoutMs": 5000}]}`. Шаги `armFault` и `disarmFault` включают и выключают
+//! именованные правила сбоев сервера ([`crate::fault_rules`]):
+//! `{"action": "armFault", "name": "loss", "rule": {"kind": "drop", "percent": 10}}`,
+//! `{"action": "disarmFault", "name": "loss"}`. Шаги `activateFaultPreset` и
+//! `deactivateFaultPreset` включают и выключают все правила пресета проекта:
+//! `{"action": "activateFaultPreset", "name": "flaky network"}`. Проверки: `{"assertions": [{"name": "...",
+//! "condition": "..."}]}`; условия записываются в синтаксисе [`crate::expression`].
+//!
+//! С `--dry-run` сервер не запускается: шаги выполняются без пауз и ожиданий,
+//! а в отчёт попадают изменения значений, которые сделал бы каждый шаг.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::{create_shared_data_store, ClearScope, ModbusDataStore, SharedDataStore};
+use crate::expression::Expr;
+use crate::fault_rules::{FaultPreset, FaultRule, FaultRules};
+use crate::integrity::check_variables;
+use crate::server::create_shared_server;
+use crate::simulation::create_shared_simulation_engine;
+use crate::types::{ModbusProject, ModbusValue, ProjectMetadata};
+
+/// Код завершения при провале проверок.
+const EXIT_FAILED: i32 = 1;
+/// Код завершения при ошибке подготовки.
+const EXIT_SETUP_ERROR: i32 = 2;
+
+/// Период опроса условия в шаге `waitFor`.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Аргументы командной строки стенда.
+#[derive(Debug, Default, PartialEq)]
+pub struct HarnessArgs {
+    pub project: Option<String>,
+    pub scenario: Option<String>,
+    pub assertions: Option<String>,
+    /// Пробный прогон без сервера.
+    pub dry_run: bool,
+}
+
+impl HarnessArgs {
+    /// Разобрать аргументы. None — режим стенда не запрошен.
+    pub fn parse(args: &[String]) -> Result<Option<Self>, String> {
+        let mut parsed = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--dry-run" {
+                parsed.dry_run = true;
+                continue;
+            }
+            let target = match arg.as_str() {
+                "--project" => &mut parsed.project,
+                "--run-scenario" => &mut parsed.scenario,
+                "--run-assertions" => &mut parsed.assertions,
+                _ => continue,
+            };
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("Для {} не указан путь к файлу", arg))?;
+            *target = Some(value.clone());
+        }
+
+        if parsed.scenario.is_none() && parsed.assertions.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(parsed))
+    }
+}
+
+/// Сценарий: последовательность шагов.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub steps: Vec<ScenarioStep>,
+}
+
+/// Шаг сценария.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum ScenarioStep {
+    /// Записать значение переменной.
+    Set {
+        variable: String,
+        value: ModbusValue,
+    },
+    /// Подождать заданное время.
+    Wait { ms: u64 },
+    /// Дождаться выполнения условия (например, записи от мастера).
+    #[serde(rename_all = "camelCase")]
+    WaitFor {
+        condition: String,
+        #[serde(default = "default_wait_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// Восстановить значения сброса всех переменных.
+    ResetValues,
+    /// Выборочно очистить хранилище (`scope`: area, group, range, valuesOnly).
+    Clear(ClearScope),
+    /// Включить правило сбоя (задержка, потеря ответа, исключение).
+    ArmFault { name: String, rule: FaultRule },
+    /// Выключить правило сбоя.
+    DisarmFault { name: String },
+    /// Включить правила пресета сбоев проекта.
+    ActivateFaultPreset { name: String },
+    /// Выключить правила пресета сбоев проекта.
+    DeactivateFaultPreset { name: String },
+}
+
+fn default_wait_timeout_ms() -> u64 {
+    5000
+}
+
+impl ScenarioStep {
+    fn action(&self) -> &'static str {
+        match self {
+            ScenarioStep::Set { .. } => "set",
+            ScenarioStep::Wait { .. } => "wait",
+            ScenarioStep::WaitFor { .. } => "waitFor",
+            ScenarioStep::ResetValues => "resetValues",
+            ScenarioStep::Clear(_) => "clear",
+            ScenarioStep::ArmFault { .. } => "armFault",
+            ScenarioStep::DisarmFault { .. } => "disarmFault",
+            ScenarioStep::ActivateFaultPreset { .. } => "activateFaultPreset",
+            ScenarioStep::DeactivateFaultPreset { .. } => "deactivateFaultPreset",
+        }
+    }
+}
+
+/// Набор проверок.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AssertionSet {
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+}
+
+/// Проверка: условие над переменными после выполнения сценария.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Assertion {
+    #[serde(default)]
+    pub name: String,
+    pub condition: String,
+}
+
+/// Результат шага сценария.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepResult {
+    pub index: usize,
+    pub action: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Результат проверки.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResult {
+    pub name: String,
+    pub condition: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Изменение значения переменной.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueChange {
+    pub variable: String,
+    /// `None` — переменной не было.
+    pub before: Option<ModbusValue>,
+    /// `None` — переменная удалена шагом.
+    pub after: Option<ModbusValue>,
+}
+
+/// Результат шага в пробном прогоне.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepPreview {
+    pub index: usize,
+    pub action: String,
+    pub ok: bool,
+    pub changes: Vec<ValueChange>,
+    /// Что шаг сделал бы вместо пропущенной паузы или ожидания.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Итоговый отчёт стенда (печатается в stdout).
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarnessReport {
+    pub passed: bool,
+    pub steps: Vec<StepResult>,
+    /// Шаги пробного прогона.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub preview: Vec<StepPreview>,
+    pub assertions: Vec<AssertionResult>,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Сведения о проекте для заголовка отчёта.
+    #[serde(skip_serializing_if = "ProjectMetadata::is_empty")]
+    pub metadata: ProjectMetadata,
+}
+
+/// Выполнить стенд по аргументам командной строки.
+/// Возвращает код завершения или None, если режим стенда не запрошен.
+pub fn run_from_args(args: &[String]) -> Option<i32> {
+    let args = match HarnessArgs::parse(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => return None,
+        Err(e) => return Some(print_report(setup_error(e))),
+    };
+
+    let started = Instant::now();
+    let mut report = tauri::async_runtime::block_on(run(&args)).unwrap_or_else(setup_error);
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    Some(print_report(report))
+}
+
+/// Загрузить файлы, запустить сервер и выполнить сценарий с проверками.
+async fn run(args: &HarnessArgs) -> Result<HarnessReport, String> {
+    let project: ModbusProject = match &args.project {
+        Some(path) => read_json(path)?,
+        None => ModbusProject::default(),
+    };
+    let scenario: Scenario = match &args.scenario {
+        Some(path) => read_json(path)?,
+        None => Scenario::default(),
+    };
+    let assertion_set: AssertionSet = match &args.assertions {
+        Some(path) => read_json(path)?,
+        None => AssertionSet::default(),
+    };
+
+    let profile = project.current_profile().cloned().unwrap_or_default();
+
+    let integrity = check_variables(&project.variables);
+    if integrity.has_fatal() {
+        return Err(format!(
+            "Проект не прошёл проверку целостности: {}",
+            integrity.fatal_summary()
+        ));
+    }
+
+    let data_store = create_shared_data_store();
+    data_store.load_variables(&project.variables);
+    data_store.set_record_files(&project.record_files)?;
+
+    // Хранилище никем не обслуживается, поэтому пробный прогон идёт прямо в нём
+    if args.dry_run {
+        let preview = preview_steps(&scenario.steps, &data_store, &project.fault_presets);
+        let assertions =
+            evaluate_assertions(&assertion_set.assertions, &data_store.numeric_snapshot());
+        return Ok(HarnessReport {
+            passed: preview.iter().all(|s| s.ok) && assertions.iter().all(|a| a.passed),
+            preview,
+            assertions,
+            metadata: project.metadata.clone(),
+            ..HarnessReport::default()
+        });
+    }
+
+    let server = create_shared_server(data_store.clone());
+    server.apply_profile(profile);
+    server.faults().set_presets(project.fault_presets.clone());
+    server.start().await?;
+
+    let simulation = create_shared_simulation_engine(data_store.clone(), server.clone());
+    simulation.apply_project(&project);
+    simulation.start();
+
+    let steps = run_steps(&scenario.steps, &data_store, server.faults()).await;
+    let assertions = evaluate_assertions(&assertion_set.assertions, &data_store.numeric_snapshot());
+    let _ = server.stop();
+
+    Ok(HarnessReport {
+        passed: steps.iter().all(|s| s.ok) && assertions.iter().all(|a| a.passed),
+        steps,
+        assertions,
+        metadata: project.metadata.clone(),
+        ..HarnessReport::default()
+    })
+}
+
+/// Выполнить шаги сценария. После первого неудачного шага остальные пропускаются.
+async fn run_steps(
+    steps: &[ScenarioStep],
+    data_store: &SharedDataStore,
+    faults: &FaultRules,
+) -> Vec<StepResult> {
+    let mut results = Vec::with_capacity(steps.len());
+
+    for (index, step) in steps.iter().enumerate() {
+        let outcome = execute_step(step, data_store, faults).await;
+
+        let ok = outcome.is_ok();
+        results.push(StepResult {
+            index,
+            action: step.action().to_string(),
+            ok,
+            error: outcome.err(),
+        });
+        if !ok {
+            break;
+        }
+    }
+
+    results
+}
+
+/// Выполнить один шаг сценария, включая паузы и ожидания.
+pub async fn execute_step(
+    step: &ScenarioStep,
+    data_store: &SharedDataStore,
+    faults: &FaultRules,
+) -> Result<(), String> {
+    match step {
+        ScenarioStep::Wait { ms } => {
+            tokio::time::sleep(Duration::from_millis(*ms)).await;
+            Ok(())
+        }
+        ScenarioStep::WaitFor {
+            condition,
+            timeout_ms,
+        } => wait_for(condition, *timeout_ms, data_store).await,
+        _ => apply_step(step, data_store, faults),
+    }
+}
+
+/// Выполнить шаг, меняющий хранилище или правила сбоев.
+/// Паузы и ожидания выполняет вызывающий.
+fn apply_step(
+    step: &ScenarioStep,
+    data_store: &ModbusDataStore,
+    faults: &FaultRules,
+) -> Result<(), String> {
+    match step {
+        ScenarioStep::Set { variable, value } => {
+            if data_store.update_simulated(variable, value.clone()) {
+                Ok(())
+            } else {
+                Err(format!("Переменная '{}' не найдена", variable))
+            }
+        }
+        ScenarioStep::ResetValues => {
+            data_store.reset_values();
+            Ok(())
+        }
+        ScenarioStep::Clear(scope) => {
+            data_store.clear_scoped(scope);
+            Ok(())
+        }
+        ScenarioStep::ArmFault { name, rule } => faults.arm(name, rule.clone()),
+        ScenarioStep::DisarmFault { name } => {
+            if faults.disarm(name) {
+                Ok(())
+            } else {
+                Err(format!("Правило сбоя '{}' не включено", name))
+            }
+        }
+        ScenarioStep::ActivateFaultPreset { name } => faults.activate_preset(name),
+        ScenarioStep::DeactivateFaultPreset { name } => faults.deactivate_preset(name),
+        ScenarioStep::Wait { .. } | ScenarioStep::WaitFor { .. } => Ok(()),
+    }
+}
+
+fn variable_values(data_store: &ModbusDataStore) -> BTreeMap<String, ModbusValue> {
+    data_store
+        .get_variables()
+        .into_iter()
+        .map(|var| (var.id, var.value))
+        .collect()
+}
+
+/// Пробный прогон сценария на `data_store` (обычно копии рабочего хранилища):
+/// паузы пропускаются, условия `waitFor` проверяются один раз и не считаются
+/// ошибкой, если ещё не выполнены. В отличие от обычного прогона все шаги
+/// выполняются и после ошибки, чтобы за один раз найти все проблемы файла.
+/// Правила сбоев включаются в отдельном наборе с пресетами `presets`
+/// и на сервер не влияют.
+pub fn preview_steps(
+    steps: &[ScenarioStep],
+    data_store: &ModbusDataStore,
+    presets: &[FaultPreset],
+) -> Vec<StepPreview> {
+    let faults = FaultRules::default();
+    faults.set_presets(presets.to_vec());
+    let mut values = variable_values(data_store);
+    steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            let mut note = None;
+            let outcome = match step {
+                ScenarioStep::Wait { ms } => {
+                    note = Some(format!("Пауза {} мс пропущена", ms));
+                    Ok(())
+                }
+                ScenarioStep::WaitFor {
+                    condition,
+                    timeout_ms,
+                } => {
+                    let snapshot = data_store.numeric_snapshot();
+                    Expr::parse(condition)
+                        .and_then(|expr| expr.eval_bool(&|name: &str| snapshot.get(name).copied()))
+                        .map(|met| {
+                            note = Some(if met {
+                                "Условие уже выполнено".to_string()
+                            } else {
+                                format!(
+                                    "Ожидание условия до {} мс (например, записи мастера)",
+                                    timeout_ms
+                                )
+                            });
+                        })
+                }
+                ScenarioStep::ArmFault { name, .. } => {
+                    note = Some(format!("Правило сбоя '{}' будет включено", name));
+                    apply_step(step, data_store, &faults)
+                }
+                ScenarioStep::ActivateFaultPreset { name } => {
+                    note = Some(format!("Пресет сбоев '{}' будет включён", name));
+                    apply_step(step, data_store, &faults)
+                }
+                _ => apply_step(step, data_store, &faults),
+            };
+
+            let after = variable_values(data_store);
+            let changes = values
+                .keys()
+                .chain(after.keys())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .filter(|id| values.get(*id) != after.get(*id))
+                .map(|id| ValueChange {
+                    variable: id.clone(),
+                    before: values.get(id).cloned(),
+                    after: after.get(id).cloned(),
+                })
+                .collect();
+            values = after;
+
+            StepPreview {
+                index,
+                action: step.action().to_string(),
+                ok: outcome.is_ok(),
+                changes,
+                note,
+                error: outcome.err(),
+            }
+        })
+        .collect()
+}
+
+/// Дождаться выполнения условия или истечения таймаута.
+async fn wait_for(
+    condition: &str,
+    timeout_ms: u64,
+    data_store: &SharedDataStore,
+) -> Result<(), String> {
+    let expr = Expr::parse(condition)?;
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+    loop {
+        let snapshot = data_store.numeric_snapshot();
+        if expr.eval_bool(&|name: &str| snapshot.get(name).copied())? {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "Условие '{}' не выполнилось за {} мс",
+                condition, timeout_ms
+            ));
+        }
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
+
+/// Вычислить проверки по снимку значений переменных.
+pub fn evaluate_assertions(
+    assertions: &[Assertion],
+    snapshot: &HashMap<String, f64>,
+) -> Vec<AssertionResult> {
+    let resolve = |name: &str| snapshot.get(name).copied();
+
+    assertions
+        .iter()
+        .map(|assertion| {
+            let outcome = Expr::parse(&assertion.condition).and_then(|e| e.eval_bool(&resolve));
+            AssertionResult {
+                name: if assertion.name.is_empty() {
+                    assertion.condition.clone()
+                } else {
+                    assertion.name.clone()
+                },
+                condition: assertion.condition.clone(),
+                passed: outcome == Ok(true),
+                error: outcome.err(),
+            }
+        })
+        .collect()
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, String> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| format!("Не удалось прочитать файл {}: {}", path, e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Ошибка JSON в файле {}: {}", path, e))
+}
+
+fn setup_error(error: String) -> HarnessReport {
+    HarnessReport {
+        error: Some(error),
+        ..HarnessReport::default()
+    }
+}
+
+/// Напечатать отчёт и вернуть код завершения.
+fn print_report(report: HarnessReport) -> i32 {
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Не удалось сериализовать отчёт: {}", e),
+    }
+    if report.error.is_some() {
+        EXIT_SETUP_ERROR
+    } else if report.passed {
+        0
+    } else {
+        EXIT_FAILED
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusVariable};
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(HarnessArgs::parse(&args(&["--verbose"])), Ok(None));
+        assert_eq!(
+            HarnessArgs::parse(&args(&[
+                "--project",
+                "p.json",
+                "--run-assertions",
+                "a.json"
+            ])),
+            Ok(Some(HarnessArgs {
+                project: Some("p.json".to_string()),
+                scenario: None,
+                assertions: Some("a.json".to_string()),
+                dry_run: false,
+            }))
+        );
+        assert!(HarnessArgs::parse(&args(&["--run-scenario"])).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_assertions() {
+        let snapshot = HashMap::from([("temp".to_string(), 42.0)]);
+        let results = evaluate_assertions(
+            &[
+                Assertion {
+                    name: "нагрев".to_string(),
+                    condition: "temp > 40".to_string(),
+                },
+                Assertion {
+                    name: String::new(),
+                    condition: "pressure > 1".to_string(),
+                },
+            ],
+            &snapshot,
+        );
+        assert!(results[0].passed);
+        assert!(!results[1].passed && results[1].error.is_some());
+        assert_eq!(results[1].name, "pressure > 1");
+    }
+
+    #[test]
+    fn test_preview_reports_changes_without_touching_store() {
+        let store = create_shared_data_store();
+        store.load_variables(&[ModbusVariable {
+            value: ModbusValue::Number(20.0),
+            ..ModbusVariable::new(
+                "temp",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            )
+        }]);
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"steps": [
+                {"action": "set", "variable": "temp", "value": 35},
+                {"action": "wait", "ms": 60000},
+                {"action": "set", "variable": "missing", "value": 1},
+                {"action": "waitFor", "condition": "temp > 30"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let sandbox = store.sandbox();
+        let preview = preview_steps(&scenario.steps, &sandbox, &[]);
+        assert_eq!(
+            preview[0].changes,
+            [ValueChange {
+                variable: "temp".to_string(),
+                before: Some(ModbusValue::Number(20.0)),
+                after: Some(ModbusValue::Number(35.0)),
+            }]
+        );
+        assert!(preview[1].ok && preview[1].note.is_some());
+        // Ошибка шага не прерывает пробный прогон
+        assert!(!preview[2].ok);
+        assert_eq!(preview[3].note.as_deref(), Some("Условие уже выполнено"));
+        assert_eq!(
+            store.get_variable("temp").unwrap().value,
+            ModbusValue::Number(20.0)
+        );
+    }
+
+    #[test]
+    fn test_preview_fault_steps() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"steps": [
+                {"action": "wait", "ms": 300000},
+                {"action": "armFault", "name": "loss", "rule": {"kind": "drop", "percent": 10}},
+                {"action": "wait", "ms": 60000},
+                {"action": "disarmFault", "name": "loss"},
+                {"action": "disarmFault", "name": "loss"},
+                {"action": "activateFaultPreset", "name": "slow device"},
+                {"action": "deactivateFaultPreset", "name": "slow device"},
+                {"action": "activateFaultPreset", "name": "strict device"}
+            ]}"#,
+        )
+        .unwrap();
+        let presets: Vec<FaultPreset> = serde_json::from_str(
+            r#"[{"name": "slow device", "rules": {"lag": {"kind": "delay", "ms": 800}}}]"#,
+        )
+        .unwrap();
+        let preview = preview_steps(&scenario.steps, &ModbusDataStore::new(), &presets);
+        assert_eq!(preview[1].action, "armFault");
+        assert_eq!(preview[5].action, "activateFaultPreset");
+        let ok: Vec<bool> = preview.iter().map(|s| s.ok).collect();
+        assert_eq!(ok, [true, true, true, true, false, true, true, false]);
+    }
+
+    #[test]
+    fn test_parse_clear_step() {
+        let step: ScenarioStep = serde_json::from_str(
+            r#"{"action": "clear", "scope": "range", "area": "coil", "start": 0, "end": 7}"#,
+        )
+        .unwrap();
+        assert_eq!(step.action(), "clear");
+        assert!(matches!(
+            step,
+            ScenarioStep::Clear(ClearScope::Range {
+                area: ModbusArea::Coil,
+                start: 0,
+                end: 7
+            })
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Поведение «сердцебиение» (heartbeat).
+//!
+//! Пока симуляция работает, coil переключается, а регистр увеличивается на 1
+//! с заданным периодом. Мастера часто следят за такой переменной, чтобы
+//! обнаружить зависшее устройство.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::ModbusValue;
+
+/// Настройка сердцебиения.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatConfig {
+    /// ID переменной: coil переключается, регистр увеличивается.
+    pub target_id: String,
+    /// Период, мс.
+    #[serde(default = "default_period_ms")]
+    pub period_ms: u64,
+    /// Значение счётчика, после которого он начинается с 0.
+    #[serde(default = "default_max_value")]
+    pub max_value: u16,
+}
+
+fn default_period_ms() -> u64 {
+    1000
+}
+
+fn default_max_value() -> u16 {
+    u16::MAX
+}
+
+impl HeartbeatConfig {
+    /// Проверить переменную и период.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.target_id.is_empty() {
+            return Err("Не задана переменная сердцебиения".to_string());
+        }
+        if self.period_ms == 0 {
+            return Err("Период сердцебиения должен быть больше нуля".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Состояние сердцебиения между тактами.
+#[derive(Debug, Default)]
+pub struct HeartbeatState {
+    /// Момент последнего шага.
+    last_beat: Option<Instant>,
+}
+
+impl HeartbeatState {
+    /// Обработать текущее значение переменной. Возвращает новое значение,
+    /// если период истёк. Первый вызов только запоминает момент начала.
+    pub fn step(
+        &mut self,
+        config: &HeartbeatConfig,
+        current: &ModbusValue,
+        now: Instant,
+    ) -> Option<ModbusValue> {
+        let Some(last) = self.last_beat else {
+            self.last_beat = Some(now);
+            return None;
+        };
+        if now.duration_since(last) < Duration::from_millis(config.period_ms) {
+            return None;
+        }
+        self.last_beat = Some(now);
+
+        Some(match current {
+            ModbusValue::Bool(on) => ModbusValue::Bool(!on),
+            value => {
+                let count = value.as_u16();
+                let next = if count >= config.max_value {
+                    0
+                } else {
+                    count + 1
+                };
+                ModbusValue::Number(next as f64)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_counts_and_wraps() {
+        let config = HeartbeatConfig {
+            target_id: "hb".to_string(),
+            period_ms: 500,
+            max_value: 2,
+        };
+        let mut state = HeartbeatState::default();
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+
+        assert_eq!(state.step(&config, &ModbusValue::Number(1.0), t0), None);
+        assert_eq!(
+            state.step(&config, &ModbusValue::Number(1.0), ms(400)),
+            None
+        );
+        assert_eq!(
+            state.step(&config, &ModbusValue::Number(1.0), ms(500)),
+            Some(ModbusValue::Number(2.0))
+        );
+        assert_eq!(
+            state.step(&config, &ModbusValue::Number(2.0), ms(1000)),
+            Some(ModbusValue::Number(0.0))
+        );
+    }
+
+    #[test]
+    fn test_heartbeat_toggles_coil() {
+        let config: HeartbeatConfig = serde_json::from_str(r#"{"targetId":"hb"}"#).unwrap();
+        assert_eq!(config.period_ms, 1000);
+        let mut state = HeartbeatState::default();
+        let t0 = Instant::now();
+        state.step(&config, &ModbusValue::Bool(false), t0);
+        assert_eq!(
+            state.step(
+                &config,
+                &ModbusValue::Bool(false),
+                t0 + Duration::from_secs(1)
+            ),
+            Some(ModbusValue::Bool(true))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Тревога отсутствия мастера.
+//!
+//! Если за заданное время от мастера не пришло ни одного запроса, сервер
+//! отправляет событие и запись в журнал, а при настройке — включает
+//! служебный coil. Первый же запрос снимает тревогу тем же путём. Так
+//! замолчавший опрос заметен в многочасовых прогонах без наблюдения.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::data_store::{ModbusDataStore, SharedDataStore};
+use crate::runtime_counters::RuntimeCounters;
+
+/// Событие смены состояния тревоги.
+pub const INACTIVITY_EVENT_NAME: &str = "master-inactivity";
+
+/// Наибольший период проверки.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Настройки тревоги в профиле подключения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InactivityAlarmConfig {
+    /// Время без запросов до срабатывания, мс.
+    pub timeout_ms: u64,
+    /// Coil, включённый на время тревоги.
+    #[serde(default)]
+    pub status_coil: Option<u16>,
+}
+
+/// Смена состояния тревоги.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InactivityEvent {
+    pub active: bool,
+    /// Время без запросов на момент события, мс.
+    pub idle_ms: u64,
+}
+
+/// Состояние тревоги.
+#[derive(Debug)]
+struct InactivityMonitor {
+    config: InactivityAlarmConfig,
+    active: bool,
+}
+
+impl InactivityMonitor {
+    fn new(config: InactivityAlarmConfig, store: &ModbusDataStore) -> Self {
+        if let Some(coil) = config.status_coil {
+            store.set_coil(coil, false);
+        }
+        Self {
+            config,
+            active: false,
+        }
+    }
+
+    /// Проверить время без запросов; событие — только при смене состояния.
+    fn check(&mut self, idle: Duration, store: &ModbusDataStore) -> Option<InactivityEvent> {
+        let active = idle >= Duration::from_millis(self.config.timeout_ms);
+        if active == self.active {
+            return None;
+        }
+        self.active = active;
+        if let Some(coil) = self.config.status_coil {
+            store.set_coil(coil, active);
+        }
+        Some(InactivityEvent {
+            active,
+            idle_ms: idle.as_millis() as u64,
+        })
+    }
+}
+
+/// Следить за запросами мастера до сигнала завершения.
+/// `notify` вызывается при срабатывании и снятии тревоги.
+pub async fn run(
+    config: InactivityAlarmConfig,
+    counters: Arc<RuntimeCounters>,
+    store: SharedDataStore,
+    notify: impl Fn(InactivityEvent),
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut monitor = InactivityMonitor::new(config, &store);
+    let period = Duration::from_millis(config.timeout_ms / 4)
+        .clamp(Duration::from_millis(10), MAX_CHECK_INTERVAL);
+    let mut tick = tokio::time::interval(period);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                if let Some(event) = monitor.check(counters.idle(), &store) {
+                    notify(event);
+                }
+            }
+            _ = shutdown_rx.recv() => break,
+        }
+    }
+    // Остановленный сервер не оставляет тревогу включённой
+    if let Some(coil) = config.status_coil {
+        store.set_coil(coil, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alarm_raises_and_clears_status_coil() {
+        let store = ModbusDataStore::new();
+        let mut monitor = InactivityMonitor::new(
+            InactivityAlarmConfig {
+                timeout_ms: 1000,
+                status_coil: Some(7),
+            },
+            &store,
+        );
+        assert_eq!(store.read_coils(7, 1), Ok(vec![false]));
+
+        assert_eq!(monitor.check(Duration::from_millis(900), &store), None);
+        let raised = monitor.check(Duration::from_millis(1200), &store).unwrap();
+        assert!(raised.active);
+        assert_eq!(store.read_coils(7, 1), Ok(vec![true]));
+        assert_eq!(monitor.check(Duration::from_millis(1500), &store), None);
+
+        // Запрос мастера обнулил время простоя
+        let cleared = monitor.check(Duration::from_millis(5), &store).unwrap();
+        assert!(!cleared.active);
+        assert_eq!(store.read_coils(7, 1), Ok(vec![false]));
+    }
+}
@@ -0,0 +1,280 @@
+//! Проверка целостности проекта перед работой с хранилищем.
+//!
+//! При загрузке проекта и перед запуском сервера переменные проверяются на
+//! совместимость с областями данных. Критические ошибки (переменная не
+//! помещается в область или выходит за адрес 65535, номер бита вне 0..15)
+//! не дают запустить сервер: хранилище обрезало бы такие переменные молча.
+//! Пересечения переменных и столкновения битовых полей — предупреждения:
+//! сервер работает, но переменные перетирают значения друг друга.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::types::{ModbusArea, ModbusDataType, ModbusVariable};
+
+/// Серьёзность замечания.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IntegritySeverity {
+    /// Сервер запускается, но поведение может отличаться от ожидаемого.
+    Warning,
+    /// Сервер не запускается.
+    Fatal,
+}
+
+/// Вид замечания.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IntegrityIssueKind {
+    /// Тип данных не подходит для области (например, float32 в coils).
+    AreaMismatch,
+    /// Многорегистровое значение выходит за адрес 65535.
+    AddressOverflow,
+    /// Номер бита вне 0..15 или бит у переменной, где он не применяется.
+    InvalidBit,
+    /// Две переменные занимают одну ячейку.
+    Overlap,
+    /// Два битовых поля занимают один бит регистра.
+    BitCollision,
+}
+
+/// Замечание к переменной проекта.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityIssue {
+    pub severity: IntegritySeverity,
+    pub kind: IntegrityIssueKind,
+    pub variable_id: String,
+    pub area: ModbusArea,
+    pub address: u16,
+    /// Вторая переменная пересечения.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub other_variable_id: Option<String>,
+    pub message: String,
+}
+
+/// Результат проверки: замечания по порядку переменных.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    /// Есть ли замечания, запрещающие запуск сервера.
+    pub fn has_fatal(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == IntegritySeverity::Fatal)
+    }
+
+    /// Текст критических замечаний для ошибки запуска.
+    pub fn fatal_summary(&self) -> String {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == IntegritySeverity::Fatal)
+            .map(|issue| issue.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Название переменной для сообщений: имя, а без него — ID.
+fn label(var: &ModbusVariable) -> &str {
+    if var.name.trim().is_empty() {
+        &var.id
+    } else {
+        &var.name
+    }
+}
+
+fn is_bit_area(area: ModbusArea) -> bool {
+    matches!(area, ModbusArea::Coil | ModbusArea::DiscreteInput)
+}
+
+/// Битовое поле регистра: bool с номером бита в регистровой области.
+fn bit_field(var: &ModbusVariable) -> Option<u8> {
+    match var.data_type {
+        ModbusDataType::Bool if !is_bit_area(var.area) => var.bit,
+        _ => None,
+    }
+}
+
+/// Занятая переменной ячейка: индекс переменной и бит (для битовых полей).
+type Occupant = (usize, Option<u8>);
+
+/// Проверить переменные проекта.
+pub fn check_variables(variables: &[ModbusVariable]) -> IntegrityReport {
+    let mut issues = Vec::new();
+    let issue = |severity, kind, var: &ModbusVariable, message: String| IntegrityIssue {
+        severity,
+        kind,
+        variable_id: var.id.clone(),
+        area: var.area,
+        address: var.address,
+        other_variable_id: None,
+        message,
+    };
+    let mut cells: BTreeMap<(u8, u16), Vec<Occupant>> = BTreeMap::new();
+    let mut pairs = BTreeSet::new();
+
+    for (index, var) in variables.iter().enumerate() {
+        let name = label(var);
+        let bit_area = is_bit_area(var.area);
+        if bit_area && var.data_type != ModbusDataType::Bool {
+            issues.push(issue(
+                IntegritySeverity::Fatal,
+                IntegrityIssueKind::AreaMismatch,
+                var,
+                format!(
+                    "'{}': тип {:?} не помещается в битовую область {:?}",
+                    name, var.data_type, var.area
+                ),
+            ));
+            continue;
+        }
+        let width = if bit_area {
+            1
+        } else {
+            var.data_type.register_count() as u32
+        };
+        if var.address as u32 + width - 1 > u16::MAX as u32 {
+            issues.push(issue(
+                IntegritySeverity::Fatal,
+                IntegrityIssueKind::AddressOverflow,
+                var,
+                format!(
+                    "'{}': {:?} по адресу {} выходит за адрес 65535",
+                    name, var.data_type, var.address
+                ),
+            ));
+            continue;
+        }
+        match var.bit {
+            Some(bit) if bit > 15 => {
+                issues.push(issue(
+                    IntegritySeverity::Fatal,
+                    IntegrityIssueKind::InvalidBit,
+                    var,
+                    format!("'{}': номер бита {} вне диапазона 0..15", name, bit),
+                ));
+                continue;
+            }
+            Some(bit) if bit_field(var).is_none() => issues.push(issue(
+                IntegritySeverity::Warning,
+                IntegrityIssueKind::InvalidBit,
+                var,
+                format!(
+                    "'{}': номер бита {} игнорируется для {:?} в {:?}",
+                    name, bit, var.data_type, var.area
+                ),
+            )),
+            _ => {}
+        }
+
+        let bit = bit_field(var);
+        for offset in 0..width {
+            let address = var.address + offset as u16;
+            let occupants = cells.entry((var.area as u8, address)).or_default();
+            for &(other, other_bit) in occupants.iter() {
+                let collides = match (bit, other_bit) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => true,
+                };
+                if !collides || !pairs.insert((other, index)) {
+                    continue;
+                }
+                let other_var = &variables[other];
+                let (kind, message) = if bit.is_some() {
+                    (
+                        IntegrityIssueKind::BitCollision,
+                        format!(
+                            "'{}' и '{}' занимают бит {}.{}",
+                            label(other_var),
+                            name,
+                            address,
+                            bit.unwrap_or_default()
+                        ),
+                    )
+                } else {
+                    (
+                        IntegrityIssueKind::Overlap,
+                        format!(
+                            "'{}' и '{}' пересекаются по адресу {} ({:?})",
+                            label(other_var),
+                            name,
+                            address,
+                            var.area
+                        ),
+                    )
+                };
+                issues.push(IntegrityIssue {
+                    other_variable_id: Some(other_var.id.clone()),
+                    ..issue(IntegritySeverity::Warning, kind, var, message)
+                });
+            }
+            occupants.push((index, bit));
+        }
+    }
+
+    IntegrityReport { issues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ModbusValue;
+
+    fn var(
+        id: &str,
+        area: ModbusArea,
+        address: u16,
+        data_type: ModbusDataType,
+        bit: Option<u8>,
+    ) -> ModbusVariable {
+        ModbusVariable {
+            name: String::new(),
+            value: ModbusValue::Number(0.0),
+            bit,
+            ..ModbusVariable::new(id, area, address, data_type)
+        }
+    }
+
+    #[test]
+    fn test_fatal_issues_and_collisions() {
+        let hr = ModbusArea::HoldingRegister;
+        let report = check_variables(&[
+            var("f", ModbusArea::Coil, 0, ModbusDataType::Float32, None),
+            var("tail", hr, 65535, ModbusDataType::Uint32, None),
+            var("wide", hr, 10, ModbusDataType::Float32, None),
+            var("word", hr, 11, ModbusDataType::Uint16, None),
+            var("b0", hr, 20, ModbusDataType::Bool, Some(0)),
+            var("b1", hr, 20, ModbusDataType::Bool, Some(1)),
+            var("b1x", hr, 20, ModbusDataType::Bool, Some(1)),
+            var("b16", hr, 21, ModbusDataType::Bool, Some(16)),
+        ]);
+        let kinds: Vec<_> = report
+            .issues
+            .iter()
+            .map(|i| (i.variable_id.as_str(), i.kind, i.severity))
+            .collect();
+        use IntegrityIssueKind::*;
+        use IntegritySeverity::*;
+        assert_eq!(
+            kinds,
+            [
+                ("f", AreaMismatch, Fatal),
+                ("tail", AddressOverflow, Fatal),
+                ("word", Overlap, Warning),
+                ("b1x", BitCollision, Warning),
+                ("b16", InvalidBit, Fatal),
+            ]
+        );
+        assert_eq!(report.issues[3].other_variable_id.as_deref(), Some("b1"));
+        assert!(report.has_fatal());
+
+        let clean = check_variables(&[var("b0", hr, 20, ModbusDataType::Bool, Some(0))]);
+        assert!(!clean.has_fatal() && clean.issues.is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+//! Формат полезной нагрузки IPC для больших ответов.
+//!
+//! Для проектов с десятками тысяч переменных сериализация в JSON занимает
+//! основную часть времени ответа. Фронтенд может запросить компактный
+//! бинарный формат MessagePack (с именованными полями, как в JSON) и получить
+//! `ArrayBuffer` вместо JSON-строки.
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Response;
+
+/// Формат ответа, запрошенный фронтендом.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+/// Сериализовать значение в байты выбранного формата.
+pub fn encode<T: Serialize>(value: &T, format: PayloadFormat) -> Result<Vec<u8>, String> {
+    match format {
+        PayloadFormat::Json => {
+            serde_json::to_vec(value).map_err(|e| format!("Ошибка сериализации JSON: {e}"))
+        }
+        PayloadFormat::MessagePack => rmp_serde::to_vec_named(value)
+            .map_err(|e| format!("Ошибка сериализации MessagePack: {e}")),
+    }
+}
+
+/// Сериализовать значение в сырой ответ IPC (без повторного кодирования в JSON).
+pub fn encode_response<T: Serialize>(value: &T, format: PayloadFormat) -> Result<Response, String> {
+    encode(value, format).map(Response::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+    #[test]
+    fn test_message_pack_roundtrip_is_smaller_than_json() {
+        let vars: Vec<ModbusVariable> = (0..100)
+            .map(|i| ModbusVariable {
+                name: format!("Var {i}"),
+                value: ModbusValue::Number(i as f64 * 1.5),
+                ..ModbusVariable::new(
+                    format!("var{i}"),
+                    ModbusArea::HoldingRegister,
+                    i,
+                    ModbusDataType::Float32,
+                )
+            })
+            .collect();
+
+        let json = encode(&vars, PayloadFormat::Json).unwrap();
+        let packed = encode(&vars, PayloadFormat::MessagePack).unwrap();
+        assert!(packed.len() < json.len());
+
+        let decoded: Vec<ModbusVariable> = rmp_serde::from_slice(&packed).unwrap();
+        assert_eq!(decoded.len(), 100);
+        assert_eq!(decoded[3].id, "var3");
+        assert_eq!(decoded[3].value.as_f32(), 4.5);
+    }
+}
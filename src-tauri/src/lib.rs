@@ -3,15 +3,132 @@
 //! Это главная точка входа библиотеки, которая настраивает Tauri-приложение
 //! со всеми необходимыми модулями и командами.
 
+mod access_lock;
+mod access_map;
+mod addressing;
+mod alarms;
+mod byte_count_stress;
+mod client_units;
+mod coil_interlock;
 mod commands;
+mod comms_failure;
+mod consistency_check;
 mod data_store;
+mod device_scan;
+mod disturbance_library;
+mod edit_session;
+mod error;
+mod event_buffer;
+mod exception_injection;
+mod exception_stats;
+mod expression;
+mod fault_rules;
+mod firmware;
+mod fragmentation;
+mod frame_clock;
+mod gateway;
+mod generator;
+mod handshake;
+mod harness;
+mod heartbeat;
+mod inactivity;
+mod integrity;
+mod ipc_payload;
+mod listener_stats;
+mod map_server;
+mod master;
+mod mdns;
+mod memory_dump;
 mod modbus_protocol;
+mod pcap_export;
+mod plc_import;
+mod poll_recorder;
+mod process_image;
+mod processing_time;
+mod project_watcher;
+mod protocol_policy;
+mod quality;
+mod register_map;
+mod request_script;
+mod request_stats;
+mod response_override;
+mod runtime_counters;
+mod scenario_tracks;
+mod schedule;
+mod seeded_rng;
+mod sensor_fault;
+mod serial_gateway;
 mod server;
+mod session_diff;
+mod settings;
+mod simulation;
+mod snapshot_schedule;
+mod state_snapshot;
+mod subscriptions;
+mod threshold;
+mod tick_stats;
+mod traffic_log;
+mod traffic_mirror;
+mod transaction_id;
+mod triggers;
 mod types;
+mod value_override;
+mod watch;
+mod write_learning;
+mod write_rate_limit;
+mod write_storm;
+mod zero_quantity;
 
 use commands::AppState;
 use data_store::create_shared_data_store;
+use edit_session::create_shared_edit_manager;
+use harness::HarnessArgs;
+use master::create_shared_master;
+use project_watcher::create_shared_project_watcher;
+use scenario_tracks::create_shared_scenario_tracks;
 use server::create_shared_server;
+use settings::create_shared_settings;
+use simulation::create_shared_simulation_engine;
+use snapshot_schedule::create_shared_snapshot_scheduler;
+use subscriptions::create_shared_subscription_manager;
+use watch::create_shared_watch_manager;
+
+/// Запуск безоконного тестового стенда, если он запрошен аргументами
+/// (`--run-scenario` / `--run-assertions`). Возвращает код завершения процесса.
+pub fn run_headless() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if HarnessArgs::parse(&args) == Ok(None) {
+        return None;
+    }
+
+    attach_parent_console();
+    // Отчёт печатается в stdout, поэтому лог — только предупреждения в stderr
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    harness::run_from_args(&args)
+}
+
+/// Подключиться к консоли родительского процесса. Релизная сборка под Windows
+/// собирается без консоли (`windows_subsystem = "windows"`), и без этого отчёт
+/// стенда не виден в терминале, из которого он запущен. Перенаправленные в
+/// файл или канал stdout и stderr не затрагиваются.
+#[cfg(windows)]
+fn attach_parent_console() {
+    /// `ATTACH_PARENT_PROCESS` из WinAPI: `(DWORD)-1`.
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Ошибка означает, что консоли нет (запуск из проводника) или она уже есть
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
 
 /// Инициализация и запуск Tauri-приложения.
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -27,23 +144,237 @@ pub fn run() {
     // Создаём общий экземпляр Modbus TCP сервера
     let server = create_shared_server(data_store.clone());
 
+    // Загружаем настройки приложения
+    let settings = create_shared_settings();
+
+    // Журнал обмена в SQLite в каталоге данных; старые записи удаляются
+    server.traffic_log().open_default(settings.data_dir());
+    if let Err(e) = server
+        .traffic_log()
+        .prune(settings.preferences().log_retention_days)
+    {
+        log::warn!("{e}");
+    }
+
+    // Наблюдатель за внешними изменениями файла проекта
+    let project_watcher = create_shared_project_watcher(data_store.clone());
+
+    // Менеджер транзакционных сессий редактирования переменных
+    let edit_manager = create_shared_edit_manager(data_store.clone());
+
+    // Менеджер подписок UI на изменения переменных
+    let subscriptions = create_shared_subscription_manager(data_store.clone());
+
+    // Выражения наблюдения над сырыми адресами
+    let watches = create_shared_watch_manager(data_store.clone());
+
+    // Движок симуляции поведения устройства (работает в фоне постоянно)
+    let simulation = create_shared_simulation_engine(data_store.clone(), server.clone());
+    simulation.start();
+    let simulation_for_setup = simulation.clone();
+
+    // Мастер для опроса удалённых устройств (режим клиента)
+    let master = create_shared_master();
+    let master_for_setup = master.clone();
+
+    // Периодические снимки хранилища для отката длительных прогонов
+    let snapshots = create_shared_snapshot_scheduler(data_store.clone());
+
+    // Параллельные дорожки сценариев
+    let tracks = create_shared_scenario_tracks(data_store.clone(), server.faults().clone());
+
     // Создаём состояние приложения, которое будет доступно во всех командах
-    let app_state = AppState { server, data_store };
+    let app_state = AppState {
+        server,
+        data_store,
+        settings,
+        project_watcher,
+        edit_manager,
+        subscriptions,
+        watches,
+        simulation,
+        master,
+        addressing: Default::default(),
+        metadata: Default::default(),
+        snapshots,
+        tracks,
+        map_server: Default::default(),
+        integrity: Default::default(),
+    };
 
     // Собираем и запускаем Tauri-приложение
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .manage(app_state)
+        .setup(move |app| {
+            // Движку нужен AppHandle для событий тревог и триггеров
+            simulation_for_setup.set_app_handle(app.handle().clone());
+            // Мастеру — для событий статистики опроса
+            master_for_setup.set_app_handle(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::start_server,
             commands::stop_server,
+            commands::disconnect_all_clients,
+            commands::inject_transaction_id_mismatches,
+            commands::inject_exception,
+            commands::cancel_exception_injection,
+            commands::get_exception_injection,
+            commands::start_comms_failure,
+            commands::stop_comms_failure,
+            commands::get_comms_failure,
+            commands::get_fault_presets,
+            commands::save_fault_preset,
+            commands::delete_fault_preset,
+            commands::activate_fault_preset,
+            commands::deactivate_fault_preset,
+            commands::export_disturbance_library,
+            commands::import_disturbance_library,
+            commands::get_armed_faults,
+            commands::push_device_event,
+            commands::get_event_buffer_status,
+            commands::simulate_master_write,
             commands::get_server_status,
+            commands::set_address_validation,
+            commands::start_polling,
+            commands::stop_polling,
+            commands::get_poll_stats,
+            commands::poll_tags_from_project,
+            commands::run_request_script,
+            commands::preview_scenario,
+            commands::start_scenario_track,
+            commands::stop_scenario_track,
+            commands::list_scenario_tracks,
+            commands::scan_devices,
+            commands::run_consistency_test,
+            commands::run_protocol_test_vectors,
+            commands::decode_frame,
+            commands::set_seed,
+            commands::get_seed,
             commands::update_variable,
             commands::get_variables,
+            commands::get_unit_ids,
+            commands::get_unit_variables,
+            commands::update_unit_variable,
+            commands::load_unit_variables,
+            commands::upsert_unit_variables,
+            commands::remove_unit_variables,
+            commands::get_variables_encoded,
             commands::reload_variables,
+            commands::reset_values,
+            commands::force_variable,
+            commands::unforce_variable,
+            commands::list_forces,
+            commands::clear_forces,
+            commands::pause_variable_simulation,
+            commands::resume_variable_simulation,
+            commands::get_paused_variables,
             commands::clear_data_store,
+            commands::clear_area,
+            commands::clear_group,
+            commands::clear_range,
+            commands::clear_values,
             commands::load_project_file,
             commands::save_project_file,
+            commands::list_recent_projects,
+            commands::pin_recent_project,
+            commands::clear_recent_projects,
+            commands::get_preferences,
+            commands::set_preferences,
+            commands::watch_project_file,
+            commands::unwatch_project_file,
+            commands::get_project_watch_status,
+            commands::get_register_map_schema,
+            commands::export_register_map,
+            commands::import_register_map,
+            commands::start_map_server,
+            commands::stop_map_server,
+            commands::get_map_server_status,
+            commands::import_plc_symbols,
+            commands::export_memory_dump,
+            commands::get_bit_bank,
+            commands::get_integrity_report,
+            commands::check_variables_integrity,
+            commands::get_record_files,
+            commands::set_record_file,
+            commands::delete_record_file,
+            commands::import_memory_dump,
+            commands::export_state,
+            commands::import_state,
+            commands::start_snapshot_schedule,
+            commands::stop_snapshot_schedule,
+            commands::list_snapshots,
+            commands::restore_snapshot,
+            commands::set_input,
+            commands::set_addressing_convention,
+            commands::get_addressing_convention,
+            commands::set_project_metadata,
+            commands::get_project_metadata,
+            commands::format_address,
+            commands::parse_address,
+            commands::begin_edit,
+            commands::edit_upsert_variables,
+            commands::edit_remove_variables,
+            commands::commit_edit,
+            commands::rollback_edit,
+            commands::get_edit_session,
+            commands::subscribe_variables,
+            commands::unsubscribe_variables,
+            commands::list_subscriptions,
+            commands::add_watch,
+            commands::remove_watch,
+            commands::list_watches,
+            commands::list_behaviors,
+            commands::upsert_behavior,
+            commands::remove_behavior,
+            commands::attach_generator,
+            commands::update_generator,
+            commands::pause_generator,
+            commands::remove_generator,
+            commands::get_simulation_stats,
+            commands::reset_simulation_stats,
+            commands::set_simulation_interval,
+            commands::get_simulation_interval,
+            commands::get_handshake_templates,
+            commands::list_alarm_definitions,
+            commands::upsert_alarm_definition,
+            commands::remove_alarm_definition,
+            commands::get_active_alarms,
+            commands::acknowledge_alarm,
+            commands::acknowledge_all_alarms,
+            commands::list_triggers,
+            commands::upsert_trigger,
+            commands::remove_trigger,
+            commands::list_quality_configs,
+            commands::upsert_quality_config,
+            commands::remove_quality_config,
+            commands::set_variable_quality,
+            commands::get_variable_qualities,
+            commands::set_sensor_fault,
+            commands::clear_sensor_fault,
+            commands::clear_all_sensor_faults,
+            commands::get_sensor_faults,
+            commands::set_value_override,
+            commands::clear_value_override,
+            commands::get_value_overrides,
+            commands::get_exception_report,
+            commands::reset_exception_stats,
+            commands::reset_statistics,
+            commands::export_statistics,
+            commands::get_observed_access,
+            commands::generate_variables_from_traffic,
+            commands::reset_observed_access,
+            commands::set_write_learning,
+            commands::get_write_quarantine,
+            commands::convert_write_quarantine,
+            commands::clear_write_quarantine,
+            commands::query_traffic_log,
+            commands::clear_traffic_log,
+            commands::export_traffic_pcap,
+            commands::set_log_event_min_severity,
+            commands::save_session_baseline,
+            commands::compare_sessions,
         ])
         .run(tauri::generate_context!())
         .expect("Ошибка при запуске Tauri-приложения");
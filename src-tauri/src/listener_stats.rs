@@ -0,0 +1,114 @@
+//! Статистика слушающих сокетов сервера.
+//!
+//! Ошибки `accept` не видны клиенту и раньше попадали только в журнал
+//! приложения. Здесь они считаются отдельно от принятых подключений:
+//! - отклонённые — соединения, сброшенные до принятия (клиент не дождался
+//!   в очереди или оборвал рукопожатие);
+//! - ошибки — остальные сбои `accept`, например исчерпание дескрипторов.
+//!
+//! Счётчики попадают в `ServerStatus` и в периодическое событие статуса.
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+
+use crate::types::ServerStatus;
+
+/// Событие статуса сервера, отправляемое раз в секунду.
+pub const STATUS_EVENT_NAME: &str = "server-status";
+
+/// Период отправки события статуса.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Счётчики всех слушающих сокетов сервера с момента запуска.
+#[derive(Debug, Default)]
+pub struct ListenerStats {
+    accepted: AtomicU64,
+    refused: AtomicU64,
+    accept_errors: AtomicU64,
+}
+
+impl ListenerStats {
+    /// Начать отсчёт заново (при запуске сервера).
+    pub fn reset(&self) {
+        self.accepted.store(0, Ordering::SeqCst);
+        self.refused.store(0, Ordering::SeqCst);
+        self.accept_errors.store(0, Ordering::SeqCst);
+    }
+
+    /// Отметить принятое подключение.
+    pub fn record_accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Учесть сбой `accept`. Возвращает true, если соединение сбросил клиент
+    /// (слушатель исправен и может сразу принимать следующее).
+    pub fn record_error(&self, error: &io::Error) -> bool {
+        let refused = matches!(
+            error.kind(),
+            io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionRefused
+        );
+        if refused {
+            self.refused.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.accept_errors.fetch_add(1, Ordering::SeqCst);
+        }
+        refused
+    }
+
+    /// Заполнить счётчики в статусе сервера.
+    pub fn fill(&self, status: &mut ServerStatus) {
+        status.accepted_connections = self.accepted.load(Ordering::SeqCst);
+        status.refused_connections = self.refused.load(Ordering::SeqCst);
+        status.accept_errors = self.accept_errors.load(Ordering::SeqCst);
+    }
+}
+
+/// Отправлять статус в UI раз в секунду до сигнала завершения.
+/// `status` вызывается на каждом такте и возвращает актуальный статус.
+pub async fn publish_status(
+    app_handle: AppHandle,
+    status: impl Fn() -> ServerStatus,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut tick = tokio::time::interval(STATUS_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                if let Err(e) = app_handle.emit(STATUS_EVENT_NAME, status()) {
+                    log::warn!("Не удалось отправить статус сервера: {}", e);
+                }
+            }
+            _ = shutdown_rx.recv() => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_errors_are_classified() {
+        let stats = ListenerStats::default();
+        stats.record_accepted();
+        stats.record_accepted();
+        assert!(stats.record_error(&io::Error::from(io::ErrorKind::ConnectionAborted)));
+        assert!(!stats.record_error(&io::Error::from_raw_os_error(24)));
+
+        let mut status = ServerStatus::default();
+        stats.fill(&mut status);
+        assert_eq!(status.accepted_connections, 2);
+        assert_eq!(status.refused_connections, 1);
+        assert_eq!(status.accept_errors, 1);
+
+        stats.reset();
+        stats.fill(&mut status);
+        assert_eq!(status.accepted_connections, 0);
+    }
+}
@@ -15,5 +15,11 @@
 /// - Создаёт Modbus TCP сервер
 /// - Запускает Tauri-приложение с Vue-фронтендом
 fn main() {
+    // Безоконный тестовый стенд для CI: JSON-отчёт в stdout и код завершения.
+    // Под Windows стенд подключается к консоли родителя, иначе отчёт не виден
+    if let Some(code) = modbus_tcp_client_rust_lib::run_headless() {
+        std::process::exit(code);
+    }
+
     modbus_tcp_client_rust_lib::run()
 }
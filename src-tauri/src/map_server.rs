@@ -0,0 +1,292 @@
+//! HTTP-доступ к карте регистров.
+//!
+//! Пока симулятор работает, коллеги в локальной сети могут открыть текущую
+//! карту регистров в браузере, не запрашивая файл экспорта. Встроенный
+//! сервер отвечает только на GET:
+//! - `/` и `/register-map.md` — Markdown-таблица;
+//! - `/register-map.json` — JSON в формате обмена ([`crate::register_map`]).
+//!
+//! Карта строится заново на каждый запрос, поэтому значения всегда текущие.
+//! Сервер необязателен и включается командой.
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+use crate::mdns;
+use crate::register_map::RegisterMap;
+use crate::server::ACCEPT_ERROR_BACKOFF;
+
+/// Порт по умолчанию.
+pub const DEFAULT_MAP_SERVER_PORT: u16 = 8502;
+
+/// Наибольший размер заголовков запроса.
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Время на получение запроса от браузера.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Источник карты: вызывается на каждый запрос.
+pub type MapSource = Arc<dyn Fn() -> RegisterMap + Send + Sync>;
+
+/// Состояние сервера карты для UI.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapServerStatus {
+    pub running: bool,
+    /// Адрес для браузера: `http://host:port/`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Ответ HTTP.
+#[derive(Debug, PartialEq)]
+struct HttpResponse {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl HttpResponse {
+    fn text(status: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", body),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        bytes.extend_from_slice(self.body.as_bytes());
+        bytes
+    }
+}
+
+/// Ответ на строку запроса `GET /путь HTTP/1.1`.
+fn respond(request_line: &str, source: &MapSource) -> HttpResponse {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return HttpResponse::text("400 Bad Request", "Некорректный запрос");
+    };
+    if method != "GET" {
+        return HttpResponse::text("405 Method Not Allowed", "Поддерживается только GET");
+    }
+    let path = target.split(['?', '#']).next().unwrap_or(target);
+    match path {
+        // Браузеры показывают text/plain, а text/markdown предлагают скачать
+        "/" => HttpResponse {
+            status: "200 OK",
+            content_type: "text/plain; charset=utf-8",
+            body: source().to_markdown(),
+        },
+        "/register-map.md" => HttpResponse {
+            status: "200 OK",
+            content_type: "text/markdown; charset=utf-8",
+            body: source().to_markdown(),
+        },
+        "/register-map.json" => match serde_json::to_string_pretty(&source()) {
+            Ok(body) => HttpResponse {
+                status: "200 OK",
+                content_type: "application/json; charset=utf-8",
+                body,
+            },
+            Err(e) => HttpResponse::text(
+                "500 Internal Server Error",
+                &format!("Не удалось сериализовать карту регистров: {}", e),
+            ),
+        },
+        _ => HttpResponse::text(
+            "404 Not Found",
+            "Доступны /, /register-map.md и /register-map.json",
+        ),
+    }
+}
+
+/// Прочитать заголовки запроса и ответить.
+async fn serve_connection(mut stream: TcpStream, source: MapSource) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    let read_headers = async {
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+            if request.len() > MAX_REQUEST_BYTES {
+                break;
+            }
+        }
+        io::Result::Ok(())
+    };
+    if tokio::time::timeout(REQUEST_TIMEOUT, read_headers)
+        .await
+        .is_err()
+    {
+        return Ok(());
+    }
+    let response = if request.len() > MAX_REQUEST_BYTES {
+        HttpResponse::text(
+            "431 Request Header Fields Too Large",
+            "Слишком длинный запрос",
+        )
+    } else {
+        let text = String::from_utf8_lossy(&request);
+        respond(text.lines().next().unwrap_or_default(), &source)
+    };
+    stream.write_all(&response.to_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Встроенный HTTP-сервер карты регистров.
+#[derive(Default)]
+pub struct MapServer {
+    /// Адрес для браузера и сигнал остановки работающего сервера.
+    running: Mutex<Option<(String, broadcast::Sender<()>)>>,
+}
+
+impl MapServer {
+    /// Запустить сервер на `host:port` (порт 0 — любой свободный).
+    /// Работающий сервер перезапускается. Возвращает адрес для браузера.
+    pub async fn start(&self, host: &str, port: u16, source: MapSource) -> io::Result<String> {
+        self.stop();
+        let listener = TcpListener::bind((host, port)).await?;
+        let local = listener.local_addr()?;
+        // На 0.0.0.0 коллеги заходят по адресу машины в локальной сети
+        let shown_host = match local.ip() {
+            ip if ip.is_unspecified() => mdns::local_ipv4()
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|| "localhost".to_string()),
+            ip => ip.to_string(),
+        };
+        let url = format!("http://{}:{}/", shown_host, local.port());
+
+        let (shutdown_tx, mut shutdown_rx) = broadcast::channel(1);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, peer)) => {
+                            log::debug!("Запрос карты регистров от {}", peer);
+                            let source = source.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = serve_connection(stream, source).await {
+                                    log::debug!("Ответ с картой регистров не отправлен: {}", e);
+                                }
+                            });
+                        }
+                        Err(e) => {
+                            log::warn!("Ошибка приёма подключения к карте регистров: {}", e);
+                            tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                        }
+                    },
+                    _ = shutdown_rx.recv() => break,
+                }
+            }
+        });
+
+        log::info!("Карта регистров доступна по HTTP на {}", local);
+        *self.running.lock() = Some((url.clone(), shutdown_tx));
+        Ok(url)
+    }
+
+    /// Остановить сервер. Возвращает false, если он не был запущен.
+    pub fn stop(&self) -> bool {
+        let Some((_, shutdown_tx)) = self.running.lock().take() else {
+            return false;
+        };
+        let _ = shutdown_tx.send(());
+        log::info!("HTTP-доступ к карте регистров выключен");
+        true
+    }
+
+    pub fn status(&self) -> MapServerStatus {
+        let running = self.running.lock();
+        MapServerStatus {
+            running: running.is_some(),
+            url: running.as_ref().map(|(url, _)| url.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addressing::AddressingConvention;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+    fn source() -> MapSource {
+        Arc::new(|| {
+            let variables = [ModbusVariable {
+                name: "Temperature".to_string(),
+                value: ModbusValue::Number(215.0),
+                ..ModbusVariable::new(
+                    "temp",
+                    ModbusArea::HoldingRegister,
+                    0,
+                    ModbusDataType::Uint16,
+                )
+            }];
+            RegisterMap::from_variables(None, &variables, AddressingConvention::Modicon)
+        })
+    }
+
+    #[test]
+    fn test_routes() {
+        let markdown = respond("GET / HTTP/1.1", &source());
+        assert_eq!(markdown.status, "200 OK");
+        assert!(markdown
+            .body
+            .contains("| Temperature | holding register | 40001 |"));
+
+        let json = respond("GET /register-map.json?t=1 HTTP/1.1", &source());
+        assert_eq!(json.content_type, "application/json; charset=utf-8");
+        assert!(RegisterMap::parse(&json.body).is_ok());
+
+        assert_eq!(
+            respond("POST / HTTP/1.1", &source()).status,
+            "405 Method Not Allowed"
+        );
+        assert_eq!(
+            respond("GET /x HTTP/1.1", &source()).status,
+            "404 Not Found"
+        );
+        let bytes = respond("GET /x HTTP/1.1", &source()).to_bytes();
+        assert!(bytes.starts_with(b"HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn test_serves_over_tcp() {
+        tauri::async_runtime::block_on(async {
+            let server = MapServer::default();
+            let url = server.start("127.0.0.1", 0, source()).await.unwrap();
+            let addr = url.trim_start_matches("http://").trim_end_matches('/');
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /register-map.md HTTP/1.1\r\nHost: x\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK"));
+            assert!(response.contains("# Карта регистров"));
+            assert!(server.status().running);
+            assert!(server.stop());
+            assert!(!server.stop());
+        });
+    }
+}
@@ -0,0 +1,601 @@
+//! Режим мастера (клиента): циклический опрос тегов на удалённом устройстве.
+//!
+//! По каждому тегу накапливается статистика — последнее значение, время
+//! последнего успешного чтения, число сбоев и средняя задержка ответа — и после
+//! каждого цикла опроса отправляется в UI событием, как в окне Modbus Poll.
+//! Значения цикла при необходимости записываются в CSV или SQLite.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+
+use crate::data_store::{read_register_value, write_register_value};
+use crate::modbus_protocol::{FunctionCode, MbapHeader};
+use crate::poll_recorder::{PollRecorder, PollRecording};
+use crate::types::{
+    chrono_now_iso, exception_code_name, ModbusArea, ModbusDataType, ModbusValue, ModbusVariable,
+    WordOrder,
+};
+
+/// Название события со статистикой опроса.
+const POLL_STATS_EVENT_NAME: &str = "poll-stats";
+
+/// Ошибка запроса мастера.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestError {
+    /// Устройство ответило исключением с указанным кодом.
+    Exception(u8),
+    /// Сбой связи или неверный ответ; соединение нужно переоткрыть.
+    Transport(String),
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::Exception(code) => write!(
+                f,
+                "Исключение: {} (0x{:02X})",
+                exception_code_name(*code),
+                code
+            ),
+            RequestError::Transport(e) => f.write_str(e),
+        }
+    }
+}
+
+/// Соединение мастера с устройством.
+pub struct MasterConnection {
+    stream: TcpStream,
+    unit_id: u8,
+    transaction_id: u16,
+    timeout: Duration,
+}
+
+impl MasterConnection {
+    /// Подключиться к устройству.
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        unit_id: u8,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        let addr = format!("{}:{}", host, port);
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(&addr))
+            .await
+            .map_err(|_| format!("Таймаут подключения к {}", addr))?
+            .map_err(|e| format!("Не удалось подключиться к {}: {}", addr, e))?;
+        let _ = stream.set_nodelay(true);
+        Ok(Self {
+            stream,
+            unit_id,
+            transaction_id: 0,
+            timeout,
+        })
+    }
+
+    /// Сменить Unit ID для следующих запросов.
+    pub fn set_unit_id(&mut self, unit_id: u8) {
+        self.unit_id = unit_id;
+    }
+
+    /// Отправить запрос и дождаться ответа. Возвращает данные PDU ответа
+    /// (без кода функции); ответ-исключение возвращается как ошибка.
+    pub async fn request(
+        &mut self,
+        function_code: u8,
+        data: &[u8],
+    ) -> Result<Vec<u8>, RequestError> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let mut frame = Vec::with_capacity(MbapHeader::SIZE + 1 + data.len());
+        MbapHeader {
+            transaction_id: self.transaction_id,
+            protocol_id: 0,
+            length: 2 + data.len() as u16,
+            unit_id: self.unit_id,
+        }
+        .write_to(&mut frame);
+        frame.push(function_code);
+        frame.extend_from_slice(data);
+
+        let exchange = async {
+            self.stream
+                .write_all(&frame)
+                .await
+                .map_err(|e| format!("Ошибка отправки: {}", e))?;
+            loop {
+                let mut header = [0u8; MbapHeader::SIZE];
+                self.stream
+                    .read_exact(&mut header)
+                    .await
+                    .map_err(|e| format!("Ошибка чтения: {}", e))?;
+                let header = MbapHeader::parse(&header).map_err(|e| e.to_string())?;
+                if header.length < 2 {
+                    return Err(format!("Неверная длина ответа {}", header.length));
+                }
+                let mut pdu = vec![0u8; header.length as usize - 1];
+                self.stream
+                    .read_exact(&mut pdu)
+                    .await
+                    .map_err(|e| format!("Ошибка чтения: {}", e))?;
+                // Запоздавший ответ на предыдущий запрос пропускаем
+                if header.transaction_id == self.transaction_id {
+                    return Ok(pdu);
+                }
+            }
+        };
+        let pdu = tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| "Таймаут ответа".to_string())
+            .and_then(|result| result)
+            .map_err(RequestError::Transport)?;
+
+        match pdu.first() {
+            Some(&code) if code == function_code => Ok(pdu[1..].to_vec()),
+            Some(&code) if code == function_code | 0x80 => {
+                Err(RequestError::Exception(pdu.get(1).copied().unwrap_or(0)))
+            }
+            _ => Err(RequestError::Transport(
+                "Ответ с чужим кодом функции".to_string(),
+            )),
+        }
+    }
+
+    /// Прочитать `quantity` ячеек области начиная с `address`.
+    /// Биты возвращаются как 0/1.
+    pub async fn read_area(
+        &mut self,
+        area: ModbusArea,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, RequestError> {
+        let function = match area {
+            ModbusArea::Coil => FunctionCode::ReadCoils,
+            ModbusArea::DiscreteInput => FunctionCode::ReadDiscreteInputs,
+            ModbusArea::InputRegister => FunctionCode::ReadInputRegisters,
+            ModbusArea::HoldingRegister => FunctionCode::ReadHoldingRegisters,
+        };
+        let mut request = address.to_be_bytes().to_vec();
+        request.extend_from_slice(&quantity.to_be_bytes());
+
+        let data = self.request(function as u8, &request).await?;
+        let invalid = |msg: &str| RequestError::Transport(msg.to_string());
+        let payload = data
+            .get(1..)
+            .filter(|p| data[0] as usize == p.len())
+            .ok_or_else(|| invalid("Неверный счётчик байт в ответе"))?;
+
+        let cells: Vec<u16> = if matches!(area, ModbusArea::Coil | ModbusArea::DiscreteInput) {
+            payload
+                .iter()
+                .flat_map(|&byte| (0..8).map(move |i| ((byte >> i) & 1) as u16))
+                .take(quantity as usize)
+                .collect()
+        } else {
+            payload
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect()
+        };
+        if cells.len() < quantity as usize {
+            return Err(invalid("Ответ короче запрошенного"));
+        }
+        Ok(cells)
+    }
+
+    /// Прочитать значение тега.
+    pub async fn read_tag(&mut self, tag: &PollTag) -> Result<ModbusValue, RequestError> {
+        if matches!(tag.area, ModbusArea::Coil | ModbusArea::DiscreteInput) {
+            let bits = self.read_area(tag.area, tag.address, 1).await?;
+            return Ok(ModbusValue::Bool(bits[0] != 0));
+        }
+        let mut regs = self
+            .read_area(tag.area, tag.address, tag.data_type.register_count())
+            .await?;
+        tag.word_order.apply(&mut regs);
+        read_register_value(&regs, 0, &tag.data_type)
+            .ok_or_else(|| RequestError::Transport("Ответ короче запрошенного".to_string()))
+    }
+
+    /// Записать значение тега: coil — функцией 0x05, регистр — 0x06,
+    /// двухрегистровые типы — 0x10. Остальные области только для чтения.
+    pub async fn write_tag(
+        &mut self,
+        tag: &PollTag,
+        value: &ModbusValue,
+    ) -> Result<(), RequestError> {
+        let words = write_words(tag, value).map_err(RequestError::Transport)?;
+        let mut request = tag.address.to_be_bytes().to_vec();
+        let function = match (tag.area, &words[..]) {
+            (ModbusArea::Coil, [word]) => {
+                request.extend_from_slice(&word.to_be_bytes());
+                FunctionCode::WriteSingleCoil
+            }
+            (_, [word]) => {
+                request.extend_from_slice(&word.to_be_bytes());
+                FunctionCode::WriteSingleRegister
+            }
+            _ => {
+                request.extend_from_slice(&(words.len() as u16).to_be_bytes());
+                request.push(words.len() as u8 * 2);
+                request.extend(words.iter().flat_map(|word| word.to_be_bytes()));
+                FunctionCode::WriteMultipleRegisters
+            }
+        };
+        self.request(function as u8, &request).await.map(|_| ())
+    }
+}
+
+/// Слова, которые уйдут устройству при записи значения тега
+/// (для coil — 0xFF00 или 0x0000).
+pub fn write_words(tag: &PollTag, value: &ModbusValue) -> Result<Vec<u16>, String> {
+    match tag.area {
+        ModbusArea::Coil => Ok(vec![if value.as_bool() { 0xFF00 } else { 0x0000 }]),
+        ModbusArea::HoldingRegister => {
+            let mut regs = vec![0u16; tag.data_type.register_count() as usize];
+            write_register_value(&mut regs, 0, &tag.data_type, value);
+            tag.word_order.apply(&mut regs);
+            Ok(regs)
+        }
+        area => Err(format!("Область {:?} только для чтения", area)),
+    }
+}
+
+/// Опрашиваемый тег.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PollTag {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub area: ModbusArea,
+    pub address: u16,
+    pub data_type: ModbusDataType,
+    /// Порядок байт значения у устройства.
+    #[serde(default)]
+    pub word_order: WordOrder,
+}
+
+impl From<&ModbusVariable> for PollTag {
+    /// Тег с тем же адресом и типом данных, что у переменной проекта.
+    fn from(var: &ModbusVariable) -> Self {
+        Self {
+            id: var.id.clone(),
+            name: var.name.clone(),
+            area: var.area,
+            address: var.address,
+            data_type: var.data_type,
+            word_order: WordOrder::default(),
+        }
+    }
+}
+
+/// Настройки опроса.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PollConfig {
+    pub host: String,
+    pub port: u16,
+    pub unit_id: u8,
+    /// Период цикла опроса, мс.
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// Таймаут ответа, мс.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    pub tags: Vec<PollTag>,
+    /// Запись значений в файл; None — без записи.
+    #[serde(default)]
+    pub recording: Option<PollRecording>,
+}
+
+fn default_interval_ms() -> u64 {
+    1000
+}
+
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+/// Статистика опроса тега.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagStats {
+    pub tag_id: String,
+    pub last_value: Option<ModbusValue>,
+    /// Время последнего успешного чтения.
+    pub last_good_read: Option<String>,
+    pub success_count: u64,
+    pub failure_count: u64,
+    pub last_error: Option<String>,
+    /// Средняя задержка успешных ответов, мкс.
+    pub average_latency_us: Option<u64>,
+    #[serde(skip)]
+    total_latency_us: u64,
+}
+
+impl TagStats {
+    fn record(&mut self, result: Result<ModbusValue, RequestError>, latency: Duration) {
+        match result {
+            Ok(value) => {
+                self.last_value = Some(value);
+                self.last_good_read = Some(chrono_now_iso());
+                self.success_count += 1;
+                self.last_error = None;
+                self.total_latency_us += latency.as_micros() as u64;
+                self.average_latency_us = Some(self.total_latency_us / self.success_count);
+            }
+            Err(e) => {
+                self.failure_count += 1;
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Мастер, опрашивающий удалённое устройство.
+pub struct ModbusMaster {
+    running: AtomicBool,
+    stop_tx: RwLock<Option<watch::Sender<bool>>>,
+    stats: Arc<RwLock<Vec<TagStats>>>,
+    app_handle: RwLock<Option<AppHandle>>,
+}
+
+impl ModbusMaster {
+    pub fn new() -> Self {
+        Self {
+            running: AtomicBool::new(false),
+            stop_tx: RwLock::new(None),
+            stats: Arc::new(RwLock::new(Vec::new())),
+            app_handle: RwLock::new(None),
+        }
+    }
+
+    /// Установить AppHandle для отправки статистики в UI.
+    pub fn set_app_handle(&self, handle: AppHandle) {
+        *self.app_handle.write() = Some(handle);
+    }
+
+    /// Текущая статистика по тегам.
+    pub fn stats(&self) -> Vec<TagStats> {
+        self.stats.read().clone()
+    }
+
+    /// Начать опрос (статистика сбрасывается).
+    pub fn start(&self, config: PollConfig) -> Result<(), String> {
+        if config.tags.is_empty() {
+            return Err("Не заданы теги для опроса".to_string());
+        }
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err("Опрос уже запущен".to_string());
+        }
+        let recorder = match config
+            .recording
+            .as_ref()
+            .map(|recording| PollRecorder::open(recording, &config.tags))
+            .transpose()
+        {
+            Ok(recorder) => recorder,
+            Err(e) => {
+                self.running.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        };
+        *self.stats.write() = config
+            .tags
+            .iter()
+            .map(|tag| TagStats {
+                tag_id: tag.id.clone(),
+                ..TagStats::default()
+            })
+            .collect();
+
+        let (stop_tx, stop_rx) = watch::channel(false);
+        *self.stop_tx.write() = Some(stop_tx);
+        tauri::async_runtime::spawn(poll_loop(
+            config,
+            self.stats.clone(),
+            recorder,
+            self.app_handle.read().clone(),
+            stop_rx,
+        ));
+        Ok(())
+    }
+
+    /// Остановить опрос.
+    pub fn stop(&self) -> Result<(), String> {
+        if !self.running.swap(false, Ordering::SeqCst) {
+            return Err("Опрос не запущен".to_string());
+        }
+        if let Some(tx) = self.stop_tx.write().take() {
+            let _ = tx.send(true);
+        }
+        Ok(())
+    }
+}
+
+impl Default for ModbusMaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Цикл опроса: все теги по очереди, затем запись в файл и событие со
+/// статистикой. При потере соединения переподключается в следующем цикле.
+async fn poll_loop(
+    config: PollConfig,
+    stats: Arc<RwLock<Vec<TagStats>>>,
+    mut recorder: Option<PollRecorder>,
+    app_handle: Option<AppHandle>,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut interval = tokio::time::interval(Duration::from_millis(config.interval_ms.max(1)));
+    let mut connection: Option<MasterConnection> = None;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = stop_rx.changed() => break,
+        }
+
+        let timestamp = chrono_now_iso();
+        let mut samples: Vec<Result<f64, String>> = Vec::with_capacity(config.tags.len());
+        for (i, tag) in config.tags.iter().enumerate() {
+            if connection.is_none() {
+                match MasterConnection::connect(&config.host, config.port, config.unit_id, timeout)
+                    .await
+                {
+                    Ok(conn) => connection = Some(conn),
+                    Err(e) => {
+                        // Без соединения все оставшиеся теги цикла считаются сбоями
+                        for entry in stats.write()[i..].iter_mut() {
+                            entry.record(Err(RequestError::Transport(e.clone())), Duration::ZERO);
+                        }
+                        samples.resize(config.tags.len(), Err(e));
+                        break;
+                    }
+                }
+            }
+            let Some(conn) = connection.as_mut() else {
+                break;
+            };
+
+            let started = Instant::now();
+            let result = conn.read_tag(tag).await;
+            let latency = started.elapsed();
+            // Исключение — ответ устройства; прочие ошибки рвут соединение
+            if matches!(result, Err(RequestError::Transport(_))) {
+                connection = None;
+            }
+            samples.push(match &result {
+                Ok(value) => Ok(value.as_f64()),
+                Err(e) => Err(e.to_string()),
+            });
+            stats.write()[i].record(result, latency);
+        }
+
+        if let Some(writer) = recorder.as_mut() {
+            if let Err(e) = writer.record(&timestamp, &samples) {
+                // Опрос продолжается, запись прекращается
+                log::warn!("{}; запись опроса остановлена", e);
+                recorder = None;
+            }
+        }
+
+        if let Some(handle) = &app_handle {
+            if let Err(e) = handle.emit(POLL_STATS_EVENT_NAME, stats.read().clone()) {
+                log::warn!(
+                    "Не удалось отправить событие {}: {}",
+                    POLL_STATS_EVENT_NAME,
+                    e
+                );
+            }
+        }
+    }
+    log::info!("Опрос {}:{} остановлен", config.host, config.port);
+}
+
+/// Общая ссылка на мастер.
+pub type SharedModbusMaster = Arc<ModbusMaster>;
+
+/// Создать общий мастер.
+pub fn create_shared_master() -> SharedModbusMaster {
+    Arc::new(ModbusMaster::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::server::create_shared_server;
+
+    fn tag(id: &str, area: ModbusArea, address: u16, data_type: ModbusDataType) -> PollTag {
+        PollTag {
+            id: id.to_string(),
+            name: String::new(),
+            area,
+            address,
+            data_type,
+            word_order: WordOrder::default(),
+        }
+    }
+
+    #[test]
+    fn test_polling_collects_stats() {
+        tauri::async_runtime::block_on(async {
+            let port = std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            let store = create_shared_data_store();
+            let variable = |id: &str, address, data_type, value| ModbusVariable {
+                value: ModbusValue::Number(value),
+                ..ModbusVariable::new(id, ModbusArea::HoldingRegister, address, data_type)
+            };
+            // Устройство с float32 в порядке CDAB: младшее слово первым
+            let bits = 21.5f32.to_bits().rotate_left(16);
+            store.load_variables(&[
+                variable("temp", 10, ModbusDataType::Float32, 21.5),
+                variable("raw", 20, ModbusDataType::Uint32, bits as f64),
+            ]);
+            let server = create_shared_server(store);
+            server.set_config("127.0.0.1".to_string(), port, 1);
+            server.start().await.unwrap();
+
+            let master = ModbusMaster::new();
+            master
+                .start(PollConfig {
+                    host: "127.0.0.1".to_string(),
+                    port,
+                    unit_id: 1,
+                    interval_ms: 20,
+                    timeout_ms: 500,
+                    tags: vec![
+                        tag(
+                            "temp",
+                            ModbusArea::HoldingRegister,
+                            10,
+                            ModbusDataType::Float32,
+                        ),
+                        tag("missing", ModbusArea::Coil, 0, ModbusDataType::Bool),
+                        PollTag {
+                            word_order: WordOrder::Cdab,
+                            ..tag(
+                                "swapped",
+                                ModbusArea::HoldingRegister,
+                                20,
+                                ModbusDataType::Float32,
+                            )
+                        },
+                    ],
+                    recording: None,
+                })
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            master.stop().unwrap();
+            server.stop().unwrap();
+
+            let stats = master.stats();
+            assert!(stats[0].success_count > 0);
+            assert_eq!(stats[0].last_value, Some(ModbusValue::Number(21.5)));
+            assert!(stats[0].last_good_read.is_some());
+            assert!(stats[0].average_latency_us.is_some());
+            assert_eq!(stats[1].success_count, 0);
+            assert!(stats[1].failure_count > 0);
+            assert!(stats[1]
+                .last_error
+                .as_deref()
+                .unwrap()
+                .contains("Illegal Data Address"));
+            assert_eq!(stats[2].last_value, Some(ModbusValue::Number(21.5)));
+        });
+    }
+}
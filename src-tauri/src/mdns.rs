@@ -0,0 +1,405 @@
+//! Анонс запущенного симулятора через mDNS/DNS-SD (zeroconf).
+//!
+//! Пока сервер работает, в локальную сеть объявляется сервис (по умолчанию
+//! `_modbus._tcp.local`) с портом и именем устройства, а на запросы обзора
+//! отвечают записи PTR, SRV, TXT и A. При остановке рассылается «прощание»
+//! с нулевым TTL, чтобы инструменты обзора сразу убрали экземпляр из списка.
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+use tokio::sync::broadcast;
+
+/// Групповой адрес и порт mDNS.
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+/// Имя для перечисления всех типов сервисов (DNS-SD).
+const SERVICES_META_QUERY: &str = "_services._dns-sd._udp.local";
+
+/// TTL записей, секунды (рекомендация RFC 6762 для записей сервиса).
+const RECORD_TTL: u32 = 120;
+
+/// Интервал между повторными анонсами после запуска.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Типы и класс записей DNS.
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Бит cache-flush для уникальных записей (SRV, TXT, A).
+const CACHE_FLUSH: u16 = 0x8000;
+
+/// Настройки mDNS-анонса в профиле подключения.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MdnsSettings {
+    /// Объявлять ли сервис в сети.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Тип сервиса DNS-SD без домена `.local`.
+    #[serde(default = "default_service_type")]
+    pub service_type: String,
+    /// Имя экземпляра; пустое — используется имя профиля.
+    #[serde(default)]
+    pub device_name: String,
+}
+
+fn default_service_type() -> String {
+    "_modbus._tcp".to_string()
+}
+
+impl Default for MdnsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            service_type: default_service_type(),
+            device_name: String::new(),
+        }
+    }
+}
+
+/// Объявляемый экземпляр сервиса.
+#[derive(Debug, Clone)]
+pub struct MdnsService {
+    /// Имя экземпляра (например, «Стенд насосной»).
+    pub instance: String,
+    /// Тип сервиса без домена (`_modbus._tcp`).
+    pub service_type: String,
+    /// Имя хоста без домена.
+    pub host: String,
+    /// Адрес, по которому доступен сервер.
+    pub address: Ipv4Addr,
+    pub port: u16,
+    /// Пары `ключ=значение` для TXT-записи.
+    pub txt: Vec<String>,
+}
+
+impl MdnsService {
+    /// Собрать описание сервиса из настроек профиля и адреса прослушивания.
+    pub fn new(settings: &MdnsSettings, profile_name: &str, bind_host: &str, port: u16) -> Self {
+        // Точка разделяет метки DNS, поэтому в имени экземпляра не допускается
+        let instance = if settings.device_name.trim().is_empty() {
+            profile_name.trim().replace('.', "-")
+        } else {
+            settings.device_name.trim().replace('.', "-")
+        };
+        let host: String = instance
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        let host = match host.trim_matches('-') {
+            "" => "modbus-simulator".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+        let address = bind_host
+            .parse::<Ipv4Addr>()
+            .ok()
+            .filter(|ip| !ip.is_unspecified())
+            .or_else(local_ipv4)
+            .unwrap_or(Ipv4Addr::LOCALHOST);
+
+        Self {
+            instance,
+            service_type: settings.service_type.trim_end_matches(".local").to_string(),
+            host,
+            address,
+            port,
+            txt: vec!["txtvers=1".to_string()],
+        }
+    }
+
+    fn service_name(&self) -> String {
+        format!("{}.local", self.service_type)
+    }
+
+    fn instance_name(&self) -> String {
+        format!("{}.{}.local", self.instance, self.service_type)
+    }
+
+    fn host_name(&self) -> String {
+        format!("{}.local", self.host)
+    }
+
+    /// Должны ли мы ответить на вопрос с таким именем и типом.
+    fn answers(&self, name: &str, qtype: u16) -> bool {
+        let is = |expected: &str, types: &[u16]| {
+            name.eq_ignore_ascii_case(expected) && (qtype == TYPE_ANY || types.contains(&qtype))
+        };
+        is(SERVICES_META_QUERY, &[TYPE_PTR])
+            || is(&self.service_name(), &[TYPE_PTR])
+            || is(&self.instance_name(), &[TYPE_SRV, TYPE_TXT])
+            || is(&self.host_name(), &[TYPE_A])
+    }
+
+    /// Сформировать ответ mDNS со всеми записями сервиса.
+    /// TTL 0 — «прощание» при остановке сервера.
+    pub fn response_packet(&self, ttl: u32) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(256);
+        // Заголовок: ID 0, флаги «ответ, авторитетный», 0 вопросов, 5 ответов
+        for word in [0u16, 0x8400, 0, 5, 0, 0] {
+            packet.extend_from_slice(&word.to_be_bytes());
+        }
+
+        let mut ptr = Vec::new();
+        encode_name(&mut ptr, &self.service_name());
+        push_record(
+            &mut packet,
+            SERVICES_META_QUERY,
+            TYPE_PTR,
+            CLASS_IN,
+            ttl,
+            &ptr,
+        );
+
+        let mut ptr = Vec::new();
+        encode_name(&mut ptr, &self.instance_name());
+        push_record(
+            &mut packet,
+            &self.service_name(),
+            TYPE_PTR,
+            CLASS_IN,
+            ttl,
+            &ptr,
+        );
+
+        let mut srv = Vec::new();
+        srv.extend_from_slice(&0u16.to_be_bytes()); // priority
+        srv.extend_from_slice(&0u16.to_be_bytes()); // weight
+        srv.extend_from_slice(&self.port.to_be_bytes());
+        encode_name(&mut srv, &self.host_name());
+        let unique = CLASS_IN | CACHE_FLUSH;
+        push_record(
+            &mut packet,
+            &self.instance_name(),
+            TYPE_SRV,
+            unique,
+            ttl,
+            &srv,
+        );
+
+        let mut txt = Vec::new();
+        for entry in &self.txt {
+            let bytes = &entry.as_bytes()[..entry.len().min(255)];
+            txt.push(bytes.len() as u8);
+            txt.extend_from_slice(bytes);
+        }
+        push_record(
+            &mut packet,
+            &self.instance_name(),
+            TYPE_TXT,
+            unique,
+            ttl,
+            &txt,
+        );
+
+        push_record(
+            &mut packet,
+            &self.host_name(),
+            TYPE_A,
+            unique,
+            ttl,
+            &self.address.octets(),
+        );
+        packet
+    }
+}
+
+/// Закодировать доменное имя метками (без сжатия).
+fn encode_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.trim_end_matches('.').split('.') {
+        let bytes = &label.as_bytes()[..label.len().min(63)];
+        out.push(bytes.len() as u8);
+        out.extend_from_slice(bytes);
+    }
+    out.push(0);
+}
+
+/// Добавить ресурсную запись в пакет.
+fn push_record(out: &mut Vec<u8>, name: &str, rtype: u16, class: u16, ttl: u32, data: &[u8]) {
+    encode_name(out, name);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&ttl.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Прочитать имя по смещению (со сжатием). Возвращает имя и смещение за ним.
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Ограничение числа переходов защищает от циклических указателей
+    for _ in 0..32 {
+        let len = *packet.get(offset)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let pointer = ((len & 0x3F) << 8) | *packet.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+            continue;
+        }
+        let label = packet.get(offset + 1..offset + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        offset += 1 + len;
+    }
+    None
+}
+
+/// Разобрать вопросы mDNS-запроса: пары (имя, тип).
+/// Ответы (пакеты с флагом QR) не считаются запросами.
+pub fn parse_questions(packet: &[u8]) -> Vec<(String, u16)> {
+    if packet.len() < 12 || packet[2] & 0x80 != 0 {
+        return Vec::new();
+    }
+    let count = u16::from_be_bytes([packet[4], packet[5]]);
+    let mut questions = Vec::new();
+    let mut offset = 12;
+    for _ in 0..count {
+        let Some((name, next)) = read_name(packet, offset) else {
+            break;
+        };
+        let Some(qtype) = packet.get(next..next + 2) else {
+            break;
+        };
+        questions.push((name, u16::from_be_bytes([qtype[0], qtype[1]])));
+        offset = next + 4;
+    }
+    questions
+}
+
+/// Определить локальный IPv4-адрес, через который идёт групповой трафик.
+pub(crate) fn local_ipv4() -> Option<Ipv4Addr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((MDNS_GROUP, MDNS_PORT)).ok()?;
+    match socket.local_addr().ok()? {
+        SocketAddr::V4(addr) if !addr.ip().is_unspecified() => Some(*addr.ip()),
+        _ => None,
+    }
+}
+
+/// Открыть сокет mDNS с общим портом 5353 (рядом с системным респондером).
+fn open_socket() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MDNS_PORT).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Объявлять сервис до сигнала завершения.
+pub async fn advertise(service: MdnsService, mut shutdown_rx: broadcast::Receiver<()>) {
+    let socket = match open_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::warn!("mDNS недоступен: {}", e);
+            return;
+        }
+    };
+    let group = SocketAddr::from((MDNS_GROUP, MDNS_PORT));
+    let announcement = service.response_packet(RECORD_TTL);
+    log::info!(
+        "mDNS: объявлен {} на {}:{}",
+        service.instance_name(),
+        service.address,
+        service.port
+    );
+
+    // RFC 6762: не менее двух анонсов с интервалом в секунду
+    let mut announcements_left = 2;
+    let mut announce = tokio::time::interval(ANNOUNCE_INTERVAL);
+    let mut buffer = vec![0u8; 9000];
+
+    loop {
+        tokio::select! {
+            _ = announce.tick(), if announcements_left > 0 => {
+                announcements_left -= 1;
+                let _ = socket.send_to(&announcement, group).await;
+            }
+            received = socket.recv_from(&mut buffer) => {
+                let Ok((len, _)) = received else {
+                    continue;
+                };
+                let asked = parse_questions(&buffer[..len])
+                    .iter()
+                    .any(|(name, qtype)| service.answers(name, *qtype));
+                if asked {
+                    let _ = socket.send_to(&announcement, group).await;
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                let _ = socket.send_to(&service.response_packet(0), group).await;
+                log::info!("mDNS: анонс {} снят", service.instance_name());
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> MdnsService {
+        let settings = MdnsSettings {
+            enabled: true,
+            device_name: "Pump Station 1".to_string(),
+            ..Default::default()
+        };
+        MdnsService::new(&settings, "Профиль", "192.168.1.20", 10502)
+    }
+
+    #[test]
+    fn test_service_names() {
+        let service = service();
+        assert_eq!(service.instance_name(), "Pump Station 1._modbus._tcp.local");
+        assert_eq!(service.host_name(), "pump-station-1.local");
+        assert_eq!(service.address, Ipv4Addr::new(192, 168, 1, 20));
+    }
+
+    #[test]
+    fn test_answers_browse_query() {
+        let service = service();
+
+        // Запрос PTR _modbus._tcp.local
+        let mut query = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        encode_name(&mut query, "_modbus._tcp.local");
+        query.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        query.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+        let questions = parse_questions(&query);
+        assert_eq!(
+            questions,
+            vec![("_modbus._tcp.local".to_string(), TYPE_PTR)]
+        );
+        assert!(service.answers(&questions[0].0, questions[0].1));
+        assert!(!service.answers("_http._tcp.local", TYPE_PTR));
+    }
+
+    #[test]
+    fn test_response_packet_is_not_a_query() {
+        let packet = service().response_packet(RECORD_TTL);
+        assert_eq!(&packet[2..4], &[0x84, 0x00]);
+        assert_eq!(u16::from_be_bytes([packet[6], packet[7]]), 5);
+        assert!(parse_questions(&packet).is_empty());
+        // Имя первой записи читается обратно
+        let (name, _) = read_name(&packet, 12).unwrap();
+        assert_eq!(name, SERVICES_META_QUERY);
+    }
+}
@@ -0,0 +1,272 @@
+//! Сырые дампы памяти областей данных.
+//!
+//! Диапазон области выгружается в двоичный файл или в Intel HEX и может быть
+//! загружен обратно, поэтому образы, снятые с реального устройства, можно
+//! загрузить в симулятор без изменений.
+//!
+//! Раскладка байтов совпадает с кадрами Modbus: регистры — big-endian по 2 байта,
+//! биты (coils, discrete inputs) упакованы по 8 в байт, младший бит первым.
+//! Начало диапазона задаётся в соглашении об адресации проекта.
+
+use serde::{Deserialize, Serialize};
+
+use crate::addressing::AddressingConvention;
+use crate::data_store::ModbusDataStore;
+use crate::types::ModbusArea;
+
+/// Количество байт данных в одной записи Intel HEX.
+const HEX_RECORD_LEN: usize = 16;
+
+/// Формат файла дампа.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpFormat {
+    /// Двоичный файл без заголовка
+    Binary,
+    /// Текстовый Intel HEX (I32HEX)
+    IntelHex,
+}
+
+/// Является ли область битовой.
+fn is_bit_area(area: ModbusArea) -> bool {
+    matches!(area, ModbusArea::Coil | ModbusArea::DiscreteInput)
+}
+
+/// Упаковать ячейки области (биты как 0/1) в байты дампа.
+pub fn cells_to_bytes(area: ModbusArea, cells: &[u16]) -> Vec<u8> {
+    if is_bit_area(area) {
+        cells
+            .chunks(8)
+            .map(|bits| {
+                bits.iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, &bit)| byte | (((bit != 0) as u8) << i))
+            })
+            .collect()
+    } else {
+        cells.iter().flat_map(|reg| reg.to_be_bytes()).collect()
+    }
+}
+
+/// Распаковать байты дампа в ячейки области.
+/// Для битовых областей каждый байт даёт 8 ячеек; нечётный последний байт
+/// регистровой области игнорируется.
+pub fn bytes_to_cells(area: ModbusArea, bytes: &[u8]) -> Vec<u16> {
+    if is_bit_area(area) {
+        bytes
+            .iter()
+            .flat_map(|&byte| (0..8).map(move |i| ((byte >> i) & 1) as u16))
+            .collect()
+    } else {
+        bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+}
+
+/// Закодировать байты в Intel HEX, начиная с адреса 0.
+/// Для данных больше 64 КБ добавляются записи расширенного линейного адреса.
+pub fn encode_intel_hex(data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut upper = 0u16;
+    for (i, chunk) in data.chunks(HEX_RECORD_LEN).enumerate() {
+        let address = i * HEX_RECORD_LEN;
+        let chunk_upper = (address >> 16) as u16;
+        if chunk_upper != upper {
+            upper = chunk_upper;
+            push_hex_record(&mut out, 0, 0x04, &upper.to_be_bytes());
+        }
+        push_hex_record(&mut out, address as u16, 0x00, chunk);
+    }
+    push_hex_record(&mut out, 0, 0x01, &[]);
+    out
+}
+
+/// Добавить одну запись Intel HEX с контрольной суммой.
+fn push_hex_record(out: &mut String, address: u16, record_type: u8, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&address.to_be_bytes());
+    bytes.push(record_type);
+    bytes.extend_from_slice(data);
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, b| sum.wrapping_add(*b))
+        .wrapping_neg();
+    bytes.push(checksum);
+
+    out.push(':');
+    for b in bytes {
+        out.push_str(&format!("{b:02X}"));
+    }
+    out.push('\n');
+}
+
+/// Разобрать Intel HEX в непрерывный массив байт.
+/// Данные отсчитываются от наименьшего адреса в файле, пропуски заполняются нулями.
+pub fn decode_intel_hex(text: &str) -> Result<Vec<u8>, String> {
+    let mut base = 0u32;
+    let mut chunks: Vec<(u32, Vec<u8>)> = Vec::new();
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let err = |msg: &str| format!("Intel HEX, строка {}: {msg}", line_no + 1);
+        let hex = line
+            .strip_prefix(':')
+            .ok_or_else(|| err("запись должна начинаться с ':'"))?;
+        if hex.len() % 2 != 0 || hex.len() < 10 {
+            return Err(err("неверная длина записи"));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| err("недопустимый шестнадцатеричный символ"))?;
+
+        let len = bytes[0] as usize;
+        if bytes.len() != len + 5 {
+            return Err(err("длина данных не совпадает с заголовком"));
+        }
+        if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(err("неверная контрольная сумма"));
+        }
+        let address = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let data = &bytes[4..4 + len];
+
+        match bytes[3] {
+            0x00 => chunks.push((base + address, data.to_vec())),
+            0x01 => break,
+            0x02 if len == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4,
+            0x04 if len == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16,
+            // Стартовые адреса (0x03, 0x05) к данным не относятся
+            0x03 | 0x05 => {}
+            other => return Err(err(&format!("неподдерживаемый тип записи {other:02X}"))),
+        }
+    }
+
+    let Some(origin) = chunks.iter().map(|(address, _)| *address).min() else {
+        return Ok(Vec::new());
+    };
+    let end = chunks
+        .iter()
+        .map(|(address, data)| (address - origin) as usize + data.len())
+        .max()
+        .unwrap_or(0);
+    let mut out = vec![0u8; end];
+    for (address, data) in chunks {
+        let at = (address - origin) as usize;
+        out[at..at + data.len()].copy_from_slice(&data);
+    }
+    Ok(out)
+}
+
+/// Закодировать ячейки области в файл дампа выбранного формата.
+pub fn encode(format: DumpFormat, area: ModbusArea, cells: &[u16]) -> Vec<u8> {
+    let bytes = cells_to_bytes(area, cells);
+    match format {
+        DumpFormat::Binary => bytes,
+        DumpFormat::IntelHex => encode_intel_hex(&bytes).into_bytes(),
+    }
+}
+
+/// Разобрать файл дампа выбранного формата в ячейки области.
+pub fn decode(format: DumpFormat, area: ModbusArea, data: &[u8]) -> Result<Vec<u16>, String> {
+    let bytes = match format {
+        DumpFormat::Binary => data.to_vec(),
+        DumpFormat::IntelHex => {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| "Файл Intel HEX должен быть текстовым".to_string())?;
+            decode_intel_hex(text)?
+        }
+    };
+    Ok(bytes_to_cells(area, &bytes))
+}
+
+/// Выгрузить диапазон области с адреса `start` в соглашении `addressing`.
+/// Возвращает файл дампа и количество выгруженных ячеек.
+pub fn export_range(
+    data_store: &ModbusDataStore,
+    addressing: AddressingConvention,
+    area: ModbusArea,
+    start: u32,
+    count: usize,
+    format: DumpFormat,
+) -> Result<(Vec<u8>, usize), String> {
+    let start = addressing.to_protocol(area, start)?;
+    let cells = data_store.dump_area(area, start, count);
+    Ok((encode(format, area, &cells), cells.len()))
+}
+
+/// Загрузить файл дампа в область с адреса `start` в соглашении `addressing`.
+/// Возвращает количество записанных ячеек.
+pub fn import_range(
+    data_store: &ModbusDataStore,
+    addressing: AddressingConvention,
+    area: ModbusArea,
+    start: u32,
+    format: DumpFormat,
+    data: &[u8],
+) -> Result<usize, String> {
+    let start = addressing.to_protocol(area, start)?;
+    let cells = decode(format, area, data)?;
+    Ok(data_store.restore_area(area, start, &cells))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits_are_packed_lsb_first() {
+        let bits = [1, 0, 1, 1, 0, 0, 0, 0, 1];
+        let bytes = cells_to_bytes(ModbusArea::Coil, &bits);
+        assert_eq!(bytes, vec![0x0D, 0x01]);
+        assert_eq!(&bytes_to_cells(ModbusArea::Coil, &bytes)[..9], &bits);
+    }
+
+    #[test]
+    fn test_intel_hex_roundtrip_with_linear_address() {
+        let regs: Vec<u16> = (0..40_000u32).map(|i| i as u16).collect();
+        let data = encode(DumpFormat::IntelHex, ModbusArea::HoldingRegister, &regs);
+        let text = String::from_utf8(data.clone()).unwrap();
+        assert!(text.contains(":020000040001F9"));
+        assert!(text.ends_with(":00000001FF\n"));
+
+        let decoded = decode(DumpFormat::IntelHex, ModbusArea::HoldingRegister, &data).unwrap();
+        assert_eq!(decoded, regs);
+    }
+
+    #[test]
+    fn test_intel_hex_rejects_bad_checksum() {
+        let err = decode_intel_hex(":02000000123400\n").unwrap_err();
+        assert!(err.contains("контрольная сумма"));
+    }
+
+    #[test]
+    fn test_one_based_range_roundtrip() {
+        let store = ModbusDataStore::new();
+        let hr = ModbusArea::HoldingRegister;
+        store.restore_area(hr, 0, &[7, 8]);
+        let one_based = AddressingConvention::OneBased;
+
+        // Регистр 1 в проекте с адресацией от 1 — адрес протокола 0
+        let (data, count) = export_range(&store, one_based, hr, 1, 2, DumpFormat::Binary).unwrap();
+        assert_eq!((data.as_slice(), count), ([0, 7, 0, 8].as_slice(), 2));
+        assert_eq!(
+            import_range(&store, one_based, hr, 11, DumpFormat::Binary, &data),
+            Ok(2)
+        );
+        assert_eq!(store.dump_area(hr, 10, 2), [7, 8]);
+        assert!(export_range(&store, one_based, hr, 0, 1, DumpFormat::Binary).is_err());
+    }
+
+    #[test]
+    fn test_intel_hex_starts_at_lowest_address() {
+        // Данные с адреса 0x0100: дамп загружается с начала диапазона
+        let data = decode_intel_hex(":020100001234B7\n:00000001FF\n").unwrap();
+        assert_eq!(data, vec![0x12, 0x34]);
+    }
+}
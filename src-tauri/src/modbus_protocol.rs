@@ -11,8 +11,25 @@
 
 #![allow(dead_code)]
 
+pub mod ascii;
+pub mod decode;
+pub mod engine;
+pub mod golden;
+
 use std::io;
 
+/// Maximum Modbus TCP frame size (MBAP header + 253-byte PDU).
+pub const MAX_FRAME_SIZE: usize = 260;
+
+/// Largest valid MBAP length field: unit ID plus a 253-byte PDU.
+pub const MAX_MBAP_LENGTH: u16 = 254;
+
+/// Reference type of every file record sub-request.
+pub const FILE_RECORD_REFERENCE_TYPE: u8 = 0x06;
+
+/// Records per file addressable by file record functions (0..=9999).
+pub const MAX_FILE_RECORDS: usize = 10000;
+
 /// Modbus function codes supported by this slave simulator.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -33,6 +50,12 @@ pub enum FunctionCode {
     WriteMultipleCoils = 0x0F,
     /// Write Multiple Registers (0x10)
     WriteMultipleRegisters = 0x10,
+    /// Read File Record (0x14)
+    ReadFileRecord = 0x14,
+    /// Write File Record (0x15)
+    WriteFileRecord = 0x15,
+    /// Mask Write Register (0x16)
+    MaskWriteRegister = 0x16,
 }
 
 impl FunctionCode {
@@ -46,6 +69,9 @@ impl FunctionCode {
             0x06 => Some(FunctionCode::WriteSingleRegister),
             0x0F => Some(FunctionCode::WriteMultipleCoils),
             0x10 => Some(FunctionCode::WriteMultipleRegisters),
+            0x14 => Some(FunctionCode::ReadFileRecord),
+            0x15 => Some(FunctionCode::WriteFileRecord),
+            0x16 => Some(FunctionCode::MaskWriteRegister),
             _ => None,
         }
     }
@@ -78,31 +104,33 @@ impl MbapHeader {
     pub const SIZE: usize = 7;
 
     pub fn parse(data: &[u8]) -> io::Result<Self> {
-        if data.len() < Self::SIZE {
+        let header = Self::parse_unchecked(data)?;
+
+        // Protocol ID must be 0 for Modbus TCP
+        if header.protocol_id != 0 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
-                "MBAP header too short",
+                "Invalid protocol ID (must be 0 for Modbus TCP)",
             ));
         }
 
-        let transaction_id = u16::from_be_bytes([data[0], data[1]]);
-        let protocol_id = u16::from_be_bytes([data[2], data[3]]);
-        let length = u16::from_be_bytes([data[4], data[5]]);
-        let unit_id = data[6];
+        Ok(header)
+    }
 
-        // Protocol ID must be 0 for Modbus TCP
-        if protocol_id != 0 {
+    /// Parse the header without validating the protocol ID.
+    pub fn parse_unchecked(data: &[u8]) -> io::Result<Self> {
+        if data.len() < Self::SIZE {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
-                "Invalid protocol ID (must be 0 for Modbus TCP)",
+                "MBAP header too short",
             ));
         }
 
         Ok(Self {
-            transaction_id,
-            protocol_id,
-            length,
-            unit_id,
+            transaction_id: u16::from_be_bytes([data[0], data[1]]),
+            protocol_id: u16::from_be_bytes([data[2], data[3]]),
+            length: u16::from_be_bytes([data[4], data[5]]),
+            unit_id: data[6],
         })
     }
 
@@ -125,6 +153,13 @@ pub struct ModbusRequest {
 impl ModbusRequest {
     /// Parse a complete Modbus TCP frame from bytes.
     pub fn parse(data: &[u8]) -> io::Result<Self> {
+        MbapHeader::parse(data)?;
+        Self::parse_lenient(data)
+    }
+
+    /// Parse a complete frame accepting any protocol ID
+    /// (the caller decides whether the deviation is acceptable).
+    pub fn parse_lenient(data: &[u8]) -> io::Result<Self> {
         if data.len() < MbapHeader::SIZE + 1 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
@@ -132,7 +167,15 @@ impl ModbusRequest {
             ));
         }
 
-        let header = MbapHeader::parse(data)?;
+        let header = MbapHeader::parse_unchecked(data)?;
+
+        // Length must cover at least unit ID and function code
+        if header.length < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid length {} (must be at least 2)", header.length),
+            ));
+        }
 
         // Check if we have complete frame
         let expected_len = MbapHeader::SIZE - 1 + header.length as usize;
@@ -159,6 +202,39 @@ impl ModbusRequest {
         })
     }
 
+    /// PDU data length (after the function code) implied by the function
+    /// and its byte count field. Returns None for unknown functions.
+    pub fn expected_data_length(&self) -> Option<usize> {
+        match FunctionCode::from_u8(self.function_code)? {
+            FunctionCode::WriteMultipleCoils | FunctionCode::WriteMultipleRegisters => {
+                self.data.get(4).map(|&byte_count| 5 + byte_count as usize)
+            }
+            FunctionCode::MaskWriteRegister => Some(6),
+            FunctionCode::ReadFileRecord | FunctionCode::WriteFileRecord => {
+                self.data.first().map(|&byte_count| 1 + byte_count as usize)
+            }
+            _ => Some(4),
+        }
+    }
+
+    /// Start address and quantity addressed by the request.
+    /// Returns None for unknown functions, file record functions (they do
+    /// not address a data area) or truncated data.
+    pub fn address_range(&self) -> Option<(u16, u16)> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let start = u16::from_be_bytes([self.data[0], self.data[1]]);
+        let quantity = u16::from_be_bytes([self.data[2], self.data[3]]);
+        match FunctionCode::from_u8(self.function_code)? {
+            FunctionCode::WriteSingleCoil
+            | FunctionCode::WriteSingleRegister
+            | FunctionCode::MaskWriteRegister => Some((start, 1)),
+            FunctionCode::ReadFileRecord | FunctionCode::WriteFileRecord => None,
+            _ => Some((start, quantity)),
+        }
+    }
+
     /// Get the expected frame length from MBAP header.
     /// Returns None if buffer is too short to read header.
     pub fn expected_frame_length(data: &[u8]) -> Option<usize> {
@@ -168,6 +244,21 @@ impl ModbusRequest {
         let length = u16::from_be_bytes([data[4], data[5]]) as usize;
         Some(MbapHeader::SIZE - 1 + length)
     }
+
+    /// Transaction IDs of all complete frames buffered in `data`,
+    /// i.e. requests received but not yet answered.
+    pub fn buffered_transaction_ids(data: &[u8]) -> Vec<u16> {
+        let mut ids = Vec::new();
+        let mut offset = 0;
+        while let Some(frame_len) = Self::expected_frame_length(&data[offset..]) {
+            if data.len() - offset < frame_len {
+                break;
+            }
+            ids.push(u16::from_be_bytes([data[offset], data[offset + 1]]));
+            offset += frame_len;
+        }
+        ids
+    }
 }
 
 /// Modbus response builder.
@@ -296,7 +387,7 @@ impl WriteSingleCoilRequest {
         Ok(Self { address, value })
     }
 
-    pub fn to_response_data(&self) -> [u8; 4] {
+    pub fn to_response_data(self) -> [u8; 4] {
         let value_raw: u16 = if self.value { 0xFF00 } else { 0x0000 };
         let mut data = [0u8; 4];
         data[0..2].copy_from_slice(&self.address.to_be_bytes());
@@ -327,7 +418,7 @@ impl WriteSingleRegisterRequest {
         Ok(Self { address, value })
     }
 
-    pub fn to_response_data(&self) -> [u8; 4] {
+    pub fn to_response_data(self) -> [u8; 4] {
         let mut data = [0u8; 4];
         data[0..2].copy_from_slice(&self.address.to_be_bytes());
         data[2..4].copy_from_slice(&self.value.to_be_bytes());
@@ -356,7 +447,7 @@ impl WriteMultipleCoilsRequest {
         let quantity = u16::from_be_bytes([data[2], data[3]]);
         let byte_count = data[4] as usize;
 
-        let expected_bytes = (quantity as usize + 7) / 8;
+        let expected_bytes = (quantity as usize).div_ceil(8);
         if byte_count != expected_bytes || data.len() < 5 + byte_count {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
@@ -454,9 +545,201 @@ impl WriteMultipleRegistersRequest {
     }
 }
 
+/// Mask write register request (function 0x16).
+#[derive(Debug, Clone, Copy)]
+pub struct MaskWriteRegisterRequest {
+    pub address: u16,
+    pub and_mask: u16,
+    pub or_mask: u16,
+}
+
+impl MaskWriteRegisterRequest {
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        if data.len() < 6 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Mask write register request data too short",
+            ));
+        }
+
+        Ok(Self {
+            address: u16::from_be_bytes([data[0], data[1]]),
+            and_mask: u16::from_be_bytes([data[2], data[3]]),
+            or_mask: u16::from_be_bytes([data[4], data[5]]),
+        })
+    }
+
+    /// Register value after applying the masks:
+    /// `(current AND and_mask) OR (or_mask AND NOT and_mask)`.
+    pub fn apply(&self, current: u16) -> u16 {
+        (current & self.and_mask) | (self.or_mask & !self.and_mask)
+    }
+}
+
+/// File record reference of a read/write file record sub-request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileRecordRef {
+    pub reference_type: u8,
+    pub file_number: u16,
+    pub record_number: u16,
+    pub record_length: u16,
+}
+
+impl FileRecordRef {
+    /// Size of the sub-request header on the wire.
+    pub const SIZE: usize = 7;
+
+    fn parse(data: &[u8]) -> Self {
+        Self {
+            reference_type: data[0],
+            file_number: u16::from_be_bytes([data[1], data[2]]),
+            record_number: u16::from_be_bytes([data[3], data[4]]),
+            record_length: u16::from_be_bytes([data[5], data[6]]),
+        }
+    }
+
+    /// Reference type, file number and record range must address a valid
+    /// file record; otherwise Illegal Data Address. A zero record length is
+    /// an Illegal Data Value.
+    fn validate(&self) -> Result<(), ExceptionCode> {
+        if self.record_length == 0 {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        let end = self.record_number as usize + self.record_length as usize;
+        if self.reference_type != FILE_RECORD_REFERENCE_TYPE
+            || self.file_number == 0
+            || end > MAX_FILE_RECORDS
+        {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        Ok(())
+    }
+}
+
+/// Read file record request (function 0x14).
+#[derive(Debug, Clone)]
+pub struct ReadFileRecordRequest {
+    pub records: Vec<FileRecordRef>,
+}
+
+impl ReadFileRecordRequest {
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        let Some((&byte_count, rest)) = data.split_first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Read file record request data too short",
+            ));
+        };
+        let byte_count = byte_count as usize;
+        if !(0x07..=0xF5).contains(&byte_count)
+            || !byte_count.is_multiple_of(FileRecordRef::SIZE)
+            || rest.len() < byte_count
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid byte count in read file record request",
+            ));
+        }
+
+        let records = rest[..byte_count]
+            .chunks_exact(FileRecordRef::SIZE)
+            .map(FileRecordRef::parse)
+            .collect();
+        Ok(Self { records })
+    }
+
+    /// Every sub-request must be valid and the response must fit one PDU.
+    pub fn validate(&self) -> Result<(), ExceptionCode> {
+        for record in &self.records {
+            record.validate()?;
+        }
+        if self.response_length() > 0xF5 {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        Ok(())
+    }
+
+    /// Response data length: length and reference type bytes plus the
+    /// record data of every sub-request.
+    pub fn response_length(&self) -> usize {
+        self.records
+            .iter()
+            .map(|record| 2 + record.record_length as usize * 2)
+            .sum()
+    }
+
+    /// Response data for the records read, in sub-request order.
+    pub fn to_response_data(&self, records: &[Vec<u16>]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(1 + self.response_length());
+        data.push(self.response_length() as u8);
+        for values in records {
+            data.push((1 + values.len() * 2) as u8);
+            data.push(FILE_RECORD_REFERENCE_TYPE);
+            data.extend_from_slice(&pack_registers(values));
+        }
+        data
+    }
+}
+
+/// One sub-request of a write file record request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRecordWrite {
+    pub reference: FileRecordRef,
+    pub values: Vec<u16>,
+}
+
+/// Write file record request (function 0x15).
+#[derive(Debug, Clone)]
+pub struct WriteFileRecordRequest {
+    pub records: Vec<FileRecordWrite>,
+}
+
+impl WriteFileRecordRequest {
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid request data length in write file record request",
+            )
+        };
+        let (&data_length, rest) = data.split_first().ok_or_else(invalid)?;
+        let data_length = data_length as usize;
+        if !(0x09..=0xFB).contains(&data_length) || rest.len() < data_length {
+            return Err(invalid());
+        }
+
+        let mut records = Vec::new();
+        let mut rest = &rest[..data_length];
+        while !rest.is_empty() {
+            if rest.len() < FileRecordRef::SIZE {
+                return Err(invalid());
+            }
+            let reference = FileRecordRef::parse(rest);
+            let end = FileRecordRef::SIZE + reference.record_length as usize * 2;
+            if rest.len() < end {
+                return Err(invalid());
+            }
+            let values = rest[FileRecordRef::SIZE..end]
+                .chunks_exact(2)
+                .map(|word| u16::from_be_bytes([word[0], word[1]]))
+                .collect();
+            records.push(FileRecordWrite { reference, values });
+            rest = &rest[end..];
+        }
+        Ok(Self { records })
+    }
+
+    pub fn validate(&self) -> Result<(), ExceptionCode> {
+        for record in &self.records {
+            record.reference.validate()?;
+        }
+        Ok(())
+    }
+}
+
 /// Helper to pack boolean values into bytes (LSB first within each byte).
 pub fn pack_bits(bits: &[bool]) -> Vec<u8> {
-    let byte_count = (bits.len() + 7) / 8;
+    let byte_count = bits.len().div_ceil(8);
     let mut bytes = vec![0u8; byte_count];
 
     for (i, &bit) in bits.iter().enumerate() {
@@ -493,6 +776,30 @@ mod tests {
         assert_eq!(header.unit_id, 1);
     }
 
+    #[test]
+    fn test_lenient_parse_accepts_protocol_id() {
+        let frame = [
+            0x00, 0x01, 0x00, 0x07, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        assert!(ModbusRequest::parse(&frame).is_err());
+        let request = ModbusRequest::parse_lenient(&frame).unwrap();
+        assert_eq!(request.header.protocol_id, 7);
+        assert_eq!(request.expected_data_length(), Some(4));
+        assert_eq!(request.data.len(), 4);
+    }
+
+    #[test]
+    fn test_buffered_transaction_ids() {
+        let frame = [
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let mut buffer = frame.to_vec();
+        buffer.extend_from_slice(&frame);
+        // Incomplete trailing frame is not counted
+        buffer.extend_from_slice(&frame[..8]);
+        assert_eq!(ModbusRequest::buffered_transaction_ids(&buffer), vec![7, 7]);
+    }
+
     #[test]
     fn test_read_request_parse() {
         let data = [0x00, 0x00, 0x00, 0x0A]; // start=0, quantity=10
@@ -501,6 +808,53 @@ mod tests {
         assert_eq!(req.quantity, 10);
     }
 
+    #[test]
+    fn test_mask_write_register_parse() {
+        // Example from the specification: 0x12 AND 0xF2 OR 0x25 -> 0x17
+        let req = MaskWriteRegisterRequest::parse(&[0x00, 0x04, 0x00, 0xF2, 0x00, 0x25]).unwrap();
+        assert_eq!(req.address, 4);
+        assert_eq!(req.apply(0x12), 0x17);
+        assert!(MaskWriteRegisterRequest::parse(&[0x00, 0x04, 0x00, 0xF2]).is_err());
+    }
+
+    #[test]
+    fn test_file_record_requests_parse() {
+        // File 4, records 1..3 and file 3, record 9
+        let read = ReadFileRecordRequest::parse(&[
+            0x0E, 0x06, 0x00, 0x04, 0x00, 0x01, 0x00, 0x02, 0x06, 0x00, 0x03, 0x00, 0x09, 0x00,
+            0x01,
+        ])
+        .unwrap();
+        assert_eq!(read.records.len(), 2);
+        assert_eq!(read.records[1].record_number, 9);
+        assert_eq!(read.validate(), Ok(()));
+        assert_eq!(read.response_length(), 10);
+        assert_eq!(
+            read.to_response_data(&[vec![0x0DFE, 0x0020], vec![0x33CD]]),
+            vec![0x0A, 0x05, 0x06, 0x0D, 0xFE, 0x00, 0x20, 0x03, 0x06, 0x33, 0xCD]
+        );
+
+        let write = WriteFileRecordRequest::parse(&[
+            0x0B, 0x06, 0x00, 0x04, 0x00, 0x07, 0x00, 0x02, 0x06, 0xAF, 0x04, 0xBE,
+        ])
+        .unwrap();
+        assert_eq!(write.records[0].values, vec![0x06AF, 0x04BE]);
+        assert_eq!(write.validate(), Ok(()));
+
+        // Sub-request data shorter than its record length
+        assert!(WriteFileRecordRequest::parse(&[
+            0x09, 0x06, 0x00, 0x04, 0x00, 0x07, 0x00, 0x02, 0x06, 0xAF
+        ])
+        .is_err());
+        let bad_reference =
+            ReadFileRecordRequest::parse(&[0x07, 0x05, 0x00, 0x04, 0x00, 0x01, 0x00, 0x02])
+                .unwrap();
+        assert_eq!(
+            bad_reference.validate(),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+    }
+
     #[test]
     fn test_pack_bits() {
         let bits = vec![true, false, true, true, false, false, false, false, true];
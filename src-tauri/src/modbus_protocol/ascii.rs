@@ -0,0 +1,182 @@
+//! Modbus ASCII framing.
+//!
+//! An ASCII frame is `:` followed by the unit ID, the PDU and an LRC byte,
+//! each byte as two uppercase hex characters, and ends with CR LF. The
+//! server keeps its MBAP pipeline: [`AsciiDecoder`] turns received ASCII
+//! frames into MBAP frames, and [`mbap_to_ascii`] turns MBAP responses back.
+
+use super::MbapHeader;
+
+/// Longest ASCII frame: `:`, unit ID + 253-byte PDU + LRC as hex, CR LF.
+pub const MAX_ASCII_FRAME_SIZE: usize = 1 + 2 * (1 + 253 + 1) + 2;
+
+/// Longitudinal redundancy check: two's complement of the byte sum.
+pub fn lrc(data: &[u8]) -> u8 {
+    data.iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+        .wrapping_neg()
+}
+
+/// Encode unit ID and PDU as an ASCII frame.
+pub fn encode(unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(pdu.len() + 2);
+    body.push(unit_id);
+    body.extend_from_slice(pdu);
+    body.push(lrc(&body));
+
+    let mut frame = Vec::with_capacity(body.len() * 2 + 3);
+    frame.push(b':');
+    for byte in body {
+        frame.extend_from_slice(format!("{:02X}", byte).as_bytes());
+    }
+    frame.extend_from_slice(b"\r\n");
+    frame
+}
+
+/// Decode an ASCII frame into unit ID and PDU, checking the LRC.
+pub fn decode(frame: &[u8]) -> Result<(u8, Vec<u8>), String> {
+    let hex = frame
+        .strip_prefix(b":")
+        .and_then(|f| f.strip_suffix(b"\r\n"))
+        .ok_or_else(|| "ASCII frame must start with ':' and end with CR LF".to_string())?;
+    if hex.len() % 2 != 0 {
+        return Err("Odd number of hex characters in ASCII frame".to_string());
+    }
+    let bytes = hex
+        .chunks_exact(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid hex byte '{}' in ASCII frame",
+                        String::from_utf8_lossy(pair)
+                    )
+                })
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    // Unit ID, function code and LRC at least
+    let Some((&received, body)) = bytes.split_last().filter(|(_, body)| body.len() >= 2) else {
+        return Err("ASCII frame too short".to_string());
+    };
+    let computed = lrc(body);
+    if received != computed {
+        return Err(format!(
+            "LRC 0x{:02X} does not match computed 0x{:02X}",
+            received, computed
+        ));
+    }
+    Ok((body[0], body[1..].to_vec()))
+}
+
+/// Convert an MBAP frame to an ASCII frame with the same unit ID and PDU.
+pub fn mbap_to_ascii(frame: &[u8]) -> Option<Vec<u8>> {
+    let header = MbapHeader::parse_unchecked(frame).ok()?;
+    Some(encode(header.unit_id, &frame[MbapHeader::SIZE..]))
+}
+
+/// Splits a byte stream into ASCII frames and converts them to MBAP frames.
+#[derive(Debug, Default)]
+pub struct AsciiDecoder {
+    buffer: Vec<u8>,
+    /// Transaction ID of the next MBAP frame. ASCII has none, so every
+    /// frame gets its own to keep duplicate detection quiet.
+    next_transaction_id: u16,
+}
+
+impl AsciiDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append received bytes.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Take the next complete frame as an MBAP frame, or the decoding error.
+    /// Bytes before `:` are skipped; a new `:` restarts the frame.
+    pub fn next_frame(&mut self) -> Option<Result<Vec<u8>, String>> {
+        let end = loop {
+            let Some(start) = self.buffer.iter().position(|&b| b == b':') else {
+                self.buffer.clear();
+                return None;
+            };
+            self.buffer.drain(..start);
+            let end = self.buffer.windows(2).position(|w| w == b"\r\n");
+            // A ':' before the end means the current frame was cut off
+            let scan_end = end.unwrap_or(self.buffer.len());
+            match self.buffer[1..scan_end].iter().position(|&b| b == b':') {
+                Some(offset) => {
+                    self.buffer.drain(..offset + 1);
+                }
+                None => break end,
+            }
+        };
+        let Some(end) = end else {
+            if self.buffer.len() > MAX_ASCII_FRAME_SIZE {
+                self.buffer.clear();
+                return Some(Err("ASCII frame exceeds maximum length".to_string()));
+            }
+            return None;
+        };
+        let frame: Vec<u8> = self.buffer.drain(..end + 2).collect();
+
+        Some(decode(&frame).map(|(unit_id, pdu)| {
+            let transaction_id = self.next_transaction_id;
+            self.next_transaction_id = self.next_transaction_id.wrapping_add(1);
+            let mut mbap = Vec::with_capacity(MbapHeader::SIZE + pdu.len());
+            MbapHeader {
+                transaction_id,
+                protocol_id: 0,
+                length: pdu.len() as u16 + 1,
+                unit_id,
+            }
+            .write_to(&mut mbap);
+            mbap.extend_from_slice(&pdu);
+            mbap
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_roundtrip_and_lrc() {
+        // Read 3 holding registers at 0x006B of unit 0x11
+        let frame = encode(0x11, &[0x03, 0x00, 0x6B, 0x00, 0x03]);
+        assert_eq!(frame, b":1103006B00037E\r\n");
+        assert_eq!(
+            decode(&frame),
+            Ok((0x11, vec![0x03, 0x00, 0x6B, 0x00, 0x03]))
+        );
+        assert!(decode(b":1103006B00037F\r\n").unwrap_err().contains("LRC"));
+        assert!(decode(b":1103006B00037E").is_err());
+    }
+
+    #[test]
+    fn test_decoder_resyncs_on_noise_and_split_frames() {
+        let mut decoder = AsciiDecoder::new();
+        decoder.push(b"\x00noise:1103006B");
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(b"00037E\r\n:11030");
+        let mbap = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(
+            mbap,
+            [0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]
+        );
+        assert_eq!(decoder.next_frame(), None);
+
+        // The cut-off frame is dropped when a new one starts
+        decoder.push(b":1103006B00037E\r\n");
+        let mbap = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(mbap[..2], [0x00, 0x01]);
+        assert_eq!(
+            mbap_to_ascii(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x11, 0x83, 0x02]),
+            Some(b":1183026A\r\n".to_vec())
+        );
+    }
+}
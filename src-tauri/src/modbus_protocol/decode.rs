@@ -0,0 +1,561 @@
+//! Structured decoding of arbitrary Modbus frames for the log view.
+//!
+//! A frame is Modbus TCP (MBAP header + PDU), Modbus RTU (address + PDU +
+//! CRC-16) or Modbus ASCII (`:`, hex address + PDU + LRC, CR LF). Unless the
+//! caller names the framing, it is detected: a consistent MBAP header wins,
+//! then a leading `:`, then a valid RTU CRC. The PDU is
+//! classified as request, response or exception by its function code and
+//! length, and register payloads are additionally shown as signed and 32-bit
+//! values (big-endian word order), so the UI does not need its own protocol
+//! parser.
+
+use serde::{Deserialize, Serialize};
+
+use super::ascii::lrc;
+use super::{FileRecordRef, FunctionCode, MbapHeader, FILE_RECORD_REFERENCE_TYPE};
+use crate::types::{exception_code_name, function_code_name};
+
+/// Frame encapsulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Framing {
+    Tcp,
+    Rtu,
+    Ascii,
+}
+
+/// What the PDU carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FrameKind {
+    Request,
+    Response,
+    Exception,
+    /// Single, mask and file record writes: the response echoes the request.
+    RequestOrResponse,
+    /// Unsupported function or a length that fits neither direction.
+    Unknown,
+}
+
+/// MBAP header fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MbapFields {
+    pub transaction_id: u16,
+    pub protocol_id: u16,
+    pub length: u16,
+    pub unit_id: u8,
+}
+
+/// RTU checksum as received and as computed over the frame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrcCheck {
+    pub received: u16,
+    pub computed: u16,
+    pub valid: bool,
+}
+
+/// Named PDU field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedField {
+    pub name: String,
+    pub value: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Decoded data values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Payload {
+    /// Register words with their common interpretations. 32-bit values are
+    /// built from consecutive word pairs, high word first.
+    #[serde(rename_all = "camelCase")]
+    Registers {
+        registers: Vec<u16>,
+        int16: Vec<i16>,
+        uint32: Vec<u32>,
+        int32: Vec<i32>,
+        float32: Vec<f32>,
+    },
+    /// Packed bits, LSB of the first byte first (trailing padding included).
+    Bits { bits: Vec<bool> },
+}
+
+/// Structured breakdown of a frame.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedFrame {
+    pub framing: Framing,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mbap: Option<MbapFields>,
+    pub unit_id: u8,
+    pub function_code: u8,
+    pub function_name: String,
+    pub kind: FrameKind,
+    pub fields: Vec<DecodedField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Payload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crc: Option<CrcCheck>,
+    /// ASCII checksum (LRC) as received and as computed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lrc: Option<CrcCheck>,
+    /// Inconsistencies that did not prevent decoding.
+    pub warnings: Vec<String>,
+}
+
+/// Parse hex text with or without separators ("00 01 FF", "0001ff").
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':' && *c != '-')
+        .collect();
+    if !digits.is_ascii() {
+        return Err("Hex text contains non-hex characters".to_string());
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err("Odd number of hex digits".to_string());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| format!("Invalid hex byte '{}'", &digits[i..i + 2]))
+        })
+        .collect()
+}
+
+/// Modbus RTU CRC-16 (polynomial 0xA001, initial value 0xFFFF).
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u16, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn tcp_consistent(frame: &[u8]) -> bool {
+    MbapHeader::parse(frame)
+        .is_ok_and(|h| frame.len() > MbapHeader::SIZE && h.length as usize == frame.len() - 6)
+}
+
+fn rtu_crc(frame: &[u8]) -> CrcCheck {
+    let (body, crc) = frame.split_at(frame.len() - 2);
+    let received = u16::from_le_bytes([crc[0], crc[1]]);
+    let computed = crc16(body);
+    CrcCheck {
+        received,
+        computed,
+        valid: received == computed,
+    }
+}
+
+/// Decode a frame. `framing` forces the encapsulation; `None` detects it.
+pub fn decode_frame(frame: &[u8], framing: Option<Framing>) -> Result<DecodedFrame, String> {
+    let framing = match framing {
+        Some(framing) => framing,
+        None if tcp_consistent(frame) => Framing::Tcp,
+        None if frame.first() == Some(&b':') => Framing::Ascii,
+        None if frame.len() >= 4 && rtu_crc(frame).valid => Framing::Rtu,
+        None => {
+            return Err(
+                "Frame is neither a consistent MBAP frame nor an RTU frame with a valid CRC"
+                    .to_string(),
+            )
+        }
+    };
+
+    let mut warnings = Vec::new();
+    let ascii_bytes;
+    let mut lrc_check = None;
+    let (mbap, unit_id, pdu, crc) = match framing {
+        Framing::Tcp => {
+            if frame.len() < MbapHeader::SIZE + 1 {
+                return Err("Frame too short for MBAP header and function code".to_string());
+            }
+            let header = MbapHeader::parse_unchecked(frame).map_err(|e| e.to_string())?;
+            if header.protocol_id != 0 {
+                warnings.push(format!(
+                    "Protocol ID {} (must be 0 for Modbus TCP)",
+                    header.protocol_id
+                ));
+            }
+            let declared = header.length as usize + 6;
+            if declared != frame.len() {
+                warnings.push(format!(
+                    "MBAP length declares {} bytes, frame has {}",
+                    declared,
+                    frame.len()
+                ));
+            }
+            let end = declared.clamp(MbapHeader::SIZE + 1, frame.len());
+            let mbap = MbapFields {
+                transaction_id: header.transaction_id,
+                protocol_id: header.protocol_id,
+                length: header.length,
+                unit_id: header.unit_id,
+            };
+            (
+                Some(mbap),
+                header.unit_id,
+                &frame[MbapHeader::SIZE..end],
+                None,
+            )
+        }
+        Framing::Rtu => {
+            if frame.len() < 4 {
+                return Err("Frame too short for address, function code and CRC".to_string());
+            }
+            let crc = rtu_crc(frame);
+            if !crc.valid {
+                warnings.push(format!(
+                    "CRC 0x{:04X} does not match computed 0x{:04X}",
+                    crc.received, crc.computed
+                ));
+            }
+            (None, frame[0], &frame[1..frame.len() - 2], Some(crc))
+        }
+        Framing::Ascii => {
+            let text = frame
+                .strip_prefix(b":")
+                .ok_or_else(|| "ASCII frame must start with ':'".to_string())?;
+            let text = match text.strip_suffix(b"\r\n") {
+                Some(text) => text,
+                None => {
+                    warnings.push("ASCII frame does not end with CR LF".to_string());
+                    text
+                }
+            };
+            ascii_bytes = std::str::from_utf8(text)
+                .map_err(|_| "ASCII frame contains non-hex characters".to_string())
+                .and_then(parse_hex)?;
+            if ascii_bytes.len() < 3 {
+                return Err("Frame too short for address, function code and LRC".to_string());
+            }
+            let (&received, body) = ascii_bytes.split_last().expect("checked length");
+            let computed = lrc(body);
+            if received != computed {
+                warnings.push(format!(
+                    "LRC 0x{:02X} does not match computed 0x{:02X}",
+                    received, computed
+                ));
+            }
+            lrc_check = Some(CrcCheck {
+                received: received as u16,
+                computed: computed as u16,
+                valid: received == computed,
+            });
+            (None, body[0], &body[1..], None)
+        }
+    };
+
+    let function_code = pdu[0];
+    let (kind, fields, payload) = decode_pdu(function_code, &pdu[1..], &mut warnings);
+    Ok(DecodedFrame {
+        framing,
+        mbap,
+        unit_id,
+        function_code,
+        function_name: function_code_name(function_code & 0x7F).to_string(),
+        kind,
+        fields,
+        payload,
+        crc,
+        lrc: lrc_check,
+        warnings,
+    })
+}
+
+fn field(name: &str, value: u16) -> DecodedField {
+    DecodedField {
+        name: name.to_string(),
+        value,
+        description: None,
+    }
+}
+
+fn word(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn registers(data: &[u8]) -> Payload {
+    let registers: Vec<u16> = data.chunks_exact(2).map(|w| word(w, 0)).collect();
+    let pairs: Vec<u32> = registers
+        .chunks_exact(2)
+        .map(|p| ((p[0] as u32) << 16) | p[1] as u32)
+        .collect();
+    Payload::Registers {
+        int16: registers.iter().map(|&r| r as i16).collect(),
+        int32: pairs.iter().map(|&v| v as i32).collect(),
+        float32: pairs.iter().map(|&v| f32::from_bits(v)).collect(),
+        uint32: pairs,
+        registers,
+    }
+}
+
+fn bits(data: &[u8]) -> Payload {
+    Payload::Bits {
+        bits: data
+            .iter()
+            .flat_map(|byte| (0..8).map(move |i| byte & (1 << i) != 0))
+            .collect(),
+    }
+}
+
+/// Decode file record sub-requests (or read sub-responses, told apart by
+/// their odd length byte in place of the reference type). `None` if they
+/// do not fill the data exactly.
+fn decode_file_records(
+    function: FunctionCode,
+    data: &[u8],
+) -> Option<(FrameKind, Vec<DecodedField>, Option<Payload>)> {
+    let (&byte_count, mut rest) = data.split_first()?;
+    if byte_count as usize != rest.len() {
+        return None;
+    }
+    let read_response = function == FunctionCode::ReadFileRecord
+        && rest.first() != Some(&FILE_RECORD_REFERENCE_TYPE);
+    let mut fields = vec![field("byteCount", byte_count as u16)];
+    let mut values = Vec::new();
+    while !rest.is_empty() {
+        if read_response {
+            let (&length, tail) = rest.split_first()?;
+            let length = length as usize;
+            if length == 0 || tail.len() < length {
+                return None;
+            }
+            fields.push(field("fileResponseLength", length as u16));
+            values.extend_from_slice(&tail[1..length]);
+            rest = &tail[length..];
+            continue;
+        }
+        if rest.len() < FileRecordRef::SIZE {
+            return None;
+        }
+        let record_length = word(rest, 5);
+        fields.push(field("fileNumber", word(rest, 1)));
+        fields.push(field("recordNumber", word(rest, 3)));
+        fields.push(field("recordLength", record_length));
+        let end = match function {
+            FunctionCode::WriteFileRecord => FileRecordRef::SIZE + record_length as usize * 2,
+            _ => FileRecordRef::SIZE,
+        };
+        if rest.len() < end {
+            return None;
+        }
+        values.extend_from_slice(&rest[FileRecordRef::SIZE..end]);
+        rest = &rest[end..];
+    }
+    let kind = match function {
+        FunctionCode::WriteFileRecord => FrameKind::RequestOrResponse,
+        _ if read_response => FrameKind::Response,
+        _ => FrameKind::Request,
+    };
+    let payload = (!values.is_empty()).then(|| registers(&values));
+    Some((kind, fields, payload))
+}
+
+/// Classify the PDU and decode its fields. `data` follows the function code.
+fn decode_pdu(
+    function_code: u8,
+    data: &[u8],
+    warnings: &mut Vec<String>,
+) -> (FrameKind, Vec<DecodedField>, Option<Payload>) {
+    if function_code & 0x80 != 0 {
+        let Some(&code) = data.first() else {
+            warnings.push("Exception response without exception code".to_string());
+            return (FrameKind::Exception, Vec::new(), None);
+        };
+        let exception = DecodedField {
+            description: Some(exception_code_name(code).to_string()),
+            ..field("exceptionCode", code as u16)
+        };
+        return (FrameKind::Exception, vec![exception], None);
+    }
+
+    let Some(function) = FunctionCode::from_u8(function_code) else {
+        warnings.push(format!("Unsupported function 0x{:02X}", function_code));
+        return (FrameKind::Unknown, Vec::new(), None);
+    };
+    let is_bits = matches!(
+        function,
+        FunctionCode::ReadCoils
+            | FunctionCode::ReadDiscreteInputs
+            | FunctionCode::WriteMultipleCoils
+    );
+    let address_and_count = |second: &str| {
+        vec![
+            field("address", word(data, 0)),
+            field(second, word(data, 2)),
+        ]
+    };
+
+    match function {
+        FunctionCode::ReadCoils
+        | FunctionCode::ReadDiscreteInputs
+        | FunctionCode::ReadHoldingRegisters
+        | FunctionCode::ReadInputRegisters => {
+            if data.len() == 4 {
+                return (FrameKind::Request, address_and_count("quantity"), None);
+            }
+            if let Some((&byte_count, values)) = data.split_first() {
+                if byte_count as usize == values.len() {
+                    let payload = if is_bits {
+                        bits(values)
+                    } else {
+                        registers(values)
+                    };
+                    if !is_bits && byte_count % 2 != 0 {
+                        warnings.push(format!("Odd register byte count {}", byte_count));
+                    }
+                    return (
+                        FrameKind::Response,
+                        vec![field("byteCount", byte_count as u16)],
+                        Some(payload),
+                    );
+                }
+            }
+        }
+        FunctionCode::WriteSingleCoil | FunctionCode::WriteSingleRegister if data.len() == 4 => {
+            let mut value = field("value", word(data, 2));
+            if function == FunctionCode::WriteSingleCoil {
+                value.description = Some(
+                    match value.value {
+                        0xFF00 => "ON",
+                        0x0000 => "OFF",
+                        _ => "invalid coil value",
+                    }
+                    .to_string(),
+                );
+            }
+            return (
+                FrameKind::RequestOrResponse,
+                vec![field("address", word(data, 0)), value],
+                None,
+            );
+        }
+        FunctionCode::WriteMultipleCoils | FunctionCode::WriteMultipleRegisters => {
+            if data.len() == 4 {
+                return (FrameKind::Response, address_and_count("quantity"), None);
+            }
+            if data.len() >= 5 && data[4] as usize + 5 == data.len() {
+                let mut fields = address_and_count("quantity");
+                fields.push(field("byteCount", data[4] as u16));
+                let values = &data[5..];
+                let payload = if is_bits {
+                    bits(values)
+                } else {
+                    registers(values)
+                };
+                return (FrameKind::Request, fields, Some(payload));
+            }
+        }
+        FunctionCode::MaskWriteRegister if data.len() == 6 => {
+            return (
+                FrameKind::RequestOrResponse,
+                vec![
+                    field("address", word(data, 0)),
+                    field("andMask", word(data, 2)),
+                    field("orMask", word(data, 4)),
+                ],
+                None,
+            );
+        }
+        FunctionCode::ReadFileRecord | FunctionCode::WriteFileRecord => {
+            if let Some(decoded) = decode_file_records(function, data) {
+                return decoded;
+            }
+        }
+        _ => {}
+    }
+
+    warnings.push(format!(
+        "{} data bytes fit neither a request nor a response of function 0x{:02X}",
+        data.len(),
+        function_code
+    ));
+    (FrameKind::Unknown, Vec::new(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tcp_read_response_with_interpretations() {
+        let frame = parse_hex("00 01 00 00 00 07 01 03 04 41 48 00 00").unwrap();
+        let decoded = decode_frame(&frame, None).unwrap();
+        assert_eq!(decoded.framing, Framing::Tcp);
+        assert_eq!(decoded.mbap.as_ref().unwrap().transaction_id, 1);
+        assert_eq!(decoded.kind, FrameKind::Response);
+        assert!(decoded.warnings.is_empty());
+        let Some(Payload::Registers { float32, int16, .. }) = decoded.payload else {
+            panic!("expected registers");
+        };
+        assert_eq!(float32, [12.5]);
+        assert_eq!(int16, [0x4148, 0]);
+    }
+
+    #[test]
+    fn test_rtu_request_and_exception() {
+        // Read 10 holding registers from address 0 of unit 1
+        assert!(parse_hex("010300000000A").is_err());
+        let mut frame = parse_hex("01 03 00 00 00 0A").unwrap();
+        let crc = crc16(&frame);
+        assert_eq!(crc, 0xCDC5);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        let decoded = decode_frame(&frame, None).unwrap();
+        assert_eq!(decoded.framing, Framing::Rtu);
+        assert_eq!(decoded.kind, FrameKind::Request);
+        assert_eq!(decoded.fields[1], field("quantity", 10));
+
+        let exception = decode_frame(&parse_hex("0001000000030183 02").unwrap(), None).unwrap();
+        assert_eq!(exception.kind, FrameKind::Exception);
+        assert_eq!(
+            exception.fields[0].description.as_deref(),
+            Some("Illegal Data Address")
+        );
+
+        let bad_crc = decode_frame(&[0x01, 0x03, 0x00, 0x00], Some(Framing::Rtu)).unwrap();
+        assert!(!bad_crc.crc.unwrap().valid);
+        assert!(decode_frame(&[0x01, 0x03, 0x00, 0x00], None).is_err());
+    }
+
+    #[test]
+    fn test_ascii_request() {
+        let decoded = decode_frame(b":1103006B00037E\r\n", None).unwrap();
+        assert_eq!(decoded.framing, Framing::Ascii);
+        assert_eq!(decoded.unit_id, 0x11);
+        assert_eq!(decoded.kind, FrameKind::Request);
+        assert_eq!(decoded.fields[0], field("address", 0x6B));
+        assert!(decoded.lrc.unwrap().valid);
+
+        let bad = decode_frame(b":1103006B000300\r\n", None).unwrap();
+        assert_eq!(bad.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_file_record_request_and_response() {
+        let request = parse_hex("00 01 00 00 00 0A 01 14 07 06 00 04 00 01 00 02").unwrap();
+        let decoded = decode_frame(&request, None).unwrap();
+        assert_eq!(decoded.function_name, "Read File Record");
+        assert_eq!(decoded.kind, FrameKind::Request);
+        assert_eq!(decoded.fields[1], field("fileNumber", 4));
+        assert_eq!(decoded.fields[3], field("recordLength", 2));
+
+        let response = parse_hex("00 01 00 00 00 09 01 14 06 05 06 0D FE 00 20").unwrap();
+        let decoded = decode_frame(&response, None).unwrap();
+        assert_eq!(decoded.kind, FrameKind::Response);
+        let Some(Payload::Registers { registers, .. }) = decoded.payload else {
+            panic!("expected registers");
+        };
+        assert_eq!(registers, [0x0DFE, 0x0020]);
+    }
+}
@@ -0,0 +1,565 @@
+//! Sans-io protocol core shared by every transport.
+//!
+//! The core never touches sockets or timers. A transport feeds received
+//! bytes into a [`FrameDecoder`], parses each complete frame into a
+//! [`ModbusRequest`] and hands it to [`process_request`], which runs the
+//! pipeline `parse → action → response`:
+//! - [`Action::decode`] validates the request PDU into a typed action;
+//! - [`Action::execute`] applies it to a [`DataModel`] and yields a [`Reply`];
+//! - [`Reply::encode`] builds the response frame.
+//!
+//! Each step is a plain function of its inputs, so protocol behaviour can be
+//! unit-tested without sockets, and new transports (UDP, serial, replay)
+//! only have to provide framing and I/O.
+
+use std::collections::HashSet;
+
+use super::{
+    pack_bits, pack_registers, ExceptionCode, FunctionCode, MaskWriteRegisterRequest, MbapHeader,
+    ModbusRequest, ModbusResponse, ReadFileRecordRequest, ReadRequest, WriteFileRecordRequest,
+    WriteMultipleCoilsRequest, WriteMultipleRegistersRequest, WriteSingleCoilRequest,
+    WriteSingleRegisterRequest, MAX_FRAME_SIZE, MAX_MBAP_LENGTH,
+};
+
+/// Data areas a request is executed against.
+pub trait DataModel {
+    fn read_coils(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode>;
+    fn read_discrete_inputs(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode>;
+    fn read_holding_registers(&self, start: u16, count: u16) -> Result<Vec<u16>, ExceptionCode>;
+    fn read_input_registers(&self, start: u16, count: u16) -> Result<Vec<u16>, ExceptionCode>;
+    fn write_single_coil(&self, address: u16, value: bool) -> Result<(), ExceptionCode>;
+    fn write_single_register(&self, address: u16, value: u16) -> Result<(), ExceptionCode>;
+    fn write_multiple_coils(&self, start: u16, values: &[bool]) -> Result<(), ExceptionCode>;
+    fn write_multiple_registers(&self, start: u16, values: &[u16]) -> Result<(), ExceptionCode>;
+    /// Apply the AND/OR masks to one holding register atomically.
+    fn mask_write_register(&self, write: &MaskWriteRegisterRequest) -> Result<(), ExceptionCode>;
+    /// Read the records of every sub-request, in sub-request order.
+    fn read_file_record(
+        &self,
+        read: &ReadFileRecordRequest,
+    ) -> Result<Vec<Vec<u16>>, ExceptionCode>;
+    /// Write every sub-request; nothing is written if any of them fails.
+    fn write_file_record(&self, write: &WriteFileRecordRequest) -> Result<(), ExceptionCode>;
+}
+
+/// Validated request, ready to be executed.
+#[derive(Debug, Clone)]
+pub enum Action {
+    ReadCoils(ReadRequest),
+    ReadDiscreteInputs(ReadRequest),
+    ReadHoldingRegisters(ReadRequest),
+    ReadInputRegisters(ReadRequest),
+    WriteSingleCoil(WriteSingleCoilRequest),
+    WriteSingleRegister(WriteSingleRegisterRequest),
+    WriteMultipleCoils(WriteMultipleCoilsRequest),
+    WriteMultipleRegisters(WriteMultipleRegistersRequest),
+    MaskWriteRegister(MaskWriteRegisterRequest),
+    ReadFileRecord(ReadFileRecordRequest),
+    WriteFileRecord(WriteFileRecordRequest),
+}
+
+/// Successful outcome of an action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// Response PDU data (after the function code).
+    Data(Vec<u8>),
+    /// Echo the request PDU data (single writes, mask and file record writes).
+    Echo,
+}
+
+impl Action {
+    /// Decode and validate the request PDU. Malformed data maps to
+    /// Illegal Data Value, unknown functions to Illegal Function.
+    pub fn decode(request: &ModbusRequest) -> Result<Self, ExceptionCode> {
+        let invalid = |_| ExceptionCode::IllegalDataValue;
+        let Some(function) = FunctionCode::from_u8(request.function_code) else {
+            log::warn!("Unsupported function code: 0x{:02X}", request.function_code);
+            return Err(ExceptionCode::IllegalFunction);
+        };
+        let data = &request.data;
+
+        let action = match function {
+            FunctionCode::ReadCoils | FunctionCode::ReadDiscreteInputs => {
+                let read = ReadRequest::parse(data).map_err(invalid)?;
+                read.validate_bits()?;
+                if function == FunctionCode::ReadCoils {
+                    Action::ReadCoils(read)
+                } else {
+                    Action::ReadDiscreteInputs(read)
+                }
+            }
+            FunctionCode::ReadHoldingRegisters | FunctionCode::ReadInputRegisters => {
+                let read = ReadRequest::parse(data).map_err(invalid)?;
+                read.validate_registers()?;
+                if function == FunctionCode::ReadHoldingRegisters {
+                    Action::ReadHoldingRegisters(read)
+                } else {
+                    Action::ReadInputRegisters(read)
+                }
+            }
+            FunctionCode::WriteSingleCoil => {
+                Action::WriteSingleCoil(WriteSingleCoilRequest::parse(data).map_err(invalid)?)
+            }
+            FunctionCode::WriteSingleRegister => Action::WriteSingleRegister(
+                WriteSingleRegisterRequest::parse(data).map_err(invalid)?,
+            ),
+            FunctionCode::WriteMultipleCoils => {
+                let write = WriteMultipleCoilsRequest::parse(data).map_err(invalid)?;
+                write.validate()?;
+                Action::WriteMultipleCoils(write)
+            }
+            FunctionCode::WriteMultipleRegisters => {
+                let write = WriteMultipleRegistersRequest::parse(data).map_err(invalid)?;
+                write.validate()?;
+                Action::WriteMultipleRegisters(write)
+            }
+            FunctionCode::MaskWriteRegister => {
+                Action::MaskWriteRegister(MaskWriteRegisterRequest::parse(data).map_err(invalid)?)
+            }
+            FunctionCode::ReadFileRecord => {
+                let read = ReadFileRecordRequest::parse(data).map_err(invalid)?;
+                read.validate()?;
+                Action::ReadFileRecord(read)
+            }
+            FunctionCode::WriteFileRecord => {
+                let write = WriteFileRecordRequest::parse(data).map_err(invalid)?;
+                write.validate()?;
+                Action::WriteFileRecord(write)
+            }
+        };
+        Ok(action)
+    }
+
+    /// Apply the action to the data model.
+    pub fn execute<M: DataModel + ?Sized>(&self, model: &M) -> Result<Reply, ExceptionCode> {
+        let bits = |bits: Vec<bool>| with_byte_count(pack_bits(&bits));
+        let registers = |regs: Vec<u16>| with_byte_count(pack_registers(&regs));
+
+        let reply = match self {
+            Action::ReadCoils(read) => {
+                Reply::Data(bits(model.read_coils(read.start_address, read.quantity)?))
+            }
+            Action::ReadDiscreteInputs(read) => Reply::Data(bits(
+                model.read_discrete_inputs(read.start_address, read.quantity)?,
+            )),
+            Action::ReadHoldingRegisters(read) => Reply::Data(registers(
+                model.read_holding_registers(read.start_address, read.quantity)?,
+            )),
+            Action::ReadInputRegisters(read) => Reply::Data(registers(
+                model.read_input_registers(read.start_address, read.quantity)?,
+            )),
+            Action::WriteSingleCoil(write) => {
+                model.write_single_coil(write.address, write.value)?;
+                Reply::Echo
+            }
+            Action::WriteSingleRegister(write) => {
+                model.write_single_register(write.address, write.value)?;
+                Reply::Echo
+            }
+            Action::WriteMultipleCoils(write) => {
+                model.write_multiple_coils(write.start_address, &write.values)?;
+                Reply::Data(write.to_response_data().to_vec())
+            }
+            Action::WriteMultipleRegisters(write) => {
+                model.write_multiple_registers(write.start_address, &write.values)?;
+                Reply::Data(write.to_response_data().to_vec())
+            }
+            Action::MaskWriteRegister(write) => {
+                model.mask_write_register(write)?;
+                Reply::Echo
+            }
+            Action::ReadFileRecord(read) => {
+                Reply::Data(read.to_response_data(&model.read_file_record(read)?))
+            }
+            Action::WriteFileRecord(write) => {
+                model.write_file_record(write)?;
+                Reply::Echo
+            }
+        };
+        Ok(reply)
+    }
+}
+
+impl Reply {
+    /// Build the response frame for the request.
+    pub fn encode(&self, request: &ModbusRequest) -> Vec<u8> {
+        let data = match self {
+            Reply::Data(data) => data,
+            Reply::Echo => &request.data,
+        };
+        ModbusResponse::build_response(request, request.function_code, data)
+    }
+}
+
+/// Prefix packed read data with its byte count.
+fn with_byte_count(packed: Vec<u8>) -> Vec<u8> {
+    let mut data = Vec::with_capacity(1 + packed.len());
+    data.push(packed.len() as u8);
+    data.extend_from_slice(&packed);
+    data
+}
+
+/// Process a parsed request and build the response frame
+/// (an exception response if the request cannot be executed).
+pub fn process_request<M: DataModel + ?Sized>(request: &ModbusRequest, model: &M) -> Vec<u8> {
+    match Action::decode(request).and_then(|action| action.execute(model)) {
+        Ok(reply) => reply.encode(request),
+        Err(code) => ModbusResponse::build_exception(request, request.function_code, code),
+    }
+}
+
+/// Bytes buffered behind an MBAP length field outside `2..=254`. No valid
+/// frame can be that long or short, so nothing after it can be trusted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IllegalLength {
+    /// Length field of the header.
+    pub declared: u16,
+    /// Bytes received after the length field.
+    pub received: usize,
+    /// The offending bytes, header first.
+    pub frame: Vec<u8>,
+}
+
+impl IllegalLength {
+    /// Illegal Data Value response to the frame, if it carries a function code.
+    pub fn exception_response(&self) -> Option<Vec<u8>> {
+        let header = MbapHeader::parse_unchecked(&self.frame).ok()?;
+        let &function_code = self.frame.get(MbapHeader::SIZE)?;
+        let request = ModbusRequest {
+            header,
+            function_code,
+            data: Vec::new(),
+        };
+        Some(ModbusResponse::build_exception(
+            &request,
+            function_code,
+            ExceptionCode::IllegalDataValue,
+        ))
+    }
+}
+
+/// Reassembles MBAP frames from a byte stream (TCP and other stream transports).
+///
+/// The length field is the only framing information. Reads may split or
+/// join frames arbitrarily, so a frame is taken as soon as its declared
+/// length is buffered and any remainder waits for the next read. Only a
+/// length outside `2..=254` is illegal; it discards everything buffered.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::with_capacity(MAX_FRAME_SIZE),
+        }
+    }
+
+    /// Append received bytes (one read from the transport).
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Transaction IDs repeated among complete buffered frames, i.e. reused
+    /// while an earlier request with the same ID is still unanswered.
+    pub fn duplicate_transaction_ids(&self) -> Vec<u16> {
+        let mut in_flight = HashSet::new();
+        ModbusRequest::buffered_transaction_ids(&self.buffer)
+            .into_iter()
+            .filter(|&id| !in_flight.insert(id))
+            .collect()
+    }
+
+    /// Take the next complete frame, if any, or the bytes of a frame
+    /// with an illegal length.
+    pub fn next_frame(&mut self) -> Option<Result<Vec<u8>, IllegalLength>> {
+        let frame_len = ModbusRequest::expected_frame_length(&self.buffer)?;
+        let declared = (frame_len - (MbapHeader::SIZE - 1)) as u16;
+        if !(2..=MAX_MBAP_LENGTH).contains(&declared) {
+            // Nothing after an impossible length can be trusted
+            return Some(Err(self.take_illegal(declared, self.buffer.len())));
+        }
+        if self.buffer.len() < frame_len {
+            return None;
+        }
+        Some(Ok(self.take(frame_len)))
+    }
+
+    fn take(&mut self, len: usize) -> Vec<u8> {
+        self.buffer.drain(..len).collect()
+    }
+
+    fn take_illegal(&mut self, declared: u16, len: usize) -> IllegalLength {
+        let frame = self.take(len);
+        IllegalLength {
+            declared,
+            received: frame.len() - (MbapHeader::SIZE - 1),
+            frame,
+        }
+    }
+
+    /// Drop buffered bytes to resynchronize after a malformed frame.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Drop the buffer if it grew beyond any valid frame sequence.
+    /// Returns true if data was discarded.
+    pub fn discard_overflow(&mut self) -> bool {
+        if self.buffer.len() > MAX_FRAME_SIZE * 2 {
+            self.clear();
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// In-memory model with 8 coils, 4 holding registers and file 1
+    /// of 4 records.
+    #[derive(Default)]
+    struct Model {
+        coils: RefCell<[bool; 8]>,
+        registers: RefCell<[u16; 4]>,
+        file: RefCell<[u16; 4]>,
+    }
+
+    fn range(start: u16, count: u16, len: usize) -> Result<std::ops::Range<usize>, ExceptionCode> {
+        let end = start as usize + count as usize;
+        if end > len {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        Ok(start as usize..end)
+    }
+
+    impl DataModel for Model {
+        fn read_coils(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode> {
+            Ok(self.coils.borrow()[range(start, count, 8)?].to_vec())
+        }
+        fn read_discrete_inputs(&self, _: u16, _: u16) -> Result<Vec<bool>, ExceptionCode> {
+            Err(ExceptionCode::IllegalDataAddress)
+        }
+        fn read_holding_registers(
+            &self,
+            start: u16,
+            count: u16,
+        ) -> Result<Vec<u16>, ExceptionCode> {
+            Ok(self.registers.borrow()[range(start, count, 4)?].to_vec())
+        }
+        fn read_input_registers(&self, _: u16, _: u16) -> Result<Vec<u16>, ExceptionCode> {
+            Err(ExceptionCode::IllegalDataAddress)
+        }
+        fn write_single_coil(&self, address: u16, value: bool) -> Result<(), ExceptionCode> {
+            self.write_multiple_coils(address, &[value])
+        }
+        fn write_single_register(&self, address: u16, value: u16) -> Result<(), ExceptionCode> {
+            self.write_multiple_registers(address, &[value])
+        }
+        fn write_multiple_coils(&self, start: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+            let range = range(start, values.len() as u16, 8)?;
+            self.coils.borrow_mut()[range].copy_from_slice(values);
+            Ok(())
+        }
+        fn write_multiple_registers(
+            &self,
+            start: u16,
+            values: &[u16],
+        ) -> Result<(), ExceptionCode> {
+            let range = range(start, values.len() as u16, 4)?;
+            self.registers.borrow_mut()[range].copy_from_slice(values);
+            Ok(())
+        }
+        fn mask_write_register(
+            &self,
+            write: &MaskWriteRegisterRequest,
+        ) -> Result<(), ExceptionCode> {
+            let index = range(write.address, 1, 4)?.start;
+            let mut registers = self.registers.borrow_mut();
+            registers[index] = write.apply(registers[index]);
+            Ok(())
+        }
+        fn read_file_record(
+            &self,
+            read: &ReadFileRecordRequest,
+        ) -> Result<Vec<Vec<u16>>, ExceptionCode> {
+            read.records
+                .iter()
+                .map(|r| {
+                    if r.file_number != 1 {
+                        return Err(ExceptionCode::IllegalDataAddress);
+                    }
+                    Ok(self.file.borrow()[range(r.record_number, r.record_length, 4)?].to_vec())
+                })
+                .collect()
+        }
+        fn write_file_record(&self, write: &WriteFileRecordRequest) -> Result<(), ExceptionCode> {
+            for record in &write.records {
+                let r = record.reference;
+                if r.file_number != 1 {
+                    return Err(ExceptionCode::IllegalDataAddress);
+                }
+                range(r.record_number, r.record_length, 4)?;
+            }
+            for record in &write.records {
+                let range = range(
+                    record.reference.record_number,
+                    record.reference.record_length,
+                    4,
+                )?;
+                self.file.borrow_mut()[range].copy_from_slice(&record.values);
+            }
+            Ok(())
+        }
+    }
+
+    fn request(frame: &[u8]) -> ModbusRequest {
+        ModbusRequest::parse(frame).unwrap()
+    }
+
+    #[test]
+    fn test_pipeline_without_sockets() {
+        let model = Model::default();
+        let write = request(&[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x02, 0x12, 0x34,
+        ]);
+        let action = Action::decode(&write).unwrap();
+        assert_eq!(action.execute(&model), Ok(Reply::Echo));
+        assert_eq!(model.registers.borrow()[2], 0x1234);
+
+        let read = request(&[
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x02, 0x00, 0x01,
+        ]);
+        assert_eq!(
+            process_request(&read, &model),
+            vec![0x00, 0x02, 0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x02, 0x12, 0x34]
+        );
+
+        let out_of_range = request(&[
+            0x00, 0x03, 0x00, 0x00, 0x00, 0x06, 0x01, 0x01, 0x00, 0x07, 0x00, 0x02,
+        ]);
+        assert_eq!(
+            Action::decode(&out_of_range).unwrap().execute(&model),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+
+        // Mask write echoes the request and keeps unmasked bits
+        let mask = [
+            0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0x01, 0x16, 0x00, 0x02, 0xFF, 0x00, 0x00, 0x0F,
+        ];
+        assert_eq!(process_request(&request(&mask), &model), mask.to_vec());
+        assert_eq!(model.registers.borrow()[2], 0x120F);
+    }
+
+    #[test]
+    fn test_file_record_write_is_all_or_nothing() {
+        let model = Model::default();
+        // Records 1..=2 of file 1, then record 3 of missing file 2
+        let write = [
+            0x00, 0x05, 0x00, 0x00, 0x00, 0x17, 0x01, 0x15, 0x14, 0x06, 0x00, 0x01, 0x00, 0x01,
+            0x00, 0x02, 0x11, 0x11, 0x22, 0x22, 0x06, 0x00, 0x02, 0x00, 0x03, 0x00, 0x01, 0x33,
+            0x33,
+        ];
+        assert_eq!(
+            Action::decode(&request(&write)).unwrap().execute(&model),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+        assert_eq!(*model.file.borrow(), [0; 4]);
+
+        let write = [
+            0x00, 0x06, 0x00, 0x00, 0x00, 0x0E, 0x01, 0x15, 0x0B, 0x06, 0x00, 0x01, 0x00, 0x01,
+            0x00, 0x02, 0x11, 0x11, 0x22, 0x22,
+        ];
+        assert_eq!(process_request(&request(&write), &model), write.to_vec());
+        let read = [
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x0A, 0x01, 0x14, 0x07, 0x06, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x03,
+        ];
+        assert_eq!(
+            process_request(&request(&read), &model),
+            vec![
+                0x00, 0x07, 0x00, 0x00, 0x00, 0x0B, 0x01, 0x14, 0x08, 0x07, 0x06, 0x00, 0x00, 0x11,
+                0x11, 0x22, 0x22
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frame_decoder_reassembles_split_frames() {
+        let frame = [
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&frame[..5]);
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(&frame[5..]);
+        decoder.push(&frame);
+        assert_eq!(decoder.duplicate_transaction_ids(), vec![7]);
+        assert_eq!(decoder.next_frame(), Some(Ok(frame.to_vec())));
+        assert_eq!(decoder.next_frame(), Some(Ok(frame.to_vec())));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_frame_decoder_pipelined_frames_split_at_every_offset() {
+        let first = [
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let second = [
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x09, 0x01, 0x10, 0x00, 0x00, 0x00, 0x01, 0x02, 0x00,
+            0x06,
+        ];
+        let stream = [&first[..], &second[..]].concat();
+        for split in 1..stream.len() {
+            let mut decoder = FrameDecoder::new();
+            let mut frames = Vec::new();
+            for chunk in [&stream[..split], &stream[split..]] {
+                decoder.push(chunk);
+                while let Some(frame) = decoder.next_frame() {
+                    frames.push(frame);
+                }
+            }
+            assert_eq!(
+                frames,
+                vec![Ok(first.to_vec()), Ok(second.to_vec())],
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn test_frame_decoder_resyncs_after_illegal_length() {
+        let frame = [
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let mut decoder = FrameDecoder::new();
+
+        // Length above 254 drops the buffer instead of waiting for 64 KiB
+        decoder.push(&[0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x03]);
+        let illegal = decoder.next_frame().unwrap().unwrap_err();
+        assert_eq!((illegal.declared, illegal.received), (256, 2));
+        assert_eq!(
+            illegal.exception_response(),
+            Some(vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x83, 0x03])
+        );
+        assert_eq!(decoder.next_frame(), None);
+
+        // Length below 2 cannot hold a unit ID and function code
+        let mut tiny = frame;
+        tiny[5] = 0x01;
+        decoder.push(&tiny);
+        let illegal = decoder.next_frame().unwrap().unwrap_err();
+        assert_eq!((illegal.declared, illegal.received), (1, 6));
+        assert_eq!(decoder.next_frame(), None);
+
+        // A valid frame after the discarded bytes decodes normally
+        decoder.push(&frame);
+        assert_eq!(decoder.next_frame(), Some(Ok(frame.to_vec())));
+        assert_eq!(decoder.next_frame(), None);
+    }
+}
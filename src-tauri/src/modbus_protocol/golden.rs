@@ -0,0 +1,287 @@
+//! Protocol regression suite driven by golden test vectors.
+//!
+//! Vectors live in `vectors/*.golden`: each `[name]` block holds a request
+//! frame and the expected response frame in hex (`none` means the frame must
+//! be rejected by the parser). Every vector runs against a fresh fixture
+//! store, so vector order does not matter. The report lists mismatches and
+//! coverage: which functions and exception codes are exercised and which
+//! supported functions have no vectors yet.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use super::engine::process_request;
+use super::{FunctionCode, ModbusRequest};
+use crate::data_store::{create_shared_data_store, RecordFile, SharedDataStore};
+use crate::types::{
+    bytes_to_hex, hex_to_bytes, ModbusArea, ModbusDataType, ModbusValue, ModbusVariable,
+};
+
+/// Vector files embedded into the application.
+const VECTOR_FILES: &[(&str, &str)] = &[
+    ("read.golden", include_str!("vectors/read.golden")),
+    ("write.golden", include_str!("vectors/write.golden")),
+    (
+        "exceptions.golden",
+        include_str!("vectors/exceptions.golden"),
+    ),
+    ("malformed.golden", include_str!("vectors/malformed.golden")),
+];
+
+/// Golden vector: request and expected response.
+#[derive(Debug, Clone)]
+pub struct GoldenVector {
+    pub file: &'static str,
+    pub name: String,
+    pub request: Vec<u8>,
+    /// `None` if the frame must be rejected by the parser.
+    pub response: Option<Vec<u8>>,
+}
+
+/// Mismatch against a golden response.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoldenFailure {
+    pub file: String,
+    pub name: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+/// Suite run result.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoldenReport {
+    pub total: usize,
+    pub passed: usize,
+    pub failures: Vec<GoldenFailure>,
+    /// Function codes with at least one parsed request.
+    pub covered_functions: Vec<u8>,
+    /// Supported functions without any vector.
+    pub missing_functions: Vec<u8>,
+    /// Exception codes present in golden responses.
+    pub covered_exceptions: Vec<u8>,
+}
+
+/// Vector block being read: `response` is `Some(None)` for `response = none`.
+struct PartialVector {
+    name: String,
+    request: Option<Vec<u8>>,
+    response: Option<Option<Vec<u8>>>,
+}
+
+impl PartialVector {
+    fn finish(self, file: &'static str) -> Result<GoldenVector, String> {
+        match (self.request, self.response) {
+            (Some(request), Some(response)) => Ok(GoldenVector {
+                file,
+                name: self.name,
+                request,
+                response,
+            }),
+            _ => Err(format!(
+                "{}: vector [{}] lacks request or response",
+                file, self.name
+            )),
+        }
+    }
+}
+
+/// Parse a vector file.
+pub fn parse_vectors(file: &'static str, text: &str) -> Result<Vec<GoldenVector>, String> {
+    let mut vectors = Vec::new();
+    let mut current: Option<PartialVector> = None;
+    let err = |line: usize, msg: &str| format!("{}:{}: {}", file, line + 1, msg);
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if let Some(vector) = current.take() {
+                vectors.push(vector.finish(file)?);
+            }
+            current = Some(PartialVector {
+                name: name.to_string(),
+                request: None,
+                response: None,
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(err(index, "expected `key = value`"));
+        };
+        let Some(vector) = current.as_mut() else {
+            return Err(err(index, "value outside of a [name] block"));
+        };
+        let value = value.trim();
+        let bytes = || hex_to_bytes(value).ok_or_else(|| err(index, "invalid hex"));
+        match key.trim() {
+            "request" => vector.request = Some(bytes()?),
+            "response" if value == "none" => vector.response = Some(None),
+            "response" => vector.response = Some(Some(bytes()?)),
+            other => return Err(err(index, &format!("unknown key `{}`", other))),
+        }
+    }
+    if let Some(vector) = current {
+        vectors.push(vector.finish(file)?);
+    }
+    Ok(vectors)
+}
+
+/// All embedded vectors.
+pub fn load_vectors() -> Result<Vec<GoldenVector>, String> {
+    let mut vectors = Vec::new();
+    for &(file, text) in VECTOR_FILES {
+        vectors.extend(parse_vectors(file, text)?);
+    }
+    Ok(vectors)
+}
+
+fn variable(
+    area: ModbusArea,
+    address: u16,
+    data_type: ModbusDataType,
+    value: ModbusValue,
+) -> ModbusVariable {
+    ModbusVariable {
+        name: String::new(),
+        value,
+        ..ModbusVariable::new(format!("{:?}_{}", area, address), area, address, data_type)
+    }
+}
+
+/// Fixture store the golden responses are computed against:
+/// - coils 0..=9: 1 0 1 1 0 0 0 0 1 0;
+/// - discrete inputs 0..=3: 0 1 0 0;
+/// - input registers: 0 — uint32 0x00010002, 2 — int16 -1;
+/// - holding registers: 0 — 0x1234, 1 — 0, 2 — float32 1.5;
+/// - file 1: records 0x1111 0x2222 0x3333 0x4444.
+fn fixture_store() -> SharedDataStore {
+    let store = create_shared_data_store();
+    let mut variables = Vec::new();
+    let coils = [
+        true, false, true, true, false, false, false, false, true, false,
+    ];
+    for (address, &on) in coils.iter().enumerate() {
+        variables.push(variable(
+            ModbusArea::Coil,
+            address as u16,
+            ModbusDataType::Bool,
+            ModbusValue::Bool(on),
+        ));
+    }
+    for (address, on) in [false, true, false, false].into_iter().enumerate() {
+        variables.push(variable(
+            ModbusArea::DiscreteInput,
+            address as u16,
+            ModbusDataType::Bool,
+            ModbusValue::Bool(on),
+        ));
+    }
+    variables.push(variable(
+        ModbusArea::InputRegister,
+        0,
+        ModbusDataType::Uint32,
+        ModbusValue::Number(65538.0),
+    ));
+    variables.push(variable(
+        ModbusArea::InputRegister,
+        2,
+        ModbusDataType::Int16,
+        ModbusValue::Number(-1.0),
+    ));
+    variables.push(variable(
+        ModbusArea::HoldingRegister,
+        0,
+        ModbusDataType::Uint16,
+        ModbusValue::Number(4660.0),
+    ));
+    variables.push(variable(
+        ModbusArea::HoldingRegister,
+        1,
+        ModbusDataType::Uint16,
+        ModbusValue::Number(0.0),
+    ));
+    variables.push(variable(
+        ModbusArea::HoldingRegister,
+        2,
+        ModbusDataType::Float32,
+        ModbusValue::Number(1.5),
+    ));
+    store.load_variables(&variables);
+    store
+        .set_record_file(RecordFile {
+            file_number: 1,
+            records: vec![0x1111, 0x2222, 0x3333, 0x4444],
+        })
+        .expect("fixture file is valid");
+    store
+}
+
+/// Run a vector: the server response, or `None` if the frame is rejected.
+fn run_vector(vector: &GoldenVector) -> Option<Vec<u8>> {
+    let request = ModbusRequest::parse_lenient(&vector.request).ok()?;
+    Some(process_request(&request, fixture_store().as_ref()))
+}
+
+/// Run all embedded vectors.
+pub fn run_suite() -> Result<GoldenReport, String> {
+    let vectors = load_vectors()?;
+    let mut failures = Vec::new();
+    let mut covered_functions = BTreeSet::new();
+    let mut covered_exceptions = BTreeSet::new();
+
+    for vector in &vectors {
+        let actual = run_vector(vector);
+        if actual.is_some() {
+            covered_functions.insert(vector.request[7]);
+        }
+        if let Some(&[function, code]) = vector.response.as_deref().and_then(|r| r.get(7..9)) {
+            if function & 0x80 != 0 {
+                covered_exceptions.insert(code);
+            }
+        }
+        if actual != vector.response {
+            failures.push(GoldenFailure {
+                file: vector.file.to_string(),
+                name: vector.name.clone(),
+                expected: vector.response.as_deref().map(bytes_to_hex),
+                actual: actual.as_deref().map(bytes_to_hex),
+            });
+        }
+    }
+
+    let missing_functions = (1..0x80)
+        .filter(|&code| FunctionCode::from_u8(code).is_some() && !covered_functions.contains(&code))
+        .collect();
+    Ok(GoldenReport {
+        total: vectors.len(),
+        passed: vectors.len() - failures.len(),
+        failures,
+        covered_functions: covered_functions.into_iter().collect(),
+        missing_functions,
+        covered_exceptions: covered_exceptions.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_vectors() {
+        let report = run_suite().unwrap();
+        assert!(report.failures.is_empty(), "{:#?}", report.failures);
+        assert!(report.missing_functions.is_empty());
+        assert!(report.total > 20);
+    }
+
+    #[test]
+    fn test_vector_file_errors_have_location() {
+        let err = parse_vectors("bad.golden", "[a]\nrequest = 00 ZZ\n").unwrap_err();
+        assert_eq!(err, "bad.golden:2: invalid hex");
+    }
+}
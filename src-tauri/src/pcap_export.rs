@@ -0,0 +1,164 @@
+//! Выгрузка журнала обмена в pcap.
+//!
+//! Кадры запросов и ответов из журнала оборачиваются в синтетические
+//! заголовки IPv4 и TCP (тип канала LINKTYPE_RAW), чтобы Wireshark разобрал
+//! их как Modbus/TCP. Метки пакетов — микросекундные метки кадров
+//! ([`crate::frame_clock`]); у записей без них — миллисекундная метка журнала.
+//! Номера последовательности TCP ведутся по каждому соединению, поэтому
+//! анализатор видит непрерывный поток. Записи клиентов не по IPv4 (имитация
+//! мастера, IPv6) пропускаются.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use crate::traffic_log::timestamp_to_ms;
+use crate::types::{hex_to_bytes, LogEntry, LogEntryType};
+
+/// Заголовок файла pcap с микросекундными метками.
+const PCAP_MAGIC: u32 = 0xA1B2_C3D4;
+/// LINKTYPE_RAW: пакет начинается с заголовка IP.
+const LINKTYPE_RAW: u32 = 101;
+const SNAPLEN: u32 = 65_535;
+
+const IPV4_HEADER_LEN: usize = 20;
+const TCP_HEADER_LEN: usize = 20;
+
+/// Кадр журнала с направлением и временем.
+struct Packet {
+    time_us: u64,
+    client: SocketAddrV4,
+    to_server: bool,
+    payload: Vec<u8>,
+}
+
+fn packet(entry: &LogEntry) -> Option<Packet> {
+    // Ответы (в том числе исключения) отличаются от прочих записей временем обработки
+    let to_server = match entry.entry_type {
+        LogEntryType::Request => true,
+        _ if entry.duration_us.is_some() => false,
+        _ => return None,
+    };
+    Some(Packet {
+        time_us: entry
+            .frame_time_us
+            .unwrap_or_else(|| timestamp_to_ms(&entry.timestamp).max(0) as u64 * 1000),
+        client: entry.client_addr.parse().ok()?,
+        to_server,
+        payload: hex_to_bytes(entry.raw_data.as_deref()?)?,
+    })
+}
+
+/// Контрольная сумма заголовка IPv4.
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum();
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Сериализовать записи журнала (новые первыми, как их отдаёт журнал) в pcap. `server` — адрес сервера в пакетах.
+/// Возвращает содержимое файла и число записанных пакетов.
+pub fn encode(entries: &[LogEntry], server: SocketAddrV4) -> (Vec<u8>, usize) {
+    // Журнал отдаёт новые записи первыми; при равных метках сохраняется порядок журнала
+    let mut packets: Vec<Packet> = entries.iter().rev().filter_map(packet).collect();
+    packets.sort_by_key(|p| p.time_us);
+
+    let mut out = Vec::new();
+    for value in [PCAP_MAGIC, 0x0004_0002, 0, 0, SNAPLEN, LINKTYPE_RAW] {
+        // Версия 2.4 записывается двумя u16 одним словом (little-endian: 2, затем 4)
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // Следующий номер последовательности по (клиент, направление к серверу)
+    let mut sequences: HashMap<(SocketAddrV4, bool), u32> = HashMap::new();
+    for (id, p) in packets.iter().enumerate() {
+        let (src, dst) = if p.to_server {
+            (p.client, server)
+        } else {
+            (server, p.client)
+        };
+        let ack = *sequences.entry((p.client, !p.to_server)).or_insert(1);
+        let seq = sequences.entry((p.client, p.to_server)).or_insert(1);
+        let total_len = IPV4_HEADER_LEN + TCP_HEADER_LEN + p.payload.len();
+
+        let mut ip = Vec::with_capacity(IPV4_HEADER_LEN);
+        ip.extend_from_slice(&[0x45, 0]);
+        ip.extend_from_slice(&(total_len as u16).to_be_bytes());
+        ip.extend_from_slice(&(id as u16).to_be_bytes());
+        ip.extend_from_slice(&[0x40, 0, 64, 6, 0, 0]);
+        ip.extend_from_slice(&src.ip().octets());
+        ip.extend_from_slice(&dst.ip().octets());
+        let checksum = ipv4_checksum(&ip);
+        ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+        out.extend_from_slice(&((p.time_us / 1_000_000) as u32).to_le_bytes());
+        out.extend_from_slice(&((p.time_us % 1_000_000) as u32).to_le_bytes());
+        out.extend_from_slice(&(total_len as u32).to_le_bytes());
+        out.extend_from_slice(&(total_len as u32).to_le_bytes());
+        out.extend_from_slice(&ip);
+        out.extend_from_slice(&src.port().to_be_bytes());
+        out.extend_from_slice(&dst.port().to_be_bytes());
+        out.extend_from_slice(&seq.to_be_bytes());
+        out.extend_from_slice(&ack.to_be_bytes());
+        // Длина заголовка 5 слов, флаги PSH+ACK, окно, контрольная сумма не считается
+        out.extend_from_slice(&[0x50, 0x18, 0xFF, 0xFF, 0, 0, 0, 0]);
+        out.extend_from_slice(&p.payload);
+
+        *seq = seq.wrapping_add(p.payload.len() as u32);
+    }
+    (out, packets.len())
+}
+
+/// Адрес сервера для пакетов: адрес прослушивания, а для 0.0.0.0 и
+/// имён узлов — 127.0.0.1.
+pub fn server_addr(host: &str, port: u16) -> SocketAddrV4 {
+    let ip = host
+        .parse::<Ipv4Addr>()
+        .ok()
+        .filter(|ip| !ip.is_unspecified())
+        .unwrap_or(Ipv4Addr::LOCALHOST);
+    SocketAddrV4::new(ip, port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_and_response_packets() {
+        let request = LogEntry::new(1, LogEntryType::Request, "10.0.0.5:40000".into(), "".into())
+            .with_raw_data(&[0, 1, 0, 0, 0, 6, 1, 3, 0, 0, 0, 1])
+            .with_frame_time(1_700_000_000_000_250);
+        let response = LogEntry::new(
+            2,
+            LogEntryType::Response,
+            "10.0.0.5:40000".into(),
+            "".into(),
+        )
+        .with_raw_data(&[0, 1, 0, 0, 0, 5, 1, 3, 2, 0, 7])
+        .with_duration(120)
+        .with_frame_time(1_700_000_000_000_900);
+        let info = LogEntry::new(3, LogEntryType::Info, "10.0.0.5:40000".into(), "".into());
+
+        let (data, count) = encode(&[response, info, request], server_addr("0.0.0.0", 502));
+        assert_eq!(count, 2);
+        assert_eq!(data.len(), 24 + 2 * (16 + 40) + 12 + 11);
+
+        // Первым идёт запрос: метка 250 мкс, порт назначения 502
+        let first = &data[24..];
+        assert_eq!(u32::from_le_bytes(first[4..8].try_into().unwrap()), 250);
+        assert_eq!(ipv4_checksum(&first[16..36]), 0);
+        assert_eq!(&first[36..40], &[0x9C, 0x40, 0x01, 0xF6]);
+
+        // Ответ подтверждает 12 байт запроса
+        let second = &data[24 + 16 + 40 + 12..];
+        assert_eq!(
+            u32::from_be_bytes(second[16 + 28..16 + 32].try_into().unwrap()),
+            13
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! Импорт переменных из таблиц символов ПЛК.
+//!
+//! Поддерживаемые форматы:
+//! - TIA Portal: CSV-экспорт таблицы тегов (колонки Name, Data Type, Logical Address, Comment);
+//! - Step7: CSV-экспорт таблицы символов (Symbol, Address, Data type, Comment);
+//! - Codesys: текст объявлений переменных (`Name AT %MW10 : INT; // комментарий`).
+//!
+//! Адреса ПЛК (I/Q/M) переводятся в адреса Modbus по правилам трансляции.
+//! Siemens использует байтовую адресацию (MW100 — байты 100..101, т.е. регистр 50),
+//! Codesys — словную (%MW10 — регистр 10). Смещения правил задаются в адресах
+//! протокола, а адреса в причинах пропуска — в соглашении об адресации проекта.
+
+use serde::{Deserialize, Serialize};
+
+use crate::addressing::AddressingConvention;
+use crate::types::{generate_variable_id, ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+/// Формат таблицы символов.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlcSymbolFormat {
+    TiaCsv,
+    Step7Csv,
+    CodesysDeclarations,
+}
+
+impl PlcSymbolFormat {
+    /// Использует ли формат байтовую адресацию слов (Siemens).
+    fn byte_addressed_words(self) -> bool {
+        matches!(self, PlcSymbolFormat::TiaCsv | PlcSymbolFormat::Step7Csv)
+    }
+}
+
+/// Область памяти ПЛК.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlcMemory {
+    /// Входы (I / %I)
+    Input,
+    /// Выходы (Q / %Q)
+    Output,
+    /// Меркеры (M / %M)
+    Marker,
+}
+
+/// Правило трансляции области ПЛК в области Modbus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressRule {
+    pub memory: PlcMemory,
+    /// Область Modbus для битовых адресов (coil или discrete_input).
+    pub bit_area: ModbusArea,
+    /// Область Modbus для словных адресов (holding_register или input_register).
+    pub word_area: ModbusArea,
+    /// Смещение, добавляемое к вычисленному адресу Modbus.
+    #[serde(default)]
+    pub offset: i64,
+}
+
+/// Правила трансляции по умолчанию.
+pub fn default_address_rules() -> Vec<AddressRule> {
+    vec![
+        AddressRule {
+            memory: PlcMemory::Input,
+            bit_area: ModbusArea::DiscreteInput,
+            word_area: ModbusArea::InputRegister,
+            offset: 0,
+        },
+        AddressRule {
+            memory: PlcMemory::Output,
+            bit_area: ModbusArea::Coil,
+            word_area: ModbusArea::HoldingRegister,
+            offset: 0,
+        },
+        AddressRule {
+            memory: PlcMemory::Marker,
+            bit_area: ModbusArea::Coil,
+            word_area: ModbusArea::HoldingRegister,
+            offset: 0,
+        },
+    ]
+}
+
+/// Параметры импорта.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlcImportOptions {
+    pub format: PlcSymbolFormat,
+    /// Правила трансляции (пусто — правила по умолчанию).
+    #[serde(default)]
+    pub rules: Vec<AddressRule>,
+}
+
+/// Символ, который не удалось импортировать.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedSymbol {
+    /// Номер строки (с 1).
+    pub line: usize,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Результат импорта.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlcImportResult {
+    pub variables: Vec<ModbusVariable>,
+    pub skipped: Vec<SkippedSymbol>,
+}
+
+/// Размер операнда в адресе ПЛК.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperandSize {
+    Bit,
+    Byte,
+    Word,
+    DWord,
+}
+
+/// Разобранный адрес ПЛК.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PlcAddress {
+    memory: PlcMemory,
+    size: OperandSize,
+    /// Номер байта (для битов и Siemens) или номер слова/двойного слова (Codesys).
+    index: u32,
+    bit: u8,
+}
+
+/// Символ, извлечённый из исходного файла.
+struct RawSymbol {
+    line: usize,
+    name: String,
+    data_type: String,
+    address: String,
+    comment: Option<String>,
+    initial_value: Option<String>,
+}
+
+/// Импортировать таблицу символов.
+pub fn import_symbols(
+    content: &str,
+    options: &PlcImportOptions,
+    addressing: AddressingConvention,
+) -> PlcImportResult {
+    let rules = if options.rules.is_empty() {
+        default_address_rules()
+    } else {
+        options.rules.clone()
+    };
+
+    let symbols = match options.format {
+        PlcSymbolFormat::TiaCsv => parse_csv_symbols(
+            content,
+            &["name"],
+            &["logical address", "address"],
+            &["data type", "datatype"],
+            &["comment"],
+            [0, 3, 2, 4],
+        ),
+        PlcSymbolFormat::Step7Csv => parse_csv_symbols(
+            content,
+            &["symbol", "name"],
+            &["address"],
+            &["data type", "datatype"],
+            &["comment"],
+            [0, 1, 2, 3],
+        ),
+        PlcSymbolFormat::CodesysDeclarations => parse_codesys_declarations(content),
+    };
+
+    let mut result = PlcImportResult::default();
+    for symbol in symbols {
+        match translate_symbol(&symbol, options.format, &rules, addressing) {
+            Ok(mut var) => {
+                var.id = generate_variable_id(result.variables.len());
+                result.variables.push(var);
+            }
+            Err(reason) => result.skipped.push(SkippedSymbol {
+                line: symbol.line,
+                name: symbol.name,
+                reason,
+            }),
+        }
+    }
+    result
+}
+
+/// Перевести символ ПЛК в переменную Modbus.
+fn translate_symbol(
+    symbol: &RawSymbol,
+    format: PlcSymbolFormat,
+    rules: &[AddressRule],
+    addressing: AddressingConvention,
+) -> Result<ModbusVariable, String> {
+    let data_type = map_data_type(&symbol.data_type)
+        .ok_or_else(|| format!("неподдерживаемый тип данных '{}'", symbol.data_type))?;
+    let address = parse_plc_address(&symbol.address)
+        .ok_or_else(|| format!("нераспознанный адрес '{}'", symbol.address))?;
+    let rule = rules
+        .iter()
+        .find(|r| r.memory == address.memory)
+        .ok_or_else(|| format!("нет правила трансляции для '{}'", symbol.address))?;
+
+    let (area, base) = match address.size {
+        OperandSize::Bit => {
+            if data_type != ModbusDataType::Bool {
+                return Err("битовый адрес для небулевого типа".to_string());
+            }
+            (rule.bit_area, address.index as i64 * 8 + address.bit as i64)
+        }
+        OperandSize::Byte => return Err("байтовые операнды не поддерживаются".to_string()),
+        OperandSize::Word | OperandSize::DWord => {
+            let register = if format.byte_addressed_words() {
+                if address.index % 2 != 0 {
+                    return Err("адрес слова не выровнен по регистру".to_string());
+                }
+                address.index as i64 / 2
+            } else if address.size == OperandSize::DWord {
+                address.index as i64 * 2
+            } else {
+                address.index as i64
+            };
+            let expected = if address.size == OperandSize::DWord {
+                2
+            } else {
+                1
+            };
+            if data_type.register_count() != expected {
+                return Err(format!(
+                    "размер операнда не соответствует типу '{}'",
+                    symbol.data_type
+                ));
+            }
+            (rule.word_area, register)
+        }
+    };
+
+    let modbus_address = base + rule.offset;
+    let last = modbus_address + data_type.register_count() as i64 - 1;
+    if modbus_address < 0 || last > u16::MAX as i64 {
+        let shown = u16::try_from(modbus_address)
+            .map(|address| addressing.format(area, address))
+            .unwrap_or_else(|_| modbus_address.to_string());
+        return Err(format!(
+            "адрес Modbus {} ({}) выходит за пределы области",
+            shown, symbol.address
+        ));
+    }
+
+    let value = match symbol.initial_value.as_deref().map(str::trim) {
+        Some(v) if v.eq_ignore_ascii_case("true") => ModbusValue::Bool(true),
+        Some(v) if v.eq_ignore_ascii_case("false") => ModbusValue::Bool(false),
+        Some(v) => v
+            .parse::<f64>()
+            .map(ModbusValue::Number)
+            .unwrap_or_default(),
+        None if data_type == ModbusDataType::Bool => ModbusValue::Bool(false),
+        None => ModbusValue::default(),
+    };
+
+    Ok(ModbusVariable {
+        name: symbol.name.clone(),
+        value,
+        readonly: matches!(area, ModbusArea::DiscreteInput | ModbusArea::InputRegister)
+            .then_some(true),
+        note: symbol.comment.clone().filter(|c| !c.is_empty()),
+        ..ModbusVariable::new(String::new(), area, modbus_address as u16, data_type)
+    })
+}
+
+/// Сопоставить тип данных ПЛК типу Modbus.
+fn map_data_type(plc_type: &str) -> Option<ModbusDataType> {
+    match plc_type.trim().to_ascii_uppercase().as_str() {
+        "BOOL" => Some(ModbusDataType::Bool),
+        "INT" => Some(ModbusDataType::Int16),
+        "WORD" | "UINT" => Some(ModbusDataType::Uint16),
+        "DWORD" | "UDINT" | "DINT" => Some(ModbusDataType::Uint32),
+        "REAL" => Some(ModbusDataType::Float32),
+        _ => None,
+    }
+}
+
+/// Разобрать адрес ПЛК: `%MW100`, `MW 100`, `%M10.3`, `%IX0.1`, `Q 4.0`, `%QD8`.
+fn parse_plc_address(address: &str) -> Option<PlcAddress> {
+    let compact: String = address
+        .trim()
+        .trim_start_matches('%')
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+
+    let mut chars = compact.chars();
+    let memory = match chars.next()? {
+        'I' | 'E' => PlcMemory::Input,
+        'Q' | 'A' => PlcMemory::Output,
+        'M' => PlcMemory::Marker,
+        _ => return None,
+    };
+
+    let rest = chars.as_str();
+    let (size, digits) = match rest.chars().next()? {
+        'X' => (OperandSize::Bit, &rest[1..]),
+        'B' => (OperandSize::Byte, &rest[1..]),
+        'W' => (OperandSize::Word, &rest[1..]),
+        'D' => (OperandSize::DWord, &rest[1..]),
+        c if c.is_ascii_digit() => (OperandSize::Bit, rest),
+        _ => return None,
+    };
+
+    match digits.split_once('.') {
+        Some((byte, bit)) => {
+            if size != OperandSize::Bit {
+                return None;
+            }
+            let bit: u8 = bit.parse().ok()?;
+            if bit > 7 {
+                return None;
+            }
+            Some(PlcAddress {
+                memory,
+                size,
+                index: byte.parse().ok()?,
+                bit,
+            })
+        }
+        None if size == OperandSize::Bit => None,
+        None => Some(PlcAddress {
+            memory,
+            size,
+            index: digits.parse().ok()?,
+            bit: 0,
+        }),
+    }
+}
+
+/// Разобрать CSV с заголовком или фиксированным порядком колонок.
+/// `fallback` — индексы колонок [имя, адрес, тип, комментарий] при отсутствии заголовка.
+fn parse_csv_symbols(
+    content: &str,
+    name_headers: &[&str],
+    address_headers: &[&str],
+    type_headers: &[&str],
+    comment_headers: &[&str],
+    fallback: [usize; 4],
+) -> Vec<RawSymbol> {
+    let separator = detect_separator(content);
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+
+    let mut columns = fallback;
+    let mut pending = None;
+
+    if let Some((index, first)) = lines.next() {
+        let header: Vec<String> = split_csv_line(first, separator)
+            .into_iter()
+            .map(|h| h.trim().to_ascii_lowercase())
+            .collect();
+        let find = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+
+        match (find(name_headers), find(address_headers)) {
+            (Some(name), Some(address)) => {
+                columns = [
+                    name,
+                    address,
+                    find(type_headers).unwrap_or(usize::MAX),
+                    find(comment_headers).unwrap_or(usize::MAX),
+                ];
+            }
+            _ => pending = Some((index, first)),
+        }
+    }
+
+    pending
+        .into_iter()
+        .chain(lines)
+        .filter_map(|(index, line)| {
+            let fields = split_csv_line(line, separator);
+            let get = |i: usize| fields.get(i).map(|f| f.trim().to_string());
+            Some(RawSymbol {
+                line: index + 1,
+                name: get(columns[0]).filter(|n| !n.is_empty())?,
+                address: get(columns[1]).unwrap_or_default(),
+                data_type: get(columns[2]).unwrap_or_default(),
+                comment: get(columns[3]),
+                initial_value: None,
+            })
+        })
+        .collect()
+}
+
+/// Определить разделитель CSV по первой строке.
+fn detect_separator(content: &str) -> char {
+    let first = content.lines().next().unwrap_or_default();
+    [';', '\t', ',']
+        .into_iter()
+        .max_by_key(|sep| first.matches(*sep).count())
+        .unwrap_or(';')
+}
+
+/// Разбить строку CSV с учётом кавычек.
+fn split_csv_line(line: &str, separator: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == separator && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Разобрать объявления переменных Codesys вида
+/// `Name AT %QX0.1 : BOOL := TRUE; // комментарий`.
+fn parse_codesys_declarations(content: &str) -> Vec<RawSymbol> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let (code, comment) = split_codesys_comment(line);
+            let (name, rest) = code
+                .split_once(" AT ")
+                .or_else(|| code.split_once(" at "))?;
+            let (address, rest) = rest.split_once(':')?;
+            let rest = rest.trim().trim_end_matches(';');
+            let (data_type, initial_value) = match rest.split_once(":=") {
+                Some((t, v)) => (t.trim(), Some(v.trim().to_string())),
+                None => (rest.trim(), None),
+            };
+            Some(RawSymbol {
+                line: index + 1,
+                name: name.trim().to_string(),
+                address: address.trim().to_string(),
+                data_type: data_type.to_string(),
+                comment,
+                initial_value,
+            })
+        })
+        .collect()
+}
+
+/// Отделить комментарий (`// ...` или `(* ... *)`) от кода.
+fn split_codesys_comment(line: &str) -> (&str, Option<String>) {
+    if let Some(pos) = line.find("//") {
+        return (&line[..pos], Some(line[pos + 2..].trim().to_string()));
+    }
+    if let Some(start) = line.find("(*") {
+        let end = line[start..]
+            .find("*)")
+            .map(|e| start + e)
+            .unwrap_or(line.len());
+        return (
+            &line[..start],
+            Some(line[start + 2..end].trim().to_string()),
+        );
+    }
+    (line, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plc_address() {
+        let addr = parse_plc_address("%MW100").unwrap();
+        assert_eq!(addr.memory, PlcMemory::Marker);
+        assert_eq!(addr.size, OperandSize::Word);
+        assert_eq!(addr.index, 100);
+
+        let addr = parse_plc_address("Q 4.7").unwrap();
+        assert_eq!(addr.memory, PlcMemory::Output);
+        assert_eq!(addr.size, OperandSize::Bit);
+        assert_eq!((addr.index, addr.bit), (4, 7));
+
+        assert!(parse_plc_address("%M10.8").is_none());
+        assert!(parse_plc_address("DB1.DBW2").is_none());
+    }
+
+    #[test]
+    fn test_import_tia_csv() {
+        let csv = "Name;Path;Data Type;Logical Address;Comment\n\
+                   Motor_On;Default tag table;Bool;%M10.3;Motor command\n\
+                   Speed;Default tag table;Int;%MW100;\n\
+                   Temp;Default tag table;Real;%ID64;Sensor\n\
+                   Odd;Default tag table;Int;%MW101;\n";
+        let options = PlcImportOptions {
+            format: PlcSymbolFormat::TiaCsv,
+            rules: Vec::new(),
+        };
+        let result = import_symbols(csv, &options, AddressingConvention::ZeroBased);
+
+        assert_eq!(result.variables.len(), 3);
+        assert_eq!(result.variables[0].area, ModbusArea::Coil);
+        assert_eq!(result.variables[0].address, 83);
+        assert_eq!(result.variables[1].address, 50);
+        assert_eq!(result.variables[2].area, ModbusArea::InputRegister);
+        assert_eq!(result.variables[2].address, 32);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].name, "Odd");
+    }
+
+    #[test]
+    fn test_import_codesys_declarations_with_offset() {
+        let text = "VAR_GLOBAL\n\
+                    xPump AT %QX0.1 : BOOL := TRUE; // pump\n\
+                    wSetpoint AT %MW10 : WORD := 500; (* setpoint *)\n\
+                    rFlow AT %MD4 : REAL;\n\
+                    END_VAR\n";
+        let mut rules = default_address_rules();
+        rules[2].offset = 1000;
+        let options = PlcImportOptions {
+            format: PlcSymbolFormat::CodesysDeclarations,
+            rules,
+        };
+        let result = import_symbols(text, &options, AddressingConvention::ZeroBased);
+
+        assert!(result.skipped.is_empty());
+        assert_eq!(result.variables.len(), 3);
+        assert_eq!(result.variables[0].address, 1);
+        assert!(result.variables[0].value.as_bool());
+        assert_eq!(result.variables[1].address, 1010);
+        assert_eq!(result.variables[1].value.as_u16(), 500);
+        assert_eq!(result.variables[1].note.as_deref(), Some("setpoint"));
+        assert_eq!(result.variables[2].address, 1008);
+    }
+}
@@ -0,0 +1,205 @@
+//! Запись опрошенных значений в файл.
+//!
+//! При пусконаладке без архива мастер сохраняет значения тегов с отметкой
+//! времени после каждого цикла опроса. CSV — одна строка на цикл, колонка на
+//! тег, пустая ячейка при сбое чтения. SQLite — строка на тег в таблице
+//! `samples` вместе с текстом ошибки. Файл дописывается, а не перезаписывается.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::master::PollTag;
+use crate::request_stats::csv_field;
+
+/// Формат файла записи.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    Csv,
+    Sqlite,
+}
+
+/// Настройки записи опроса.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PollRecording {
+    pub path: PathBuf,
+    pub format: RecordFormat,
+}
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS samples (
+    timestamp TEXT NOT NULL,
+    tag_id TEXT NOT NULL,
+    value REAL,
+    error TEXT
+);";
+
+/// Куда пишутся значения.
+enum Sink {
+    Csv(BufWriter<File>),
+    Sqlite(Connection),
+}
+
+/// Открытый файл записи опроса.
+pub struct PollRecorder {
+    sink: Sink,
+    tag_ids: Vec<String>,
+}
+
+impl PollRecorder {
+    /// Открыть файл записи. Заголовок CSV пишется только в пустой файл.
+    pub fn open(recording: &PollRecording, tags: &[PollTag]) -> Result<Self, String> {
+        let path = &recording.path;
+        let sink = match recording.format {
+            RecordFormat::Csv => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| format!("Не удалось открыть {}: {}", path.display(), e))?;
+                let empty = file.metadata().map(|m| m.len() == 0).unwrap_or(true);
+                let mut writer = BufWriter::new(file);
+                if empty {
+                    let mut header = vec!["timestamp".to_string()];
+                    header.extend(tags.iter().map(|tag| {
+                        csv_field(if tag.name.is_empty() {
+                            &tag.id
+                        } else {
+                            &tag.name
+                        })
+                    }));
+                    writeln!(writer, "{}", header.join(","))
+                        .and_then(|_| writer.flush())
+                        .map_err(|e| format!("Не удалось записать {}: {}", path.display(), e))?;
+                }
+                Sink::Csv(writer)
+            }
+            RecordFormat::Sqlite => {
+                let connection = Connection::open(path)
+                    .and_then(|connection| connection.execute_batch(SCHEMA).map(|_| connection))
+                    .map_err(|e| format!("Не удалось открыть {}: {}", path.display(), e))?;
+                Sink::Sqlite(connection)
+            }
+        };
+        Ok(Self {
+            sink,
+            tag_ids: tags.iter().map(|tag| tag.id.clone()).collect(),
+        })
+    }
+
+    /// Записать результаты цикла опроса: значение или ошибку по каждому тегу
+    /// в порядке тегов.
+    pub fn record(
+        &mut self,
+        timestamp: &str,
+        samples: &[Result<f64, String>],
+    ) -> Result<(), String> {
+        match &mut self.sink {
+            Sink::Csv(writer) => {
+                let mut row = vec![timestamp.to_string()];
+                row.extend(samples.iter().map(|sample| match sample {
+                    Ok(value) => value.to_string(),
+                    Err(_) => String::new(),
+                }));
+                writeln!(writer, "{}", row.join(","))
+                    .and_then(|_| writer.flush())
+                    .map_err(|e| format!("Ошибка записи CSV: {}", e))
+            }
+            Sink::Sqlite(connection) => {
+                let err = |e: rusqlite::Error| format!("Ошибка записи в SQLite: {e}");
+                let tx = connection.transaction().map_err(err)?;
+                {
+                    let mut insert = tx
+                        .prepare_cached(
+                            "INSERT INTO samples (timestamp, tag_id, value, error) VALUES (?1, ?2, ?3, ?4)",
+                        )
+                        .map_err(err)?;
+                    for (tag_id, sample) in self.tag_ids.iter().zip(samples) {
+                        let (value, error) = match sample {
+                            Ok(value) => (Some(*value), None),
+                            Err(e) => (None, Some(e.as_str())),
+                        };
+                        insert
+                            .execute(params![timestamp, tag_id, value, error])
+                            .map_err(err)?;
+                    }
+                }
+                tx.commit().map_err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ModbusArea, ModbusDataType, WordOrder};
+
+    fn tag(id: &str, name: &str) -> PollTag {
+        PollTag {
+            id: id.to_string(),
+            name: name.to_string(),
+            area: ModbusArea::HoldingRegister,
+            address: 0,
+            data_type: ModbusDataType::Uint16,
+            word_order: WordOrder::default(),
+        }
+    }
+
+    #[test]
+    fn test_csv_and_sqlite_recording() {
+        let dir = std::env::temp_dir().join(format!("poll_record_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tags = [tag("t1", "Temp, °C"), tag("t2", "")];
+        let samples = [Ok(21.5), Err("Timeout".to_string())];
+
+        let csv = PollRecording {
+            path: dir.join("poll.csv"),
+            format: RecordFormat::Csv,
+        };
+        let _ = std::fs::remove_file(&csv.path);
+        PollRecorder::open(&csv, &tags)
+            .unwrap()
+            .record("100.000", &samples)
+            .unwrap();
+        // Повторное открытие дописывает без второго заголовка
+        PollRecorder::open(&csv, &tags)
+            .unwrap()
+            .record("101.000", &[Ok(22.0), Ok(1.0)])
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&csv.path).unwrap(),
+            "timestamp,\"Temp, °C\",t2\n100.000,21.5,\n101.000,22,1\n"
+        );
+
+        let sqlite = PollRecording {
+            path: dir.join("poll.sqlite"),
+            format: RecordFormat::Sqlite,
+        };
+        let _ = std::fs::remove_file(&sqlite.path);
+        PollRecorder::open(&sqlite, &tags)
+            .unwrap()
+            .record("100.000", &samples)
+            .unwrap();
+        let connection = Connection::open(&sqlite.path).unwrap();
+        let rows: Vec<(String, Option<f64>, Option<String>)> = connection
+            .prepare("SELECT tag_id, value, error FROM samples ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                ("t1".to_string(), Some(21.5), None),
+                ("t2".to_string(), None, Some("Timeout".to_string())),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
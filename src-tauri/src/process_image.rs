@@ -0,0 +1,98 @@
+//! Образ процесса в файле, отображённом в память.
+//!
+//! Файл состоит из заголовка и нескольких последовательных областей (по одной
+//! на каждую область данных Modbus). Каждая область отображается отдельно,
+//! поэтому хранилище может держать её под блокировкой своего шарда.
+//! Записанные в отображение байты попадают в страничный кэш ОС сразу,
+//! так что образ переживает аварийное завершение процесса.
+
+use memmap2::{MmapMut, MmapOptions};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Сигнатура и версия формата файла образа.
+const IMAGE_MAGIC: &[u8; 8] = b"MBIMAGE1";
+
+/// Открытый образ процесса.
+#[derive(Debug)]
+pub struct ProcessImage {
+    /// Отображения областей в порядке, переданном в [`ProcessImage::open`].
+    pub regions: Vec<MmapMut>,
+    /// true, если образ восстановлен из существующего файла,
+    /// false — если файл создан заново (области заполнены нулями).
+    pub restored: bool,
+}
+
+impl ProcessImage {
+    /// Открыть или создать файл образа с областями заданных размеров (в байтах).
+    /// Файл с чужой сигнатурой или другой раскладкой пересоздаётся.
+    pub fn open(path: &Path, region_sizes: &[usize]) -> Result<Self, String> {
+        let total = IMAGE_MAGIC.len() + region_sizes.iter().sum::<usize>();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Не удалось открыть образ процесса {}: {e}", path.display()))?;
+        let len = file
+            .metadata()
+            .map_err(|e| format!("Не удалось прочитать образ процесса: {e}"))?
+            .len();
+
+        let mut magic = [0u8; IMAGE_MAGIC.len()];
+        let restored =
+            len == total as u64 && (&file).read_exact(&mut magic).is_ok() && &magic == IMAGE_MAGIC;
+
+        if !restored {
+            file.set_len(0)
+                .and_then(|_| file.set_len(total as u64))
+                .and_then(|_| (&file).seek(SeekFrom::Start(0)))
+                .and_then(|_| (&file).write_all(IMAGE_MAGIC))
+                .map_err(|e| format!("Не удалось создать образ процесса: {e}"))?;
+        }
+
+        let mut regions = Vec::with_capacity(region_sizes.len());
+        let mut offset = IMAGE_MAGIC.len() as u64;
+        for &size in region_sizes {
+            // SAFETY: файл образа принадлежит симулятору; внешнее изменение его
+            // размера во время работы не поддерживается, как и для файла проекта.
+            let region = unsafe { MmapOptions::new().offset(offset).len(size).map_mut(&file) }
+                .map_err(|e| format!("Не удалось отобразить образ процесса: {e}"))?;
+            regions.push(region);
+            offset += size as u64;
+        }
+
+        Ok(Self { regions, restored })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_survives_reopen() {
+        let path = std::env::temp_dir().join(format!("mb_image_{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut image = ProcessImage::open(&path, &[4, 8]).unwrap();
+        assert!(!image.restored);
+        image.regions[1][3] = 0xAB;
+        drop(image);
+
+        let image = ProcessImage::open(&path, &[4, 8]).unwrap();
+        assert!(image.restored);
+        assert_eq!(image.regions[1][3], 0xAB);
+        drop(image);
+
+        // Другая раскладка — образ создаётся заново
+        let image = ProcessImage::open(&path, &[4, 16]).unwrap();
+        assert!(!image.restored);
+        assert_eq!(image.regions[1][3], 0);
+        drop(image);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
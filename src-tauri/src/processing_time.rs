@@ -0,0 +1,83 @@
+//! Базовое время обработки запросов по кодам функций.
+//!
+//! Реальные устройства отвечают на разные функции с разной задержкой:
+//! запись во flash может занимать десятки миллисекунд, а чтение — единицы.
+//! Задержка добавляется к каждому ответу перед отправкой; случайный разброс
+//! берётся из генератора проекта и воспроизводится при том же зерне.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::seeded_rng::ProjectRng;
+
+/// Время обработки запросов в профиле подключения.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingTimes {
+    /// Время для функций без отдельной настройки, мс.
+    #[serde(default)]
+    pub default_ms: u64,
+    /// Время для отдельных кодов функций, мс (ключ — код функции).
+    #[serde(default)]
+    pub per_function: BTreeMap<u8, u64>,
+    /// Случайная добавка к времени обработки, от 0 до указанного значения, мс.
+    #[serde(default)]
+    pub jitter_ms: u64,
+}
+
+impl ProcessingTimes {
+    /// Задержка ответа на функцию с указанным кодом.
+    /// Для ответа-исключения используется код исходной функции.
+    pub fn delay_for(&self, function_code: u8, rng: &ProjectRng) -> Duration {
+        let ms = self
+            .per_function
+            .get(&(function_code & 0x7F))
+            .copied()
+            .unwrap_or(self.default_ms);
+        let jitter = if self.jitter_ms > 0 {
+            rng.up_to(self.jitter_ms)
+        } else {
+            0
+        };
+        Duration::from_millis(ms + jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_function_overrides_default() {
+        let times: ProcessingTimes =
+            serde_json::from_str(r#"{"defaultMs": 2, "perFunction": {"6": 20, "16": 20}}"#)
+                .unwrap();
+        let rng = ProjectRng::with_seed(1);
+        assert_eq!(times.delay_for(0x03, &rng), Duration::from_millis(2));
+        assert_eq!(times.delay_for(0x06, &rng), Duration::from_millis(20));
+        assert_eq!(times.delay_for(0x90, &rng), Duration::from_millis(20));
+        assert_eq!(
+            ProcessingTimes::default().delay_for(0x10, &rng),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_jitter_is_reproducible() {
+        let times = ProcessingTimes {
+            default_ms: 5,
+            jitter_ms: 10,
+            ..Default::default()
+        };
+        let rng = ProjectRng::with_seed(99);
+        let first: Vec<Duration> = (0..8).map(|_| times.delay_for(0x03, &rng)).collect();
+        rng.restart();
+        let again: Vec<Duration> = (0..8).map(|_| times.delay_for(0x03, &rng)).collect();
+        assert_eq!(first, again);
+        assert!(first
+            .iter()
+            .all(|d| (5..=15).contains(&(d.as_millis() as u64))));
+    }
+}
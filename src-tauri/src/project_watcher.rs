@@ -0,0 +1,197 @@
+//! Отслеживание внешних изменений файла проекта.
+//!
+//! Файл проекта периодически проверяется (время изменения и размер).
+//! При внешнем изменении в UI отправляется событие с новым содержимым проекта,
+//! а при включённой автоперезагрузке переменные сливаются с хранилищем данных
+//! без потери текущих runtime-значений.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::data_store::SharedDataStore;
+use crate::types::ModbusProject;
+
+/// Название события об изменении файла проекта.
+const PROJECT_CHANGED_EVENT_NAME: &str = "project-file-changed";
+
+/// Период опроса файла проекта.
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Отпечаток файла для обнаружения изменений.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+        })
+    }
+}
+
+/// Событие об изменении файла проекта.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectFileChangedEvent {
+    pub path: String,
+    /// Новое содержимое проекта (если файл удалось разобрать).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<ModbusProject>,
+    /// Были ли переменные автоматически перезагружены в хранилище.
+    pub reloaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Состояние наблюдения за файлом проекта.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectWatchStatus {
+    pub watching: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub auto_reload: bool,
+}
+
+/// Наблюдатель за файлом проекта.
+pub struct ProjectWatcher {
+    data_store: SharedDataStore,
+    /// Отслеживаемый файл.
+    path: RwLock<Option<PathBuf>>,
+    /// Последний известный отпечаток файла.
+    stamp: RwLock<Option<FileStamp>>,
+    /// Автоматически перезагружать переменные при изменении.
+    auto_reload: AtomicBool,
+    /// Поколение наблюдения: задача опроса завершается при его смене.
+    generation: AtomicU64,
+}
+
+impl ProjectWatcher {
+    pub fn new(data_store: SharedDataStore) -> Self {
+        Self {
+            data_store,
+            path: RwLock::new(None),
+            stamp: RwLock::new(None),
+            auto_reload: AtomicBool::new(false),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Начать наблюдение за файлом. Предыдущее наблюдение прекращается.
+    pub fn watch(self: &Arc<Self>, app_handle: AppHandle, path: PathBuf, auto_reload: bool) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.stamp.write() = FileStamp::read(&path);
+        *self.path.write() = Some(path.clone());
+        self.auto_reload.store(auto_reload, Ordering::SeqCst);
+
+        log::info!("Наблюдение за файлом проекта {}", path.display());
+
+        let watcher = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                if watcher.generation.load(Ordering::SeqCst) != generation {
+                    break;
+                }
+                if let Some(event) = watcher.poll() {
+                    if let Err(e) = app_handle.emit(PROJECT_CHANGED_EVENT_NAME, &event) {
+                        log::warn!("Не удалось отправить событие изменения проекта: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Прекратить наблюдение.
+    pub fn unwatch(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *self.path.write() = None;
+        *self.stamp.write() = None;
+    }
+
+    /// Получить текущее состояние наблюдения.
+    pub fn status(&self) -> ProjectWatchStatus {
+        let path = self.path.read().clone();
+        ProjectWatchStatus {
+            watching: path.is_some(),
+            path: path.map(|p| p.to_string_lossy().to_string()),
+            auto_reload: self.auto_reload.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Отметить, что файл был записан самим приложением,
+    /// чтобы собственное сохранение не считалось внешним изменением.
+    pub fn note_saved(&self, path: &Path) {
+        if self.path.read().as_deref() == Some(path) {
+            *self.stamp.write() = FileStamp::read(path);
+        }
+    }
+
+    /// Проверить файл и сформировать событие, если он изменился.
+    fn poll(&self) -> Option<ProjectFileChangedEvent> {
+        let path = self.path.read().clone()?;
+        let stamp = FileStamp::read(&path);
+        {
+            let mut last = self.stamp.write();
+            if *last == stamp {
+                return None;
+            }
+            *last = stamp;
+        }
+
+        // Файл удалён — сообщать нечего, ждём его появления
+        stamp?;
+
+        log::info!("Файл проекта изменён извне: {}", path.display());
+
+        let mut event = ProjectFileChangedEvent {
+            path: path.to_string_lossy().to_string(),
+            project: None,
+            reloaded: false,
+            error: None,
+        };
+
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Не удалось прочитать файл проекта: {e}"))
+            .and_then(|data| {
+                serde_json::from_str::<ModbusProject>(&data)
+                    .map_err(|e| format!("Ошибка JSON проекта: {e}"))
+            });
+
+        match parsed {
+            Ok(mut project) => {
+                if self.auto_reload.load(Ordering::SeqCst) {
+                    project.variables = self.data_store.merge_variables(&project.variables);
+                    event.reloaded = true;
+                }
+                event.project = Some(project);
+            }
+            Err(e) => {
+                log::warn!("{}", e);
+                event.error = Some(e);
+            }
+        }
+
+        Some(event)
+    }
+}
+
+/// Общая ссылка на наблюдатель.
+pub type SharedProjectWatcher = Arc<ProjectWatcher>;
+
+/// Создать общий наблюдатель за файлом проекта.
+pub fn create_shared_project_watcher(data_store: SharedDataStore) -> SharedProjectWatcher {
+    Arc::new(ProjectWatcher::new(data_store))
+}
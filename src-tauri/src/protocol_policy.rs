@@ -0,0 +1,148 @@
+//! Настройки строгости протокола для профиля подключения.
+//!
+//! Реальные устройства по-разному реагируют на отклонения от спецификации:
+//! одни отбрасывают такие запросы, другие отвечают как ни в чём не бывало.
+//! Для каждого вида отклонения профиль задаёт политику — отклонить или допустить.
+//! Каждое обнаруженное отклонение записывается в лог обмена.
+
+use serde::{Deserialize, Serialize};
+
+use crate::modbus_protocol::ModbusRequest;
+
+/// Реакция на отклонение от протокола.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviationPolicy {
+    /// Отбросить запрос без ответа.
+    Reject,
+    /// Обработать запрос как обычно.
+    Tolerate,
+}
+
+/// Реакция на заголовок MBAP с длиной вне диапазона 2..254.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IllegalLengthPolicy {
+    /// Закрыть соединение.
+    Close,
+    /// Отбросить байты фрейма без ответа.
+    #[default]
+    Ignore,
+    /// Ответить исключением Illegal Data Value.
+    Exception,
+}
+
+/// Политики для каждого вида отклонения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolStrictness {
+    /// Ненулевой Protocol ID в заголовке MBAP.
+    pub protocol_id: DeviationPolicy,
+    /// Длина PDU не совпадает с ожидаемой для функции.
+    pub length_mismatch: DeviationPolicy,
+    /// Unit ID не совпадает с адресом профиля (и не широковещательный 0).
+    pub unknown_unit_id: DeviationPolicy,
+    /// Недопустимая длина в заголовке MBAP. Такой фрейм всегда отбрасывается
+    /// целиком, чтобы не сбить разбор следующих.
+    #[serde(default)]
+    pub illegal_length: IllegalLengthPolicy,
+}
+
+impl Default for ProtocolStrictness {
+    /// По умолчанию — прежнее поведение сервера.
+    fn default() -> Self {
+        Self {
+            protocol_id: DeviationPolicy::Reject,
+            length_mismatch: DeviationPolicy::Tolerate,
+            unknown_unit_id: DeviationPolicy::Reject,
+            illegal_length: IllegalLengthPolicy::default(),
+        }
+    }
+}
+
+/// Обнаруженное отклонение от протокола.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deviation {
+    ProtocolId(u16),
+    LengthMismatch { expected: usize, actual: usize },
+    UnknownUnitId(u8),
+}
+
+impl Deviation {
+    /// Описание отклонения для лога.
+    pub fn describe(&self) -> String {
+        match self {
+            Deviation::ProtocolId(id) => format!("Protocol ID = {} (ожидается 0)", id),
+            Deviation::LengthMismatch { expected, actual } => {
+                format!("длина данных PDU {} байт (ожидается {})", actual, expected)
+            }
+            Deviation::UnknownUnitId(id) => format!("неизвестный Unit ID {}", id),
+        }
+    }
+}
+
+impl ProtocolStrictness {
+    /// Политика для конкретного отклонения.
+    pub fn policy_for(&self, deviation: &Deviation) -> DeviationPolicy {
+        match deviation {
+            Deviation::ProtocolId(_) => self.protocol_id,
+            Deviation::LengthMismatch { .. } => self.length_mismatch,
+            Deviation::UnknownUnitId(_) => self.unknown_unit_id,
+        }
+    }
+}
+
+/// Найти отклонения запроса от протокола.
+pub fn find_deviations(request: &ModbusRequest, unit_id: u8) -> Vec<Deviation> {
+    let mut deviations = Vec::new();
+
+    if request.header.protocol_id != 0 {
+        deviations.push(Deviation::ProtocolId(request.header.protocol_id));
+    }
+    if let Some(expected) = request.expected_data_length() {
+        if request.data.len() != expected {
+            deviations.push(Deviation::LengthMismatch {
+                expected,
+                actual: request.data.len(),
+            });
+        }
+    }
+    if request.header.unit_id != unit_id && request.header.unit_id != 0 {
+        deviations.push(Deviation::UnknownUnitId(request.header.unit_id));
+    }
+
+    deviations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_deviations() {
+        // Protocol ID 5, unit 9, лишний байт в запросе чтения
+        let frame = [
+            0x00, 0x01, 0x00, 0x05, 0x00, 0x07, 0x09, 0x03, 0x00, 0x00, 0x00, 0x01, 0xFF,
+        ];
+        let request = ModbusRequest::parse_lenient(&frame).unwrap();
+        let deviations = find_deviations(&request, 1);
+        assert_eq!(
+            deviations,
+            vec![
+                Deviation::ProtocolId(5),
+                Deviation::LengthMismatch {
+                    expected: 4,
+                    actual: 5
+                },
+                Deviation::UnknownUnitId(9),
+            ]
+        );
+
+        let strictness = ProtocolStrictness::default();
+        assert_eq!(
+            strictness.policy_for(&deviations[1]),
+            DeviationPolicy::Tolerate
+        );
+        assert!(find_deviations(&request, 9).len() == 2);
+    }
+}
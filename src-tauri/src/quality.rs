@@ -0,0 +1,282 @@
+//! Качество данных переменных (Good/Stale/Bad).
+//!
+//! Качество задаётся вручную из UI или программно, либо вычисляется по
+//! таймауту устаревания: если значение переменной не менялось дольше
+//! заданного времени, она считается устаревшей. Ручное качество имеет
+//! приоритет над вычисленным.
+//!
+//! Для проверки обработки мастером данных плохого качества код качества
+//! может отражаться в парный регистр статуса: 0 — Good, 1 — Stale, 2 — Bad.
+
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::SharedDataStore;
+use crate::types::ModbusValue;
+
+/// Качество данных переменной.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableQuality {
+    #[default]
+    Good,
+    Stale,
+    Bad,
+}
+
+impl VariableQuality {
+    /// Код качества для регистра статуса.
+    pub fn code(self) -> u16 {
+        match self {
+            VariableQuality::Good => 0,
+            VariableQuality::Stale => 1,
+            VariableQuality::Bad => 2,
+        }
+    }
+}
+
+/// Настройка качества переменной.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityConfig {
+    /// ID переменной.
+    pub variable_id: String,
+    /// Через сколько миллисекунд без изменения значения переменная устаревает.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale_after_ms: Option<u64>,
+    /// ID парного регистра статуса, в который записывается код качества.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_variable_id: Option<String>,
+}
+
+/// Качество переменной для UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityStatus {
+    pub variable_id: String,
+    pub quality: VariableQuality,
+    /// Качество задано вручную (а не вычислено по таймауту).
+    pub manual: bool,
+}
+
+/// Состояние качества одной переменной.
+#[derive(Debug)]
+struct QualityRuntime {
+    manual: Option<VariableQuality>,
+    last_value: Option<f64>,
+    changed_at: Instant,
+    quality: VariableQuality,
+    /// Последний записанный в регистр статуса код.
+    published: Option<u16>,
+}
+
+impl QualityRuntime {
+    fn new(now: Instant) -> Self {
+        Self {
+            manual: None,
+            last_value: None,
+            changed_at: now,
+            quality: VariableQuality::Good,
+            published: None,
+        }
+    }
+}
+
+/// Настройки качества и текущее качество переменных.
+#[derive(Default)]
+pub struct QualityManager {
+    configs: RwLock<Vec<QualityConfig>>,
+    states: Mutex<HashMap<String, QualityRuntime>>,
+}
+
+impl QualityManager {
+    /// Нет ни настроек, ни вручную заданного качества.
+    pub fn is_empty(&self) -> bool {
+        self.configs.read().is_empty() && self.states.lock().is_empty()
+    }
+
+    /// Список настроек качества.
+    pub fn configs(&self) -> Vec<QualityConfig> {
+        self.configs.read().clone()
+    }
+
+    /// Заменить все настройки (например, при загрузке проекта).
+    /// Ручное качество сбрасывается.
+    pub fn set_configs(&self, configs: Vec<QualityConfig>) {
+        *self.configs.write() = configs;
+        self.states.lock().clear();
+    }
+
+    /// Добавить настройку или заменить существующую для той же переменной.
+    pub fn upsert(&self, config: QualityConfig) -> Result<QualityConfig, String> {
+        if config.variable_id.is_empty() {
+            return Err("Не задана переменная".to_string());
+        }
+        if config.stale_after_ms == Some(0) {
+            return Err("Таймаут устаревания должен быть больше нуля".to_string());
+        }
+        let mut configs = self.configs.write();
+        match configs
+            .iter_mut()
+            .find(|c| c.variable_id == config.variable_id)
+        {
+            Some(existing) => *existing = config.clone(),
+            None => configs.push(config.clone()),
+        }
+        Ok(config)
+    }
+
+    /// Удалить настройку. Возвращает false, если настройка не найдена.
+    pub fn remove(&self, variable_id: &str) -> bool {
+        let mut configs = self.configs.write();
+        let before = configs.len();
+        configs.retain(|c| c.variable_id != variable_id);
+        configs.len() != before
+    }
+
+    /// Задать качество вручную; None возвращает вычисление по таймауту.
+    /// Применяется на следующем такте движка.
+    pub fn set_manual(&self, variable_id: &str, quality: Option<VariableQuality>) {
+        let mut states = self.states.lock();
+        let state = states
+            .entry(variable_id.to_string())
+            .or_insert_with(|| QualityRuntime::new(Instant::now()));
+        state.manual = quality;
+    }
+
+    /// Ручное качество переменной, если задано.
+    pub fn manual(&self, variable_id: &str) -> Option<VariableQuality> {
+        self.states.lock().get(variable_id)?.manual
+    }
+
+    /// Качество переменных, отличное от Good или заданное вручную.
+    pub fn statuses(&self) -> Vec<QualityStatus> {
+        let states = self.states.lock();
+        let mut statuses: Vec<QualityStatus> = states
+            .iter()
+            .filter(|(_, s)| s.manual.is_some() || s.quality != VariableQuality::Good)
+            .map(|(id, s)| QualityStatus {
+                variable_id: id.clone(),
+                quality: s.quality,
+                manual: s.manual.is_some(),
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.variable_id.cmp(&b.variable_id));
+        statuses
+    }
+
+    /// Вычислить качество переменных и обновить регистры статуса.
+    /// Возвращает true, если качество хотя бы одной переменной изменилось.
+    pub fn evaluate(
+        &self,
+        data_store: &SharedDataStore,
+        snapshot: &HashMap<String, f64>,
+        now: Instant,
+    ) -> bool {
+        let configs = self.configs.read();
+        let mut states = self.states.lock();
+        let mut changed = false;
+
+        let ids: BTreeSet<String> = configs
+            .iter()
+            .map(|c| c.variable_id.clone())
+            .chain(states.keys().cloned())
+            .collect();
+
+        for id in ids {
+            let config = configs.iter().find(|c| c.variable_id == id);
+            let state = states
+                .entry(id.clone())
+                .or_insert_with(|| QualityRuntime::new(now));
+
+            let value = snapshot.get(&id).copied();
+            if value != state.last_value {
+                state.last_value = value;
+                state.changed_at = now;
+            }
+
+            let stale = config.and_then(|c| c.stale_after_ms).is_some_and(|ms| {
+                now.duration_since(state.changed_at) >= Duration::from_millis(ms)
+            });
+            let quality = state.manual.unwrap_or(if stale {
+                VariableQuality::Stale
+            } else {
+                VariableQuality::Good
+            });
+            if quality != state.quality {
+                log::info!("Качество переменной '{}': {:?}", id, quality);
+                state.quality = quality;
+                changed = true;
+            }
+
+            if let Some(status_id) = config.and_then(|c| c.status_variable_id.as_ref()) {
+                let code = quality.code();
+                if state.published != Some(code) {
+                    data_store.update_variable(status_id, ModbusValue::Number(code as f64));
+                    state.published = Some(code);
+                }
+            }
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusVariable};
+
+    #[test]
+    fn test_stale_timeout_and_manual_override() {
+        let store = create_shared_data_store();
+        store.load_variables(&[
+            ModbusVariable::new(
+                "temp",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            ),
+            ModbusVariable::new(
+                "temp_q",
+                ModbusArea::HoldingRegister,
+                1,
+                ModbusDataType::Uint16,
+            ),
+        ]);
+        let manager = QualityManager::default();
+        manager
+            .upsert(QualityConfig {
+                variable_id: "temp".to_string(),
+                stale_after_ms: Some(1000),
+                status_variable_id: Some("temp_q".to_string()),
+            })
+            .unwrap();
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+
+        assert!(!manager.evaluate(&store, &store.numeric_snapshot(), t0));
+        assert_eq!(store.read_holding_registers(1, 1).unwrap(), vec![0]);
+
+        // Значение не менялось дольше таймаута
+        assert!(manager.evaluate(&store, &store.numeric_snapshot(), ms(1000)));
+        assert_eq!(store.read_holding_registers(1, 1).unwrap(), vec![1]);
+
+        // Новое значение возвращает Good
+        store.update_variable("temp", ModbusValue::Number(5.0));
+        assert!(manager.evaluate(&store, &store.numeric_snapshot(), ms(1100)));
+        assert_eq!(store.read_holding_registers(1, 1).unwrap(), vec![0]);
+
+        manager.set_manual("temp", Some(VariableQuality::Bad));
+        manager.evaluate(&store, &store.numeric_snapshot(), ms(1200));
+        assert_eq!(store.read_holding_registers(1, 1).unwrap(), vec![2]);
+        let statuses = manager.statuses();
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses[0].manual);
+        assert_eq!(statuses[0].quality, VariableQuality::Bad);
+    }
+}
@@ -0,0 +1,416 @@
+//! Обмен картой регистров в стандартизированном JSON-формате.
+//!
+//! Карта регистров — это только список переменных (без профилей подключения
+//! и прочих настроек проекта). Формат описан JSON Schema ([`REGISTER_MAP_SCHEMA`]),
+//! поэтому файлы карт можно проверять стандартными инструментами.
+//!
+//! Адреса в файле записываются в соглашении, указанном в поле `addressing`
+//! (по умолчанию — 0-based адреса протокола).
+
+use serde::{Deserialize, Serialize};
+
+use crate::addressing::AddressingConvention;
+use crate::types::{
+    generate_variable_id, ModbusArea, ModbusDataType, ModbusValue, ModbusVariable, ProjectMetadata,
+};
+
+/// Идентификатор схемы карты регистров.
+pub const REGISTER_MAP_SCHEMA_ID: &str =
+    "https://github.com/voronovmaksim88/ModBus_TCP_Client_RUST/schemas/register-map-v1.json";
+
+/// Текущая версия формата карты регистров.
+pub const REGISTER_MAP_VERSION: u32 = 1;
+
+/// JSON Schema (draft 2020-12) формата карты регистров.
+pub const REGISTER_MAP_SCHEMA: &str = r##"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "$id": "https://github.com/voronovmaksim88/ModBus_TCP_Client_RUST/schemas/register-map-v1.json",
+  "title": "Modbus register map",
+  "description": "List of Modbus variables exchanged between the simulator and other tools.",
+  "type": "object",
+  "required": ["version", "variables"],
+  "properties": {
+    "$schema": { "type": "string" },
+    "version": { "const": 1 },
+    "name": { "type": "string", "description": "Human readable map name" },
+    "addressing": {
+      "enum": ["zero_based", "one_based", "modicon"],
+      "default": "zero_based",
+      "description": "Address convention used by the 'address' fields: protocol address, protocol address + 1, or Modicon number (40001 = holding register 0)"
+    },
+    "metadata": {
+      "type": "object",
+      "description": "Test campaign the map was exported from",
+      "properties": {
+        "description": { "type": "string" },
+        "customer": { "type": "string" },
+        "firmware": { "type": "string", "description": "Firmware version of the device under test" },
+        "tags": { "type": "array", "items": { "type": "string" } }
+      },
+      "additionalProperties": false
+    },
+    "variables": {
+      "type": "array",
+      "items": { "$ref": "#/$defs/variable" }
+    }
+  },
+  "additionalProperties": false,
+  "$defs": {
+    "variable": {
+      "type": "object",
+      "required": ["name", "area", "address", "dataType"],
+      "properties": {
+        "id": { "type": "string", "description": "Stable identifier; generated on import when missing" },
+        "name": { "type": "string" },
+        "area": { "enum": ["coil", "discrete_input", "input_register", "holding_register"] },
+        "address": { "type": "integer", "minimum": 0, "maximum": 465536 },
+        "dataType": { "enum": ["bool", "uint16", "int16", "uint32", "float32"] },
+        "value": { "type": ["number", "boolean", "null"], "description": "Initial value" },
+        "bit": { "type": "integer", "minimum": 0, "maximum": 15 },
+        "readonly": { "type": "boolean" },
+        "note": { "type": "string" },
+        "initialValue": { "type": ["number", "boolean"], "description": "Value applied when the project is loaded" },
+        "resetValue": { "type": ["number", "boolean"], "description": "Value applied by a reset" }
+      },
+      "additionalProperties": false
+    }
+  }
+}
+"##;
+
+/// Карта регистров в формате обмена.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RegisterMap {
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub addressing: AddressingConvention,
+    /// Сведения о проекте, из которого выгружена карта.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ProjectMetadata>,
+    pub variables: Vec<RegisterMapEntry>,
+}
+
+/// Одна переменная карты регистров.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RegisterMapEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub area: ModbusArea,
+    /// Адрес в соглашении карты (`RegisterMap::addressing`).
+    pub address: u32,
+    pub data_type: ModbusDataType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<ModbusValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bit: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readonly: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_value: Option<ModbusValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_value: Option<ModbusValue>,
+}
+
+impl RegisterMap {
+    /// Построить карту регистров из списка переменных.
+    /// Адреса записываются в указанном соглашении.
+    pub fn from_variables(
+        name: Option<String>,
+        variables: &[ModbusVariable],
+        addressing: AddressingConvention,
+    ) -> Self {
+        Self {
+            schema: Some(REGISTER_MAP_SCHEMA_ID.to_string()),
+            version: REGISTER_MAP_VERSION,
+            name,
+            addressing,
+            metadata: None,
+            variables: variables
+                .iter()
+                .map(|var| RegisterMapEntry {
+                    id: Some(var.id.clone()),
+                    name: var.name.clone(),
+                    area: var.area,
+                    address: addressing.to_display(var.area, var.address),
+                    data_type: var.data_type,
+                    value: Some(var.value.clone()),
+                    bit: var.bit,
+                    readonly: var.readonly,
+                    note: var.note.clone(),
+                    initial_value: var.initial_value.clone(),
+                    reset_value: var.reset_value.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Разобрать и проверить карту регистров из JSON.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let map: RegisterMap =
+            serde_json::from_str(json).map_err(|e| format!("Ошибка JSON карты регистров: {e}"))?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// Проверить ограничения, которые не выражаются через serde.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != REGISTER_MAP_VERSION {
+            return Err(format!(
+                "Неподдерживаемая версия карты регистров: {} (ожидается {})",
+                self.version, REGISTER_MAP_VERSION
+            ));
+        }
+
+        let errors: Vec<String> = self
+            .variables
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                entry
+                    .validate(self.addressing)
+                    .err()
+                    .map(|e| format!("#{i}: {e}"))
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Некорректная карта регистров: {}",
+                errors.join("; ")
+            ))
+        }
+    }
+
+    /// Преобразовать карту в переменные проекта (адреса протокола).
+    /// Переменным без ID назначаются новые идентификаторы.
+    /// Карта должна быть предварительно проверена через [`RegisterMap::validate`].
+    pub fn into_variables(self) -> Vec<ModbusVariable> {
+        let addressing = self.addressing;
+        self.variables
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| ModbusVariable {
+                name: entry.name,
+                value: entry.value.unwrap_or_default(),
+                bit: entry.bit,
+                readonly: entry.readonly,
+                note: entry.note,
+                initial_value: entry.initial_value,
+                reset_value: entry.reset_value,
+                ..ModbusVariable::new(
+                    entry.id.unwrap_or_else(|| generate_variable_id(i)),
+                    entry.area,
+                    addressing
+                        .to_protocol(entry.area, entry.address)
+                        .unwrap_or_default(),
+                    entry.data_type,
+                )
+            })
+            .collect()
+    }
+
+    /// Карта в виде Markdown-таблицы для чтения человеком.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# {}\n\n",
+            self.name.as_deref().unwrap_or("Карта регистров")
+        );
+        if let Some(metadata) = &self.metadata {
+            for (label, value) in [
+                ("Описание", &metadata.description),
+                ("Заказчик", &metadata.customer),
+                ("Прошивка", &metadata.firmware),
+            ] {
+                if !value.is_empty() {
+                    out += &format!("- {}: {}\n", label, markdown_cell(value));
+                }
+            }
+            if !metadata.tags.is_empty() {
+                out += &format!("- Метки: {}\n", markdown_cell(&metadata.tags.join(", ")));
+            }
+        }
+        let addressing = match self.addressing {
+            AddressingConvention::ZeroBased => "адреса протокола с 0",
+            AddressingConvention::OneBased => "адреса с 1",
+            AddressingConvention::Modicon => "номера Modicon",
+        };
+        out += &format!(
+            "- Адресация: {}\n- Переменных: {}\n\n",
+            addressing,
+            self.variables.len()
+        );
+
+        out += "| Имя | Область | Адрес | Тип | Значение | Доступ | Примечание |\n";
+        out += "|---|---|---|---|---|---|---|\n";
+        for entry in &self.variables {
+            let area = match entry.area {
+                ModbusArea::Coil => "coil",
+                ModbusArea::DiscreteInput => "discrete input",
+                ModbusArea::InputRegister => "input register",
+                ModbusArea::HoldingRegister => "holding register",
+            };
+            let address = match entry.bit {
+                Some(bit) => format!("{}.{}", entry.address, bit),
+                None => entry.address.to_string(),
+            };
+            let value = match &entry.value {
+                Some(ModbusValue::Bool(b)) => b.to_string(),
+                Some(ModbusValue::Number(n)) => n.to_string(),
+                Some(ModbusValue::Null) | None => String::new(),
+            };
+            let read_only_area = matches!(
+                entry.area,
+                ModbusArea::DiscreteInput | ModbusArea::InputRegister
+            );
+            let access = if read_only_area || entry.readonly == Some(true) {
+                "R"
+            } else {
+                "RW"
+            };
+            let data_type = match entry.data_type {
+                ModbusDataType::Bool => "bool",
+                ModbusDataType::Uint16 => "uint16",
+                ModbusDataType::Int16 => "int16",
+                ModbusDataType::Uint32 => "uint32",
+                ModbusDataType::Float32 => "float32",
+            };
+            out += &format!(
+                "| {} | {} | {} | {} | {} | {} | {} |\n",
+                markdown_cell(&entry.name),
+                area,
+                address,
+                data_type,
+                value,
+                access,
+                markdown_cell(entry.note.as_deref().unwrap_or(""))
+            );
+        }
+        out
+    }
+}
+
+/// Экранировать текст для ячейки Markdown-таблицы.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+impl RegisterMapEntry {
+    fn validate(&self, addressing: AddressingConvention) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("пустое имя переменной".to_string());
+        }
+        let address = addressing.to_protocol(self.area, self.address)?;
+        let last = address as u32 + self.data_type.register_count() as u32 - 1;
+        if last > u16::MAX as u32 {
+            return Err(format!(
+                "'{}' выходит за пределы адресного пространства",
+                self.name
+            ));
+        }
+        if let Some(bit) = self.bit {
+            if bit > 15 {
+                return Err(format!(
+                    "'{}': номер бита {} вне диапазона 0..15",
+                    self.name, bit
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_map_roundtrip() {
+        let vars = vec![ModbusVariable {
+            name: "Temperature".to_string(),
+            value: ModbusValue::Number(21.5),
+            readonly: Some(true),
+            ..ModbusVariable::new(
+                "var1",
+                ModbusArea::InputRegister,
+                7,
+                ModbusDataType::Float32,
+            )
+        }];
+
+        let mut map = RegisterMap::from_variables(None, &vars, AddressingConvention::Modicon);
+        map.metadata = Some(ProjectMetadata {
+            firmware: "2.1.0".to_string(),
+            tags: vec!["fat".to_string()],
+            ..Default::default()
+        });
+        let json = serde_json::to_string(&map).unwrap();
+        let parsed = RegisterMap::parse(&json).unwrap();
+        assert_eq!(parsed.metadata, map.metadata);
+        let imported = parsed.into_variables();
+
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].id, "var1");
+        assert!(json.contains("30008"));
+        assert_eq!(imported[0].address, 7);
+        assert_eq!(imported[0].data_type, ModbusDataType::Float32);
+    }
+
+    #[test]
+    fn test_register_map_markdown() {
+        let vars = vec![ModbusVariable {
+            name: "Alarm | trip".to_string(),
+            value: ModbusValue::Bool(true),
+            bit: Some(3),
+            note: Some("line 1\nline 2".to_string()),
+            ..ModbusVariable::new(
+                "alarm",
+                ModbusArea::HoldingRegister,
+                4,
+                ModbusDataType::Bool,
+            )
+        }];
+        let mut map = RegisterMap::from_variables(None, &vars, AddressingConvention::OneBased);
+        map.metadata = Some(ProjectMetadata {
+            customer: "ACME".to_string(),
+            ..Default::default()
+        });
+        let markdown = map.to_markdown();
+        assert!(markdown.starts_with("# Карта регистров\n"));
+        assert!(markdown.contains("- Заказчик: ACME\n"));
+        assert!(markdown.contains(
+            "| Alarm \\| trip | holding register | 5.3 | bool | true | RW | line 1 line 2 |"
+        ));
+    }
+
+    #[test]
+    fn test_register_map_rejects_overflow_and_unknown_fields() {
+        let overflow = r#"{"version":1,"variables":[
+            {"name":"x","area":"holding_register","address":65535,"dataType":"uint32"}]}"#;
+        assert!(RegisterMap::parse(overflow).is_err());
+
+        let unknown = r#"{"version":1,"variables":[
+            {"name":"x","area":"coil","address":0,"dataType":"bool","scale":2}]}"#;
+        assert!(RegisterMap::parse(unknown).is_err());
+
+        let wrong_area = r#"{"version":1,"addressing":"modicon","variables":[
+            {"name":"x","area":"input_register","address":40001,"dataType":"uint16"}]}"#;
+        assert!(RegisterMap::parse(wrong_area).is_err());
+    }
+
+    #[test]
+    fn test_register_map_schema_is_valid_json() {
+        let schema: serde_json::Value = serde_json::from_str(REGISTER_MAP_SCHEMA).unwrap();
+        assert_eq!(schema["$id"], REGISTER_MAP_SCHEMA_ID);
+    }
+}
@@ -0,0 +1,391 @@
+//! Сценарии запросов мастера — лёгкий язык протокольных тестов.
+//!
+//! Сценарий — последовательность шагов против удалённого устройства:
+//! прочитать ячейку и сравнить с ожидаемым значением, записать значение,
+//! подождать. Для чтения и записи можно ожидать ответ-исключение с заданным
+//! кодом. Невыполненное ожидание отмечает шаг неудачным, но сценарий
+//! продолжается; сбой связи прерывает его, остальные шаги не выполняются.
+//!
+//! Пробный прогон ([`preview`]) не подключается к устройству: для записей
+//! сообщаются слова, которые были бы отправлены, паузы пропускаются.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::master::{write_words, MasterConnection, PollTag, RequestError};
+use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ProjectMetadata, WordOrder};
+
+/// Сценарий запросов.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestScript {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+    /// Таймаут ответа, мс.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    pub steps: Vec<ScriptStep>,
+}
+
+fn default_port() -> u16 {
+    502
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_data_type() -> ModbusDataType {
+    ModbusDataType::Uint16
+}
+
+/// Шаг сценария запросов.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum ScriptStep {
+    /// Прочитать значение; при `expect` — сравнить с допуском `tolerance`.
+    #[serde(rename_all = "camelCase")]
+    Read {
+        area: ModbusArea,
+        address: u16,
+        #[serde(default = "default_data_type")]
+        data_type: ModbusDataType,
+        #[serde(default)]
+        expect: Option<ModbusValue>,
+        #[serde(default)]
+        tolerance: f64,
+        /// Ожидаемый код исключения вместо значения.
+        #[serde(default)]
+        expect_exception: Option<u8>,
+    },
+    /// Записать значение в coil или holding register.
+    #[serde(rename_all = "camelCase")]
+    Write {
+        area: ModbusArea,
+        address: u16,
+        #[serde(default = "default_data_type")]
+        data_type: ModbusDataType,
+        value: ModbusValue,
+        #[serde(default)]
+        expect_exception: Option<u8>,
+    },
+    /// Подождать заданное время.
+    Wait { ms: u64 },
+}
+
+impl ScriptStep {
+    fn action(&self) -> &'static str {
+        match self {
+            ScriptStep::Read { .. } => "read",
+            ScriptStep::Write { .. } => "write",
+            ScriptStep::Wait { .. } => "wait",
+        }
+    }
+}
+
+/// Результат шага.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptStepResult {
+    pub index: usize,
+    pub action: String,
+    pub ok: bool,
+    /// Прочитанное значение.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<ModbusValue>,
+    /// Код исключения в ответе устройства.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exception_code: Option<u8>,
+    /// Слова записи в пробном прогоне.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: f64,
+}
+
+/// Отчёт о выполнении сценария.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptReport {
+    pub passed: bool,
+    pub steps: Vec<ScriptStepResult>,
+    /// Сбой подключения или связи, прервавший сценарий.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Пробный прогон без обращения к устройству.
+    pub dry_run: bool,
+    /// Сведения о проекте для заголовка отчёта.
+    #[serde(skip_serializing_if = "ProjectMetadata::is_empty")]
+    pub metadata: ProjectMetadata,
+}
+
+/// Итог шага до замера времени.
+struct Outcome {
+    value: Option<ModbusValue>,
+    exception_code: Option<u8>,
+    error: Option<String>,
+}
+
+/// Сравнить ответ устройства с ожиданиями шага.
+fn check(
+    response: Result<Option<ModbusValue>, u8>,
+    expect: Option<&ModbusValue>,
+    tolerance: f64,
+    expect_exception: Option<u8>,
+) -> Outcome {
+    let (value, exception_code) = match response {
+        Ok(value) => (value, None),
+        Err(code) => (None, Some(code)),
+    };
+    let error = match (exception_code, expect_exception) {
+        (Some(code), Some(expected)) if code == expected => None,
+        (Some(code), Some(expected)) => Some(format!(
+            "Ожидалось исключение 0x{:02X}, получено 0x{:02X}",
+            expected, code
+        )),
+        (Some(code), None) => Some(format!("Исключение 0x{:02X}", code)),
+        (None, Some(expected)) => Some(format!(
+            "Ожидалось исключение 0x{:02X}, получен обычный ответ",
+            expected
+        )),
+        (None, None) => match (expect, &value) {
+            (Some(expected), Some(actual)) if !values_match(expected, actual, tolerance) => {
+                Some(format!("Ожидалось {:?}, прочитано {:?}", expected, actual))
+            }
+            _ => None,
+        },
+    };
+    Outcome {
+        value,
+        exception_code,
+        error,
+    }
+}
+
+fn values_match(expected: &ModbusValue, actual: &ModbusValue, tolerance: f64) -> bool {
+    match (expected, actual) {
+        (ModbusValue::Bool(_), _) | (_, ModbusValue::Bool(_)) => {
+            expected.as_bool() == actual.as_bool()
+        }
+        _ => (expected.as_f64() - actual.as_f64()).abs() <= tolerance,
+    }
+}
+
+fn tag(area: ModbusArea, address: u16, data_type: ModbusDataType) -> PollTag {
+    PollTag {
+        id: String::new(),
+        name: String::new(),
+        area,
+        address,
+        data_type,
+        word_order: WordOrder::default(),
+    }
+}
+
+/// Выполнить сценарий запросов.
+pub async fn run(script: &RequestScript) -> ScriptReport {
+    let started = Instant::now();
+    let mut report = ScriptReport::default();
+
+    let connection = MasterConnection::connect(
+        &script.host,
+        script.port,
+        script.unit_id,
+        Duration::from_millis(script.timeout_ms),
+    )
+    .await;
+    let mut connection = match connection {
+        Ok(connection) => connection,
+        Err(e) => {
+            report.error = Some(e);
+            report.duration_ms = started.elapsed().as_millis() as u64;
+            return report;
+        }
+    };
+
+    for (index, step) in script.steps.iter().enumerate() {
+        let step_started = Instant::now();
+        let response = match step {
+            ScriptStep::Read {
+                area,
+                address,
+                data_type,
+                ..
+            } => connection
+                .read_tag(&tag(*area, *address, *data_type))
+                .await
+                .map(Some),
+            ScriptStep::Write {
+                area,
+                address,
+                data_type,
+                value,
+                ..
+            } => connection
+                .write_tag(&tag(*area, *address, *data_type), value)
+                .await
+                .map(|_| None),
+            ScriptStep::Wait { ms } => {
+                tokio::time::sleep(Duration::from_millis(*ms)).await;
+                Ok(None)
+            }
+        };
+        let response = match response {
+            Ok(value) => Ok(value),
+            Err(RequestError::Exception(code)) => Err(code),
+            Err(RequestError::Transport(e)) => {
+                report.error = Some(format!("Шаг {}: {}", index + 1, e));
+                break;
+            }
+        };
+        let outcome = match step {
+            ScriptStep::Read {
+                expect,
+                tolerance,
+                expect_exception,
+                ..
+            } => check(response, expect.as_ref(), *tolerance, *expect_exception),
+            ScriptStep::Write {
+                expect_exception, ..
+            } => check(response, None, 0.0, *expect_exception),
+            ScriptStep::Wait { .. } => check(response, None, 0.0, None),
+        };
+        report.steps.push(ScriptStepResult {
+            index,
+            action: step.action().to_string(),
+            ok: outcome.error.is_none(),
+            value: outcome.value,
+            exception_code: outcome.exception_code,
+            words: None,
+            error: outcome.error,
+            duration_ms: step_started.elapsed().as_secs_f64() * 1000.0,
+        });
+    }
+
+    report.passed = report.error.is_none() && report.steps.iter().all(|step| step.ok);
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+/// Пробный прогон: проверить шаги и вычислить слова записей без подключения.
+pub fn preview(script: &RequestScript) -> ScriptReport {
+    let steps: Vec<ScriptStepResult> = script
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            let words = match step {
+                ScriptStep::Write {
+                    area,
+                    address,
+                    data_type,
+                    value,
+                    ..
+                } => Some(write_words(&tag(*area, *address, *data_type), value)),
+                _ => None,
+            };
+            let (words, error) = match words {
+                Some(Ok(words)) => (Some(words), None),
+                Some(Err(e)) => (None, Some(e)),
+                None => (None, None),
+            };
+            ScriptStepResult {
+                index,
+                action: step.action().to_string(),
+                ok: error.is_none(),
+                value: None,
+                exception_code: None,
+                words,
+                error,
+                duration_ms: 0.0,
+            }
+        })
+        .collect();
+    ScriptReport {
+        passed: steps.iter().all(|step| step.ok),
+        steps,
+        dry_run: true,
+        ..ScriptReport::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::server::create_shared_server;
+    use crate::types::ModbusVariable;
+
+    #[test]
+    fn test_script_against_local_server() {
+        tauri::async_runtime::block_on(async {
+            let store = create_shared_data_store();
+            store.load_variables(&[ModbusVariable::new(
+                "setpoint",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Float32,
+            )]);
+            let server = create_shared_server(store);
+            let port = std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            server.set_config("127.0.0.1".to_string(), port, 1);
+            server.start().await.unwrap();
+
+            let script: RequestScript = serde_json::from_value(serde_json::json!({
+                "host": "127.0.0.1",
+                "port": port,
+                "steps": [
+                    { "action": "write", "area": "holding_register", "address": 0,
+                      "dataType": "float32", "value": 21.5 },
+                    { "action": "wait", "ms": 1 },
+                    { "action": "read", "area": "holding_register", "address": 0,
+                      "dataType": "float32", "expect": 21.5 },
+                    { "action": "read", "area": "holding_register", "address": 0,
+                      "dataType": "float32", "expect": 20, "tolerance": 2 },
+                    { "action": "read", "area": "coil", "address": 7, "expectException": 2 },
+                ]
+            }))
+            .unwrap();
+            // Пробный прогон сообщает слова записи, не трогая устройство
+            let dry = preview(&script);
+            assert!(dry.passed && dry.dry_run);
+            assert_eq!(dry.steps[0].words, Some(vec![0x41AC, 0x0000]));
+
+            let report = run(&script).await;
+            assert!(report.passed, "{:#?}", report);
+            assert_eq!(report.steps.len(), 5);
+            assert_eq!(report.steps[2].value, Some(ModbusValue::Number(21.5)));
+
+            let script = RequestScript {
+                steps: vec![ScriptStep::Read {
+                    area: ModbusArea::HoldingRegister,
+                    address: 0,
+                    data_type: ModbusDataType::Float32,
+                    expect: Some(ModbusValue::Number(1.0)),
+                    tolerance: 0.0,
+                    expect_exception: None,
+                }],
+                ..script
+            };
+            let report = run(&script).await;
+            assert!(!report.passed);
+            assert!(report.steps[0].error.is_some());
+
+            let _ = server.stop();
+        });
+    }
+}
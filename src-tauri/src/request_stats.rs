@@ -0,0 +1,370 @@
+//! Статистика обработанных запросов по функциям и клиентам.
+//!
+//! Для каждого кода функции и каждого клиента считаются запросы и ответы с
+//! исключением, время обработки собирается в гистограмму. Вместе с остальными
+//! счётчиками (статус сервера, отчёт об исключениях, такты симуляции) они
+//! выгружаются в архивный отчёт фазы испытаний — JSON или CSV.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::exception_stats::ExceptionStatEntry;
+use crate::tick_stats::TickStatsSnapshot;
+use crate::types::{chrono_now_iso, function_code_name, ProjectMetadata, ServerStatus};
+
+/// Верхние границы интервалов гистограммы времени обработки, мкс.
+const LATENCY_BOUNDS_US: [u64; 9] = [
+    100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000,
+];
+
+/// Интервал гистограммы.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramBucket {
+    /// Верхняя граница, мкс; `None` — последний интервал без границы.
+    pub le_us: Option<u64>,
+    pub count: u64,
+}
+
+/// Гистограмма времени обработки запросов.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyHistogram {
+    pub buckets: Vec<HistogramBucket>,
+    pub count: u64,
+    pub min_us: Option<u64>,
+    pub max_us: Option<u64>,
+    pub avg_us: Option<u64>,
+    #[serde(skip)]
+    total_us: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        let buckets = LATENCY_BOUNDS_US
+            .iter()
+            .map(|&bound| Some(bound))
+            .chain([None])
+            .map(|le_us| HistogramBucket { le_us, count: 0 })
+            .collect();
+        Self {
+            buckets,
+            count: 0,
+            min_us: None,
+            max_us: None,
+            avg_us: None,
+            total_us: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, duration_us: u64) {
+        let index = LATENCY_BOUNDS_US
+            .iter()
+            .position(|&bound| duration_us <= bound)
+            .unwrap_or(LATENCY_BOUNDS_US.len());
+        self.buckets[index].count += 1;
+        self.count += 1;
+        self.total_us += duration_us;
+        self.min_us = Some(self.min_us.map_or(duration_us, |min| min.min(duration_us)));
+        self.max_us = Some(self.max_us.map_or(duration_us, |max| max.max(duration_us)));
+        self.avg_us = Some(self.total_us / self.count);
+    }
+}
+
+/// Счётчики одного кода функции.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionStats {
+    pub function_code: u8,
+    pub function_name: String,
+    pub requests: u64,
+    pub exceptions: u64,
+    pub latency: LatencyHistogram,
+}
+
+/// Счётчики одного клиента.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientStats {
+    pub client: String,
+    pub requests: u64,
+    pub exceptions: u64,
+    pub last_seen: String,
+}
+
+#[derive(Debug)]
+struct Inner {
+    since: String,
+    functions: BTreeMap<u8, FunctionStats>,
+    clients: BTreeMap<String, ClientStats>,
+    latency: LatencyHistogram,
+}
+
+impl Default for Inner {
+    fn default() -> Self {
+        Self {
+            since: chrono_now_iso(),
+            functions: BTreeMap::new(),
+            clients: BTreeMap::new(),
+            latency: LatencyHistogram::default(),
+        }
+    }
+}
+
+/// Накопитель статистики запросов.
+#[derive(Debug, Default)]
+pub struct RequestStats {
+    inner: Mutex<Inner>,
+}
+
+impl RequestStats {
+    /// Учесть обработанный запрос.
+    pub fn record(&self, function_code: u8, client_addr: &str, exception: bool, duration_us: u64) {
+        let mut inner = self.inner.lock();
+        let function = inner
+            .functions
+            .entry(function_code)
+            .or_insert_with(|| FunctionStats {
+                function_code,
+                function_name: function_code_name(function_code).to_string(),
+                requests: 0,
+                exceptions: 0,
+                latency: LatencyHistogram::default(),
+            });
+        function.requests += 1;
+        function.exceptions += exception as u64;
+        function.latency.record(duration_us);
+
+        let client = inner
+            .clients
+            .entry(client_addr.to_string())
+            .or_insert_with(|| ClientStats {
+                client: client_addr.to_string(),
+                requests: 0,
+                exceptions: 0,
+                last_seen: String::new(),
+            });
+        client.requests += 1;
+        client.exceptions += exception as u64;
+        client.last_seen = chrono_now_iso();
+
+        inner.latency.record(duration_us);
+    }
+
+    /// Начать отсчёт заново.
+    pub fn reset(&self) {
+        *self.inner.lock() = Inner::default();
+    }
+
+    /// Начало отсчёта, счётчики по функциям и клиентам, общая гистограмма.
+    pub fn snapshot(
+        &self,
+    ) -> (
+        String,
+        Vec<FunctionStats>,
+        Vec<ClientStats>,
+        LatencyHistogram,
+    ) {
+        let inner = self.inner.lock();
+        (
+            inner.since.clone(),
+            inner.functions.values().cloned().collect(),
+            inner.clients.values().cloned().collect(),
+            inner.latency.clone(),
+        )
+    }
+}
+
+/// Общая ссылка на статистику запросов.
+pub type SharedRequestStats = Arc<RequestStats>;
+
+/// Создать общую статистику запросов.
+pub fn create_shared_request_stats() -> SharedRequestStats {
+    Arc::new(RequestStats::default())
+}
+
+/// Формат файла отчёта.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatisticsFormat {
+    Json,
+    Csv,
+}
+
+/// Отчёт со всеми счётчиками.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsReport {
+    pub generated_at: String,
+    /// Начало отсчёта (запуск приложения или последний сброс).
+    pub since: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ProjectMetadata>,
+    pub server: ServerStatus,
+    pub functions: Vec<FunctionStats>,
+    pub clients: Vec<ClientStats>,
+    pub latency: LatencyHistogram,
+    pub exceptions: Vec<ExceptionStatEntry>,
+    pub simulation: TickStatsSnapshot,
+}
+
+impl StatisticsReport {
+    /// Закодировать отчёт в выбранный формат.
+    pub fn encode(&self, format: StatisticsFormat) -> Result<String, String> {
+        match format {
+            StatisticsFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+            StatisticsFormat::Csv => Ok(self.to_csv()),
+        }
+    }
+
+    /// Одна таблица «раздел, объект, показатель, значение».
+    fn to_csv(&self) -> String {
+        let mut rows = CsvRows::default();
+        rows.push("report", "", "generated_at", &self.generated_at);
+        rows.push("report", "", "since", &self.since);
+        let server = &self.server;
+        for (metric, value) in [
+            ("connections", server.connections_count as u64),
+            ("accepted_connections", server.accepted_connections),
+            ("refused_connections", server.refused_connections),
+            ("accept_errors", server.accept_errors),
+            (
+                "duplicate_transaction_ids",
+                server.duplicate_transaction_ids,
+            ),
+            ("write_storms", server.write_storms),
+            ("storm_suppressed_writes", server.storm_suppressed_writes),
+        ] {
+            rows.push("server", "", metric, value);
+        }
+
+        rows.histogram("total", "", &self.latency);
+        for function in &self.functions {
+            let subject = format!(
+                "0x{:02X} {}",
+                function.function_code, function.function_name
+            );
+            rows.push("function", &subject, "requests", function.requests);
+            rows.push("function", &subject, "exceptions", function.exceptions);
+            rows.histogram("function", &subject, &function.latency);
+        }
+        for client in &self.clients {
+            rows.push("client", &client.client, "requests", client.requests);
+            rows.push("client", &client.client, "exceptions", client.exceptions);
+            rows.push("client", &client.client, "last_seen", &client.last_seen);
+        }
+        for entry in &self.exceptions {
+            let subject = format!(
+                "0x{:02X} {}+{} {}",
+                entry.function_code, entry.start_address, entry.quantity, entry.exception_name
+            );
+            rows.push("exception", &subject, "count", entry.count);
+        }
+        let simulation = &self.simulation;
+        rows.push("simulation", "", "ticks", simulation.tick_count);
+        rows.push("simulation", "", "overruns", simulation.overruns);
+        rows.push("simulation", "", "max_jitter_ms", simulation.max_jitter_ms);
+        rows.push("simulation", "", "max_tick_ms", simulation.max_tick_ms);
+        rows.out
+    }
+}
+
+/// Строки CSV-отчёта.
+struct CsvRows {
+    out: String,
+}
+
+impl Default for CsvRows {
+    fn default() -> Self {
+        Self {
+            out: String::from("section,subject,metric,value\n"),
+        }
+    }
+}
+
+impl CsvRows {
+    fn push(&mut self, section: &str, subject: &str, metric: &str, value: impl ToString) {
+        let _ = writeln!(
+            self.out,
+            "{},{},{},{}",
+            section,
+            csv_field(subject),
+            metric,
+            csv_field(&value.to_string())
+        );
+    }
+
+    fn histogram(&mut self, section: &str, subject: &str, latency: &LatencyHistogram) {
+        for bucket in &latency.buckets {
+            let metric = match bucket.le_us {
+                Some(bound) => format!("latency_le_{}us", bound),
+                None => "latency_inf".to_string(),
+            };
+            self.push(section, subject, &metric, bucket.count);
+        }
+        for (metric, value) in [
+            ("latency_min_us", latency.min_us),
+            ("latency_max_us", latency.max_us),
+            ("latency_avg_us", latency.avg_us),
+        ] {
+            let value = value.map(|v| v.to_string()).unwrap_or_default();
+            self.push(section, subject, metric, value);
+        }
+    }
+}
+
+/// Экранировать поле CSV при необходимости.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_histogram_and_reset() {
+        let stats = RequestStats::default();
+        stats.record(0x03, "10.0.0.1:5000", false, 80);
+        stats.record(0x03, "10.0.0.2:5000", true, 2_000);
+        stats.record(0x10, "10.0.0.1:5000", false, 2_000_000);
+
+        let (since, functions, clients, latency) = stats.snapshot();
+        assert_eq!(functions[0].requests, 2);
+        assert_eq!(functions[0].exceptions, 1);
+        assert_eq!(functions[0].latency.buckets[0].count, 1);
+        assert_eq!(functions[0].latency.buckets[3].count, 1);
+        assert_eq!(clients[0].requests, 2);
+        assert_eq!(latency.count, 3);
+        assert_eq!(latency.buckets.last().unwrap().count, 1);
+        assert_eq!(latency.max_us, Some(2_000_000));
+
+        let report = StatisticsReport {
+            generated_at: since.clone(),
+            since,
+            metadata: None,
+            server: ServerStatus::default(),
+            functions,
+            clients,
+            latency,
+            exceptions: Vec::new(),
+            simulation: TickStatsSnapshot::default(),
+        };
+        let csv = report.encode(StatisticsFormat::Csv).unwrap();
+        assert!(csv.contains("function,0x03 Read Holding Registers,exceptions,1\n"));
+        assert!(csv.contains("client,10.0.0.1:5000,requests,2\n"));
+
+        stats.reset();
+        assert_eq!(stats.snapshot().3.count, 0);
+    }
+}
@@ -0,0 +1,185 @@
+//! Подменённые ответы для отдельных функций и адресов.
+//!
+//! Некоторые устройства отвечают не по спецификации: с лишними байтами,
+//! неверным счётчиком или чужим кодом функции. Такое поведение воспроизводится
+//! байт в байт готовым PDU ответа, минуя хранилище данных.
+//!
+//! PDU задаётся шестнадцатеричными байтами через пробел, например
+//! `"03 04 00 2A FF FF"`. Вместо байтов допускаются поля-шаблоны:
+//! - `{fc}` — код функции запроса (1 байт);
+//! - `{addr}` — начальный адрес запроса (2 байта, big-endian);
+//! - `{qty}` — количество из запроса (2 байта, big-endian);
+//! - `{len}` — число байт PDU после этого поля (1 байт).
+
+use serde::{Deserialize, Serialize};
+
+use crate::modbus_protocol::{ModbusRequest, ModbusResponse};
+
+/// Подменённый ответ в профиле подключения.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseOverride {
+    /// Код функции запроса.
+    pub function_code: u8,
+    /// Начальный адрес запроса; None — любой адрес.
+    #[serde(default)]
+    pub address: Option<u16>,
+    /// Шаблон PDU ответа (код функции и данные).
+    pub pdu: String,
+}
+
+/// Элемент разобранного шаблона.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Byte(u8),
+    FunctionCode,
+    Address,
+    Quantity,
+    Length,
+}
+
+impl Token {
+    /// Размер поля в байтах.
+    fn width(self) -> usize {
+        match self {
+            Token::Address | Token::Quantity => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Подменённый ответ с разобранным шаблоном.
+#[derive(Debug, Clone)]
+struct CompiledOverride {
+    function_code: u8,
+    address: Option<u16>,
+    tokens: Vec<Token>,
+}
+
+/// Набор подменённых ответов, готовый к использованию сервером.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseOverrides {
+    entries: Vec<CompiledOverride>,
+}
+
+impl ResponseOverrides {
+    /// Разобрать шаблоны профиля. Ошибка указывает номер неверной записи.
+    pub fn compile(overrides: &[ResponseOverride]) -> Result<Self, String> {
+        let entries = overrides
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let tokens = parse_template(&item.pdu)
+                    .map_err(|e| format!("Подменённый ответ #{}: {}", i + 1, e))?;
+                Ok(CompiledOverride {
+                    function_code: item.function_code,
+                    address: item.address,
+                    tokens,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Кадр ответа для запроса, если для него задана подмена.
+    /// Используется первая подходящая запись.
+    pub fn respond(&self, request: &ModbusRequest) -> Option<Vec<u8>> {
+        let start = request.address_range().map(|(start, _)| start);
+        let entry = self.entries.iter().find(|entry| {
+            entry.function_code == request.function_code
+                && entry.address.is_none_or(|address| Some(address) == start)
+        })?;
+        let pdu = render(&entry.tokens, request);
+        Some(ModbusResponse::build_response(request, pdu[0], &pdu[1..]))
+    }
+}
+
+/// Разобрать шаблон PDU.
+fn parse_template(template: &str) -> Result<Vec<Token>, String> {
+    let tokens = template
+        .split_whitespace()
+        .map(|word| match word {
+            "{fc}" => Ok(Token::FunctionCode),
+            "{addr}" => Ok(Token::Address),
+            "{qty}" => Ok(Token::Quantity),
+            "{len}" => Ok(Token::Length),
+            _ => u8::from_str_radix(word, 16)
+                .map(Token::Byte)
+                .map_err(|_| format!("недопустимый элемент '{}'", word)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if tokens.is_empty() {
+        return Err("PDU не может быть пустым".to_string());
+    }
+    if tokens.iter().map(|t| t.width()).sum::<usize>() > 253 {
+        return Err("PDU длиннее 253 байт".to_string());
+    }
+    Ok(tokens)
+}
+
+/// Подставить поля запроса в шаблон.
+fn render(tokens: &[Token], request: &ModbusRequest) -> Vec<u8> {
+    let field = |offset: usize| {
+        [
+            request.data.get(offset).copied().unwrap_or(0),
+            request.data.get(offset + 1).copied().unwrap_or(0),
+        ]
+    };
+    let mut pdu = Vec::with_capacity(tokens.len());
+    for (i, token) in tokens.iter().enumerate() {
+        match *token {
+            Token::Byte(b) => pdu.push(b),
+            Token::FunctionCode => pdu.push(request.function_code),
+            Token::Address => pdu.extend_from_slice(&field(0)),
+            Token::Quantity => pdu.extend_from_slice(&field(2)),
+            Token::Length => {
+                let rest: usize = tokens[i + 1..].iter().map(|t| t.width()).sum();
+                pdu.push(rest as u8);
+            }
+        }
+    }
+    pdu
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_request(start: u16, quantity: u16) -> ModbusRequest {
+        let mut frame = vec![0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03];
+        frame.extend_from_slice(&start.to_be_bytes());
+        frame.extend_from_slice(&quantity.to_be_bytes());
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_template_fields_are_substituted() {
+        let overrides = ResponseOverrides::compile(&[ResponseOverride {
+            function_code: 0x03,
+            address: Some(100),
+            pdu: "{fc} {len} {addr} {qty} ff".to_string(),
+        }])
+        .unwrap();
+
+        let response = overrides.respond(&read_request(100, 2)).unwrap();
+        assert_eq!(
+            response,
+            vec![
+                0x00, 0x07, 0x00, 0x00, 0x00, 0x08, 0x01, 0x03, 0x05, 0x00, 0x64, 0x00, 0x02, 0xFF
+            ]
+        );
+        assert!(overrides.respond(&read_request(101, 2)).is_none());
+    }
+
+    #[test]
+    fn test_invalid_template_is_rejected() {
+        let err = ResponseOverrides::compile(&[ResponseOverride {
+            function_code: 0x03,
+            address: None,
+            pdu: "03 zz".to_string(),
+        }])
+        .unwrap_err();
+        assert!(err.contains("#1"));
+        assert!(err.contains("'zz'"));
+    }
+}
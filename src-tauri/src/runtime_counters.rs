@@ -0,0 +1,145 @@
+//! Служебные счётчики времени работы, отображаемые в input registers.
+//!
+//! Многие профили устройств содержат время работы, время с последнего
+//! обращения мастера и счётчик запросов. Каждый счётчик — uint32 (старшее
+//! слово первым) по адресу из профиля; значения обновляются раз в секунду и
+//! при каждом запросе, так что мастер видит и собственный запрос.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::data_store::{ModbusDataStore, SharedDataStore};
+
+/// Период обновления счётчиков времени.
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Адреса счётчиков в input registers; `None` — счётчик не публикуется.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeCounterRegisters {
+    /// Секунды с момента запуска сервера.
+    #[serde(default)]
+    pub uptime_address: Option<u16>,
+    /// Секунды с последнего запроса мастера (с запуска, если запросов не было).
+    #[serde(default)]
+    pub idle_address: Option<u16>,
+    /// Число обработанных запросов с момента запуска.
+    #[serde(default)]
+    pub request_count_address: Option<u16>,
+}
+
+impl RuntimeCounterRegisters {
+    /// Публикуется ли хотя бы один счётчик.
+    pub fn is_enabled(&self) -> bool {
+        self.uptime_address.is_some()
+            || self.idle_address.is_some()
+            || self.request_count_address.is_some()
+    }
+}
+
+/// Состояние счётчиков работающего сервера.
+#[derive(Debug)]
+pub struct RuntimeCounters {
+    started: Mutex<Instant>,
+    last_request: Mutex<Option<Instant>>,
+    requests: AtomicU64,
+}
+
+impl Default for RuntimeCounters {
+    fn default() -> Self {
+        Self {
+            started: Mutex::new(Instant::now()),
+            last_request: Mutex::new(None),
+            requests: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RuntimeCounters {
+    /// Начать отсчёт заново (при запуске сервера).
+    pub fn reset(&self) {
+        *self.started.lock() = Instant::now();
+        *self.last_request.lock() = None;
+        self.requests.store(0, Ordering::SeqCst);
+    }
+
+    /// Отметить запрос мастера.
+    pub fn record_request(&self) {
+        *self.last_request.lock() = Some(Instant::now());
+        self.requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Число запросов с момента запуска.
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    /// Время с последнего запроса мастера (с запуска, если запросов не было).
+    pub fn idle(&self) -> Duration {
+        let started = *self.started.lock();
+        self.last_request.lock().unwrap_or(started).elapsed()
+    }
+
+    /// Записать текущие значения в input registers.
+    pub fn publish(&self, store: &ModbusDataStore, registers: &RuntimeCounterRegisters) {
+        let started = *self.started.lock();
+        let values = [
+            (registers.uptime_address, started.elapsed().as_secs()),
+            (registers.idle_address, self.idle().as_secs()),
+            (registers.request_count_address, self.requests()),
+        ];
+        for (address, value) in values {
+            let Some(address) = address else {
+                continue;
+            };
+            let value = value as u32;
+            store.set_input_registers(address, &[(value >> 16) as u16, value as u16]);
+        }
+    }
+}
+
+/// Обновлять счётчики времени до сигнала завершения.
+pub async fn run(
+    counters: Arc<RuntimeCounters>,
+    store: SharedDataStore,
+    registers: RuntimeCounterRegisters,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut tick = tokio::time::interval(PUBLISH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => counters.publish(&store, &registers),
+            _ = shutdown_rx.recv() => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+
+    #[test]
+    fn test_counters_are_published_as_uint32() {
+        let store = create_shared_data_store();
+        let registers = RuntimeCounterRegisters {
+            uptime_address: Some(0),
+            idle_address: None,
+            request_count_address: Some(10),
+        };
+        let counters = RuntimeCounters::default();
+        counters.record_request();
+        counters.record_request();
+        counters.publish(&store, &registers);
+
+        assert_eq!(store.read_input_registers(0, 2), Ok(vec![0, 0]));
+        assert_eq!(store.read_input_registers(10, 2), Ok(vec![0, 2]));
+        // Неотображённые адреса по-прежнему недоступны мастеру
+        assert!(store.read_input_registers(2, 1).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Параллельные дорожки сценариев.
+//!
+//! Несколько сценариев выполняются одновременно и независимо, например
+//! дорожка технологических значений и дорожка инжекции сбоев. Каждая дорожка
+//! запускается, останавливается и зацикливается отдельно. Перед запуском
+//! проверяется, не записывают ли уже работающие дорожки те же переменные:
+//! две дорожки, задающие одну переменную, перетирают значения друг друга.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use tauri::async_runtime::JoinHandle;
+
+use crate::data_store::{ClearScope, ModbusDataStore, SharedDataStore};
+use crate::fault_rules::FaultRules;
+use crate::harness::{self, Scenario, ScenarioStep};
+
+/// Состояние дорожки для UI.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackStatus {
+    pub name: String,
+    pub looped: bool,
+    pub running: bool,
+    /// Завершённые проходы сценария.
+    pub iterations: u64,
+    /// Индекс выполняемого (или последнего выполненного) шага.
+    pub step: usize,
+    /// Переменные, которые записывает дорожка.
+    pub variables: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Переменная, которую записывают две дорожки.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackConflict {
+    pub variable: String,
+    /// Уже работающая дорожка.
+    pub track: String,
+}
+
+struct Track {
+    status: Arc<Mutex<TrackStatus>>,
+    task: JoinHandle<()>,
+}
+
+/// Переменные, которые меняют шаги: `set` — свою переменную, сброс значений
+/// и очистка только значений — все, выборочная очистка — попавшие под неё.
+pub fn touched_variables(steps: &[ScenarioStep], data_store: &ModbusDataStore) -> BTreeSet<String> {
+    let variables = data_store.get_variables();
+    let mut touched = BTreeSet::new();
+    for step in steps {
+        match step {
+            ScenarioStep::Set { variable, .. } => {
+                touched.insert(variable.clone());
+            }
+            ScenarioStep::ResetValues | ScenarioStep::Clear(ClearScope::ValuesOnly) => {
+                touched.extend(variables.iter().map(|v| v.id.clone()));
+            }
+            ScenarioStep::Clear(scope) => {
+                touched.extend(
+                    variables
+                        .iter()
+                        .filter(|v| scope.matches(v))
+                        .map(|v| v.id.clone()),
+                );
+            }
+            _ => {}
+        }
+    }
+    touched
+}
+
+/// Менеджер дорожек.
+pub struct ScenarioTracks {
+    data_store: SharedDataStore,
+    faults: Arc<FaultRules>,
+    tracks: RwLock<BTreeMap<String, Track>>,
+}
+
+impl ScenarioTracks {
+    pub fn new(data_store: SharedDataStore, faults: Arc<FaultRules>) -> Self {
+        Self {
+            data_store,
+            faults,
+            tracks: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Работает ли дорожка с именем `name`.
+    pub fn is_running(&self, name: &str) -> bool {
+        self.tracks
+            .read()
+            .get(name)
+            .is_some_and(|track| track.status.lock().running)
+    }
+
+    /// Переменные сценария, которые уже записывают другие работающие дорожки.
+    pub fn conflicts(&self, name: &str, scenario: &Scenario) -> Vec<TrackConflict> {
+        let touched = touched_variables(&scenario.steps, &self.data_store);
+        let mut conflicts = Vec::new();
+        for (other, track) in self.tracks.read().iter() {
+            let status = track.status.lock();
+            if other == name || !status.running {
+                continue;
+            }
+            conflicts.extend(
+                status
+                    .variables
+                    .iter()
+                    .filter(|v| touched.contains(*v))
+                    .map(|variable| TrackConflict {
+                        variable: variable.clone(),
+                        track: other.clone(),
+                    }),
+            );
+        }
+        conflicts
+    }
+
+    /// Запустить дорожку. Остановленная дорожка с тем же именем заменяется.
+    /// С `looped` сценарий повторяется до остановки или первой ошибки.
+    pub fn start(&self, name: &str, scenario: Scenario, looped: bool) -> Result<(), String> {
+        if name.trim().is_empty() {
+            return Err("Имя дорожки не задано".to_string());
+        }
+        if scenario.steps.is_empty() {
+            return Err(format!("В сценарии дорожки '{}' нет шагов", name));
+        }
+        let mut tracks = self.tracks.write();
+        if tracks
+            .get(name)
+            .is_some_and(|track| track.status.lock().running)
+        {
+            return Err(format!("Дорожка '{}' уже запущена", name));
+        }
+
+        let status = Arc::new(Mutex::new(TrackStatus {
+            name: name.to_string(),
+            looped,
+            running: true,
+            variables: touched_variables(&scenario.steps, &self.data_store)
+                .into_iter()
+                .collect(),
+            ..TrackStatus::default()
+        }));
+        let task = tauri::async_runtime::spawn(run_track(
+            scenario.steps,
+            looped,
+            status.clone(),
+            self.data_store.clone(),
+            self.faults.clone(),
+        ));
+        log::info!(
+            "Дорожка сценария '{}' запущена{}",
+            name,
+            if looped { " в цикле" } else { "" }
+        );
+        tracks.insert(name.to_string(), Track { status, task });
+        Ok(())
+    }
+
+    /// Остановить дорожку. Включённые ею правила сбоев остаются включёнными.
+    pub fn stop(&self, name: &str) -> Result<(), String> {
+        let tracks = self.tracks.read();
+        let track = tracks
+            .get(name)
+            .ok_or_else(|| format!("Дорожка '{}' не найдена", name))?;
+        track.task.abort();
+        track.status.lock().running = false;
+        log::info!("Дорожка сценария '{}' остановлена", name);
+        Ok(())
+    }
+
+    /// Остановить и забыть все дорожки.
+    pub fn clear(&self) {
+        for (_, track) in std::mem::take(&mut *self.tracks.write()) {
+            track.task.abort();
+        }
+    }
+
+    /// Состояние всех дорожек по именам.
+    pub fn list(&self) -> Vec<TrackStatus> {
+        self.tracks
+            .read()
+            .values()
+            .map(|track| track.status.lock().clone())
+            .collect()
+    }
+}
+
+/// Выполнять шаги дорожки до конца сценария, остановки или ошибки.
+async fn run_track(
+    steps: Vec<ScenarioStep>,
+    looped: bool,
+    status: Arc<Mutex<TrackStatus>>,
+    data_store: SharedDataStore,
+    faults: Arc<FaultRules>,
+) {
+    loop {
+        for (index, step) in steps.iter().enumerate() {
+            status.lock().step = index;
+            if let Err(e) = harness::execute_step(step, &data_store, &faults).await {
+                let mut status = status.lock();
+                log::warn!("Дорожка '{}', шаг {}: {}", status.name, index, e);
+                status.error = Some(e);
+                status.running = false;
+                return;
+            }
+        }
+        status.lock().iterations += 1;
+        if !looped {
+            break;
+        }
+        // Сценарий без пауз не должен занимать поток выполнения целиком
+        tokio::task::yield_now().await;
+    }
+    status.lock().running = false;
+}
+
+impl Drop for ScenarioTracks {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Общий менеджер дорожек.
+pub type SharedScenarioTracks = Arc<ScenarioTracks>;
+
+/// Создать общий менеджер дорожек.
+pub fn create_shared_scenario_tracks(
+    data_store: SharedDataStore,
+    faults: Arc<FaultRules>,
+) -> SharedScenarioTracks {
+    Arc::new(ScenarioTracks::new(data_store, faults))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+    fn scenario(json: &str) -> Scenario {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_parallel_tracks_and_conflicts() {
+        let store = create_shared_data_store();
+        store.load_variables(&[
+            ModbusVariable::new(
+                "temp",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            ),
+            ModbusVariable::new(
+                "pressure",
+                ModbusArea::HoldingRegister,
+                1,
+                ModbusDataType::Uint16,
+            ),
+        ]);
+        let tracks = ScenarioTracks::new(store.clone(), Arc::new(FaultRules::default()));
+
+        let process = scenario(
+            r#"{"steps": [
+                {"action": "set", "variable": "temp", "value": 40},
+                {"action": "wait", "ms": 60000}
+            ]}"#,
+        );
+        let faults = scenario(
+            r#"{"steps": [
+                {"action": "armFault", "name": "loss", "rule": {"kind": "drop", "percent": 10}},
+                {"action": "wait", "ms": 60000}
+            ]}"#,
+        );
+        let reset = scenario(r#"{"steps": [{"action": "resetValues"}]}"#);
+
+        tauri::async_runtime::block_on(async {
+            tracks.start("process", process.clone(), true).unwrap();
+            assert!(tracks.conflicts("faults", &faults).is_empty());
+            tracks.start("faults", faults, false).unwrap();
+            assert!(tracks.start("process", process, true).is_err());
+
+            let conflicts = tracks.conflicts("reset", &reset);
+            assert_eq!(
+                conflicts,
+                [TrackConflict {
+                    variable: "temp".to_string(),
+                    track: "process".to_string(),
+                }]
+            );
+
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            assert_eq!(
+                store.get_variable("temp").unwrap().value,
+                ModbusValue::Number(40.0)
+            );
+            tracks.stop("process").unwrap();
+            assert!(!tracks.is_running("process"));
+            assert!(tracks.is_running("faults"));
+            assert!(tracks.conflicts("reset", &reset).is_empty());
+            assert!(tracks.stop("missing").is_err());
+        });
+        let names: Vec<String> = tracks.list().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["faults", "process"]);
+    }
+}
@@ -0,0 +1,183 @@
+//! Расписания значений по времени суток и дням недели.
+//!
+//! Правило вида «с 08:00 до 20:00 по будням — 75, с 20:00 до 08:00 — 40»
+//! переключает значение переменной без участия оператора, поэтому длительные
+//! тесты видят реалистичные колебания нагрузки. Интервал, у которого конец
+//! раньше начала, переходит через полночь и относится к дню своего начала.
+
+use serde::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// Интервал расписания.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleEntry {
+    /// Дни недели (1 — понедельник … 7 — воскресенье); пусто — каждый день.
+    #[serde(default)]
+    pub weekdays: Vec<u8>,
+    /// Начало интервала, "ЧЧ:ММ".
+    pub start: String,
+    /// Конец интервала (не включительно), "ЧЧ:ММ".
+    pub end: String,
+    /// Значение переменной внутри интервала.
+    pub value: f64,
+}
+
+/// Настройка расписания.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleConfig {
+    /// ID переменной, значение которой переключается.
+    pub target_id: String,
+    /// Смещение местного времени от UTC, минуты.
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Интервалы; при пересечении действует первый подходящий.
+    pub entries: Vec<ScheduleEntry>,
+    /// Значение вне всех интервалов; None — оставить последнее.
+    #[serde(default)]
+    pub default_value: Option<f64>,
+}
+
+/// Разобрать время "ЧЧ:ММ" в минуты от полуночи.
+fn parse_time(text: &str) -> Option<i64> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    ((0..24).contains(&hours) && (0..60).contains(&minutes)).then_some(hours * 60 + minutes)
+}
+
+impl ScheduleConfig {
+    /// Проверить переменную, время интервалов и дни недели.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.target_id.is_empty() {
+            return Err("Не задана переменная расписания".to_string());
+        }
+        if self.entries.is_empty() {
+            return Err("Расписание не содержит интервалов".to_string());
+        }
+        for (i, entry) in self.entries.iter().enumerate() {
+            for time in [&entry.start, &entry.end] {
+                if parse_time(time).is_none() {
+                    return Err(format!("Интервал #{}: неверное время '{}'", i + 1, time));
+                }
+            }
+            if let Some(day) = entry.weekdays.iter().find(|d| !(1..=7).contains(*d)) {
+                return Err(format!("Интервал #{}: неверный день недели {}", i + 1, day));
+            }
+        }
+        Ok(())
+    }
+
+    /// Значение по расписанию в момент `unix_secs` (секунды UTC).
+    pub fn value_at(&self, unix_secs: i64) -> Option<f64> {
+        let local_minutes = unix_secs.div_euclid(60) + self.utc_offset_minutes as i64;
+        let day = local_minutes.div_euclid(MINUTES_PER_DAY);
+        let minute = local_minutes.rem_euclid(MINUTES_PER_DAY);
+        // 1 января 1970 года — четверг
+        let weekday = |day: i64| ((day + 3).rem_euclid(7) + 1) as u8;
+        let on_day = |entry: &ScheduleEntry, day: i64| {
+            entry.weekdays.is_empty() || entry.weekdays.contains(&weekday(day))
+        };
+
+        self.entries
+            .iter()
+            .find(|entry| {
+                let (Some(start), Some(end)) = (parse_time(&entry.start), parse_time(&entry.end))
+                else {
+                    return false;
+                };
+                if start < end {
+                    on_day(entry, day) && (start..end).contains(&minute)
+                } else {
+                    // Через полночь: хвост вчерашнего интервала или начало сегодняшнего
+                    (minute >= start && on_day(entry, day))
+                        || (minute < end && on_day(entry, day - 1))
+                }
+            })
+            .map(|entry| entry.value)
+            .or(self.default_value)
+    }
+}
+
+/// Состояние расписания между тактами.
+#[derive(Debug, Default)]
+pub struct ScheduleState {
+    last: Option<f64>,
+}
+
+impl ScheduleState {
+    /// Вычислить значение в момент `unix_secs`. Возвращает значение,
+    /// если его нужно записать (только при смене интервала), чтобы не
+    /// перетирать ручные изменения внутри интервала.
+    pub fn step(&mut self, config: &ScheduleConfig, unix_secs: i64) -> Option<f64> {
+        let value = config.value_at(unix_secs)?;
+        if self.last == Some(value) {
+            return None;
+        }
+        self.last = Some(value);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Понедельник, 2 января 2023 года, 00:00 UTC.
+    const MONDAY: i64 = 1_672_617_600;
+
+    fn at(day: i64, hours: i64, minutes: i64) -> i64 {
+        MONDAY + day * 86_400 + hours * 3600 + minutes * 60
+    }
+
+    fn entry(weekdays: &[u8], start: &str, end: &str, value: f64) -> ScheduleEntry {
+        ScheduleEntry {
+            weekdays: weekdays.to_vec(),
+            start: start.to_string(),
+            end: end.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_day_night_on_weekdays() {
+        let config = ScheduleConfig {
+            target_id: "load".to_string(),
+            utc_offset_minutes: 0,
+            entries: vec![
+                entry(&[1, 2, 3, 4, 5], "08:00", "20:00", 75.0),
+                entry(&[1, 2, 3, 4, 5], "20:00", "08:00", 40.0),
+            ],
+            default_value: Some(10.0),
+        };
+        assert!(config.validate().is_ok());
+
+        assert_eq!(config.value_at(at(0, 9, 0)), Some(75.0));
+        assert_eq!(config.value_at(at(0, 21, 0)), Some(40.0));
+        // Ночь с пятницы на субботу относится к пятнице
+        assert_eq!(config.value_at(at(5, 3, 0)), Some(40.0));
+        assert_eq!(config.value_at(at(5, 9, 0)), Some(10.0));
+        // Ночь с воскресенья на понедельник не входит в будни
+        assert_eq!(config.value_at(at(7, 3, 0)), Some(10.0));
+    }
+
+    #[test]
+    fn test_offset_and_change_detection() {
+        let config = ScheduleConfig {
+            target_id: "load".to_string(),
+            utc_offset_minutes: 180,
+            entries: vec![entry(&[], "08:00", "09:00", 1.0)],
+            default_value: None,
+        };
+        let mut state = ScheduleState::default();
+        assert_eq!(state.step(&config, at(0, 4, 59)), None);
+        assert_eq!(state.step(&config, at(0, 5, 0)), Some(1.0));
+        assert_eq!(state.step(&config, at(0, 5, 30)), None);
+
+        let mut bad = config.clone();
+        bad.entries[0].end = "24:00".to_string();
+        assert!(bad.validate().is_err());
+    }
+}
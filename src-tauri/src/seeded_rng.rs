@@ -0,0 +1,127 @@
+//! Детерминированный генератор случайных чисел проекта.
+//!
+//! Все случайные элементы симулятора берут числа из одного генератора с
+//! известным зерном. Зерно сохраняется в проекте, а последовательность
+//! начинается заново при каждом запуске сервера, поэтому проблемный прогон
+//! можно воспроизвести точно.
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+
+/// Зёрна ограничены 53 битами, чтобы без потерь проходить через JSON во фронтенд.
+const SEED_MASK: u64 = (1 << 53) - 1;
+
+/// Состояние SplitMix64.
+#[derive(Debug)]
+struct RngState {
+    seed: u64,
+    state: u64,
+}
+
+impl RngState {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// Генератор случайных чисел с зерном проекта.
+#[derive(Debug)]
+pub struct ProjectRng {
+    inner: Mutex<RngState>,
+}
+
+impl ProjectRng {
+    /// Создать генератор с заданным зерном.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            inner: Mutex::new(RngState { seed, state: seed }),
+        }
+    }
+
+    /// Текущее зерно.
+    pub fn seed(&self) -> u64 {
+        self.inner.lock().seed
+    }
+
+    /// Задать зерно и начать последовательность с начала.
+    pub fn set_seed(&self, seed: u64) {
+        *self.inner.lock() = RngState { seed, state: seed };
+    }
+
+    /// Начать последовательность с начала с тем же зерном.
+    pub fn restart(&self) {
+        let mut inner = self.inner.lock();
+        inner.state = inner.seed;
+    }
+
+    /// Следующее случайное 64-битное число.
+    pub fn next_u64(&self) -> u64 {
+        self.inner.lock().next_u64()
+    }
+
+    /// Случайное число в диапазоне 0..=max.
+    pub fn up_to(&self, max: u64) -> u64 {
+        match max.checked_add(1) {
+            Some(bound) => self.next_u64() % bound,
+            None => self.next_u64(),
+        }
+    }
+}
+
+/// Новое зерно из текущего времени (для проектов без сохранённого зерна).
+pub fn fresh_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    RngState {
+        seed: 0,
+        state: nanos,
+    }
+    .next_u64()
+        & SEED_MASK
+}
+
+/// Общая ссылка на генератор проекта.
+pub type SharedRng = Arc<ProjectRng>;
+
+/// Создать общий генератор со случайным зерном.
+pub fn create_shared_rng() -> SharedRng {
+    Arc::new(ProjectRng::with_seed(fresh_seed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_gives_same_sequence() {
+        let a = ProjectRng::with_seed(42);
+        let first: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+
+        a.restart();
+        let again: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(first, again);
+
+        let b = ProjectRng::with_seed(7);
+        b.set_seed(42);
+        assert_eq!(b.next_u64(), first[0]);
+        assert_ne!(ProjectRng::with_seed(43).next_u64(), first[0]);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let rng = ProjectRng::with_seed(1);
+        for _ in 0..1000 {
+            assert!(rng.up_to(5) <= 5);
+        }
+        assert_eq!(rng.up_to(0), 0);
+        assert!(fresh_seed() <= SEED_MASK);
+    }
+}
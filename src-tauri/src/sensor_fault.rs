@@ -0,0 +1,234 @@
+//! Имитация отказов датчиков.
+//!
+//! Переменной назначается режим отказа: значение замерзает, скачет в 0 или
+//! в максимум, становится NaN (float32) или дрейфует с заданной скоростью.
+//! Пока отказ активен, движок симуляции на каждом такте перезаписывает
+//! значение, поэтому ни UI, ни поведения его не восстановят. После снятия
+//! отказа переменная возвращается к значению, которое было до отказа.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::SharedDataStore;
+use crate::types::{ModbusDataType, ModbusValue};
+
+/// Режим отказа датчика.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SensorFault {
+    /// Значение замерзает на последнем.
+    Freeze,
+    /// Скачок в 0.
+    Zero,
+    /// Скачок в максимум: 0xFFFF / 0xFFFFFFFF для целых, f32::MAX для float32.
+    Max,
+    /// NaN (только float32).
+    Nan,
+    /// Дрейф от последнего значения с заданной скоростью, единиц в секунду.
+    #[serde(rename_all = "camelCase")]
+    Drift { rate_per_sec: f64 },
+}
+
+impl SensorFault {
+    /// Проверить, что режим применим к типу переменной.
+    fn check_type(self, data_type: ModbusDataType) -> Result<(), String> {
+        match (self, data_type) {
+            (SensorFault::Nan, ModbusDataType::Float32) => Ok(()),
+            (SensorFault::Nan, _) => Err("NaN возможен только для float32".to_string()),
+            (SensorFault::Drift { .. }, ModbusDataType::Bool) => {
+                Err("Дрейф невозможен для битовой переменной".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Значение переменной при отказе через `elapsed_secs` после его начала.
+    fn value(
+        self,
+        data_type: ModbusDataType,
+        before: &ModbusValue,
+        elapsed_secs: f64,
+    ) -> ModbusValue {
+        let bool_type = data_type == ModbusDataType::Bool;
+        match self {
+            SensorFault::Freeze => before.clone(),
+            SensorFault::Zero if bool_type => ModbusValue::Bool(false),
+            SensorFault::Zero => ModbusValue::Number(0.0),
+            SensorFault::Max => match data_type {
+                ModbusDataType::Bool => ModbusValue::Bool(true),
+                ModbusDataType::Uint16 => ModbusValue::Number(u16::MAX as f64),
+                ModbusDataType::Int16 => ModbusValue::Number(-1.0),
+                ModbusDataType::Uint32 => ModbusValue::Number(u32::MAX as f64),
+                ModbusDataType::Float32 => ModbusValue::Number(f32::MAX as f64),
+            },
+            SensorFault::Nan => ModbusValue::Number(f64::NAN),
+            SensorFault::Drift { rate_per_sec } => {
+                ModbusValue::Number(before.as_f64() + rate_per_sec * elapsed_secs)
+            }
+        }
+    }
+}
+
+/// Активный отказ для UI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorFaultStatus {
+    pub variable_id: String,
+    pub fault: SensorFault,
+    /// Значение, которое вернётся после снятия отказа.
+    pub before: ModbusValue,
+}
+
+/// Состояние активного отказа.
+#[derive(Debug)]
+struct ActiveFault {
+    fault: SensorFault,
+    data_type: ModbusDataType,
+    /// Значение переменной до отказа.
+    before: ModbusValue,
+    started: Instant,
+}
+
+/// Активные отказы датчиков.
+#[derive(Default)]
+pub struct SensorFaultManager {
+    active: Mutex<HashMap<String, ActiveFault>>,
+}
+
+impl SensorFaultManager {
+    /// Назначить переменной отказ (заменяет предыдущий; значение «до отказа»
+    /// сохраняется от первого).
+    pub fn set(
+        &self,
+        data_store: &SharedDataStore,
+        variable_id: &str,
+        fault: SensorFault,
+    ) -> Result<(), String> {
+        let var = data_store
+            .get_variable(variable_id)
+            .ok_or_else(|| format!("Переменная '{}' не найдена", variable_id))?;
+        fault.check_type(var.data_type)?;
+
+        let now = Instant::now();
+        let mut active = self.active.lock();
+        let entry = active
+            .entry(variable_id.to_string())
+            .or_insert_with(|| ActiveFault {
+                fault,
+                data_type: var.data_type,
+                before: var.value.clone(),
+                started: now,
+            });
+        entry.fault = fault;
+        entry.started = now;
+        data_store.update_variable(variable_id, fault.value(var.data_type, &entry.before, 0.0));
+        log::info!("Отказ датчика '{}': {:?}", variable_id, fault);
+        Ok(())
+    }
+
+    /// Снять отказ и вернуть значение до отказа. Возвращает false, если отказа не было.
+    pub fn clear(&self, data_store: &SharedDataStore, variable_id: &str) -> bool {
+        let Some(fault) = self.active.lock().remove(variable_id) else {
+            return false;
+        };
+        data_store.update_variable(variable_id, fault.before);
+        log::info!("Отказ датчика '{}' снят", variable_id);
+        true
+    }
+
+    /// Снять все отказы. Возвращает количество снятых.
+    pub fn clear_all(&self, data_store: &SharedDataStore) -> usize {
+        let faults: Vec<(String, ActiveFault)> = self.active.lock().drain().collect();
+        for (id, fault) in &faults {
+            data_store.update_variable(id, fault.before.clone());
+        }
+        faults.len()
+    }
+
+    /// Список активных отказов.
+    pub fn statuses(&self) -> Vec<SensorFaultStatus> {
+        let mut statuses: Vec<SensorFaultStatus> = self
+            .active
+            .lock()
+            .iter()
+            .map(|(id, fault)| SensorFaultStatus {
+                variable_id: id.clone(),
+                fault: fault.fault,
+                before: fault.before.clone(),
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.variable_id.cmp(&b.variable_id));
+        statuses
+    }
+
+    /// Перезаписать значения переменных с активными отказами.
+    pub fn apply(&self, data_store: &SharedDataStore, now: Instant) {
+        for (id, fault) in self.active.lock().iter() {
+            let elapsed = now.duration_since(fault.started).as_secs_f64();
+            let value = fault.fault.value(fault.data_type, &fault.before, elapsed);
+            data_store.update_variable(id, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusArea, ModbusVariable};
+    use std::time::Duration;
+
+    fn variable(id: &str, address: u16, data_type: ModbusDataType, value: f64) -> ModbusVariable {
+        ModbusVariable {
+            value: ModbusValue::Number(value),
+            ..ModbusVariable::new(id, ModbusArea::InputRegister, address, data_type)
+        }
+    }
+
+    #[test]
+    fn test_freeze_survives_updates_and_clear_restores() {
+        let store = create_shared_data_store();
+        store.load_variables(&[variable("level", 0, ModbusDataType::Uint16, 42.0)]);
+        let faults = SensorFaultManager::default();
+
+        faults.set(&store, "level", SensorFault::Freeze).unwrap();
+        store.update_variable("level", ModbusValue::Number(50.0));
+        faults.apply(&store, Instant::now());
+        assert_eq!(store.read_input_registers(0, 1).unwrap(), vec![42]);
+
+        faults.set(&store, "level", SensorFault::Max).unwrap();
+        assert_eq!(store.read_input_registers(0, 1).unwrap(), vec![0xFFFF]);
+
+        assert!(faults.clear(&store, "level"));
+        assert_eq!(store.read_input_registers(0, 1).unwrap(), vec![42]);
+        assert!(faults.statuses().is_empty());
+    }
+
+    #[test]
+    fn test_drift_and_nan() {
+        let store = create_shared_data_store();
+        store.load_variables(&[
+            variable("temp", 0, ModbusDataType::Float32, 20.0),
+            variable("count", 2, ModbusDataType::Uint16, 0.0),
+        ]);
+        let faults = SensorFaultManager::default();
+
+        faults
+            .set(&store, "temp", SensorFault::Drift { rate_per_sec: 0.5 })
+            .unwrap();
+        faults.apply(&store, Instant::now() + Duration::from_secs(10));
+        let values = store.get_variable_values(&["temp".to_string()]);
+        assert!((values[0].1.as_f64() - 25.0).abs() < 0.1);
+
+        faults.set(&store, "temp", SensorFault::Nan).unwrap();
+        let regs = store.read_input_registers(0, 2).unwrap();
+        let bits = ((regs[0] as u32) << 16) | regs[1] as u32;
+        assert!(f32::from_bits(bits).is_nan());
+
+        assert!(faults.set(&store, "count", SensorFault::Nan).is_err());
+        assert_eq!(faults.clear_all(&store), 1);
+    }
+}
@@ -0,0 +1,367 @@
+//! Имитация шлюза Modbus TCP → RTU с несколькими последовательными ведомыми.
+//!
+//! Unit ID запроса выбирает ведомого «на линии»: у каждого свои переменные
+//! (отдельное хранилище), задержка ответа и доля запросов, на которые он не
+//! отвечает — тогда мастер получает исключение 0x0B (Gateway Target Device
+//! Failed to Respond), как от настоящего шлюза после таймаута линии. Unit ID
+//! сервера по-прежнему обслуживается переменными проекта, а для unit ID без
+//! ведомого шлюз отвечает 0x0A (Gateway Path Unavailable).
+//!
+//! С общей линией (multi-drop) запросы ко всем ведомым проходят по одной
+//! шине по очереди: каждый занимает её на время передачи запроса, ответа
+//! ведомого и передачи ответа при заданной скорости. Тогда суммарная задержка,
+//! которую видит TCP-мастер при параллельных запросах, такая же, как у
+//! настоящего шлюза с несколькими устройствами на одной линии RS-485.
+//!
+//! Хранилища ведомых ([`UnitStores`]) принадлежат серверу и создаются при
+//! применении профиля. Шлюз находит хранилище ведомого на каждом запросе,
+//! поэтому значения и наборы переменных, изменённые из UI по unit ID, мастер
+//! видит сразу, без перезапуска сервера.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::data_store::{create_shared_data_store, SharedDataStore};
+use crate::gateway::{GATEWAY_PATH_UNAVAILABLE, GATEWAY_TARGET_FAILED};
+use crate::integrity;
+use crate::modbus_protocol::engine::process_request;
+use crate::modbus_protocol::{ModbusRequest, ModbusResponse};
+use crate::seeded_rng::ProjectRng;
+use crate::types::ModbusVariable;
+
+/// Ведомый на последовательной линии за шлюзом.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerialSlaveConfig {
+    pub unit_id: u8,
+    #[serde(default)]
+    pub name: String,
+    /// Время ответа по линии, мс.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Случайная добавка к времени ответа, 0..=jitter_ms мс.
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Процент запросов без ответа ведомого (0..=100).
+    #[serde(default)]
+    pub failure_percent: u8,
+    /// Переменные ведомого.
+    #[serde(default)]
+    pub variables: Vec<ModbusVariable>,
+}
+
+/// Бит на символ RTU: старт, 8 бит данных, чётность или второй стоп, стоп.
+const BITS_PER_CHAR: f64 = 11.0;
+
+/// Минимальная пауза между кадрами RTU, символов.
+const INTER_FRAME_CHARS: f64 = 3.5;
+
+/// Настройки имитации шлюза в профиле подключения.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerialGatewayConfig {
+    #[serde(default)]
+    pub slaves: Vec<SerialSlaveConfig>,
+    /// Все ведомые на одной линии: запросы обслуживаются строго по очереди.
+    #[serde(default)]
+    pub shared_bus: bool,
+    /// Скорость линии, бод; задаёт время передачи кадров (0 — мгновенно).
+    #[serde(default)]
+    pub baud_rate: u32,
+}
+
+impl SerialGatewayConfig {
+    /// Проверить unit ID и параметры ведомых; `server_unit_id` занят проектом.
+    pub fn validate(&self, server_unit_id: u8) -> Result<(), String> {
+        let mut seen = Vec::with_capacity(self.slaves.len());
+        for slave in &self.slaves {
+            if slave.unit_id == 0 || slave.unit_id == server_unit_id {
+                return Err(format!(
+                    "Unit ID {} недоступен для ведомого шлюза",
+                    slave.unit_id
+                ));
+            }
+            if seen.contains(&slave.unit_id) {
+                return Err(format!("Unit ID {} задан дважды", slave.unit_id));
+            }
+            if slave.failure_percent > 100 {
+                return Err(format!(
+                    "Доля отказов ведомого {} больше 100%",
+                    slave.unit_id
+                ));
+            }
+            seen.push(slave.unit_id);
+        }
+        Ok(())
+    }
+}
+
+/// Хранилища ведомых по unit ID.
+#[derive(Default)]
+pub struct UnitStores {
+    stores: RwLock<BTreeMap<u8, SharedDataStore>>,
+}
+
+impl UnitStores {
+    /// Создать хранилища заново по ведомым настройки шлюза.
+    pub fn load(&self, config: Option<&SerialGatewayConfig>) {
+        let slaves = config
+            .map(|config| config.slaves.as_slice())
+            .unwrap_or_default();
+        *self.stores.write() = slaves
+            .iter()
+            .map(|slave| {
+                let data_store = create_shared_data_store();
+                data_store.load_variables(&slave.variables);
+                (slave.unit_id, data_store)
+            })
+            .collect();
+    }
+
+    /// Хранилище ведомого.
+    pub fn get(&self, unit_id: u8) -> Option<SharedDataStore> {
+        self.stores.read().get(&unit_id).cloned()
+    }
+
+    /// Создать хранилище ведомого, если его ещё нет.
+    fn ensure(&self, slave: &SerialSlaveConfig) {
+        self.stores.write().entry(slave.unit_id).or_insert_with(|| {
+            let data_store = create_shared_data_store();
+            data_store.load_variables(&slave.variables);
+            data_store
+        });
+    }
+
+    /// Заменить набор переменных ведомого. Значения переменных с тем же ID,
+    /// областью, адресом и типом сохраняются. Возвращает загруженный набор.
+    pub fn set_variables(
+        &self,
+        unit_id: u8,
+        variables: &[ModbusVariable],
+    ) -> Result<Vec<ModbusVariable>, String> {
+        let report = integrity::check_variables(variables);
+        if report.has_fatal() {
+            return Err(report.fatal_summary());
+        }
+        let data_store = self
+            .get(unit_id)
+            .ok_or_else(|| format!("Ведомый с unit ID {} не найден", unit_id))?;
+        Ok(data_store.merge_variables(variables))
+    }
+
+    /// Unit ID ведомых по возрастанию.
+    pub fn unit_ids(&self) -> Vec<u8> {
+        self.stores.read().keys().copied().collect()
+    }
+}
+
+/// Шлюз с ведомыми, общий для всех клиентов сервера.
+pub struct SerialGateway {
+    server_unit_id: u8,
+    slaves: HashMap<u8, SerialSlaveConfig>,
+    /// Хранилища ведомых, общие с сервером.
+    stores: Arc<UnitStores>,
+    /// Общая линия; `None` — ведомые отвечают независимо.
+    bus: Option<Mutex<()>>,
+    baud_rate: u32,
+}
+
+impl SerialGateway {
+    /// Шлюз над хранилищами `stores`; ведомому без хранилища создаётся новое.
+    pub fn new(config: SerialGatewayConfig, server_unit_id: u8, stores: Arc<UnitStores>) -> Self {
+        let bus = config.shared_bus.then(|| Mutex::new(()));
+        let baud_rate = config.baud_rate;
+        let slaves = config
+            .slaves
+            .into_iter()
+            .map(|config| {
+                stores.ensure(&config);
+                (config.unit_id, config)
+            })
+            .collect();
+        Self {
+            server_unit_id,
+            slaves,
+            stores,
+            bus,
+            baud_rate,
+        }
+    }
+
+    /// Время передачи кадра RTU из `bytes` байт с паузой после него.
+    fn frame_time(&self, bytes: usize) -> Duration {
+        if self.baud_rate == 0 {
+            return Duration::ZERO;
+        }
+        let chars = bytes as f64 + INTER_FRAME_CHARS;
+        Duration::from_secs_f64(chars * BITS_PER_CHAR / self.baud_rate as f64)
+    }
+
+    /// Обслуживается ли unit ID шлюзом (а не переменными проекта).
+    fn routes(&self, unit_id: u8) -> bool {
+        unit_id != self.server_unit_id && unit_id != 0
+    }
+
+    /// Ответ ведомого или исключение шлюза; `None` для unit ID сервера.
+    pub async fn respond(&self, request: &ModbusRequest, rng: &ProjectRng) -> Option<Vec<u8>> {
+        let unit_id = request.header.unit_id;
+        if !self.routes(unit_id) {
+            return None;
+        }
+        // Хранилища ведомого нет, если шлюз перенастроили без перезапуска
+        let (Some(slave), Some(data_store)) = (self.slaves.get(&unit_id), self.stores.get(unit_id))
+        else {
+            return Some(exception(request, GATEWAY_PATH_UNAVAILABLE));
+        };
+
+        // Линия занята до конца передачи ответа (или таймаута ведомого)
+        let _bus = match &self.bus {
+            Some(bus) => Some(bus.lock().await),
+            None => None,
+        };
+        // Кадр RTU: адрес, PDU и CRC
+        let request_time = self.frame_time(request.data.len() + 4);
+        let delay = Duration::from_millis(slave.delay_ms + rng.up_to(slave.jitter_ms));
+        if !(request_time + delay).is_zero() {
+            tokio::time::sleep(request_time + delay).await;
+        }
+        if slave.failure_percent > 0 && rng.up_to(99) < slave.failure_percent as u64 {
+            return Some(exception(request, GATEWAY_TARGET_FAILED));
+        }
+        let response = process_request(request, data_store.as_ref());
+        // Ответ без MBAP, но с адресом и CRC
+        let response_time = self.frame_time(response.len().saturating_sub(4));
+        if !response_time.is_zero() {
+            tokio::time::sleep(response_time).await;
+        }
+        Some(response)
+    }
+}
+
+fn exception(request: &ModbusRequest, code: u8) -> Vec<u8> {
+    ModbusResponse::build_response(request, request.function_code | 0x80, &[code])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus_protocol::MbapHeader;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue};
+
+    fn read_request(unit_id: u8) -> ModbusRequest {
+        let mut frame = Vec::new();
+        MbapHeader {
+            transaction_id: 1,
+            protocol_id: 0,
+            length: 6,
+            unit_id,
+        }
+        .write_to(&mut frame);
+        frame.extend_from_slice(&[0x03, 0, 0, 0, 1]);
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    fn slave(unit_id: u8, value: f64, failure_percent: u8) -> SerialSlaveConfig {
+        SerialSlaveConfig {
+            unit_id,
+            name: String::new(),
+            delay_ms: 0,
+            jitter_ms: 0,
+            failure_percent,
+            variables: vec![ModbusVariable {
+                name: "level".to_string(),
+                value: ModbusValue::Number(value),
+                ..ModbusVariable::new(
+                    format!("level{}", unit_id),
+                    ModbusArea::HoldingRegister,
+                    0,
+                    ModbusDataType::Uint16,
+                )
+            }],
+        }
+    }
+
+    #[test]
+    fn test_unit_ids_route_to_slaves() {
+        tauri::async_runtime::block_on(async {
+            let config = SerialGatewayConfig {
+                slaves: vec![slave(2, 20.0, 0), slave(3, 30.0, 100)],
+                ..Default::default()
+            };
+            assert!(config.validate(1).is_ok());
+            assert!(config.validate(2).is_err());
+
+            // Значения хранилищ видны шлюзу, созданному позже
+            let stores = Arc::new(UnitStores::default());
+            stores.load(Some(&config));
+            assert_eq!(stores.unit_ids(), [2, 3]);
+            stores
+                .get(2)
+                .unwrap()
+                .update_variable("level2", ModbusValue::Number(21.0));
+
+            let gateway = SerialGateway::new(config.clone(), 1, stores.clone());
+            let rng = ProjectRng::with_seed(1);
+
+            assert_eq!(gateway.respond(&read_request(1), &rng).await, None);
+            let response = gateway.respond(&read_request(2), &rng).await.unwrap();
+            assert_eq!(&response[6..], &[2, 0x03, 2, 0, 21]);
+            let response = gateway.respond(&read_request(3), &rng).await.unwrap();
+            assert_eq!(&response[7..], &[0x83, GATEWAY_TARGET_FAILED]);
+            let response = gateway.respond(&read_request(9), &rng).await.unwrap();
+            assert_eq!(&response[7..], &[0x83, GATEWAY_PATH_UNAVAILABLE]);
+
+            // Новый набор переменных ведомого виден работающему шлюзу
+            let mut moved = slave(2, 5.0, 0).variables;
+            moved[0].id = "flow2".to_string();
+            stores.set_variables(2, &moved).unwrap();
+            let response = gateway.respond(&read_request(2), &rng).await.unwrap();
+            assert_eq!(&response[6..], &[2, 0x03, 2, 0, 5]);
+            assert!(stores.set_variables(9, &moved).is_err());
+
+            // После перезагрузки без ведомого 2 шлюз отвечает, что пути нет
+            stores.load(Some(&SerialGatewayConfig {
+                slaves: vec![slave(3, 30.0, 0)],
+                ..config
+            }));
+            let response = gateway.respond(&read_request(2), &rng).await.unwrap();
+            assert_eq!(&response[7..], &[0x83, GATEWAY_PATH_UNAVAILABLE]);
+        });
+    }
+
+    #[test]
+    fn test_shared_bus_serializes_requests() {
+        tauri::async_runtime::block_on(async {
+            let slow = |unit_id| SerialSlaveConfig {
+                delay_ms: 50,
+                ..slave(unit_id, 0.0, 0)
+            };
+            let gateway = SerialGateway::new(
+                SerialGatewayConfig {
+                    slaves: vec![slow(2), slow(3)],
+                    shared_bus: true,
+                    baud_rate: 9600,
+                },
+                1,
+                Arc::default(),
+            );
+            // 8 + 3.5 символа запроса и 7 + 3.5 символа ответа по 11 бит на 9600 бод
+            assert_eq!(gateway.frame_time(8).as_micros(), 13_177);
+
+            let rng = ProjectRng::with_seed(1);
+            let (first, second) = (read_request(2), read_request(3));
+            let started = std::time::Instant::now();
+            let (a, b) = tokio::join!(
+                gateway.respond(&first, &rng),
+                gateway.respond(&second, &rng)
+            );
+            assert!(a.is_some() && b.is_some());
+            assert!(started.elapsed() >= Duration::from_millis(2 * (50 + 13 + 12)));
+        });
+    }
+}
@@ -9,24 +9,62 @@
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use parking_lot::RwLock;
+use serde::Serialize;
 use tauri::{AppHandle, Emitter};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpSocket, TcpStream, UdpSocket};
 use tokio::sync::broadcast;
 
+use crate::access_lock::{AccessDecision, AccessLock, AccessLockConfig};
+use crate::access_map::{create_shared_access_map, SharedAccessMap};
+use crate::byte_count_stress::ByteCountStress;
+use crate::client_units::{ClientUnit, ClientUnits};
+use crate::coil_interlock::{CoilInterlockGroup, CoilInterlocks};
+use crate::comms_failure::CommsFailure;
 use crate::data_store::SharedDataStore;
+use crate::error::{AppError, ErrorCode};
+use crate::event_buffer::{EventBuffer, EventBufferConfig};
+use crate::exception_injection::ExceptionInjector;
+use crate::exception_stats::{self, create_shared_exception_stats, SharedExceptionStats};
+use crate::fault_rules::FaultRules;
+use crate::firmware::{self, FirmwareLevel};
+use crate::fragmentation::Fragmentation;
+use crate::frame_clock;
+use crate::gateway::{Gateway, GatewayConfig, WritePolicy};
+use crate::inactivity::{self, InactivityAlarmConfig, INACTIVITY_EVENT_NAME};
+use crate::listener_stats::{self, ListenerStats};
+use crate::mdns::{self, MdnsService, MdnsSettings};
+use crate::modbus_protocol::ascii::{self, AsciiDecoder};
+use crate::modbus_protocol::engine::{process_request, FrameDecoder, IllegalLength};
 use crate::modbus_protocol::{
-    pack_bits, pack_registers, ExceptionCode, FunctionCode, ModbusRequest, ModbusResponse,
-    ReadRequest, WriteMultipleCoilsRequest, WriteMultipleRegistersRequest, WriteSingleCoilRequest,
-    WriteSingleRegisterRequest,
+    pack_bits, pack_registers, ExceptionCode, FileRecordRef, FunctionCode,
+    MaskWriteRegisterRequest, MbapHeader, ModbusRequest, ModbusResponse, ReadFileRecordRequest,
+    ReadRequest, WriteFileRecordRequest, WriteMultipleCoilsRequest, WriteMultipleRegistersRequest,
+    WriteSingleCoilRequest, WriteSingleRegisterRequest, MAX_FRAME_SIZE,
 };
-use crate::types::{function_code_name, LogEntry, LogEntryType, ServerStatus};
-
-/// Максимальный размер фрейма Modbus TCP (256 байт ADU максимум).
-const MAX_FRAME_SIZE: usize = 260;
+use crate::processing_time::ProcessingTimes;
+use crate::protocol_policy::{
+    find_deviations, Deviation, DeviationPolicy, IllegalLengthPolicy, ProtocolStrictness,
+};
+use crate::request_stats::{create_shared_request_stats, SharedRequestStats};
+use crate::response_override::{ResponseOverride, ResponseOverrides};
+use crate::runtime_counters::{self, RuntimeCounterRegisters, RuntimeCounters};
+use crate::seeded_rng::{create_shared_rng, SharedRng};
+use crate::serial_gateway::{SerialGateway, SerialGatewayConfig, UnitStores};
+use crate::traffic_log::{create_shared_traffic_log, SharedTrafficLog};
+use crate::traffic_mirror::{MirrorConfig, TrafficMirror};
+use crate::transaction_id::{self, TransactionIdInjector};
+use crate::types::{
+    exception_code_name, function_code_name, ConnectionFraming, LogEntry, LogEntryType,
+    LogSubsystem, ModbusConnectionProfile, ModbusVariable, ServerStatus,
+};
+use crate::write_learning::WriteLearning;
+use crate::write_rate_limit::{RateCheck, WriteRateLimit, WriteRateLimiter};
+use crate::write_storm::{self, WriteStormConfig, WriteStorms};
+use crate::zero_quantity::ZeroQuantityReads;
 
 /// Размер буфера чтения.
 const READ_BUFFER_SIZE: usize = 1024;
@@ -34,16 +72,51 @@ const READ_BUFFER_SIZE: usize = 1024;
 /// Название события для отправки логов в UI.
 const LOG_EVENT_NAME: &str = "modbus-log";
 
+/// Пауза после сбоя приёма подключения (кроме сброса клиентом).
+pub(crate) const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Адрес клиента в журнале для имитированных запросов мастера.
+const SIMULATION_CLIENT: &str = "SIMULATION";
+
+/// Ответ на имитированный запрос мастера.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedResponse {
+    /// Полный кадр ответа (MBAP + PDU).
+    pub raw: Vec<u8>,
+    /// Код исключения, если ответ — исключение.
+    pub exception_code: Option<u8>,
+}
+
+impl SimulatedResponse {
+    fn new(raw: Vec<u8>) -> Self {
+        let exception_code = match raw.get(7..9) {
+            Some(&[function, code]) if function & 0x80 != 0 => Some(code),
+            _ => None,
+        };
+        Self {
+            raw,
+            exception_code,
+        }
+    }
+}
+
 /// Состояние сервера, которое может быть разделено между задачами.
 pub struct ModbusServer {
     /// Флаг, указывающий, запущен ли сервер.
     running: AtomicBool,
     /// Текущее количество подключённых клиентов.
-    connections_count: AtomicUsize,
+    connections_count: Arc<AtomicUsize>,
+    /// Счётчики приёма подключений.
+    listener_stats: Arc<ListenerStats>,
     /// Конфигурация сервера.
     config: RwLock<ServerConfig>,
+    /// Профиль, из которого получена конфигурация.
+    profile: RwLock<Option<ModbusConnectionProfile>>,
     /// Отправитель сигнала завершения.
     shutdown_tx: RwLock<Option<broadcast::Sender<()>>>,
+    /// Сигнал закрыть все клиентские соединения (слушатель продолжает работу).
+    disconnect_tx: broadcast::Sender<()>,
     /// Последнее сообщение об ошибке.
     last_error: RwLock<Option<String>>,
     /// Хранилище данных для регистров и коилов.
@@ -52,6 +125,42 @@ pub struct ModbusServer {
     log_id_counter: AtomicU64,
     /// Handle приложения Tauri для отправки событий.
     app_handle: RwLock<Option<AppHandle>>,
+    /// Статистика исключений по диапазонам адресов.
+    exception_stats: SharedExceptionStats,
+    /// Счётчики запросов по функциям и клиентам, гистограммы времени обработки.
+    request_stats: SharedRequestStats,
+    /// Адреса и функции, к которым обращался мастер.
+    access_map: SharedAccessMap,
+    /// Карантин записей мастера в неописанные адреса.
+    write_learning: Arc<WriteLearning>,
+    /// Счётчик повторно использованных Transaction ID.
+    duplicate_transactions: Arc<AtomicU64>,
+    /// Журнал обмена с поиском (SQLite).
+    traffic_log: SharedTrafficLog,
+    /// Запрошенные подмены Transaction ID в ответах.
+    transaction_ids: Arc<TransactionIdInjector>,
+    /// Служебные счётчики времени работы и запросов.
+    runtime_counters: Arc<RuntimeCounters>,
+    /// Ответы-исключения по команде.
+    exception_injector: Arc<ExceptionInjector>,
+    /// Режим отказа связи.
+    comms_failure: Arc<CommsFailure>,
+    /// Правила инжекции сбоев, включаемые сценарием.
+    faults: Arc<FaultRules>,
+    /// Ограничения скорости изменения переменных при записи мастера.
+    rate_limiter: Arc<WriteRateLimiter>,
+    /// Группы взаимоисключающих coils.
+    interlocks: Arc<CoilInterlocks>,
+    /// Защита диапазонов паролем.
+    access_lock: Arc<AccessLock>,
+    /// Хранилища ведомых имитируемого шлюза TCP → RTU.
+    unit_stores: Arc<UnitStores>,
+    /// Штормы записи одного адреса.
+    write_storms: Arc<WriteStorms>,
+    /// Буфер событий с указателями в регистрах.
+    event_buffer: EventBuffer,
+    /// Генератор случайных чисел с зерном проекта.
+    rng: SharedRng,
 }
 
 /// Конфигурация сервера.
@@ -59,7 +168,53 @@ pub struct ModbusServer {
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Дополнительные порты с тем же хранилищем данных.
+    pub port_aliases: Vec<u16>,
+    /// Размер очереди входящих подключений; None — значение tokio по умолчанию.
+    pub listen_backlog: Option<u32>,
+    /// Пауза после каждого принятого подключения (медленное устройство).
+    pub accept_delay: Duration,
     pub unit_id: u8,
+    /// Кадрирование в соединении.
+    pub framing: ConnectionFraming,
+    /// Слушать также UDP: датаграмма — один кадр MBAP.
+    pub udp: bool,
+    /// Реакция на отклонения от протокола.
+    pub strictness: ProtocolStrictness,
+    /// Базовое время обработки по кодам функций.
+    pub processing_times: ProcessingTimes,
+    /// Подменённые ответы для отдельных функций и адресов.
+    pub response_overrides: Vec<ResponseOverride>,
+    /// Проверять эхо Transaction ID в ответах.
+    pub verify_transaction_ids: bool,
+    /// Дробление ответов на сегменты.
+    pub fragmentation: Fragmentation,
+    /// Адреса служебных счётчиков в input registers.
+    pub runtime_counters: RuntimeCounterRegisters,
+    /// Режим шлюза: политики записи по диапазонам.
+    pub gateway: Option<GatewayConfig>,
+    /// Несоответствие счётчика байт в ответах чтения.
+    pub byte_count_stress: ByteCountStress,
+    /// Ответ на чтение с нулевым количеством.
+    pub zero_quantity_reads: ZeroQuantityReads,
+    /// Копия кадров на внешний анализатор.
+    pub traffic_mirror: Option<MirrorConfig>,
+    /// Имитация шлюза TCP → RTU с последовательными ведомыми.
+    pub serial_gateway: Option<SerialGatewayConfig>,
+    /// Unit ID по IP клиента.
+    pub client_units: Vec<ClientUnit>,
+    /// Описанные уровни прошивки.
+    pub firmware_levels: Vec<FirmwareLevel>,
+    /// Активный уровень прошивки.
+    pub firmware: Option<String>,
+    /// Кольцевой буфер событий в holding registers.
+    pub event_buffer: Option<EventBufferConfig>,
+    /// Тревога отсутствия запросов мастера.
+    pub inactivity_alarm: Option<InactivityAlarmConfig>,
+    /// Анонс через mDNS.
+    pub mdns: MdnsSettings,
+    /// Имя профиля (имя экземпляра mDNS по умолчанию).
+    pub device_name: String,
 }
 
 impl Default for ServerConfig {
@@ -67,8 +222,61 @@ impl Default for ServerConfig {
         Self {
             host: "0.0.0.0".to_string(),
             port: 502,
+            port_aliases: Vec::new(),
+            listen_backlog: None,
+            accept_delay: Duration::ZERO,
             unit_id: 1,
+            framing: ConnectionFraming::default(),
+            udp: false,
+            strictness: ProtocolStrictness::default(),
+            processing_times: ProcessingTimes::default(),
+            response_overrides: Vec::new(),
+            verify_transaction_ids: false,
+            fragmentation: Fragmentation::default(),
+            runtime_counters: RuntimeCounterRegisters::default(),
+            gateway: None,
+            byte_count_stress: ByteCountStress::default(),
+            zero_quantity_reads: ZeroQuantityReads::default(),
+            traffic_mirror: None,
+            serial_gateway: None,
+            client_units: Vec::new(),
+            firmware_levels: Vec::new(),
+            firmware: None,
+            event_buffer: None,
+            inactivity_alarm: None,
+            mdns: MdnsSettings::default(),
+            device_name: String::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Все порты для прослушивания: основной первым, дубликаты отбрасываются.
+    pub fn listen_ports(&self) -> Vec<u16> {
+        let mut ports = vec![self.port];
+        for &port in &self.port_aliases {
+            if !ports.contains(&port) {
+                ports.push(port);
+            }
+        }
+        ports
+    }
+
+    /// Активный уровень прошивки; ошибка, если он не описан.
+    pub fn firmware_level(&self) -> Result<Option<&FirmwareLevel>, String> {
+        firmware::select(&self.firmware_levels, self.firmware.as_deref())
+    }
+
+    /// Конфигурация с особенностями активного уровня прошивки.
+    pub fn with_firmware(mut self) -> Result<Self, String> {
+        if let Some(level) = self.firmware_level()?.cloned() {
+            level.apply_quirks(
+                &mut self.response_overrides,
+                &mut self.byte_count_stress,
+                &mut self.zero_quantity_reads,
+            );
         }
+        Ok(self)
     }
 }
 
@@ -77,13 +285,34 @@ impl ModbusServer {
     pub fn new(data_store: SharedDataStore) -> Self {
         Self {
             running: AtomicBool::new(false),
-            connections_count: AtomicUsize::new(0),
+            connections_count: Arc::new(AtomicUsize::new(0)),
+            listener_stats: Arc::new(ListenerStats::default()),
             config: RwLock::new(ServerConfig::default()),
+            profile: RwLock::new(None),
             shutdown_tx: RwLock::new(None),
+            disconnect_tx: broadcast::channel(1).0,
             last_error: RwLock::new(None),
             data_store,
             log_id_counter: AtomicU64::new(1),
             app_handle: RwLock::new(None),
+            exception_stats: create_shared_exception_stats(),
+            request_stats: create_shared_request_stats(),
+            access_map: create_shared_access_map(),
+            write_learning: Arc::new(WriteLearning::default()),
+            duplicate_transactions: Arc::new(AtomicU64::new(0)),
+            traffic_log: create_shared_traffic_log(),
+            transaction_ids: Arc::new(TransactionIdInjector::default()),
+            runtime_counters: Arc::new(RuntimeCounters::default()),
+            exception_injector: Arc::new(ExceptionInjector::default()),
+            comms_failure: Arc::new(CommsFailure::default()),
+            faults: Arc::new(FaultRules::default()),
+            rate_limiter: Arc::new(WriteRateLimiter::default()),
+            interlocks: Arc::new(CoilInterlocks::default()),
+            access_lock: Arc::new(AccessLock::default()),
+            unit_stores: Arc::new(UnitStores::default()),
+            write_storms: Arc::new(WriteStorms::default()),
+            event_buffer: EventBuffer::default(),
+            rng: create_shared_rng(),
         }
     }
 
@@ -92,6 +321,41 @@ impl ModbusServer {
         *self.app_handle.write() = Some(handle);
     }
 
+    /// Применить все настройки профиля подключения (действуют при следующем запуске).
+    pub fn apply_profile(&self, profile: ModbusConnectionProfile) {
+        *self.profile.write() = Some(profile.clone());
+        self.set_config(profile.host, profile.port, profile.unit_id);
+        self.set_framing(profile.framing);
+        self.set_udp(profile.udp);
+        self.set_strictness(profile.strictness);
+        self.set_processing_times(profile.processing_times);
+        self.set_response_overrides(profile.response_overrides);
+        self.set_verify_transaction_ids(profile.verify_transaction_ids);
+        self.set_fragmentation(profile.fragmentation);
+        self.set_runtime_counters(profile.runtime_counters);
+        self.set_gateway(profile.gateway);
+        self.set_byte_count_stress(profile.byte_count_stress);
+        self.set_zero_quantity_reads(profile.zero_quantity_reads);
+        self.set_traffic_mirror(profile.traffic_mirror);
+        self.set_serial_gateway(profile.serial_gateway);
+        self.set_client_units(profile.client_units);
+        self.set_firmware(profile.firmware_levels, profile.firmware);
+        self.set_write_rate_limits(profile.write_rate_limits);
+        self.set_event_buffer(profile.event_buffer);
+        self.set_coil_interlocks(profile.coil_interlocks);
+        self.set_access_lock(profile.access_lock);
+        self.set_inactivity_alarm(profile.inactivity_alarm);
+        self.set_write_storm(profile.write_storm);
+        self.set_port_aliases(profile.port_aliases);
+        self.set_accept_options(profile.listen_backlog, profile.accept_delay_ms);
+        self.set_mdns(profile.mdns, profile.name);
+    }
+
+    /// Последний применённый профиль подключения.
+    pub fn profile(&self) -> Option<ModbusConnectionProfile> {
+        self.profile.read().clone()
+    }
+
     /// Обновить конфигурацию сервера.
     pub fn set_config(&self, host: String, port: u16, unit_id: u8) {
         let mut config = self.config.write();
@@ -100,6 +364,252 @@ impl ModbusServer {
         config.unit_id = unit_id;
     }
 
+    /// Задать кадрирование соединений (применяется при следующем запуске).
+    pub fn set_framing(&self, framing: ConnectionFraming) {
+        self.config.write().framing = framing;
+    }
+
+    /// Включить приём Modbus/UDP (применяется при следующем запуске).
+    pub fn set_udp(&self, udp: bool) {
+        self.config.write().udp = udp;
+    }
+
+    /// Задать политику строгости протокола (применяется при следующем запуске).
+    pub fn set_strictness(&self, strictness: ProtocolStrictness) {
+        self.config.write().strictness = strictness;
+    }
+
+    /// Задать дополнительные порты (применяются при следующем запуске).
+    pub fn set_port_aliases(&self, ports: Vec<u16>) {
+        self.config.write().port_aliases = ports;
+    }
+
+    /// Задать время обработки запросов по кодам функций
+    /// (применяется при следующем запуске).
+    pub fn set_processing_times(&self, times: ProcessingTimes) {
+        self.config.write().processing_times = times;
+    }
+
+    /// Задать подменённые ответы (применяются при следующем запуске).
+    pub fn set_response_overrides(&self, overrides: Vec<ResponseOverride>) {
+        self.config.write().response_overrides = overrides;
+    }
+
+    /// Включить проверку эха Transaction ID (применяется при следующем запуске).
+    pub fn set_verify_transaction_ids(&self, verify: bool) {
+        self.config.write().verify_transaction_ids = verify;
+    }
+
+    /// Задать дробление ответов (применяется при следующем запуске).
+    pub fn set_fragmentation(&self, fragmentation: Fragmentation) {
+        self.config.write().fragmentation = fragmentation;
+    }
+
+    /// Задать адреса служебных счётчиков (применяется при следующем запуске).
+    pub fn set_runtime_counters(&self, registers: RuntimeCounterRegisters) {
+        self.config.write().runtime_counters = registers;
+    }
+
+    /// Задать режим шлюза (применяется при следующем запуске).
+    pub fn set_gateway(&self, gateway: Option<GatewayConfig>) {
+        self.config.write().gateway = gateway;
+    }
+
+    /// Задать искажение счётчика байт в ответах чтения
+    /// (применяется при следующем запуске).
+    pub fn set_byte_count_stress(&self, stress: ByteCountStress) {
+        self.config.write().byte_count_stress = stress;
+    }
+
+    /// Задать ответ на чтение с нулевым количеством (применяется при следующем запуске).
+    pub fn set_zero_quantity_reads(&self, reads: ZeroQuantityReads) {
+        self.config.write().zero_quantity_reads = reads;
+    }
+
+    /// Задать анализатор для копии трафика (применяется при следующем запуске).
+    pub fn set_traffic_mirror(&self, mirror: Option<MirrorConfig>) {
+        self.config.write().traffic_mirror = mirror;
+    }
+
+    /// Задать ведомых имитируемого шлюза TCP → RTU (применяется при следующем запуске).
+    /// Хранилища ведомых создаются сразу с начальными значениями переменных.
+    pub fn set_serial_gateway(&self, gateway: Option<SerialGatewayConfig>) {
+        self.unit_stores.load(gateway.as_ref());
+        self.config.write().serial_gateway = gateway;
+    }
+
+    /// Хранилище ведомого шлюза по unit ID.
+    pub fn unit_store(&self, unit_id: u8) -> Option<SharedDataStore> {
+        self.unit_stores.get(unit_id)
+    }
+
+    /// Unit ID ведомых шлюза.
+    pub fn unit_ids(&self) -> Vec<u8> {
+        self.unit_stores.unit_ids()
+    }
+
+    /// Заменить набор переменных ведомого шлюза; работающий шлюз видит его
+    /// сразу, а настройки шлюза обновляются для следующего запуска.
+    pub fn set_unit_variables(
+        &self,
+        unit_id: u8,
+        variables: &[ModbusVariable],
+    ) -> Result<Vec<ModbusVariable>, String> {
+        let applied = self.unit_stores.set_variables(unit_id, variables)?;
+        if let Some(slave) = self
+            .config
+            .write()
+            .serial_gateway
+            .as_mut()
+            .and_then(|gateway| gateway.slaves.iter_mut().find(|s| s.unit_id == unit_id))
+        {
+            slave.variables = variables.to_vec();
+        }
+        self.log_info(
+            "SERVER",
+            &format!(
+                "Переменные ведомого {} обновлены: {}",
+                unit_id,
+                applied.len()
+            ),
+        );
+        Ok(applied)
+    }
+
+    /// Задать unit ID по IP клиента (применяется при следующем запуске).
+    pub fn set_client_units(&self, units: Vec<ClientUnit>) {
+        self.config.write().client_units = units;
+    }
+
+    /// Задать уровни прошивки и активный уровень (применяется при следующем запуске).
+    pub fn set_firmware(&self, levels: Vec<FirmwareLevel>, firmware: Option<String>) {
+        let mut config = self.config.write();
+        config.firmware_levels = levels;
+        config.firmware = firmware;
+    }
+
+    /// Задать ограничения скорости изменения переменных при записи мастера.
+    /// Действуют сразу, в том числе для имитированных запросов.
+    pub fn set_write_rate_limits(&self, limits: Vec<WriteRateLimit>) {
+        self.rate_limiter.set_limits(limits);
+    }
+
+    /// Задать группы взаимоисключающих coils. Действуют сразу.
+    pub fn set_coil_interlocks(&self, groups: Vec<CoilInterlockGroup>) {
+        self.interlocks.set_groups(groups);
+    }
+
+    /// Задать защиту диапазонов паролем. Действует сразу, все клиенты
+    /// блокируются.
+    pub fn set_access_lock(&self, config: Option<AccessLockConfig>) {
+        self.access_lock.set_config(config);
+    }
+
+    /// Задать порог шторма записи; `None` — журналировать все записи. Действует сразу.
+    pub fn set_write_storm(&self, config: Option<WriteStormConfig>) {
+        self.write_storms.set_config(config);
+    }
+
+    /// Задать тревогу отсутствия мастера (применяется при следующем запуске).
+    pub fn set_inactivity_alarm(&self, alarm: Option<InactivityAlarmConfig>) {
+        self.config.write().inactivity_alarm = alarm;
+    }
+
+    /// Задать окно буфера событий (применяется при следующем запуске).
+    pub fn set_event_buffer(&self, event_buffer: Option<EventBufferConfig>) {
+        self.config.write().event_buffer = event_buffer;
+    }
+
+    /// Буфер событий, доступный мастеру через регистры.
+    pub fn event_buffer(&self) -> &EventBuffer {
+        &self.event_buffer
+    }
+
+    /// Сколько подмен Transaction ID ещё ожидает выполнения.
+    pub fn pending_transaction_id_mismatches(&self) -> u32 {
+        self.transaction_ids.pending()
+    }
+
+    /// Подменить Transaction ID в следующих `count` ответах; 0 отменяет
+    /// ожидающие подмены. Возвращает число ожидающих подмен.
+    pub fn inject_transaction_id_mismatches(&self, count: u32) -> u32 {
+        if count == 0 {
+            self.transaction_ids.cancel();
+            return 0;
+        }
+        self.transaction_ids.schedule(count)
+    }
+
+    /// Задать размер очереди подключений и паузу между accept
+    /// (применяются при следующем запуске).
+    pub fn set_accept_options(&self, listen_backlog: Option<u32>, accept_delay_ms: u64) {
+        let mut config = self.config.write();
+        config.listen_backlog = listen_backlog;
+        config.accept_delay = Duration::from_millis(accept_delay_ms);
+    }
+
+    /// Задать настройки mDNS-анонса (применяются при следующем запуске).
+    pub fn set_mdns(&self, settings: MdnsSettings, device_name: String) {
+        let mut config = self.config.write();
+        config.mdns = settings;
+        config.device_name = device_name;
+    }
+
+    /// Статистика исключений по диапазонам адресов.
+    pub fn exception_stats(&self) -> &SharedExceptionStats {
+        &self.exception_stats
+    }
+
+    /// Статистика запросов по функциям и клиентам.
+    pub fn request_stats(&self) -> &SharedRequestStats {
+        &self.request_stats
+    }
+
+    /// Обнулить все счётчики сервера: запросы, исключения, повторы
+    /// Transaction ID и приём подключений.
+    pub fn reset_statistics(&self) {
+        self.request_stats.reset();
+        self.exception_stats.reset();
+        self.duplicate_transactions.store(0, Ordering::SeqCst);
+        self.listener_stats.reset();
+        self.write_storms.reset();
+    }
+
+    /// Карта обращений мастера.
+    pub fn access_map(&self) -> &SharedAccessMap {
+        &self.access_map
+    }
+
+    /// Режим обучения по записям мастера.
+    pub fn write_learning(&self) -> &Arc<WriteLearning> {
+        &self.write_learning
+    }
+
+    /// Журнал обмена с поиском.
+    pub fn traffic_log(&self) -> &SharedTrafficLog {
+        &self.traffic_log
+    }
+
+    /// Ответы-исключения по команде.
+    pub fn exception_injector(&self) -> &Arc<ExceptionInjector> {
+        &self.exception_injector
+    }
+
+    /// Режим отказа связи.
+    pub fn comms_failure(&self) -> &Arc<CommsFailure> {
+        &self.comms_failure
+    }
+
+    /// Правила инжекции сбоев.
+    pub fn faults(&self) -> &Arc<FaultRules> {
+        &self.faults
+    }
+
+    /// Генератор случайных чисел проекта.
+    pub fn rng(&self) -> &SharedRng {
+        &self.rng
+    }
+
     /// Проверить, запущен ли сервер.
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
@@ -110,14 +620,21 @@ impl ModbusServer {
         let config = self.config.read();
         let error = self.last_error.read().clone();
 
-        ServerStatus {
+        let mut status = ServerStatus {
             running: self.running.load(Ordering::SeqCst),
             host: config.host.clone(),
             port: config.port,
+            port_aliases: config.listen_ports()[1..].to_vec(),
             unit_id: config.unit_id,
             connections_count: self.connections_count.load(Ordering::SeqCst),
+            duplicate_transaction_ids: self.duplicate_transactions.load(Ordering::SeqCst),
+            address_validation: self.data_store.address_validation(),
             error,
-        }
+            ..Default::default()
+        };
+        self.listener_stats.fill(&mut status);
+        self.write_storms.fill(&mut status);
+        status
     }
 
     /// Сгенерировать следующий ID для записи лога.
@@ -127,6 +644,10 @@ impl ModbusServer {
 
     /// Отправить запись лога в UI.
     pub fn emit_log(&self, entry: LogEntry) {
+        self.traffic_log.record(&entry);
+        if !self.traffic_log.should_emit(&entry) {
+            return;
+        }
         if let Some(handle) = self.app_handle.read().as_ref() {
             if let Err(e) = handle.emit(LOG_EVENT_NAME, &entry) {
                 log::warn!("Не удалось отправить лог в UI: {}", e);
@@ -141,7 +662,8 @@ impl ModbusServer {
             LogEntryType::Info,
             client_addr.to_string(),
             message.to_string(),
-        );
+        )
+        .with_subsystem(LogSubsystem::Server);
         log::info!("[{}] {}", client_addr, message);
         self.emit_log(entry);
     }
@@ -153,26 +675,173 @@ impl ModbusServer {
             LogEntryType::Error,
             client_addr.to_string(),
             message.to_string(),
-        );
+        )
+        .with_subsystem(LogSubsystem::Server);
         log::error!("[{}] {}", client_addr, message);
         self.emit_log(entry);
     }
 
+    /// Отправить запись подсистемы симуляции (поведения, триггеры, такты).
+    pub fn log_simulation(&self, entry_type: LogEntryType, message: &str) {
+        let entry = LogEntry::new(
+            self.next_log_id(),
+            entry_type,
+            "simulation".to_string(),
+            message.to_string(),
+        )
+        .with_subsystem(LogSubsystem::Simulation);
+        self.emit_log(entry);
+    }
+
+    /// Общие ресурсы обработки запросов для текущей конфигурации.
+    fn connection_context(
+        &self,
+        config: &ServerConfig,
+        response_overrides: ResponseOverrides,
+        app_handle: Option<AppHandle>,
+        log_counter: Arc<AtomicU64>,
+    ) -> ConnectionContext {
+        ConnectionContext {
+            data_store: self.data_store.clone(),
+            unit_id: config.unit_id,
+            framing: config.framing,
+            strictness: config.strictness,
+            processing_times: Arc::new(config.processing_times.clone()),
+            response_overrides: Arc::new(response_overrides),
+            fragmentation: Arc::new(config.fragmentation.clone()),
+            rng: self.rng.clone(),
+            app_handle,
+            log_counter,
+            exception_stats: self.exception_stats.clone(),
+            request_stats: self.request_stats.clone(),
+            access_map: self.access_map.clone(),
+            write_learning: self.write_learning.clone(),
+            duplicate_transactions: self.duplicate_transactions.clone(),
+            traffic_log: self.traffic_log.clone(),
+            verify_transaction_ids: config.verify_transaction_ids,
+            transaction_ids: self.transaction_ids.clone(),
+            runtime_counters: self.runtime_counters.clone(),
+            exception_injector: self.exception_injector.clone(),
+            comms_failure: self.comms_failure.clone(),
+            faults: self.faults.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            interlocks: self.interlocks.clone(),
+            access_lock: self.access_lock.clone(),
+            write_storms: self.write_storms.clone(),
+            counter_registers: config.runtime_counters,
+            gateway: config
+                .gateway
+                .clone()
+                .map(|gateway| Arc::new(Gateway::new(gateway))),
+            byte_count_stress: config.byte_count_stress,
+            zero_quantity_reads: Arc::new(config.zero_quantity_reads.clone()),
+            serial_gateway: config.serial_gateway.clone().map(|gateway| {
+                Arc::new(SerialGateway::new(
+                    gateway,
+                    config.unit_id,
+                    self.unit_stores.clone(),
+                ))
+            }),
+            // Назначения проверяются при запуске сервера
+            client_units: Arc::new(
+                ClientUnits::new(
+                    &config.client_units,
+                    config.unit_id,
+                    config.serial_gateway.as_ref(),
+                )
+                .unwrap_or_default(),
+            ),
+            // Уровень проверяется при запуске сервера
+            firmware: config
+                .firmware_level()
+                .ok()
+                .flatten()
+                .cloned()
+                .map(Arc::new),
+            mirror: None,
+            disconnect_tx: self.disconnect_tx.clone(),
+        }
+    }
+
+    /// Выполнить запрос записи тем же путём, что и сетевой запрос мастера
+    /// (проверки протокола, исключения, журнал, синхронизация переменных),
+    /// но без сокета. Работает и при остановленном сервере.
+    pub async fn simulate_master_write(
+        &self,
+        function_code: u8,
+        address: u16,
+        values: &[u16],
+    ) -> Result<SimulatedResponse, String> {
+        let config = self.config.read().clone().with_firmware()?;
+        let response_overrides = ResponseOverrides::compile(&config.response_overrides)?;
+        let log_counter = Arc::new(AtomicU64::new(self.log_id_counter.load(Ordering::SeqCst)));
+        let context = self.connection_context(
+            &config,
+            response_overrides,
+            self.app_handle.read().clone(),
+            log_counter,
+        );
+
+        let data = write_request_data(function_code, address, values)?;
+        let mut frame = Vec::with_capacity(MbapHeader::SIZE + 1 + data.len());
+        MbapHeader {
+            transaction_id: 0,
+            protocol_id: 0,
+            length: 2 + data.len() as u16,
+            unit_id: config.unit_id,
+        }
+        .write_to(&mut frame);
+        frame.push(function_code);
+        frame.extend_from_slice(&data);
+        if frame.len() > MAX_FRAME_SIZE {
+            return Err("Запрос длиннее максимального фрейма Modbus TCP".to_string());
+        }
+
+        match handle_frame(&context, &frame, SIMULATION_CLIENT, frame_clock::now_us()).await {
+            FrameOutcome::Response(response) => Ok(SimulatedResponse::new(response)),
+            FrameOutcome::Rejected => Err("Запрос отклонён политикой протокола".to_string()),
+            FrameOutcome::Dropped => Err("Ответ не отправлен по правилу сбоя".to_string()),
+            FrameOutcome::Malformed(e) => Err(e),
+            FrameOutcome::Close => Err("Соединение закрыто политикой протокола".to_string()),
+        }
+    }
+
     /// Запустить сервер.
-    pub async fn start(&self) -> Result<(), String> {
+    pub async fn start(&self) -> Result<(), AppError> {
         if self.running.load(Ordering::SeqCst) {
-            return Err("Сервер уже запущен".to_string());
+            return Err(AppError::new(
+                ErrorCode::AlreadyRunning,
+                "Сервер уже запущен",
+            ));
         }
 
-        let config = self.config.read().clone();
+        let (config, response_overrides) = self.validated_config().map_err(AppError::invalid)?;
         let bind_addr = format!("{}:{}", config.host, config.port);
 
-        // Пытаемся привязаться к адресу
-        let listener = TcpListener::bind(&bind_addr)
-            .await
-            .map_err(|e| format!("Не удалось привязаться к {}: {}", bind_addr, e))?;
-
-        log::info!("Modbus TCP сервер слушает на {}", bind_addr);
+        // Пытаемся привязаться к основному порту и ко всем дополнительным
+        let ports = config.listen_ports();
+        let mut listeners = Vec::with_capacity(ports.len());
+        for port in &ports {
+            let addr = format!("{}:{}", config.host, port);
+            let listener = bind_listener(&addr, config.listen_backlog)
+                .await
+                .map_err(|e| {
+                    AppError::io(&e, format!("Не удалось привязаться к {}", addr), &addr)
+                })?;
+            log::info!("Modbus TCP сервер слушает на {}", addr);
+            listeners.push(listener);
+        }
+        let mut udp_sockets = Vec::new();
+        if config.udp {
+            for port in &ports {
+                let addr = format!("{}:{}", config.host, port);
+                let socket = UdpSocket::bind(&addr).await.map_err(|e| {
+                    AppError::io(&e, format!("Не удалось привязать UDP к {}", addr), &addr)
+                })?;
+                log::info!("Modbus UDP сервер слушает на {}", addr);
+                udp_sockets.push(socket);
+            }
+        }
 
         // Создаём канал завершения
         let (shutdown_tx, _) = broadcast::channel::<()>(1);
@@ -181,91 +850,293 @@ impl ModbusServer {
         // Очищаем предыдущую ошибку
         *self.last_error.write() = None;
 
+        // Каждый запуск воспроизводит одну и ту же случайную последовательность
+        self.rng.restart();
+
         // Отмечаем сервер как запущенный
         self.running.store(true, Ordering::SeqCst);
+        self.duplicate_transactions.store(0, Ordering::SeqCst);
+        self.listener_stats.reset();
+        self.rate_limiter.reset();
+        self.write_storms.reset();
+        self.access_lock.reset();
+        // Каждый запуск начинается с пустого буфера событий
+        self.event_buffer
+            .start(&self.data_store, config.event_buffer);
 
         // Логируем запуск
-        self.log_info("SERVER", &format!("Сервер запущен на {}", bind_addr));
+        if ports.len() > 1 {
+            let aliases: Vec<String> = ports[1..].iter().map(|p| p.to_string()).collect();
+            self.log_info(
+                "SERVER",
+                &format!(
+                    "Сервер запущен на {} (также порты {})",
+                    bind_addr,
+                    aliases.join(", ")
+                ),
+            );
+        } else {
+            self.log_info("SERVER", &format!("Сервер запущен на {}", bind_addr));
+        }
+
+        // Служебные счётчики отсчитываются от запуска и обновляются раз в секунду
+        self.runtime_counters.reset();
+        if config.runtime_counters.is_enabled() {
+            tokio::spawn(runtime_counters::run(
+                self.runtime_counters.clone(),
+                self.data_store.clone(),
+                config.runtime_counters,
+                shutdown_tx.subscribe(),
+            ));
+        }
+
+        // Объявляем сервис в локальной сети
+        if config.mdns.enabled {
+            let service =
+                MdnsService::new(&config.mdns, &config.device_name, &config.host, config.port);
+            tokio::spawn(mdns::advertise(service, shutdown_tx.subscribe()));
+        }
 
         // Клонируем ссылки для цикла принятия соединений
         let server_running = Arc::new(AtomicBool::new(true));
         let server_running_clone = server_running.clone();
-        let data_store = self.data_store.clone();
-        let connections_count = Arc::new(AtomicUsize::new(0));
-        let unit_id = config.unit_id;
+        let connections_count = self.connections_count.clone();
+        let listener_stats = self.listener_stats.clone();
         let app_handle = self.app_handle.read().clone();
+
+        // Статус с живыми счётчиками раз в секунду, чтобы сбои приёма были видны
+        if let Some(handle) = app_handle.clone() {
+            let base = self.get_status();
+            let connections_count = connections_count.clone();
+            let duplicate_transactions = self.duplicate_transactions.clone();
+            let listener_stats = listener_stats.clone();
+            let write_storms = self.write_storms.clone();
+            let data_store = self.data_store.clone();
+            let status = move || {
+                let mut status = ServerStatus {
+                    connections_count: connections_count.load(Ordering::SeqCst),
+                    duplicate_transaction_ids: duplicate_transactions.load(Ordering::SeqCst),
+                    address_validation: data_store.address_validation(),
+                    ..base.clone()
+                };
+                listener_stats.fill(&mut status);
+                write_storms.fill(&mut status);
+                status
+            };
+            tokio::spawn(listener_stats::publish_status(
+                handle.clone(),
+                status,
+                shutdown_tx.subscribe(),
+            ));
+            // Тренд исключений по окнам для графика доли ошибок
+            tokio::spawn(exception_stats::publish_trend(
+                handle,
+                self.exception_stats.clone(),
+                self.runtime_counters.clone(),
+                shutdown_tx.subscribe(),
+            ));
+        }
         let log_id_counter = Arc::new(AtomicU64::new(self.log_id_counter.load(Ordering::SeqCst)));
+        let mut context = self.connection_context(
+            &config,
+            response_overrides,
+            app_handle.clone(),
+            log_id_counter.clone(),
+        );
+        if let Some(mirror) = config.traffic_mirror.clone() {
+            context.mirror = Some(TrafficMirror::spawn(mirror, shutdown_tx.subscribe()));
+        }
+
+        {
+            let app_handle = app_handle.clone();
+            let traffic_log = self.traffic_log.clone();
+            let log_id_counter = log_id_counter.clone();
+            let notify = move |storm: write_storm::StormSummary| {
+                emit_log_entry(
+                    &app_handle,
+                    &traffic_log,
+                    LogEntry::new(
+                        log_id_counter.fetch_add(1, Ordering::SeqCst),
+                        LogEntryType::Warning,
+                        storm.client,
+                        format!(
+                            "Шторм записи {:?} {}: {} записей за {:.1} с не попали в журнал",
+                            storm.area,
+                            storm.address,
+                            storm.writes,
+                            storm.duration.as_secs_f64()
+                        ),
+                    )
+                    .with_address_range(Some((storm.address, 1)))
+                    .with_subsystem(LogSubsystem::Data),
+                );
+            };
+            tokio::spawn(write_storm::run(
+                self.write_storms.clone(),
+                notify,
+                shutdown_tx.subscribe(),
+            ));
+        }
+
+        // Тревога отсутствия мастера отсчитывается от запуска, как счётчик простоя
+        if let Some(alarm) = config.inactivity_alarm {
+            let app_handle = app_handle.clone();
+            let traffic_log = self.traffic_log.clone();
+            let log_id_counter = log_id_counter.clone();
+            let notify = move |event: inactivity::InactivityEvent| {
+                let (entry_type, message) = if event.active {
+                    (
+                        LogEntryType::Warning,
+                        format!("Нет запросов мастера {} мс", event.idle_ms),
+                    )
+                } else {
+                    (
+                        LogEntryType::Info,
+                        "Запросы мастера возобновились".to_string(),
+                    )
+                };
+                emit_log_entry(
+                    &app_handle,
+                    &traffic_log,
+                    LogEntry::new(
+                        log_id_counter.fetch_add(1, Ordering::SeqCst),
+                        entry_type,
+                        "SERVER".to_string(),
+                        message,
+                    ),
+                );
+                if let Some(handle) = &app_handle {
+                    let _ = handle.emit(INACTIVITY_EVENT_NAME, event);
+                }
+            };
+            tokio::spawn(inactivity::run(
+                alarm,
+                self.runtime_counters.clone(),
+                self.data_store.clone(),
+                notify,
+                shutdown_tx.subscribe(),
+            ));
+        }
+
+        for socket in udp_sockets {
+            tokio::spawn(serve_udp(socket, context.clone(), shutdown_tx.subscribe()));
+        }
 
-        // Запускаем цикл принятия соединений
-        let connections_count_clone = connections_count;
-        tokio::spawn(async move {
-            let mut shutdown_rx = shutdown_tx.subscribe();
-
-            loop {
-                tokio::select! {
-                    // Принимаем новые соединения
-                    accept_result = listener.accept() => {
-                        match accept_result {
-                            Ok((socket, addr)) => {
-                                log::info!("Новое соединение от {}", addr);
-                                connections_count_clone.fetch_add(1, Ordering::SeqCst);
-
-                                // Отправляем лог о подключении
-                                if let Some(ref handle) = app_handle {
-                                    let entry = LogEntry::new(
+        // Запускаем цикл принятия соединений для каждого порта (хранилище общее)
+        let accept_delay = config.accept_delay;
+        for listener in listeners {
+            let connections_count_clone = connections_count.clone();
+            let listener_stats = listener_stats.clone();
+            let shutdown_tx = shutdown_tx.clone();
+            let server_running_clone = server_running_clone.clone();
+            let app_handle = app_handle.clone();
+            let log_id_counter = log_id_counter.clone();
+            let context = context.clone();
+            tokio::spawn(async move {
+                let mut shutdown_rx = shutdown_tx.subscribe();
+
+                loop {
+                    tokio::select! {
+                        // Принимаем новые соединения
+                        accept_result = listener.accept() => {
+                            match accept_result {
+                                Ok((socket, addr)) => {
+                                    log::info!("Новое соединение от {}", addr);
+                                    listener_stats.record_accepted();
+                                    connections_count_clone.fetch_add(1, Ordering::SeqCst);
+
+                                    // Отправляем лог о подключении
+                                    emit_log_entry(&app_handle, &context.traffic_log, LogEntry::new(
                                         log_id_counter.fetch_add(1, Ordering::SeqCst),
                                         LogEntryType::Info,
                                         addr.to_string(),
                                         "Клиент подключился".to_string(),
-                                    );
-                                    let _ = handle.emit(LOG_EVENT_NAME, &entry);
+                                    ));
+
+                                    let connections_count = connections_count_clone.clone();
+                                    let mut client_shutdown_rx = shutdown_tx.subscribe();
+                                    let client_context = context.clone();
+                                    let access_lock = context.access_lock.clone();
+
+                                    // Запускаем обработчик для этого соединения
+                                    tokio::spawn(async move {
+                                        handle_connection(
+                                            socket,
+                                            addr,
+                                            client_context,
+                                            &mut client_shutdown_rx,
+                                        ).await;
+                                        // Разблокировка паролем действует только для этого соединения
+                                        access_lock.disconnect(&addr.to_string());
+                                        connections_count.fetch_sub(1, Ordering::SeqCst);
+                                        log::info!("Соединение закрыто: {}", addr);
+                                    });
+                                }
+                                Err(e) => {
+                                    if listener_stats.record_error(&e) {
+                                        log::warn!("Соединение сброшено до принятия: {}", e);
+                                    } else {
+                                        log::error!("Не удалось принять соединение: {}", e);
+                                        // Исчерпание ресурсов не проходит мгновенно:
+                                        // без паузы цикл крутился бы вхолостую
+                                        tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                                    }
                                 }
-
-                                let data_store = data_store.clone();
-                                let connections_count = connections_count_clone.clone();
-                                let mut client_shutdown_rx = shutdown_tx.subscribe();
-                                let client_app_handle = app_handle.clone();
-                                let client_log_counter = log_id_counter.clone();
-
-                                // Запускаем обработчик для этого соединения
-                                tokio::spawn(async move {
-                                    handle_connection(
-                                        socket,
-                                        addr,
-                                        data_store,
-                                        unit_id,
-                                        &mut client_shutdown_rx,
-                                        client_app_handle,
-                                        client_log_counter,
-                                    ).await;
-                                    connections_count.fetch_sub(1, Ordering::SeqCst);
-                                    log::info!("Соединение закрыто: {}", addr);
-                                });
-                            }
-                            Err(e) => {
-                                log::error!("Не удалось принять соединение: {}", e);
                             }
                         }
+                        // Получен сигнал завершения
+                        _ = shutdown_rx.recv() => {
+                            log::info!("Получен сигнал завершения сервера");
+                            server_running_clone.store(false, Ordering::SeqCst);
+                            break;
+                        }
                     }
-                    // Получен сигнал завершения
-                    _ = shutdown_rx.recv() => {
-                        log::info!("Получен сигнал завершения сервера");
-                        server_running_clone.store(false, Ordering::SeqCst);
-                        break;
+
+                    // Медленное устройство: следующие подключения ждут в очереди ядра
+                    if !accept_delay.is_zero() {
+                        tokio::select! {
+                            _ = tokio::time::sleep(accept_delay) => {}
+                            _ = shutdown_rx.recv() => {
+                                log::info!("Получен сигнал завершения сервера");
+                                server_running_clone.store(false, Ordering::SeqCst);
+                                break;
+                            }
+                        }
                     }
                 }
-            }
 
-            log::info!("Цикл принятия соединений завершён");
-        });
+                log::info!("Цикл принятия соединений завершён");
+            });
+        }
 
         Ok(())
     }
 
+    /// Проверить настройки перед запуском: конфигурация с особенностями
+    /// прошивки и скомпилированные подменённые ответы.
+    fn validated_config(&self) -> Result<(ServerConfig, ResponseOverrides), String> {
+        let config = self.config.read().clone().with_firmware()?;
+        let response_overrides = ResponseOverrides::compile(&config.response_overrides)?;
+        config.zero_quantity_reads.validate()?;
+        if let Some(gateway) = &config.serial_gateway {
+            gateway.validate(config.unit_id)?;
+        }
+        ClientUnits::new(
+            &config.client_units,
+            config.unit_id,
+            config.serial_gateway.as_ref(),
+        )?;
+        if let Some(event_buffer) = &config.event_buffer {
+            event_buffer.validate()?;
+        }
+        self.access_lock.validate()?;
+        Ok((config, response_overrides))
+    }
+
     /// Остановить сервер.
-    pub fn stop(&self) -> Result<(), String> {
+    pub fn stop(&self) -> Result<(), AppError> {
         if !self.running.load(Ordering::SeqCst) {
-            return Err("Сервер не запущен".to_string());
+            return Err(AppError::new(ErrorCode::NotRunning, "Сервер не запущен"));
         }
 
         // Отправляем сигнал завершения
@@ -278,7 +1149,6 @@ impl ModbusServer {
 
         // Отмечаем как остановленный
         self.running.store(false, Ordering::SeqCst);
-        self.connections_count.store(0, Ordering::SeqCst);
 
         // Логируем остановку
         self.log_info("SERVER", "Сервер остановлен");
@@ -288,24 +1158,114 @@ impl ModbusServer {
         Ok(())
     }
 
+    /// Закрыть все клиентские соединения, не останавливая прослушивание портов.
+    /// Возвращает количество закрытых соединений.
+    pub fn disconnect_all(&self) -> Result<usize, AppError> {
+        if !self.running.load(Ordering::SeqCst) {
+            return Err(AppError::new(ErrorCode::NotRunning, "Сервер не запущен"));
+        }
+
+        let count = self.disconnect_tx.send(()).unwrap_or(0);
+        self.log_info(
+            "SERVER",
+            &format!("Принудительно закрыто соединений: {}", count),
+        );
+        Ok(count)
+    }
+
     /// Установить сообщение об ошибке.
     pub fn set_error(&self, error: String) {
         *self.last_error.write() = Some(error);
     }
 }
 
+/// Открыть слушающий сокет. С заданным размером очереди (backlog) сокет
+/// создаётся вручную, иначе используется значение tokio по умолчанию.
+async fn bind_listener(addr: &str, backlog: Option<u32>) -> std::io::Result<TcpListener> {
+    let Some(backlog) = backlog else {
+        return TcpListener::bind(addr).await;
+    };
+    let socket_addr = tokio::net::lookup_host(addr).await?.next().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::AddrNotAvailable, "адрес не найден")
+    })?;
+    let socket = if socket_addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    socket.bind(socket_addr)?;
+    socket.listen(backlog)
+}
+
+/// Общие ресурсы, передаваемые каждому клиентскому соединению.
+#[derive(Clone)]
+struct ConnectionContext {
+    data_store: SharedDataStore,
+    unit_id: u8,
+    framing: ConnectionFraming,
+    strictness: ProtocolStrictness,
+    processing_times: Arc<ProcessingTimes>,
+    response_overrides: Arc<ResponseOverrides>,
+    fragmentation: Arc<Fragmentation>,
+    rng: SharedRng,
+    app_handle: Option<AppHandle>,
+    log_counter: Arc<AtomicU64>,
+    exception_stats: SharedExceptionStats,
+    request_stats: SharedRequestStats,
+    access_map: SharedAccessMap,
+    write_learning: Arc<WriteLearning>,
+    duplicate_transactions: Arc<AtomicU64>,
+    traffic_log: SharedTrafficLog,
+    verify_transaction_ids: bool,
+    transaction_ids: Arc<TransactionIdInjector>,
+    runtime_counters: Arc<RuntimeCounters>,
+    exception_injector: Arc<ExceptionInjector>,
+    comms_failure: Arc<CommsFailure>,
+    faults: Arc<FaultRules>,
+    rate_limiter: Arc<WriteRateLimiter>,
+    interlocks: Arc<CoilInterlocks>,
+    access_lock: Arc<AccessLock>,
+    write_storms: Arc<WriteStorms>,
+    counter_registers: RuntimeCounterRegisters,
+    gateway: Option<Arc<Gateway>>,
+    byte_count_stress: ByteCountStress,
+    zero_quantity_reads: Arc<ZeroQuantityReads>,
+    serial_gateway: Option<Arc<SerialGateway>>,
+    client_units: Arc<ClientUnits>,
+    firmware: Option<Arc<FirmwareLevel>>,
+    /// Копия сетевых кадров на анализатор (только для запущенного сервера).
+    mirror: Option<TrafficMirror>,
+    disconnect_tx: broadcast::Sender<()>,
+}
+
 /// Обработать одно клиентское соединение.
 async fn handle_connection(
     mut socket: TcpStream,
     addr: SocketAddr,
-    data_store: SharedDataStore,
-    unit_id: u8,
+    context: ConnectionContext,
     shutdown_rx: &mut broadcast::Receiver<()>,
-    app_handle: Option<AppHandle>,
-    log_counter: Arc<AtomicU64>,
 ) {
+    let ConnectionContext {
+        app_handle,
+        log_counter,
+        duplicate_transactions,
+        traffic_log,
+        fragmentation,
+        mirror,
+        disconnect_tx,
+        ..
+    } = &context;
+    let mut disconnect_rx = disconnect_tx.subscribe();
+    // Без Nagle сегменты дробления уходят по отдельности
+    if fragmentation.is_enabled() {
+        if let Err(e) = socket.set_nodelay(true) {
+            log::warn!("Не удалось отключить Nagle для {}: {}", addr, e);
+        }
+    }
     let mut buffer = vec![0u8; READ_BUFFER_SIZE];
-    let mut frame_buffer = Vec::with_capacity(MAX_FRAME_SIZE);
+    let mut decoder = FrameDecoder::new();
+    let mut ascii_decoder = (context.framing == ConnectionFraming::Ascii).then(AsciiDecoder::new);
     let client_addr = addr.to_string();
 
     loop {
@@ -315,7 +1275,7 @@ async fn handle_connection(
                 match read_result {
                     Ok(0) => {
                         // Соединение закрыто
-                        emit_log_entry(&app_handle, &log_counter, LogEntry::new(
+                        emit_log_entry(app_handle, traffic_log, LogEntry::new(
                             log_counter.fetch_add(1, Ordering::SeqCst),
                             LogEntryType::Info,
                             client_addr.clone(),
@@ -324,89 +1284,80 @@ async fn handle_connection(
                         break;
                     }
                     Ok(n) => {
-                        frame_buffer.extend_from_slice(&buffer[..n]);
-
-                        // Обрабатываем полные фреймы
-                        while let Some(frame_len) = ModbusRequest::expected_frame_length(&frame_buffer) {
-                            if frame_buffer.len() >= frame_len {
-                                // Извлекаем и обрабатываем фрейм
-                                let frame_data: Vec<u8> = frame_buffer.drain(..frame_len).collect();
-                                let request_start = Instant::now();
-
-                                match ModbusRequest::parse(&frame_data) {
-                                    Ok(request) => {
-                                        // Проверяем Unit ID
-                                        if request.header.unit_id != unit_id && request.header.unit_id != 0 {
-                                            log::debug!(
-                                                "Игнорируем запрос для unit ID {} (мы {})",
-                                                request.header.unit_id,
-                                                unit_id
-                                            );
-                                            continue;
-                                        }
-
-                                        // Логируем запрос
-                                        let func_name = function_code_name(request.function_code);
-                                        let request_summary = format_request_summary(&request);
-
-                                        let request_log = LogEntry::new(
+                        let received_us = frame_clock::now_us();
+                        match ascii_decoder.as_mut() {
+                            // Кадры ASCII переводятся в MBAP и идут по общему пути
+                            Some(ascii) => {
+                                ascii.push(&buffer[..n]);
+                                while let Some(next) = ascii.next_frame() {
+                                    match next {
+                                        Ok(frame_data) => decoder.push(&frame_data),
+                                        Err(e) => emit_log_entry(app_handle, traffic_log, LogEntry::new(
                                             log_counter.fetch_add(1, Ordering::SeqCst),
-                                            LogEntryType::Request,
+                                            LogEntryType::Error,
                                             client_addr.clone(),
-                                            request_summary,
-                                        )
-                                        .with_function(request.function_code, func_name)
-                                        .with_raw_data(&frame_data);
-
-                                        emit_log_entry(&app_handle, &log_counter, request_log);
-
-                                        // Обрабатываем запрос и отправляем ответ
-                                        let response = process_request(&request, &data_store);
-                                        let duration_us = request_start.elapsed().as_micros() as u64;
+                                            format!("Ошибка кадра ASCII: {}", e),
+                                        ).with_raw_data(&buffer[..n])),
+                                    }
+                                }
+                            }
+                            None => decoder.push(&buffer[..n]),
+                        }
 
-                                        // Логируем ответ
-                                        let response_summary = format_response_summary(&request, &response);
-                                        let is_error = response.len() > 7 && (response[7] & 0x80) != 0;
+                        // Все полные фреймы в буфере ещё ждут ответа: одинаковый
+                        // Transaction ID среди них — признак ошибки в реализации мастера
+                        for transaction_id in decoder.duplicate_transaction_ids() {
+                            duplicate_transactions.fetch_add(1, Ordering::SeqCst);
+                            log::warn!("Клиент {} повторно использовал Transaction ID {}", addr, transaction_id);
+                            emit_log_entry(app_handle, traffic_log, LogEntry::new(
+                                log_counter.fetch_add(1, Ordering::SeqCst),
+                                LogEntryType::Warning,
+                                client_addr.clone(),
+                                format!("Повторный Transaction ID {} при незавершённом запросе", transaction_id),
+                            ));
+                        }
 
-                                        let response_log = LogEntry::new(
-                                            log_counter.fetch_add(1, Ordering::SeqCst),
-                                            if is_error { LogEntryType::Error } else { LogEntryType::Response },
-                                            client_addr.clone(),
-                                            response_summary,
-                                        )
-                                        .with_function(request.function_code, func_name)
-                                        .with_raw_data(&response)
-                                        .with_duration(duration_us);
-
-                                        emit_log_entry(&app_handle, &log_counter, response_log);
-
-                                        if let Err(e) = socket.write_all(&response).await {
-                                            log::error!("Не удалось отправить ответ {}: {}", addr, e);
-                                            return;
-                                        }
+                        // Обрабатываем полные фреймы
+                        while let Some(next) = decoder.next_frame() {
+                            if let Some(mirror) = mirror {
+                                mirror.send(match &next {
+                                    Ok(frame_data) => frame_data,
+                                    Err(illegal) => &illegal.frame,
+                                });
+                            }
+                            let outcome = match next {
+                                Ok(frame_data) => {
+                                    handle_frame(&context, &frame_data, &client_addr, received_us).await
+                                }
+                                Err(illegal) => illegal_length_outcome(&context, &illegal, &client_addr),
+                            };
+                            match outcome {
+                                FrameOutcome::Response(response) => {
+                                    if let Some(mirror) = mirror {
+                                        mirror.send(&response);
                                     }
-                                    Err(e) => {
-                                        log::error!("Не удалось разобрать запрос от {}: {}", addr, e);
-                                        emit_log_entry(&app_handle, &log_counter, LogEntry::new(
-                                            log_counter.fetch_add(1, Ordering::SeqCst),
-                                            LogEntryType::Error,
-                                            client_addr.clone(),
-                                            format!("Ошибка разбора запроса: {}", e),
-                                        ).with_raw_data(&frame_data));
-                                        // Очищаем буфер при ошибке разбора для ресинхронизации
-                                        frame_buffer.clear();
+                                    let response = match ascii_decoder {
+                                        Some(_) => match ascii::mbap_to_ascii(&response) {
+                                            Some(frame) => frame,
+                                            None => continue,
+                                        },
+                                        None => response,
+                                    };
+                                    if let Err(e) = fragmentation.write(&mut socket, &response).await {
+                                        log::error!("Не удалось отправить ответ {}: {}", addr, e);
+                                        return;
                                     }
                                 }
-                            } else {
-                                // Нужно больше данных
-                                break;
+                                FrameOutcome::Rejected | FrameOutcome::Dropped => {}
+                                // Очищаем буфер при ошибке разбора для ресинхронизации
+                                FrameOutcome::Malformed(_) => decoder.clear(),
+                                FrameOutcome::Close => return,
                             }
                         }
 
                         // Предотвращаем переполнение буфера
-                        if frame_buffer.len() > MAX_FRAME_SIZE * 2 {
+                        if decoder.discard_overflow() {
                             log::warn!("Переполнение буфера фреймов от {}, очистка", addr);
-                            frame_buffer.clear();
                         }
                     }
                     Err(e) => {
@@ -420,12 +1371,85 @@ async fn handle_connection(
                 log::debug!("Соединение {} получило сигнал завершения", addr);
                 break;
             }
+            // Принудительное отключение всех клиентов
+            _ = disconnect_rx.recv() => {
+                emit_log_entry(app_handle, traffic_log, LogEntry::new(
+                    log_counter.fetch_add(1, Ordering::SeqCst),
+                    LogEntryType::Info,
+                    client_addr.clone(),
+                    "Соединение закрыто сервером".to_string(),
+                ));
+                break;
+            }
+        }
+    }
+}
+
+/// Обслуживать Modbus/UDP: каждая датаграмма — один кадр MBAP, ответ уходит
+/// датаграммой отправителю. Дробление и ASCII к UDP не применяются.
+async fn serve_udp(
+    socket: UdpSocket,
+    context: ConnectionContext,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        let (n, addr) = tokio::select! {
+            received = socket.recv_from(&mut buffer) => match received {
+                Ok(received) => received,
+                Err(e) => {
+                    // Например, ICMP «порт недоступен» от прошлого ответа
+                    log::warn!("Ошибка приёма UDP: {}", e);
+                    continue;
+                }
+            },
+            _ = shutdown_rx.recv() => break,
+        };
+        let received_us = frame_clock::now_us();
+        let datagram = &buffer[..n];
+        let client_addr = addr.to_string();
+        if let Some(mirror) = &context.mirror {
+            mirror.send(datagram);
+        }
+
+        let declared = MbapHeader::parse_unchecked(datagram)
+            .ok()
+            .map(|header| MbapHeader::SIZE - 1 + header.length as usize);
+        if declared != Some(n) {
+            emit_log_entry(
+                &context.app_handle,
+                &context.traffic_log,
+                LogEntry::new(
+                    context.log_counter.fetch_add(1, Ordering::SeqCst),
+                    LogEntryType::Error,
+                    client_addr,
+                    format!("Датаграмма {} байт не совпадает с длиной MBAP", n),
+                )
+                .with_raw_data(datagram),
+            );
+            continue;
+        }
+
+        if let FrameOutcome::Response(response) =
+            handle_frame(&context, datagram, &client_addr, received_us).await
+        {
+            if let Some(mirror) = &context.mirror {
+                mirror.send(&response);
+            }
+            if let Err(e) = socket.send_to(&response, addr).await {
+                log::error!("Не удалось отправить ответ UDP {}: {}", addr, e);
+            }
         }
     }
+    log::info!("Приём UDP завершён");
 }
 
 /// Вспомогательная функция для отправки записи лога.
-fn emit_log_entry(app_handle: &Option<AppHandle>, _log_counter: &Arc<AtomicU64>, entry: LogEntry) {
+fn emit_log_entry(app_handle: &Option<AppHandle>, traffic_log: &SharedTrafficLog, entry: LogEntry) {
+    traffic_log.record(&entry);
+    if !traffic_log.should_emit(&entry) {
+        return;
+    }
     if let Some(handle) = app_handle {
         let _ = handle.emit(LOG_EVENT_NAME, &entry);
     }
@@ -488,286 +1512,661 @@ fn format_request_summary(request: &ModbusRequest) -> String {
                 "Запись регистров (ошибка разбора)".to_string()
             }
         }
-        None => {
-            format!("Неизвестная функция 0x{:02X}", request.function_code)
+        Some(FunctionCode::MaskWriteRegister) => {
+            if let Ok(req) = MaskWriteRegisterRequest::parse(&request.data) {
+                format!(
+                    "Запись регистра по маске по адресу {}: AND 0x{:04X}, OR 0x{:04X}",
+                    req.address, req.and_mask, req.or_mask
+                )
+            } else {
+                "Запись регистра по маске (ошибка разбора)".to_string()
+            }
         }
-    }
-}
-
-/// Форматировать краткое описание ответа.
-fn format_response_summary(request: &ModbusRequest, response: &[u8]) -> String {
-    // Проверяем, является ли ответ ошибкой
-    if response.len() > 8 && (response[7] & 0x80) != 0 {
-        let exception_code = response[8];
-        let exception_name = match exception_code {
-            0x01 => "Illegal Function",
-            0x02 => "Illegal Data Address",
-            0x03 => "Illegal Data Value",
-            0x04 => "Server Device Failure",
-            _ => "Unknown Exception",
-        };
-        return format!("Ошибка: {} (0x{:02X})", exception_name, exception_code);
-    }
-
-    match FunctionCode::from_u8(request.function_code) {
-        Some(FunctionCode::ReadCoils) | Some(FunctionCode::ReadDiscreteInputs) => {
-            if response.len() > 8 {
-                let byte_count = response[8] as usize;
-                format!("OK: {} байт данных", byte_count)
+        Some(FunctionCode::ReadFileRecord) => {
+            if let Ok(req) = ReadFileRecordRequest::parse(&request.data) {
+                format!("Чтение {}", format_file_records(&req.records))
             } else {
-                "OK".to_string()
+                "Чтение файловых записей (ошибка разбора)".to_string()
             }
         }
-        Some(FunctionCode::ReadHoldingRegisters) | Some(FunctionCode::ReadInputRegisters) => {
-            if response.len() > 8 {
-                let byte_count = response[8] as usize;
-                format!("OK: {} регистров", byte_count / 2)
+        Some(FunctionCode::WriteFileRecord) => {
+            if let Ok(req) = WriteFileRecordRequest::parse(&request.data) {
+                let references: Vec<FileRecordRef> =
+                    req.records.iter().map(|record| record.reference).collect();
+                format!("Запись {}", format_file_records(&references))
             } else {
-                "OK".to_string()
+                "Запись файловых записей (ошибка разбора)".to_string()
             }
         }
-        Some(FunctionCode::WriteSingleCoil) => "OK: Coil записан".to_string(),
-        Some(FunctionCode::WriteSingleRegister) => "OK: Регистр записан".to_string(),
-        Some(FunctionCode::WriteMultipleCoils) => "OK: Coils записаны".to_string(),
-        Some(FunctionCode::WriteMultipleRegisters) => "OK: Регистры записаны".to_string(),
-        None => "Ответ отправлен".to_string(),
+        None => {
+            format!("Неизвестная функция 0x{:02X}", request.function_code)
+        }
     }
 }
 
-/// Обработать Modbus запрос и сгенерировать ответ.
-fn process_request(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let function_code = request.function_code;
+/// Подзапросы файловых записей: "файл 4 записи 1..2, файл 3 запись 9".
+fn format_file_records(references: &[FileRecordRef]) -> String {
+    references
+        .iter()
+        .map(|r| {
+            if r.record_length == 1 {
+                format!("файл {} запись {}", r.file_number, r.record_number)
+            } else {
+                format!(
+                    "файл {} записи {}..{}",
+                    r.file_number,
+                    r.record_number,
+                    r.record_number as u32 + r.record_length as u32 - 1
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
+/// Данные PDU запроса записи (без кода функции). Для coils ненулевое
+/// значение означает ON.
+fn write_request_data(function_code: u8, address: u16, values: &[u16]) -> Result<Vec<u8>, String> {
+    let mut data = address.to_be_bytes().to_vec();
     match FunctionCode::from_u8(function_code) {
-        Some(FunctionCode::ReadCoils) => handle_read_coils(request, data_store),
-        Some(FunctionCode::ReadDiscreteInputs) => handle_read_discrete_inputs(request, data_store),
-        Some(FunctionCode::ReadHoldingRegisters) => {
-            handle_read_holding_registers(request, data_store)
+        Some(function @ (FunctionCode::WriteSingleCoil | FunctionCode::WriteSingleRegister)) => {
+            let &[value] = values else {
+                return Err("Для записи одной ячейки нужно ровно одно значение".to_string());
+            };
+            let word = match function {
+                FunctionCode::WriteSingleCoil if value != 0 => 0xFF00,
+                FunctionCode::WriteSingleCoil => 0x0000,
+                _ => value,
+            };
+            data.extend_from_slice(&word.to_be_bytes());
         }
-        Some(FunctionCode::ReadInputRegisters) => handle_read_input_registers(request, data_store),
-        Some(FunctionCode::WriteSingleCoil) => handle_write_single_coil(request, data_store),
-        Some(FunctionCode::WriteSingleRegister) => {
-            handle_write_single_register(request, data_store)
+        Some(FunctionCode::WriteMultipleCoils) => {
+            let bits: Vec<bool> = values.iter().map(|&value| value != 0).collect();
+            let bytes = pack_bits(&bits);
+            data.extend_from_slice(&(values.len() as u16).to_be_bytes());
+            data.push(bytes.len() as u8);
+            data.extend_from_slice(&bytes);
         }
-        Some(FunctionCode::WriteMultipleCoils) => handle_write_multiple_coils(request, data_store),
         Some(FunctionCode::WriteMultipleRegisters) => {
-            handle_write_multiple_registers(request, data_store)
+            let bytes = pack_registers(values);
+            data.extend_from_slice(&(values.len() as u16).to_be_bytes());
+            data.push(bytes.len() as u8);
+            data.extend_from_slice(&bytes);
         }
-        None => {
-            log::warn!("Неподдерживаемый код функции: 0x{:02X}", function_code);
-            ModbusResponse::build_exception(request, function_code, ExceptionCode::IllegalFunction)
+        _ => {
+            return Err(format!(
+                "Функция 0x{:02X} не является функцией записи",
+                function_code
+            ))
         }
     }
+    Ok(data)
 }
 
-/// Обработать Read Coils (0x01).
-fn handle_read_coils(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let read_req = match ReadRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
-                request,
-                request.function_code,
-                ExceptionCode::IllegalDataValue,
-            );
-        }
-    };
-
-    if let Err(e) = read_req.validate_bits() {
-        return ModbusResponse::build_exception(request, request.function_code, e);
-    }
+/// Итог обработки одного фрейма запроса.
+enum FrameOutcome {
+    /// Ответ для отправки мастеру.
+    Response(Vec<u8>),
+    /// Запрос отклонён политикой протокола, ответа нет.
+    Rejected,
+    /// Запрос обработан, ответ не отправлен по правилу сбоя.
+    Dropped,
+    /// Фрейм не разобран.
+    Malformed(String),
+    /// Соединение закрывается по политике протокола.
+    Close,
+}
 
-    match data_store.read_coils(read_req.start_address, read_req.quantity) {
-        Ok(coils) => {
-            let packed = pack_bits(&coils);
-            let mut data = vec![packed.len() as u8];
-            data.extend_from_slice(&packed);
-            ModbusResponse::build_response(request, request.function_code, &data)
-        }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
-    }
+/// Реакция на фрейм с недопустимой длиной MBAP по политике профиля.
+fn illegal_length_outcome(
+    context: &ConnectionContext,
+    illegal: &IllegalLength,
+    client_addr: &str,
+) -> FrameOutcome {
+    let policy = context.strictness.illegal_length;
+    let (outcome, action) = match policy {
+        IllegalLengthPolicy::Close => (FrameOutcome::Close, "соединение закрыто"),
+        IllegalLengthPolicy::Ignore => (FrameOutcome::Rejected, "фрейм отброшен"),
+        IllegalLengthPolicy::Exception => match illegal.exception_response() {
+            Some(response) => (FrameOutcome::Response(response), "ответ Illegal Data Value"),
+            None => (FrameOutcome::Rejected, "фрейм без кода функции отброшен"),
+        },
+    };
+    log::warn!(
+        "Недопустимая длина MBAP от {}: заявлено {}, получено {} байт",
+        client_addr,
+        illegal.declared,
+        illegal.received
+    );
+    emit_log_entry(
+        &context.app_handle,
+        &context.traffic_log,
+        LogEntry::new(
+            context.log_counter.fetch_add(1, Ordering::SeqCst),
+            LogEntryType::Error,
+            client_addr.to_string(),
+            format!(
+                "Недопустимая длина MBAP: заявлено {}, получено {} байт — {}",
+                illegal.declared, illegal.received, action
+            ),
+        )
+        .with_raw_data(&illegal.frame),
+    );
+    outcome
 }
 
-/// Обработать Read Discrete Inputs (0x02).
-fn handle_read_discrete_inputs(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let read_req = match ReadRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
-                request,
-                request.function_code,
-                ExceptionCode::IllegalDataValue,
-            );
-        }
+/// Ответ на запрос записи по политике шлюза; `None` — обслужить локально.
+async fn gateway_response(
+    gateway: Option<&Gateway>,
+    request: &ModbusRequest,
+    data_store: &SharedDataStore,
+) -> Option<Vec<u8>> {
+    let gateway = gateway?;
+    let response = match gateway.policy_for_request(request)? {
+        WritePolicy::Local => return None,
+        WritePolicy::Reject => ModbusResponse::build_exception(
+            request,
+            request.function_code,
+            ExceptionCode::IllegalDataAddress,
+        ),
+        WritePolicy::Forward => match gateway.forward(request).await {
+            Ok(data) => {
+                // Write-through: локальная карта видит записанное устройством значение,
+                // адреса без переменных пропускаются
+                let _ = process_request(request, data_store.as_ref());
+                ModbusResponse::build_response(request, request.function_code, &data)
+            }
+            Err(code) => {
+                ModbusResponse::build_response(request, request.function_code | 0x80, &[code])
+            }
+        },
     };
+    Some(response)
+}
 
-    if let Err(e) = read_req.validate_bits() {
-        return ModbusResponse::build_exception(request, request.function_code, e);
+/// Записать в журнал нарушения ограничений скорости изменения.
+fn log_rate_violations(context: &ConnectionContext, check: &RateCheck, client_addr: &str) {
+    for violation in &check.violations {
+        let outcome = match violation.applied {
+            Some(applied) => format!("записано {}", applied),
+            None => "запись отклонена".to_string(),
+        };
+        emit_log_entry(
+            &context.app_handle,
+            &context.traffic_log,
+            LogEntry::new(
+                context.log_counter.fetch_add(1, Ordering::SeqCst),
+                LogEntryType::Warning,
+                client_addr.to_string(),
+                format!(
+                    "Слишком быстрое изменение '{}': {} → {}, {}",
+                    violation.variable_id, violation.from, violation.requested, outcome
+                ),
+            )
+            .with_subsystem(LogSubsystem::Data),
+        );
     }
+}
 
-    match data_store.read_discrete_inputs(read_req.start_address, read_req.quantity) {
-        Ok(inputs) => {
-            let packed = pack_bits(&inputs);
-            let mut data = vec![packed.len() as u8];
-            data.extend_from_slice(&packed);
-            ModbusResponse::build_response(request, request.function_code, &data)
-        }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
+/// Исключение Illegal Data Value, если ограничение скорости отклоняет запись.
+fn rate_limit_rejection(
+    context: &ConnectionContext,
+    request: &ModbusRequest,
+    check: &RateCheck,
+    client_addr: &str,
+) -> Option<Vec<u8>> {
+    if !check.rejected() {
+        return None;
     }
+    log_rate_violations(context, check, client_addr);
+    Some(ModbusResponse::build_exception(
+        request,
+        request.function_code,
+        ExceptionCode::IllegalDataValue,
+    ))
 }
 
-/// Обработать Read Holding Registers (0x03).
-fn handle_read_holding_registers(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let read_req = match ReadRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
+/// Проверить блокировки coils. `Ok` — coils, которые нужно выключить после
+/// записи; `Err` — ответ-исключение на запрещённую запись.
+fn check_interlocks(
+    context: &ConnectionContext,
+    request: &ModbusRequest,
+    client_addr: &str,
+) -> Result<Vec<u16>, Vec<u8>> {
+    context
+        .interlocks
+        .check(request, &context.data_store)
+        .map_err(|group| {
+            emit_log_entry(
+                &context.app_handle,
+                &context.traffic_log,
+                LogEntry::new(
+                    context.log_counter.fetch_add(1, Ordering::SeqCst),
+                    LogEntryType::Warning,
+                    client_addr.to_string(),
+                    format!("Запись отклонена блокировкой группы coils '{}'", group),
+                )
+                .with_subsystem(LogSubsystem::Data),
+            );
+            ModbusResponse::build_exception(
                 request,
                 request.function_code,
                 ExceptionCode::IllegalDataValue,
-            );
-        }
-    };
+            )
+        })
+}
 
-    if let Err(e) = read_req.validate_registers() {
-        return ModbusResponse::build_exception(request, request.function_code, e);
-    }
+/// Проверить защиту паролем и записать попытки в журнал. Возвращает
+/// готовый ответ на ввод пароля или исключение на закрытый диапазон.
+fn check_access(
+    context: &ConnectionContext,
+    request: &ModbusRequest,
+    client_addr: &str,
+) -> Option<Vec<u8>> {
+    let (message, response) =
+        match context
+            .access_lock
+            .check(request, client_addr, Instant::now())?
+        {
+            AccessDecision::Unlock {
+                accepted: true,
+                response,
+            } => ("Доступ разблокирован паролем".to_string(), response),
+            AccessDecision::Unlock {
+                accepted: false,
+                response,
+            } => ("Неверный пароль разблокировки".to_string(), response),
+            AccessDecision::Denied { range, response } => (
+                format!("Обращение к защищённому диапазону '{}' без пароля", range),
+                response,
+            ),
+        };
+    emit_log_entry(
+        &context.app_handle,
+        &context.traffic_log,
+        LogEntry::new(
+            context.log_counter.fetch_add(1, Ordering::SeqCst),
+            LogEntryType::Security,
+            client_addr.to_string(),
+            message,
+        )
+        .with_function(
+            request.function_code,
+            function_code_name(request.function_code),
+        )
+        .with_address_range(request.address_range()),
+    );
+    Some(response)
+}
 
-    match data_store.read_holding_registers(read_req.start_address, read_req.quantity) {
-        Ok(regs) => {
-            let packed = pack_registers(&regs);
-            let mut data = vec![packed.len() as u8];
-            data.extend_from_slice(&packed);
-            ModbusResponse::build_response(request, request.function_code, &data)
-        }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
-    }
+/// Ответ ведомого имитируемого шлюза TCP → RTU; `None` для unit ID сервера.
+async fn serial_response(
+    gateway: Option<&SerialGateway>,
+    request: &ModbusRequest,
+    rng: &SharedRng,
+) -> Option<Vec<u8>> {
+    gateway?.respond(request, rng).await
 }
 
-/// Обработать Read Input Registers (0x04).
-fn handle_read_input_registers(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let read_req = match ReadRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
-                request,
-                request.function_code,
-                ExceptionCode::IllegalDataValue,
+/// Обработать один фрейм запроса: проверки протокола, учёт, журнал и ответ.
+/// Общий путь для сетевых запросов и имитации запросов мастера без сокета.
+async fn handle_frame(
+    context: &ConnectionContext,
+    frame_data: &[u8],
+    client_addr: &str,
+    received_us: u64,
+) -> FrameOutcome {
+    let ConnectionContext {
+        data_store,
+        unit_id,
+        strictness,
+        processing_times,
+        response_overrides,
+        rng,
+        app_handle,
+        log_counter,
+        exception_stats,
+        request_stats,
+        access_map,
+        write_learning,
+        traffic_log,
+        verify_transaction_ids,
+        transaction_ids,
+        runtime_counters,
+        exception_injector,
+        comms_failure,
+        faults,
+        counter_registers,
+        gateway,
+        byte_count_stress,
+        zero_quantity_reads,
+        serial_gateway,
+        client_units,
+        firmware,
+        write_storms,
+        ..
+    } = context;
+    let request_start = Instant::now();
+
+    let mut request = match ModbusRequest::parse_lenient(frame_data) {
+        Ok(request) => request,
+        Err(e) => {
+            log::error!("Не удалось разобрать запрос от {}: {}", client_addr, e);
+            emit_log_entry(
+                app_handle,
+                traffic_log,
+                LogEntry::new(
+                    log_counter.fetch_add(1, Ordering::SeqCst),
+                    LogEntryType::Error,
+                    client_addr.to_string(),
+                    format!("Ошибка разбора запроса: {}", e),
+                )
+                .with_raw_data(frame_data),
             );
+            return FrameOutcome::Malformed(e.to_string());
         }
     };
 
-    if let Err(e) = read_req.validate_registers() {
-        return ModbusResponse::build_exception(request, request.function_code, e);
+    // Клиент с назначенным unit ID обслуживается им, ответ получает исходный Unit ID
+    let requested_unit_id = request.header.unit_id;
+    if let Some(assigned) = client_units.unit_for(client_addr) {
+        request.header.unit_id = assigned;
     }
 
-    match data_store.read_input_registers(read_req.start_address, read_req.quantity) {
-        Ok(regs) => {
-            let packed = pack_registers(&regs);
-            let mut data = vec![packed.len() as u8];
-            data.extend_from_slice(&packed);
-            ModbusResponse::build_response(request, request.function_code, &data)
-        }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
+    // Проверяем отклонения от протокола согласно политике профиля
+    let mut rejected = false;
+    // Unit ID ведомых шлюза не считаются чужими: на них отвечает шлюз
+    let deviations = find_deviations(&request, *unit_id)
+        .into_iter()
+        .filter(|deviation| {
+            !(serial_gateway.is_some() && matches!(deviation, Deviation::UnknownUnitId(_)))
+        });
+    for deviation in deviations {
+        let policy = strictness.policy_for(&deviation);
+        let (entry_type, action) = match policy {
+            DeviationPolicy::Reject => (LogEntryType::Error, "запрос отклонён"),
+            DeviationPolicy::Tolerate => (LogEntryType::Warning, "допущено"),
+        };
+        emit_log_entry(
+            app_handle,
+            traffic_log,
+            LogEntry::new(
+                log_counter.fetch_add(1, Ordering::SeqCst),
+                entry_type,
+                client_addr.to_string(),
+                format!(
+                    "Отклонение от протокола: {} — {}",
+                    deviation.describe(),
+                    action
+                ),
+            )
+            .with_raw_data(frame_data),
+        );
+        rejected |= policy == DeviationPolicy::Reject;
+    }
+    if rejected {
+        return FrameOutcome::Rejected;
     }
-}
 
-/// Обработать Write Single Coil (0x05).
-fn handle_write_single_coil(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let write_req = match WriteSingleCoilRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
-                request,
-                request.function_code,
-                ExceptionCode::IllegalDataValue,
-            );
-        }
-    };
+    // Отмечаем адреса обращения для карты трафика
+    access_map.record(&request);
 
-    match data_store.write_single_coil(write_req.address, write_req.value) {
-        Ok(()) => {
-            // Эхо данных запроса в ответ
-            ModbusResponse::build_response(request, request.function_code, &request.data)
-        }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
+    // Служебные счётчики учитывают и текущий запрос
+    runtime_counters.record_request();
+    if counter_registers.is_enabled() {
+        runtime_counters.publish(data_store, counter_registers);
     }
-}
 
-/// Обработать Write Single Register (0x06).
-fn handle_write_single_register(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let write_req = match WriteSingleRegisterRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
-                request,
-                request.function_code,
-                ExceptionCode::IllegalDataValue,
-            );
-        }
+    // Записи в шторме только считаются, в журнал идёт сводка по его окончании
+    let quiet = write_storms.record(&request, client_addr, request_start);
+
+    // Логируем запрос
+    let func_name = function_code_name(request.function_code);
+    let request_summary = format_request_summary(&request);
+
+    let request_log = LogEntry::new(
+        log_counter.fetch_add(1, Ordering::SeqCst),
+        LogEntryType::Request,
+        client_addr.to_string(),
+        request_summary,
+    )
+    .with_function(request.function_code, func_name)
+    .with_address_range(request.address_range())
+    .with_raw_data(frame_data)
+    .with_frame_time(received_us);
+
+    if !quiet {
+        emit_log_entry(app_handle, traffic_log, request_log);
+    }
+
+    // Обрабатываем запрос
+    // Отказ связи и инжекция исключений (по команде, затем по правилам сбоев) важнее подменённых ответов, те — ведомых шлюза TCP → RTU
+    // и политик записи шлюза
+    let rate_check = context
+        .rate_limiter
+        .evaluate(&request, data_store, Instant::now());
+    let (interlock_clear, interlock_rejection) =
+        match check_interlocks(context, &request, client_addr) {
+            Ok(clear) => (clear, None),
+            Err(response) => (Vec::new(), Some(response)),
+        };
+    let fault = faults.effect(&request, rng);
+    let injected = comms_failure
+        .respond(&request)
+        .or_else(|| exception_injector.respond(&request))
+        .or(fault.exception)
+        .or_else(|| {
+            // Ведомые шлюза TCP → RTU не зависят от прошивки сервера
+            firmware
+                .as_ref()
+                .filter(|_| request.header.unit_id == *unit_id)
+                .and_then(|level| level.respond(&request))
+        })
+        // Пароль проверяется, только если ответ не подменён отказом связи или сбоем
+        .or_else(|| check_access(context, &request, client_addr))
+        .or_else(|| rate_limit_rejection(context, &request, &rate_check, client_addr))
+        .or(interlock_rejection)
+        .or_else(|| response_overrides.respond(&request))
+        .or_else(|| zero_quantity_reads.respond(&request));
+    let mut response = match injected {
+        Some(response) => response,
+        None => match serial_response(serial_gateway.as_deref(), &request, rng).await {
+            Some(response) => response,
+            None => match gateway_response(gateway.as_deref(), &request, data_store).await {
+                Some(response) => {
+                    let denied = gateway
+                        .as_deref()
+                        .and_then(|g| g.policy_for_request(&request))
+                        == Some(WritePolicy::Reject);
+                    if denied {
+                        emit_log_entry(
+                            app_handle,
+                            traffic_log,
+                            LogEntry::new(
+                                log_counter.fetch_add(1, Ordering::SeqCst),
+                                LogEntryType::Security,
+                                client_addr.to_string(),
+                                "Запись в защищённый диапазон шлюза отклонена".to_string(),
+                            )
+                            .with_function(request.function_code, func_name)
+                            .with_address_range(request.address_range()),
+                        );
+                    }
+                    response
+                }
+                None => {
+                    // Обрезка по ограничению скорости касается только своих данных
+                    log_rate_violations(context, &rate_check, client_addr);
+                    rate_check.clamp(&mut request);
+                    let response = if write_learning.is_enabled() {
+                        process_request(&request, &write_learning.model(data_store))
+                    } else {
+                        process_request(&request, data_store.as_ref())
+                    };
+                    // Соседи по группе выключаются и время записи для ограничения
+                    // скорости учитывается только после выполненной записи
+                    if response.get(7).is_some_and(|function| function & 0x80 == 0) {
+                        for &address in &interlock_clear {
+                            let _ = data_store.write_single_coil(address, false);
+                        }
+                        context.rate_limiter.commit(rate_check);
+                    }
+                    response
+                }
+            },
+        },
     };
 
-    match data_store.write_single_register(write_req.address, write_req.value) {
-        Ok(()) => {
-            // Эхо данных запроса в ответ
-            ModbusResponse::build_response(request, request.function_code, &request.data)
-        }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
+    if let Some(unit) = response.get_mut(6) {
+        *unit = requested_unit_id;
     }
-}
 
-/// Обработать Write Multiple Coils (0x0F).
-fn handle_write_multiple_coils(request: &ModbusRequest, data_store: &SharedDataStore) -> Vec<u8> {
-    let write_req = match WriteMultipleCoilsRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
-                request,
-                request.function_code,
-                ExceptionCode::IllegalDataValue,
+    // Проверка эха Transaction ID и намеренная подмена
+    if *verify_transaction_ids {
+        if let Some(actual) = transaction_id::check_echo(&request, &response) {
+            emit_log_entry(
+                app_handle,
+                traffic_log,
+                LogEntry::new(
+                    log_counter.fetch_add(1, Ordering::SeqCst),
+                    LogEntryType::Error,
+                    client_addr.to_string(),
+                    format!(
+                        "Transaction ID ответа {} не совпадает с запросом {}",
+                        actual, request.header.transaction_id
+                    ),
+                ),
             );
         }
-    };
-
-    if let Err(e) = write_req.validate() {
-        return ModbusResponse::build_exception(request, request.function_code, e);
+    }
+    if let Some((byte_count, actual)) = byte_count_stress.apply(&mut response) {
+        emit_log_entry(
+            app_handle,
+            traffic_log,
+            LogEntry::new(
+                log_counter.fetch_add(1, Ordering::SeqCst),
+                LogEntryType::Info,
+                client_addr.to_string(),
+                format!(
+                    "Намеренное несоответствие счётчика байт: {} в поле, {} в кадре",
+                    byte_count, actual
+                ),
+            )
+            .with_subsystem(LogSubsystem::Protocol),
+        );
+    }
+    if let Some((original, injected)) = transaction_ids.apply(&mut response) {
+        emit_log_entry(
+            app_handle,
+            traffic_log,
+            LogEntry::new(
+                log_counter.fetch_add(1, Ordering::SeqCst),
+                LogEntryType::Info,
+                client_addr.to_string(),
+                format!(
+                    "Намеренная подмена Transaction ID: {} → {}",
+                    original, injected
+                ),
+            )
+            .with_subsystem(LogSubsystem::Protocol),
+        );
+    }
+    let processing_time = processing_times.delay_for(request.function_code, rng) + fault.delay;
+    if !processing_time.is_zero() {
+        tokio::time::sleep(processing_time).await;
+    }
+    let duration_us = request_start.elapsed().as_micros() as u64;
+
+    if let Some(rule) = fault.drop {
+        emit_log_entry(
+            app_handle,
+            traffic_log,
+            LogEntry::new(
+                log_counter.fetch_add(1, Ordering::SeqCst),
+                LogEntryType::Info,
+                client_addr.to_string(),
+                format!("Ответ не отправлен по правилу сбоя '{}'", rule),
+            )
+            .with_function(request.function_code, func_name)
+            .with_subsystem(LogSubsystem::Protocol),
+        );
+        return FrameOutcome::Dropped;
     }
 
-    match data_store.write_multiple_coils(write_req.start_address, &write_req.values) {
-        Ok(()) => {
-            let response_data = write_req.to_response_data();
-            ModbusResponse::build_response(request, request.function_code, &response_data)
-        }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
+    // Логируем ответ
+    let response_summary = format_response_summary(&request, &response);
+    let is_error = response.len() > 7 && (response[7] & 0x80) != 0;
+    if is_error && response.len() > 8 {
+        exception_stats.record(&request, response[8], client_addr);
+    }
+    request_stats.record(request.function_code, client_addr, is_error, duration_us);
+
+    let response_log = LogEntry::new(
+        log_counter.fetch_add(1, Ordering::SeqCst),
+        if is_error {
+            LogEntryType::Error
+        } else {
+            LogEntryType::Response
+        },
+        client_addr.to_string(),
+        response_summary,
+    )
+    .with_function(request.function_code, func_name)
+    .with_address_range(request.address_range())
+    .with_raw_data(&response)
+    .with_duration(duration_us)
+    .with_frame_time(frame_clock::now_us());
+
+    if !quiet {
+        emit_log_entry(app_handle, traffic_log, response_log);
     }
-}
 
-/// Обработать Write Multiple Registers (0x10).
-fn handle_write_multiple_registers(
-    request: &ModbusRequest,
-    data_store: &SharedDataStore,
-) -> Vec<u8> {
-    let write_req = match WriteMultipleRegistersRequest::parse(&request.data) {
-        Ok(r) => r,
-        Err(_) => {
-            return ModbusResponse::build_exception(
-                request,
-                request.function_code,
-                ExceptionCode::IllegalDataValue,
-            );
-        }
-    };
+    FrameOutcome::Response(response)
+}
 
-    if let Err(e) = write_req.validate() {
-        return ModbusResponse::build_exception(request, request.function_code, e);
+/// Форматировать краткое описание ответа.
+fn format_response_summary(request: &ModbusRequest, response: &[u8]) -> String {
+    // Проверяем, является ли ответ ошибкой
+    if response.len() > 8 && (response[7] & 0x80) != 0 {
+        let exception_code = response[8];
+        return format!(
+            "Ошибка: {} (0x{:02X})",
+            exception_code_name(exception_code),
+            exception_code
+        );
     }
 
-    match data_store.write_multiple_registers(write_req.start_address, &write_req.values) {
-        Ok(()) => {
-            let response_data = write_req.to_response_data();
-            ModbusResponse::build_response(request, request.function_code, &response_data)
+    match FunctionCode::from_u8(request.function_code) {
+        Some(FunctionCode::ReadCoils) | Some(FunctionCode::ReadDiscreteInputs) => {
+            if response.len() > 8 {
+                let byte_count = response[8] as usize;
+                format!("OK: {} байт данных", byte_count)
+            } else {
+                "OK".to_string()
+            }
+        }
+        Some(FunctionCode::ReadHoldingRegisters) | Some(FunctionCode::ReadInputRegisters) => {
+            if response.len() > 8 {
+                let byte_count = response[8] as usize;
+                format!("OK: {} регистров", byte_count / 2)
+            } else {
+                "OK".to_string()
+            }
         }
-        Err(e) => ModbusResponse::build_exception(request, request.function_code, e),
+        Some(FunctionCode::WriteSingleCoil) => "OK: Coil записан".to_string(),
+        Some(FunctionCode::WriteSingleRegister) => "OK: Регистр записан".to_string(),
+        Some(FunctionCode::WriteMultipleCoils) => "OK: Coils записаны".to_string(),
+        Some(FunctionCode::WriteMultipleRegisters) => "OK: Регистры записаны".to_string(),
+        Some(FunctionCode::MaskWriteRegister) => "OK: Маска применена к регистру".to_string(),
+        Some(FunctionCode::ReadFileRecord) => {
+            if response.len() > 8 {
+                format!("OK: {} байт файловых записей", response[8])
+            } else {
+                "OK".to_string()
+            }
+        }
+        Some(FunctionCode::WriteFileRecord) => "OK: Файловые записи записаны".to_string(),
+        None => "Ответ отправлен".to_string(),
     }
 }
 
@@ -778,3 +2177,171 @@ pub type SharedModbusServer = Arc<ModbusServer>;
 pub fn create_shared_server(data_store: SharedDataStore) -> SharedModbusServer {
     Arc::new(ModbusServer::new(data_store))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::modbus_protocol::ExceptionCode;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+    #[test]
+    fn test_simulated_write_uses_request_path() {
+        tauri::async_runtime::block_on(async {
+            let store = create_shared_data_store();
+            store.load_variables(&[ModbusVariable {
+                name: "Уставка".to_string(),
+                ..ModbusVariable::new(
+                    "setpoint",
+                    ModbusArea::HoldingRegister,
+                    10,
+                    ModbusDataType::Uint32,
+                )
+            }]);
+            let server = create_shared_server(store.clone());
+
+            let response = server
+                .simulate_master_write(FunctionCode::WriteMultipleRegisters as u8, 10, &[1, 2])
+                .await
+                .unwrap();
+            assert_eq!(response.exception_code, None);
+            assert_eq!(
+                store.get_variable("setpoint").unwrap().value,
+                ModbusValue::Number(65538.0)
+            );
+
+            // Неопределённый адрес — то же исключение, что получил бы мастер
+            let response = server
+                .simulate_master_write(FunctionCode::WriteSingleRegister as u8, 20, &[1])
+                .await
+                .unwrap();
+            assert_eq!(
+                response.exception_code,
+                Some(ExceptionCode::IllegalDataAddress as u8)
+            );
+            assert_eq!(server.access_map().observed(&store).len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_udp_replies_per_datagram() {
+        tauri::async_runtime::block_on(async {
+            let store = create_shared_data_store();
+            store.load_variables(&[ModbusVariable {
+                name: String::new(),
+                value: ModbusValue::Number(1234.0),
+                ..ModbusVariable::new(
+                    "hr3",
+                    ModbusArea::HoldingRegister,
+                    3,
+                    ModbusDataType::Uint16,
+                )
+            }]);
+            let server = create_shared_server(store);
+            let port = std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            server.set_config("127.0.0.1".to_string(), port, 1);
+            server.set_udp(true);
+            server.start().await.unwrap();
+
+            let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            client.connect(("127.0.0.1", port)).await.unwrap();
+            let mut reply = [0u8; 64];
+
+            // Чтение одного регистра — ответ отдельной датаграммой
+            client
+                .send(&[
+                    0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x03, 0x00, 0x01,
+                ])
+                .await
+                .unwrap();
+            let n = tokio::time::timeout(Duration::from_secs(2), client.recv(&mut reply))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                reply[..n],
+                [0x00, 0x07, 0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x02, 0x04, 0xD2]
+            );
+
+            // Длина MBAP не совпадает с датаграммой — ответа нет
+            client
+                .send(&[
+                    0x00, 0x08, 0x00, 0x00, 0x00, 0x09, 0x01, 0x03, 0x00, 0x03, 0x00, 0x01,
+                ])
+                .await
+                .unwrap();
+            assert!(
+                tokio::time::timeout(Duration::from_millis(200), client.recv(&mut reply))
+                    .await
+                    .is_err()
+            );
+            server.stop().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_gateway_forwards_writes_by_policy() {
+        use crate::gateway::WritePolicyRange;
+
+        tauri::async_runtime::block_on(async {
+            let register = |address: u16| ModbusVariable {
+                name: String::new(),
+                ..ModbusVariable::new(
+                    format!("hr{}", address),
+                    ModbusArea::HoldingRegister,
+                    address,
+                    ModbusDataType::Uint16,
+                )
+            };
+            let device_store = create_shared_data_store();
+            device_store.load_variables(&[register(5)]);
+            let device = create_shared_server(device_store.clone());
+            let port = std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            device.set_config("127.0.0.1".to_string(), port, 1);
+            device.start().await.unwrap();
+
+            let store = create_shared_data_store();
+            store.load_variables(&[register(5), register(6)]);
+            let gateway = create_shared_server(store.clone());
+            gateway.set_gateway(Some(GatewayConfig {
+                host: "127.0.0.1".to_string(),
+                port,
+                unit_id: 1,
+                timeout_ms: 1000,
+                default_policy: WritePolicy::Reject,
+                ranges: vec![WritePolicyRange {
+                    area: ModbusArea::HoldingRegister,
+                    start: 5,
+                    end: 5,
+                    policy: WritePolicy::Forward,
+                }],
+            }));
+
+            let write = FunctionCode::WriteSingleRegister as u8;
+            let response = gateway
+                .simulate_master_write(write, 5, &[42])
+                .await
+                .unwrap();
+            assert_eq!(response.exception_code, None);
+            assert_eq!(device_store.read_holding_registers(5, 1).unwrap(), vec![42]);
+            assert_eq!(store.read_holding_registers(5, 1).unwrap(), vec![42]);
+
+            let response = gateway.simulate_master_write(write, 6, &[1]).await.unwrap();
+            assert_eq!(
+                response.exception_code,
+                Some(ExceptionCode::IllegalDataAddress as u8)
+            );
+            assert_eq!(store.read_holding_registers(6, 1).unwrap(), vec![0]);
+
+            device.stop().unwrap();
+        });
+    }
+}
@@ -0,0 +1,279 @@
+//! Сравнение сессий обмена.
+//!
+//! Сессия сворачивается в профиль: какие запросы отправлял мастер (функция,
+//! адрес, количество), какие значения записывал по каждому адресу и какие
+//! исключения получал. Профиль можно сохранить как эталон и потом сравнить
+//! с новой записью, чтобы проверить, что новая прошивка мастера ведёт себя
+//! так же, как старая.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::modbus_protocol::{
+    FunctionCode, ModbusRequest, WriteMultipleCoilsRequest, WriteMultipleRegistersRequest,
+    WriteSingleCoilRequest, WriteSingleRegisterRequest,
+};
+use crate::traffic_log::{TrafficLog, TrafficQuery};
+use crate::types::{hex_to_bytes, LogEntry, LogEntryType, ProjectMetadata};
+
+/// Источник сессии для сравнения.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "source", rename_all = "lowercase")]
+pub enum SessionSource {
+    /// Записи журнала обмена, выбранные фильтром (обычно интервал времени и клиент).
+    Traffic(TrafficQuery),
+    /// Ранее сохранённый профиль-эталон.
+    Baseline { path: String },
+}
+
+impl SessionSource {
+    /// Загрузить профиль сессии из журнала обмена или файла эталона.
+    pub fn load(&self, traffic_log: &TrafficLog) -> Result<SessionProfile, String> {
+        match self {
+            SessionSource::Traffic(query) => {
+                Ok(SessionProfile::from_entries(&traffic_log.collect(query)?))
+            }
+            SessionSource::Baseline { path } => {
+                let data = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Не удалось прочитать эталон {}: {}", path, e))?;
+                serde_json::from_str(&data)
+                    .map_err(|e| format!("Некорректный файл эталона {}: {}", path, e))
+            }
+        }
+    }
+}
+
+/// Свёрнутый профиль сессии.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionProfile {
+    /// Количество запросов по шаблону «FC03 @0 x10».
+    pub requests: BTreeMap<String, u64>,
+    /// Различные записанные значения по адресу «coil 5» / «register 100».
+    pub writes: BTreeMap<String, BTreeSet<u16>>,
+    /// Количество исключений по ключу «FC03 0x02».
+    pub exceptions: BTreeMap<String, u64>,
+    pub total_requests: u64,
+}
+
+impl SessionProfile {
+    /// Построить профиль по записям журнала.
+    pub fn from_entries(entries: &[LogEntry]) -> Self {
+        let mut profile = Self::default();
+
+        for entry in entries {
+            let Some(frame) = entry.raw_data.as_deref().and_then(hex_to_bytes) else {
+                continue;
+            };
+            match entry.entry_type {
+                LogEntryType::Request => {
+                    if let Ok(request) = ModbusRequest::parse_lenient(&frame) {
+                        profile.add_request(&request);
+                    }
+                }
+                LogEntryType::Error if frame.len() > 8 && frame[7] & 0x80 != 0 => {
+                    let key = format!("FC{:02X} 0x{:02X}", frame[7] & 0x7F, frame[8]);
+                    *profile.exceptions.entry(key).or_default() += 1;
+                }
+                _ => {}
+            }
+        }
+
+        profile
+    }
+
+    fn add_request(&mut self, request: &ModbusRequest) {
+        self.total_requests += 1;
+        let pattern = match request.address_range() {
+            Some((start, quantity)) => {
+                format!("FC{:02X} @{} x{}", request.function_code, start, quantity)
+            }
+            None => format!("FC{:02X}", request.function_code),
+        };
+        *self.requests.entry(pattern).or_default() += 1;
+
+        let mut write = |area: &str, address: u16, value: u16| {
+            self.writes
+                .entry(format!("{} {}", area, address))
+                .or_default()
+                .insert(value);
+        };
+        match FunctionCode::from_u8(request.function_code) {
+            Some(FunctionCode::WriteSingleCoil) => {
+                if let Ok(req) = WriteSingleCoilRequest::parse(&request.data) {
+                    write("coil", req.address, req.value as u16);
+                }
+            }
+            Some(FunctionCode::WriteSingleRegister) => {
+                if let Ok(req) = WriteSingleRegisterRequest::parse(&request.data) {
+                    write("register", req.address, req.value);
+                }
+            }
+            Some(FunctionCode::WriteMultipleCoils) => {
+                if let Ok(req) = WriteMultipleCoilsRequest::parse(&request.data) {
+                    for (i, value) in req.values.iter().enumerate() {
+                        write(
+                            "coil",
+                            req.start_address.wrapping_add(i as u16),
+                            *value as u16,
+                        );
+                    }
+                }
+            }
+            Some(FunctionCode::WriteMultipleRegisters) => {
+                if let Ok(req) = WriteMultipleRegistersRequest::parse(&request.data) {
+                    for (i, value) in req.values.iter().enumerate() {
+                        write("register", req.start_address.wrapping_add(i as u16), *value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Расхождение счётчика между эталоном и текущей сессией.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountDiff {
+    pub key: String,
+    pub baseline: u64,
+    pub current: u64,
+}
+
+/// Расхождение записанных значений по адресу.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteDiff {
+    pub key: String,
+    pub baseline: Vec<u16>,
+    pub current: Vec<u16>,
+}
+
+/// Отчёт о сравнении двух сессий.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDiffReport {
+    /// Сессии совпадают по шаблонам запросов, записанным значениям и исключениям.
+    pub identical: bool,
+    pub baseline_total: u64,
+    pub current_total: u64,
+    /// Шаблоны запросов, встречающиеся только в одной из сессий
+    /// (количество запросов зависит от длительности записи и не сравнивается).
+    pub request_patterns: Vec<CountDiff>,
+    pub writes: Vec<WriteDiff>,
+    pub exceptions: Vec<CountDiff>,
+    /// Сведения о проекте для заголовка отчёта.
+    #[serde(skip_serializing_if = "ProjectMetadata::is_empty")]
+    pub metadata: ProjectMetadata,
+}
+
+/// Сравнить профиль-эталон с текущим.
+pub fn compare_profiles(baseline: &SessionProfile, current: &SessionProfile) -> SessionDiffReport {
+    let request_patterns: Vec<CountDiff> = count_diffs(&baseline.requests, &current.requests)
+        .into_iter()
+        .filter(|d| d.baseline == 0 || d.current == 0)
+        .collect();
+    let exceptions = count_diffs(&baseline.exceptions, &current.exceptions);
+
+    let keys: BTreeSet<&String> = baseline
+        .writes
+        .keys()
+        .chain(current.writes.keys())
+        .collect();
+    let writes: Vec<WriteDiff> = keys
+        .into_iter()
+        .filter(|key| baseline.writes.get(*key) != current.writes.get(*key))
+        .map(|key| WriteDiff {
+            key: key.clone(),
+            baseline: values_of(&baseline.writes, key),
+            current: values_of(&current.writes, key),
+        })
+        .collect();
+
+    SessionDiffReport {
+        identical: request_patterns.is_empty() && writes.is_empty() && exceptions.is_empty(),
+        baseline_total: baseline.total_requests,
+        current_total: current.total_requests,
+        request_patterns,
+        writes,
+        exceptions,
+        metadata: ProjectMetadata::default(),
+    }
+}
+
+fn count_diffs(
+    baseline: &BTreeMap<String, u64>,
+    current: &BTreeMap<String, u64>,
+) -> Vec<CountDiff> {
+    let keys: BTreeSet<&String> = baseline.keys().chain(current.keys()).collect();
+    keys.into_iter()
+        .map(|key| CountDiff {
+            key: key.clone(),
+            baseline: baseline.get(key).copied().unwrap_or(0),
+            current: current.get(key).copied().unwrap_or(0),
+        })
+        .filter(|d| d.baseline != d.current)
+        .collect()
+}
+
+fn values_of(writes: &BTreeMap<String, BTreeSet<u16>>, key: &str) -> Vec<u16> {
+    writes
+        .get(key)
+        .map(|values| values.iter().copied().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(tid: u8, pdu: &[u8]) -> LogEntry {
+        let mut frame = vec![0x00, tid, 0x00, 0x00, 0x00, pdu.len() as u8 + 1, 0x01];
+        frame.extend_from_slice(pdu);
+        LogEntry::new(
+            tid as u64,
+            LogEntryType::Request,
+            "m".to_string(),
+            String::new(),
+        )
+        .with_raw_data(&frame)
+    }
+
+    #[test]
+    fn test_compare_sessions() {
+        let read = [0x03, 0x00, 0x00, 0x00, 0x0A];
+        let exception = LogEntry::new(9, LogEntryType::Error, "m".to_string(), String::new())
+            .with_raw_data(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x83, 0x02]);
+
+        let baseline = SessionProfile::from_entries(&[
+            request(1, &read),
+            request(2, &read),
+            request(3, &[0x06, 0x00, 0x64, 0x00, 0x01]),
+        ]);
+        // Новая прошивка читает так же (реже), но пишет другое значение и получает исключение
+        let current = SessionProfile::from_entries(&[
+            request(1, &read),
+            request(2, &[0x06, 0x00, 0x64, 0x00, 0x02]),
+            exception,
+        ]);
+        assert_eq!(baseline.requests["FC03 @0 x10"], 2);
+
+        let report = compare_profiles(&baseline, &current);
+        assert!(!report.identical);
+        assert!(report.request_patterns.is_empty());
+        assert_eq!(report.writes.len(), 1);
+        assert_eq!(report.writes[0].key, "register 100");
+        assert_eq!(
+            (
+                report.writes[0].baseline.clone(),
+                report.writes[0].current.clone()
+            ),
+            (vec![1], vec![2])
+        );
+        assert_eq!(report.exceptions[0].key, "FC03 0x02");
+
+        assert!(compare_profiles(&baseline, &baseline).identical);
+    }
+}
@@ -0,0 +1,369 @@
+//! Настройки приложения, сохраняемые между запусками.
+//!
+//! Настройки хранятся в JSON-файле рядом с исполняемым файлом приложения.
+//! Кроме списка недавних проектов это предпочтения пользователя
+//! ([`Preferences`]): порты по умолчанию, срок хранения журнала обмена,
+//! каталог данных, язык и согласие на телеметрию. Без них каждый запуск
+//! начинался бы с зашитых значений.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Имя файла настроек.
+const SETTINGS_FILE_NAME: &str = "modbus_settings.json";
+
+/// Максимальное количество незакреплённых недавних проектов.
+const MAX_RECENT_PROJECTS: usize = 10;
+
+/// Файл проекта по умолчанию в каталоге данных.
+const DEFAULT_PROJECT_FILE_NAME: &str = "modbus_project.json";
+
+/// Недавно открытый проект.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentProject {
+    /// Полный путь к файлу проекта.
+    pub path: String,
+    /// Закреплён ли проект (закреплённые не вытесняются из списка).
+    #[serde(default)]
+    pub pinned: bool,
+    /// Время последнего открытия (секунды с эпохи Unix).
+    pub last_opened: u64,
+}
+
+/// Предпочтения пользователя.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Preferences {
+    /// Порт Modbus TCP нового профиля подключения.
+    pub default_port: u16,
+    /// Порт HTTP-доступа к карте регистров.
+    pub map_server_port: u16,
+    /// Сколько дней хранить журнал обмена; 0 — без ограничения.
+    pub log_retention_days: u32,
+    /// Каталог проекта по умолчанию и журнала обмена; `None` — каталог приложения.
+    pub data_dir: Option<String>,
+    /// Язык интерфейса (`ru`, `en`).
+    pub locale: String,
+    /// Согласие на отправку анонимной статистики использования.
+    pub telemetry_opt_in: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            default_port: 502,
+            map_server_port: crate::map_server::DEFAULT_MAP_SERVER_PORT,
+            log_retention_days: 0,
+            data_dir: None,
+            locale: "ru".to_string(),
+            telemetry_opt_in: false,
+        }
+    }
+}
+
+impl Preferences {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.default_port == 0 || self.map_server_port == 0 {
+            return Err("Порт по умолчанию должен быть от 1 до 65535".to_string());
+        }
+        if self.locale.trim().is_empty() {
+            return Err("Язык интерфейса не задан".to_string());
+        }
+        if let Some(dir) = &self.data_dir {
+            if !PathBuf::from(dir).is_dir() {
+                return Err(format!("Каталог данных {} не существует", dir));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Настройки приложения.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSettings {
+    /// Список недавно открытых проектов.
+    #[serde(default)]
+    pub recent_projects: Vec<RecentProject>,
+    /// Предпочтения пользователя.
+    #[serde(default)]
+    pub preferences: Preferences,
+}
+
+impl AppSettings {
+    /// Отметить проект как открытый только что.
+    /// Незакреплённые проекты сверх лимита удаляются (самые старые первыми).
+    pub fn touch_recent_project(&mut self, path: &str, now: u64) {
+        if let Some(entry) = self.recent_projects.iter_mut().find(|p| p.path == path) {
+            entry.last_opened = now;
+        } else {
+            self.recent_projects.push(RecentProject {
+                path: path.to_string(),
+                pinned: false,
+                last_opened: now,
+            });
+        }
+
+        self.sort_recent_projects();
+
+        // Удаляем лишние незакреплённые записи с конца списка
+        let mut unpinned = 0;
+        self.recent_projects.retain(|p| {
+            if p.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= MAX_RECENT_PROJECTS
+        });
+    }
+
+    /// Закрепить или открепить проект.
+    /// Возвращает false, если проекта нет в списке.
+    pub fn set_recent_project_pinned(&mut self, path: &str, pinned: bool) -> bool {
+        match self.recent_projects.iter_mut().find(|p| p.path == path) {
+            Some(entry) => {
+                entry.pinned = pinned;
+                self.sort_recent_projects();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Очистить список недавних проектов.
+    pub fn clear_recent_projects(&mut self, keep_pinned: bool) {
+        if keep_pinned {
+            self.recent_projects.retain(|p| p.pinned);
+        } else {
+            self.recent_projects.clear();
+        }
+    }
+
+    /// Закреплённые проекты первыми, затем по времени открытия (новые первыми).
+    fn sort_recent_projects(&mut self) {
+        self.recent_projects.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then(b.last_opened.cmp(&a.last_opened))
+        });
+    }
+}
+
+/// Потокобезопасное хранилище настроек с сохранением в файл.
+#[derive(Debug)]
+pub struct SettingsStore {
+    /// Путь к файлу настроек (None — настройки только в памяти).
+    path: Option<PathBuf>,
+    settings: RwLock<AppSettings>,
+}
+
+impl SettingsStore {
+    /// Загрузить настройки из файла. При ошибке используются значения по умолчанию.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let settings = path
+            .as_ref()
+            .filter(|p| p.exists())
+            .and_then(|p| match std::fs::read_to_string(p) {
+                Ok(data) => match serde_json::from_str(&data) {
+                    Ok(settings) => Some(settings),
+                    Err(e) => {
+                        log::warn!("Ошибка JSON настроек, используются значения по умолчанию: {e}");
+                        None
+                    }
+                },
+                Err(e) => {
+                    log::warn!("Не удалось прочитать файл настроек: {e}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            settings: RwLock::new(settings),
+        }
+    }
+
+    /// Получить копию текущих настроек.
+    pub fn get(&self) -> AppSettings {
+        self.settings.read().clone()
+    }
+
+    /// Текущие предпочтения.
+    pub fn preferences(&self) -> Preferences {
+        self.settings.read().preferences.clone()
+    }
+
+    /// Каталог данных: из предпочтений или каталог приложения.
+    pub fn data_dir(&self) -> Result<PathBuf, String> {
+        match &self.settings.read().preferences.data_dir {
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => app_dir(),
+        }
+    }
+
+    /// Файл проекта по умолчанию в каталоге данных.
+    pub fn default_project_path(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir()?.join(DEFAULT_PROJECT_FILE_NAME))
+    }
+
+    /// Изменить настройки и сохранить их в файл. Изменения применяются к
+    /// копии и попадают в память только после успешного сохранения.
+    pub fn update<R>(&self, f: impl FnOnce(&mut AppSettings) -> R) -> Result<R, String> {
+        let mut settings = self.settings.write();
+        let mut updated = settings.clone();
+        let result = f(&mut updated);
+        self.save(&updated)?;
+        *settings = updated;
+        Ok(result)
+    }
+
+    fn save(&self, settings: &AppSettings) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let data = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Не удалось сериализовать настройки: {e}"))?;
+        std::fs::write(path, data).map_err(|e| format!("Не удалось записать файл настроек: {e}"))
+    }
+}
+
+/// Каталог приложения (рядом с исполняемым файлом).
+pub fn app_dir() -> Result<PathBuf, String> {
+    let exe_path =
+        std::env::current_exe().map_err(|e| format!("Не удалось получить путь к exe: {e}"))?;
+    let dir = exe_path
+        .parent()
+        .ok_or("Не удалось определить каталог приложения")?;
+    Ok(dir.to_path_buf())
+}
+
+/// Текущее время в секундах с эпохи Unix.
+pub fn unix_time_secs() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Общая ссылка на хранилище настроек.
+pub type SharedSettings = Arc<SettingsStore>;
+
+/// Создать общее хранилище настроек, загрузив его из файла рядом с приложением.
+pub fn create_shared_settings() -> SharedSettings {
+    let path = match app_dir() {
+        Ok(dir) => Some(dir.join(SETTINGS_FILE_NAME)),
+        Err(e) => {
+            log::warn!("Настройки не будут сохраняться: {e}");
+            None
+        }
+    };
+    Arc::new(SettingsStore::load(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_touch_recent_project_moves_to_top() {
+        let mut settings = AppSettings::default();
+        settings.touch_recent_project("a.json", 1);
+        settings.touch_recent_project("b.json", 2);
+        settings.touch_recent_project("a.json", 3);
+
+        let paths: Vec<_> = settings
+            .recent_projects
+            .iter()
+            .map(|p| p.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["a.json", "b.json"]);
+    }
+
+    #[test]
+    fn test_recent_projects_limit_keeps_pinned() {
+        let mut settings = AppSettings::default();
+        settings.touch_recent_project("pinned.json", 0);
+        assert!(settings.set_recent_project_pinned("pinned.json", true));
+
+        for i in 0..(MAX_RECENT_PROJECTS as u64 + 5) {
+            settings.touch_recent_project(&format!("{i}.json"), i + 1);
+        }
+
+        // Закреплённый проект не вытесняется и остаётся первым
+        assert_eq!(settings.recent_projects[0].path, "pinned.json");
+        assert_eq!(settings.recent_projects.len(), MAX_RECENT_PROJECTS + 1);
+    }
+
+    #[test]
+    fn test_preferences_defaults_and_persistence() {
+        // Файл настроек прежних версий без предпочтений
+        let settings: AppSettings = serde_json::from_str(r#"{"recentProjects": []}"#).unwrap();
+        assert_eq!(settings.preferences, Preferences::default());
+        let partial: Preferences = serde_json::from_str(r#"{"defaultPort": 1502}"#).unwrap();
+        assert_eq!(
+            (partial.default_port, partial.locale.as_str()),
+            (1502, "ru")
+        );
+
+        let dir = std::env::temp_dir().join(format!("modbus_prefs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SETTINGS_FILE_NAME);
+        let store = SettingsStore::load(Some(path.clone()));
+        store
+            .update(|s| {
+                s.preferences.log_retention_days = 7;
+                s.preferences.data_dir = Some(dir.to_string_lossy().to_string());
+            })
+            .unwrap();
+        let reloaded = SettingsStore::load(Some(path));
+        assert_eq!(reloaded.preferences().log_retention_days, 7);
+        assert_eq!(
+            reloaded.default_project_path().unwrap(),
+            dir.join(DEFAULT_PROJECT_FILE_NAME)
+        );
+        assert!(reloaded.preferences().validate().is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let invalid = Preferences {
+            default_port: 0,
+            ..Preferences::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_failed_save_keeps_previous_settings() {
+        // Путь к каталогу: запись файла настроек завершится ошибкой
+        let dir = std::env::temp_dir().join(format!("modbus_settings_dir_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = SettingsStore::load(Some(dir.clone()));
+
+        let result = store.update(|s| s.preferences.log_retention_days = 7);
+        assert!(result.is_err());
+        assert_eq!(
+            store.preferences().log_retention_days,
+            Preferences::default().log_retention_days
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_clear_recent_projects() {
+        let mut settings = AppSettings::default();
+        settings.touch_recent_project("a.json", 1);
+        settings.touch_recent_project("b.json", 2);
+        settings.set_recent_project_pinned("a.json", true);
+
+        settings.clear_recent_projects(true);
+        assert_eq!(settings.recent_projects.len(), 1);
+
+        settings.clear_recent_projects(false);
+        assert!(settings.recent_projects.is_empty());
+    }
+}
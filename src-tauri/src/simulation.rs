@@ -0,0 +1,694 @@
+//! Движок симуляции поведения устройства.
+//!
+//! Фоновая задача с фиксированным периодом выполняет настроенные поведения
+//! (обмен команда/статус, пороговая автоматика, расписания, сердцебиение,
+//! генераторы значений, временные подмены) поверх хранилища данных, вычисляет
+//! условия тревог, триггеров событий UI и качество данных переменных. Поведения адресуют переменные по ID и хранятся в файле проекта.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::alarms::AlarmManager;
+use crate::data_store::SharedDataStore;
+use crate::generator::{GeneratorConfig, GeneratorState, GeneratorStatus};
+use crate::handshake::{HandshakeConfig, HandshakeState};
+use crate::heartbeat::{HeartbeatConfig, HeartbeatState};
+use crate::quality::QualityManager;
+use crate::schedule::{ScheduleConfig, ScheduleState};
+use crate::sensor_fault::SensorFaultManager;
+use crate::server::SharedModbusServer;
+use crate::threshold::{ThresholdConfig, ThresholdState};
+use crate::tick_stats::{TickSample, TickStats, TickStatsSnapshot};
+use crate::triggers::TriggerManager;
+use crate::types::{LogEntryType, ModbusProject, ModbusValue, ModbusVariable};
+use crate::value_override::ValueOverrideManager;
+
+/// Период такта симуляции по умолчанию, мс.
+const DEFAULT_TICK_INTERVAL_MS: u64 = 100;
+
+/// Допустимый период такта, мс: от быстрых демонстраций до длительных испытаний.
+const TICK_INTERVAL_RANGE_MS: std::ops::RangeInclusive<u64> = 50..=1000;
+
+/// Период такта для проектов без настройки.
+pub fn default_tick_interval_ms() -> u64 {
+    DEFAULT_TICK_INTERVAL_MS
+}
+
+/// Название события об изменении списка активных тревог.
+const ALARMS_CHANGED_EVENT_NAME: &str = "alarms-changed";
+
+/// Название события об изменении качества данных переменных.
+const QUALITY_CHANGED_EVENT_NAME: &str = "quality-changed";
+
+/// Вид поведения и его настройки.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BehaviorKind {
+    Handshake(HandshakeConfig),
+    Threshold(ThresholdConfig),
+    Schedule(ScheduleConfig),
+    Heartbeat(HeartbeatConfig),
+    Generator(GeneratorConfig),
+}
+
+/// Настроенное поведение симуляции.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Behavior {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub kind: BehaviorKind,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Состояние выполнения поведения между тактами.
+#[derive(Debug)]
+enum BehaviorState {
+    Handshake(HandshakeState),
+    Threshold(ThresholdState),
+    Schedule(ScheduleState),
+    Heartbeat(HeartbeatState),
+    Generator(GeneratorState),
+}
+
+impl BehaviorState {
+    fn new(kind: &BehaviorKind) -> Self {
+        match kind {
+            BehaviorKind::Handshake(_) => BehaviorState::Handshake(HandshakeState::default()),
+            BehaviorKind::Threshold(_) => BehaviorState::Threshold(ThresholdState::default()),
+            BehaviorKind::Schedule(_) => BehaviorState::Schedule(ScheduleState::default()),
+            BehaviorKind::Heartbeat(_) => BehaviorState::Heartbeat(HeartbeatState::default()),
+            BehaviorKind::Generator(_) => BehaviorState::Generator(GeneratorState::default()),
+        }
+    }
+}
+
+/// Движок симуляции.
+pub struct SimulationEngine {
+    data_store: SharedDataStore,
+    /// Сервер — для записей в лог обмена.
+    server: SharedModbusServer,
+    behaviors: RwLock<Vec<Behavior>>,
+    states: Mutex<HashMap<String, BehaviorState>>,
+    alarms: AlarmManager,
+    triggers: TriggerManager,
+    quality: QualityManager,
+    sensor_faults: SensorFaultManager,
+    value_overrides: ValueOverrideManager,
+    /// Диагностика тактов.
+    tick_stats: Mutex<TickStats>,
+    /// Начало предыдущего такта.
+    last_tick: Mutex<Option<Instant>>,
+    /// Период такта, мс.
+    tick_interval_ms: AtomicU64,
+    app_handle: RwLock<Option<AppHandle>>,
+    running: AtomicBool,
+    next_id: AtomicU64,
+}
+
+impl SimulationEngine {
+    pub fn new(data_store: SharedDataStore, server: SharedModbusServer) -> Self {
+        Self {
+            data_store,
+            server,
+            behaviors: RwLock::new(Vec::new()),
+            states: Mutex::new(HashMap::new()),
+            alarms: AlarmManager::default(),
+            triggers: TriggerManager::default(),
+            quality: QualityManager::default(),
+            sensor_faults: SensorFaultManager::default(),
+            value_overrides: ValueOverrideManager::default(),
+            tick_stats: Mutex::new(TickStats::new(Duration::from_millis(
+                DEFAULT_TICK_INTERVAL_MS,
+            ))),
+            last_tick: Mutex::new(None),
+            tick_interval_ms: AtomicU64::new(DEFAULT_TICK_INTERVAL_MS),
+            app_handle: RwLock::new(None),
+            running: AtomicBool::new(false),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Установить AppHandle для отправки событий в UI.
+    pub fn set_app_handle(&self, handle: AppHandle) {
+        *self.app_handle.write() = Some(handle);
+    }
+
+    /// Тревоги, вычисляемые движком.
+    pub fn alarms(&self) -> &AlarmManager {
+        &self.alarms
+    }
+
+    /// Триггеры событий UI, вычисляемые движком.
+    pub fn triggers(&self) -> &TriggerManager {
+        &self.triggers
+    }
+
+    /// Качество данных переменных, вычисляемое движком.
+    pub fn quality(&self) -> &QualityManager {
+        &self.quality
+    }
+
+    /// Активные отказы датчиков, поддерживаемые движком.
+    pub fn sensor_faults(&self) -> &SensorFaultManager {
+        &self.sensor_faults
+    }
+
+    /// Временные подмены значений.
+    pub fn value_overrides(&self) -> &ValueOverrideManager {
+        &self.value_overrides
+    }
+
+    /// Статистика тактов симуляции.
+    pub fn tick_stats(&self) -> TickStatsSnapshot {
+        self.tick_stats.lock().snapshot()
+    }
+
+    /// Начать сбор статистики тактов заново.
+    pub fn reset_tick_stats(&self) {
+        self.tick_stats.lock().reset();
+    }
+
+    /// Период такта симуляции.
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_millis(self.tick_interval_ms.load(Ordering::SeqCst))
+    }
+
+    /// Задать период такта (действует со следующего такта). Статистика тактов
+    /// начинается заново с новым номинальным интервалом.
+    pub fn set_tick_interval(&self, interval_ms: u64) -> Result<(), String> {
+        if !TICK_INTERVAL_RANGE_MS.contains(&interval_ms) {
+            return Err(format!(
+                "Период такта {} мс вне диапазона {}–{} мс",
+                interval_ms,
+                TICK_INTERVAL_RANGE_MS.start(),
+                TICK_INTERVAL_RANGE_MS.end()
+            ));
+        }
+        if self.tick_interval_ms.swap(interval_ms, Ordering::SeqCst) != interval_ms {
+            *self.tick_stats.lock() = TickStats::new(Duration::from_millis(interval_ms));
+            *self.last_tick.lock() = None;
+        }
+        Ok(())
+    }
+
+    /// Запустить фоновый цикл симуляции (повторный вызов ничего не делает).
+    pub fn start(self: &Arc<Self>) {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let engine = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut period = engine.tick_interval();
+            let mut interval = tokio::time::interval(period);
+            while engine.running.load(Ordering::SeqCst) {
+                interval.tick().await;
+                engine.tick(Instant::now());
+                if engine.tick_interval() != period {
+                    period = engine.tick_interval();
+                    interval = tokio::time::interval(period);
+                    interval.reset();
+                }
+            }
+        });
+    }
+
+    /// Выполнить один такт: все включённые поведения, временные подмены и
+    /// отказы датчиков, затем тревоги, триггеры и качество. Время такта учитывается в статистике.
+    pub fn tick(&self, now: Instant) {
+        let started = Instant::now();
+        let interval = self
+            .last_tick
+            .lock()
+            .replace(now)
+            .map(|last| now.duration_since(last));
+
+        let slowest = self.run_behaviors(now);
+        let behaviors = started.elapsed();
+        self.value_overrides
+            .apply(&self.data_store, now, |id| self.has_running_generator(id));
+        self.sensor_faults.apply(&self.data_store, now);
+        self.evaluate_conditions(now);
+
+        let total = started.elapsed();
+        let sample = TickSample {
+            interval,
+            behaviors,
+            total,
+            slowest: slowest
+                .as_ref()
+                .map(|(id, duration)| (id.as_str(), *duration)),
+        };
+        if self.tick_stats.lock().record(sample) {
+            let culprit = slowest
+                .map(|(id, duration)| {
+                    format!(
+                        "; дольше всех поведение '{}' — {:.1} мс",
+                        id,
+                        duration.as_secs_f64() * 1000.0
+                    )
+                })
+                .unwrap_or_default();
+            let message = format!(
+                "Такт симуляции занял {:.1} мс при интервале {} мс{}",
+                total.as_secs_f64() * 1000.0,
+                self.tick_interval().as_millis(),
+                culprit
+            );
+            log::warn!("{}", message);
+            self.server.log_simulation(LogEntryType::Warning, &message);
+        }
+    }
+
+    /// Вычислить тревоги, триггеры и качество по текущим значениям.
+    fn evaluate_conditions(&self, now: Instant) {
+        if self.alarms.is_empty() && self.triggers.is_empty() && self.quality.is_empty() {
+            return;
+        }
+        let snapshot = self.data_store.numeric_snapshot();
+
+        if self.quality.evaluate(&self.data_store, &snapshot, now) {
+            self.emit(QUALITY_CHANGED_EVENT_NAME, self.quality.statuses());
+        }
+
+        if self.alarms.evaluate(&self.data_store, &snapshot) {
+            self.emit(ALARMS_CHANGED_EVENT_NAME, self.alarms.active_alarms());
+        }
+
+        for fired in self.triggers.evaluate(&snapshot) {
+            if fired.log {
+                let message = format!("Событие '{}': {}", fired.event.name, fired.event.message);
+                log::info!("{}", message);
+                self.server.log_simulation(LogEntryType::Info, &message);
+            }
+            self.emit(&fired.event_name, fired.event);
+        }
+    }
+
+    fn emit<T: Serialize + Clone>(&self, event: &str, payload: T) {
+        if let Some(handle) = self.app_handle.read().as_ref() {
+            if let Err(e) = handle.emit(event, payload) {
+                log::warn!("Не удалось отправить событие {}: {}", event, e);
+            }
+        }
+    }
+
+    /// Выполнить включённые поведения. Возвращает самое долгое из них.
+    fn run_behaviors(&self, now: Instant) -> Option<(String, Duration)> {
+        let behaviors = self.behaviors.read();
+        let mut states = self.states.lock();
+        let mut slowest: Option<(&str, Duration)> = None;
+
+        for behavior in behaviors.iter().filter(|b| b.enabled) {
+            let started = Instant::now();
+            let state = states
+                .entry(behavior.id.clone())
+                .or_insert_with(|| BehaviorState::new(&behavior.kind));
+
+            match (&behavior.kind, state) {
+                (BehaviorKind::Handshake(config), BehaviorState::Handshake(state)) => {
+                    self.run_handshake(config, state, now)
+                }
+                (BehaviorKind::Threshold(config), BehaviorState::Threshold(state)) => {
+                    self.run_threshold(config, state)
+                }
+                (BehaviorKind::Schedule(config), BehaviorState::Schedule(state)) => {
+                    self.run_schedule(config, state)
+                }
+                (BehaviorKind::Heartbeat(config), BehaviorState::Heartbeat(state)) => {
+                    self.run_heartbeat(config, state, now)
+                }
+                (BehaviorKind::Generator(config), BehaviorState::Generator(state)) => {
+                    self.run_generator(config, state, now)
+                }
+                // Состояние сбрасывается при замене поведения, поэтому виды всегда совпадают
+                _ => {}
+            }
+
+            let elapsed = started.elapsed();
+            if slowest.is_none_or(|(_, longest)| elapsed > longest) {
+                slowest = Some((&behavior.id, elapsed));
+            }
+        }
+        slowest.map(|(id, duration)| (id.to_string(), duration))
+    }
+
+    fn run_handshake(&self, config: &HandshakeConfig, state: &mut HandshakeState, now: Instant) {
+        let command = match self.read_value(&config.command_id) {
+            Some(value) => value.as_u16(),
+            None => return,
+        };
+
+        if let Some(output) = state.step(config, command, now) {
+            self.data_store
+                .update_simulated(&config.status_id, ModbusValue::Number(output.status as f64));
+            if output.reset_command {
+                self.data_store
+                    .update_simulated(&config.command_id, ModbusValue::Number(0.0));
+            }
+        }
+    }
+
+    fn run_threshold(&self, config: &ThresholdConfig, state: &mut ThresholdState) {
+        if let Some(value) = self.read_value(&config.source_id) {
+            if let Some(output) = state.step(config, value.as_f64()) {
+                self.data_store.set_flag(&config.target_id, output);
+            }
+        }
+    }
+
+    fn run_schedule(&self, config: &ScheduleConfig, state: &mut ScheduleState) {
+        let unix_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        if let Some(value) = state.step(config, unix_secs) {
+            let value = match self.read_value(&config.target_id) {
+                Some(ModbusValue::Bool(_)) => ModbusValue::Bool(value != 0.0),
+                _ => ModbusValue::Number(value),
+            };
+            self.data_store.update_simulated(&config.target_id, value);
+        }
+    }
+
+    fn run_heartbeat(&self, config: &HeartbeatConfig, state: &mut HeartbeatState, now: Instant) {
+        if let Some(current) = self.read_value(&config.target_id) {
+            if let Some(next) = state.step(config, &current, now) {
+                self.data_store.update_simulated(&config.target_id, next);
+            }
+        }
+    }
+
+    fn run_generator(&self, config: &GeneratorConfig, state: &mut GeneratorState, now: Instant) {
+        let Some(current) = self.read_value(&config.target_id) else {
+            return;
+        };
+        if let Some(value) = state.step(config, now, self.server.rng()) {
+            let value = match current {
+                ModbusValue::Bool(_) => ModbusValue::Bool(value != 0.0),
+                _ => ModbusValue::Number(value),
+            };
+            self.data_store.update_simulated(&config.target_id, value);
+        }
+    }
+
+    fn read_value(&self, id: &str) -> Option<ModbusValue> {
+        self.data_store
+            .get_variable_values(&[id.to_string()])
+            .pop()
+            .map(|(_, value)| value)
+    }
+
+    /// Применить настройки симуляции из проекта (период такта, поведения,
+    /// тревоги, триггеры, качество).
+    pub fn apply_project(&self, project: &ModbusProject) {
+        if let Err(e) = self.set_tick_interval(project.simulation_interval_ms) {
+            log::warn!("{}; используется {} мс", e, DEFAULT_TICK_INTERVAL_MS);
+            let _ = self.set_tick_interval(DEFAULT_TICK_INTERVAL_MS);
+        }
+        self.set_behaviors(project.behaviors.clone());
+        self.alarms.set_definitions(project.alarms.clone());
+        self.triggers.set_definitions(project.triggers.clone());
+        self.quality.set_configs(project.quality.clone());
+    }
+
+    /// Записать текущие настройки симуляции в проект перед сохранением.
+    pub fn fill_project(&self, project: &mut ModbusProject) {
+        project.simulation_interval_ms = self.tick_interval().as_millis() as u64;
+        project.behaviors = self.behaviors();
+        project.alarms = self.alarms.definitions();
+        project.triggers = self.triggers.definitions();
+        project.quality = self.quality.configs();
+    }
+
+    /// Список настроенных поведений.
+    pub fn behaviors(&self) -> Vec<Behavior> {
+        self.behaviors.read().clone()
+    }
+
+    /// Заменить все поведения (например, при загрузке проекта).
+    pub fn set_behaviors(&self, behaviors: Vec<Behavior>) {
+        let behaviors: Vec<Behavior> = behaviors.into_iter().map(|b| self.with_id(b)).collect();
+        *self.behaviors.write() = behaviors;
+        self.states.lock().clear();
+    }
+
+    /// Добавить поведение или заменить существующее с тем же ID.
+    /// Состояние выполнения заменённого поведения сбрасывается.
+    pub fn upsert_behavior(&self, behavior: Behavior) -> Result<Behavior, String> {
+        validate_behavior(&behavior)?;
+        let behavior = self.with_id(behavior);
+
+        let mut behaviors = self.behaviors.write();
+        match behaviors.iter_mut().find(|b| b.id == behavior.id) {
+            Some(existing) => *existing = behavior.clone(),
+            None => behaviors.push(behavior.clone()),
+        }
+        self.states.lock().remove(&behavior.id);
+        Ok(behavior)
+    }
+
+    /// Удалить поведение. Возвращает false, если поведение не найдено.
+    pub fn remove_behavior(&self, id: &str) -> bool {
+        let mut behaviors = self.behaviors.write();
+        let before = behaviors.len();
+        behaviors.retain(|b| b.id != id);
+        self.states.lock().remove(id);
+        behaviors.len() != before
+    }
+
+    /// Подключить генератор к переменной во время работы.
+    pub fn attach_generator(&self, config: GeneratorConfig) -> Result<Behavior, String> {
+        config.validate()?;
+        if self.data_store.get_variable(&config.target_id).is_none() {
+            return Err(format!("Переменная '{}' не найдена", config.target_id));
+        }
+        if self
+            .generator_index(&self.behaviors.read(), &config.target_id)
+            .is_some()
+        {
+            return Err(format!(
+                "К переменной '{}' уже подключён генератор",
+                config.target_id
+            ));
+        }
+        self.upsert_behavior(Behavior {
+            id: format!("generator_{}", config.target_id),
+            name: String::new(),
+            enabled: true,
+            kind: BehaviorKind::Generator(config),
+        })
+    }
+
+    /// Изменить параметры генератора переменной. Фаза сигнала сохраняется.
+    pub fn update_generator(&self, config: GeneratorConfig) -> Result<Behavior, String> {
+        config.validate()?;
+        let mut behaviors = self.behaviors.write();
+        let index = self
+            .generator_index(&behaviors, &config.target_id)
+            .ok_or_else(|| format!("У переменной '{}' нет генератора", config.target_id))?;
+        behaviors[index].kind = BehaviorKind::Generator(config);
+        Ok(behaviors[index].clone())
+    }
+
+    /// Приостановить или возобновить генератор переменной.
+    pub fn pause_generator(&self, variable_id: &str, paused: bool) -> Result<Behavior, String> {
+        let mut behaviors = self.behaviors.write();
+        let index = self
+            .generator_index(&behaviors, variable_id)
+            .ok_or_else(|| format!("У переменной '{}' нет генератора", variable_id))?;
+        behaviors[index].enabled = !paused;
+        Ok(behaviors[index].clone())
+    }
+
+    /// Отключить генератор переменной. Возвращает false, если генератора нет.
+    pub fn remove_generator(&self, variable_id: &str) -> bool {
+        let index = self.generator_index(&self.behaviors.read(), variable_id);
+        match index {
+            Some(index) => {
+                let id = self.behaviors.read()[index].id.clone();
+                self.remove_behavior(&id)
+            }
+            None => false,
+        }
+    }
+
+    /// Управляет ли переменной включённый генератор (и переменная не на паузе).
+    pub fn has_running_generator(&self, variable_id: &str) -> bool {
+        if self.data_store.is_simulation_paused(variable_id) {
+            return false;
+        }
+        let behaviors = self.behaviors.read();
+        self.generator_index(&behaviors, variable_id)
+            .is_some_and(|index| behaviors[index].enabled)
+    }
+
+    /// Отметить у переменных подключённые генераторы.
+    pub fn annotate_generators(&self, variables: &mut [ModbusVariable]) {
+        let behaviors = self.behaviors.read();
+        let statuses: HashMap<&str, GeneratorStatus> = behaviors
+            .iter()
+            .filter_map(|behavior| match &behavior.kind {
+                BehaviorKind::Generator(config) => Some((
+                    config.target_id.as_str(),
+                    GeneratorStatus {
+                        behavior_id: behavior.id.clone(),
+                        waveform: config.waveform.clone(),
+                        update_interval_ms: config.update_interval_ms,
+                        paused: !behavior.enabled,
+                    },
+                )),
+                _ => None,
+            })
+            .collect();
+        for var in variables {
+            var.generator = statuses.get(var.id.as_str()).cloned();
+        }
+    }
+
+    fn generator_index(&self, behaviors: &[Behavior], variable_id: &str) -> Option<usize> {
+        behaviors.iter().position(|behavior| {
+            matches!(&behavior.kind, BehaviorKind::Generator(config) if config.target_id == variable_id)
+        })
+    }
+
+    fn with_id(&self, mut behavior: Behavior) -> Behavior {
+        if behavior.id.is_empty() {
+            behavior.id = format!("behavior_{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        }
+        behavior
+    }
+}
+
+/// Проверить настройки поведения.
+fn validate_behavior(behavior: &Behavior) -> Result<(), String> {
+    match &behavior.kind {
+        BehaviorKind::Handshake(config) => {
+            if config.command_id.is_empty() || config.status_id.is_empty() {
+                return Err("Не заданы переменные команды и статуса".to_string());
+            }
+            if config.command_id == config.status_id {
+                return Err("Команда и статус должны быть разными переменными".to_string());
+            }
+        }
+        BehaviorKind::Threshold(config) => config.validate()?,
+        BehaviorKind::Schedule(config) => config.validate()?,
+        BehaviorKind::Heartbeat(config) => config.validate()?,
+        BehaviorKind::Generator(config) => config.validate()?,
+    }
+    Ok(())
+}
+
+/// Общая ссылка на движок симуляции.
+pub type SharedSimulationEngine = Arc<SimulationEngine>;
+
+/// Создать общий движок симуляции.
+pub fn create_shared_simulation_engine(
+    data_store: SharedDataStore,
+    server: SharedModbusServer,
+) -> SharedSimulationEngine {
+    Arc::new(SimulationEngine::new(data_store, server))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::server::create_shared_server;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusVariable};
+
+    #[test]
+    fn test_engine_runs_handshake_on_master_write() {
+        let store = create_shared_data_store();
+        store.load_variables(&[
+            ModbusVariable::new(
+                "cmd",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            ),
+            ModbusVariable::new(
+                "status",
+                ModbusArea::HoldingRegister,
+                1,
+                ModbusDataType::Uint16,
+            ),
+        ]);
+        let engine = SimulationEngine::new(store.clone(), create_shared_server(store.clone()));
+
+        let behavior = engine
+            .upsert_behavior(Behavior {
+                id: String::new(),
+                name: "valve".to_string(),
+                enabled: true,
+                kind: BehaviorKind::Handshake(HandshakeConfig {
+                    command_id: "cmd".to_string(),
+                    status_id: "status".to_string(),
+                    delay_ms: 0,
+                    reset_command: true,
+                    ..HandshakeConfig::default()
+                }),
+            })
+            .unwrap();
+        assert!(!behavior.id.is_empty());
+
+        // Мастер записывает команду
+        store.write_single_register(0, 7).unwrap();
+        let now = Instant::now();
+        engine.tick(now);
+        assert_eq!(store.read_holding_registers(1, 1).unwrap(), vec![1]);
+        engine.tick(now);
+        assert_eq!(store.read_holding_registers(0, 2).unwrap(), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_behavior_json_format() {
+        let json = r#"{"id":"h1","kind":"handshake","commandId":"a","statusId":"b"}"#;
+        let behavior: Behavior = serde_json::from_str(json).unwrap();
+        assert!(behavior.enabled);
+        let BehaviorKind::Handshake(config) = behavior.kind else {
+            panic!("ожидается handshake");
+        };
+        assert_eq!(config.busy_status, 1);
+        assert_eq!(config.delay_ms, 1000);
+
+        let json = r#"{"kind":"threshold","sourceId":"t","targetId":"c","setThreshold":80,"clearThreshold":75}"#;
+        let behavior: Behavior = serde_json::from_str(json).unwrap();
+        assert!(matches!(behavior.kind, BehaviorKind::Threshold(_)));
+    }
+
+    #[test]
+    fn test_tick_interval_persists_in_project() {
+        let store = create_shared_data_store();
+        let engine = SimulationEngine::new(store.clone(), create_shared_server(store));
+        assert!(engine.set_tick_interval(20).is_err());
+        engine.set_tick_interval(500).unwrap();
+        assert_eq!(engine.tick_stats().interval_ms, 500.0);
+
+        let mut project = ModbusProject::default();
+        engine.fill_project(&mut project);
+        assert_eq!(project.simulation_interval_ms, 500);
+
+        // Проект без настройки получает период по умолчанию
+        let project: ModbusProject =
+            serde_json::from_str(r#"{"profiles":[],"currentProfileId":null,"variables":[]}"#)
+                .unwrap();
+        engine.apply_project(&project);
+        assert_eq!(engine.tick_interval(), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,255 @@
+//! Периодические снимки хранилища данных.
+//!
+//! Во время длительных прогонов хранилище сохраняется в памяти через заданный
+//! интервал: переменные с текущими значениями, ненулевые участки областей и
+//! форсирование. Хранятся только последние `keep` снимков. После аномалии
+//! хранилище можно откатить к любому из них, не останавливая сервер.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::{ForcedVariable, SharedDataStore};
+use crate::state_snapshot::{area_segments, AreaSegment};
+use crate::types::{chrono_now_iso, ModbusArea, ModbusVariable};
+
+/// Все области хранилища.
+const AREAS: [ModbusArea; 4] = [
+    ModbusArea::Coil,
+    ModbusArea::DiscreteInput,
+    ModbusArea::InputRegister,
+    ModbusArea::HoldingRegister,
+];
+
+/// Настройки расписания снимков.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotScheduleConfig {
+    /// Интервал между снимками, секунды.
+    pub interval_secs: u64,
+    /// Сколько последних снимков хранить.
+    pub keep: usize,
+}
+
+impl SnapshotScheduleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_secs == 0 {
+            return Err("Интервал снимков должен быть больше нуля".to_string());
+        }
+        if self.keep == 0 {
+            return Err("Нужно хранить хотя бы один снимок".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Снимок хранилища.
+#[derive(Debug, Clone)]
+struct StoreSnapshot {
+    id: u64,
+    taken_at: String,
+    variables: Vec<ModbusVariable>,
+    areas: Vec<AreaSegment>,
+    forces: Vec<ForcedVariable>,
+}
+
+/// Сведения о снимке для UI.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    pub id: u64,
+    pub taken_at: String,
+    pub variable_count: usize,
+}
+
+/// Состояние расписания.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotScheduleStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<SnapshotScheduleConfig>,
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+/// Планировщик снимков.
+pub struct SnapshotScheduler {
+    data_store: SharedDataStore,
+    config: RwLock<Option<SnapshotScheduleConfig>>,
+    snapshots: Mutex<VecDeque<StoreSnapshot>>,
+    next_id: AtomicU64,
+    /// Поколение расписания: задача снимков завершается при его смене.
+    generation: AtomicU64,
+}
+
+impl SnapshotScheduler {
+    pub fn new(data_store: SharedDataStore) -> Self {
+        Self {
+            data_store,
+            config: RwLock::new(None),
+            snapshots: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Включить расписание. Предыдущее расписание прекращается,
+    /// накопленные снимки сохраняются (лишние отбрасываются).
+    pub fn start(self: &Arc<Self>, config: SnapshotScheduleConfig) -> Result<(), String> {
+        config.validate()?;
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.config.write() = Some(config);
+        self.prune(config.keep);
+
+        log::info!(
+            "Снимки хранилища каждые {} с, хранится {}",
+            config.interval_secs,
+            config.keep
+        );
+
+        let scheduler = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let period = Duration::from_secs(config.interval_secs);
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                if scheduler.generation.load(Ordering::SeqCst) != generation {
+                    break;
+                }
+                scheduler.take(config.keep);
+            }
+        });
+        Ok(())
+    }
+
+    /// Выключить расписание. Снимки остаются доступными для отката.
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *self.config.write() = None;
+    }
+
+    /// Снять хранилище и оставить не больше `keep` последних снимков.
+    fn take(&self, keep: usize) -> SnapshotInfo {
+        let snapshot = StoreSnapshot {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            taken_at: chrono_now_iso(),
+            variables: self.data_store.get_variables(),
+            areas: AREAS
+                .into_iter()
+                .flat_map(|area| area_segments(&self.data_store, area))
+                .collect(),
+            forces: self.data_store.forced_variables(),
+        };
+        let info = info(&snapshot);
+        self.snapshots.lock().push_back(snapshot);
+        self.prune(keep);
+        log::debug!("Снимок хранилища #{} ({})", info.id, info.taken_at);
+        info
+    }
+
+    fn prune(&self, keep: usize) {
+        let mut snapshots = self.snapshots.lock();
+        while snapshots.len() > keep {
+            snapshots.pop_front();
+        }
+    }
+
+    /// Расписание и снимки от старых к новым.
+    pub fn status(&self) -> SnapshotScheduleStatus {
+        SnapshotScheduleStatus {
+            config: *self.config.read(),
+            snapshots: self.snapshots.lock().iter().map(info).collect(),
+        }
+    }
+
+    /// Откатить хранилище к снимку: переменные, все ячейки областей
+    /// (адреса, ненулевые после снимка, обнуляются) и форсирование.
+    pub fn restore(&self, id: u64) -> Result<SnapshotInfo, String> {
+        let snapshot = self
+            .snapshots
+            .lock()
+            .iter()
+            .find(|snapshot| snapshot.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Снимок #{} не найден", id))?;
+
+        let store = &self.data_store;
+        store.clear_forces();
+        store.load_variables(&snapshot.variables);
+        for area in AREAS {
+            let mut words = vec![0u16; u16::MAX as usize + 1];
+            for segment in snapshot.areas.iter().filter(|s| s.area == area) {
+                let start = segment.start as usize;
+                words[start..start + segment.words.len()].copy_from_slice(&segment.words);
+            }
+            store.restore_area(area, 0, &words);
+        }
+        for forced in &snapshot.forces {
+            store.force_variable(&forced.id, forced.value.clone());
+        }
+        log::info!(
+            "Хранилище откачено к снимку #{} ({})",
+            id,
+            snapshot.taken_at
+        );
+        Ok(info(&snapshot))
+    }
+}
+
+fn info(snapshot: &StoreSnapshot) -> SnapshotInfo {
+    SnapshotInfo {
+        id: snapshot.id,
+        taken_at: snapshot.taken_at.clone(),
+        variable_count: snapshot.variables.len(),
+    }
+}
+
+/// Общая ссылка на планировщик снимков.
+pub type SharedSnapshotScheduler = Arc<SnapshotScheduler>;
+
+/// Создать общий планировщик снимков.
+pub fn create_shared_snapshot_scheduler(data_store: SharedDataStore) -> SharedSnapshotScheduler {
+    Arc::new(SnapshotScheduler::new(data_store))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusDataType, ModbusValue};
+
+    #[test]
+    fn test_keeps_last_snapshots_and_rolls_back() {
+        let store = create_shared_data_store();
+        store.load_variables(&[ModbusVariable {
+            value: ModbusValue::Number(10.0),
+            ..ModbusVariable::new(
+                "level",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            )
+        }]);
+        let scheduler = SnapshotScheduler::new(store.clone());
+        let first = scheduler.take(2);
+        store.update_variable("level", ModbusValue::Number(20.0));
+        scheduler.take(2);
+        scheduler.take(2);
+        assert_eq!(scheduler.status().snapshots.len(), 2);
+        assert!(scheduler.restore(first.id).is_err());
+
+        let kept = scheduler.status().snapshots[0].id;
+        store.update_variable("level", ModbusValue::Number(99.0));
+        store.restore_area(ModbusArea::HoldingRegister, 50, &[5]);
+        scheduler.restore(kept).unwrap();
+        assert_eq!(
+            store.get_variable("level").unwrap().value,
+            ModbusValue::Number(20.0)
+        );
+        assert_eq!(store.dump_area(ModbusArea::HoldingRegister, 50, 1), [0]);
+    }
+}
@@ -0,0 +1,249 @@
+//! Снимок полного состояния приложения в одном файле.
+//!
+//! Снимок фиксирует ситуацию посреди испытаний: профиль сервера и признак
+//! его работы, переменные с текущими значениями, содержимое областей данных
+//! (включая адреса без переменных), настройки симуляции, зерно генератора,
+//! а также все активные вмешательства — форсирование, отказы датчиков,
+//! инжекции исключений и подмены Transaction ID. Загрузка снимка на другой
+//! машине воспроизводит эту ситуацию.
+//!
+//! Внутреннее состояние поведений (фаза обмена команда/статус, фаза
+//! генераторов) и позиция случайной последовательности не переносятся:
+//! поведения продолжают работу с восстановленных значений, последовательность
+//! начинается с зерна заново.
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::{ForcedVariable, SharedDataStore};
+use crate::exception_injection::ExceptionInjection;
+use crate::sensor_fault::SensorFaultStatus;
+use crate::server::ModbusServer;
+use crate::simulation::SimulationEngine;
+use crate::types::{ModbusArea, ModbusConnectionProfile, ModbusProject};
+
+/// Текущая версия формата снимка.
+pub const STATE_SNAPSHOT_VERSION: u32 = 1;
+
+/// Ненулевой участок области данных.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AreaSegment {
+    pub area: ModbusArea,
+    pub start: u16,
+    /// Ячейки подряд; биты — 0/1.
+    pub words: Vec<u16>,
+}
+
+/// Снимок состояния приложения.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSnapshot {
+    pub version: u32,
+    /// Время создания, секунды Unix.
+    pub saved_at: u64,
+    /// Профиль сервера, переменные с текущими значениями, настройки симуляции,
+    /// соглашение об адресации, зерно генератора и сведения о проекте.
+    pub project: ModbusProject,
+    /// Профиль, применённый к серверу (может отличаться от текущего профиля проекта).
+    #[serde(default)]
+    pub server_profile: Option<ModbusConnectionProfile>,
+    pub server_running: bool,
+    /// Ненулевые участки всех областей (нулевые ячейки не записываются).
+    pub areas: Vec<AreaSegment>,
+    #[serde(default)]
+    pub forces: Vec<ForcedVariable>,
+    #[serde(default)]
+    pub sensor_faults: Vec<SensorFaultStatus>,
+    #[serde(default)]
+    pub exception_injection: Option<ExceptionInjection>,
+    #[serde(default)]
+    pub transaction_id_mismatches: u32,
+}
+
+/// Разбить область на ненулевые участки.
+pub fn area_segments(data_store: &SharedDataStore, area: ModbusArea) -> Vec<AreaSegment> {
+    let words = data_store.dump_area(area, 0, u16::MAX as usize + 1);
+    let mut segments: Vec<AreaSegment> = Vec::new();
+    let mut previous_nonzero = false;
+    for (address, &word) in words.iter().enumerate() {
+        if word == 0 {
+            previous_nonzero = false;
+            continue;
+        }
+        match segments.last_mut() {
+            Some(segment) if previous_nonzero => segment.words.push(word),
+            _ => segments.push(AreaSegment {
+                area,
+                start: address as u16,
+                words: vec![word],
+            }),
+        }
+        previous_nonzero = true;
+    }
+    segments
+}
+
+impl StateSnapshot {
+    /// Снять состояние. `project` задаёт профиль и настройки проекта;
+    /// переменные и настройки симуляции берутся из бэкенда.
+    pub fn capture(
+        mut project: ModbusProject,
+        data_store: &SharedDataStore,
+        server: &ModbusServer,
+        simulation: &SimulationEngine,
+        saved_at: u64,
+    ) -> Self {
+        project.variables = data_store.get_variables();
+        project.random_seed = Some(server.rng().seed());
+        project.persist_process_image = false;
+        simulation.fill_project(&mut project);
+
+        let areas = [
+            ModbusArea::Coil,
+            ModbusArea::DiscreteInput,
+            ModbusArea::InputRegister,
+            ModbusArea::HoldingRegister,
+        ]
+        .into_iter()
+        .flat_map(|area| area_segments(data_store, area))
+        .collect();
+
+        Self {
+            version: STATE_SNAPSHOT_VERSION,
+            saved_at,
+            project,
+            server_profile: server.profile(),
+            server_running: server.is_running(),
+            areas,
+            forces: data_store.forced_variables(),
+            sensor_faults: simulation.sensor_faults().statuses(),
+            exception_injection: server.exception_injector().pending(),
+            transaction_id_mismatches: server.pending_transaction_id_mismatches(),
+        }
+    }
+
+    /// Восстановить хранилище, симуляцию и вмешательства. Профиль сервера
+    /// применяется, но сервер не запускается — это решает вызывающий.
+    pub fn restore(
+        &self,
+        data_store: &SharedDataStore,
+        server: &ModbusServer,
+        simulation: &SimulationEngine,
+    ) -> Result<(), String> {
+        if self.version != STATE_SNAPSHOT_VERSION {
+            return Err(format!(
+                "Неподдерживаемая версия снимка: {} (ожидается {})",
+                self.version, STATE_SNAPSHOT_VERSION
+            ));
+        }
+
+        simulation.sensor_faults().clear_all(data_store);
+        data_store.clear_forces();
+        data_store.load_variables(&self.project.variables);
+        for segment in &self.areas {
+            data_store.restore_area(segment.area, segment.start, &segment.words);
+        }
+        for forced in &self.forces {
+            data_store.force_variable(&forced.id, forced.value.clone());
+        }
+        // Отказ запоминает значение «до отказа» в момент назначения
+        for status in &self.sensor_faults {
+            data_store.update_variable(&status.variable_id, status.before.clone());
+            simulation
+                .sensor_faults()
+                .set(data_store, &status.variable_id, status.fault)?;
+        }
+
+        simulation.apply_project(&self.project);
+        if let Some(seed) = self.project.random_seed {
+            server.rng().set_seed(seed);
+        }
+
+        let injector = server.exception_injector();
+        injector.cancel();
+        if let Some(injection) = &self.exception_injection {
+            injector.schedule(injection.clone())?;
+        }
+        server.inject_transaction_id_mismatches(0);
+        if self.transaction_id_mismatches > 0 {
+            server.inject_transaction_id_mismatches(self.transaction_id_mismatches);
+        }
+
+        let profile = self
+            .server_profile
+            .as_ref()
+            .or_else(|| self.project.current_profile());
+        if let Some(profile) = profile {
+            server.apply_profile(profile.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::sensor_fault::SensorFault;
+    use crate::server::create_shared_server;
+    use crate::simulation::create_shared_simulation_engine;
+    use crate::types::{ModbusDataType, ModbusValue, ModbusVariable};
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let store = create_shared_data_store();
+        let server = create_shared_server(store.clone());
+        let simulation = create_shared_simulation_engine(store.clone(), server.clone());
+        store.load_variables(&[
+            ModbusVariable::new(
+                "speed",
+                ModbusArea::HoldingRegister,
+                0,
+                ModbusDataType::Uint16,
+            ),
+            ModbusVariable::new(
+                "level",
+                ModbusArea::HoldingRegister,
+                1,
+                ModbusDataType::Uint16,
+            ),
+        ]);
+        store.update_variable("speed", ModbusValue::Number(1200.0));
+        store.update_variable("level", ModbusValue::Number(40.0));
+        store.restore_area(ModbusArea::HoldingRegister, 100, &[7, 8]);
+        store.force_variable("speed", ModbusValue::Number(1500.0));
+        simulation
+            .sensor_faults()
+            .set(&store, "level", SensorFault::Zero)
+            .unwrap();
+        server.inject_transaction_id_mismatches(3);
+
+        let snapshot =
+            StateSnapshot::capture(ModbusProject::default(), &store, &server, &simulation, 0);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: StateSnapshot = serde_json::from_str(&json).unwrap();
+
+        let store = create_shared_data_store();
+        let server = create_shared_server(store.clone());
+        let simulation = create_shared_simulation_engine(store.clone(), server.clone());
+        snapshot.restore(&store, &server, &simulation).unwrap();
+
+        assert_eq!(
+            store.get_variable("speed").unwrap().value,
+            ModbusValue::Number(1500.0)
+        );
+        assert_eq!(store.forced_variables().len(), 1);
+        assert_eq!(store.dump_area(ModbusArea::HoldingRegister, 100, 2), [7, 8]);
+        assert_eq!(
+            store.get_variable("level").unwrap().value,
+            ModbusValue::Number(0.0)
+        );
+        // После снятия отказа возвращается значение до него
+        simulation.sensor_faults().clear(&store, "level");
+        assert_eq!(
+            store.get_variable("level").unwrap().value,
+            ModbusValue::Number(40.0)
+        );
+        assert_eq!(server.pending_transaction_id_mismatches(), 3);
+    }
+}
@@ -0,0 +1,227 @@
+//! Подписки UI на изменения выбранных переменных.
+//!
+//! Каждая подписка — фоновая задача, которая с заданным периодом опрашивает
+//! хранилище данных и отправляет компактное событие только с изменившимися
+//! переменными из отслеживаемого набора (с учётом зоны нечувствительности).
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::data_store::SharedDataStore;
+use crate::types::ModbusValue;
+
+/// Название события с обновлениями подписки.
+const SUBSCRIPTION_EVENT_NAME: &str = "variable-updates";
+
+/// Минимальный период опроса подписки.
+const MIN_INTERVAL_MS: u64 = 20;
+
+/// Обновление значения одной переменной.
+#[derive(Debug, Clone, Serialize)]
+pub struct VariableUpdate {
+    pub id: String,
+    pub value: ModbusValue,
+}
+
+/// Событие с обновлениями подписки.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionEvent {
+    pub subscription_id: u64,
+    pub updates: Vec<VariableUpdate>,
+}
+
+/// Описание подписки для UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionInfo {
+    pub id: u64,
+    pub ids: Vec<String>,
+    pub interval_ms: u64,
+    pub deadband: f64,
+}
+
+/// Активная подписка.
+struct Subscription {
+    info: SubscriptionInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Менеджер подписок.
+pub struct SubscriptionManager {
+    data_store: SharedDataStore,
+    subscriptions: RwLock<HashMap<u64, Subscription>>,
+    next_id: AtomicU64,
+}
+
+impl SubscriptionManager {
+    pub fn new(data_store: SharedDataStore) -> Self {
+        Self {
+            data_store,
+            subscriptions: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Создать подписку на набор переменных. Возвращает ID подписки.
+    /// Первое событие содержит текущие значения всех переменных набора.
+    pub fn subscribe(
+        &self,
+        app_handle: AppHandle,
+        ids: Vec<String>,
+        interval_ms: u64,
+        deadband: f64,
+    ) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let interval_ms = interval_ms.max(MIN_INTERVAL_MS);
+        let deadband = deadband.max(0.0);
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        self.subscriptions.write().insert(
+            id,
+            Subscription {
+                info: SubscriptionInfo {
+                    id,
+                    ids: ids.clone(),
+                    interval_ms,
+                    deadband,
+                },
+                cancelled: cancelled.clone(),
+            },
+        );
+
+        log::debug!(
+            "Подписка {} на {} переменных, период {} мс",
+            id,
+            ids.len(),
+            interval_ms
+        );
+
+        let data_store = self.data_store.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut last_sent: HashMap<String, ModbusValue> = HashMap::new();
+            let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+
+            while !cancelled.load(Ordering::SeqCst) {
+                interval.tick().await;
+
+                let updates: Vec<VariableUpdate> = data_store
+                    .get_variable_values(&ids)
+                    .into_iter()
+                    .filter(|(var_id, value)| match last_sent.get(var_id) {
+                        Some(old) => value_changed(old, value, deadband),
+                        None => true,
+                    })
+                    .map(|(id, value)| VariableUpdate { id, value })
+                    .collect();
+
+                if updates.is_empty() {
+                    continue;
+                }
+                for update in &updates {
+                    last_sent.insert(update.id.clone(), update.value.clone());
+                }
+
+                let event = SubscriptionEvent {
+                    subscription_id: id,
+                    updates,
+                };
+                if let Err(e) = app_handle.emit(SUBSCRIPTION_EVENT_NAME, &event) {
+                    log::warn!("Не удалось отправить обновления подписки {}: {}", id, e);
+                }
+            }
+        });
+
+        id
+    }
+
+    /// Отменить подписку. Возвращает false, если подписка не найдена.
+    pub fn unsubscribe(&self, id: u64) -> bool {
+        match self.subscriptions.write().remove(&id) {
+            Some(sub) => {
+                sub.cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Список активных подписок.
+    pub fn list(&self) -> Vec<SubscriptionInfo> {
+        let mut list: Vec<_> = self
+            .subscriptions
+            .read()
+            .values()
+            .map(|s| s.info.clone())
+            .collect();
+        list.sort_by_key(|s| s.id);
+        list
+    }
+}
+
+/// Изменилось ли значение с учётом зоны нечувствительности (только для чисел).
+fn value_changed(old: &ModbusValue, new: &ModbusValue, deadband: f64) -> bool {
+    match (old, new) {
+        (ModbusValue::Number(a), ModbusValue::Number(b)) => {
+            if a.is_nan() || b.is_nan() {
+                a.is_nan() != b.is_nan()
+            } else if deadband > 0.0 {
+                (a - b).abs() > deadband
+            } else {
+                a != b
+            }
+        }
+        (ModbusValue::Bool(a), ModbusValue::Bool(b)) => a != b,
+        (ModbusValue::Null, ModbusValue::Null) => false,
+        _ => true,
+    }
+}
+
+/// Общая ссылка на менеджер подписок.
+pub type SharedSubscriptionManager = Arc<SubscriptionManager>;
+
+/// Создать общий менеджер подписок.
+pub fn create_shared_subscription_manager(
+    data_store: SharedDataStore,
+) -> SharedSubscriptionManager {
+    Arc::new(SubscriptionManager::new(data_store))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_changed_with_deadband() {
+        let a = ModbusValue::Number(10.0);
+        assert!(!value_changed(&a, &ModbusValue::Number(10.4), 0.5));
+        assert!(value_changed(&a, &ModbusValue::Number(10.6), 0.5));
+        assert!(value_changed(&a, &ModbusValue::Number(10.1), 0.0));
+        assert!(!value_changed(&a, &ModbusValue::Number(10.0), 0.0));
+    }
+
+    #[test]
+    fn test_value_changed_bool_and_type_switch() {
+        assert!(value_changed(
+            &ModbusValue::Bool(false),
+            &ModbusValue::Bool(true),
+            100.0
+        ));
+        assert!(value_changed(
+            &ModbusValue::Bool(true),
+            &ModbusValue::Number(1.0),
+            100.0
+        ));
+        assert!(!value_changed(
+            &ModbusValue::Number(f64::NAN),
+            &ModbusValue::Number(f64::NAN),
+            0.0
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+//! Пороговая автоматика: установка coil по значению переменной.
+//!
+//! Облегчённая альтернатива сценариям: правило вида «установить coil 12, когда
+//! holding register 40010 > 80, и сбросить, когда < 75». Разница порогов
+//! образует гистерезис, чтобы выход не «дребезжал» около порога.
+
+use serde::{Deserialize, Serialize};
+
+/// Направление срабатывания правила.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThresholdDirection {
+    /// Установка при превышении порога.
+    #[default]
+    Above,
+    /// Установка при снижении ниже порога.
+    Below,
+}
+
+/// Настройка порогового правила.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThresholdConfig {
+    /// ID отслеживаемой переменной.
+    pub source_id: String,
+    /// ID переменной-выхода (обычно coil).
+    pub target_id: String,
+    #[serde(default)]
+    pub direction: ThresholdDirection,
+    /// Порог установки выхода.
+    pub set_threshold: f64,
+    /// Порог сброса выхода.
+    pub clear_threshold: f64,
+}
+
+impl ThresholdConfig {
+    /// Проверить согласованность порогов с направлением.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.source_id.is_empty() || self.target_id.is_empty() {
+            return Err("Не заданы отслеживаемая переменная и выход".to_string());
+        }
+        let consistent = match self.direction {
+            ThresholdDirection::Above => self.clear_threshold <= self.set_threshold,
+            ThresholdDirection::Below => self.clear_threshold >= self.set_threshold,
+        };
+        if !consistent {
+            return Err(format!(
+                "Порог сброса {} несовместим с порогом установки {}",
+                self.clear_threshold, self.set_threshold
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Состояние порогового правила между тактами.
+#[derive(Debug, Default)]
+pub struct ThresholdState {
+    output: Option<bool>,
+}
+
+impl ThresholdState {
+    /// Обработать текущее значение. Возвращает новое состояние выхода,
+    /// если его нужно записать. Внутри зоны гистерезиса выход не меняется
+    /// (при первом вычислении — сброшен).
+    pub fn step(&mut self, config: &ThresholdConfig, value: f64) -> Option<bool> {
+        let (set, clear) = match config.direction {
+            ThresholdDirection::Above => {
+                (value > config.set_threshold, value < config.clear_threshold)
+            }
+            ThresholdDirection::Below => {
+                (value < config.set_threshold, value > config.clear_threshold)
+            }
+        };
+
+        let current = self.output.unwrap_or(false);
+        let next = if set {
+            true
+        } else if clear {
+            false
+        } else {
+            current
+        };
+
+        if self.output == Some(next) {
+            return None;
+        }
+        self.output = Some(next);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(direction: ThresholdDirection, set: f64, clear: f64) -> ThresholdConfig {
+        ThresholdConfig {
+            source_id: "temp".to_string(),
+            target_id: "fan".to_string(),
+            direction,
+            set_threshold: set,
+            clear_threshold: clear,
+        }
+    }
+
+    #[test]
+    fn test_threshold_hysteresis() {
+        let config = config(ThresholdDirection::Above, 80.0, 75.0);
+        let mut state = ThresholdState::default();
+
+        // Первое вычисление всегда записывает выход
+        assert_eq!(state.step(&config, 78.0), Some(false));
+        assert_eq!(state.step(&config, 81.0), Some(true));
+        // Внутри зоны гистерезиса выход держится
+        assert_eq!(state.step(&config, 77.0), None);
+        assert_eq!(state.step(&config, 74.0), Some(false));
+        assert_eq!(state.step(&config, 79.0), None);
+    }
+
+    #[test]
+    fn test_threshold_below_and_validation() {
+        let low = config(ThresholdDirection::Below, 10.0, 12.0);
+        assert!(low.validate().is_ok());
+        let mut state = ThresholdState::default();
+        assert_eq!(state.step(&low, 9.0), Some(true));
+        assert_eq!(state.step(&low, 11.0), None);
+        assert_eq!(state.step(&low, 13.0), Some(false));
+
+        assert!(config(ThresholdDirection::Above, 75.0, 80.0)
+            .validate()
+            .is_err());
+        assert!(config(ThresholdDirection::Below, 12.0, 10.0)
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,174 @@
+//! Диагностика такта движка симуляции.
+//!
+//! Для каждого такта учитываются отклонение фактического интервала от
+//! номинального (джиттер), время выполнения поведений и общее время такта.
+//! Такт дольше номинального интервала считается переполнением: следующий
+//! такт начнётся с опозданием. Самое долгое поведение попадает в статистику
+//! и в предупреждение о переполнении, чтобы тяжёлый проект можно было настроить.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Самое долгое поведение за время сбора статистики.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowBehavior {
+    pub id: String,
+    pub duration_ms: f64,
+}
+
+/// Статистика тактов для UI.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TickStatsSnapshot {
+    /// Номинальный интервал такта, мс.
+    pub interval_ms: f64,
+    pub tick_count: u64,
+    /// Такты дольше номинального интервала.
+    pub overruns: u64,
+    pub last_jitter_ms: f64,
+    pub max_jitter_ms: f64,
+    pub avg_jitter_ms: f64,
+    /// Время выполнения поведений за такт.
+    pub last_behaviors_ms: f64,
+    pub max_behaviors_ms: f64,
+    pub avg_behaviors_ms: f64,
+    /// Общее время такта (поведения, тревоги, триггеры, качество).
+    pub last_tick_ms: f64,
+    pub max_tick_ms: f64,
+    pub slowest_behavior: Option<SlowBehavior>,
+}
+
+/// Замер одного такта.
+#[derive(Debug, Clone, Copy)]
+pub struct TickSample<'a> {
+    /// Фактический интервал от начала предыдущего такта (нет у первого такта).
+    pub interval: Option<Duration>,
+    pub behaviors: Duration,
+    pub total: Duration,
+    /// Самое долгое поведение такта.
+    pub slowest: Option<(&'a str, Duration)>,
+}
+
+/// Накопитель статистики тактов.
+#[derive(Debug)]
+pub struct TickStats {
+    interval: Duration,
+    snapshot: TickStatsSnapshot,
+    jitter_samples: u64,
+    jitter_total_ms: f64,
+    behaviors_total_ms: f64,
+    /// Предыдущий такт был переполнен (предупреждение уже выдано).
+    overrunning: bool,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl TickStats {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            snapshot: TickStatsSnapshot {
+                interval_ms: ms(interval),
+                ..Default::default()
+            },
+            jitter_samples: 0,
+            jitter_total_ms: 0.0,
+            behaviors_total_ms: 0.0,
+            overrunning: false,
+        }
+    }
+
+    /// Учесть такт. Возвращает true, если с этого такта началось переполнение
+    /// (повторно не сообщается, пока такты не уложатся в интервал).
+    pub fn record(&mut self, sample: TickSample) -> bool {
+        let stats = &mut self.snapshot;
+        stats.tick_count += 1;
+
+        if let Some(interval) = sample.interval {
+            let jitter = ms(interval.abs_diff(self.interval));
+            stats.last_jitter_ms = jitter;
+            stats.max_jitter_ms = stats.max_jitter_ms.max(jitter);
+            self.jitter_samples += 1;
+            self.jitter_total_ms += jitter;
+            stats.avg_jitter_ms = self.jitter_total_ms / self.jitter_samples as f64;
+        }
+
+        let behaviors = ms(sample.behaviors);
+        stats.last_behaviors_ms = behaviors;
+        stats.max_behaviors_ms = stats.max_behaviors_ms.max(behaviors);
+        self.behaviors_total_ms += behaviors;
+        stats.avg_behaviors_ms = self.behaviors_total_ms / stats.tick_count as f64;
+
+        let total = ms(sample.total);
+        stats.last_tick_ms = total;
+        stats.max_tick_ms = stats.max_tick_ms.max(total);
+
+        if let Some((id, duration)) = sample.slowest {
+            let duration_ms = ms(duration);
+            if stats
+                .slowest_behavior
+                .as_ref()
+                .is_none_or(|slowest| duration_ms > slowest.duration_ms)
+            {
+                stats.slowest_behavior = Some(SlowBehavior {
+                    id: id.to_string(),
+                    duration_ms,
+                });
+            }
+        }
+
+        let overrun = sample.total > self.interval;
+        if overrun {
+            stats.overruns += 1;
+        }
+        let started = overrun && !self.overrunning;
+        self.overrunning = overrun;
+        started
+    }
+
+    /// Текущая статистика.
+    pub fn snapshot(&self) -> TickStatsSnapshot {
+        self.snapshot.clone()
+    }
+
+    /// Начать сбор статистики заново.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrun_reported_once() {
+        let mut stats = TickStats::new(Duration::from_millis(100));
+        let sample = |interval: Option<u64>, total: u64| TickSample {
+            interval: interval.map(Duration::from_millis),
+            behaviors: Duration::from_millis(total),
+            total: Duration::from_millis(total),
+            slowest: Some(("script", Duration::from_millis(total))),
+        };
+
+        assert!(!stats.record(sample(None, 10)));
+        assert!(stats.record(sample(Some(110), 150)));
+        assert!(!stats.record(sample(Some(150), 160)));
+        assert!(!stats.record(sample(Some(160), 20)));
+        assert!(stats.record(sample(Some(90), 120)));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.tick_count, 5);
+        assert_eq!(snapshot.overruns, 3);
+        assert_eq!(snapshot.max_jitter_ms, 60.0);
+        assert_eq!(snapshot.avg_jitter_ms, 32.5);
+        assert_eq!(snapshot.slowest_behavior.unwrap().duration_ms, 160.0);
+
+        stats.reset();
+        assert_eq!(stats.snapshot().tick_count, 0);
+    }
+}
@@ -0,0 +1,505 @@
+//! Журнал обмена в SQLite с поиском.
+//!
+//! Каждая запись лога обмена сохраняется в базу с индексированными столбцами
+//! (время, клиент, функция, диапазон адресов). Это позволяет анализировать
+//! многочасовые записи прямо в приложении: фильтровать и листать постранично.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{chrono_now_iso, LogEntry, LogEntryType, LogSeverity, LogSubsystem};
+
+/// Имя файла базы журнала обмена (рядом с приложением).
+const TRAFFIC_LOG_FILE_NAME: &str = "traffic_log.sqlite";
+
+/// Размер страницы по умолчанию и максимальный.
+const DEFAULT_PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 1000;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS traffic (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        log_id INTEGER NOT NULL,
+        time_ms INTEGER NOT NULL,
+        entry_type TEXT NOT NULL,
+        client TEXT NOT NULL,
+        function_code INTEGER,
+        function_name TEXT,
+        address_start INTEGER,
+        address_end INTEGER,
+        summary TEXT NOT NULL,
+        raw_data TEXT,
+        duration_us INTEGER,
+        severity INTEGER NOT NULL DEFAULT 1,
+        subsystem TEXT NOT NULL DEFAULT 'protocol',
+        time_us INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS idx_traffic_time ON traffic (time_ms);
+    CREATE INDEX IF NOT EXISTS idx_traffic_client ON traffic (client, time_ms);
+    CREATE INDEX IF NOT EXISTS idx_traffic_function ON traffic (function_code, time_ms);
+    CREATE INDEX IF NOT EXISTS idx_traffic_address ON traffic (address_start, address_end);
+";
+
+/// Столбцы, добавленные после первой версии схемы, для существующих баз.
+const ADDED_COLUMNS: [(&str, &str); 3] = [
+    ("severity", "INTEGER NOT NULL DEFAULT 1"),
+    ("subsystem", "TEXT NOT NULL DEFAULT 'protocol'"),
+    ("time_us", "INTEGER"),
+];
+
+/// Фильтр запроса к журналу. Все условия необязательны и объединяются через И.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrafficQuery {
+    /// Начало интервала, мс с эпохи Unix (включительно).
+    pub from_ms: Option<i64>,
+    /// Конец интервала, мс с эпохи Unix (включительно).
+    pub to_ms: Option<i64>,
+    /// Адрес клиента (точное совпадение или префикс, например IP без порта).
+    pub client: Option<String>,
+    pub function_code: Option<u8>,
+    pub entry_type: Option<LogEntryType>,
+    /// Минимальная важность (включительно).
+    pub min_severity: Option<LogSeverity>,
+    pub subsystem: Option<LogSubsystem>,
+    /// Диапазон адресов: выбираются записи, пересекающиеся с ним.
+    pub address_from: Option<u16>,
+    pub address_to: Option<u16>,
+    /// Подстрока в описании записи.
+    pub text: Option<String>,
+    pub offset: u32,
+    /// Размер страницы (по умолчанию 100, не более 1000).
+    pub limit: Option<u32>,
+}
+
+/// Страница результатов запроса.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficPage {
+    /// Общее количество записей, подходящих под фильтр.
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+    pub entries: Vec<LogEntry>,
+}
+
+/// Журнал обмена. Пока база не открыта, записи не сохраняются.
+#[derive(Default)]
+pub struct TrafficLog {
+    connection: Mutex<Option<Connection>>,
+    /// Записи ниже этой важности сохраняются, но не отправляются в UI.
+    event_min_severity: Mutex<LogSeverity>,
+}
+
+impl TrafficLog {
+    /// Открыть базу журнала (None — база в памяти).
+    pub fn open(&self, path: Option<&Path>) -> Result<(), String> {
+        let connection = match path {
+            Some(path) => Connection::open(path),
+            None => Connection::open_in_memory(),
+        }
+        .map_err(|e| format!("Не удалось открыть журнал обмена: {e}"))?;
+
+        connection
+            .execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .and_then(|_| add_missing_columns(&connection))
+            .map_err(|e| format!("Не удалось создать таблицы журнала обмена: {e}"))?;
+
+        *self.connection.lock() = Some(connection);
+        Ok(())
+    }
+
+    /// Открыть базу журнала в каталоге данных. Ошибка только записывается в лог.
+    pub fn open_default(&self, data_dir: Result<PathBuf, String>) {
+        let result = data_dir.and_then(|dir| self.open(Some(&dir.join(TRAFFIC_LOG_FILE_NAME))));
+        if let Err(e) = result {
+            log::warn!("Журнал обмена не будет сохраняться: {e}");
+        }
+    }
+
+    /// Задать минимальную важность записей, отправляемых в UI.
+    pub fn set_event_min_severity(&self, severity: LogSeverity) {
+        *self.event_min_severity.lock() = severity;
+    }
+
+    /// Отправлять ли запись в UI.
+    pub fn should_emit(&self, entry: &LogEntry) -> bool {
+        entry.severity >= *self.event_min_severity.lock()
+    }
+
+    /// Сохранить запись лога.
+    pub fn record(&self, entry: &LogEntry) {
+        let guard = self.connection.lock();
+        let Some(connection) = guard.as_ref() else {
+            return;
+        };
+
+        let address_end = entry
+            .address
+            .zip(entry.quantity)
+            .map(|(start, quantity)| start as i64 + quantity.max(1) as i64 - 1);
+        let result = connection.execute(
+            "INSERT INTO traffic (log_id, time_ms, entry_type, client, function_code, function_name,
+                address_start, address_end, summary, raw_data, duration_us, severity, subsystem,
+                time_us)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                entry.id as i64,
+                timestamp_to_ms(&entry.timestamp),
+                entry_type_name(&entry.entry_type),
+                entry.client_addr,
+                entry.function_code,
+                entry.function_name,
+                entry.address,
+                address_end,
+                entry.summary,
+                entry.raw_data,
+                entry.duration_us.map(|d| d as i64),
+                entry.severity as i64,
+                subsystem_name(entry.subsystem),
+                entry.frame_time_us.map(|t| t as i64),
+            ],
+        );
+        if let Err(e) = result {
+            log::warn!("Не удалось сохранить запись журнала обмена: {e}");
+        }
+    }
+
+    /// Найти записи по фильтру (новые записи первыми).
+    pub fn query(&self, query: &TrafficQuery) -> Result<TrafficPage, String> {
+        let guard = self.connection.lock();
+        let connection = guard.as_ref().ok_or("Журнал обмена не открыт")?;
+
+        let (condition, values) = build_condition(query);
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let err = |e: rusqlite::Error| format!("Ошибка запроса к журналу обмена: {e}");
+
+        let total: i64 = connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM traffic{condition}"),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(err)?;
+
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT log_id, time_ms, entry_type, client, function_code, function_name,
+                    address_start, address_end, summary, raw_data, duration_us, severity, subsystem,
+                    time_us
+                 FROM traffic{condition} ORDER BY time_ms DESC, id DESC LIMIT {limit} OFFSET {}",
+                query.offset
+            ))
+            .map_err(err)?;
+        let entries = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                let address: Option<u16> = row.get(6)?;
+                let address_end: Option<i64> = row.get(7)?;
+                Ok(LogEntry {
+                    id: row.get::<_, i64>(0)? as u64,
+                    timestamp: ms_to_timestamp(row.get(1)?),
+                    entry_type: parse_entry_type(&row.get::<_, String>(2)?),
+                    severity: parse_severity(row.get(11)?),
+                    subsystem: parse_subsystem(&row.get::<_, String>(12)?),
+                    client_addr: row.get(3)?,
+                    function_code: row.get(4)?,
+                    function_name: row.get(5)?,
+                    address,
+                    quantity: address
+                        .zip(address_end)
+                        .map(|(start, end)| (end - start as i64 + 1) as u16),
+                    summary: row.get(8)?,
+                    raw_data: row.get(9)?,
+                    duration_us: row.get::<_, Option<i64>>(10)?.map(|d| d as u64),
+                    frame_time_us: row.get::<_, Option<i64>>(13)?.map(|t| t as u64),
+                })
+            })
+            .map_err(err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err)?;
+
+        Ok(TrafficPage {
+            total: total as u64,
+            offset: query.offset,
+            limit,
+            entries,
+        })
+    }
+
+    /// Все записи, подходящие под фильтр (без постраничного ограничения).
+    pub fn collect(&self, query: &TrafficQuery) -> Result<Vec<LogEntry>, String> {
+        let mut page_query = TrafficQuery {
+            offset: 0,
+            limit: Some(MAX_PAGE_SIZE),
+            ..query.clone()
+        };
+        let mut entries = Vec::new();
+        loop {
+            let page = self.query(&page_query)?;
+            let count = page.entries.len() as u32;
+            entries.extend(page.entries);
+            if count < MAX_PAGE_SIZE {
+                return Ok(entries);
+            }
+            page_query.offset += count;
+        }
+    }
+
+    /// Удалить записи старше `retention_days` дней (0 — хранить всё).
+    /// Возвращает число удалённых записей.
+    pub fn prune(&self, retention_days: u32) -> Result<usize, String> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+        let guard = self.connection.lock();
+        let Some(connection) = guard.as_ref() else {
+            return Ok(0);
+        };
+        let now_ms = timestamp_to_ms(&chrono_now_iso());
+        let cutoff = now_ms - retention_days as i64 * 24 * 60 * 60 * 1000;
+        connection
+            .execute("DELETE FROM traffic WHERE time_ms < ?1", params![cutoff])
+            .map_err(|e| format!("Не удалось удалить старые записи журнала обмена: {e}"))
+    }
+
+    /// Удалить все записи журнала.
+    pub fn clear(&self) -> Result<(), String> {
+        let guard = self.connection.lock();
+        let connection = guard.as_ref().ok_or("Журнал обмена не открыт")?;
+        connection
+            .execute("DELETE FROM traffic", [])
+            .map(|_| ())
+            .map_err(|e| format!("Не удалось очистить журнал обмена: {e}"))
+    }
+}
+
+/// Общая ссылка на журнал обмена.
+pub type SharedTrafficLog = Arc<TrafficLog>;
+
+/// Создать журнал обмена (база открывается отдельно).
+pub fn create_shared_traffic_log() -> SharedTrafficLog {
+    Arc::new(TrafficLog::default())
+}
+
+/// Собрать условие WHERE и значения параметров по фильтру.
+fn build_condition(query: &TrafficQuery) -> (String, Vec<Value>) {
+    let mut clauses = Vec::new();
+    let mut values = Vec::new();
+
+    if let Some(from) = query.from_ms {
+        clauses.push("time_ms >= ?");
+        values.push(Value::Integer(from));
+    }
+    if let Some(to) = query.to_ms {
+        clauses.push("time_ms <= ?");
+        values.push(Value::Integer(to));
+    }
+    if let Some(client) = query.client.as_ref().filter(|c| !c.is_empty()) {
+        clauses.push("(client = ? OR client LIKE ? || ':%')");
+        values.push(Value::Text(client.clone()));
+        values.push(Value::Text(client.clone()));
+    }
+    if let Some(code) = query.function_code {
+        clauses.push("function_code = ?");
+        values.push(Value::Integer(code as i64));
+    }
+    if let Some(entry_type) = &query.entry_type {
+        clauses.push("entry_type = ?");
+        values.push(Value::Text(entry_type_name(entry_type).to_string()));
+    }
+    if let Some(severity) = query.min_severity {
+        clauses.push("severity >= ?");
+        values.push(Value::Integer(severity as i64));
+    }
+    if let Some(subsystem) = query.subsystem {
+        clauses.push("subsystem = ?");
+        values.push(Value::Text(subsystem_name(subsystem).to_string()));
+    }
+    if query.address_from.is_some() || query.address_to.is_some() {
+        clauses.push("address_start <= ? AND address_end >= ?");
+        values.push(Value::Integer(query.address_to.unwrap_or(u16::MAX) as i64));
+        values.push(Value::Integer(query.address_from.unwrap_or(0) as i64));
+    }
+    if let Some(text) = query.text.as_ref().filter(|t| !t.is_empty()) {
+        clauses.push("summary LIKE '%' || ? || '%'");
+        values.push(Value::Text(text.clone()));
+    }
+
+    if clauses.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!(" WHERE {}", clauses.join(" AND ")), values)
+    }
+}
+
+fn entry_type_name(entry_type: &LogEntryType) -> &'static str {
+    match entry_type {
+        LogEntryType::Request => "request",
+        LogEntryType::Response => "response",
+        LogEntryType::Error => "error",
+        LogEntryType::Info => "info",
+        LogEntryType::Warning => "warning",
+        LogEntryType::Security => "security",
+    }
+}
+
+fn parse_entry_type(name: &str) -> LogEntryType {
+    match name {
+        "request" => LogEntryType::Request,
+        "response" => LogEntryType::Response,
+        "error" => LogEntryType::Error,
+        "warning" => LogEntryType::Warning,
+        "security" => LogEntryType::Security,
+        _ => LogEntryType::Info,
+    }
+}
+
+fn parse_severity(rank: i64) -> LogSeverity {
+    match rank {
+        0 => LogSeverity::Debug,
+        2 => LogSeverity::Warning,
+        3 => LogSeverity::Error,
+        4 => LogSeverity::Critical,
+        _ => LogSeverity::Info,
+    }
+}
+
+fn subsystem_name(subsystem: LogSubsystem) -> &'static str {
+    match subsystem {
+        LogSubsystem::Server => "server",
+        LogSubsystem::Protocol => "protocol",
+        LogSubsystem::Data => "data",
+        LogSubsystem::Simulation => "simulation",
+    }
+}
+
+fn parse_subsystem(name: &str) -> LogSubsystem {
+    match name {
+        "server" => LogSubsystem::Server,
+        "data" => LogSubsystem::Data,
+        "simulation" => LogSubsystem::Simulation,
+        _ => LogSubsystem::Protocol,
+    }
+}
+
+/// Добавить столбцы новых версий схемы в базу, созданную старой версией.
+fn add_missing_columns(connection: &Connection) -> rusqlite::Result<()> {
+    let mut statement = connection.prepare("SELECT name FROM pragma_table_info('traffic')")?;
+    let existing = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (name, definition) in ADDED_COLUMNS {
+        if !existing.iter().any(|column| column == name) {
+            connection.execute_batch(&format!(
+                "ALTER TABLE traffic ADD COLUMN {name} {definition}"
+            ))?;
+            if name == "severity" {
+                // Важность старых записей — по умолчанию для их типа
+                connection.execute_batch(
+                    "UPDATE traffic SET severity = 0 WHERE entry_type IN ('request', 'response');
+                     UPDATE traffic SET severity = 3 WHERE entry_type = 'error';",
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Преобразовать временную метку вида "секунды.миллисекунды" в миллисекунды.
+pub fn timestamp_to_ms(timestamp: &str) -> i64 {
+    let (secs, millis) = timestamp.split_once('.').unwrap_or((timestamp, "0"));
+    secs.parse::<i64>().unwrap_or(0) * 1000 + millis.parse::<i64>().unwrap_or(0)
+}
+
+fn ms_to_timestamp(ms: i64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, timestamp: &str, client: &str, code: u8, range: (u16, u16)) -> LogEntry {
+        let mut entry = LogEntry::new(
+            id,
+            LogEntryType::Request,
+            client.to_string(),
+            format!("Запрос {}", id),
+        )
+        .with_function(code, "Read Holding Registers")
+        .with_address_range(Some(range));
+        entry.timestamp = timestamp.to_string();
+        entry
+    }
+
+    #[test]
+    fn test_query_filters_and_paging() {
+        let log = TrafficLog::default();
+        log.open(None).unwrap();
+        log.record(&entry(1, "100.000", "10.0.0.1:5000", 3, (0, 10)));
+        log.record(&entry(2, "101.500", "10.0.0.2:5001", 3, (100, 2)));
+        log.record(&entry(3, "102.000", "10.0.0.1:5000", 16, (5, 1)));
+
+        // Клиент по IP без порта и пересечение диапазона адресов
+        let page = log
+            .query(&TrafficQuery {
+                client: Some("10.0.0.1".to_string()),
+                address_from: Some(5),
+                address_to: Some(5),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.entries[0].id, 3);
+        assert_eq!(page.entries[0].quantity, Some(1));
+
+        let page = log
+            .query(&TrafficQuery {
+                from_ms: Some(101_000),
+                function_code: Some(3),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].timestamp, "101.500");
+
+        let page = log
+            .query(&TrafficQuery {
+                offset: 2,
+                limit: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!((page.total, page.entries.len()), (3, 1));
+        assert_eq!(page.entries[0].id, 1);
+
+        let mut warning = entry(4, "103.000", "10.0.0.1:5000", 3, (0, 1));
+        warning.entry_type = LogEntryType::Warning;
+        warning.severity = LogSeverity::Warning;
+        log.record(&warning.with_subsystem(LogSubsystem::Data));
+        let page = log
+            .query(&TrafficQuery {
+                min_severity: Some(LogSeverity::Info),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].subsystem, LogSubsystem::Data);
+        assert!(matches!(page.entries[0].entry_type, LogEntryType::Warning));
+
+        // Записи из начала эпохи старше любого срока хранения
+        assert_eq!(log.prune(0).unwrap(), 0);
+        assert_eq!(log.prune(30).unwrap(), 4);
+        log.clear().unwrap();
+        assert_eq!(log.query(&TrafficQuery::default()).unwrap().total, 0);
+    }
+}
@@ -0,0 +1,219 @@
+//! Зеркалирование трафика на внешний анализатор.
+//!
+//! Копия каждого сырого кадра (запроса мастера и ответа сервера) отправляется
+//! на адрес анализатора: по UDP — одна датаграмма на кадр, по TCP — кадры
+//! подряд в одном потоке (длина известна из MBAP). Так Wireshark или свой
+//! сборщик на другой машине видит обмен, не находясь на пути трафика.
+//!
+//! Отправка идёт в отдельной задаче через ограниченную очередь: медленный или
+//! недоступный анализатор не задерживает ответы мастеру, лишние кадры
+//! отбрасываются. TCP-соединение переоткрывается не чаще раза в секунду.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::{broadcast, mpsc};
+
+/// Размер очереди кадров на отправку.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Минимальная пауза между попытками подключения к анализатору.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Таймаут подключения к анализатору по TCP.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Транспорт до анализатора.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MirrorTransport {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+/// Настройки зеркалирования в профиле подключения.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorConfig {
+    #[serde(default)]
+    pub transport: MirrorTransport,
+    pub host: String,
+    pub port: u16,
+}
+
+/// Очередь кадров на зеркалирование; клон разделяет ту же очередь.
+#[derive(Debug, Clone)]
+pub struct TrafficMirror {
+    tx: mpsc::Sender<Vec<u8>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl TrafficMirror {
+    /// Запустить задачу отправки до сигнала завершения.
+    pub fn spawn(config: MirrorConfig, shutdown_rx: broadcast::Receiver<()>) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        tokio::spawn(run(config, rx, dropped.clone(), shutdown_rx));
+        Self { tx, dropped }
+    }
+
+    /// Поставить копию кадра в очередь; при заполненной очереди кадр отбрасывается.
+    pub fn send(&self, frame: &[u8]) {
+        if self.tx.try_send(frame.to_vec()).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Соединение с анализатором.
+enum Sink {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl Sink {
+    async fn open(config: &MirrorConfig) -> Result<Self, String> {
+        let addr = (config.host.as_str(), config.port);
+        match config.transport {
+            MirrorTransport::Udp => {
+                let bind = if config.host.contains(':') {
+                    "[::]:0"
+                } else {
+                    "0.0.0.0:0"
+                };
+                let socket = UdpSocket::bind(bind).await.map_err(|e| e.to_string())?;
+                socket.connect(addr).await.map_err(|e| e.to_string())?;
+                Ok(Sink::Udp(socket))
+            }
+            MirrorTransport::Tcp => {
+                let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr))
+                    .await
+                    .map_err(|_| "таймаут подключения".to_string())?
+                    .map_err(|e| e.to_string())?;
+                let _ = stream.set_nodelay(true);
+                Ok(Sink::Tcp(stream))
+            }
+        }
+    }
+
+    async fn send(&mut self, frame: &[u8]) -> std::io::Result<()> {
+        match self {
+            Sink::Udp(socket) => socket.send(frame).await.map(|_| ()),
+            Sink::Tcp(stream) => stream.write_all(frame).await,
+        }
+    }
+}
+
+/// Отправлять кадры из очереди на анализатор.
+async fn run(
+    config: MirrorConfig,
+    mut rx: mpsc::Receiver<Vec<u8>>,
+    dropped: Arc<AtomicU64>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let target = format!("{}:{}", config.host, config.port);
+    let mut sink: Option<Sink> = None;
+    let mut last_attempt: Option<Instant> = None;
+
+    loop {
+        let frame = tokio::select! {
+            frame = rx.recv() => match frame {
+                Some(frame) => frame,
+                None => break,
+            },
+            _ = shutdown_rx.recv() => break,
+        };
+
+        if sink.is_none() {
+            if last_attempt.is_some_and(|at| at.elapsed() < RECONNECT_INTERVAL) {
+                continue;
+            }
+            last_attempt = Some(Instant::now());
+            match Sink::open(&config).await {
+                Ok(opened) => {
+                    log::info!("Зеркалирование трафика на {}", target);
+                    sink = Some(opened);
+                }
+                Err(e) => {
+                    log::warn!("Анализатор {} недоступен: {}", target, e);
+                    continue;
+                }
+            }
+        }
+
+        if let Some(opened) = sink.as_mut() {
+            if let Err(e) = opened.send(&frame).await {
+                // UDP без слушателя даёт ICMP-отказ — кадр просто теряется
+                if matches!(opened, Sink::Tcp(_)) {
+                    log::warn!("Соединение с анализатором {} потеряно: {}", target, e);
+                    sink = None;
+                }
+            }
+        }
+    }
+
+    let dropped = dropped.load(Ordering::Relaxed);
+    if dropped > 0 {
+        log::warn!(
+            "Анализатор {}: отброшено кадров при переполнении очереди: {}",
+            target,
+            dropped
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_frames_reach_udp_and_tcp_analyzers() {
+        tauri::async_runtime::block_on(async {
+            let (shutdown_tx, _) = broadcast::channel::<()>(1);
+            let request = [0u8, 1, 0, 0, 0, 6, 1, 0x03, 0, 0, 0, 1];
+            let response = [0u8, 1, 0, 0, 0, 5, 1, 0x03, 2, 0, 42];
+
+            let analyzer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let mirror = TrafficMirror::spawn(
+                MirrorConfig {
+                    transport: MirrorTransport::Udp,
+                    host: "127.0.0.1".to_string(),
+                    port: analyzer.local_addr().unwrap().port(),
+                },
+                shutdown_tx.subscribe(),
+            );
+            mirror.send(&request);
+            mirror.send(&response);
+            let mut datagram = [0u8; 64];
+            let n = analyzer.recv(&mut datagram).await.unwrap();
+            assert_eq!(&datagram[..n], &request);
+            let n = analyzer.recv(&mut datagram).await.unwrap();
+            assert_eq!(&datagram[..n], &response);
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let mirror = TrafficMirror::spawn(
+                MirrorConfig {
+                    transport: MirrorTransport::Tcp,
+                    host: "127.0.0.1".to_string(),
+                    port: listener.local_addr().unwrap().port(),
+                },
+                shutdown_tx.subscribe(),
+            );
+            mirror.send(&request);
+            mirror.send(&response);
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = vec![0u8; request.len() + response.len()];
+            stream.read_exact(&mut received).await.unwrap();
+            assert_eq!(received, [&request[..], &response[..]].concat());
+
+            let _ = shutdown_tx.send(());
+        });
+    }
+}
@@ -0,0 +1,81 @@
+//! Проверка и намеренная подмена Transaction ID в ответах.
+//!
+//! В режиме проверки сервер убеждается, что каждый ответ повторяет
+//! Transaction ID запроса (например, после подменённых ответов), и пишет
+//! нарушения в лог обмена. По команде следующие N ответов уходят с
+//! изменённым Transaction ID — так проверяется, отбрасывает ли мастер
+//! чужие ответы.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::modbus_protocol::ModbusRequest;
+
+/// Transaction ID кадра (первые два байта MBAP).
+fn frame_transaction_id(frame: &[u8]) -> Option<u16> {
+    Some(u16::from_be_bytes([*frame.first()?, *frame.get(1)?]))
+}
+
+/// Проверить, что ответ повторяет Transaction ID запроса.
+/// Возвращает Transaction ID ответа при расхождении.
+pub fn check_echo(request: &ModbusRequest, response: &[u8]) -> Option<u16> {
+    frame_transaction_id(response).filter(|&id| id != request.header.transaction_id)
+}
+
+/// Счётчик запрошенных подмен Transaction ID.
+#[derive(Debug, Default)]
+pub struct TransactionIdInjector {
+    pending: AtomicU32,
+}
+
+impl TransactionIdInjector {
+    /// Подменить Transaction ID в следующих `count` ответах (добавляется к
+    /// ещё не выполненным). Возвращает число ожидающих подмен.
+    pub fn schedule(&self, count: u32) -> u32 {
+        self.pending.fetch_add(count, Ordering::SeqCst) + count
+    }
+
+    /// Число ожидающих подмен.
+    pub fn pending(&self) -> u32 {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Отменить ожидающие подмены.
+    pub fn cancel(&self) {
+        self.pending.store(0, Ordering::SeqCst);
+    }
+
+    /// Если подмена запрошена — увеличить Transaction ID ответа на 1.
+    /// Возвращает прежний и новый Transaction ID.
+    pub fn apply(&self, response: &mut [u8]) -> Option<(u16, u16)> {
+        let original = frame_transaction_id(response)?;
+        self.pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .ok()?;
+        let injected = original.wrapping_add(1);
+        response[..2].copy_from_slice(&injected.to_be_bytes());
+        Some((original, injected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus_protocol::ModbusResponse;
+
+    #[test]
+    fn test_injected_mismatch_is_detected() {
+        let frame = [
+            0x12, 0x34, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let request = ModbusRequest::parse(&frame).unwrap();
+        let mut response = ModbusResponse::build_response(&request, 0x03, &[0x02, 0x00, 0x2A]);
+        assert_eq!(check_echo(&request, &response), None);
+
+        let injector = TransactionIdInjector::default();
+        assert_eq!(injector.apply(&mut response), None);
+        assert_eq!(injector.schedule(1), 1);
+        assert_eq!(injector.apply(&mut response), Some((0x1234, 0x1235)));
+        assert_eq!(check_echo(&request, &response), Some(0x1235));
+        assert_eq!(injector.apply(&mut response), None);
+    }
+}
@@ -0,0 +1,225 @@
+//! Условные события для UI.
+//!
+//! Триггер — выражение над переменными (см. [`crate::expression`]). Когда условие
+//! становится истинным, движок симуляции отправляет в UI событие с заданным
+//! именем и, при необходимости, запись в лог обмена. Повторно триггер
+//! срабатывает только после того, как условие снова станет ложным.
+
+use std::collections::HashMap;
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::expression::Expr;
+use crate::types::chrono_now_iso;
+
+/// Имя события по умолчанию.
+const DEFAULT_TRIGGER_EVENT_NAME: &str = "process-trigger";
+
+/// Определение триггера.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerDefinition {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Условие срабатывания.
+    pub condition: String,
+    /// Имя события Tauri, отправляемого при срабатывании.
+    #[serde(default = "default_event_name")]
+    pub event_name: String,
+    #[serde(default)]
+    pub message: String,
+    /// Добавлять запись в лог обмена.
+    #[serde(default)]
+    pub log: bool,
+}
+
+fn default_event_name() -> String {
+    DEFAULT_TRIGGER_EVENT_NAME.to_string()
+}
+
+/// Событие срабатывания триггера.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerFiredEvent {
+    pub trigger_id: String,
+    pub name: String,
+    pub message: String,
+    pub timestamp: String,
+}
+
+/// Сработавший триггер: имя события и его содержимое.
+#[derive(Debug, Clone)]
+pub struct FiredTrigger {
+    pub event_name: String,
+    pub log: bool,
+    pub event: TriggerFiredEvent,
+}
+
+/// Определения триггеров и состояние их условий.
+#[derive(Default)]
+pub struct TriggerManager {
+    definitions: RwLock<Vec<(TriggerDefinition, Expr)>>,
+    /// Последнее значение условия каждого триггера.
+    states: Mutex<HashMap<String, bool>>,
+}
+
+impl TriggerManager {
+    /// Есть ли определения триггеров.
+    pub fn is_empty(&self) -> bool {
+        self.definitions.read().is_empty()
+    }
+
+    /// Список определений триггеров.
+    pub fn definitions(&self) -> Vec<TriggerDefinition> {
+        self.definitions
+            .read()
+            .iter()
+            .map(|(def, _)| def.clone())
+            .collect()
+    }
+
+    /// Заменить все определения (например, при загрузке проекта).
+    /// Определения с некорректным условием пропускаются.
+    pub fn set_definitions(&self, definitions: Vec<TriggerDefinition>) {
+        let parsed = definitions
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, def)| match compile(def, i) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::warn!("Триггер пропущен: {}", e);
+                    None
+                }
+            })
+            .collect();
+        *self.definitions.write() = parsed;
+        self.states.lock().clear();
+    }
+
+    /// Добавить триггер или заменить существующий с тем же ID.
+    pub fn upsert(&self, definition: TriggerDefinition) -> Result<TriggerDefinition, String> {
+        let mut definitions = self.definitions.write();
+        let (definition, expr) = compile(definition, definitions.len())?;
+
+        match definitions.iter_mut().find(|(d, _)| d.id == definition.id) {
+            Some(existing) => *existing = (definition.clone(), expr),
+            None => definitions.push((definition.clone(), expr)),
+        }
+        self.states.lock().remove(&definition.id);
+        Ok(definition)
+    }
+
+    /// Удалить триггер. Возвращает false, если триггер не найден.
+    pub fn remove(&self, id: &str) -> bool {
+        let mut definitions = self.definitions.write();
+        let before = definitions.len();
+        definitions.retain(|(d, _)| d.id != id);
+        self.states.lock().remove(id);
+        definitions.len() != before
+    }
+
+    /// Вычислить условия и вернуть триггеры, условие которых стало истинным.
+    /// Ошибки вычисления (например, неизвестная переменная) считаются ложным условием.
+    pub fn evaluate(&self, snapshot: &HashMap<String, f64>) -> Vec<FiredTrigger> {
+        let definitions = self.definitions.read();
+        let mut states = self.states.lock();
+        let resolve = |name: &str| snapshot.get(name).copied();
+
+        definitions
+            .iter()
+            .filter_map(|(def, expr)| {
+                let now = expr.eval_bool(&resolve).unwrap_or(false);
+                let was = states.insert(def.id.clone(), now).unwrap_or(false);
+                (now && !was).then(|| FiredTrigger {
+                    event_name: def.event_name.clone(),
+                    log: def.log,
+                    event: TriggerFiredEvent {
+                        trigger_id: def.id.clone(),
+                        name: def.name.clone(),
+                        message: def.message.clone(),
+                        timestamp: chrono_now_iso(),
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+/// Проверить имя события и условие, назначить ID при необходимости.
+fn compile(
+    mut definition: TriggerDefinition,
+    seq: usize,
+) -> Result<(TriggerDefinition, Expr), String> {
+    let expr = Expr::parse(&definition.condition)
+        .map_err(|e| format!("Условие триггера '{}': {}", definition.name, e))?;
+    // Tauri допускает в именах событий только буквы, цифры и символы - / : _
+    let valid_name = !definition.event_name.is_empty()
+        && definition
+            .event_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | ':' | '_'));
+    if !valid_name {
+        return Err(format!(
+            "Недопустимое имя события '{}'",
+            definition.event_name
+        ));
+    }
+    if definition.id.is_empty() {
+        definition.id = format!("trigger_{}_{}", seq + 1, chrono_now_iso().replace('.', ""));
+    }
+    if definition.name.is_empty() {
+        definition.name = definition.condition.clone();
+    }
+    Ok((definition, expr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(condition: &str, event_name: &str) -> TriggerDefinition {
+        TriggerDefinition {
+            id: String::new(),
+            name: String::new(),
+            condition: condition.to_string(),
+            event_name: event_name.to_string(),
+            message: "Насос запущен".to_string(),
+            log: false,
+        }
+    }
+
+    #[test]
+    fn test_trigger_fires_on_rising_edge_only() {
+        let manager = TriggerManager::default();
+        manager
+            .upsert(trigger("pump == 1", "pump-started"))
+            .unwrap();
+
+        let mut snapshot = HashMap::from([("pump".to_string(), 0.0)]);
+        assert!(manager.evaluate(&snapshot).is_empty());
+
+        snapshot.insert("pump".to_string(), 1.0);
+        let fired = manager.evaluate(&snapshot);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].event_name, "pump-started");
+        // Пока условие истинно — повторно не срабатывает
+        assert!(manager.evaluate(&snapshot).is_empty());
+
+        snapshot.insert("pump".to_string(), 0.0);
+        assert!(manager.evaluate(&snapshot).is_empty());
+        snapshot.insert("pump".to_string(), 1.0);
+        assert_eq!(manager.evaluate(&snapshot).len(), 1);
+    }
+
+    #[test]
+    fn test_trigger_validation() {
+        let manager = TriggerManager::default();
+        assert!(manager.upsert(trigger("pump ==", "ok")).is_err());
+        assert!(manager.upsert(trigger("pump", "bad name!")).is_err());
+        assert!(manager.upsert(trigger("pump", "")).is_err());
+        assert!(manager.is_empty());
+    }
+}
@@ -5,8 +5,36 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::access_lock::AccessLockConfig;
+use crate::addressing::AddressingConvention;
+use crate::alarms::AlarmDefinition;
+use crate::byte_count_stress::ByteCountStress;
+use crate::client_units::ClientUnit;
+use crate::coil_interlock::CoilInterlockGroup;
+use crate::data_store::RecordFile;
+use crate::event_buffer::EventBufferConfig;
+use crate::fault_rules::FaultPreset;
+use crate::firmware::FirmwareLevel;
+use crate::fragmentation::Fragmentation;
+use crate::gateway::GatewayConfig;
+use crate::generator::GeneratorStatus;
+use crate::inactivity::InactivityAlarmConfig;
+use crate::mdns::MdnsSettings;
+use crate::processing_time::ProcessingTimes;
+use crate::protocol_policy::ProtocolStrictness;
+use crate::quality::QualityConfig;
+use crate::response_override::ResponseOverride;
+use crate::runtime_counters::RuntimeCounterRegisters;
+use crate::serial_gateway::SerialGatewayConfig;
+use crate::simulation::{default_tick_interval_ms, Behavior};
+use crate::traffic_mirror::MirrorConfig;
+use crate::triggers::TriggerDefinition;
+use crate::write_rate_limit::WriteRateLimit;
+use crate::write_storm::WriteStormConfig;
+use crate::zero_quantity::ZeroQuantityReads;
+
 /// Modbus memory area type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ModbusArea {
     /// Coils (0x) - read/write single bit
@@ -43,6 +71,48 @@ impl ModbusDataType {
     }
 }
 
+/// Byte order of a value on the wire. Letters name the bytes of the
+/// big-endian value `ABCD`; `CDAB` is the word-swapped float32 used by many PLCs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum WordOrder {
+    /// High word first, high byte first (Modbus default).
+    #[default]
+    Abcd,
+    /// Low word first.
+    Cdab,
+    /// High word first, bytes swapped within each word.
+    Badc,
+    /// Low word first, bytes swapped within each word.
+    Dcba,
+}
+
+impl WordOrder {
+    /// Convert registers between this order and `ABCD` in place.
+    /// Every order is its own inverse, so the same call serves reads and writes.
+    pub fn apply(&self, regs: &mut [u16]) {
+        if matches!(self, WordOrder::Cdab | WordOrder::Dcba) {
+            regs.reverse();
+        }
+        if matches!(self, WordOrder::Badc | WordOrder::Dcba) {
+            for word in regs.iter_mut() {
+                *word = word.swap_bytes();
+            }
+        }
+    }
+}
+
+/// Кадрирование запросов и ответов в соединении.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionFraming {
+    /// Modbus TCP: заголовок MBAP.
+    #[default]
+    Mbap,
+    /// Modbus ASCII: ':', hex, LRC, CR LF (старые щитовые приборы).
+    Ascii,
+}
+
 /// Connection profile for the Modbus slave.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -52,6 +122,84 @@ pub struct ModbusConnectionProfile {
     pub host: String,
     pub port: u16,
     pub unit_id: u8,
+    /// Кадрирование в соединении: MBAP или ASCII.
+    #[serde(default)]
+    pub framing: ConnectionFraming,
+    /// Дополнительно принимать Modbus/UDP на тех же портах.
+    #[serde(default)]
+    pub udp: bool,
+    /// Реакция на отклонения от протокола (Protocol ID, длина, Unit ID).
+    #[serde(default)]
+    pub strictness: ProtocolStrictness,
+    /// Анонс сервера в локальной сети через mDNS.
+    #[serde(default)]
+    pub mdns: MdnsSettings,
+    /// Дополнительные порты того же устройства (например, 10502 рядом с 502).
+    #[serde(default)]
+    pub port_aliases: Vec<u16>,
+    /// Размер очереди входящих подключений (listen backlog); None — по умолчанию.
+    #[serde(default)]
+    pub listen_backlog: Option<u32>,
+    /// Пауза после каждого принятого подключения, мс: остальные ждут в очереди.
+    #[serde(default)]
+    pub accept_delay_ms: u64,
+    /// Базовое время обработки запросов по кодам функций.
+    #[serde(default)]
+    pub processing_times: ProcessingTimes,
+    /// Подменённые ответы, минующие хранилище данных.
+    #[serde(default)]
+    pub response_overrides: Vec<ResponseOverride>,
+    /// Проверять, что ответы повторяют Transaction ID запроса.
+    #[serde(default)]
+    pub verify_transaction_ids: bool,
+    /// Дробление ответов на мелкие TCP-сегменты.
+    #[serde(default)]
+    pub fragmentation: Fragmentation,
+    /// Адреса служебных счётчиков времени работы в input registers.
+    #[serde(default)]
+    pub runtime_counters: RuntimeCounterRegisters,
+    /// Режим шлюза: политики записи для адресов реального устройства.
+    #[serde(default)]
+    pub gateway: Option<GatewayConfig>,
+    /// Намеренное несоответствие счётчика байт в ответах чтения.
+    #[serde(default)]
+    pub byte_count_stress: ByteCountStress,
+    /// Ответ на чтение с нулевым количеством.
+    #[serde(default)]
+    pub zero_quantity_reads: ZeroQuantityReads,
+    /// Копия кадров на внешний анализатор трафика.
+    #[serde(default)]
+    pub traffic_mirror: Option<MirrorConfig>,
+    /// Имитация шлюза TCP → RTU с ведомыми по unit ID.
+    #[serde(default)]
+    pub serial_gateway: Option<SerialGatewayConfig>,
+    /// Unit ID, которым обслуживаются запросы клиентов с заданными IP.
+    #[serde(default)]
+    pub client_units: Vec<ClientUnit>,
+    /// Ограничения скорости изменения переменных при записи мастера.
+    #[serde(default)]
+    pub write_rate_limits: Vec<WriteRateLimit>,
+    /// Кольцевой буфер событий в holding registers.
+    #[serde(default)]
+    pub event_buffer: Option<EventBufferConfig>,
+    /// Группы взаимоисключающих coils.
+    #[serde(default)]
+    pub coil_interlocks: Vec<CoilInterlockGroup>,
+    /// Диапазоны, доступные после записи пароля в регистр разблокировки.
+    #[serde(default)]
+    pub access_lock: Option<AccessLockConfig>,
+    /// Тревога отсутствия запросов мастера.
+    #[serde(default)]
+    pub inactivity_alarm: Option<InactivityAlarmConfig>,
+    /// Сводка штормов записи одного адреса.
+    #[serde(default)]
+    pub write_storm: Option<WriteStormConfig>,
+    /// Описанные уровни прошивки устройства.
+    #[serde(default)]
+    pub firmware_levels: Vec<FirmwareLevel>,
+    /// Имя активного уровня прошивки.
+    #[serde(default)]
+    pub firmware: Option<String>,
 }
 
 impl Default for ModbusConnectionProfile {
@@ -62,6 +210,32 @@ impl Default for ModbusConnectionProfile {
             host: "127.0.0.1".to_string(),
             port: 502,
             unit_id: 1,
+            framing: ConnectionFraming::default(),
+            udp: false,
+            strictness: ProtocolStrictness::default(),
+            mdns: MdnsSettings::default(),
+            port_aliases: Vec::new(),
+            listen_backlog: None,
+            accept_delay_ms: 0,
+            processing_times: ProcessingTimes::default(),
+            response_overrides: Vec::new(),
+            verify_transaction_ids: false,
+            fragmentation: Fragmentation::default(),
+            runtime_counters: RuntimeCounterRegisters::default(),
+            gateway: None,
+            byte_count_stress: ByteCountStress::default(),
+            zero_quantity_reads: ZeroQuantityReads::default(),
+            traffic_mirror: None,
+            serial_gateway: None,
+            client_units: Vec::new(),
+            write_rate_limits: Vec::new(),
+            event_buffer: None,
+            coil_interlocks: Vec::new(),
+            access_lock: None,
+            inactivity_alarm: None,
+            write_storm: None,
+            firmware_levels: Vec::new(),
+            firmware: None,
         }
     }
 }
@@ -88,10 +262,50 @@ pub struct ModbusVariable {
     /// User note/comment.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub note: Option<String>,
+    /// Value applied when the project is loaded (instead of `value`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_value: Option<ModbusValue>,
+    /// Value restored by `reset_values`; falls back to `initial_value`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_value: Option<ModbusValue>,
+    /// Attached value generator (runtime state, filled by `get_variables`).
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<GeneratorStatus>,
+}
+
+impl ModbusVariable {
+    /// Create a variable named after its ID with a zero value (`false` for
+    /// bool) and no optional settings.
+    pub fn new(
+        id: impl Into<String>,
+        area: ModbusArea,
+        address: u16,
+        data_type: ModbusDataType,
+    ) -> Self {
+        let id = id.into();
+        let value = match data_type {
+            ModbusDataType::Bool => ModbusValue::Bool(false),
+            _ => ModbusValue::default(),
+        };
+        Self {
+            name: id.clone(),
+            id,
+            area,
+            address,
+            data_type,
+            value,
+            bit: None,
+            readonly: None,
+            note: None,
+            initial_value: None,
+            reset_value: None,
+            generator: None,
+        }
+    }
 }
 
 /// Value that can be either boolean or numeric.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ModbusValue {
     Bool(bool),
@@ -154,6 +368,21 @@ impl ModbusValue {
         }
     }
 
+    /// Convert value to f64 (for expressions).
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            ModbusValue::Bool(b) => {
+                if *b {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ModbusValue::Number(n) => *n,
+            ModbusValue::Null => 0.0,
+        }
+    }
+
     /// Convert value to f32.
     pub fn as_f32(&self) -> f32 {
         match self {
@@ -183,6 +412,76 @@ pub struct ModbusProject {
     pub profiles: Vec<ModbusConnectionProfile>,
     pub current_profile_id: Option<String>,
     pub variables: Vec<ModbusVariable>,
+    /// Соглашение об адресации для отображения и ввода адресов.
+    #[serde(default)]
+    pub addressing: AddressingConvention,
+    /// Поведения симуляции (обмен команда/статус и т.п.).
+    #[serde(default)]
+    pub behaviors: Vec<Behavior>,
+    /// Период такта симуляции, мс.
+    #[serde(default = "default_tick_interval_ms")]
+    pub simulation_interval_ms: u64,
+    /// Определения тревог.
+    #[serde(default)]
+    pub alarms: Vec<AlarmDefinition>,
+    /// Триггеры событий UI.
+    #[serde(default)]
+    pub triggers: Vec<TriggerDefinition>,
+    /// Настройки качества данных переменных.
+    #[serde(default)]
+    pub quality: Vec<QualityConfig>,
+    /// Именованные пресеты правил сбоев.
+    #[serde(default)]
+    pub fault_presets: Vec<FaultPreset>,
+    /// Файлы записей для функций 0x14/0x15.
+    #[serde(default)]
+    pub record_files: Vec<RecordFile>,
+    /// Хранить области данных в файле образа процесса рядом с проектом,
+    /// чтобы значения переживали перезапуск и аварийное завершение.
+    #[serde(default)]
+    pub persist_process_image: bool,
+    /// Зерно генератора случайных чисел; None — новое зерно при загрузке.
+    #[serde(default)]
+    pub random_seed: Option<u64>,
+    /// Сведения об испытаниях для экспорта и отчётов.
+    #[serde(default, skip_serializing_if = "ProjectMetadata::is_empty")]
+    pub metadata: ProjectMetadata,
+}
+
+/// Произвольные сведения о проекте: к какой кампании испытаний относятся
+/// журналы и отчёты. Попадают в экспорт карты регистров и заголовки отчётов.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectMetadata {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub customer: String,
+    /// Версия прошивки испытываемого устройства.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub firmware: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl ProjectMetadata {
+    /// Ни одно поле не заполнено.
+    pub fn is_empty(&self) -> bool {
+        self.description.is_empty()
+            && self.customer.is_empty()
+            && self.firmware.is_empty()
+            && self.tags.is_empty()
+    }
+}
+
+impl ModbusProject {
+    /// Текущий профиль подключения (или первый, если текущий не выбран).
+    pub fn current_profile(&self) -> Option<&ModbusConnectionProfile> {
+        self.current_profile_id
+            .as_ref()
+            .and_then(|id| self.profiles.iter().find(|p| &p.id == id))
+            .or_else(|| self.profiles.first())
+    }
 }
 
 impl Default for ModbusProject {
@@ -192,10 +491,49 @@ impl Default for ModbusProject {
             current_profile_id: Some(profile.id.clone()),
             profiles: vec![profile],
             variables: Vec::new(),
+            addressing: AddressingConvention::default(),
+            behaviors: Vec::new(),
+            simulation_interval_ms: default_tick_interval_ms(),
+            alarms: Vec::new(),
+            triggers: Vec::new(),
+            quality: Vec::new(),
+            fault_presets: Vec::new(),
+            record_files: Vec::new(),
+            persist_process_image: false,
+            random_seed: None,
+            metadata: ProjectMetadata::default(),
         }
     }
 }
 
+/// Событие об изменении набора переменных в хранилище данных.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariablesChangedEvent {
+    /// Причина изменения (например, "edit_commit").
+    pub reason: String,
+    pub variables: Vec<ModbusVariable>,
+}
+
+/// Прогресс загрузки переменных в хранилище.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadProgressEvent {
+    pub loaded: usize,
+    pub total: usize,
+}
+
+/// Проверка адресов запросов мастера.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AddressValidation {
+    /// Адреса без переменных отвечают IllegalDataAddress.
+    #[default]
+    Strict,
+    /// Доступна любая ячейка области; неописанные читаются нулями.
+    Permissive,
+}
+
 /// Server status information sent to frontend.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -203,8 +541,25 @@ pub struct ServerStatus {
     pub running: bool,
     pub host: String,
     pub port: u16,
+    /// Дополнительные порты, которые слушает сервер.
+    pub port_aliases: Vec<u16>,
     pub unit_id: u8,
     pub connections_count: usize,
+    /// Сколько раз клиенты повторно использовали Transaction ID незавершённого запроса.
+    pub duplicate_transaction_ids: u64,
+    /// Подключения, принятые с момента запуска.
+    pub accepted_connections: u64,
+    /// Подключения, сброшенные клиентом до принятия.
+    pub refused_connections: u64,
+    /// Прочие сбои приёма подключений (например, исчерпание дескрипторов).
+    pub accept_errors: u64,
+    /// Штормы записи с момента запуска.
+    pub write_storms: u64,
+    /// Записи в штормах, не попавшие в журнал.
+    pub storm_suppressed_writes: u64,
+    /// Текущая проверка адресов.
+    #[serde(default)]
+    pub address_validation: AddressValidation,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
@@ -215,8 +570,16 @@ impl Default for ServerStatus {
             running: false,
             host: "0.0.0.0".to_string(),
             port: 502,
+            port_aliases: Vec::new(),
             unit_id: 1,
             connections_count: 0,
+            duplicate_transaction_ids: 0,
+            accepted_connections: 0,
+            refused_connections: 0,
+            accept_errors: 0,
+            write_storms: 0,
+            storm_suppressed_writes: 0,
+            address_validation: AddressValidation::default(),
             error: None,
         }
     }
@@ -234,6 +597,59 @@ pub enum LogEntryType {
     Error,
     /// Информационное сообщение (подключение/отключение)
     Info,
+    /// Отклонение, не прервавшее обработку (допущенное нарушение протокола,
+    /// обрезанная или отклонённая по правилам запись)
+    Warning,
+    /// Запрет доступа (запись в защищённый диапазон)
+    Security,
+}
+
+impl LogEntryType {
+    /// Важность записи этого типа по умолчанию.
+    pub fn default_severity(&self) -> LogSeverity {
+        match self {
+            LogEntryType::Request | LogEntryType::Response => LogSeverity::Debug,
+            LogEntryType::Info => LogSeverity::Info,
+            LogEntryType::Warning | LogEntryType::Security => LogSeverity::Warning,
+            LogEntryType::Error => LogSeverity::Error,
+        }
+    }
+
+    /// Подсистема записи этого типа по умолчанию.
+    pub fn default_subsystem(&self) -> LogSubsystem {
+        match self {
+            LogEntryType::Info | LogEntryType::Security => LogSubsystem::Server,
+            _ => LogSubsystem::Protocol,
+        }
+    }
+}
+
+/// Важность записи лога; упорядочена по возрастанию.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSeverity {
+    /// Обычный обмен (запросы и ответы)
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+/// Подсистема, создавшая запись лога.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSubsystem {
+    /// Сокеты, подключения, жизненный цикл сервера
+    Server,
+    /// Кадры Modbus и их проверки
+    #[default]
+    Protocol,
+    /// Переменные и правила записи
+    Data,
+    /// Поведения, генераторы, тревоги
+    Simulation,
 }
 
 /// Запись лога для отображения в UI.
@@ -244,8 +660,14 @@ pub struct LogEntry {
     pub id: u64,
     /// Временная метка (ISO 8601)
     pub timestamp: String,
-    /// Тип записи (request/response/error/info)
+    /// Тип записи (request/response/error/info/warning/security)
     pub entry_type: LogEntryType,
+    /// Важность (для фильтрации и цветовой разметки)
+    #[serde(default)]
+    pub severity: LogSeverity,
+    /// Подсистема-источник
+    #[serde(default)]
+    pub subsystem: LogSubsystem,
     /// IP-адрес клиента
     pub client_addr: String,
     /// Код функции Modbus (если применимо)
@@ -254,6 +676,12 @@ pub struct LogEntry {
     /// Название функции (человекочитаемое)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_name: Option<String>,
+    /// Начальный адрес запроса (если применимо)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<u16>,
+    /// Количество адресов в запросе
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u16>,
     /// Краткое описание запроса/ответа
     pub summary: String,
     /// Сырые данные в hex (опционально)
@@ -262,6 +690,10 @@ pub struct LogEntry {
     /// Время обработки в микросекундах (для ответов)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_us: Option<u64>,
+    /// Время приёма запроса или отправки ответа, мкс с эпохи Unix
+    /// (монотонные часы, см. `frame_clock`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_time_us: Option<u64>,
 }
 
 impl LogEntry {
@@ -270,13 +702,18 @@ impl LogEntry {
         Self {
             id,
             timestamp: chrono_now_iso(),
+            severity: entry_type.default_severity(),
+            subsystem: entry_type.default_subsystem(),
             entry_type,
             client_addr,
             function_code: None,
             function_name: None,
+            address: None,
+            quantity: None,
             summary,
             raw_data: None,
             duration_us: None,
+            frame_time_us: None,
         }
     }
 
@@ -287,6 +724,15 @@ impl LogEntry {
         self
     }
 
+    /// Установить диапазон адресов запроса.
+    pub fn with_address_range(mut self, range: Option<(u16, u16)>) -> Self {
+        if let Some((address, quantity)) = range {
+            self.address = Some(address);
+            self.quantity = Some(quantity);
+        }
+        self
+    }
+
     /// Установить сырые данные в hex.
     pub fn with_raw_data(mut self, data: &[u8]) -> Self {
         self.raw_data = Some(bytes_to_hex(data));
@@ -298,10 +744,28 @@ impl LogEntry {
         self.duration_us = Some(duration_us);
         self
     }
+
+    /// Установить высокоточное время кадра.
+    pub fn with_frame_time(mut self, frame_time_us: u64) -> Self {
+        self.frame_time_us = Some(frame_time_us);
+        self
+    }
+
+    /// Установить подсистему-источник.
+    pub fn with_subsystem(mut self, subsystem: LogSubsystem) -> Self {
+        self.subsystem = subsystem;
+        self
+    }
+
+    /// Установить важность, отличную от важности типа по умолчанию.
+    pub fn with_severity(mut self, severity: LogSeverity) -> Self {
+        self.severity = severity;
+        self
+    }
 }
 
 /// Получить текущее время в формате ISO 8601.
-fn chrono_now_iso() -> String {
+pub fn chrono_now_iso() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     let now = SystemTime::now()
@@ -316,14 +780,32 @@ fn chrono_now_iso() -> String {
     format!("{}.{:03}", secs, millis)
 }
 
+/// Сгенерировать уникальный ID переменной (в том же стиле, что и во фронтенде).
+pub fn generate_variable_id(seq: usize) -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("var_{:x}_{}", millis, seq)
+}
+
 /// Преобразовать байты в hex-строку.
-fn bytes_to_hex(data: &[u8]) -> String {
+pub fn bytes_to_hex(data: &[u8]) -> String {
     data.iter()
         .map(|b| format!("{:02X}", b))
         .collect::<Vec<_>>()
         .join(" ")
 }
 
+/// Разобрать hex-строку вида "00 01 FF".
+pub fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    hex.split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect()
+}
+
 /// Получить человекочитаемое название функции Modbus.
 pub fn function_code_name(code: u8) -> &'static str {
     match code {
@@ -335,6 +817,25 @@ pub fn function_code_name(code: u8) -> &'static str {
         0x06 => "Write Single Register",
         0x0F => "Write Multiple Coils",
         0x10 => "Write Multiple Registers",
+        0x14 => "Read File Record",
+        0x15 => "Write File Record",
+        0x16 => "Mask Write Register",
         _ => "Unknown Function",
     }
 }
+
+/// Получить человекочитаемое название кода исключения Modbus.
+pub fn exception_code_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "Illegal Function",
+        0x02 => "Illegal Data Address",
+        0x03 => "Illegal Data Value",
+        0x04 => "Server Device Failure",
+        0x05 => "Acknowledge",
+        0x06 => "Server Device Busy",
+        0x08 => "Memory Parity Error",
+        0x0A => "Gateway Path Unavailable",
+        0x0B => "Gateway Target Device Failed to Respond",
+        _ => "Unknown Exception",
+    }
+}
@@ -0,0 +1,190 @@
+//! Временная подмена значений переменных.
+//!
+//! Значение переменной задаётся на N секунд, например для всплеска во время
+//! демонстрации. Пока подмена активна, движок симуляции на каждом такте
+//! перезаписывает значение поверх генераторов и записей мастера. По истечении
+//! срока переменная возвращается к прежнему значению, а если к ней подключён
+//! работающий генератор — остаётся под его управлением.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::data_store::SharedDataStore;
+use crate::types::ModbusValue;
+
+/// Наибольшая длительность подмены, с.
+pub const MAX_OVERRIDE_SECS: f64 = 3600.0;
+
+/// Активная подмена для UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueOverrideStatus {
+    pub variable_id: String,
+    pub value: ModbusValue,
+    /// Значение, которое вернётся по истечении срока.
+    pub before: ModbusValue,
+    /// Оставшееся время, с.
+    pub remaining_secs: f64,
+}
+
+/// Состояние активной подмены.
+#[derive(Debug)]
+struct ActiveOverride {
+    value: ModbusValue,
+    /// Значение переменной до подмены.
+    before: ModbusValue,
+    expires: Instant,
+}
+
+/// Активные временные подмены.
+#[derive(Default)]
+pub struct ValueOverrideManager {
+    active: Mutex<HashMap<String, ActiveOverride>>,
+}
+
+impl ValueOverrideManager {
+    /// Подменить значение переменной на `duration_secs` секунд (заменяет
+    /// предыдущую подмену; значение «до подмены» сохраняется от первой).
+    pub fn set(
+        &self,
+        data_store: &SharedDataStore,
+        variable_id: &str,
+        value: ModbusValue,
+        duration_secs: f64,
+        now: Instant,
+    ) -> Result<(), String> {
+        if !(duration_secs > 0.0 && duration_secs <= MAX_OVERRIDE_SECS) {
+            return Err(format!(
+                "Длительность подмены должна быть от 0 до {} с",
+                MAX_OVERRIDE_SECS
+            ));
+        }
+        let var = data_store
+            .get_variable(variable_id)
+            .ok_or_else(|| format!("Переменная '{}' не найдена", variable_id))?;
+
+        let expires = now + Duration::from_secs_f64(duration_secs);
+        let mut active = self.active.lock();
+        let entry = active
+            .entry(variable_id.to_string())
+            .or_insert_with(|| ActiveOverride {
+                value: value.clone(),
+                before: var.value.clone(),
+                expires,
+            });
+        entry.value = value.clone();
+        entry.expires = expires;
+        data_store.update_variable(variable_id, value);
+        log::info!("Подмена значения '{}' на {} с", variable_id, duration_secs);
+        Ok(())
+    }
+
+    /// Снять подмену досрочно. Значение восстанавливается, если переменная
+    /// не под управлением генератора. Возвращает false, если подмены не было.
+    pub fn clear(&self, data_store: &SharedDataStore, variable_id: &str, generated: bool) -> bool {
+        let Some(entry) = self.active.lock().remove(variable_id) else {
+            return false;
+        };
+        if !generated {
+            data_store.update_variable(variable_id, entry.before);
+        }
+        log::info!("Подмена значения '{}' снята", variable_id);
+        true
+    }
+
+    /// Список активных подмен.
+    pub fn statuses(&self, now: Instant) -> Vec<ValueOverrideStatus> {
+        let mut statuses: Vec<ValueOverrideStatus> = self
+            .active
+            .lock()
+            .iter()
+            .map(|(id, entry)| ValueOverrideStatus {
+                variable_id: id.clone(),
+                value: entry.value.clone(),
+                before: entry.before.clone(),
+                remaining_secs: entry.expires.saturating_duration_since(now).as_secs_f64(),
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.variable_id.cmp(&b.variable_id));
+        statuses
+    }
+
+    /// Перезаписать значения активных подмен и снять истёкшие.
+    /// `generated` сообщает, управляет ли переменной работающий генератор.
+    pub fn apply(
+        &self,
+        data_store: &SharedDataStore,
+        now: Instant,
+        generated: impl Fn(&str) -> bool,
+    ) {
+        let mut active = self.active.lock();
+        active.retain(|id, entry| {
+            if now < entry.expires {
+                data_store.update_variable(id, entry.value.clone());
+                return true;
+            }
+            if !generated(id) {
+                data_store.update_variable(id, entry.before.clone());
+            }
+            log::info!("Подмена значения '{}' истекла", id);
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+    use crate::types::{ModbusArea, ModbusDataType, ModbusVariable};
+
+    fn variable(id: &str, address: u16, value: f64) -> ModbusVariable {
+        ModbusVariable {
+            value: ModbusValue::Number(value),
+            ..ModbusVariable::new(
+                id,
+                ModbusArea::HoldingRegister,
+                address,
+                ModbusDataType::Uint16,
+            )
+        }
+    }
+
+    #[test]
+    fn test_override_holds_then_reverts() {
+        let store = create_shared_data_store();
+        store.load_variables(&[variable("flow", 0, 10.0), variable("wave", 1, 5.0)]);
+        let overrides = ValueOverrideManager::default();
+        let t0 = Instant::now();
+
+        overrides
+            .set(&store, "flow", ModbusValue::Number(900.0), 2.0, t0)
+            .unwrap();
+        overrides
+            .set(&store, "wave", ModbusValue::Number(700.0), 2.0, t0)
+            .unwrap();
+        assert_eq!(store.read_holding_registers(0, 2).unwrap(), vec![900, 700]);
+
+        // Запись мастера перетирается на следующем такте
+        store.update_variable("flow", ModbusValue::Number(11.0));
+        overrides.apply(&store, t0 + Duration::from_secs(1), |_| false);
+        assert_eq!(store.read_holding_registers(0, 1).unwrap(), vec![900]);
+        assert!(
+            (overrides.statuses(t0 + Duration::from_secs(1))[0].remaining_secs - 1.0).abs() < 1e-6
+        );
+
+        // Генератор уже записал своё значение — его не трогаем
+        store.update_variable("wave", ModbusValue::Number(6.0));
+        overrides.apply(&store, t0 + Duration::from_secs(2), |id| id == "wave");
+        assert_eq!(store.read_holding_registers(0, 2).unwrap(), vec![10, 6]);
+        assert!(overrides.statuses(t0).is_empty());
+
+        assert!(overrides
+            .set(&store, "flow", ModbusValue::Number(1.0), 0.0, t0)
+            .is_err());
+        assert!(!overrides.clear(&store, "flow", false));
+    }
+}
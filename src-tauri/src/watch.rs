@@ -0,0 +1,241 @@
+//! Выражения наблюдения над сырыми адресами.
+//!
+//! Упакованные слова состояния часто не описаны переменными проекта. Выражение
+//! наблюдения обращается к ячейкам напрямую — `HR[100] & 0x00FF`,
+//! `(IR[7] >> 4) & 0b11`, `CO[0x10]` — и может смешивать их с переменными.
+//! Каждое наблюдение — фоновая задача, которая с заданным периодом вычисляет
+//! выражение и отправляет событие только при изменении результата; целые
+//! результаты дополнительно показываются в шестнадцатеричном и двоичном виде.
+
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::data_store::{ModbusDataStore, SharedDataStore};
+use crate::expression::Expr;
+use crate::types::ModbusArea;
+
+/// Название события с результатом наблюдения.
+const WATCH_EVENT_NAME: &str = "watch-update";
+
+/// Минимальный период вычисления.
+const MIN_INTERVAL_MS: u64 = 20;
+
+/// Результат вычисления выражения.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchEvent {
+    pub watch_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// Неотрицательный целый результат: `0x00FF`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hex: Option<String>,
+    /// Неотрицательный целый результат: `0000_0000_1111_1111`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Описание наблюдения для UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchInfo {
+    pub id: u64,
+    pub expression: String,
+    pub interval_ms: u64,
+}
+
+struct Watch {
+    info: WatchInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Разобрать ссылку на ячейку: `HR[100]`, `IR[0x10]`, `CO[3]`, `DI[2]`.
+fn raw_address(name: &str) -> Option<(ModbusArea, u16)> {
+    let (prefix, rest) = name.split_once('[')?;
+    let index = rest.strip_suffix(']')?;
+    let area = match prefix.to_ascii_uppercase().as_str() {
+        "HR" => ModbusArea::HoldingRegister,
+        "IR" => ModbusArea::InputRegister,
+        "CO" => ModbusArea::Coil,
+        "DI" => ModbusArea::DiscreteInput,
+        _ => return None,
+    };
+    let address = match index
+        .strip_prefix("0x")
+        .or_else(|| index.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+        None => index.parse().ok()?,
+    };
+    Some((area, address))
+}
+
+/// Вычислить выражение по текущему состоянию хранилища.
+fn evaluate(expr: &Expr, data_store: &ModbusDataStore) -> Result<f64, String> {
+    // Значения переменных собираются, только если выражение к ним обращается
+    let snapshot = OnceCell::new();
+    expr.eval(&|name: &str| match raw_address(name) {
+        Some((area, address)) => Some(data_store.dump_area(area, address, 1)[0] as f64),
+        None => snapshot
+            .get_or_init(|| data_store.numeric_snapshot())
+            .get(name)
+            .copied(),
+    })
+}
+
+/// Событие по результату вычисления.
+fn watch_event(watch_id: u64, result: Result<f64, String>) -> WatchEvent {
+    let integer = result
+        .as_ref()
+        .ok()
+        .filter(|v| v.fract() == 0.0 && **v >= 0.0 && **v <= u64::MAX as f64)
+        .map(|v| *v as u64);
+    WatchEvent {
+        watch_id,
+        hex: integer.map(|v| format!("0x{:04X}", v)),
+        binary: integer.map(|v| {
+            let digits = format!("{:016b}", v);
+            let first = digits.len() % 4;
+            let mut grouped = digits[..first].to_string();
+            for chunk in digits.as_bytes()[first..].chunks(4) {
+                if !grouped.is_empty() {
+                    grouped.push('_');
+                }
+                grouped.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+            }
+            grouped
+        }),
+        value: result.as_ref().ok().copied(),
+        error: result.err(),
+    }
+}
+
+/// Менеджер выражений наблюдения.
+pub struct WatchManager {
+    data_store: SharedDataStore,
+    watches: RwLock<HashMap<u64, Watch>>,
+    next_id: AtomicU64,
+}
+
+impl WatchManager {
+    pub fn new(data_store: SharedDataStore) -> Self {
+        Self {
+            data_store,
+            watches: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Добавить наблюдение. Первое событие содержит текущий результат.
+    pub fn add(
+        &self,
+        app_handle: AppHandle,
+        expression: String,
+        interval_ms: u64,
+    ) -> Result<WatchInfo, String> {
+        let expr = Expr::parse(&expression)?;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let info = WatchInfo {
+            id,
+            expression,
+            interval_ms: interval_ms.max(MIN_INTERVAL_MS),
+        };
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.watches.write().insert(
+            id,
+            Watch {
+                info: info.clone(),
+                cancelled: cancelled.clone(),
+            },
+        );
+        log::debug!("Наблюдение {}: {}", id, info.expression);
+
+        let data_store = self.data_store.clone();
+        let interval_ms = info.interval_ms;
+        tauri::async_runtime::spawn(async move {
+            let mut last_sent: Option<WatchEvent> = None;
+            let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+
+            while !cancelled.load(Ordering::SeqCst) {
+                interval.tick().await;
+                let event = watch_event(id, evaluate(&expr, &data_store));
+                if last_sent.as_ref() == Some(&event) {
+                    continue;
+                }
+                if let Err(e) = app_handle.emit(WATCH_EVENT_NAME, &event) {
+                    log::warn!("Не удалось отправить результат наблюдения {}: {}", id, e);
+                }
+                last_sent = Some(event);
+            }
+        });
+
+        Ok(info)
+    }
+
+    /// Удалить наблюдение. Возвращает false, если оно не найдено.
+    pub fn remove(&self, id: u64) -> bool {
+        match self.watches.write().remove(&id) {
+            Some(watch) => {
+                watch.cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Список активных наблюдений.
+    pub fn list(&self) -> Vec<WatchInfo> {
+        let mut list: Vec<_> = self
+            .watches
+            .read()
+            .values()
+            .map(|w| w.info.clone())
+            .collect();
+        list.sort_by_key(|w| w.id);
+        list
+    }
+}
+
+/// Общая ссылка на менеджер наблюдений.
+pub type SharedWatchManager = Arc<WatchManager>;
+
+/// Создать общий менеджер наблюдений.
+pub fn create_shared_watch_manager(data_store: SharedDataStore) -> SharedWatchManager {
+    Arc::new(WatchManager::new(data_store))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_words_and_formatting() {
+        let store = ModbusDataStore::new();
+        store.restore_area(ModbusArea::HoldingRegister, 100, &[0xA5F0]);
+        store.restore_area(ModbusArea::Coil, 16, &[1]);
+        assert_eq!(
+            raw_address("hr[0x64]"),
+            Some((ModbusArea::HoldingRegister, 100))
+        );
+        assert_eq!(raw_address("XX[1]"), None);
+
+        let expr = Expr::parse("(HR[100] & 0x00FF) + CO[0x10]").unwrap();
+        let event = watch_event(1, evaluate(&expr, &store));
+        assert_eq!(event.value, Some(241.0));
+        assert_eq!(event.hex.as_deref(), Some("0x00F1"));
+        assert_eq!(event.binary.as_deref(), Some("0000_0000_1111_0001"));
+
+        let expr = Expr::parse("HR[100] / 3 + missing").unwrap();
+        let event = watch_event(1, evaluate(&expr, &store));
+        assert!(event.error.is_some() && event.hex.is_none());
+    }
+}
@@ -0,0 +1,280 @@
+//! Режим обучения по записям мастера.
+//!
+//! При построении карты под существующий мастер его записи в неописанные
+//! адреса обычно отклоняются исключением Illegal Data Address, и мастер
+//! останавливается на первой ошибке. В режиме обучения такие записи
+//! принимаются: описанные адреса записываются как обычно, а неописанные
+//! попадают в карантин с последним записанным значением. Одной командой
+//! карантин превращается в определения переменных.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::data_store::ModbusDataStore;
+use crate::modbus_protocol::engine::DataModel;
+use crate::modbus_protocol::{
+    ExceptionCode, MaskWriteRegisterRequest, ReadFileRecordRequest, WriteFileRecordRequest,
+};
+use crate::types::{generate_variable_id, ModbusArea, ModbusDataType, ModbusValue, ModbusVariable};
+
+/// Записи мастера в один неописанный адрес.
+#[derive(Debug, Default)]
+struct LearnedCell {
+    last_value: u16,
+    writes: u64,
+}
+
+/// Адрес в карантине.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedWrite {
+    pub area: ModbusArea,
+    pub address: u16,
+    /// Последнее записанное значение (для coils — 0 или 1).
+    pub last_value: u16,
+    /// Число записей мастера.
+    pub writes: u64,
+}
+
+/// Карантин записей в неописанные адреса.
+#[derive(Debug, Default)]
+pub struct WriteLearning {
+    enabled: AtomicBool,
+    /// Ключ — (holding register, адрес); coils идут первыми.
+    cells: Mutex<BTreeMap<(bool, u16), LearnedCell>>,
+}
+
+impl WriteLearning {
+    /// Включить или выключить режим обучения. Карантин сохраняется.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Включён ли режим обучения.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Хранилище, которое принимает записи в неописанные адреса в карантин.
+    pub fn model<'a>(&'a self, store: &'a ModbusDataStore) -> LearningModel<'a> {
+        LearningModel {
+            store,
+            learning: self,
+        }
+    }
+
+    /// Учесть запись; `value` получает последнее значение адреса в карантине.
+    fn record(&self, holding: bool, address: u16, value: impl FnOnce(u16) -> u16) {
+        let mut cells = self.cells.lock();
+        let cell = cells.entry((holding, address)).or_default();
+        cell.last_value = value(cell.last_value);
+        cell.writes += 1;
+    }
+
+    /// Содержимое карантина: coils, затем holding registers по возрастанию адреса.
+    pub fn quarantine(&self) -> Vec<QuarantinedWrite> {
+        self.cells
+            .lock()
+            .iter()
+            .map(|(&(holding, address), cell)| QuarantinedWrite {
+                area: if holding {
+                    ModbusArea::HoldingRegister
+                } else {
+                    ModbusArea::Coil
+                },
+                address,
+                last_value: cell.last_value,
+                writes: cell.writes,
+            })
+            .collect()
+    }
+
+    /// Очистить карантин.
+    pub fn clear(&self) {
+        self.cells.lock().clear();
+    }
+
+    /// Превратить карантин в определения переменных (bool для coils, uint16
+    /// для регистров) с последним записанным значением и очистить его.
+    /// Адреса, которые тем временем описаны переменными, пропускаются.
+    /// Переменные добавляет в проект UI.
+    pub fn convert(&self, store: &ModbusDataStore) -> Vec<ModbusVariable> {
+        let quarantine = self.quarantine();
+        self.clear();
+        quarantine
+            .into_iter()
+            .filter(|write| !store.is_defined(write.area, write.address))
+            .enumerate()
+            .map(|(seq, write)| {
+                let (prefix, data_type, value) = match write.area {
+                    ModbusArea::Coil => (
+                        "coil",
+                        ModbusDataType::Bool,
+                        ModbusValue::Bool(write.last_value != 0),
+                    ),
+                    _ => (
+                        "hr",
+                        ModbusDataType::Uint16,
+                        ModbusValue::Number(write.last_value as f64),
+                    ),
+                };
+                ModbusVariable {
+                    name: format!("{}_{}", prefix, write.address),
+                    value,
+                    note: Some(format!(
+                        "Создано по записям мастера в режиме обучения: {} записей",
+                        write.writes
+                    )),
+                    ..ModbusVariable::new(
+                        generate_variable_id(seq),
+                        write.area,
+                        write.address,
+                        data_type,
+                    )
+                }
+            })
+            .collect()
+    }
+}
+
+/// Хранилище в режиме обучения: чтение без изменений, запись с
+/// неописанными адресами раскладывается по ячейкам.
+pub struct LearningModel<'a> {
+    store: &'a ModbusDataStore,
+    learning: &'a WriteLearning,
+}
+
+impl LearningModel<'_> {
+    fn write_cells(&self, holding: bool, start: u16, values: &[u16]) {
+        let area = if holding {
+            ModbusArea::HoldingRegister
+        } else {
+            ModbusArea::Coil
+        };
+        for (offset, &value) in values.iter().enumerate() {
+            let address = start.wrapping_add(offset as u16);
+            if !self.store.is_defined(area, address) {
+                self.learning.record(holding, address, |_| value);
+            } else if holding {
+                let _ = self.store.write_single_register(address, value);
+            } else {
+                let _ = self.store.write_single_coil(address, value != 0);
+            }
+        }
+    }
+}
+
+impl DataModel for LearningModel<'_> {
+    fn read_coils(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode> {
+        self.store.read_coils(start, count)
+    }
+
+    fn read_discrete_inputs(&self, start: u16, count: u16) -> Result<Vec<bool>, ExceptionCode> {
+        self.store.read_discrete_inputs(start, count)
+    }
+
+    fn read_holding_registers(&self, start: u16, count: u16) -> Result<Vec<u16>, ExceptionCode> {
+        self.store.read_holding_registers(start, count)
+    }
+
+    fn read_input_registers(&self, start: u16, count: u16) -> Result<Vec<u16>, ExceptionCode> {
+        self.store.read_input_registers(start, count)
+    }
+
+    fn write_single_coil(&self, address: u16, value: bool) -> Result<(), ExceptionCode> {
+        self.write_multiple_coils(address, &[value])
+    }
+
+    fn write_single_register(&self, address: u16, value: u16) -> Result<(), ExceptionCode> {
+        self.write_multiple_registers(address, &[value])
+    }
+
+    fn write_multiple_coils(&self, start: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        // Полностью описанный диапазон пишется одной операцией
+        match self.store.write_multiple_coils(start, values) {
+            Err(ExceptionCode::IllegalDataAddress) => {
+                let words: Vec<u16> = values.iter().map(|&v| v as u16).collect();
+                self.write_cells(false, start, &words);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn write_multiple_registers(&self, start: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        match self.store.write_multiple_registers(start, values) {
+            Err(ExceptionCode::IllegalDataAddress) => {
+                self.write_cells(true, start, values);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn mask_write_register(&self, write: &MaskWriteRegisterRequest) -> Result<(), ExceptionCode> {
+        if self
+            .store
+            .is_defined(ModbusArea::HoldingRegister, write.address)
+        {
+            return DataModel::mask_write_register(self.store, write);
+        }
+        // Маски применяются к последнему значению из карантина
+        self.learning
+            .record(true, write.address, |last| write.apply(last));
+        Ok(())
+    }
+
+    // Файлы записей в обучении не участвуют
+    fn read_file_record(
+        &self,
+        read: &ReadFileRecordRequest,
+    ) -> Result<Vec<Vec<u16>>, ExceptionCode> {
+        self.store.read_file_record(read)
+    }
+
+    fn write_file_record(&self, write: &WriteFileRecordRequest) -> Result<(), ExceptionCode> {
+        self.store.write_file_record(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_store::create_shared_data_store;
+
+    #[test]
+    fn test_undefined_writes_are_quarantined_and_converted() {
+        let store = create_shared_data_store();
+        store.load_variables(&[ModbusVariable::new(
+            "setpoint",
+            ModbusArea::HoldingRegister,
+            10,
+            ModbusDataType::Uint16,
+        )]);
+        let learning = WriteLearning::default();
+        let model = learning.model(&store);
+        model.write_multiple_registers(9, &[1, 2, 3]).unwrap();
+        model.write_single_register(11, 7).unwrap();
+        model.write_single_coil(4, true).unwrap();
+
+        assert_eq!(store.read_holding_registers(10, 1).unwrap(), vec![2]);
+        let quarantine = learning.quarantine();
+        assert_eq!(quarantine.len(), 3);
+        assert_eq!(
+            (quarantine[0].area, quarantine[0].address),
+            (ModbusArea::Coil, 4)
+        );
+        assert_eq!((quarantine[2].last_value, quarantine[2].writes), (7, 2));
+        // Чтение неописанных адресов по-прежнему отклоняется
+        assert!(model.read_holding_registers(9, 1).is_err());
+
+        let variables = learning.convert(&store);
+        let names: Vec<&str> = variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["coil_4", "hr_9", "hr_11"]);
+        assert_eq!(variables[2].value, ModbusValue::Number(7.0));
+        assert!(learning.quarantine().is_empty());
+    }
+}
@@ -0,0 +1,310 @@
+//! Ограничение скорости изменения переменных при записи мастера.
+//!
+//! Многие приводы не принимают ступенчатое изменение уставки: новое значение
+//! может отличаться от текущего не больше чем на `скорость × время` с
+//! предыдущей выполненной записи (для первой записи — с запуска сервера).
+//! Запись, отклонённая по другой причине, время отсчёта не сдвигает.
+//! Запись с превышением либо отклоняется исключением Illegal Data Value,
+//! либо обрезается до допустимого значения. Ограничения действуют на
+//! числовые переменные holding registers (функции 0x06 и 0x10).
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::data_store::{read_register_value, write_register_value, ModbusDataStore};
+use crate::modbus_protocol::{FunctionCode, ModbusRequest};
+use crate::types::{ModbusArea, ModbusDataType, ModbusValue};
+
+/// Реакция на слишком быстрое изменение.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RateLimitAction {
+    /// Отклонить запись целиком (Illegal Data Value).
+    #[default]
+    Reject,
+    /// Записать ближайшее допустимое значение.
+    Clamp,
+}
+
+/// Ограничение скорости изменения переменной.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteRateLimit {
+    pub variable_id: String,
+    /// Допустимое изменение, единиц в секунду.
+    pub max_rate_per_sec: f64,
+    #[serde(default)]
+    pub action: RateLimitAction,
+}
+
+/// Нарушение ограничения.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateViolation {
+    pub variable_id: String,
+    pub from: f64,
+    pub requested: f64,
+    /// Записанное значение при обрезке; `None` — запись отклонена.
+    pub applied: Option<f64>,
+}
+
+/// Ограничения скорости и время последних принятых записей.
+#[derive(Debug)]
+pub struct WriteRateLimiter {
+    limits: Mutex<Vec<WriteRateLimit>>,
+    started: Mutex<Instant>,
+    last_write: Mutex<HashMap<String, Instant>>,
+}
+
+impl Default for WriteRateLimiter {
+    fn default() -> Self {
+        Self {
+            limits: Mutex::new(Vec::new()),
+            started: Mutex::new(Instant::now()),
+            last_write: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Позиция первого слова значений в данных запроса записи регистров.
+fn words_offset(request: &ModbusRequest) -> Option<usize> {
+    match FunctionCode::from_u8(request.function_code)? {
+        FunctionCode::WriteSingleRegister => Some(2),
+        FunctionCode::WriteMultipleRegisters => Some(5),
+        _ => None,
+    }
+}
+
+impl WriteRateLimiter {
+    /// Заменить ограничения.
+    pub fn set_limits(&self, limits: Vec<WriteRateLimit>) {
+        *self.limits.lock() = limits;
+        self.last_write.lock().clear();
+    }
+
+    /// Начать отсчёт времени заново (при запуске сервера).
+    pub fn reset(&self) {
+        *self.started.lock() = Instant::now();
+        self.last_write.lock().clear();
+    }
+
+    /// Оценить запрос записи, ничего не меняя: ни данные запроса, ни время
+    /// записей. Обрезку применяет [`RateCheck::clamp`], время записей
+    /// обновляет [`WriteRateLimiter::commit`] после выполненной записи.
+    pub fn evaluate(
+        &self,
+        request: &ModbusRequest,
+        data_store: &ModbusDataStore,
+        now: Instant,
+    ) -> RateCheck {
+        let mut check = RateCheck {
+            violations: Vec::new(),
+            patches: Vec::new(),
+            accepted: Vec::new(),
+            now,
+        };
+        let limits = self.limits.lock();
+        if limits.is_empty() {
+            return check;
+        }
+        let (Some(offset), Some((start, count))) = (words_offset(request), request.address_range())
+        else {
+            return check;
+        };
+        // Позиция слова регистра `address` в данных запроса, если он записывается
+        let position = |address: usize| -> Option<usize> {
+            let index = address.checked_sub(start as usize)?;
+            let at = offset + index * 2;
+            (index < count as usize && at + 2 <= request.data.len()).then_some(at)
+        };
+        let written = |address: usize| -> Option<u16> {
+            position(address).map(|at| u16::from_be_bytes([request.data[at], request.data[at + 1]]))
+        };
+
+        let last_write = self.last_write.lock();
+        let started = *self.started.lock();
+
+        for limit in limits.iter() {
+            let Some(var) = data_store.get_variable(&limit.variable_id) else {
+                continue;
+            };
+            if var.area != ModbusArea::HoldingRegister
+                || var.bit.is_some()
+                || var.data_type == ModbusDataType::Bool
+            {
+                continue;
+            }
+            let width = var.data_type.register_count() as usize;
+            let address = var.address as usize;
+            let mut words = data_store.dump_area(ModbusArea::HoldingRegister, var.address, width);
+            let mut touched = false;
+            for (i, word) in words.iter_mut().enumerate() {
+                if let Some(value) = written(address + i) {
+                    *word = value;
+                    touched = true;
+                }
+            }
+            if !touched {
+                continue;
+            }
+            let Some(requested) = read_register_value(&words, 0, &var.data_type) else {
+                continue;
+            };
+            let from = var.value.as_f64();
+            let requested = requested.as_f64();
+            let since = last_write
+                .get(&limit.variable_id)
+                .copied()
+                .unwrap_or(started);
+            let allowed = limit.max_rate_per_sec * now.duration_since(since).as_secs_f64();
+            let change = requested - from;
+
+            if change.abs() <= allowed {
+                check.accepted.push(limit.variable_id.clone());
+                continue;
+            }
+            let applied = match limit.action {
+                RateLimitAction::Reject => None,
+                RateLimitAction::Clamp => {
+                    let clamped = from + allowed.copysign(change);
+                    write_register_value(
+                        &mut words,
+                        0,
+                        &var.data_type,
+                        &ModbusValue::Number(clamped),
+                    );
+                    for (i, word) in words.into_iter().enumerate() {
+                        if let Some(at) = position(address + i) {
+                            check.patches.push((at, word));
+                        }
+                    }
+                    check.accepted.push(limit.variable_id.clone());
+                    Some(clamped)
+                }
+            };
+            check.violations.push(RateViolation {
+                variable_id: limit.variable_id.clone(),
+                from,
+                requested,
+                applied,
+            });
+        }
+        check
+    }
+
+    /// Учесть выполненную запись: время принятых изменений становится
+    /// началом отсчёта для следующих записей.
+    pub fn commit(&self, check: RateCheck) {
+        if check.rejected() {
+            return;
+        }
+        let mut last_write = self.last_write.lock();
+        for id in check.accepted {
+            last_write.insert(id, check.now);
+        }
+    }
+}
+
+/// Итог оценки запроса записи.
+#[derive(Debug)]
+pub struct RateCheck {
+    pub violations: Vec<RateViolation>,
+    /// Обрезанные слова: позиция в данных запроса и значение.
+    patches: Vec<(usize, u16)>,
+    /// Переменные, изменение которых допустимо (в том числе после обрезки).
+    accepted: Vec<String>,
+    now: Instant,
+}
+
+impl RateCheck {
+    /// Хоть одно нарушение отклоняет запись целиком.
+    pub fn rejected(&self) -> bool {
+        self.violations.iter().any(|v| v.applied.is_none())
+    }
+
+    /// Заменить в данных запроса значения на обрезанные.
+    pub fn clamp(&self, request: &mut ModbusRequest) {
+        if self.rejected() {
+            return;
+        }
+        for &(at, word) in &self.patches {
+            request.data[at..at + 2].copy_from_slice(&word.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus_protocol::MbapHeader;
+    use crate::types::ModbusVariable;
+    use std::time::Duration;
+
+    fn write_request(value: u16) -> ModbusRequest {
+        let mut frame = Vec::new();
+        MbapHeader {
+            transaction_id: 1,
+            protocol_id: 0,
+            length: 6,
+            unit_id: 1,
+        }
+        .write_to(&mut frame);
+        frame.push(0x06);
+        frame.extend_from_slice(&[0, 5]);
+        frame.extend_from_slice(&value.to_be_bytes());
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_reject_and_clamp() {
+        let store = ModbusDataStore::new();
+        store.load_variables(&[ModbusVariable {
+            value: ModbusValue::Number(100.0),
+            ..ModbusVariable::new(
+                "speed",
+                ModbusArea::HoldingRegister,
+                5,
+                ModbusDataType::Uint16,
+            )
+        }]);
+        let limiter = WriteRateLimiter::default();
+        let limit = |action| WriteRateLimit {
+            variable_id: "speed".to_string(),
+            max_rate_per_sec: 10.0,
+            action,
+        };
+        limiter.set_limits(vec![limit(RateLimitAction::Reject)]);
+        let start = *limiter.started.lock();
+
+        // За 1 с допустимо изменение на 10
+        let request = write_request(105);
+        let now = start + Duration::from_secs(1);
+        let check = limiter.evaluate(&request, &store, now);
+        assert!(check.violations.is_empty());
+
+        // Без commit (запись не выполнена) время отсчёта не сдвигается
+        let request = write_request(125);
+        let later = now + Duration::from_secs(2);
+        assert!(limiter
+            .evaluate(&request, &store, later)
+            .violations
+            .is_empty());
+
+        limiter.commit(check);
+        let mut request = write_request(200);
+        let check = limiter.evaluate(&request, &store, later);
+        assert_eq!(check.violations[0].applied, None);
+        check.clamp(&mut request);
+        assert_eq!(request.data[2..4], 200u16.to_be_bytes());
+
+        limiter.set_limits(vec![limit(RateLimitAction::Clamp)]);
+        let mut request = write_request(200);
+        let check = limiter.evaluate(&request, &store, later);
+        assert_eq!(check.violations[0].applied, Some(130.0));
+        assert_eq!(request.data[2..4], 200u16.to_be_bytes());
+        check.clamp(&mut request);
+        assert_eq!(request.data[2..4], 130u16.to_be_bytes());
+    }
+}
@@ -0,0 +1,266 @@
+//! Обнаружение штормов записи.
+//!
+//! Неисправный мастер может писать один и тот же регистр сотни раз в секунду,
+//! и журнал обмена тонет в одинаковых записях. Если клиент записал адрес
+//! больше `max_writes_per_sec` раз за секунду, начинается шторм: запросы и
+//! ответы по этому адресу больше не журналируются, а только считаются. Шторм
+//! заканчивается после секунды с допустимым числом записей (или при остановке
+//! сервера), и в журнал попадает одна сводная запись с числом записей.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::modbus_protocol::{FunctionCode, ModbusRequest};
+use crate::types::{ModbusArea, ServerStatus};
+
+/// Окно подсчёта записей.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Настройки обнаружения в профиле подключения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteStormConfig {
+    /// Допустимое число записей одного адреса за секунду.
+    pub max_writes_per_sec: u32,
+}
+
+/// Завершившийся шторм.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StormSummary {
+    pub client: String,
+    pub area: ModbusArea,
+    pub address: u16,
+    /// Записи, не попавшие в журнал.
+    pub writes: u64,
+    pub duration: Duration,
+}
+
+/// Клиент, область и первый адрес записи.
+type StormKey = (String, ModbusArea, u16);
+
+#[derive(Debug)]
+struct AddressState {
+    window_start: Instant,
+    window_writes: u32,
+    /// Начало шторма и число подавленных записей.
+    storm: Option<(Instant, u64)>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    config: Option<WriteStormConfig>,
+    addresses: HashMap<StormKey, AddressState>,
+    finished: Vec<StormSummary>,
+}
+
+/// Штормы записи всех клиентов сервера.
+#[derive(Debug, Default)]
+pub struct WriteStorms {
+    inner: Mutex<Inner>,
+    storms: AtomicU64,
+    suppressed: AtomicU64,
+}
+
+/// Область и первый адрес запроса записи.
+fn write_target(request: &ModbusRequest) -> Option<(ModbusArea, u16)> {
+    let area = match FunctionCode::from_u8(request.function_code)? {
+        FunctionCode::WriteSingleCoil | FunctionCode::WriteMultipleCoils => ModbusArea::Coil,
+        FunctionCode::WriteSingleRegister
+        | FunctionCode::WriteMultipleRegisters
+        | FunctionCode::MaskWriteRegister => ModbusArea::HoldingRegister,
+        _ => return None,
+    };
+    Some((area, request.address_range()?.0))
+}
+
+/// Закрыть истёкшее окно; шторм с допустимым окном завершается.
+fn roll(
+    key: &StormKey,
+    state: &mut AddressState,
+    limit: u32,
+    now: Instant,
+) -> Option<StormSummary> {
+    if now.duration_since(state.window_start) < WINDOW {
+        return None;
+    }
+    let calm = state.window_writes <= limit;
+    state.window_start = now;
+    state.window_writes = 0;
+    if !calm {
+        return None;
+    }
+    let (started, writes) = state.storm.take()?;
+    Some(summary(key, started, writes, now))
+}
+
+fn summary(key: &StormKey, started: Instant, writes: u64, now: Instant) -> StormSummary {
+    StormSummary {
+        client: key.0.clone(),
+        area: key.1,
+        address: key.2,
+        writes,
+        duration: now.duration_since(started),
+    }
+}
+
+impl WriteStorms {
+    /// Задать настройки; `None` — выключить обнаружение.
+    pub fn set_config(&self, config: Option<WriteStormConfig>) {
+        let mut inner = self.inner.lock();
+        inner.config = config;
+        inner.addresses.clear();
+    }
+
+    /// Начать подсчёт заново (при запуске сервера и сбросе статистики).
+    pub fn reset(&self) {
+        let mut inner = self.inner.lock();
+        inner.addresses.clear();
+        inner.finished.clear();
+        self.storms.store(0, Ordering::SeqCst);
+        self.suppressed.store(0, Ordering::SeqCst);
+    }
+
+    /// Учесть запрос. `true` — запрос идёт в шторме и не журналируется.
+    pub fn record(&self, request: &ModbusRequest, client: &str, now: Instant) -> bool {
+        let Some((area, address)) = write_target(request) else {
+            return false;
+        };
+        let mut inner = self.inner.lock();
+        let Some(config) = inner.config else {
+            return false;
+        };
+        let key = (client.to_string(), area, address);
+        let state = inner
+            .addresses
+            .entry(key.clone())
+            .or_insert_with(|| AddressState {
+                window_start: now,
+                window_writes: 0,
+                storm: None,
+            });
+        let ended = roll(&key, state, config.max_writes_per_sec, now);
+        state.window_writes += 1;
+        if state.storm.is_none() && state.window_writes > config.max_writes_per_sec {
+            state.storm = Some((now, 0));
+            self.storms.fetch_add(1, Ordering::SeqCst);
+        }
+        let quiet = match &mut state.storm {
+            Some((_, writes)) => {
+                *writes += 1;
+                self.suppressed.fetch_add(1, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        };
+        inner.finished.extend(ended);
+        quiet
+    }
+
+    /// Забрать завершившиеся штормы. При `stop` завершаются и текущие.
+    pub fn flush(&self, now: Instant, stop: bool) -> Vec<StormSummary> {
+        let mut inner = self.inner.lock();
+        let limit = inner.config.map_or(0, |c| c.max_writes_per_sec);
+        let mut finished = std::mem::take(&mut inner.finished);
+        inner.addresses.retain(|key, state| {
+            finished.extend(roll(key, state, limit, now));
+            if stop {
+                if let Some((started, writes)) = state.storm.take() {
+                    finished.push(summary(key, started, writes, now));
+                }
+            }
+            // Адрес без шторма и без записей в текущем окне больше не нужен
+            state.storm.is_some() || state.window_writes > 0
+        });
+        finished
+    }
+
+    /// Заполнить счётчики штормов в статусе сервера.
+    pub fn fill(&self, status: &mut ServerStatus) {
+        status.write_storms = self.storms.load(Ordering::SeqCst);
+        status.storm_suppressed_writes = self.suppressed.load(Ordering::SeqCst);
+    }
+}
+
+/// Раз в секунду передавать завершившиеся штормы в `notify` до сигнала
+/// завершения; при завершении передаются и незаконченные.
+pub async fn run(
+    storms: Arc<WriteStorms>,
+    notify: impl Fn(StormSummary),
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut tick = tokio::time::interval(WINDOW);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                for summary in storms.flush(Instant::now(), false) {
+                    notify(summary);
+                }
+            }
+            _ = shutdown_rx.recv() => break,
+        }
+    }
+    for summary in storms.flush(Instant::now(), true) {
+        notify(summary);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus_protocol::MbapHeader;
+
+    fn write_register(address: u16) -> ModbusRequest {
+        let mut frame = Vec::new();
+        MbapHeader {
+            transaction_id: 1,
+            protocol_id: 0,
+            length: 6,
+            unit_id: 1,
+        }
+        .write_to(&mut frame);
+        frame.push(0x06);
+        frame.extend_from_slice(&address.to_be_bytes());
+        frame.extend_from_slice(&[0, 1]);
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_storm_is_suppressed_and_summarized() {
+        let storms = WriteStorms::default();
+        storms.set_config(Some(WriteStormConfig {
+            max_writes_per_sec: 3,
+        }));
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let request = write_register(10);
+
+        let quiet: Vec<bool> = (0..5)
+            .map(|i| storms.record(&request, "master", at(i * 10)))
+            .collect();
+        assert_eq!(quiet, [false, false, false, true, true]);
+        // Другой адрес считается отдельно
+        assert!(!storms.record(&write_register(11), "master", at(50)));
+
+        // В следующей секунде шторм продолжается, хотя окно ещё не переполнено
+        assert!(storms.record(&request, "master", at(1100)));
+        assert!(storms.flush(at(1500), false).is_empty());
+
+        // Спокойная секунда завершает шторм одной сводкой
+        let finished = storms.flush(at(2200), false);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].address, 10);
+        assert_eq!(finished[0].writes, 3);
+        assert!(!storms.record(&request, "master", at(2300)));
+
+        let mut status = ServerStatus::default();
+        storms.fill(&mut status);
+        assert_eq!(status.write_storms, 1);
+        assert_eq!(status.storm_suppressed_writes, 3);
+    }
+}
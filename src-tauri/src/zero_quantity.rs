@@ -0,0 +1,114 @@
+//! Ответ на чтение с нулевым количеством.
+//!
+//! По спецификации запрос чтения (0x01–0x04) с количеством 0 получает
+//! исключение Illegal Data Value. Неисправные мастера такие запросы всё же
+//! шлют, а реальные устройства отвечают на них по-разному: исключением,
+//! пустым успешным ответом или чем-то своим. Режим выбирается в профиле,
+//! чтобы воспроизвести поведение, замеченное на объекте.
+
+use serde::{Deserialize, Serialize};
+
+use crate::modbus_protocol::{ModbusRequest, ModbusResponse};
+use crate::response_override::{ResponseOverride, ResponseOverrides};
+
+/// Реакция на чтение с нулевым количеством.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum ZeroQuantityReads {
+    /// Исключение Illegal Data Value (по спецификации).
+    #[default]
+    IllegalDataValue,
+    /// Успешный ответ со счётчиком байт 0 и без данных.
+    EmptySuccess,
+    /// Готовый PDU в формате подменённых ответов (с полями `{fc}`, `{len}` …).
+    Canned { pdu: String },
+}
+
+impl ZeroQuantityReads {
+    /// Проверить шаблон готового ответа.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ZeroQuantityReads::Canned { pdu } => canned(0x03, pdu)
+                .map(|_| ())
+                .map_err(|e| format!("Ответ на чтение 0 элементов: {}", e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Кадр ответа, если запрос — чтение 0 элементов и режим отличается
+    /// от спецификации. Иначе запрос обрабатывается как обычно.
+    pub fn respond(&self, request: &ModbusRequest) -> Option<Vec<u8>> {
+        if !(0x01..=0x04).contains(&request.function_code) {
+            return None;
+        }
+        let (_, quantity) = request.address_range()?;
+        if quantity != 0 {
+            return None;
+        }
+        match self {
+            ZeroQuantityReads::IllegalDataValue => None,
+            ZeroQuantityReads::EmptySuccess => Some(ModbusResponse::build_response(
+                request,
+                request.function_code,
+                &[0],
+            )),
+            ZeroQuantityReads::Canned { pdu } => {
+                canned(request.function_code, pdu).ok()?.respond(request)
+            }
+        }
+    }
+}
+
+fn canned(function_code: u8, pdu: &str) -> Result<ResponseOverrides, String> {
+    ResponseOverrides::compile(&[ResponseOverride {
+        function_code,
+        address: None,
+        pdu: pdu.to_string(),
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_zero(function_code: u8) -> ModbusRequest {
+        let frame = [
+            0x00,
+            0x05,
+            0x00,
+            0x00,
+            0x00,
+            0x06,
+            0x01,
+            function_code,
+            0,
+            10,
+            0,
+            0,
+        ];
+        ModbusRequest::parse(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_modes() {
+        assert_eq!(ZeroQuantityReads::default().respond(&read_zero(0x03)), None);
+        assert_eq!(
+            ZeroQuantityReads::EmptySuccess.respond(&read_zero(0x01)),
+            Some(vec![0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0x01, 0x01, 0x00])
+        );
+
+        let canned = ZeroQuantityReads::Canned {
+            pdu: "{fc} 02 00 00".to_string(),
+        };
+        assert!(canned.validate().is_ok());
+        assert_eq!(
+            canned.respond(&read_zero(0x04)).unwrap()[7..],
+            [0x04, 0x02, 0x00, 0x00]
+        );
+        assert!(ZeroQuantityReads::Canned {
+            pdu: "zz".to_string()
+        }
+        .validate()
+        .is_err());
+    }
+}